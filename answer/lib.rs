@@ -31,6 +31,7 @@ use encoding::{
 };
 use lending_iterator::higher_order::Hkt;
 use primitive::maybe_owns::MaybeOwns;
+use serde::{Deserialize, Serialize};
 use storage::snapshot::ReadableSnapshot;
 
 pub mod variable;
@@ -53,7 +54,7 @@ impl fmt::Display for Concept<'_> {
     }
 }
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Type {
     // WARNING: Changing order of enum will change Ord and `minimum_type()`! This must align with the storage encoding
     Entity(EntityType),
@@ -6,9 +6,10 @@
 
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
 use structural_equality::{ordered_hash_combine, StructuralEquality};
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct Variable {
     id: VariableId,
     anonymous: bool,
@@ -56,7 +57,7 @@ impl fmt::Debug for Variable {
     }
 }
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct VariableId {
     // TODO: retain line/character from original query at which point this Variable was declared
     id: u16,
@@ -115,6 +115,38 @@ impl<'a> VariableValue<'a> {
             VariableValue::ValueList(_) => "value list",
         }
     }
+
+    /// A total order over variable values, unlike `partial_cmp`: values of different variants are ordered by their
+    /// variant, and values by [`Value::total_cmp`]. This is the order instruction iterators produce their tuples in,
+    /// so intersections merge them by it.
+    pub fn total_cmp(&self, other: &VariableValue<'_>) -> Ordering {
+        match (self, other) {
+            (Self::Type(self_type), VariableValue::Type(other_type)) => self_type.cmp(other_type),
+            (Self::Thing(self_thing), VariableValue::Thing(other_thing)) => self_thing.cmp(other_thing),
+            (Self::Value(self_value), VariableValue::Value(other_value)) => self_value.total_cmp(other_value),
+            (Self::ThingList(self_list), VariableValue::ThingList(other_list)) => self_list.cmp(other_list),
+            (Self::ValueList(self_list), VariableValue::ValueList(other_list)) => {
+                let mismatch = self_list
+                    .iter()
+                    .zip(other_list.iter())
+                    .map(|(lhs, rhs)| lhs.total_cmp(rhs))
+                    .find(|ordering| ordering.is_ne());
+                mismatch.unwrap_or_else(|| self_list.len().cmp(&other_list.len()))
+            }
+            _ => self.variant_index().cmp(&other.variant_index()),
+        }
+    }
+
+    fn variant_index(&self) -> u8 {
+        match self {
+            VariableValue::None => 0,
+            VariableValue::Type(_) => 1,
+            VariableValue::Thing(_) => 2,
+            VariableValue::Value(_) => 3,
+            VariableValue::ThingList(_) => 4,
+            VariableValue::ValueList(_) => 5,
+        }
+    }
 }
 
 impl Hkt for VariableValue<'static> {
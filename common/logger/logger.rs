@@ -49,6 +49,10 @@ pub fn initialise_logging_global(logdir: &PathBuf) {
 }
 
 pub fn initialise_logging() -> DefaultGuard {
-    let subscriber = SubscriberBuilder::default().with_max_level(Level::TRACE).finish();
+    initialise_logging_at(Level::TRACE)
+}
+
+pub fn initialise_logging_at(max_level: Level) -> DefaultGuard {
+    let subscriber = SubscriberBuilder::default().with_max_level(max_level).finish();
     tracing::subscriber::set_default(subscriber)
 }
@@ -12,6 +12,7 @@ use ir::pattern::{variable_category::VariableCategory, IrID, ParameterID};
 use crate::annotation::expression::instructions::op_codes::ExpressionOpCode;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExecutableExpression<ID> {
     pub(crate) instructions: Vec<ExpressionOpCode>,
     pub(crate) variables: Vec<ID>,
@@ -44,6 +45,73 @@ impl<ID> ExecutableExpression<ID> {
 }
 
 impl<ID: IrID> ExecutableExpression<ID> {
+    /// The direction in which the value of the expression follows the value of `variable`, if the expression reads
+    /// no other variable and only loads, casts, rounds, or adds and subtracts constants to it. Rows ordered by the
+    /// variable are then also ordered by the value of the expression.
+    pub fn monotonicity_in(&self, variable: ID) -> Option<Monotonicity> {
+        #[derive(Clone, Copy)]
+        enum Operand {
+            Constant,
+            Follows(Monotonicity),
+        }
+
+        let mut variables = self.variables.iter();
+        let mut stack = Vec::new();
+        for instruction in &self.instructions {
+            match instruction {
+                ExpressionOpCode::LoadConstant => stack.push(Operand::Constant),
+                ExpressionOpCode::LoadVariable => {
+                    if *variables.next()? != variable {
+                        return None;
+                    }
+                    stack.push(Operand::Follows(Monotonicity::NonDecreasing))
+                }
+                ExpressionOpCode::CastUnaryIntegerToDouble
+                | ExpressionOpCode::CastLeftIntegerToDouble
+                | ExpressionOpCode::CastRightIntegerToDouble
+                | ExpressionOpCode::CastUnaryIntegerToDecimal
+                | ExpressionOpCode::CastLeftIntegerToDecimal
+                | ExpressionOpCode::CastRightIntegerToDecimal
+                | ExpressionOpCode::CastUnaryDecimalToDouble
+                | ExpressionOpCode::CastLeftDecimalToDouble
+                | ExpressionOpCode::CastRightDecimalToDouble
+                | ExpressionOpCode::MathRoundDouble
+                | ExpressionOpCode::MathCeilDouble
+                | ExpressionOpCode::MathFloorDouble => (),
+                ExpressionOpCode::OpIntegerAddInteger
+                | ExpressionOpCode::OpDoubleAddDouble
+                | ExpressionOpCode::OpDecimalAddDecimal
+                | ExpressionOpCode::OpIntegerSubtractInteger
+                | ExpressionOpCode::OpDoubleSubtractDouble
+                | ExpressionOpCode::OpDecimalSubtractDecimal => {
+                    let is_subtraction = matches!(
+                        instruction,
+                        ExpressionOpCode::OpIntegerSubtractInteger
+                            | ExpressionOpCode::OpDoubleSubtractDouble
+                            | ExpressionOpCode::OpDecimalSubtractDecimal
+                    );
+                    let right = match stack.pop()? {
+                        Operand::Follows(monotonicity) if is_subtraction => Operand::Follows(monotonicity.reversed()),
+                        right => right,
+                    };
+                    let left = stack.pop()?;
+                    stack.push(match (left, right) {
+                        (Operand::Constant, Operand::Constant) => Operand::Constant,
+                        (Operand::Follows(monotonicity), Operand::Constant)
+                        | (Operand::Constant, Operand::Follows(monotonicity)) => Operand::Follows(monotonicity),
+                        (Operand::Follows(left), Operand::Follows(right)) if left == right => Operand::Follows(left),
+                        (Operand::Follows(_), Operand::Follows(_)) => return None,
+                    })
+                }
+                _ => return None,
+            }
+        }
+        match stack.as_slice() {
+            &[Operand::Follows(monotonicity)] => Some(monotonicity),
+            _ => None,
+        }
+    }
+
     pub fn map<T: IrID>(self, mapping: &HashMap<ID, T>) -> ExecutableExpression<T> {
         let Self { instructions, variables, constants, return_type } = self;
         ExecutableExpression {
@@ -55,7 +123,23 @@ impl<ID: IrID> ExecutableExpression<ID> {
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Monotonicity {
+    NonDecreasing,
+    NonIncreasing,
+}
+
+impl Monotonicity {
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::NonDecreasing => Self::NonIncreasing,
+            Self::NonIncreasing => Self::NonDecreasing,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionValueType {
     // TODO: we haven't implemented ConceptList, only ValueList right now.
     // TODO: this should hold an actual ValueType, not a Category!
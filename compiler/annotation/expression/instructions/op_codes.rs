@@ -8,6 +8,7 @@ use std::fmt;
 
 // TODO: Rewrite so we generate the dispatcher macro along with the enum. SEe https://cprohm.de/blog/rust-macros/
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExpressionOpCode {
     // Basics
     LoadConstant,
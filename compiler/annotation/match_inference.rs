@@ -15,7 +15,10 @@ use answer::{variable::Variable, Type as TypeAnnotation};
 use concept::type_::type_manager::TypeManager;
 use ir::{
     pattern::{
-        conjunction::Conjunction, constraint::Constraint, nested_pattern::NestedPattern, Scope, ScopeId, Vertex,
+        conjunction::Conjunction,
+        constraint::{Constraint, Links},
+        nested_pattern::NestedPattern,
+        Scope, ScopeId, Vertex,
     },
     pipeline::{
         block::{Block, BlockContext},
@@ -24,6 +27,7 @@ use ir::{
 };
 use itertools::{chain, Itertools};
 use storage::snapshot::ReadableSnapshot;
+use typeql::common::Span;
 
 use crate::annotation::{
     function::AnnotatedFunctionSignatures,
@@ -312,7 +316,8 @@ impl TypeInferenceGraph<'_> {
         edges.into_iter().for_each(|edge| {
             let TypeInferenceEdge { constraint, left_to_right, right_to_left, .. } = edge;
             if let Constraint::Links(links) = edge.constraint {
-                if let Some((other_left_right, other_right_left)) = combine_links_edges.remove(&edge.right) {
+                // the role vertex may be shared with other links constraints, so pair the two edges by constraint
+                if let Some((other_left_right, other_right_left)) = combine_links_edges.remove(constraint) {
                     let lrf_annotation = {
                         if &edge.left == links.relation() {
                             LinksAnnotations::build(left_to_right, right_to_left, other_left_right, other_right_left)
@@ -322,7 +327,7 @@ impl TypeInferenceGraph<'_> {
                     };
                     constraint_annotations.insert(constraint.clone(), ConstraintTypeAnnotations::Links(lrf_annotation));
                 } else {
-                    combine_links_edges.insert(edge.right, (left_to_right, right_to_left));
+                    combine_links_edges.insert(constraint, (left_to_right, right_to_left));
                 }
             } else {
                 let lr_annotations = LeftRightAnnotations::build(left_to_right, right_to_left);
@@ -349,6 +354,7 @@ impl TypeInferenceGraph<'_> {
         &self,
         variable_registry: &VariableRegistry,
     ) -> Result<(), TypeInferenceError> {
+        self.check_shared_role_variables_satisfiable(variable_registry)?;
         let thing_variable_present = self
             .vertices
             .annotations
@@ -368,6 +374,42 @@ impl TypeInferenceGraph<'_> {
             .try_for_each(|graph| graph.check_thing_constraints_satisfiable(variable_registry))?;
         Ok(())
     }
+
+    /// A role variable of several `links` constraints ranges over the role types all of their relations relate, so
+    /// it has no types when the relations are unrelated. Reported before the general unsatisfiability, to say why.
+    fn check_shared_role_variables_satisfiable(
+        &self,
+        variable_registry: &VariableRegistry,
+    ) -> Result<(), TypeInferenceError> {
+        let mut links_by_role: BTreeMap<Variable, Vec<(&Links<Variable>, Option<Span>)>> = BTreeMap::new();
+        for constraint in self.conjunction.constraints() {
+            if let Constraint::Links(links) = constraint {
+                if let Some(role) = links.role_type().as_variable() {
+                    links_by_role.entry(role).or_default().push((links, constraint.source_span()));
+                }
+            }
+        }
+        let variable_name = |vertex: &Vertex<Variable>| match vertex.as_variable() {
+            Some(variable) => variable_registry
+                .get_variable_name(variable)
+                .cloned()
+                .unwrap_or(VariableRegistry::UNNAMED_VARIABLE_DISPLAY_NAME.to_string()),
+            None => vertex.to_string(),
+        };
+        for (role, links) in links_by_role {
+            let role_vertex = Vertex::Variable(role);
+            let is_empty = self.vertices.annotations.get(&role_vertex).is_some_and(|types| types.is_empty());
+            if links.len() > 1 && is_empty {
+                let relation_variables = links.iter().map(|(links, _)| variable_name(links.relation())).join(", ");
+                return Err(TypeInferenceError::SharedRoleVariableWithoutCommonRoleType {
+                    role_variable: variable_name(&role_vertex),
+                    relation_variables,
+                    source_span: links.last().unwrap().1,
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
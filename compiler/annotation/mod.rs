@@ -212,6 +212,13 @@ typedb_error!(
             right_types: String,
             source_span: Option<Span>,
         ),
+        SharedRoleVariableWithoutCommonRoleType(
+            12,
+            "The role variable '{role_variable}' is shared by the relations '{relation_variables}', but no role type can be related by all of them. Role types are scoped to the relation type declaring them, so a role variable can only be shared by relations that relate the same role type, such as a relation type and its subtypes.",
+            role_variable: String,
+            relation_variables: String,
+            source_span: Option<Span>,
+        ),
         OptionalTypesUnsupported(255, "Optional types are not yet supported."),
         ListTypesUnsupported(256, "List types are not yet supported."),
     }
@@ -65,6 +65,10 @@ impl TypeAnnotations {
         self.vertex.get(vertex)
     }
 
+    pub fn vertex_annotations_mut(&mut self) -> &mut BTreeMap<Vertex<Variable>, Arc<BTreeSet<Type>>> {
+        &mut self.vertex
+    }
+
     pub fn constraint_annotations(&self) -> &HashMap<Constraint<Variable>, ConstraintTypeAnnotations> {
         &self.constraints
     }
@@ -121,6 +125,11 @@ pub struct LeftRightAnnotations {
 
 impl LeftRightAnnotations {
     pub fn new(left_to_right: BTreeMap<Type, Vec<Type>>, right_to_left: BTreeMap<Type, Vec<Type>>) -> Self {
+        // executors iterate these lists directly when producing tuples, so they must follow the canonical type order
+        debug_assert!(left_to_right
+            .values()
+            .chain(right_to_left.values())
+            .all(|types| types.windows(2).all(|pair| pair[0] <= pair[1])));
         Self { left_to_right: Arc::new(left_to_right), right_to_left: Arc::new(right_to_left) }
     }
 
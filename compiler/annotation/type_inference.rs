@@ -155,6 +155,7 @@ pub mod tests {
                 vec![],
                 vec![(VariableCategory::Object, VariableOptionality::Required)],
                 true,
+                false,
             );
             conjunction
                 .constraints_mut()
@@ -898,6 +899,81 @@ pub mod tests {
         assert_eq!(expected_graph, graph);
     }
 
+    #[test]
+    fn shared_role_variable() {
+        let (_tmp_dir, storage) = setup_storage();
+        let (type_manager, thing_manager) = managers();
+
+        let ((_, _, _), _, (_, type_has_fear, type_is_feared)) =
+            setup_types(storage.clone().open_snapshot_write(), &type_manager, &thing_manager);
+
+        {
+            // Case 1: $r1 links ($role: $a); $r2 links ($role: $b);
+            let snapshot = storage.clone().open_snapshot_write();
+            let mut translation_context = PipelineTranslationContext::new();
+            let mut value_parameters = ParameterRegistry::new();
+            let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+            let mut conjunction = builder.conjunction_mut();
+            let [var_r1, var_r2, var_role, var_a, var_b] = ["r1", "r2", "role", "a", "b"]
+                .map(|name| conjunction.constraints_mut().get_or_declare_variable(name, None).unwrap());
+
+            conjunction.constraints_mut().add_links(var_r1, var_a, var_role, None).unwrap();
+            conjunction.constraints_mut().add_links(var_r2, var_b, var_role, None).unwrap();
+
+            let block = builder.finish().unwrap();
+            let graph = compute_type_inference_graph(
+                &snapshot,
+                &block,
+                &translation_context.variable_registry,
+                &type_manager,
+                &BTreeMap::new(),
+                &EmptyAnnotatedFunctionSignatures,
+                false,
+            )
+            .unwrap();
+            assert_eq!(
+                graph.vertices.get(&Vertex::Variable(var_role)),
+                Some(&BTreeSet::from([type_has_fear, type_is_feared]))
+            );
+        }
+
+        {
+            // Case 2: $r1 links ($role: $a); $a isa cat; $r2 links ($role: $b); $b isa dog;
+            let snapshot = storage.clone().open_snapshot_write();
+            let mut translation_context = PipelineTranslationContext::new();
+            let mut value_parameters = ParameterRegistry::new();
+            let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+            let mut conjunction = builder.conjunction_mut();
+            let [var_r1, var_r2, var_role, var_a, var_b] = ["r1", "r2", "role", "a", "b"]
+                .map(|name| conjunction.constraints_mut().get_or_declare_variable(name, None).unwrap());
+
+            conjunction.constraints_mut().add_links(var_r1, var_a, var_role, None).unwrap();
+            conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_a, Vertex::Label(LABEL_CAT), None).unwrap();
+            conjunction.constraints_mut().add_links(var_r2, var_b, var_role, None).unwrap();
+            conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_b, Vertex::Label(LABEL_DOG), None).unwrap();
+
+            let block = builder.finish().unwrap();
+            let err = compute_type_inference_graph(
+                &snapshot,
+                &block,
+                &translation_context.variable_registry,
+                &type_manager,
+                &BTreeMap::new(),
+                &EmptyAnnotatedFunctionSignatures,
+                false,
+            )
+            .unwrap_err();
+            assert_true!(match err {
+                TypeInferenceError::SharedRoleVariableWithoutCommonRoleType {
+                    role_variable,
+                    relation_variables,
+                    ..
+                } => role_variable == "role" && relation_variables == "r1, r2",
+                _ => false,
+            });
+        }
+    }
+
     #[test]
     fn type_constraints() {
         // Some version of `$a isa animal, has name $n;`
@@ -7,9 +7,15 @@
 use std::{collections::HashMap, sync::Arc};
 
 use answer::variable::Variable;
-use concept::{thing::statistics::Statistics, type_::attribute_type::AttributeType};
+use concept::{
+    thing::statistics::{Statistics, StatisticsFreshness},
+    type_::attribute_type::AttributeType,
+};
 use error::typedb_error;
-use ir::{pattern::ParameterID, pipeline::VariableRegistry};
+use ir::{
+    pattern::ParameterID,
+    pipeline::{ParameterRegistry, VariableRegistry},
+};
 
 use crate::{
     annotation::fetch::{AnnotatedFetch, AnnotatedFetchListSubFetch, AnnotatedFetchObject, AnnotatedFetchSome},
@@ -18,6 +24,7 @@ use crate::{
             executable::{compile_single_untabled_function, ExecutableFunction},
             ExecutableFunctionRegistry,
         },
+        match_::planner::plan_cache::PlanCache,
         next_executable_id,
         pipeline::{compile_stages_and_fetch, ExecutableStage, TypePopulations},
         ExecutableCompilationError,
@@ -67,17 +74,30 @@ pub struct ExecutableFetchListSubFetch {
 
 pub fn compile_fetch(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
+    value_parameters: &ParameterRegistry,
     available_functions: &ExecutableFunctionRegistry,
     fetch: AnnotatedFetch,
     variable_positions: &HashMap<Variable, VariablePosition>,
 ) -> Result<(ExecutableFetch, TypePopulations), FetchCompilationError> {
-    let (compiled, type_populations) =
-        compile_object(statistics, available_functions, fetch.object, variable_positions)?;
+    let (compiled, type_populations) = compile_object(
+        statistics,
+        statistics_freshness,
+        plan_cache,
+        value_parameters,
+        available_functions,
+        fetch.object,
+        variable_positions,
+    )?;
     Ok((ExecutableFetch::new(compiled), type_populations))
 }
 
 fn compile_object(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
+    value_parameters: &ParameterRegistry,
     available_functions: &ExecutableFunctionRegistry,
     fetch_object: AnnotatedFetchObject,
     variable_positions: &HashMap<Variable, VariablePosition>,
@@ -87,7 +107,15 @@ fn compile_object(
             let mut compiled_entries = HashMap::with_capacity(entries.len());
             let mut type_populations = TypePopulations::default();
             for (key, value) in entries {
-                let (compiled, pop) = compile_some(statistics, available_functions, value, variable_positions)?;
+                let (compiled, pop) = compile_some(
+                    statistics,
+                    statistics_freshness,
+                    plan_cache,
+                    value_parameters,
+                    available_functions,
+                    value,
+                    variable_positions,
+                )?;
                 compiled_entries.insert(key, compiled);
                 type_populations.extend(pop);
             }
@@ -104,6 +132,9 @@ fn compile_object(
 
 fn compile_some(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
+    value_parameters: &ParameterRegistry,
     available_functions: &ExecutableFunctionRegistry,
     some: AnnotatedFetchSome,
     variable_positions: &HashMap<Variable, VariablePosition>,
@@ -122,25 +153,47 @@ fn compile_some(
             Ok((FetchSomeInstruction::SingleAttribute(*position, attribute_type), TypePopulations::default()))
         }
         AnnotatedFetchSome::SingleFunction(function) => {
-            let compiled = compile_single_untabled_function(statistics, available_functions, function)
-                .map_err(|err| FetchCompilationError::AnonymousFunctionCompilation { typedb_source: Box::new(err) })?;
+            let compiled = compile_single_untabled_function(
+                statistics,
+                statistics_freshness,
+                plan_cache,
+                available_functions,
+                function,
+            )
+            .map_err(|err| FetchCompilationError::AnonymousFunctionCompilation { typedb_source: Box::new(err) })?;
             Ok((FetchSomeInstruction::SingleFunction(compiled, variable_positions.clone()), TypePopulations::default()))
         }
         AnnotatedFetchSome::Object(object) => {
-            let (compiled, type_populations) =
-                compile_object(statistics, available_functions, *object, variable_positions)?;
+            let (compiled, type_populations) = compile_object(
+                statistics,
+                statistics_freshness,
+                plan_cache,
+                value_parameters,
+                available_functions,
+                *object,
+                variable_positions,
+            )?;
             Ok((FetchSomeInstruction::Object(Box::new(compiled)), type_populations))
         }
         AnnotatedFetchSome::ListFunction(function) => {
-            let compiled = compile_single_untabled_function(statistics, available_functions, function)
-                .map_err(|err| FetchCompilationError::AnonymousFunctionCompilation { typedb_source: Box::new(err) })?;
+            let compiled = compile_single_untabled_function(
+                statistics,
+                statistics_freshness,
+                plan_cache,
+                available_functions,
+                function,
+            )
+            .map_err(|err| FetchCompilationError::AnonymousFunctionCompilation { typedb_source: Box::new(err) })?;
             Ok((FetchSomeInstruction::ListFunction(compiled, variable_positions.clone()), TypePopulations::default()))
         }
         AnnotatedFetchSome::ListSubFetch(sub_fetch) => {
             let AnnotatedFetchListSubFetch { variable_registry, input_variables, stages, fetch } = sub_fetch;
             let (input_positions, compiled_stages, compiled_fetch, type_populations) = compile_stages_and_fetch(
                 statistics,
+                statistics_freshness,
+                plan_cache,
                 &variable_registry,
+                value_parameters,
                 available_functions,
                 &stages,
                 Some(fetch),
@@ -7,7 +7,7 @@
 use std::{collections::HashMap, sync::Arc};
 
 use answer::variable::Variable;
-use concept::thing::statistics::Statistics;
+use concept::thing::statistics::{Statistics, StatisticsFreshness};
 use ir::pipeline::{
     function_signature::{FunctionID, FunctionIDAPI},
     ParameterRegistry,
@@ -19,9 +19,10 @@ use crate::{
     executable::{
         function::{
             recursion_analyser::{all_calls_in_pipeline, determine_compilation_order_and_tabling_types},
-            ExecutableFunctionRegistry, FunctionCallCostProvider, FunctionTablingType,
+            ExecutableFunctionRegistry, FunctionCallCost, FunctionCallCostProvider, FunctionTablingType,
+            FunctionVersions,
         },
-        match_::planner::vertex::Cost,
+        match_::planner::{plan_cache::PlanCache, vertex::Cost},
         next_executable_id,
         pipeline::{compile_pipeline_stages, ExecutableStage},
         reduce::ReduceRowsExecutable,
@@ -38,7 +39,8 @@ pub struct ExecutableFunction {
     pub returns: ExecutableReturn,
     pub tabling_type: FunctionTablingType,
     pub parameter_registry: Arc<ParameterRegistry>,
-    pub(crate) single_call_cost: Cost,
+    pub(crate) call_cost: FunctionCallCost,
+    pub(crate) version: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -51,27 +53,50 @@ pub enum ExecutableReturn {
 
 pub(crate) fn compile_single_untabled_function(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     cached_plans: &ExecutableFunctionRegistry,
     to_compile: AnnotatedFunction,
 ) -> Result<ExecutableFunction, ExecutableCompilationError> {
-    compile_function(statistics, to_compile, cached_plans, FunctionTablingType::Untabled)
+    // functions inlined into a fetch are not part of the schema, and cannot be redefined
+    compile_function(
+        statistics,
+        statistics_freshness,
+        plan_cache,
+        to_compile,
+        cached_plans,
+        FunctionTablingType::Untabled,
+        0,
+    )
 }
 
 pub(crate) fn compile_functions<FIDType: FunctionIDAPI>(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     cached_plans: &ExecutableFunctionRegistry,
+    function_versions: &FunctionVersions,
     mut to_compile: HashMap<FIDType, AnnotatedFunction>,
 ) -> Result<HashMap<FIDType, ExecutableFunction>, ExecutableCompilationError> {
     // TODO: Cache compiled schema functions?
     let (post_order, tabling_types) = determine_compilation_order_and_tabling_types(&to_compile)?;
-    let mut context = FunctionCompilationContext::new(cached_plans, tabling_types);
+    let mut context = FunctionCompilationContext::new(cached_plans, function_versions, tabling_types);
 
     // Compiling functions in post-order ensures dependencies are compiled first, and we have a cost available.
     for fid in post_order {
         debug_assert!(to_compile.contains_key(&fid)); // occurs exactly-once in post_order
         if let Some(function) = to_compile.remove(&fid) {
             let tabling_type = context.tabling_types.get(&fid).unwrap().clone();
-            let compiled_function = compile_function(statistics, function, &context, tabling_type)?;
+            let version = function_versions.current_version(&fid.clone().into());
+            let compiled_function = compile_function(
+                statistics,
+                statistics_freshness,
+                plan_cache,
+                function,
+                &context,
+                tabling_type,
+                version,
+            )?;
             context.compiled.insert(fid.clone(), compiled_function);
         }
     }
@@ -81,9 +106,12 @@ pub(crate) fn compile_functions<FIDType: FunctionIDAPI>(
 
 fn compile_function(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     function: AnnotatedFunction,
     call_cost_provider: &impl FunctionCallCostProvider,
     is_tabled: FunctionTablingType,
+    version: u64,
 ) -> Result<ExecutableFunction, ExecutableCompilationError> {
     debug_assert!(all_calls_in_pipeline(function.stages.as_slice()).iter().all(|f| {
         call_cost_provider.get_call_cost(f);
@@ -92,7 +120,10 @@ fn compile_function(
     let AnnotatedFunction { variable_registry, parameter_registry, arguments, stages, return_, .. } = function;
     let (argument_positions, executable_stages, _) = compile_pipeline_stages(
         statistics,
+        statistics_freshness,
+        plan_cache,
         &variable_registry,
+        &parameter_registry,
         call_cost_provider,
         &stages,
         arguments.into_iter(),
@@ -100,12 +131,12 @@ fn compile_function(
     )?;
 
     let returns = compile_return_operation(&executable_stages, return_)?;
-    debug_assert!(executable_stages.iter().any(|stage| matches!(stage, ExecutableStage::Match(_))));
-    let single_call_cost =
+    debug_assert!(executable_stages.iter().any(|stage| matches!(stage, ExecutableStage::Match { .. })));
+    let body_cost =
         executable_stages
             .iter()
             .filter_map(|stage| {
-                if let ExecutableStage::Match(m) = stage {
+                if let ExecutableStage::Match { executable: m, .. } = stage {
                     Some(m.planner_statistics().query_cost)
                 } else {
                     None
@@ -113,6 +144,7 @@ fn compile_function(
             })
             .reduce(|x, y| x.chain(y))
             .unwrap();
+    let call_cost = FunctionCallCost::from_body_cost(body_cost);
     Ok(ExecutableFunction {
         executable_id: next_executable_id(),
         executable_stages,
@@ -120,7 +152,8 @@ fn compile_function(
         returns,
         parameter_registry: Arc::new(parameter_registry),
         tabling_type: is_tabled,
-        single_call_cost,
+        call_cost,
+        version,
     })
 }
 
@@ -151,14 +184,24 @@ fn compile_return_operation(
 // Private
 struct FunctionCompilationContext<'a, FIDType: FunctionIDAPI> {
     precompiled: &'a ExecutableFunctionRegistry,
+    function_versions: &'a FunctionVersions,
     compiled: HashMap<FIDType, ExecutableFunction>,
 
     tabling_types: HashMap<FIDType, FunctionTablingType>,
 }
 
 impl<'a, FIDType: FunctionIDAPI> FunctionCompilationContext<'a, FIDType> {
-    fn new(cached_plans: &'a ExecutableFunctionRegistry, tabling_types: HashMap<FIDType, FunctionTablingType>) -> Self {
-        FunctionCompilationContext { precompiled: cached_plans, compiled: HashMap::new(), tabling_types }
+    fn new(
+        cached_plans: &'a ExecutableFunctionRegistry,
+        function_versions: &'a FunctionVersions,
+        tabling_types: HashMap<FIDType, FunctionTablingType>,
+    ) -> Self {
+        FunctionCompilationContext {
+            precompiled: cached_plans,
+            function_versions,
+            compiled: HashMap::new(),
+            tabling_types,
+        }
     }
 
     pub(crate) fn get_executable_function(&self, function_id: &FunctionID) -> Option<&ExecutableFunction> {
@@ -171,15 +214,16 @@ impl<'a, FIDType: FunctionIDAPI> FunctionCompilationContext<'a, FIDType> {
         }
     }
 
-    fn cycle_breaking_cost(&self) -> Cost {
-        Cost { cost: 1.0, io_ratio: 1.0 } // TODO: Improve. This should simulate depth 1 recursion.
+    fn cycle_breaking_cost(&self) -> FunctionCallCost {
+        // TODO: Improve. This should simulate depth 1 recursion.
+        FunctionCallCost::from_body_cost(Cost { cost: 1.0, io_ratio: 1.0 })
     }
 }
 
 impl<FIDType: FunctionIDAPI> FunctionCallCostProvider for FunctionCompilationContext<'_, FIDType> {
-    fn get_call_cost(&self, function_id: &FunctionID) -> Cost {
+    fn get_call_cost(&self, function_id: &FunctionID) -> FunctionCallCost {
         if let Some(function) = self.get_executable_function(function_id) {
-            function.single_call_cost
+            function.call_cost
         } else {
             debug_assert!(matches!(
                 FIDType::try_from(function_id.clone())
@@ -189,4 +233,11 @@ impl<FIDType: FunctionIDAPI> FunctionCallCostProvider for FunctionCompilationCon
             self.cycle_breaking_cost()
         }
     }
+
+    fn function_version(&self, function_id: &FunctionID) -> u64 {
+        match self.get_executable_function(function_id) {
+            Some(function) => function.version,
+            None => self.function_versions.current_version(function_id),
+        }
+    }
 }
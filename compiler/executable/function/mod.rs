@@ -4,7 +4,11 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{collections::HashMap, fmt, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+};
 
 use encoding::graph::definition::definition_key::DefinitionKey;
 use ir::pipeline::function_signature::FunctionID;
@@ -14,6 +18,8 @@ use crate::executable::{function::executable::ExecutableFunction, match_::planne
 pub mod executable;
 mod recursion_analyser;
 
+pub(crate) use recursion_analyser::all_calls_in_conjunction;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StronglyConnectedComponentID(FunctionID);
 
@@ -24,7 +30,78 @@ pub enum FunctionTablingType {
 }
 
 pub trait FunctionCallCostProvider {
-    fn get_call_cost(&self, function_id: &FunctionID) -> Cost;
+    fn get_call_cost(&self, function_id: &FunctionID) -> FunctionCallCost;
+
+    /// The version of the definition the call is compiled against, see `FunctionVersions::invalidate`.
+    fn function_version(&self, function_id: &FunctionID) -> u64;
+}
+
+/// The cost of a single call, derived from the plan of the function body. Arguments are always bound when a function
+/// is called, so the variants are distinguished by whether the returned variables are bound too.
+#[derive(Debug, Clone, Copy)]
+pub struct FunctionCallCost {
+    /// Returned variables are unbound: every returned row is produced.
+    pub(crate) producing: Cost,
+    /// Returned variables are bound: the call only filters the input row.
+    pub(crate) checking: Cost,
+}
+
+impl FunctionCallCost {
+    pub(crate) fn from_body_cost(body_cost: Cost) -> Self {
+        let checking = Cost { cost: body_cost.cost, io_ratio: f64::min(body_cost.io_ratio, 1.0) };
+        Self { producing: body_cost, checking }
+    }
+
+    /// The cost of a call to a function declared to return exactly one row, which behaves like an expression.
+    pub(crate) fn single_row(self) -> Self {
+        Self {
+            producing: Cost { cost: self.producing.cost, io_ratio: 1.0 },
+            checking: Cost { cost: self.checking.cost, io_ratio: 1.0 },
+        }
+    }
+}
+
+type InvalidationListener = Box<dyn Fn(&FunctionID) + Send + Sync>;
+
+/// The versions of the schema function definitions of a database, bumped as schema transactions redefining or
+/// undefining functions commit. Functions that were never invalidated are at version 0.
+#[derive(Default)]
+pub struct FunctionVersions {
+    versions: RwLock<HashMap<DefinitionKey, u64>>,
+    listeners: RwLock<Vec<InvalidationListener>>,
+}
+
+impl fmt::Debug for FunctionVersions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionVersions").field("versions", &self.versions).finish_non_exhaustive()
+    }
+}
+
+impl FunctionVersions {
+    /// Marks the definition of a schema function as changed, so that executables compiled against the previous
+    /// definition are rejected by registries compiled against the new one, and notifies the invalidation listeners.
+    /// Must only be called once the change is committed.
+    pub fn invalidate(&self, function_id: &FunctionID) {
+        if let FunctionID::Schema(key) = function_id {
+            *self.versions.write().unwrap().entry(key.clone()).or_default() += 1;
+        }
+        for listener in self.listeners.read().unwrap().iter() {
+            listener(function_id);
+        }
+    }
+
+    /// Registers a callback fired with the id of every function of the database that is redefined or undefined.
+    pub fn on_invalidation(&self, listener: impl Fn(&FunctionID) + Send + Sync + 'static) {
+        self.listeners.write().unwrap().push(Box::new(listener));
+    }
+
+    /// The version of the current definition of the function. Preamble functions only live as long as their query.
+    pub(crate) fn current_version(&self, function_id: &FunctionID) -> u64 {
+        match function_id {
+            FunctionID::Schema(key) => self.versions.read().unwrap().get(key).copied().unwrap_or(0),
+            FunctionID::Preamble(_) => 0,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -62,10 +139,9 @@ impl ExecutableFunctionRegistry {
     pub(crate) fn schema_functions(&self) -> Arc<HashMap<DefinitionKey, ExecutableFunction>> {
         self.schema_functions.clone()
     }
-}
 
-impl FunctionCallCostProvider for ExecutableFunctionRegistry {
-    fn get_call_cost(&self, function_id: &FunctionID) -> Cost {
-        self.get(function_id).unwrap().single_call_cost
+    /// The version of the definition the function in this registry was compiled from.
+    pub fn function_version(&self, function_id: &FunctionID) -> Option<u64> {
+        self.get(function_id).map(|function| function.version)
     }
 }
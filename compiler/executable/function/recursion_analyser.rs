@@ -161,7 +161,7 @@ pub(super) fn all_calls_in_pipeline(stages: &[AnnotatedStage]) -> HashSet<Functi
     call_accumulator
 }
 
-fn all_calls_in_conjunction(conjunction: &Conjunction, call_accumulator: &mut HashSet<FunctionID>) {
+pub(crate) fn all_calls_in_conjunction(conjunction: &Conjunction, call_accumulator: &mut HashSet<FunctionID>) {
     for constraint in conjunction.constraints() {
         if let Constraint::FunctionCallBinding(binding) = constraint {
             call_accumulator.insert(binding.function_call().function_id());
@@ -19,13 +19,19 @@ use ir::pattern::{
     IrID, ParameterID, Vertex,
 };
 use itertools::Itertools;
+use unicase::UniCase;
 
-use crate::{annotation::type_annotations::TypeAnnotations, ExecutorVariable, VariablePosition};
+use crate::{
+    annotation::type_annotations::TypeAnnotations, executable::match_::instructions::pushdown::PushdownCandidate,
+    ExecutorVariable, VariablePosition,
+};
 
+pub mod pushdown;
 pub mod thing;
 pub mod type_;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum VariableMode {
     Input,
     Output,
@@ -59,6 +65,7 @@ impl fmt::Display for VariableMode {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariableModes {
     modes: HashMap<ExecutorVariable, VariableMode>,
 }
@@ -88,6 +95,13 @@ impl VariableModes {
         modes
     }
 
+    /// Upgrades a counted or checked variable to an output, so iterators enumerate each of its values.
+    pub(crate) fn enumerate(&mut self, variable: ExecutorVariable) {
+        if let Some(mode @ (VariableMode::Count | VariableMode::Check)) = self.modes.get_mut(&variable) {
+            *mode = VariableMode::Output;
+        }
+    }
+
     fn insert(&mut self, variable_position: ExecutorVariable, mode: VariableMode) {
         let existing = self.modes.insert(variable_position, mode);
         debug_assert!(existing.is_none() || existing == Some(mode))
@@ -109,6 +123,10 @@ impl VariableModes {
         self.modes.values().all(|mode| mode != &VariableMode::Input)
     }
 
+    pub fn any_counted(&self) -> bool {
+        self.modes.values().any(|mode| mode == &VariableMode::Count)
+    }
+
     pub fn make_var_mapped(&self, mapping: &HashMap<ExecutorVariable, Variable>) -> VarMappedVariableModes {
         let mut var_mapped_modes = HashMap::new();
         for (var, mode) in &self.modes {
@@ -118,6 +136,14 @@ impl VariableModes {
     }
 }
 
+impl FromIterator<(ExecutorVariable, VariableMode)> for VariableModes {
+    fn from_iter<T: IntoIterator<Item = (ExecutorVariable, VariableMode)>>(iter: T) -> Self {
+        let mut modes = Self::new();
+        iter.into_iter().for_each(|(variable, mode)| modes.insert(variable, mode));
+        modes
+    }
+}
+
 impl fmt::Display for VariableModes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (key, group) in &self.modes.iter().sorted_by_key(|(_, value)| *value).group_by(|(_, value)| *value) {
@@ -148,6 +174,7 @@ impl fmt::Display for VarMappedVariableModes {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConstraintInstruction<ID> {
     Is(IsInstruction<ID>),
 
@@ -189,6 +216,8 @@ pub enum ConstraintInstruction<ID> {
     Links(thing::LinksInstruction<ID>),
     // player -> relation
     LinksReverse(thing::LinksReverseInstruction<ID>),
+    // relation <-> player, both bound
+    BoundLinks(thing::BoundLinksInstruction<ID>),
 
     // $x --> $y
     // RolePlayerIndex(IR, IterateBounds)
@@ -234,7 +263,10 @@ impl<ID: IrID> ConstraintInstruction<ID> {
             Self::Has(thing::HasInstruction { has, .. })
             | Self::HasReverse(thing::HasReverseInstruction { has, .. }) => has.ids_foreach(apply),
             Self::Links(thing::LinksInstruction { links, .. })
-            | Self::LinksReverse(thing::LinksReverseInstruction { links, .. }) => links.ids_foreach(apply),
+            | Self::LinksReverse(thing::LinksReverseInstruction { links, .. })
+            | Self::BoundLinks(thing::BoundLinksInstruction {
+                canonical: thing::LinksInstruction { links, .. }, ..
+            }) => links.ids_foreach(apply),
             Self::IndexedRelation(thing::IndexedRelationInstruction {
                 player_start,
                 player_end,
@@ -270,9 +302,11 @@ impl<ID: IrID> ConstraintInstruction<ID> {
             | Self::Has(thing::HasInstruction { inputs, .. })
             | Self::HasReverse(thing::HasReverseInstruction { inputs, .. })
             | Self::Links(thing::LinksInstruction { inputs, .. })
-            | Self::LinksReverse(thing::LinksReverseInstruction { inputs, .. }) => {
-                inputs.iter().cloned().for_each(apply)
-            }
+            | Self::LinksReverse(thing::LinksReverseInstruction { inputs, .. })
+            | Self::BoundLinks(thing::BoundLinksInstruction {
+                canonical: thing::LinksInstruction { inputs, .. },
+                ..
+            }) => inputs.iter().cloned().for_each(apply),
             | Self::IndexedRelation(thing::IndexedRelationInstruction { inputs, .. }) => {
                 inputs.iter().cloned().for_each(apply)
             }
@@ -327,7 +361,11 @@ impl<ID: IrID> ConstraintInstruction<ID> {
                 }
             }),
             Self::Links(thing::LinksInstruction { links, inputs, .. })
-            | Self::LinksReverse(thing::LinksReverseInstruction { links, inputs, .. }) => links.ids_foreach(|var| {
+            | Self::LinksReverse(thing::LinksReverseInstruction { links, inputs, .. })
+            | Self::BoundLinks(thing::BoundLinksInstruction {
+                canonical: thing::LinksInstruction { links, inputs, .. },
+                ..
+            }) => links.ids_foreach(|var| {
                 if !inputs.contains(var) {
                     apply(var)
                 }
@@ -360,6 +398,48 @@ impl<ID: IrID> ConstraintInstruction<ID> {
         }
     }
 
+    /// Applies `apply` to every parameter the instruction or any of its checks reads.
+    pub fn parameters_foreach(&self, mut apply: impl FnMut(ParameterID)) {
+        if let Self::Iid(thing::IidInstruction { iid, .. }) = self {
+            iid.iid().as_parameter().into_iter().for_each(&mut apply);
+        }
+        self.checks().iter().for_each(|check| check.parameters_foreach(&mut apply));
+    }
+
+    pub fn checks(&self) -> &[CheckInstruction<ID>] {
+        match self {
+            Self::Is(inner) => &inner.checks,
+            Self::Iid(inner) => &inner.checks,
+            Self::TypeList(inner) => &inner.checks,
+            Self::Sub(inner) => &inner.checks,
+            Self::SubReverse(inner) => &inner.checks,
+            Self::Owns(inner) => &inner.checks,
+            Self::OwnsReverse(inner) => &inner.checks,
+            Self::Relates(inner) => &inner.checks,
+            Self::RelatesReverse(inner) => &inner.checks,
+            Self::Plays(inner) => &inner.checks,
+            Self::PlaysReverse(inner) => &inner.checks,
+            Self::Isa(inner) => &inner.checks,
+            Self::IsaReverse(inner) => &inner.checks,
+            Self::Has(inner) => &inner.checks,
+            Self::HasReverse(inner) => &inner.checks,
+            Self::Links(inner) => &inner.checks,
+            Self::LinksReverse(inner) => &inner.checks,
+            Self::BoundLinks(inner) => &inner.canonical.checks,
+            Self::IndexedRelation(inner) => &inner.checks,
+        }
+    }
+
+    /// The checks of the instruction a storage backend could apply while it scans the instruction's tuples.
+    pub fn pushdown_candidates(&self) -> Vec<PushdownCandidate<ID>> {
+        let mut used_variables = Vec::new();
+        self.used_variables_foreach(|var| used_variables.push(var));
+        self.checks()
+            .iter()
+            .filter_map(|check| PushdownCandidate::from_check(check, |var| used_variables.contains(&var)))
+            .collect()
+    }
+
     pub(crate) fn add_check(&mut self, check: CheckInstruction<ID>) {
         match self {
             Self::Is(inner) => inner.add_check(check),
@@ -379,6 +459,7 @@ impl<ID: IrID> ConstraintInstruction<ID> {
             Self::HasReverse(inner) => inner.add_check(check),
             Self::Links(inner) => inner.add_check(check),
             Self::LinksReverse(inner) => inner.add_check(check),
+            Self::BoundLinks(inner) => inner.add_check(check),
             Self::IndexedRelation(inner) => inner.add_check(check),
         };
     }
@@ -402,6 +483,7 @@ impl<ID: IrID> ConstraintInstruction<ID> {
             Self::HasReverse(inner) => ConstraintInstruction::HasReverse(inner.map(mapping)),
             Self::Links(inner) => ConstraintInstruction::Links(inner.map(mapping)),
             Self::LinksReverse(inner) => ConstraintInstruction::LinksReverse(inner.map(mapping)),
+            Self::BoundLinks(inner) => ConstraintInstruction::BoundLinks(inner.map(mapping)),
             Self::IndexedRelation(inner) => ConstraintInstruction::IndexedRelation(inner.map(mapping)),
         }
     }
@@ -427,12 +509,14 @@ impl<ID: IrID> fmt::Display for ConstraintInstruction<ID> {
             ConstraintInstruction::HasReverse(instruction) => write!(f, "{instruction}"),
             ConstraintInstruction::Links(instruction) => write!(f, "{instruction}"),
             ConstraintInstruction::LinksReverse(instruction) => write!(f, "{instruction}"),
+            ConstraintInstruction::BoundLinks(instruction) => write!(f, "{instruction}"),
             ConstraintInstruction::IndexedRelation(instruction) => write!(f, "{instruction}"),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IsInstruction<ID> {
     pub is: Is<ID>,
     pub inputs: Inputs<ID>,
@@ -468,7 +552,8 @@ impl<ID: IrID> fmt::Display for IsInstruction<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum CheckVertex<ID> {
     Variable(ID),
     Type(Type),
@@ -555,14 +640,23 @@ impl<ID: IrID> fmt::Display for CheckVertex<ID> {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum CheckInstruction<ID> {
     TypeList {
         type_var: ID,
+        #[cfg_attr(
+            feature = "serialization",
+            serde(with = "crate::executable::match_::planner::serialization::shared")
+        )]
         types: Arc<BTreeSet<Type>>,
     },
     ThingTypeList {
         thing_var: ID,
+        #[cfg_attr(
+            feature = "serialization",
+            serde(with = "crate::executable::match_::planner::serialization::shared")
+        )]
         types: Arc<BTreeSet<Type>>,
     },
     Iid {
@@ -624,11 +718,91 @@ pub enum CheckInstruction<ID> {
         lhs: CheckVertex<ID>,
         rhs: CheckVertex<ID>,
         comparator: Comparator,
+        /// The matcher of a `like` or `contains` comparison against a constant, prepared when the check is lowered.
+        #[cfg_attr(feature = "serialization", serde(skip))]
+        matcher: Option<StringMatcher>,
     },
     Unsatisfiable,
 }
 
 impl<ID: IrID> CheckInstruction<ID> {
+    /// A static estimate of how expensive the check is to evaluate for a single row, used to order the checks of a
+    /// step so that the cheap ones reject rows before any storage is read:
+    ///   0 - decided by the row alone,
+    ///   1 - needs the schema or the value of an attribute,
+    ///   2 - looks up an edge in storage.
+    pub fn cost_rank(&self) -> u8 {
+        match self {
+            Self::Unsatisfiable
+            | Self::Iid { .. }
+            | Self::Is { .. }
+            | Self::TypeList { .. }
+            | Self::ThingTypeList { .. }
+            | Self::LinksDeduplication { .. } => 0,
+            Self::Sub { .. }
+            | Self::Owns { .. }
+            | Self::Relates { .. }
+            | Self::Plays { .. }
+            | Self::Isa { .. }
+            | Self::Comparison { .. } => 1,
+            Self::Has { .. } | Self::Links { .. } | Self::IndexedRelation { .. } => 2,
+        }
+    }
+
+    pub fn variables_foreach(&self, mut apply: impl FnMut(ID)) {
+        let mut apply_vertex = |vertex: &CheckVertex<ID>| vertex.as_variable().into_iter().for_each(&mut apply);
+        match self {
+            Self::TypeList { type_var: var, .. }
+            | Self::ThingTypeList { thing_var: var, .. }
+            | Self::Iid { var, .. } => apply(*var),
+            Self::Sub { subtype: lhs, supertype: rhs, .. }
+            | Self::Owns { owner: lhs, attribute: rhs }
+            | Self::Relates { relation: lhs, role_type: rhs }
+            | Self::Plays { player: lhs, role_type: rhs }
+            | Self::Isa { type_: lhs, thing: rhs, .. }
+            | Self::Has { owner: lhs, attribute: rhs }
+            | Self::Comparison { lhs, rhs, .. } => {
+                apply_vertex(lhs);
+                apply_vertex(rhs);
+            }
+            Self::Links { relation, player, role } => [relation, player, role].into_iter().for_each(apply_vertex),
+            Self::IndexedRelation { start_player, end_player, relation, start_role, end_role } => {
+                [start_player, end_player, relation, start_role, end_role].into_iter().for_each(apply_vertex)
+            }
+            &Self::Is { lhs, rhs } => [lhs, rhs].into_iter().for_each(apply),
+            &Self::LinksDeduplication { role1, player1, role2, player2 } => {
+                [role1, player1, role2, player2].into_iter().for_each(apply)
+            }
+            Self::Unsatisfiable => (),
+        }
+    }
+
+    pub fn parameters_foreach(&self, mut apply: impl FnMut(ParameterID)) {
+        let mut apply_vertex = |vertex: &CheckVertex<ID>| vertex.as_parameter().into_iter().for_each(&mut apply);
+        match self {
+            &Self::Iid { iid, .. } => apply(iid),
+            Self::Sub { subtype: lhs, supertype: rhs, .. }
+            | Self::Owns { owner: lhs, attribute: rhs }
+            | Self::Relates { relation: lhs, role_type: rhs }
+            | Self::Plays { player: lhs, role_type: rhs }
+            | Self::Isa { type_: lhs, thing: rhs, .. }
+            | Self::Has { owner: lhs, attribute: rhs }
+            | Self::Comparison { lhs, rhs, .. } => {
+                apply_vertex(lhs);
+                apply_vertex(rhs);
+            }
+            Self::Links { relation, player, role } => [relation, player, role].into_iter().for_each(apply_vertex),
+            Self::IndexedRelation { start_player, end_player, relation, start_role, end_role } => {
+                [start_player, end_player, relation, start_role, end_role].into_iter().for_each(apply_vertex)
+            }
+            Self::TypeList { .. }
+            | Self::ThingTypeList { .. }
+            | Self::Is { .. }
+            | Self::LinksDeduplication { .. }
+            | Self::Unsatisfiable => (),
+        }
+    }
+
     pub fn map<T: IrID>(self, mapping: &HashMap<ID, T>) -> CheckInstruction<T> {
         match self {
             Self::TypeList { type_var, types } => CheckInstruction::TypeList { type_var: mapping[&type_var], types },
@@ -677,8 +851,8 @@ impl<ID: IrID> CheckInstruction<ID> {
                 role2: mapping[&role2],
                 player2: mapping[&player2],
             },
-            Self::Comparison { lhs, rhs, comparator } => {
-                CheckInstruction::Comparison { lhs: lhs.map(mapping), rhs: rhs.map(mapping), comparator }
+            Self::Comparison { lhs, rhs, comparator, matcher } => {
+                CheckInstruction::Comparison { lhs: lhs.map(mapping), rhs: rhs.map(mapping), comparator, matcher }
             }
             Self::Unsatisfiable => CheckInstruction::Unsatisfiable,
         }
@@ -739,7 +913,7 @@ impl<ID: IrID> fmt::Display for CheckInstruction<ID> {
             Self::LinksDeduplication { role1, player1, role2, player2 } => {
                 write!(f, "({role1},{player1}) __links_deduplication__ ({role2},{player2})")?;
             }
-            Self::Comparison { lhs, rhs, comparator } => {
+            Self::Comparison { lhs, rhs, comparator, .. } => {
                 write!(f, "{lhs} {comparator} {rhs}")?;
             }
             Self::Unsatisfiable => {
@@ -750,7 +924,62 @@ impl<ID: IrID> fmt::Display for CheckInstruction<ID> {
     }
 }
 
+/// The constant right-hand side of a `like` or `contains` comparison, prepared once so that checking a row neither
+/// recompiles the regex nor case-folds the needle again. Executables may be reused with other parameter values, so
+/// the matcher records the string it was prepared from, to be compared against the value the check is executed with.
+#[derive(Clone, Debug)]
+pub struct StringMatcher {
+    source: Arc<str>,
+    kind: StringMatcherKind,
+}
+
+#[derive(Clone, Debug)]
+enum StringMatcherKind {
+    Regex(regex::Regex),
+    CaseFoldedNeedle(String),
+}
+
+impl StringMatcher {
+    /// Prepares the matcher of a comparison against `source`, if the comparator matches strings by pattern.
+    pub fn prepare(comparator: Comparator, source: &str) -> Result<Option<Self>, regex::Error> {
+        let kind = match comparator {
+            Comparator::Like => StringMatcherKind::Regex(regex::Regex::new(source)?),
+            Comparator::Contains => StringMatcherKind::CaseFoldedNeedle(UniCase::new(source).to_folded_case()),
+            Comparator::Equal
+            | Comparator::NotEqual
+            | Comparator::Less
+            | Comparator::Greater
+            | Comparator::LessOrEqual
+            | Comparator::GreaterOrEqual => return Ok(None),
+        };
+        Ok(Some(Self { source: Arc::from(source), kind }))
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        match &self.kind {
+            StringMatcherKind::Regex(regex) => regex.is_match(value),
+            StringMatcherKind::CaseFoldedNeedle(needle) => {
+                UniCase::new(value).to_folded_case().contains(needle.as_str())
+            }
+        }
+    }
+}
+
+impl PartialEq for StringMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        let is_regex = |matcher: &Self| matches!(matcher.kind, StringMatcherKind::Regex(_));
+        self.source == other.source && is_regex(self) == is_regex(other)
+    }
+}
+
+impl Eq for StringMatcher {}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum Inputs<ID> {
     None([ID; 0]),
     Single([ID; 1]),
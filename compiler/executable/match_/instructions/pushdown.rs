@@ -0,0 +1,109 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{collections::BTreeSet, fmt, sync::Arc};
+
+use answer::Type;
+use ir::pattern::{constraint::Comparator, IrID, ParameterID};
+
+use crate::executable::match_::instructions::{CheckInstruction, CheckVertex};
+
+/// A check of an instruction simple enough for a storage backend to apply while it scans the instruction's tuples.
+/// A backend that claims a candidate guarantees that every tuple it returns satisfies it, so the executor does not
+/// evaluate the check again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub struct PushdownCandidate<ID> {
+    pub variable: ID,
+    pub filter: PushdownFilter,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub enum PushdownFilter {
+    /// The variable is one of the types.
+    Types(
+        #[cfg_attr(
+            feature = "serialization",
+            serde(with = "crate::executable::match_::planner::serialization::shared")
+        )]
+        Arc<BTreeSet<Type>>,
+    ),
+    /// The variable is an instance of one of the types.
+    InstanceOfTypes(
+        #[cfg_attr(
+            feature = "serialization",
+            serde(with = "crate::executable::match_::planner::serialization::shared")
+        )]
+        Arc<BTreeSet<Type>>,
+    ),
+    /// The value of the variable compares to the constant as `variable <comparator> value`. Only the comparators
+    /// that bound a range of values are candidates.
+    ValueRange { comparator: Comparator, value: ParameterID },
+}
+
+impl<ID: IrID> PushdownCandidate<ID> {
+    /// The candidate the check makes, if it filters a single variable of the scanned tuples against constants.
+    pub fn from_check(check: &CheckInstruction<ID>, is_scanned: impl Fn(ID) -> bool) -> Option<Self> {
+        let (variable, filter) = match check {
+            CheckInstruction::TypeList { type_var, types } => (*type_var, PushdownFilter::Types(types.clone())),
+            CheckInstruction::ThingTypeList { thing_var, types } => {
+                (*thing_var, PushdownFilter::InstanceOfTypes(types.clone()))
+            }
+            CheckInstruction::Comparison { lhs, rhs, comparator, .. } => {
+                let (variable, value, comparator) = match (lhs, rhs) {
+                    (&CheckVertex::Variable(variable), &CheckVertex::Parameter(value)) => {
+                        (variable, value, *comparator)
+                    }
+                    (&CheckVertex::Parameter(value), &CheckVertex::Variable(variable)) => {
+                        (variable, value, mirrored_range_comparator(*comparator)?)
+                    }
+                    _ => return None,
+                };
+                mirrored_range_comparator(comparator)?;
+                (variable, PushdownFilter::ValueRange { comparator, value })
+            }
+            _ => return None,
+        };
+        is_scanned(variable).then_some(Self { variable, filter })
+    }
+}
+
+/// The comparator that holds with its operands swapped, for comparators that bound a range of values.
+fn mirrored_range_comparator(comparator: Comparator) -> Option<Comparator> {
+    match comparator {
+        Comparator::Equal => Some(Comparator::Equal),
+        Comparator::Less => Some(Comparator::Greater),
+        Comparator::LessOrEqual => Some(Comparator::GreaterOrEqual),
+        Comparator::Greater => Some(Comparator::Less),
+        Comparator::GreaterOrEqual => Some(Comparator::LessOrEqual),
+        Comparator::NotEqual | Comparator::Like | Comparator::Contains => None,
+    }
+}
+
+impl<ID: IrID> fmt::Display for PushdownCandidate<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_types = |f: &mut fmt::Formatter<'_>, types: &BTreeSet<Type>| {
+            write!(f, "(")?;
+            for type_ in types {
+                write!(f, "{type_}, ")?;
+            }
+            write!(f, ")")
+        };
+        let Self { variable, filter } = self;
+        match filter {
+            PushdownFilter::Types(types) => {
+                write!(f, "{variable} type ")?;
+                write_types(f, types)
+            }
+            PushdownFilter::InstanceOfTypes(types) => {
+                write!(f, "{variable} thing_is_of_type ")?;
+                write_types(f, types)
+            }
+            PushdownFilter::ValueRange { comparator, value } => write!(f, "{variable} {} {value}", comparator.name()),
+        }
+    }
+}
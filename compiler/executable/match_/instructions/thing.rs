@@ -23,8 +23,10 @@ use crate::{
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IidInstruction<ID> {
     pub iid: Iid<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -60,9 +62,11 @@ impl<ID: IrID> fmt::Display for IidInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IsaInstruction<ID> {
     pub isa: Isa<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub instance_type_to_types: Arc<BTreeMap<Type, Vec<Type>>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -100,9 +104,11 @@ impl<ID: IrID> fmt::Display for IsaInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IsaReverseInstruction<ID> {
     pub isa: Isa<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub type_to_instance_types: Arc<BTreeMap<Type, Vec<Type>>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -140,10 +146,13 @@ impl<ID: IrID> fmt::Display for IsaReverseInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct HasInstruction<ID> {
     pub has: Has<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     owner_to_attribute_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     attribute_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -192,10 +201,13 @@ impl<ID: IrID> fmt::Display for HasInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct HasReverseInstruction<ID> {
     pub has: Has<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     attribute_to_owner_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     owner_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -243,11 +255,15 @@ impl<ID: IrID> fmt::Display for HasReverseInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinksInstruction<ID> {
     pub links: Links<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     relation_to_player_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     player_to_role_types: Arc<BTreeMap<Type, BTreeSet<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     player_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -301,11 +317,15 @@ impl<ID: IrID> fmt::Display for LinksInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct LinksReverseInstruction<ID> {
     pub links: Links<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     player_to_relation_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     relation_to_role_types: Arc<BTreeMap<Type, BTreeSet<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     relation_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -359,8 +379,70 @@ impl<ID: IrID> fmt::Display for LinksReverseInstruction<ID> {
     }
 }
 
+/// A `links` whose relation and player are both bound, so only the role is produced. Either direction verifies the
+/// edge, so both candidates are kept and the executor probes whichever side the hint expects to scan less from.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundLinksInstruction<ID> {
+    pub canonical: LinksInstruction<ID>,
+    pub reverse: LinksReverseInstruction<ID>,
+    pub hint: BoundLinksHint,
+}
+
+/// Expected number of role players scanned from a bound relation, and of relations scanned from a bound player, per
+/// type. A type missing from its map has no estimate.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundLinksHint {
+    pub prefer_relation_side: bool,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
+    pub relation_fan_out: Arc<HashMap<Type, f64>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
+    pub player_fan_out: Arc<HashMap<Type, f64>>,
+}
+
+impl BoundLinksInstruction<Variable> {
+    pub fn new(
+        links: Links<Variable>,
+        relation: Variable,
+        player: Variable,
+        hint: BoundLinksHint,
+        type_annotations: &TypeAnnotations,
+    ) -> Self {
+        let inputs = Inputs::Dual([relation, player]);
+        let canonical = LinksInstruction::new(links.clone(), inputs, type_annotations);
+        let reverse = LinksReverseInstruction::new(links, inputs, type_annotations);
+        Self { canonical, reverse, hint }
+    }
+}
+
+impl<ID: IrID> BoundLinksInstruction<ID> {
+    pub(crate) fn add_check(&mut self, check: CheckInstruction<ID>) {
+        self.canonical.add_check(check.clone());
+        self.reverse.add_check(check)
+    }
+
+    pub fn map<T: IrID>(self, mapping: &HashMap<ID, T>) -> BoundLinksInstruction<T> {
+        let Self { canonical, reverse, hint } = self;
+        BoundLinksInstruction { canonical: canonical.map(mapping), reverse: reverse.map(mapping), hint }
+    }
+}
+
+impl<ID: IrID> fmt::Display for BoundLinksInstruction<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.hint.prefer_relation_side { "relation" } else { "player" };
+        write!(
+            f,
+            "Bound[{}] ({side}-side preferred) filter {}",
+            &self.canonical.links,
+            DisplayVec::new(&self.canonical.checks)
+        )
+    }
+}
+
 // We use a lowered form of the IndexedRelation, since it is fully symmetric otherwise
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IndexedRelationInstruction<ID> {
     pub player_start: ID,
     pub player_end: ID,
@@ -372,9 +454,13 @@ pub struct IndexedRelationInstruction<ID> {
     pub checks: Vec<CheckInstruction<ID>>,
 
     // the prefixes we will generally want to construct are [rel type][from][to type]
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub relation_to_player_start_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub player_start_to_player_end_types: Arc<BTreeMap<Type, BTreeSet<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub role_start_types: Arc<BTreeSet<RoleType>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     pub role_end_types: Arc<BTreeSet<RoleType>>,
 }
 
@@ -23,8 +23,10 @@ use crate::{
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeListInstruction<ID> {
     pub type_var: ID,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -68,10 +70,13 @@ impl<ID: IrID> fmt::Display for TypeListInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubInstruction<ID> {
     pub sub: Sub<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     sub_to_supertypes: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     supertypes: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -119,10 +124,13 @@ impl<ID: IrID> fmt::Display for SubInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubReverseInstruction<ID> {
     pub sub: Sub<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     super_to_subtypes: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     subtypes: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -170,10 +178,13 @@ impl<ID: IrID> fmt::Display for SubReverseInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct OwnsInstruction<ID> {
     pub owns: Owns<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     owner_attribute_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     attribute_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -221,10 +232,13 @@ impl<ID: IrID> fmt::Display for OwnsInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct OwnsReverseInstruction<ID> {
     pub owns: Owns<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     attribute_owner_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     owner_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -272,10 +286,13 @@ impl<ID: IrID> fmt::Display for OwnsReverseInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct RelatesInstruction<ID> {
     pub relates: Relates<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     relation_role_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     role_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -324,10 +341,13 @@ impl<ID: IrID> fmt::Display for RelatesInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct RelatesReverseInstruction<ID> {
     pub relates: Relates<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     role_relation_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     relation_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -376,10 +396,13 @@ impl<ID: IrID> fmt::Display for RelatesReverseInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlaysInstruction<ID> {
     pub plays: Plays<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     player_role_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     role_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -428,10 +451,13 @@ impl<ID: IrID> fmt::Display for PlaysInstruction<ID> {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlaysReverseInstruction<ID> {
     pub plays: Plays<ID>,
     pub inputs: Inputs<ID>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     role_player_types: Arc<BTreeMap<Type, Vec<Type>>>,
+    #[cfg_attr(feature = "serialization", serde(with = "crate::executable::match_::planner::serialization::shared"))]
     player_types: Arc<BTreeSet<Type>>,
     pub checks: Vec<CheckInstruction<ID>>,
 }
@@ -7,28 +7,54 @@
 use std::{
     collections::{HashMap, HashSet},
     fmt, slice,
+    sync::Arc,
 };
 
 use answer::variable::Variable;
+use encoding::value::{value_type::ValueType, ValueEncodable};
 use error::unimplemented_feature;
-use ir::{pattern::BranchID, pipeline::function_signature::FunctionID};
+use ir::{
+    pattern::{BranchID, ParameterID},
+    pipeline::{function_signature::FunctionID, ParameterRegistry},
+};
+use itertools::Itertools;
+use typeql::common::Span;
 
 use crate::{
     annotation::expression::compiled_expression::ExecutableExpression,
-    executable::match_::{
-        instructions::{CheckInstruction, ConstraintInstruction, VariableModes},
-        planner::plan::PlannerStatistics,
+    executable::{
+        function::FunctionCallCostProvider,
+        match_::{
+            instructions::{pushdown::PushdownCandidate, CheckInstruction, ConstraintInstruction, VariableModes},
+            planner::{explain::PlanExplanation, plan::PlannerStatistics},
+        },
     },
     ExecutorVariable, VariablePosition,
 };
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConjunctionExecutable {
     executable_id: u64,
     pub(crate) steps: Vec<ExecutionStep>,
+    step_labels: Vec<StepLabel>,
     variable_positions: HashMap<Variable, VariablePosition>,
     variable_reverse_map: HashMap<ExecutorVariable, Variable>,
     planner_statistics: PlannerStatistics,
+    explanation: Option<Arc<PlanExplanation>>,
+    row_count_only: bool,
+    grouped_count_only: bool,
+    result_semantics: ResultSemantics,
+    function_versions: HashMap<FunctionID, u64>,
+    parameter_kinds: HashMap<ParameterID, ParameterKind>,
+}
+
+/// What an executable expects of a parameter it references: the executors read the parameter as this kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterKind {
+    Value(ValueType),
+    Iid,
 }
 
 impl ConjunctionExecutable {
@@ -39,7 +65,159 @@ impl ConjunctionExecutable {
         variable_reverse_map: HashMap<ExecutorVariable, Variable>,
         planner_statistics: PlannerStatistics,
     ) -> Self {
-        Self { executable_id, steps, variable_positions, variable_reverse_map, planner_statistics }
+        Self {
+            executable_id,
+            steps,
+            step_labels: Vec::new(),
+            variable_positions,
+            variable_reverse_map,
+            planner_statistics,
+            explanation: None,
+            row_count_only: false,
+            grouped_count_only: false,
+            result_semantics: ResultSemantics::Bag,
+            function_versions: HashMap::new(),
+            parameter_kinds: HashMap::new(),
+        }
+    }
+
+    /// Attaches the label of each step, in the order of the steps.
+    pub fn with_step_labels(self, step_labels: Vec<StepLabel>) -> Self {
+        debug_assert_eq!(step_labels.len(), self.steps.len());
+        Self { step_labels, ..self }
+    }
+
+    pub fn with_explanation(self, explanation: PlanExplanation) -> Self {
+        Self { explanation: Some(Arc::new(explanation)), ..self }
+    }
+
+    /// Marks an executable with no selected variables whose only consumer counts its rows. Such executables emit
+    /// width-0 rows that carry only a multiplicity, and their rows are not deduplicated.
+    pub fn with_row_count_only(self) -> Self {
+        debug_assert!(self.selected_variables().is_empty());
+        Self { row_count_only: true, ..self }
+    }
+
+    /// Marks an executable whose only consumer counts its rows per group of the selected variables. Every other
+    /// variable is counted towards the multiplicity of the rows, so that, for example, the relations of each player
+    /// are counted rather than enumerated, and its rows are not deduplicated.
+    pub fn with_grouped_count_only(self) -> Self {
+        Self { grouped_count_only: true, ..self }
+    }
+
+    pub fn with_result_semantics(self, result_semantics: ResultSemantics) -> Self {
+        Self { result_semantics, ..self }
+    }
+
+    /// Records the versions of the functions called anywhere in this executable, which the executable is only valid
+    /// against.
+    pub(crate) fn with_function_versions(self, call_cost_provider: &impl FunctionCallCostProvider) -> Self {
+        let mut called_functions = HashSet::new();
+        self.collect_called_functions(&mut called_functions);
+        let function_versions = (called_functions.into_iter())
+            .map(|function_id| {
+                let version = call_cost_provider.function_version(&function_id);
+                (function_id, version)
+            })
+            .collect();
+        Self { function_versions, ..self }
+    }
+
+    /// Records the parameters referenced anywhere in this executable, with the kinds they held at compilation, which
+    /// the executable is only valid against.
+    pub(crate) fn with_parameter_kinds(self, parameters: &ParameterRegistry) -> Self {
+        let mut referenced_parameters = HashSet::new();
+        self.referenced_parameters_foreach(&mut |parameter| {
+            referenced_parameters.insert(parameter);
+        });
+        let parameter_kinds = (referenced_parameters.into_iter())
+            .map(|parameter| {
+                let kind = match parameter {
+                    ParameterID::Value(..) => ParameterKind::Value(parameters.value_unchecked(parameter).value_type()),
+                    ParameterID::Iid(..) => ParameterKind::Iid,
+                    ParameterID::FetchKey(..) => unreachable!("Fetch keys are not referenced by match executables"),
+                };
+                (parameter, kind)
+            })
+            .collect();
+        Self { parameter_kinds, ..self }
+    }
+
+    fn referenced_parameters_foreach(&self, apply: &mut impl FnMut(ParameterID)) {
+        for step in &self.steps {
+            match step {
+                ExecutionStep::Intersection(step) => {
+                    step.instructions.iter().for_each(|(instruction, _)| instruction.parameters_foreach(&mut *apply));
+                    step.checks.iter().for_each(|check| check.parameters_foreach(&mut *apply));
+                }
+                ExecutionStep::UnsortedJoin(step) => {
+                    step.iterate_instruction.parameters_foreach(&mut *apply);
+                    step.check_instructions.iter().for_each(|check| check.parameters_foreach(&mut *apply));
+                }
+                ExecutionStep::Assignment(step) => step.expression.constants().iter().copied().for_each(&mut *apply),
+                ExecutionStep::Check(step) => {
+                    step.check_instructions.iter().for_each(|check| check.parameters_foreach(&mut *apply))
+                }
+                ExecutionStep::Disjunction(disjunction) => {
+                    disjunction.branches.iter().for_each(|branch| branch.referenced_parameters_foreach(apply))
+                }
+                ExecutionStep::Negation(negation) => negation.negation.referenced_parameters_foreach(apply),
+                ExecutionStep::Optional(optional) => optional.optional.referenced_parameters_foreach(apply),
+                ExecutionStep::FunctionCall(_) => (),
+            }
+        }
+    }
+
+    fn collect_called_functions(&self, called_functions: &mut HashSet<FunctionID>) {
+        for step in &self.steps {
+            match step {
+                ExecutionStep::FunctionCall(step) => {
+                    called_functions.insert(step.function_id.clone());
+                }
+                ExecutionStep::Disjunction(disjunction) => {
+                    disjunction.branches.iter().for_each(|branch| branch.collect_called_functions(called_functions))
+                }
+                ExecutionStep::Negation(negation) => negation.negation.collect_called_functions(called_functions),
+                ExecutionStep::Optional(optional) => optional.optional.collect_called_functions(called_functions),
+                ExecutionStep::Intersection(_)
+                | ExecutionStep::UnsortedJoin(_)
+                | ExecutionStep::Assignment(_)
+                | ExecutionStep::Check(_) => (),
+            }
+        }
+    }
+
+    /// Lets the last step skip repeated values of its sort variable when a downstream `distinct` over
+    /// `distinct_variables` covers no other variable the step produces: the further rows for the same sort value
+    /// would only be discarded as duplicates.
+    pub fn with_distinct_outputs(mut self, distinct_variables: &HashSet<Variable>) -> Self {
+        let distinct_positions: HashSet<VariablePosition> =
+            distinct_variables.iter().filter_map(|var| self.variable_positions.get(var).copied()).collect();
+        if let Some(ExecutionStep::Intersection(step)) = self.steps.last_mut() {
+            let sort_position = step.sort_variable.as_position();
+            let covers_only_sort_variable = sort_position.is_some_and(|sort_position| {
+                step.new_variables.contains(&sort_position)
+                    && step
+                        .new_variables
+                        .iter()
+                        .all(|&position| position == sort_position || !distinct_positions.contains(&position))
+                    && distinct_positions.contains(&sort_position)
+            });
+            step.dedup_on_sort_variable = covers_only_sort_variable;
+        }
+        self
+    }
+
+    /// Lets the last step stop producing rows for an input row once it has produced `limit` of them, when a
+    /// downstream `limit` would discard the rest anyway. The input row is identified by the values the step carries
+    /// over from it.
+    pub fn with_per_input_row_limit(mut self, limit: u64) -> Self {
+        if let Some(ExecutionStep::Intersection(step)) = self.steps.last_mut() {
+            let input_positions =
+                step.selected_variables.iter().filter(|position| !step.new_variables.contains(position)).copied();
+            step.per_group_limit = Some((input_positions.collect(), limit));
+        }
+        self
     }
 
     pub fn executable_id(&self) -> u64 {
@@ -50,6 +228,27 @@ impl ConjunctionExecutable {
         &self.steps
     }
 
+    /// The checks a storage backend could apply while scanning, as (step index, instruction index, candidate) for
+    /// every instruction of the intersection steps. Nested executables expose their own.
+    pub fn pushdown_candidates(
+        &self,
+    ) -> impl Iterator<Item = (usize, usize, &PushdownCandidate<ExecutorVariable>)> + '_ {
+        self.steps.iter().enumerate().flat_map(|(step_index, step)| {
+            let candidates = match step {
+                ExecutionStep::Intersection(intersection) => intersection.pushdown_candidates.as_slice(),
+                _ => &[],
+            };
+            candidates.iter().enumerate().flat_map(move |(instruction_index, candidates)| {
+                candidates.iter().map(move |candidate| (step_index, instruction_index, candidate))
+            })
+        })
+    }
+
+    /// The label of the step at `index`, if the executable was planned rather than assembled step by step.
+    pub fn step_label(&self, index: usize) -> Option<StepLabel> {
+        self.step_labels.get(index).copied()
+    }
+
     pub fn outputs(&self) -> &[VariablePosition] {
         self.steps.last().unwrap().selected_variables()
     }
@@ -62,10 +261,45 @@ impl ConjunctionExecutable {
         &self.variable_reverse_map
     }
 
+    /// The versions of the functions called by this executable, as of its compilation. The executable must only be
+    /// executed against a function registry holding the same versions.
+    pub fn function_versions(&self) -> &HashMap<FunctionID, u64> {
+        &self.function_versions
+    }
+
+    /// The kinds of the parameters referenced by this executable, as of its compilation. The executable must only be
+    /// executed against a parameter registry holding each of them with the same kind.
+    pub fn parameter_kinds(&self) -> &HashMap<ParameterID, ParameterKind> {
+        &self.parameter_kinds
+    }
+
     pub fn planner_statistics(&self) -> &PlannerStatistics {
         &self.planner_statistics
     }
 
+    /// The planner's account of how this conjunction was planned, if requested via `PlannerConfig::record_explanation`.
+    pub fn explanation(&self) -> Option<&PlanExplanation> {
+        self.explanation.as_deref()
+    }
+
+    pub fn is_row_count_only(&self) -> bool {
+        self.row_count_only
+    }
+
+    pub fn is_grouped_count_only(&self) -> bool {
+        self.grouped_count_only
+    }
+
+    /// Whether a row of this executable may stand for several answers, carrying their number as its multiplicity
+    /// rather than repeating the row: the rows of count-only executables, and of those counting unselected variables.
+    pub fn may_emit_multiplicities(&self) -> bool {
+        self.row_count_only || self.grouped_count_only || self.steps.iter().any(ExecutionStep::may_emit_multiplicities)
+    }
+
+    pub fn result_semantics(&self) -> ResultSemantics {
+        self.result_semantics
+    }
+
     pub fn selected_variables(&self) -> &[VariablePosition] {
         let Some(last) = self.steps().last() else { return &[] };
         last.selected_variables()
@@ -84,7 +318,69 @@ impl fmt::Display for ConjunctionExecutable {
     }
 }
 
+/// Identifies a step by the patterns it executes rather than by its position, so that the profiles of a query can be
+/// compared step by step across plans that order its steps differently. A step is labelled by the kinds and source
+/// spans of its patterns, in no particular order, and the steps of a nested executable also by the label of the step
+/// they are nested in. The label is a stable hash (FNV-1a), so it does not change between releases either.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepLabel(u64);
+
+impl StepLabel {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    /// Labels a step executing patterns of the given kinds at the given source spans, nested in the step labelled
+    /// `parent`, if any.
+    pub fn new<'a>(parent: Option<StepLabel>, patterns: impl IntoIterator<Item = (&'a str, Option<Span>)>) -> Self {
+        let mut patterns = patterns
+            .into_iter()
+            .map(|(kind, span)| (span.map(|span| (span.begin_offset, span.end_offset)), kind))
+            .collect_vec();
+        patterns.sort_unstable();
+        patterns.dedup();
+        let mut hash = Self::FNV_OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ byte as u64).wrapping_mul(Self::FNV_PRIME);
+            }
+        };
+        if let Some(StepLabel(parent)) = parent {
+            write(&parent.to_le_bytes());
+        }
+        for (span, kind) in patterns {
+            if let Some((begin, end)) = span {
+                write(&(begin as u64).to_le_bytes());
+                write(&(end as u64).to_le_bytes());
+            }
+            write(kind.as_bytes());
+            write(&[0]);
+        }
+        StepLabel(hash)
+    }
+}
+
+impl fmt::Display for StepLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// How the rows of an executable are observed by its consumer.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResultSemantics {
+    /// Every answer is produced, with its multiplicity.
+    #[default]
+    Bag,
+    /// Only whether any answer exists is observed, as for the body of a negation. Variables that are not selected
+    /// are checked rather than counted, so the first combination of their values suffices, and executors probe for
+    /// one answer at a time instead of filling batches.
+    Exists,
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExecutionStep {
     Intersection(IntersectionStep),
     UnsortedJoin(UnsortedJoinStep),
@@ -110,6 +406,20 @@ impl ExecutionStep {
         }
     }
 
+    fn may_emit_multiplicities(&self) -> bool {
+        match self {
+            ExecutionStep::Intersection(step) => step.instructions.iter().any(|(_, modes)| modes.any_counted()),
+            ExecutionStep::UnsortedJoin(step) => step.iterate_variable_modes.any_counted(),
+            ExecutionStep::Assignment(_) | ExecutionStep::Check(_) | ExecutionStep::Negation(_) => false,
+            ExecutionStep::Disjunction(step) => {
+                step.branches.iter().any(ConjunctionExecutable::may_emit_multiplicities)
+            }
+            ExecutionStep::Optional(step) => step.optional.may_emit_multiplicities(),
+            // a function may return its rows with any multiplicity
+            ExecutionStep::FunctionCall(_) => true,
+        }
+    }
+
     #[cfg(unused_unimplemented_function)]
     pub fn new_variables(&self) -> &[VariablePosition] {
         match self {
@@ -154,13 +464,25 @@ impl fmt::Display for ExecutionStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntersectionStep {
     pub sort_variable: ExecutorVariable,
+    pub secondary_sort_variable: Option<ExecutorVariable>,
     pub instructions: Vec<(ConstraintInstruction<ExecutorVariable>, VariableModes)>,
+    /// For each instruction, the checks a storage backend could apply while it scans the instruction's tuples.
+    pub pushdown_candidates: Vec<Vec<PushdownCandidate<ExecutorVariable>>>,
     new_variables: Vec<VariablePosition>,
     pub output_width: u32,
     bound_variables: Vec<VariablePosition>,
     pub selected_variables: Vec<VariablePosition>,
+    /// Emit a single row for each value of the sort variable, skipping the rest of its group.
+    pub dedup_on_sort_variable: bool,
+    /// Emit at most the given number of rows for each combination of values at the given input positions,
+    /// skipping the remaining rows of the input rows sharing them.
+    pub per_group_limit: Option<(Vec<VariablePosition>, u64)>,
+    /// Checks spanning several instructions, evaluated on each assembled row before it is emitted.
+    pub checks: Vec<CheckInstruction<ExecutorVariable>>,
+    single_instruction_fast_path: bool,
 }
 
 impl IntersectionStep {
@@ -189,6 +511,7 @@ impl IntersectionStep {
             });
         });
 
+        let pushdown_candidates = instructions.iter().map(ConstraintInstruction::pushdown_candidates).collect();
         let instructions = instructions
             .into_iter()
             .map(|instruction| {
@@ -196,7 +519,61 @@ impl IntersectionStep {
                 (instruction, variable_modes)
             })
             .collect();
-        Self { sort_variable, instructions, new_variables, output_width, bound_variables, selected_variables }
+        Self {
+            sort_variable,
+            secondary_sort_variable: None,
+            instructions,
+            pushdown_candidates,
+            new_variables,
+            output_width,
+            bound_variables,
+            selected_variables,
+            dedup_on_sort_variable: false,
+            per_group_limit: None,
+            checks: Vec::new(),
+            single_instruction_fast_path: true,
+        }
+    }
+
+    /// Joins the instructions on the pair of the sort variable and `secondary_sort_variable`, which every instruction
+    /// must produce directly after the sort variable. Both join variables are enumerated, since counting or checking
+    /// the secondary one would merge distinct join keys.
+    pub fn with_secondary_sort_variable(mut self, secondary_sort_variable: ExecutorVariable) -> Self {
+        for (_, modes) in &mut self.instructions {
+            modes.enumerate(self.sort_variable);
+            modes.enumerate(secondary_sort_variable);
+        }
+        self.secondary_sort_variable = Some(secondary_sort_variable);
+        self
+    }
+
+    /// Filters the assembled rows with checks whose variables are spread over several instructions. The checked
+    /// variables are enumerated, so every combination of their values is checked individually.
+    pub fn with_checks(mut self, checks: Vec<CheckInstruction<ExecutorVariable>>) -> Self {
+        for check in &checks {
+            check.variables_foreach(|var| {
+                for (_, modes) in &mut self.instructions {
+                    modes.enumerate(var);
+                }
+            });
+        }
+        self.checks = checks;
+        self
+    }
+
+    /// Forces the n-way merge even when the step consists of a single instruction.
+    pub fn without_single_instruction_fast_path(mut self) -> Self {
+        self.single_instruction_fast_path = false;
+        self
+    }
+
+    /// Whether the step can stream the tuples of its only instruction directly, since there is nothing to intersect
+    /// them with. Checks and a secondary sort variable keep the step on the general path.
+    pub fn uses_single_instruction_fast_path(&self) -> bool {
+        self.single_instruction_fast_path
+            && self.secondary_sort_variable.is_none()
+            && self.checks.is_empty()
+            && matches!(self.instructions.as_slice(), [(instruction, _)] if instruction.checks().is_empty())
     }
 
     fn new_variables(&self) -> &[VariablePosition] {
@@ -219,12 +596,25 @@ impl fmt::Display for IntersectionStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Sorted Iterator Intersection [bound_vars={:?}, selected={:?}, output_size={}, sort_by={}]",
+            "Sorted Iterator Intersection [bound_vars={:?}, selected={:?}, output_size={}, sort_by={}",
             self.bound_variables, self.selected_variables, self.output_width, self.sort_variable
         )?;
+        if let Some(secondary_sort_variable) = self.secondary_sort_variable {
+            write!(f, ", {secondary_sort_variable}")?;
+        }
+        if self.dedup_on_sort_variable {
+            write!(f, ", distinct")?;
+        }
+        if let Some((group_positions, limit)) = &self.per_group_limit {
+            write!(f, ", limit {limit} per {group_positions:?}")?;
+        }
+        write!(f, "]")?;
         for (instruction, modes) in &self.instructions {
             write!(f, "\n      {instruction} with ({modes})")?;
         }
+        for check in &self.checks {
+            write!(f, "\n      {check}")?;
+        }
         Ok(())
     }
 }
@@ -238,35 +628,48 @@ impl fmt::Display for VarMappedIntersectionStep<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Sorted Iterator Intersection [bound_vars={:?}, output_size={}, sort_by={}]",
+            "Sorted Iterator Intersection [bound_vars={:?}, output_size={}, sort_by={}",
             &self.step.bound_variables.iter().map(|&v| self.map[&ExecutorVariable::RowPosition(v)]).collect::<Vec<_>>(),
             self.step.output_width,
             self.map[&self.step.sort_variable]
         )?;
+        if let Some(secondary_sort_variable) = self.step.secondary_sort_variable {
+            write!(f, ", {}", self.map[&secondary_sort_variable])?;
+        }
+        write!(f, "]")?;
         for (instruction, modes) in &self.step.instructions {
             let var_mapped_instruction = instruction.clone().map(self.map);
             let var_mapped_modes = modes.make_var_mapped(self.map);
             write!(f, "\n      {var_mapped_instruction} with ({var_mapped_modes})")?;
         }
+        for check in &self.step.checks {
+            write!(f, "\n      {}", check.clone().map(self.map))?;
+        }
         Ok(())
     }
 }
 
+/// Iterates a single instruction for each input row, without merging it with any other iterator, and keeps the rows
+/// accepted by the checks.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnsortedJoinStep {
     pub iterate_instruction: ConstraintInstruction<ExecutorVariable>,
-    pub check_instructions: Vec<ConstraintInstruction<ExecutorVariable>>,
+    pub iterate_variable_modes: VariableModes,
+    /// Evaluated on each row produced by the iterate instruction, before it is emitted.
+    pub check_instructions: Vec<CheckInstruction<ExecutorVariable>>,
     new_variables: Vec<VariablePosition>,
     bound_variables: Vec<VariablePosition>,
-    selected_variables: Vec<VariablePosition>,
+    pub selected_variables: Vec<VariablePosition>,
     pub output_width: u32,
 }
 
 impl UnsortedJoinStep {
     pub fn new(
         iterate_instruction: ConstraintInstruction<ExecutorVariable>,
-        check_instructions: Vec<ConstraintInstruction<ExecutorVariable>>,
+        check_instructions: Vec<CheckInstruction<ExecutorVariable>>,
         selected_variables: &[VariablePosition],
+        named_variables: &HashSet<ExecutorVariable>,
         output_width: u32,
     ) -> Self {
         let mut bound_variables = Vec::with_capacity(check_instructions.len() * 2);
@@ -284,16 +687,19 @@ impl UnsortedJoinStep {
                 bound_variables.push(var)
             }
         });
-        check_instructions.iter().for_each(|instruction| {
-            instruction.input_variables_foreach(|var| {
-                let var = var.as_position().unwrap();
-                if !bound_variables.contains(&var) {
-                    bound_variables.push(var)
+        check_instructions.iter().for_each(|check| {
+            check.variables_foreach(|var| {
+                if let Some(var) = var.as_position() {
+                    if !bound_variables.contains(&var) && !new_variables.contains(&var) {
+                        bound_variables.push(var)
+                    }
                 }
             })
         });
+        let iterate_variable_modes = VariableModes::new_for(&iterate_instruction, selected_variables, named_variables);
         Self {
             iterate_instruction,
+            iterate_variable_modes,
             check_instructions,
             new_variables,
             bound_variables,
@@ -325,6 +731,7 @@ impl fmt::Display for UnsortedJoinStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct AssignmentStep {
     pub expression: ExecutableExpression<VariablePosition>,
     pub input_positions: Vec<VariablePosition>,
@@ -369,6 +776,7 @@ impl fmt::Display for AssignmentStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct CheckStep {
     pub check_instructions: Vec<CheckInstruction<ExecutorVariable>>,
     pub selected_variables: Vec<VariablePosition>,
@@ -377,10 +785,12 @@ pub struct CheckStep {
 
 impl CheckStep {
     pub fn new(
-        check_instructions: Vec<CheckInstruction<ExecutorVariable>>,
+        mut check_instructions: Vec<CheckInstruction<ExecutorVariable>>,
         selected_variables: Vec<VariablePosition>,
         output_width: u32,
     ) -> Self {
+        // the checks are conjunctive and evaluated in order, so the cheap ones go first to reject rows early
+        check_instructions.sort_by_key(CheckInstruction::cost_rank);
         Self { check_instructions, selected_variables, output_width }
     }
 
@@ -419,11 +829,13 @@ impl fmt::Display for VarMappedCheckStep<'_> {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisjunctionStep {
     pub branch_ids: Vec<BranchID>,
     pub branches: Vec<ConjunctionExecutable>,
     pub selected_variables: Vec<VariablePosition>,
     pub output_width: u32,
+    pub interleave_branches: bool,
 }
 
 impl DisjunctionStep {
@@ -433,7 +845,14 @@ impl DisjunctionStep {
         selected_variables: Vec<VariablePosition>,
         output_width: u32,
     ) -> Self {
-        Self { branch_ids, branches, selected_variables, output_width }
+        Self { branch_ids, branches, selected_variables, output_width, interleave_branches: false }
+    }
+
+    /// Lets the branches take turns producing batches, instead of emitting the answers of each branch after those of
+    /// the branches before it.
+    pub fn with_interleaved_branches(mut self) -> Self {
+        self.interleave_branches = true;
+        self
     }
 
     pub fn output_width(&self) -> u32 {
@@ -443,7 +862,11 @@ impl DisjunctionStep {
 
 impl fmt::Display for DisjunctionStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Disjunction [selected={:?}, output_size={}]", self.selected_variables, self.output_width)?;
+        write!(
+            f,
+            "Disjunction [selected={:?}, output_size={}, interleaved={}]",
+            self.selected_variables, self.output_width, self.interleave_branches
+        )?;
         for branch in &self.branches {
             write!(f, "\n      --- Start branch ---")?;
             write!(f, "{}", branch)?;
@@ -454,6 +877,7 @@ impl fmt::Display for DisjunctionStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct NegationStep {
     pub negation: ConjunctionExecutable,
     pub selected_variables: Vec<VariablePosition>,
@@ -480,6 +904,7 @@ impl fmt::Display for NegationStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct OptionalStep {
     pub optional: ConjunctionExecutable,
 }
@@ -494,6 +919,7 @@ impl fmt::Display for OptionalStep {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionCallStep {
     // TODO: Deduplication, selection counting etc.
     pub function_id: FunctionID,
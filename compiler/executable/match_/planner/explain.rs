@@ -0,0 +1,117 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The decisions the planner made for one conjunction, in execution order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanExplanation {
+    pub steps: Vec<PlanStepExplanation>,
+    pub notes: Vec<String>,
+    pub cost: f64,
+    pub io_ratio: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanStepExplanation {
+    pub order: usize,
+    pub pattern: String,
+    pub direction: Option<String>,
+    pub join_variable: Option<String>,
+    pub cost: f64,
+    pub io_ratio: f64,
+    pub produced_variables: Vec<String>,
+    #[serde(default)]
+    pub source_ranges: Vec<SourceRange>, // byte offsets into the query text, sorted and disjoint
+    #[serde(default)]
+    pub annotation_sizes: Vec<AnnotationSize>, // the annotation sets and estimates the step was costed from
+    pub nested: Vec<PlanExplanation>, // negation, or one per disjunction branch
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceRange {
+    pub begin: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationSize {
+    pub label: String,
+    pub size: f64,
+}
+
+impl SourceRange {
+    /// Sorts the ranges and merges those that overlap or touch. Given the query text, ranges separated only by
+    /// whitespace or statement separators are merged too, so a statement reads as one range.
+    pub(super) fn coalesce(mut ranges: Vec<SourceRange>, query_text: Option<&str>) -> Vec<SourceRange> {
+        ranges.sort();
+        let mut coalesced: Vec<SourceRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match coalesced.last_mut() {
+                Some(last) if last.end >= range.begin || is_separator(query_text, last.end, range.begin) => {
+                    last.end = usize::max(last.end, range.end)
+                }
+                _ => coalesced.push(range),
+            }
+        }
+        coalesced
+    }
+}
+
+fn is_separator(query_text: Option<&str>, begin: usize, end: usize) -> bool {
+    let Some(gap) = query_text.and_then(|text| text.get(begin..end)) else { return false };
+    gap.chars().all(|char| char.is_whitespace() || char == ',' || char == ';')
+}
+
+impl PlanExplanation {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        writeln!(f, "{:indent$}Plan (cost: {:.2}, io ratio: {:.2})", "", self.cost, self.io_ratio)?;
+        for note in &self.notes {
+            writeln!(f, "{:indent$}  note: {note}", "")?;
+        }
+        for step in &self.steps {
+            write!(f, "{:indent$}  {}: {}", "", step.order, step.pattern)?;
+            if let Some(direction) = &step.direction {
+                write!(f, " [{direction}]")?;
+            }
+            if let Some(join_variable) = &step.join_variable {
+                write!(f, " join on {join_variable}")?;
+            }
+            write!(
+                f,
+                " (cost: {:.2}, io ratio: {:.2}) -> [{}]",
+                step.cost,
+                step.io_ratio,
+                step.produced_variables.join(", ")
+            )?;
+            if !step.source_ranges.is_empty() {
+                let ranges = step.source_ranges.iter().map(|range| format!("{}..{}", range.begin, range.end));
+                write!(f, " @ {}", ranges.collect::<Vec<_>>().join(", "))?;
+            }
+            if !step.annotation_sizes.is_empty() {
+                let sizes = step.annotation_sizes.iter().map(|size| format!("{}: {}", size.label, size.size));
+                write!(f, " sizes: [{}]", sizes.collect::<Vec<_>>().join(", "))?;
+            }
+            writeln!(f)?;
+            for nested in &step.nested {
+                nested.fmt_indented(f, indent + 4)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PlanExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
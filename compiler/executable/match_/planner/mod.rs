@@ -6,18 +6,20 @@
 
 use std::{
     collections::{hash_map, BTreeMap, BTreeSet, HashMap, HashSet},
+    iter::zip,
     sync::Arc,
 };
 
-use answer::variable::Variable;
-use concept::thing::statistics::Statistics;
+use answer::{variable::Variable, Type};
+use concept::thing::statistics::{Statistics, StatisticsFreshness};
 use error::typedb_error;
 use ir::{
     pattern::{constraint::ExpressionBinding, BranchID, Vertex},
-    pipeline::{block::Block, function_signature::FunctionID, VariableRegistry},
+    pipeline::{block::Block, function_signature::FunctionID, ParameterRegistry, VariableRegistry},
 };
 use itertools::Itertools;
 use tracing::{debug, trace};
+use typeql::common::Span;
 
 use crate::{
     annotation::{expression::compiled_expression::ExecutableExpression, type_annotations::BlockAnnotations},
@@ -28,9 +30,11 @@ use crate::{
             planner::{
                 conjunction_executable::{
                     AssignmentStep, CheckStep, ConjunctionExecutable, DisjunctionStep, ExecutionStep, FunctionCallStep,
-                    IntersectionStep, NegationStep,
+                    IntersectionStep, NegationStep, ResultSemantics, StepLabel,
                 },
-                plan::{plan_conjunction, PlannerStatistics, QueryPlanningError},
+                plan::{plan_conjunction, PlannerConfig, PlannerStatistics, QueryPlanningError},
+                plan_cache::PlanCache,
+                vertex::instance_count,
             },
         },
         next_executable_id,
@@ -39,7 +43,11 @@ use crate::{
 };
 
 pub mod conjunction_executable;
+pub mod explain;
 pub mod plan;
+pub mod plan_cache;
+#[cfg(feature = "serialization")]
+pub mod serialization;
 pub(crate) mod vertex;
 
 typedb_error! {
@@ -56,18 +64,32 @@ pub fn compile(
     type_annotations: &BlockAnnotations,
     variable_registry: &VariableRegistry,
     expressions: &HashMap<ExpressionBinding<Variable>, ExecutableExpression<Variable>>,
+    value_parameters: &ParameterRegistry,
     statistics: &Statistics,
     call_cost_provider: &impl FunctionCallCostProvider,
+    planner_config: &PlannerConfig,
+    plan_cache: Option<&PlanCache>,
 ) -> Result<ConjunctionExecutable, MatchCompilationError> {
     let conjunction = block.conjunction();
     let block_context = block.block_context();
 
     debug!("Planning conjunction:\n{conjunction}");
 
+    let estimated_statistics;
+    let statistics = match planner_config.statistics_freshness {
+        StatisticsFreshness::Current => statistics,
+        StatisticsFreshness::Stale => {
+            let mut types = type_annotations.referenced_types();
+            types.extend(input_variable_annotations.values().flat_map(|types| types.iter().copied()));
+            estimated_statistics = estimate_unrecorded_types(statistics, &types);
+            &estimated_statistics
+        }
+    };
+
     let assigned_identities =
         input_variables.iter().map(|(&var, &position)| (var, ExecutorVariable::RowPosition(position))).collect();
 
-    let plan = plan_conjunction(
+    let conjunction_plan = plan_conjunction(
         conjunction,
         block_context,
         input_variables,
@@ -75,20 +97,43 @@ pub fn compile(
         type_annotations,
         variable_registry,
         expressions,
+        value_parameters,
         statistics,
         call_cost_provider,
+        planner_config,
+        plan_cache,
     )
-    .map_err(|source| MatchCompilationError::PlanningError { typedb_source: source })?
-    .lower(
-        input_variable_annotations,
-        input_variables.keys().copied(),
-        selected_variables.iter().copied(),
-        &assigned_identities,
-        variable_registry,
-        None,
-    )
-    .map_err(|source| MatchCompilationError::PlanningError { typedb_source: source })?
-    .finish(variable_registry);
+    .map_err(|source| MatchCompilationError::PlanningError { typedb_source: source })?;
+    trace!("Planner graph:\n{}", conjunction_plan.to_dot());
+
+    let explanation = match planner_config.record_explanation {
+        true => Some(
+            conjunction_plan
+                .explain_with_source(variable_registry, None)
+                .map_err(|source| MatchCompilationError::PlanningError { typedb_source: source })?,
+        ),
+        false => None,
+    };
+
+    let mut plan = conjunction_plan
+        .lower(
+            input_variable_annotations,
+            input_variables.keys().copied(),
+            selected_variables.iter().copied(),
+            &assigned_identities,
+            0,
+            variable_registry,
+            None,
+            ResultSemantics::Bag,
+        )
+        .map_err(|source| MatchCompilationError::PlanningError { typedb_source: source })?
+        .finish(variable_registry, None)
+        .with_function_versions(call_cost_provider)
+        .with_parameter_kinds(value_parameters);
+    if let Some(explanation) = explanation {
+        debug!("Plan explanation:\n{explanation}");
+        plan = plan.with_explanation(explanation);
+    }
 
     trace!("Finished planning conjunction:\n{conjunction}");
     debug!("Lowered plan:\n{plan}");
@@ -96,15 +141,102 @@ pub fn compile(
     Ok(plan)
 }
 
+/// Stale statistics may not record types written to since they were synchronised, which must not be planned as if
+/// they were empty. Estimates each such type to have as many instances as the average recorded type of its kind, and as
+/// many edges to the other given types as it has instances.
+fn estimate_unrecorded_types(statistics: &Statistics, types: &BTreeSet<Type>) -> Statistics {
+    fn mean_count<T>(counts: &HashMap<T, u64>) -> u64 {
+        let (sum, recorded) = counts.values().filter(|&&count| count > 0).fold((0, 0), |(sum, n), &c| (sum + c, n + 1));
+        if recorded == 0 {
+            1
+        } else {
+            u64::max(sum / recorded, 1)
+        }
+    }
+
+    let is_unrecorded = |type_: &Type| !matches!(type_, Type::RoleType(_)) && instance_count(type_, statistics) == 0;
+    if !types.iter().any(is_unrecorded) {
+        return statistics.clone();
+    }
+
+    let mut estimated = statistics.clone();
+    for type_ in types.iter().filter(|type_| is_unrecorded(type_)) {
+        match type_ {
+            Type::Entity(entity) => estimated.update_entities(*entity, mean_count(&statistics.entity_counts) as i64),
+            Type::Relation(relation) => {
+                estimated.update_relations(*relation, mean_count(&statistics.relation_counts) as i64)
+            }
+            Type::Attribute(attribute) => {
+                estimated.update_attributes(*attribute, mean_count(&statistics.attribute_counts) as i64)
+            }
+            Type::RoleType(_) => unreachable!("Cannot count role instances"),
+        }
+    }
+
+    let objects = types.iter().filter(|type_| matches!(type_, Type::Entity(_) | Type::Relation(_))).collect_vec();
+    let attributes = types.iter().filter(|type_| matches!(type_, Type::Attribute(_))).collect_vec();
+    let relations = types.iter().filter(|type_| matches!(type_, Type::Relation(_))).collect_vec();
+    let roles = types.iter().filter(|type_| matches!(type_, Type::RoleType(_))).collect_vec();
+
+    for (&owner, &attribute) in itertools::iproduct!(&objects, &attributes) {
+        let (owner_type, attribute_type) = (owner.as_object_type(), attribute.as_attribute_type());
+        let recorded = statistics.has_attribute_counts.get(&owner_type).and_then(|counts| counts.get(&attribute_type));
+        if (is_unrecorded(owner) || is_unrecorded(attribute)) && recorded.is_none() {
+            let count = instance_count(attribute, &estimated);
+            estimated.update_has(owner_type, attribute_type, count as i64);
+        }
+    }
+
+    for (&relation, &role, &player) in itertools::iproduct!(&relations, &roles, &objects) {
+        let (relation_type, role_type, player_type) =
+            (relation.as_relation_type(), role.as_role_type(), player.as_object_type());
+        let recorded = statistics
+            .relation_role_player_counts
+            .get(&relation_type)
+            .and_then(|counts| counts.get(&role_type))
+            .and_then(|counts| counts.get(&player_type));
+        if (is_unrecorded(relation) || is_unrecorded(player)) && recorded.is_none() {
+            let count = instance_count(relation, &estimated);
+            estimated.update_role_player(player_type, role_type, relation_type, count as i64);
+        }
+    }
+
+    for (i, &player_1) in objects.iter().enumerate() {
+        for &player_2 in &objects[i..] {
+            let (player_1_type, player_2_type) = (player_1.as_object_type(), player_2.as_object_type());
+            let recorded =
+                statistics.links_index_counts.get(&player_1_type).and_then(|counts| counts.get(&player_2_type));
+            if (is_unrecorded(player_1) || is_unrecorded(player_2)) && recorded.is_none() {
+                let count = mean_count(&estimated.relation_counts);
+                estimated.update_indexed_player(player_1_type, player_2_type, count as i64);
+            }
+        }
+    }
+
+    estimated
+}
+
 #[derive(Debug)]
 struct IntersectionBuilder {
     sort_variable: Option<Variable>,
+    secondary_sort_variable: Option<Variable>,
     instructions: Vec<ConstraintInstruction<ExecutorVariable>>,
+    checks: Vec<CheckInstruction<ExecutorVariable>>,
 }
 
 impl IntersectionBuilder {
     fn new() -> Self {
-        Self { sort_variable: None, instructions: Vec::new() }
+        Self { sort_variable: None, secondary_sort_variable: None, instructions: Vec::new(), checks: Vec::new() }
+    }
+
+    /// Whether the instructions together produce some of the variables and have all of them in their rows.
+    fn covers(&self, variables: &[ExecutorVariable]) -> bool {
+        let is_produced = |var: ExecutorVariable| self.instructions.iter().any(|inst| inst.is_new_variable(var));
+        let is_available = |var: ExecutorVariable| {
+            var.as_position().is_some()
+                && self.instructions.iter().any(|inst| inst.is_new_variable(var) || inst.is_input_variable(var))
+        };
+        variables.iter().any(|&var| is_produced(var)) && variables.iter().all(|&var| is_available(var))
     }
 }
 
@@ -134,11 +266,12 @@ impl NegationBuilder {
 struct DisjunctionBuilder {
     branch_ids: Vec<BranchID>,
     branches: Vec<MatchExecutableBuilder>,
+    interleave_branches: bool,
 }
 
 impl DisjunctionBuilder {
-    fn new(branch_ids: Vec<BranchID>, branches: Vec<MatchExecutableBuilder>) -> Self {
-        Self { branch_ids, branches }
+    fn new(branch_ids: Vec<BranchID>, branches: Vec<MatchExecutableBuilder>, interleave_branches: bool) -> Self {
+        Self { branch_ids, branches, interleave_branches }
     }
 }
 
@@ -195,22 +328,32 @@ impl StepInstructionsBuilder {
 
 impl From<StepInstructionsBuilder> for StepBuilder {
     fn from(instructions_builder: StepInstructionsBuilder) -> Self {
-        StepBuilder { selected_variables: Vec::new(), builder: instructions_builder }
+        StepBuilder { selected_variables: Vec::new(), builder: instructions_builder, patterns: Vec::new() }
     }
 }
 
+/// The kind and source span of a pattern lowered into a step, from which the step is labelled.
+type LoweredPattern = (&'static str, Option<Span>);
+
 #[derive(Debug)]
 struct StepBuilder {
     selected_variables: Vec<Variable>,
     builder: StepInstructionsBuilder,
+    patterns: Vec<LoweredPattern>,
 }
 
 impl StepBuilder {
+    fn label(&self, parent: Option<StepLabel>) -> StepLabel {
+        StepLabel::new(parent, self.patterns.iter().copied())
+    }
+
     fn finish(
         self,
         index: &HashMap<Variable, ExecutorVariable>,
         named_variables: &HashSet<ExecutorVariable>,
         variable_registry: &VariableRegistry,
+        single_instruction_fast_path: bool,
+        label: StepLabel,
     ) -> ExecutionStep {
         let selected_variables = self
             .selected_variables
@@ -220,15 +363,35 @@ impl StepBuilder {
         let output_width = selected_variables.iter().map(|position| position.as_usize() as u32 + 1).max().unwrap_or(0);
 
         match self.builder {
-            StepInstructionsBuilder::Intersection(IntersectionBuilder { sort_variable, instructions }) => {
+            StepInstructionsBuilder::Intersection(IntersectionBuilder {
+                sort_variable,
+                secondary_sort_variable,
+                instructions,
+                checks,
+            }) => {
                 let sort_variable = index[&sort_variable.unwrap()];
-                ExecutionStep::Intersection(IntersectionStep::new(
+                debug_assert!(
+                    instructions.len() < 2
+                        || instructions.iter().all(|instruction| instruction.is_new_variable(sort_variable)),
+                    "every instruction of a joining intersection must produce its sort variable"
+                );
+                let mut step = IntersectionStep::new(
                     sort_variable,
                     instructions,
                     selected_variables,
                     named_variables,
                     output_width,
-                ))
+                )
+                .with_checks(checks);
+                if !single_instruction_fast_path {
+                    step = step.without_single_instruction_fast_path();
+                }
+                match secondary_sort_variable {
+                    Some(secondary_sort_variable) => {
+                        ExecutionStep::Intersection(step.with_secondary_sort_variable(index[&secondary_sort_variable]))
+                    }
+                    None => ExecutionStep::Intersection(step),
+                }
             }
 
             StepInstructionsBuilder::Check(CheckBuilder { instructions }) => {
@@ -247,15 +410,19 @@ impl StepBuilder {
             }
 
             StepInstructionsBuilder::Negation(NegationBuilder { negation }) => ExecutionStep::Negation(
-                NegationStep::new(negation.finish(variable_registry), selected_variables, output_width),
+                NegationStep::new(negation.finish(variable_registry, Some(label)), selected_variables, output_width),
             ),
-            StepInstructionsBuilder::Disjunction(DisjunctionBuilder { branch_ids, branches }) => {
-                ExecutionStep::Disjunction(DisjunctionStep::new(
+            StepInstructionsBuilder::Disjunction(DisjunctionBuilder { branch_ids, branches, interleave_branches }) => {
+                let step = DisjunctionStep::new(
                     branch_ids,
-                    branches.into_iter().map(|builder| builder.finish(variable_registry)).collect(),
+                    branches.into_iter().map(|builder| builder.finish(variable_registry, Some(label))).collect(),
                     selected_variables,
                     output_width,
-                ))
+                );
+                match interleave_branches {
+                    true => ExecutionStep::Disjunction(step.with_interleaved_branches()),
+                    false => ExecutionStep::Disjunction(step),
+                }
             }
 
             StepInstructionsBuilder::FunctionCall(FunctionCallBuilder {
@@ -291,15 +458,22 @@ struct MatchExecutableBuilder {
 
     planner_statistics: PlannerStatistics,
     branch_id: Option<BranchID>,
+    result_semantics: ResultSemantics,
+    single_instruction_fast_path: bool,
+    counted_variables: HashSet<Variable>,
+    lowered_patterns: Vec<LoweredPattern>,
 }
 
 impl MatchExecutableBuilder {
     fn new(
         branch_id: Option<BranchID>,
         assigned_positions: &HashMap<Variable, ExecutorVariable>,
+        reserved_positions: u32,
         selected_variables: Vec<Variable>,
         input_variables: Vec<Variable>,
         planner_statistics: PlannerStatistics,
+        result_semantics: ResultSemantics,
+        single_instruction_fast_path: bool,
     ) -> Self {
         let index = assigned_positions.clone();
         let produced_so_far = HashSet::from_iter(input_variables.iter().copied());
@@ -311,7 +485,7 @@ impl MatchExecutableBuilder {
             .max()
             .map(|pos| pos.position + 1)
             .unwrap_or(0);
-        let next_output = VariablePosition::new(next_position);
+        let next_output = VariablePosition::new(u32::max(next_position, reserved_positions));
         Self {
             branch_id,
             selected_variables,
@@ -324,9 +498,32 @@ impl MatchExecutableBuilder {
             index,
             next_output,
             planner_statistics,
+            result_semantics,
+            single_instruction_fast_path,
+            counted_variables: HashSet::new(),
+            lowered_patterns: Vec::new(),
         }
     }
 
+    /// Sets the pattern being lowered, which labels the steps its instructions, checks and steps are added to.
+    fn set_lowered_pattern(&mut self, kind: &'static str, source_spans: Vec<Span>) {
+        self.lowered_patterns = match source_spans.is_empty() {
+            true => vec![(kind, None)],
+            false => source_spans.into_iter().map(|span| (kind, Some(span))).collect(),
+        };
+    }
+
+    fn record_lowered_pattern(&mut self) {
+        if let Some(current) = self.current.as_mut() {
+            current.patterns.extend(self.lowered_patterns.iter().copied());
+        }
+    }
+
+    /// Counts the answers of an unnamed variable towards the multiplicity of rows, as for named variables.
+    fn count_multiplicity_of(&mut self, variable: Variable) {
+        self.counted_variables.insert(variable);
+    }
+
     fn push_instruction(&mut self, sort_variable: Variable, instruction: ConstraintInstruction<Variable>) {
         if let Some(StepBuilder { builder: StepInstructionsBuilder::Intersection(intersection_builder), .. }) =
             self.current.as_deref()
@@ -346,8 +543,10 @@ impl MatchExecutableBuilder {
             self.current = Some(Box::new(StepBuilder {
                 selected_variables: Vec::from_iter(self.current_outputs.iter().copied()),
                 builder: StepInstructionsBuilder::Intersection(IntersectionBuilder::new()),
+                patterns: Vec::new(),
             }));
         }
+        self.record_lowered_pattern();
 
         instruction.new_variables_foreach(|variable| {
             self.produced_so_far.insert(variable);
@@ -358,8 +557,14 @@ impl MatchExecutableBuilder {
         current.instructions.push(instruction.map(&self.index));
     }
 
+    fn set_secondary_sort_variable(&mut self, secondary_sort_variable: Variable) {
+        let current = self.current.as_mut().unwrap().builder.as_intersection_mut().unwrap();
+        debug_assert!(current.instructions.len() > 1);
+        current.secondary_sort_variable = Some(secondary_sort_variable);
+    }
+
     fn push_check(&mut self, variables: &[Variable], check: CheckInstruction<ExecutorVariable>) {
-        // if it is a comparison or IID (TODO) we can inline the check into previous instructions
+        // if it is a comparison, is or IID (TODO) we can inline the check into previous instructions
         if self.inline_as_optimisation(variables, &check) {
             return;
         }
@@ -372,15 +577,23 @@ impl MatchExecutableBuilder {
             self.current = Some(Box::new(StepBuilder {
                 selected_variables: Vec::from_iter(self.current_outputs.iter().copied()),
                 builder: StepInstructionsBuilder::Check(CheckBuilder::default()),
+                patterns: Vec::new(),
             }))
         }
+        self.record_lowered_pattern();
         let current = self.current.as_mut().unwrap().builder.as_check_mut().unwrap();
-        current.instructions.push(check);
+        // a constraint may be lowered to a check more than once, e.g. also to enforce the annotations of an input
+        if !current.instructions.contains(&check) {
+            current.instructions.push(check);
+        }
     }
 
     /// inject the check as an optimisation into previously built steps
     fn inline_as_optimisation(&mut self, variables: &[Variable], check: &CheckInstruction<ExecutorVariable>) -> bool {
-        if !matches!(check, CheckInstruction::Comparison { .. } | CheckInstruction::Iid { .. }) {
+        if !matches!(
+            check,
+            CheckInstruction::Comparison { .. } | CheckInstruction::Is { .. } | CheckInstruction::Iid { .. }
+        ) {
             // TODO: inject IID check as well
             return false;
         }
@@ -409,6 +622,19 @@ impl MatchExecutableBuilder {
                 }
             }
         }
+        if !inlined {
+            // no single instruction sees all the variables, but the rows assembled by an intersection may
+            let variables = variables.iter().map(|var| self.index[var]).collect_vec();
+            for (i, step) in self.steps.iter_mut().chain(self.current.as_mut().map(|box_| box_.as_mut())).enumerate() {
+                if let StepInstructionsBuilder::Intersection(intersection) = &mut step.builder {
+                    if intersection.covers(&variables) {
+                        intersection.checks.push(check.clone());
+                        inlined = true;
+                        added_to_current |= i == steps_count;
+                    }
+                }
+            }
+        }
         if added_to_current {
             self.current.as_mut().unwrap().selected_variables = Vec::from_iter(self.current_outputs.iter().copied());
         }
@@ -422,10 +648,14 @@ impl MatchExecutableBuilder {
                 self.index.insert(var, pos);
                 self.reverse_index.insert(pos, var);
                 self.next_output.position += 1;
+                if let Some(position) = pos.as_position() {
+                    self.next_output.position = u32::max(self.next_output.position, position.position + 1);
+                }
             }
         }
         self.produced_so_far.extend(self.current_outputs.iter().copied());
         step.selected_variables = Vec::from_iter(self.current_outputs.iter().copied());
+        step.patterns.extend(self.lowered_patterns.iter().copied());
 
         self.steps.push(step);
     }
@@ -477,17 +707,30 @@ impl MatchExecutableBuilder {
         }
     }
 
-    fn finish(mut self, variable_registry: &VariableRegistry) -> ConjunctionExecutable {
+    /// Finishes the steps into an executable, labelling them as nested in the step labelled `parent`, if any.
+    fn finish(mut self, variable_registry: &VariableRegistry, parent: Option<StepLabel>) -> ConjunctionExecutable {
         self.finish_one();
-        let named_variables = self
-            .index
-            .iter()
-            .filter_map(|(var, &pos)| variable_registry.variable_names().get(var).and(Some(pos)))
-            .collect();
-        let steps = self
-            .steps
-            .into_iter()
-            .map(|builder| builder.finish(&self.index, &named_variables, variable_registry))
+        // named variables are counted to preserve multiplicities, which are not observed under exists semantics
+        let named_variables = match self.result_semantics {
+            ResultSemantics::Bag => (self.index.iter())
+                .filter(|(var, _)| {
+                    variable_registry.variable_names().contains_key(var) || self.counted_variables.contains(var)
+                })
+                .map(|(_, &pos)| pos)
+                .collect(),
+            ResultSemantics::Exists => HashSet::new(),
+        };
+        let step_labels = self.steps.iter().map(|builder| builder.label(parent)).collect_vec();
+        let steps = zip(self.steps, &step_labels)
+            .map(|(builder, &label)| {
+                builder.finish(
+                    &self.index,
+                    &named_variables,
+                    variable_registry,
+                    self.single_instruction_fast_path,
+                    label,
+                )
+            })
             .collect();
         ConjunctionExecutable::new(
             next_executable_id(),
@@ -496,5 +739,63 @@ impl MatchExecutableBuilder {
             self.reverse_index,
             self.planner_statistics,
         )
+        .with_step_labels(step_labels)
+        .with_result_semantics(self.result_semantics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap},
+        sync::Arc,
+    };
+
+    use answer::{variable::Variable, Type};
+    use concept::type_::attribute_type::AttributeType;
+    use encoding::graph::type_::vertex::{PrefixedTypeVertexEncoding, TypeID};
+    use ir::translation::PipelineTranslationContext;
+
+    use super::MatchExecutableBuilder;
+    use crate::{
+        executable::match_::{
+            instructions::CheckInstruction,
+            planner::{
+                conjunction_executable::{ExecutionStep, ResultSemantics},
+                plan::PlannerStatistics,
+            },
+        },
+        ExecutorVariable,
+    };
+
+    #[test]
+    fn identical_checks_are_lowered_into_a_step_once() {
+        let variable = Variable::new(0);
+        let position = ExecutorVariable::new_position(0);
+        let mut builder = MatchExecutableBuilder::new(
+            None,
+            &HashMap::from([(variable, position)]),
+            0,
+            vec![variable],
+            vec![variable],
+            PlannerStatistics::new(),
+            ResultSemantics::Bag,
+            false,
+        );
+        let [name, age] = [0, 1].map(|id| Type::Attribute(AttributeType::build_from_type_id(TypeID::new(id))));
+        let is_name = CheckInstruction::ThingTypeList { thing_var: position, types: Arc::new(BTreeSet::from([name])) };
+        let is_age = CheckInstruction::ThingTypeList { thing_var: position, types: Arc::new(BTreeSet::from([age])) };
+
+        // e.g. a constraint checked for itself and again for the annotations of its input
+        builder.push_check(&[variable], is_name.clone());
+        builder.push_check(&[variable], is_age.clone());
+        builder.push_check(&[variable], is_name.clone());
+
+        let translation_context = PipelineTranslationContext::new();
+        let executable = builder.finish(&translation_context.variable_registry, None);
+        let [ExecutionStep::Check(step)] = executable.steps() else {
+            panic!("expected a single check step, got {:?}", executable.steps());
+        };
+        assert_eq!(step.check_instructions, vec![is_name, is_age]);
     }
 }
@@ -10,11 +10,17 @@ use std::{
     collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
     fmt,
     hash::{DefaultHasher, Hash, Hasher},
-    sync::Arc,
+    iter::{self, zip},
+    mem,
+    num::NonZeroUsize,
+    panic,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
 use answer::variable::Variable;
-use concept::thing::statistics::Statistics;
+use concept::thing::statistics::{Statistics, StatisticsFreshness};
 use error::{typedb_error, unimplemented_feature};
 use ir::{
     pattern::{
@@ -23,14 +29,18 @@ use ir::{
             Comparator, Comparison, Constraint, ExpressionBinding, FunctionCallBinding, Has, Iid, IndexedRelation, Is,
             Isa, Kind, Label, Links, LinksDeduplication, Owns, Plays, Relates, RoleName, Sub, Unsatisfiable, Value,
         },
+        disjunction::Disjunction,
+        enclosing_span,
         nested_pattern::NestedPattern,
         variable_category::VariableCategory,
-        BranchID, Scope, Vertex,
+        BranchID, Scope, ScopeId, Vertex,
     },
-    pipeline::{block::BlockContext, VariableRegistry},
+    pipeline::{block::BlockContext, function_signature::FunctionID, ParameterRegistry, VariableRegistry},
 };
 use itertools::{chain, Itertools};
+use structural_equality::StructuralEquality;
 use tracing::{event, Level};
+use typeql::common::Span;
 
 use crate::{
     annotation::{
@@ -38,29 +48,38 @@ use crate::{
         type_annotations::{BlockAnnotations, TypeAnnotations},
     },
     executable::{
-        function::FunctionCallCostProvider,
+        function::{all_calls_in_conjunction, FunctionCallCostProvider},
         match_::{
             instructions::{
                 thing::{
-                    HasInstruction, HasReverseInstruction, IidInstruction, IndexedRelationInstruction, IsaInstruction,
-                    IsaReverseInstruction, LinksInstruction, LinksReverseInstruction,
+                    BoundLinksInstruction, HasInstruction, HasReverseInstruction, IidInstruction,
+                    IndexedRelationInstruction, IsaInstruction, IsaReverseInstruction, LinksInstruction,
+                    LinksReverseInstruction,
                 },
                 type_::{
                     OwnsInstruction, OwnsReverseInstruction, PlaysInstruction, PlaysReverseInstruction,
                     RelatesInstruction, RelatesReverseInstruction, SubInstruction, SubReverseInstruction,
                 },
-                CheckInstruction, CheckVertex, ConstraintInstruction, Inputs, IsInstruction,
+                CheckInstruction, CheckVertex, ConstraintInstruction, Inputs, IsInstruction, StringMatcher,
             },
             planner::{
+                conjunction_executable::ResultSemantics,
+                explain::{AnnotationSize, PlanExplanation, PlanStepExplanation, SourceRange},
+                plan_cache::{
+                    CachedPlan, CachedVertex, DisjunctionMemoKey, DisjunctionPlanMemo, PlanCache, PlanCacheKey,
+                },
                 vertex::{
                     constraint::{
-                        ConstraintVertex, HasPlanner, IidPlanner, IndexedRelationPlanner, IsaPlanner, LinksPlanner,
-                        OwnsPlanner, PlaysPlanner, RelatesPlanner, SubPlanner, TypeListPlanner,
+                        ConstraintVertex, HasPlanner, IidPlanner, IndexedRelationPlanner, IsaPlanner, JoinContract,
+                        LinksPlanner, OwnsPlanner, PlaysPlanner, RelatesPlanner, SubPlanner, TypeListPlanner,
+                    },
+                    variable::{
+                        EqualityClass, InputPlanner, ListPlanner, ThingPlanner, TypePlanner, ValuePlanner,
+                        VariableVertex,
                     },
-                    variable::{InputPlanner, ThingPlanner, TypePlanner, ValuePlanner, VariableVertex},
                     ComparisonPlanner, Cost, CostMetaData, Costed, Direction, DisjunctionPlanner, ExpressionPlanner,
                     FunctionCallPlanner, Input, IsPlanner, LinksDeduplicationPlanner, NegationPlanner, PlannerVertex,
-                    UnsatisfiablePlanner,
+                    UnsatisfiablePlanner, SEEK_ITERATOR_RELATIVE_COST,
                 },
                 DisjunctionBuilder, ExpressionBuilder, FunctionCallBuilder, IntersectionBuilder,
                 MatchExecutableBuilder, NegationBuilder, StepBuilder, StepInstructionsBuilder,
@@ -72,13 +91,197 @@ use crate::{
 
 pub const MAX_BEAM_WIDTH: usize = 96;
 pub const MIN_BEAM_WIDTH: usize = 1;
-pub const AVERAGE_QUERY_OUTPUT_SIZE: f64 = 1.0; // replace with actual statistical estimate
-pub const AVERAGE_STEP_COST: f64 = 1.0; // replace with actual heuristic
+pub const DEFAULT_ASTAR_MAX_PATTERNS: usize = 8;
+pub const DEFAULT_ASTAR_NODE_BUDGET: usize = 4096;
+pub const DEFAULT_PARALLEL_EXTENSION_MIN_PATTERNS: usize = 12;
+pub const PARALLEL_BRANCH_PLANNING_MIN_BRANCHES: usize = 8; // fewer branches are planned on the calling thread
+pub const WIDE_DISJUNCTION_BRANCHES: usize = 32; // disjunctions with more branches plan each with a narrower beam
+pub const AVERAGE_QUERY_OUTPUT_SIZE: f64 = 1.0; // used when the conjunction registers no statistics to derive it from
+pub const AVERAGE_STEP_COST: f64 = 1.0; // used when the conjunction registers no statistics to derive it from
 pub const VARIABLE_PRODUCTION_ADVANTAGE: f64 = 0.05; // this is a percentage 0.00 <= x < 1.00
+pub const SUSPICIOUS_TYPE_SET_SIZE: usize = 16; // larger type sets of an instance variable are noted when planning
+
+/// Limits on the effort spent by the beam search planner. Applies to the planned conjunction and, recursively,
+/// to all nested disjunction and negation plans.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct PlannerConfig {
+    /// Upper bound on the number of partial plans kept after each planning step. A width of 1 plans greedily.
+    pub max_beam_width: usize,
+    /// Upper bound on the number of extensions of each partial plan considered in each planning step.
+    pub max_extension_width: usize,
+    /// Conjunctions with at most this many patterns are planned with a best-first (A*) search, which finds the
+    /// cheapest ordering the beam search could find, instead of the beam. A value of 0 always uses the beam.
+    pub astar_max_patterns: usize,
+    /// Number of partial plans the best-first search may expand before falling back to the beam search.
+    pub astar_node_budget: usize,
+    /// Whether to attach a structured explanation of the chosen plan to the compiled executable.
+    pub record_explanation: bool,
+    /// Time allowed for the beam search of each conjunction. Once exceeded, the remaining patterns are planned
+    /// greedily instead of aborting the search.
+    pub time_budget: Option<Duration>,
+    /// The semantics negation bodies are lowered with. Since a negation only observes whether its body has an
+    /// answer, `Exists` allows skipping work that only enumerates or counts further answers.
+    pub negation_semantics: ResultSemantics,
+    /// Whether intersections of a single instruction without checks stream its tuples directly, bypassing the n-way
+    /// merge. Disabling it is only useful to compare the two paths.
+    pub single_instruction_fast_path: bool,
+    /// Conjunctions with at least this many patterns extend the partial plans in the beam in parallel. Smaller
+    /// conjunctions are planned on the calling thread, where spawning threads would cost more than it saves.
+    pub parallel_extension_min_patterns: usize,
+    /// Whether comparisons, `is` and deduplication checks are moved to directly after the step binding the last of
+    /// their variables. Disabling it is only useful to compare the two orderings.
+    pub hoist_checks: bool,
+    /// Whether the branches of disjunctions take turns producing batches, so a branch with many answers does not
+    /// delay the first answers of the others. By default, each branch emits its answers only once the previous
+    /// branches are exhausted.
+    pub interleave_branches: bool,
+    /// Whether the statistics account for all the data visible to the planned query. When they are stale, types
+    /// without recorded instances are estimated from the recorded ones instead of planned as if they were empty.
+    pub statistics_freshness: StatisticsFreshness,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            max_beam_width: MAX_BEAM_WIDTH,
+            max_extension_width: usize::MAX,
+            astar_max_patterns: DEFAULT_ASTAR_MAX_PATTERNS,
+            astar_node_budget: DEFAULT_ASTAR_NODE_BUDGET,
+            record_explanation: false,
+            time_budget: None,
+            negation_semantics: ResultSemantics::Exists,
+            single_instruction_fast_path: true,
+            parallel_extension_min_patterns: DEFAULT_PARALLEL_EXTENSION_MIN_PATTERNS,
+            hoist_checks: true,
+            interleave_branches: false,
+            statistics_freshness: StatisticsFreshness::Current,
+        }
+    }
+}
 
 typedb_error! {
     pub QueryPlanningError(component = "Query Planner", prefix = "QPL") {
-        ExpectedPlannableConjunction(1, "Planning failed as no valid pattern ordering was found by the query planner (this is a bug!)"),
+        ExpectedPlannableConjunction(
+            1,
+            "Planning failed as no valid pattern ordering was found for the conjunction in scope {scope} (disjunction branch: {branch_id:?}) after {completed_iterations} planning iterations, with the patterns [{unplanned_patterns}] left unplanned (this is a bug!)",
+            scope: ScopeId,
+            branch_id: Option<BranchID>,
+            unplanned_patterns: String,
+            completed_iterations: usize,
+        ),
+        ExpressionInputNotInScope(
+            2,
+            "The expression input variable '{variable}' is not bound in the scope of the expression.",
+            variable: String,
+            source_span: Option<Span>,
+        ),
+        MissingAnnotations(
+            3,
+            "Planning failed as the conjunction in scope {scope} (disjunction branch: {branch_id:?}) has no type annotations (this is a bug!)",
+            scope: ScopeId,
+            branch_id: Option<BranchID>,
+            source_span: Option<Span>,
+        ),
+        MissingConstraintAnnotations(
+            4,
+            "Planning failed as the constraint '{constraint}' has no type annotations (this is a bug!)",
+            constraint: String,
+            source_span: Option<Span>,
+        ),
+        DisjunctionBranchPlanningFailed(
+            5,
+            "Planning failed for the disjunction branch {branch_id:?} in scope {scope}, with the inputs [{inputs}].",
+            scope: ScopeId,
+            branch_id: BranchID,
+            inputs: String,
+            typedb_source: Box<QueryPlanningError>,
+        ),
+        UnresolvableDependencies(
+            6,
+            "Planning failed as the patterns [{unplanned_patterns}] in scope {scope} (disjunction branch: {branch_id:?}) depend on the variables {variables:?}, which no other pattern of the conjunction binds.",
+            scope: ScopeId,
+            branch_id: Option<BranchID>,
+            unplanned_patterns: String,
+            variables: Vec<String>,
+            source_span: Option<Span>,
+        ),
+        InvalidComparisonRegex(
+            7,
+            "The regex '{regex}' of a 'like' comparison failed to compile.",
+            regex: String,
+            source: regex::Error,
+            source_span: Option<Span>,
+        ),
+    }
+}
+
+/// How a planning error should be handled: user errors are reported back as-is, resource limits may succeed when
+/// retried with a larger budget, and internal errors indicate a bug in the planner or in the earlier compiler stages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlanningErrorClassification {
+    UserError,
+    ResourceLimit,
+    Internal,
+}
+
+impl QueryPlanningError {
+    pub fn classification(&self) -> PlanningErrorClassification {
+        match self {
+            Self::ExpressionInputNotInScope { .. }
+            | Self::UnresolvableDependencies { .. }
+            | Self::InvalidComparisonRegex { .. } => PlanningErrorClassification::UserError,
+            Self::ExpectedPlannableConjunction { .. }
+            | Self::MissingAnnotations { .. }
+            | Self::MissingConstraintAnnotations { .. } => PlanningErrorClassification::Internal,
+            Self::DisjunctionBranchPlanningFailed { typedb_source, .. } => typedb_source.classification(),
+        }
+    }
+
+    /// The scope of the conjunction that failed to plan, if the error is attributed to one.
+    pub fn scope(&self) -> Option<ScopeId> {
+        match self {
+            Self::ExpectedPlannableConjunction { scope, .. }
+            | Self::MissingAnnotations { scope, .. }
+            | Self::DisjunctionBranchPlanningFailed { scope, .. }
+            | Self::UnresolvableDependencies { scope, .. } => Some(*scope),
+            Self::ExpressionInputNotInScope { .. }
+            | Self::MissingConstraintAnnotations { .. }
+            | Self::InvalidComparisonRegex { .. } => None,
+        }
+    }
+
+    /// The disjunction branch that failed to plan, if the error is attributed to one.
+    pub fn branch_id(&self) -> Option<BranchID> {
+        match self {
+            Self::ExpectedPlannableConjunction { branch_id, .. }
+            | Self::MissingAnnotations { branch_id, .. }
+            | Self::UnresolvableDependencies { branch_id, .. } => *branch_id,
+            Self::DisjunctionBranchPlanningFailed { branch_id, .. } => Some(*branch_id),
+            Self::ExpressionInputNotInScope { .. }
+            | Self::MissingConstraintAnnotations { .. }
+            | Self::InvalidComparisonRegex { .. } => None,
+        }
+    }
+
+    /// The names of the variables the error is about, in the order they are reported.
+    pub fn variables(&self) -> &[String] {
+        match self {
+            Self::ExpressionInputNotInScope { variable, .. } => std::slice::from_ref(variable),
+            Self::UnresolvableDependencies { variables, .. } => variables,
+            Self::DisjunctionBranchPlanningFailed { typedb_source, .. } => typedb_source.variables(),
+            Self::ExpectedPlannableConjunction { .. }
+            | Self::MissingAnnotations { .. }
+            | Self::MissingConstraintAnnotations { .. }
+            | Self::InvalidComparisonRegex { .. } => &[],
+        }
+    }
+
+    /// The error planning failed with at the innermost disjunction branch.
+    pub fn root_cause(&self) -> &QueryPlanningError {
+        match self {
+            Self::DisjunctionBranchPlanningFailed { typedb_source, .. } => typedb_source.root_cause(),
+            _ => self,
+        }
     }
 }
 
@@ -90,33 +293,55 @@ pub(crate) fn plan_conjunction<'a>(
     type_annotations: &'a BlockAnnotations,
     variable_registry: &VariableRegistry,
     expressions: &'a HashMap<ExpressionBinding<Variable>, ExecutableExpression<Variable>>,
+    value_parameters: &ParameterRegistry,
     statistics: &'a Statistics,
     call_cost_provider: &'a impl FunctionCallCostProvider,
+    config: &PlannerConfig,
+    plan_cache: Option<&PlanCache>,
 ) -> Result<ConjunctionPlan<'a>, QueryPlanningError> {
-    make_builder(
+    let disjunction_memo = Arc::new(DisjunctionPlanMemo::default());
+    let planning_buffers = Arc::new(PlanningBuffers::default());
+    let mut plan = make_builder(
         conjunction,
+        None,
         block_context,
         variable_positions,
         shared_variables,
         type_annotations,
         variable_registry,
         expressions,
+        value_parameters,
         statistics,
         call_cost_provider,
+        config,
+        plan_cache,
+        &disjunction_memo,
+        &planning_buffers,
     )?
-    .plan()
+    .plan_with_cache(conjunction, plan_cache)?;
+    let (hits, misses) = disjunction_memo.lookups();
+    plan.planner_statistics.set_disjunction_memo_lookups(hits, misses);
+    let (allocated, retained_bytes) = planning_buffers.usage();
+    plan.planner_statistics.set_planning_buffer_usage(allocated, retained_bytes);
+    Ok(plan)
 }
 
 fn make_builder<'a>(
     conjunction: &'a Conjunction,
+    branch_id: Option<BranchID>,
     block_context: &BlockContext,
     variable_positions: &HashMap<Variable, VariablePosition>,
     shared_variables: &HashSet<Variable>,
     block_annotations: &'a BlockAnnotations,
     variable_registry: &VariableRegistry,
     expressions: &'a HashMap<ExpressionBinding<Variable>, ExecutableExpression<Variable>>,
+    value_parameters: &ParameterRegistry,
     statistics: &'a Statistics,
     call_cost_provider: &impl FunctionCallCostProvider,
+    config: &PlannerConfig,
+    plan_cache: Option<&PlanCache>,
+    disjunction_memo: &Arc<DisjunctionPlanMemo>,
+    planning_buffers: &Arc<PlanningBuffers>,
 ) -> Result<ConjunctionPlanBuilder<'a>, QueryPlanningError> {
     let mut negation_subplans = Vec::new();
     let mut disjunction_planners = Vec::new();
@@ -124,25 +349,33 @@ fn make_builder<'a>(
         match pattern {
             NestedPattern::Disjunction(disjunction) => {
                 let planner = DisjunctionPlanBuilder::new(
+                    disjunction,
+                    disjunction_memo.clone(),
+                    variable_registry,
                     disjunction.conjunctions_by_branch_id().map(|(id, _)| *id).collect(),
                     disjunction
-                        .conjunctions()
-                        .iter()
-                        .map(|branch| {
+                        .conjunctions_by_branch_id()
+                        .map(|(&branch_id, branch)| {
                             let branch_shared_variables = branch
                                 .referenced_variables()
                                 .filter(|var| block_context.is_variable_available(conjunction.scope_id(), *var))
                                 .collect();
                             make_builder(
                                 branch,
+                                Some(branch_id),
                                 block_context,
                                 variable_positions,
                                 &branch_shared_variables,
                                 block_annotations,
                                 variable_registry,
                                 expressions,
+                                value_parameters,
                                 statistics,
                                 call_cost_provider,
+                                config,
+                                plan_cache,
+                                disjunction_memo,
+                                planning_buffers,
                             )
                         })
                         .collect::<Result<Vec<_>, _>>()?,
@@ -155,32 +388,49 @@ fn make_builder<'a>(
                 shared_variables.extend(negation.required_inputs(block_context));
                 shared_variables =
                     shared_variables.intersection(&negation.referenced_variables().collect()).copied().collect();
-                negation_subplans.push(
-                    make_builder(
-                        negation.conjunction(),
-                        block_context,
-                        variable_positions,
-                        &shared_variables,
-                        block_annotations,
-                        variable_registry,
-                        expressions,
-                        statistics,
-                        call_cost_provider,
-                    )?
-                    .with_inputs(negation.required_inputs(block_context))
-                    .plan()?,
-                )
+                let negation_builder = make_builder(
+                    negation.conjunction(),
+                    None,
+                    block_context,
+                    variable_positions,
+                    &shared_variables,
+                    block_annotations,
+                    variable_registry,
+                    expressions,
+                    value_parameters,
+                    statistics,
+                    call_cost_provider,
+                    config,
+                    plan_cache,
+                    disjunction_memo,
+                    planning_buffers,
+                )?
+                .with_inputs(negation.required_inputs(block_context), |var| {
+                    variable_registry.get_variable_category(var)
+                });
+                debug_assert!(negation_builder.plans_as_inputs(&negation.required_inputs(block_context).collect_vec()));
+                negation_subplans.push(negation_builder.plan_with_cache(negation.conjunction(), plan_cache)?)
             }
             NestedPattern::Optional(_) => unimplemented_feature!(Optionals),
         }
     }
 
-    let conjunction_annotations = block_annotations.type_annotations_of(conjunction).unwrap();
+    let conjunction_annotations =
+        block_annotations.type_annotations_of(conjunction).ok_or_else(|| QueryPlanningError::MissingAnnotations {
+            scope: conjunction.scope_id(),
+            branch_id,
+            source_span: conjunction.constraints().iter().find_map(Constraint::source_span),
+        })?;
     let mut plan_builder = ConjunctionPlanBuilder::new(
+        conjunction.scope_id(),
+        branch_id,
         conjunction.required_inputs(block_context).collect(),
         conjunction_annotations,
         statistics,
-    );
+        *config,
+    )
+    .with_planning_buffers(planning_buffers.clone())
+    .with_called_function_versions(conjunction, call_cost_provider);
 
     plan_builder.register_variables(
         variable_positions.keys().copied(),
@@ -188,7 +438,13 @@ fn make_builder<'a>(
         conjunction.local_variables(block_context),
         variable_registry,
     );
-    plan_builder.register_constraints(conjunction, expressions, call_cost_provider);
+    plan_builder.register_constraints(
+        conjunction,
+        expressions,
+        value_parameters,
+        call_cost_provider,
+        variable_registry,
+    )?;
     plan_builder.register_negations(negation_subplans);
     plan_builder.register_disjunctions(disjunction_planners);
 
@@ -198,6 +454,13 @@ fn make_builder<'a>(
 #[derive(Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct VariableVertexId(usize);
 
+#[cfg(test)]
+impl VariableVertexId {
+    pub(crate) fn new(id: usize) -> Self {
+        Self(id)
+    }
+}
+
 impl fmt::Debug for VariableVertexId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "V({})", self.0)
@@ -259,12 +522,21 @@ impl VertexId {
 
 #[derive(Clone)]
 pub(super) struct ConjunctionPlanBuilder<'a> {
+    scope: ScopeId,
+    branch_id: Option<BranchID>,
     shared_variables: Vec<Variable>,
     required_inputs: Vec<Variable>,
     graph: Graph<'a>,
     local_annotations: &'a TypeAnnotations,
     statistics: &'a Statistics,
     planner_statistics: PlannerStatistics,
+    config: PlannerConfig,
+    planning_notes: Vec<String>,
+    anonymous_relations: HashSet<Variable>,
+    equalities: Vec<(VariableVertexId, VariableVertexId)>, // pairs of variables related by `==` or `is`
+    planning_buffers: Arc<PlanningBuffers>,
+    variable_names: HashMap<Variable, String>, // the names of the named variables, for error messages
+    called_function_versions: BTreeMap<FunctionID, u64>, // of the calls in the conjunction and its nested patterns
 }
 
 impl fmt::Debug for ConjunctionPlanBuilder<'_> {
@@ -277,17 +549,56 @@ impl fmt::Debug for ConjunctionPlanBuilder<'_> {
 }
 
 impl<'a> ConjunctionPlanBuilder<'a> {
-    fn new(required_inputs: Vec<Variable>, local_annotations: &'a TypeAnnotations, statistics: &'a Statistics) -> Self {
+    fn new(
+        scope: ScopeId,
+        branch_id: Option<BranchID>,
+        required_inputs: Vec<Variable>,
+        local_annotations: &'a TypeAnnotations,
+        statistics: &'a Statistics,
+        config: PlannerConfig,
+    ) -> Self {
         Self {
+            scope,
+            branch_id,
             shared_variables: Vec::new(),
             graph: Graph::default(),
             local_annotations,
             statistics,
             planner_statistics: PlannerStatistics::new(),
             required_inputs,
+            config,
+            planning_notes: Vec::new(),
+            anonymous_relations: HashSet::new(),
+            equalities: Vec::new(),
+            planning_buffers: Arc::default(),
+            variable_names: HashMap::new(),
+            called_function_versions: BTreeMap::new(),
         }
     }
 
+    fn with_planning_buffers(mut self, planning_buffers: Arc<PlanningBuffers>) -> Self {
+        self.planning_buffers = planning_buffers;
+        self
+    }
+
+    // The costs of the calls depend on the definitions of the called functions, so plans are only reused by
+    // conjunctions calling the same versions.
+    fn with_called_function_versions(
+        mut self,
+        conjunction: &Conjunction,
+        call_cost_provider: &impl FunctionCallCostProvider,
+    ) -> Self {
+        let mut called_functions = HashSet::new();
+        all_calls_in_conjunction(conjunction, &mut called_functions);
+        self.called_function_versions = (called_functions.into_iter())
+            .map(|function_id| {
+                let version = call_cost_provider.function_version(&function_id);
+                (function_id, version)
+            })
+            .collect();
+        self
+    }
+
     pub(super) fn shared_variables(&self) -> &[Variable] {
         &self.shared_variables
     }
@@ -304,18 +615,38 @@ impl<'a> ConjunctionPlanBuilder<'a> {
             .filter(|&v| self.graph.elements[&VertexId::Variable(v)].as_variable().is_some_and(|v| v.is_input()))
     }
 
-    pub(super) fn with_inputs(mut self, input_variables: impl Iterator<Item = Variable>) -> Self {
+    pub(super) fn with_inputs(
+        mut self,
+        input_variables: impl Iterator<Item = Variable>,
+        variable_category: impl Fn(Variable) -> Option<VariableCategory>,
+    ) -> Self {
         for var in input_variables {
             if let Some(&id) = self.graph.variable_index.get(&var) {
                 self.graph.elements.insert(
                     VertexId::Variable(id),
                     PlannerVertex::Variable(VariableVertex::Input(InputPlanner::from_variable(var))),
                 );
+            } else {
+                // an input only used inside nested patterns has no vertex yet, but must still be planned as bound
+                match variable_category(var) {
+                    None | Some(VariableCategory::AttributeOrValue) => {
+                        unreachable!("Insufficiently bound input variable should have been flagged earlier")
+                    }
+                    Some(_) => self.register_input_var(var),
+                }
             }
         }
         self
     }
 
+    fn plans_as_inputs<'v>(&self, variables: impl IntoIterator<Item = &'v Variable>) -> bool {
+        variables.into_iter().all(|var| {
+            self.graph.variable_index.get(var).is_some_and(|id| {
+                self.graph.elements[&VertexId::Variable(*id)].as_variable().is_some_and(|vertex| vertex.is_input())
+            })
+        })
+    }
+
     fn register_variables(
         &mut self,
         input_variables: impl Iterator<Item = Variable>,
@@ -325,11 +656,12 @@ impl<'a> ConjunctionPlanBuilder<'a> {
     ) {
         self.shared_variables.reserve(input_variables.size_hint().0 + shared_variables.size_hint().0);
 
-        for variable in input_variables {
+        // Registered in a canonical order, as vertex ids are used to break ties between equally costed plans
+        for variable in input_variables.sorted() {
             self.register_input_var(variable);
         }
 
-        for variable in shared_variables {
+        for variable in shared_variables.sorted() {
             if self.graph.variable_index.contains_key(&variable) {
                 continue;
             }
@@ -350,14 +682,14 @@ impl<'a> ConjunctionPlanBuilder<'a> {
                 | VariableCategory::ObjectList
                 | VariableCategory::ThingList
                 | VariableCategory::AttributeList
-                | VariableCategory::ValueList => unimplemented_feature!(Lists),
+                | VariableCategory::ValueList => self.register_list_var(variable),
                 VariableCategory::AttributeOrValue => {
                     unreachable!("Insufficiently bound variable should have been flagged earlier")
                 }
             }
         }
 
-        for variable in local_variables {
+        for variable in local_variables.sorted() {
             if self.graph.variable_index.contains_key(&variable) {
                 continue;
             }
@@ -377,12 +709,19 @@ impl<'a> ConjunctionPlanBuilder<'a> {
                 | VariableCategory::ObjectList
                 | VariableCategory::ThingList
                 | VariableCategory::AttributeList
-                | VariableCategory::ValueList => unimplemented_feature!(Lists),
+                | VariableCategory::ValueList => self.register_list_var(variable),
                 VariableCategory::AttributeOrValue => {
                     unreachable!("Insufficiently bound variable would have been flagged earlier")
                 }
             }
         }
+
+        let variable_names = self
+            .graph
+            .variable_index
+            .keys()
+            .filter_map(|&variable| Some((variable, variable_registry.get_variable_name(variable)?.clone())));
+        self.variable_names.extend(variable_names);
     }
 
     fn register_input_var(&mut self, variable: Variable) {
@@ -407,13 +746,27 @@ impl<'a> ConjunctionPlanBuilder<'a> {
         self.graph.push_variable(variable, VariableVertex::Value(planner));
     }
 
+    fn register_list_var(&mut self, variable: Variable) {
+        let planner = ListPlanner::from_variable(variable);
+        self.graph.push_variable(variable, VariableVertex::List(planner));
+    }
+
     fn register_constraints(
         &mut self,
         conjunction: &'a Conjunction,
         expressions: &'a HashMap<ExpressionBinding<Variable>, ExecutableExpression<Variable>>,
+        value_parameters: &ParameterRegistry,
         call_cost_provider: &impl FunctionCallCostProvider,
-    ) {
+        variable_registry: &VariableRegistry,
+    ) -> Result<(), QueryPlanningError> {
+        self.anonymous_relations = self.unplanned_anonymous_relations(conjunction);
         for constraint in conjunction.constraints() {
+            if self.is_implied_by_type_annotations(constraint, conjunction) {
+                event!(Level::DEBUG, "Not planning constraint implied by type annotations: {constraint}");
+                self.planning_notes
+                    .push(format!("'{constraint}' is implied by the inferred types and was not planned"));
+                continue;
+            }
             match constraint {
                 Constraint::Kind(kind) => self.register_kind(kind),
                 Constraint::RoleName(role_name) => self.register_role_name(role_name),
@@ -431,44 +784,137 @@ impl<'a> ConjunctionPlanBuilder<'a> {
                 Constraint::Links(links) => self.register_links(links),
                 Constraint::IndexedRelation(indexed_relation) => self.register_indexed_relation(indexed_relation),
 
-                Constraint::ExpressionBinding(binding) => self.register_expression_binding(binding, expressions),
+                Constraint::ExpressionBinding(binding) => {
+                    self.register_expression_binding(binding, expressions, variable_registry)?
+                }
                 Constraint::FunctionCallBinding(call) => self.register_function_call_binding(call, call_cost_provider),
 
                 Constraint::Is(is) => self.register_is(is),
-                Constraint::Comparison(comparison) => self.register_comparison(comparison),
+                Constraint::Comparison(comparison) => self.register_comparison(comparison, value_parameters)?,
                 Constraint::LinksDeduplication(dedup) => self.register_links_deduplication(dedup),
                 Constraint::Unsatisfiable(optimised_unsatisfiable) => {
                     self.register_optimised_to_unsatisfiable(optimised_unsatisfiable)
                 }
             }
         }
+        Ok(())
+    }
+
+    // An `isa` to a label or a `label` constraint is redundant when type inference already restricts its variable
+    // to the implied types, and another constraint in the conjunction produces the variable.
+    fn is_implied_by_type_annotations(&self, constraint: &Constraint<Variable>, conjunction: &Conjunction) -> bool {
+        fn produces_thing(constraint: &Constraint<Variable>) -> bool {
+            matches!(constraint, Constraint::Has(_) | Constraint::Links(_) | Constraint::IndexedRelation(_))
+        }
+        fn produces_type(constraint: &Constraint<Variable>) -> bool {
+            matches!(
+                constraint,
+                Constraint::Sub(_)
+                    | Constraint::Owns(_)
+                    | Constraint::Relates(_)
+                    | Constraint::Plays(_)
+                    | Constraint::Isa(_)
+                    | Constraint::Links(_)
+            )
+        }
+
+        let (variable, is_producer): (_, fn(&Constraint<Variable>) -> bool) = match constraint {
+            Constraint::Isa(isa) => {
+                let (&Vertex::Variable(thing), Vertex::Label(_)) = (isa.thing(), isa.type_()) else { return false };
+                let (Some(thing_types), Some(isa_types)) = (
+                    self.local_annotations.vertex_annotations_of(isa.thing()),
+                    self.local_annotations.vertex_annotations_of(isa.type_()),
+                ) else {
+                    return false;
+                };
+                if !thing_types.iter().all(|type_| isa_types.contains(type_)) {
+                    return false;
+                }
+                (thing, produces_thing)
+            }
+            Constraint::Label(label) => {
+                let &Vertex::Variable(type_var) = label.type_() else { return false };
+                let (Some(var_types), Some(label_types)) = (
+                    self.local_annotations.vertex_annotations_of(label.type_()),
+                    self.local_annotations.vertex_annotations_of(label.type_label()),
+                ) else {
+                    return false;
+                };
+                if var_types.len() != 1 || var_types != label_types {
+                    return false;
+                }
+                (type_var, produces_type)
+            }
+            _ => return false,
+        };
+        let is_input = self
+            .graph
+            .variable_index
+            .get(&variable)
+            .is_some_and(|id| self.graph.elements[&VertexId::Variable(*id)].as_variable().unwrap().is_input());
+        !is_input
+            && conjunction.constraints().iter().any(|other| {
+                !std::ptr::eq(other, constraint) && is_producer(other) && other.produced_ids().any(|id| id == variable)
+            })
+    }
+
+    // The anonymous relations that are never planned as a variable of their own: their `isa` is implied by the type
+    // annotations, so they are only ever produced by their one `links` or indexed relation, and never joined on.
+    fn unplanned_anonymous_relations(&self, conjunction: &Conjunction) -> HashSet<Variable> {
+        let mut relations = conjunction.single_use_anonymous_relations();
+        relations.retain(|relation| {
+            let is_input = self.graph.variable_index.get(relation).is_some_and(|id| {
+                self.graph.elements[&VertexId::Variable(*id)].as_variable().is_some_and(|var| var.is_input())
+            });
+            let is_typed_by_planned_isa = conjunction.constraints().iter().any(|constraint| {
+                matches!(constraint, Constraint::Isa(isa) if isa.thing().as_variable() == Some(*relation))
+                    && !self.is_implied_by_type_annotations(constraint, conjunction)
+            });
+            !is_input && !is_typed_by_planned_isa && !self.shared_variables.contains(relation)
+        });
+        relations
+    }
+
+    fn push_constraint(&mut self, constraint: ConstraintVertex<'a>) {
+        // type lists legitimately range over many types, e.g. every subtype of a kind
+        let annotation_sizes = match &constraint {
+            ConstraintVertex::TypeList(_) => None,
+            constraint => constraint.annotation_sizes(),
+        };
+        if let Some(annotation_sizes) = annotation_sizes {
+            if annotation_sizes.type_sets().any(|(_, size)| size > SUSPICIOUS_TYPE_SET_SIZE) {
+                let note = format!("'{constraint}' was costed over more than {SUSPICIOUS_TYPE_SET_SIZE} types");
+                self.planning_notes.push(format!("{note} of a variable ({annotation_sizes})"));
+            }
+        }
+        self.graph.push_constraint(constraint);
     }
 
     fn register_label(&mut self, label: &'a Label<Variable>) {
         let planner = TypeListPlanner::from_label_constraint(label, &self.graph.variable_index, self.local_annotations);
-        self.graph.push_constraint(ConstraintVertex::TypeList(planner));
+        self.push_constraint(ConstraintVertex::TypeList(planner));
     }
 
     fn register_role_name(&mut self, role_name: &'a RoleName<Variable>) {
         let planner =
             TypeListPlanner::from_role_name_constraint(role_name, &self.graph.variable_index, self.local_annotations);
-        self.graph.push_constraint(ConstraintVertex::TypeList(planner));
+        self.push_constraint(ConstraintVertex::TypeList(planner));
     }
 
     fn register_kind(&mut self, kind: &'a Kind<Variable>) {
         let planner = TypeListPlanner::from_kind_constraint(kind, &self.graph.variable_index, self.local_annotations);
-        self.graph.push_constraint(ConstraintVertex::TypeList(planner));
+        self.push_constraint(ConstraintVertex::TypeList(planner));
     }
 
     fn register_sub(&mut self, sub: &'a Sub<Variable>) {
         let planner = SubPlanner::from_constraint(sub, &self.graph.variable_index, self.local_annotations);
-        self.graph.push_constraint(ConstraintVertex::Sub(planner));
+        self.push_constraint(ConstraintVertex::Sub(planner));
     }
 
     fn register_owns(&mut self, owns: &'a Owns<Variable>) {
         let planner =
             OwnsPlanner::from_constraint(owns, &self.graph.variable_index, self.local_annotations, self.statistics);
-        self.graph.push_constraint(ConstraintVertex::Owns(planner));
+        self.push_constraint(ConstraintVertex::Owns(planner));
     }
 
     fn register_relates(&mut self, relates: &'a Relates<Variable>) {
@@ -478,46 +924,49 @@ impl<'a> ConjunctionPlanBuilder<'a> {
             self.local_annotations,
             self.statistics,
         );
-        self.graph.push_constraint(ConstraintVertex::Relates(planner));
+        self.push_constraint(ConstraintVertex::Relates(planner));
     }
 
     fn register_plays(&mut self, plays: &'a Plays<Variable>) {
         let planner =
             PlaysPlanner::from_constraint(plays, &self.graph.variable_index, self.local_annotations, self.statistics);
-        self.graph.push_constraint(ConstraintVertex::Plays(planner));
+        self.push_constraint(ConstraintVertex::Plays(planner));
     }
 
     fn register_value(&mut self, value: &'a Value<Variable>) {
         let planner = TypeListPlanner::from_value_constraint(value, &self.graph.variable_index, self.local_annotations);
-        self.graph.push_constraint(ConstraintVertex::TypeList(planner));
+        self.push_constraint(ConstraintVertex::TypeList(planner));
     }
 
     fn register_isa(&mut self, isa: &'a Isa<Variable>) {
         let planner =
             IsaPlanner::from_constraint(isa, &self.graph.variable_index, self.local_annotations, self.statistics);
-        self.graph.push_constraint(ConstraintVertex::Isa(planner));
+        self.push_constraint(ConstraintVertex::Isa(planner));
     }
 
     fn register_iid(&mut self, iid: &'a Iid<Variable>) {
         let planner =
             IidPlanner::from_constraint(iid, &self.graph.variable_index, self.local_annotations, self.statistics);
-        // TODO not setting exact bound for the var here as the checker can't currently take advantage of that
-        //      so the cost would be misleading the planner
-        self.graph.push_constraint(ConstraintVertex::Iid(planner));
+        let var = self.graph.variable_index[&iid.var().as_variable().unwrap()];
+        self.graph.elements.get_mut(&VertexId::Variable(var)).unwrap().as_variable_mut().unwrap().set_exact_bound();
+        self.push_constraint(ConstraintVertex::Iid(planner));
     }
 
     fn register_has(&mut self, has: &'a Has<Variable>) {
         let planner =
             HasPlanner::from_constraint(has, &self.graph.variable_index, self.local_annotations, self.statistics);
-        self.planner_statistics.increment_has(planner.unbound_typed_expected_size);
-        self.graph.push_constraint(ConstraintVertex::Has(planner));
+        self.planner_statistics.increment_has(planner.ownerships.edge_count);
+        self.push_constraint(ConstraintVertex::Has(planner));
     }
 
     fn register_links(&mut self, links: &'a Links<Variable>) {
-        let planner =
+        let mut planner =
             LinksPlanner::from_constraint(links, &self.graph.variable_index, self.local_annotations, self.statistics);
-        self.planner_statistics.increment_links(planner.unbound_typed_expected_size);
-        self.graph.push_constraint(ConstraintVertex::Links(planner));
+        if links.relation().as_variable().is_some_and(|relation| self.anonymous_relations.contains(&relation)) {
+            planner = planner.with_anonymous_relation();
+        }
+        self.planner_statistics.increment_links(planner.role_players.edge_count);
+        self.push_constraint(ConstraintVertex::Links(planner));
     }
 
     fn register_indexed_relation(&mut self, indexed_relation: &'a IndexedRelation<Variable>) {
@@ -527,19 +976,44 @@ impl<'a> ConjunctionPlanBuilder<'a> {
             self.local_annotations,
             self.statistics,
         );
-        self.graph.push_constraint(ConstraintVertex::IndexedRelation(planner))
+        self.push_constraint(ConstraintVertex::IndexedRelation(planner))
     }
 
     fn register_expression_binding(
         &mut self,
         binding: &ExpressionBinding<Variable>,
         expressions: &'a HashMap<ExpressionBinding<Variable>, ExecutableExpression<Variable>>,
-    ) {
+        variable_registry: &VariableRegistry,
+    ) -> Result<(), QueryPlanningError> {
         let variable = binding.left().as_variable().unwrap();
         let output = self.graph.variable_index[&variable];
         let expression = &expressions[binding];
-        let inputs = expression.variables().iter().map(|&var| self.graph.variable_index[&var]).unique().collect_vec();
-        self.graph.push_expression(output, ExpressionPlanner::from_expression(expression, inputs, output));
+        let inputs = (expression.variables().iter())
+            .map(|var| {
+                self.graph.variable_index.get(var).copied().ok_or_else(|| {
+                    let variable = variable_registry.get_variable_name(*var).cloned().unwrap_or(String::new());
+                    QueryPlanningError::ExpressionInputNotInScope { variable, source_span: binding.source_span() }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs = inputs.into_iter().unique().collect_vec();
+        let output_vertex = self.graph.elements[&VertexId::Variable(output)].as_variable().unwrap();
+        if output_vertex.expected_list_length().is_some() {
+            // indexing into input lists yields at most their length, while constructed lists are assumed to be short
+            let expected_length = (inputs.iter())
+                .filter_map(|input| {
+                    self.graph.elements[&VertexId::Variable(*input)].as_variable()?.expected_list_length()
+                })
+                .reduce(f64::max)
+                .unwrap_or(ListPlanner::DEFAULT_EXPECTED_LENGTH);
+            let output_vertex = self.graph.elements.get_mut(&VertexId::Variable(output)).unwrap();
+            output_vertex.as_variable_mut().unwrap().set_expected_list_length(expected_length);
+        }
+        self.graph.push_expression(
+            output,
+            ExpressionPlanner::from_expression(expression, inputs, output, binding.source_span()),
+        );
+        Ok(())
     }
 
     fn register_function_call_binding(
@@ -547,9 +1021,9 @@ impl<'a> ConjunctionPlanBuilder<'a> {
         call_binding: &'a FunctionCallBinding<Variable>,
         call_cost_provider: &impl FunctionCallCostProvider,
     ) {
-        let arguments =
+        let arguments: Vec<_> =
             call_binding.function_call().argument_ids().map(|variable| self.graph.variable_index[&variable]).collect();
-        let return_vars = call_binding
+        let return_vars: Vec<_> = call_binding
             .assigned()
             .iter()
             .map(|vertex| {
@@ -557,8 +1031,18 @@ impl<'a> ConjunctionPlanBuilder<'a> {
                 self.graph.variable_index[variable]
             })
             .collect();
-        // TODO: Use the real cost when we have function planning
-        let cost = call_cost_provider.get_call_cost(&call_binding.function_call().function_id());
+        let function_id = call_binding.function_call().function_id();
+        let mut cost = call_cost_provider.get_call_cost(&function_id);
+        if call_binding.returns_single_row() {
+            // the returned values are fully determined by the arguments, as with an expression output
+            cost = cost.single_row();
+            for &return_var in &return_vars {
+                let return_vertex = self.graph.elements.get_mut(&VertexId::Variable(return_var)).unwrap();
+                for &argument in &arguments {
+                    return_vertex.as_variable_mut().unwrap().add_exact(argument);
+                }
+            }
+        }
         self.graph.push_function_call(FunctionCallPlanner::from_constraint(call_binding, arguments, return_vars, cost));
     }
 
@@ -567,6 +1051,7 @@ impl<'a> ConjunctionPlanBuilder<'a> {
         let rhs = self.graph.variable_index[&is.rhs().as_variable().unwrap()];
         self.graph.elements.get_mut(&VertexId::Variable(lhs)).unwrap().as_variable_mut().unwrap().add_is(rhs);
         self.graph.elements.get_mut(&VertexId::Variable(rhs)).unwrap().as_variable_mut().unwrap().add_is(lhs);
+        self.equalities.push((lhs, rhs));
         self.graph.push_is(IsPlanner::from_constraint(
             is,
             &self.graph.variable_index,
@@ -584,37 +1069,81 @@ impl<'a> ConjunctionPlanBuilder<'a> {
         ));
     }
 
-    fn register_comparison(&mut self, comparison: &'a Comparison<Variable>) {
+    fn register_comparison(
+        &mut self,
+        comparison: &'a Comparison<Variable>,
+        value_parameters: &ParameterRegistry,
+    ) -> Result<(), QueryPlanningError> {
         let lhs = Input::from_vertex(comparison.lhs(), &self.graph.variable_index);
         let rhs = Input::from_vertex(comparison.rhs(), &self.graph.variable_index);
+        if let (Input::Variable(lhs), Input::Variable(rhs), Comparator::Equal) = (lhs, rhs, comparison.comparator()) {
+            self.equalities.push((lhs, rhs));
+        }
         if let Input::Variable(lhs) = lhs {
             let lhs = self.graph.elements.get_mut(&VertexId::Variable(lhs)).unwrap().as_variable_mut().unwrap();
+            let constant = constant_numeric_value(comparison.rhs(), value_parameters);
             match comparison.comparator() {
                 Comparator::Equal => lhs.add_equal(rhs),
                 Comparator::NotEqual => (), // no tangible impact on traversal costs
-                Comparator::Less | Comparator::LessOrEqual => lhs.add_upper_bound(rhs),
-                Comparator::Greater | Comparator::GreaterOrEqual => lhs.add_lower_bound(rhs),
+                Comparator::Less | Comparator::LessOrEqual => {
+                    lhs.add_upper_bound(rhs);
+                    if let Some(value) = constant {
+                        lhs.add_constant_upper_bound(value);
+                    }
+                }
+                Comparator::Greater | Comparator::GreaterOrEqual => {
+                    lhs.add_lower_bound(rhs);
+                    if let Some(value) = constant {
+                        lhs.add_constant_lower_bound(value);
+                    }
+                }
                 Comparator::Like => (),
                 Comparator::Contains => (),
             }
         }
         if let Input::Variable(rhs) = rhs {
             let rhs = self.graph.elements.get_mut(&VertexId::Variable(rhs)).unwrap().as_variable_mut().unwrap();
+            let constant = constant_numeric_value(comparison.lhs(), value_parameters);
+            // the comparator is read from the left, so it bounds the right hand side from the opposite direction
             match comparison.comparator() {
                 Comparator::Equal => rhs.add_equal(lhs),
                 Comparator::NotEqual => (), // no tangible impact on traversal costs
-                Comparator::Less | Comparator::LessOrEqual => rhs.add_upper_bound(lhs),
-                Comparator::Greater | Comparator::GreaterOrEqual => rhs.add_lower_bound(lhs),
+                Comparator::Less | Comparator::LessOrEqual => {
+                    rhs.add_lower_bound(lhs);
+                    if let Some(value) = constant {
+                        rhs.add_constant_lower_bound(value);
+                    }
+                }
+                Comparator::Greater | Comparator::GreaterOrEqual => {
+                    rhs.add_upper_bound(lhs);
+                    if let Some(value) = constant {
+                        rhs.add_constant_upper_bound(value);
+                    }
+                }
                 Comparator::Like => (),
                 Comparator::Contains => (),
             }
         }
+        let matcher = match comparison.rhs().as_parameter().and_then(|rhs| value_parameters.value(rhs)) {
+            Some(encoding::value::value::Value::String(pattern)) => {
+                StringMatcher::prepare(comparison.comparator(), pattern).map_err(|source| {
+                    QueryPlanningError::InvalidComparisonRegex {
+                        regex: pattern.to_string(),
+                        source,
+                        source_span: comparison.source_span(),
+                    }
+                })?
+            }
+            _ => None,
+        };
         self.graph.push_comparison(ComparisonPlanner::from_constraint(
             comparison,
+            matcher,
             &self.graph.variable_index,
             self.local_annotations,
             self.statistics,
         ));
+        Ok(())
     }
 
     fn register_optimised_to_unsatisfiable(&mut self, optimised_unsatisfiable: &'a Unsatisfiable) {
@@ -649,175 +1178,897 @@ impl<'a> ConjunctionPlanBuilder<'a> {
     // We record directionality information for each pattern in the plan, indicating which prefix index to use for pattern retrieval
 
     fn beam_search_plan(
-        &self,
-    ) -> Result<(Vec<VertexId>, HashMap<PatternVertexId, CostMetaData>, Cost), QueryPlanningError> {
+        &mut self,
+        search_patterns: HashSet<PatternVertexId>,
+        deadline: Option<Instant>,
+    ) -> Result<CompleteCostPlan, QueryPlanningError> {
         const INDENT: &str = "";
 
-        let search_patterns: HashSet<_> = self.graph.pattern_to_variable.keys().copied().collect();
         let num_patterns = search_patterns.len();
 
         const BEAM_REDUCTION_CYCLE: usize = 2;
         const EXTENSION_REDUCTION_CYCLE: usize = 2;
-        let mut beam_width = (num_patterns * 2).clamp(2, MAX_BEAM_WIDTH);
-        let mut extension_width = (num_patterns / 2) + 5; // ensure this is larger than (num_patterns / 2) or change narrowing logic (note, join options means patterns may appear twice as extensions)
+        let max_beam_width = usize::max(self.config.max_beam_width, MIN_BEAM_WIDTH);
+        let max_extension_width = usize::max(self.config.max_extension_width, 1);
+        let min_beam_width = usize::min(2, max_beam_width);
+        let min_extension_width = usize::min(2, max_extension_width);
+        let mut beam_width = usize::min(usize::max(num_patterns * 2, 2), max_beam_width);
+        let mut extension_width = usize::min((num_patterns / 2) + 5, max_extension_width); // ensure this is larger than (num_patterns / 2) or change narrowing logic (note, join options means patterns may appear twice as extensions)
 
         let mut best_partial_plans = Vec::with_capacity(beam_width);
         best_partial_plans.push(PartialCostPlan::new(
             self.graph.elements.len(),
-            search_patterns.clone(),
+            search_patterns,
             self.input_variables(),
+            self.planner_statistics.completion_estimate(),
         ));
 
-        let mut extension_heap = BinaryHeap::with_capacity(extension_width); // reused
-        let mut new_plans_heap = BinaryHeap::with_capacity(beam_width);
-        let mut new_plans_hashset = HashSet::with_capacity(beam_width);
+        let mut buffers = self.planning_buffers.take(beam_width, extension_width); // reused across iterations
+        let mut is_degraded = false;
         for i in 0..num_patterns {
             event!(Level::TRACE, "{INDENT:4}PLANNER STEP {}", i);
 
-            // TODO: Do we need this?
-            if i % BEAM_REDUCTION_CYCLE == 0 {
-                beam_width = usize::max(beam_width.saturating_sub(1), 2);
+            if !is_degraded && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                event!(Level::DEBUG, "Planner time budget exceeded at step {i}, planning remaining patterns greedily");
+                is_degraded = true;
             }
-            if i % EXTENSION_REDUCTION_CYCLE == 0 {
-                extension_width = usize::max(extension_width.saturating_sub(1), 2);
-            } // Narrow the beam until it greedy at the tail (for large queries)
-
-            new_plans_heap.clear();
-            for plan in best_partial_plans.drain(..) {
-                event!(
-                    Level::TRACE,
-                    "{INDENT:8}PLAN: {:?} ONGOING: {:?} STASH: {:?} COST: {:?} + {:?} = {:?} HEURISTIC: {:?}",
-                    plan.vertex_ordering,
-                    plan.ongoing_step,
-                    plan.ongoing_step_stash,
-                    plan.cumulative_cost,
-                    plan.ongoing_step_cost,
-                    plan.cumulative_cost.chain(plan.ongoing_step_cost),
-                    plan.heuristic
-                );
-
-                debug_assert!(extension_heap.is_empty());
-                // Add best k extensions from this plan to new_plan_heap (k = extension_width)
-                for extension in plan.extensions_iter(&self.graph) {
-                    let extension = extension?;
-                    if extension.is_trivial(&self.graph) {
-                        extension_heap.clear();
-                        extension_heap.push(Reverse(extension));
-                        break;
-                    } else {
-                        extension_heap.push(Reverse(extension));
-                    }
-                }
-                for Reverse(extension) in drain_sorted(&mut extension_heap).take(extension_width) {
-                    new_plans_heap.push(Reverse(plan.extend_with(&self.graph, extension)));
+            if is_degraded {
+                beam_width = 1;
+                extension_width = 1;
+            } else {
+                // TODO: Do we need this?
+                if i % BEAM_REDUCTION_CYCLE == 0 {
+                    beam_width = usize::max(beam_width.saturating_sub(1), min_beam_width);
                 }
+                if i % EXTENSION_REDUCTION_CYCLE == 0 {
+                    extension_width = usize::max(extension_width.saturating_sub(1), min_extension_width);
+                } // Narrow the beam until it greedy at the tail (for large queries)
+            }
+
+            buffers.new_plans_heap.clear();
+            // Add best k extensions from each plan to new_plan_heap (k = extension_width), in the order of the plans
+            let extended_plans: Vec<Vec<_>> =
+                if best_partial_plans.len() > 1 && num_patterns >= self.config.parallel_extension_min_patterns {
+                    self.extend_plans_in_parallel(&best_partial_plans, extension_width)?
+                } else {
+                    best_partial_plans
+                        .iter()
+                        .map(|plan| plan.best_extensions(&self.graph, extension_width, &mut buffers.extension_heap))
+                        .try_collect()?
+                };
+            if extended_plans.iter().all(Vec::is_empty) {
+                return Err(self.no_plan_error(best_partial_plans[0].remaining_patterns.iter(), i));
             }
-            // Pick best (k = beam_width) plans to beam.
+            best_partial_plans.clear();
+            buffers.new_plans_heap.extend(extended_plans.into_iter().flatten().map(Reverse));
+            // Pick best (k = beam_width) plans to beam. Of plans in the same state, that is with the same planned,
+            // ongoing and stashed patterns, those that another one dominates are dropped: both are completed by the
+            // same extensions, at a cost no lower for the dominated one.
             debug_assert!(best_partial_plans.is_empty());
-            new_plans_hashset.clear();
-            for Reverse(plan) in drain_sorted(&mut new_plans_heap) {
-                if new_plans_hashset.insert(plan.hash()) {
-                    best_partial_plans.push(plan);
-                    if best_partial_plans.len() >= beam_width {
-                        break;
-                    }
+            buffers.new_plans_index.clear();
+            for Reverse(plan) in drain_sorted(&mut buffers.new_plans_heap) {
+                let same_state = buffers.new_plans_index.entry(plan.astar_state_key()).or_default();
+                if same_state.iter().any(|&index| plan.is_dominated_by(&best_partial_plans[index])) {
+                    continue;
+                }
+                if let Some(&index) = same_state.iter().find(|&&index| best_partial_plans[index].is_dominated_by(&plan))
+                {
+                    best_partial_plans[index] = plan;
+                    continue;
+                }
+                same_state.push(best_partial_plans.len());
+                best_partial_plans.push(plan);
+                if best_partial_plans.len() >= beam_width {
+                    break;
                 }
             }
+            buffers.shrink(beam_width, extension_width);
         }
+        self.planning_buffers.give_back(buffers);
 
-        let best_plan =
-            best_partial_plans.into_iter().min().ok_or(QueryPlanningError::ExpectedPlannableConjunction {})?;
+        if is_degraded {
+            self.planner_statistics.mark_degraded_plan();
+        }
+        let best_plan = best_partial_plans.into_iter().min().unwrap(); // every iteration extends at least one plan
         let complete_plan = best_plan.into_complete_plan(&self.graph);
-        event!(
-            Level::TRACE,
-            "\n Final plan (before lowering):\n --> Order: {:?} --> MetaData \n {:?}",
-            complete_plan.vertex_ordering,
-            complete_plan.pattern_metadata
-        );
-        Ok((complete_plan.vertex_ordering, complete_plan.pattern_metadata, complete_plan.cumulative_cost))
+        if tracing::enabled!(Level::TRACE) {
+            event!(
+                Level::TRACE,
+                "\n Final plan (before lowering):\n --> Order: {:?} --> MetaData \n {:?}",
+                complete_plan.vertex_ordering,
+                complete_plan.pattern_metadata
+            );
+        }
+        Ok(complete_plan)
     }
 
-    // Execute plans
-    pub(super) fn plan(self) -> Result<ConjunctionPlan<'a>, QueryPlanningError> {
-        // Beam plan
-        let (ordering, metadata, cost) = self.beam_search_plan()?;
-
-        let element_to_order = ordering.iter().copied().enumerate().map(|(order, index)| (index, order)).collect();
-
-        let Self { shared_variables, graph, local_annotations: type_annotations, mut planner_statistics, .. } = self;
-
-        planner_statistics.finalize(cost);
-        Ok(ConjunctionPlan {
-            shared_variables,
-            graph,
-            local_annotations: type_annotations,
-            ordering,
-            metadata,
-            element_to_order,
-            planner_statistics,
+    // Extends each plan on a scoped thread per chunk of the beam. The extensions only read the graph, and are returned
+    // in the order of the plans, so the merged beam is the same as when extending the plans serially.
+    fn extend_plans_in_parallel(
+        &self,
+        plans: &[PartialCostPlan],
+        extension_width: usize,
+    ) -> Result<Vec<Vec<PartialCostPlan>>, QueryPlanningError> {
+        let threads = usize::min(thread::available_parallelism().map_or(1, NonZeroUsize::get), plans.len());
+        let chunk_size = plans.len().div_ceil(threads);
+        let graph = &self.graph;
+        thread::scope(|scope| {
+            let handles = plans
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let mut extension_heap = BinaryHeap::with_capacity(extension_width);
+                        chunk
+                            .iter()
+                            .map(|plan| plan.best_extensions(graph, extension_width, &mut extension_heap))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .collect_vec();
+            let mut extended_plans = Vec::with_capacity(plans.len());
+            for handle in handles {
+                extended_plans.extend(handle.join().unwrap_or_else(|payload| panic::resume_unwind(payload))?);
+            }
+            Ok(extended_plans)
         })
     }
-}
-
-struct DrainSorted<'a, T: Ord> {
-    heap: &'a mut BinaryHeap<T>,
-}
 
-fn drain_sorted<T: Ord>(heap: &mut BinaryHeap<T>) -> impl Iterator<Item = T> + '_ {
-    DrainSorted { heap }
-}
+    // Best-first search over partial plans from a single priority queue, keyed by a lower bound on the cost of their
+    // cheapest completion. Since the bound never overestimates, the first complete plan taken from the queue is the
+    // cheapest one reachable by the same extensions the beam search takes. Gives up (returning None) once more than
+    // `astar_node_budget` plans have been expanded, or the deadline passes.
+    fn astar_plan(
+        &self,
+        search_patterns: HashSet<PatternVertexId>,
+        deadline: Option<Instant>,
+    ) -> Result<Option<CompleteCostPlan>, QueryPlanningError> {
+        const INDENT: &str = "";
 
-impl<'a, T: Ord> Iterator for DrainSorted<'a, T> {
-    type Item = T;
+        let lower_bounds = self.pattern_lower_bounds(&search_patterns)?;
+        let start = PartialCostPlan::new(
+            self.graph.elements.len(),
+            search_patterns,
+            self.input_variables(),
+            self.planner_statistics.completion_estimate(),
+        );
+        let mut open = BinaryHeap::new();
+        open.push(Reverse(AStarEntry { bound: start.completion_lower_bound(&lower_bounds), plan: start }));
+        let mut expanded: HashMap<AStarStateKey, Vec<PartialCostPlan>> = HashMap::new();
+        let mut num_expanded = 0;
+        let mut extensions = Vec::new(); // reused
+
+        while let Some(Reverse(AStarEntry { plan, .. })) = open.pop() {
+            if plan.remaining_patterns.is_empty() {
+                let complete_plan = plan.into_complete_plan(&self.graph);
+                if tracing::enabled!(Level::TRACE) {
+                    event!(
+                        Level::TRACE,
+                        "\n Final A* plan after expanding {num_expanded} plans (before lowering):\n --> Order: {:?} --> MetaData \n {:?}",
+                        complete_plan.vertex_ordering,
+                        complete_plan.pattern_metadata
+                    );
+                }
+                return Ok(Some(complete_plan));
+            }
+            let same_state = expanded.entry(plan.astar_state_key()).or_default();
+            if same_state.iter().any(|other| plan.is_dominated_by(other)) {
+                continue;
+            }
+            num_expanded += 1;
+            if num_expanded > self.config.astar_node_budget {
+                event!(Level::TRACE, "{INDENT:4}A* node budget exhausted, falling back to beam search");
+                return Ok(None);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                event!(Level::DEBUG, "Planner time budget exceeded during A* search, falling back to beam search");
+                return Ok(None);
+            }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.heap.pop()
+            debug_assert!(extensions.is_empty());
+            for extension in plan.extensions_iter(&self.graph) {
+                let extension = extension?;
+                if extension.is_trivial(&self.graph) {
+                    extensions.clear();
+                    extensions.push(extension);
+                    break;
+                } else {
+                    extensions.push(extension);
+                }
+            }
+            for extension in extensions.drain(..) {
+                let extended = plan.extend_with(&self.graph, extension);
+                let bound = if extended.remaining_patterns.is_empty() {
+                    extended.completed_cost().cost
+                } else {
+                    extended.completion_lower_bound(&lower_bounds)
+                };
+                open.push(Reverse(AStarEntry { bound, plan: extended }));
+            }
+            same_state.push(plan);
+        }
+        let all_patterns = self.graph.pattern_to_variable.keys().copied();
+        Err(self.no_plan_error(all_patterns, num_expanded))
     }
-}
 
-impl<'a, T: Ord> Drop for DrainSorted<'a, T> {
-    fn drop(&mut self) {
-        self.heap.clear();
+    // The lowest cost and io ratio each pattern may be planned with, whichever of its variables are bound by then.
+    // Restrictions on its variables only make a pattern cheaper and more selective, so all other variables are bound.
+    fn pattern_lower_bounds(
+        &self,
+        patterns: &HashSet<PatternVertexId>,
+    ) -> Result<HashMap<PatternVertexId, PatternLowerBound>, QueryPlanningError> {
+        const MAX_ENUMERATED_VARIABLES: usize = 6; // patterns with more variables are bounded by zero instead
+
+        let all_variables = self.graph.elements.keys().copied().filter(|vertex| vertex.as_variable_id().is_some());
+        let all_variables = all_variables.sorted().collect_vec();
+        let mut lower_bounds = HashMap::with_capacity(patterns.len());
+        for &pattern in patterns {
+            let pattern_id = VertexId::Pattern(pattern);
+            let planner = &self.graph.elements[&pattern_id];
+            let own_variables = self.graph.pattern_to_variable[&pattern].iter().sorted().collect_vec();
+            let mut lower_bound = PatternLowerBound {
+                cost: f64::INFINITY,
+                io_ratio: f64::INFINITY,
+                can_be_trivial: planner.can_be_trivial(),
+                join_size: None,
+            };
+            // a disjunction is only costed by planning its branches, which is not worth repeating for every input set
+            let is_disjunction = matches!(planner, PlannerVertex::Disjunction(_));
+            if !is_disjunction && own_variables.len() <= MAX_ENUMERATED_VARIABLES {
+                for bound_variables in own_variables.iter().copied().powerset() {
+                    let inputs = (all_variables.iter().copied())
+                        .filter(|vertex| {
+                            let var = vertex.as_variable_id().unwrap();
+                            !own_variables.contains(&&var) || bound_variables.contains(&&var)
+                        })
+                        .collect_vec();
+                    if !planner.is_valid(pattern_id, &inputs, &self.graph) {
+                        continue;
+                    }
+                    let (cost, _) = planner.cost_and_metadata(&inputs, None, &self.graph)?;
+                    lower_bound.cost = f64::min(lower_bound.cost, cost.cost);
+                    lower_bound.io_ratio = f64::min(lower_bound.io_ratio, cost.io_ratio);
+                }
+            }
+            if lower_bound.cost.is_infinite() {
+                (lower_bound.cost, lower_bound.io_ratio) = (0.0, 0.0);
+            }
+            // a joined pattern divides the io ratio of its step by the size of the join, which is at most the product
+            // of the sizes of the variables it can be joined on
+            if let PlannerVertex::Constraint(constraint) = planner {
+                if let Some(join) = constraint.join_variables() {
+                    lower_bound.join_size = Some(
+                        [join.canonical, join.reverse]
+                            .into_iter()
+                            .dedup()
+                            .map(|var| {
+                                let variable = self.graph.elements[&VertexId::Variable(var)].as_variable().unwrap();
+                                variable.unrestricted_expected_output_size()
+                            })
+                            .product(),
+                    );
+                }
+            }
+            lower_bounds.insert(pattern, lower_bound);
+        }
+        Ok(lower_bounds)
     }
-}
-
-#[derive(Clone, Copy, Debug)]
-pub struct PlannerStatistics {
-    links_count: (f64, f64), // vertex count, key count
-    has_count: (f64, f64),
-    var_count: (f64, f64),
-    pub(crate) query_cost: Cost,
-    // TODO: pass info about individual steps
-}
 
-impl PlannerStatistics {
-    pub fn new() -> PlannerStatistics {
-        PlannerStatistics {
-            links_count: (0.0, 0.0),
-            has_count: (0.0, 0.0),
-            var_count: (0.0, 0.0),
-            query_cost: Cost::NOOP,
+    fn search_plan(&mut self) -> Result<CompleteCostPlan, QueryPlanningError> {
+        let deadline = self.config.time_budget.map(|budget| Instant::now() + budget);
+        let components = self.graph.connected_components();
+        if components.len() > 1 {
+            return self.plan_components(components, deadline);
         }
+        let search_patterns = self.graph.pattern_to_variable.keys().copied().collect();
+        self.plan_patterns(search_patterns, deadline)
     }
 
-    pub(crate) fn increment_var(&mut self, count: f64) {
-        self.var_count.0 += 1.0;
-        self.var_count.1 += count;
+    // Small searches are planned best-first, larger ones, or ones the best-first search gives up on, with the beam
+    fn plan_patterns(
+        &mut self,
+        search_patterns: HashSet<PatternVertexId>,
+        deadline: Option<Instant>,
+    ) -> Result<CompleteCostPlan, QueryPlanningError> {
+        if search_patterns.len() <= self.config.astar_max_patterns {
+            if let Some(plan) = self.astar_plan(search_patterns.clone(), deadline)? {
+                return Ok(plan);
+            }
+        }
+        self.beam_search_plan(search_patterns, deadline)
     }
 
-    pub(crate) fn increment_has(&mut self, count: f64) {
-        self.has_count.0 += 1.0;
-        self.has_count.1 += count;
+    // Components share no variables, so interleaving their patterns only multiplies the orderings searched, and can
+    // place cartesian steps mid-plan. Each component is planned on its own, and the plans are concatenated cheapest
+    // first: every component is executed once per answer of the components before it.
+    fn plan_components(
+        &mut self,
+        components: Vec<HashSet<PatternVertexId>>,
+        deadline: Option<Instant>,
+    ) -> Result<CompleteCostPlan, QueryPlanningError> {
+        let mut component_plans: Vec<_> =
+            components.into_iter().map(|component| self.plan_patterns(component, deadline)).try_collect()?;
+        component_plans.sort_by(|lhs, rhs| lhs.cumulative_cost.cost.total_cmp(&rhs.cumulative_cost.cost));
+
+        let mut vertex_ordering = Vec::with_capacity(self.graph.elements.len());
+        let mut ordered = HashSet::new();
+        let mut pattern_metadata = HashMap::new();
+        let mut compound_joins = HashMap::new();
+        let mut cumulative_cost = Cost::NOOP;
+        for plan in component_plans {
+            // every component plan starts with the input variables
+            vertex_ordering.extend(plan.vertex_ordering.into_iter().filter(|&vertex| ordered.insert(vertex)));
+            pattern_metadata.extend(plan.pattern_metadata);
+            compound_joins.extend(plan.compound_joins);
+            cumulative_cost = cumulative_cost.chain(plan.cumulative_cost);
+        }
+        Ok(CompleteCostPlan { vertex_ordering, pattern_metadata, compound_joins, cumulative_cost })
     }
 
-    pub(crate) fn increment_links(&mut self, count: f64) {
-        self.links_count.0 += 1.0;
-        self.links_count.1 += count;
-    }
+    // Greedily extends the plan with the cheapest valid extension, never joining patterns into one step.
+    // Used as a fallback when the beam search unexpectedly yields no plan.
+    fn greedy_plan(&self) -> Result<CompleteCostPlan, QueryPlanningError> {
+        let search_patterns: HashSet<_> = self.graph.pattern_to_variable.keys().copied().collect();
+        let mut plan = PartialCostPlan::new(
+            self.graph.elements.len(),
+            search_patterns,
+            self.input_variables(),
+            self.planner_statistics.completion_estimate(),
+        );
+        let mut completed_iterations = 0;
+        while !plan.remaining_patterns.is_empty() {
+            let mut best_extension = None;
+            for extension in plan.extensions_iter(&self.graph) {
+                let extension = extension?;
+                if extension.step_join_var.is_none() && !best_extension.as_ref().is_some_and(|best| extension >= *best)
+                {
+                    best_extension = Some(extension);
+                }
+            }
+            let Some(extension) = best_extension else {
+                return Err(self.unresolvable_dependencies_error(&plan, completed_iterations));
+            };
+            plan = plan.extend_with(&self.graph, extension);
+            completed_iterations += 1;
+        }
+        Ok(plan.into_complete_plan(&self.graph))
+    }
+
+    fn no_plan_error(
+        &self,
+        unplanned_patterns: impl Iterator<Item = PatternVertexId>,
+        completed_iterations: usize,
+    ) -> QueryPlanningError {
+        let unplanned_patterns = unplanned_patterns
+            .sorted()
+            .map(|pattern| self.graph.elements[&VertexId::Pattern(pattern)].to_string())
+            .join(", ");
+        QueryPlanningError::ExpectedPlannableConjunction {
+            scope: self.scope,
+            branch_id: self.branch_id,
+            unplanned_patterns,
+            completed_iterations,
+        }
+    }
+
+    // No remaining pattern can be planned. If they depend on variables that are still unbound, no pattern binds those
+    // variables before them, which is a user error; otherwise the planner failed to find a valid ordering.
+    fn unresolvable_dependencies_error(
+        &self,
+        plan: &PartialCostPlan,
+        completed_iterations: usize,
+    ) -> QueryPlanningError {
+        let available = plan.available_variables().collect::<HashSet<_>>();
+        let unbound = (plan.remaining_patterns.iter())
+            .flat_map(|pattern| self.graph.elements[&VertexId::Pattern(pattern)].variables())
+            .filter(|var| !available.contains(var))
+            .map(|var| self.graph.index_to_variable[&var])
+            .sorted()
+            .dedup()
+            .collect_vec();
+        if unbound.is_empty() {
+            return self.no_plan_error(plan.remaining_patterns.iter(), completed_iterations);
+        }
+        let unplanned_patterns = plan.remaining_patterns.iter().sorted().collect_vec();
+        let source_span = enclosing_span(
+            (unplanned_patterns.iter())
+                .flat_map(|&pattern| self.graph.elements[&VertexId::Pattern(pattern)].source_spans()),
+        );
+        QueryPlanningError::UnresolvableDependencies {
+            scope: self.scope,
+            branch_id: self.branch_id,
+            unplanned_patterns: (unplanned_patterns.into_iter())
+                .map(|pattern| self.graph.elements[&VertexId::Pattern(pattern)].to_string())
+                .join(", "),
+            variables: (unbound.into_iter())
+                .map(|var| self.variable_names.get(&var).cloned().unwrap_or_else(|| var.to_string()))
+                .collect(),
+            source_span,
+        }
+    }
+
+    // Execute plans
+    pub(super) fn plan(mut self) -> Result<ConjunctionPlan<'a>, QueryPlanningError> {
+        let complete_plan = self.complete_plan()?;
+        Ok(self.into_plan(complete_plan))
+    }
+
+    // Plans through the plan cache if there is one. On a hit, the cached ordering is rebuilt against this builder's
+    // graph, which is freshly built for the conjunction and so references the current annotations and statistics.
+    pub(super) fn plan_with_cache(
+        mut self,
+        conjunction: &Conjunction,
+        plan_cache: Option<&PlanCache>,
+    ) -> Result<ConjunctionPlan<'a>, QueryPlanningError> {
+        let Some(plan_cache) = plan_cache else {
+            return self.plan();
+        };
+        let key = PlanCacheKey::new(self.statistics.sequence_number, conjunction, self.plan_context_hash());
+
+        let cached = plan_cache.get(key, conjunction);
+        let restored = cached.as_deref().and_then(|cached| self.restore_cached_plan(cached));
+        if cached.is_some() && restored.is_none() {
+            plan_cache.remove(key);
+        }
+        let (hits, misses) = plan_cache.record_lookup(restored.is_some());
+
+        let complete_plan = match (restored, cached) {
+            (Some(complete_plan), Some(cached)) => {
+                self.mark_restored_plan(&cached);
+                complete_plan
+            }
+            _ => {
+                let complete_plan = self.complete_plan()?;
+                // a degraded plan only reflects how far the search got in time, so it is replanned on the next lookup
+                if !self.planner_statistics.is_degraded_plan() {
+                    plan_cache.insert(key, conjunction, self.make_cached_plan(&complete_plan));
+                }
+                complete_plan
+            }
+        };
+        self.planner_statistics.set_plan_cache_lookups(hits, misses);
+        Ok(self.into_plan(complete_plan))
+    }
+
+    fn complete_plan(&mut self) -> Result<CompleteCostPlan, QueryPlanningError> {
+        self.unify_equality_classes();
+        let mut complete_plan = match self.search_plan() {
+            Err(QueryPlanningError::ExpectedPlannableConjunction { .. }) => {
+                event!(Level::WARN, "Query planner found no plan for conjunction, falling back to a greedy plan");
+                if tracing::enabled!(Level::DEBUG) {
+                    event!(Level::DEBUG, "Planner graph with no plan found:\n{}", self.graph.to_dot());
+                }
+                self.planner_statistics.mark_fallback_plan();
+                self.greedy_plan()
+            }
+            result => result,
+        }?;
+        if self.config.hoist_checks {
+            complete_plan.hoist_checks(&self.graph);
+        }
+        Ok(complete_plan)
+    }
+
+    // Variables related by `==` or `is` take the same values in every answer, so once any of them is produced, none
+    // of them is expected to take more values than the smallest. Classes are found by union-find over those relations.
+    fn unify_equality_classes(&mut self) {
+        fn find(parents: &mut HashMap<VariableVertexId, VariableVertexId>, var: VariableVertexId) -> VariableVertexId {
+            let parent = *parents.entry(var).or_insert(var);
+            if parent == var {
+                return var;
+            }
+            let root = find(parents, parent);
+            parents.insert(var, root);
+            root
+        }
+
+        let mut parents = HashMap::new();
+        for &(lhs, rhs) in &self.equalities {
+            let lhs_root = find(&mut parents, lhs);
+            let rhs_root = find(&mut parents, rhs);
+            parents.insert(lhs_root, rhs_root);
+        }
+        let variables = parents.keys().copied().sorted().collect_vec();
+        let classes = variables.into_iter().map(|var| (find(&mut parents, var), var)).into_group_map();
+        for members in classes.into_values() {
+            // sizes are taken before any member is produced, where the classes have no effect yet
+            let expected_size = (members.iter())
+                .map(|&member| {
+                    let vertex = self.graph.elements[&VertexId::Variable(member)].as_variable().unwrap();
+                    vertex.restricted_expected_output_size(&[])
+                })
+                .fold(f64::INFINITY, f64::min);
+            for &member in &members {
+                let vertex = self.graph.elements.get_mut(&VertexId::Variable(member)).unwrap();
+                vertex
+                    .as_variable_mut()
+                    .unwrap()
+                    .set_equality_class(EqualityClass::new(members.clone(), expected_size));
+            }
+        }
+    }
+
+    // Everything besides the conjunction itself that the plan depends on
+    fn plan_context_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.config.hash(&mut hasher);
+        self.called_function_versions.hash(&mut hasher);
+        self.required_inputs.iter().sorted().for_each(|var| var.hash(&mut hasher));
+        self.shared_variables.iter().sorted().for_each(|var| var.hash(&mut hasher));
+        for (var, id) in self.graph.variable_index.iter().sorted_by_key(|(&var, _)| var) {
+            var.hash(&mut hasher);
+            let variable = self.graph.elements[&VertexId::Variable(*id)].as_variable();
+            variable.is_some_and(|v| v.is_input()).hash(&mut hasher);
+            // plans of the same conjunction with constant bounds of different selectivity may differ
+            let (lower, upper) = variable.map_or((None, None), |v| v.constant_bounds());
+            (lower.map(f64::to_bits), upper.map(f64::to_bits)).hash(&mut hasher);
+        }
+        self.graph.elements.len().hash(&mut hasher);
+        self.local_annotations.vertex_annotations().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn make_cached_plan(&self, complete_plan: &CompleteCostPlan) -> CachedPlan {
+        let variable = |id: &VariableVertexId| self.graph.index_to_variable[id];
+        CachedPlan {
+            ordering: (complete_plan.vertex_ordering.iter())
+                .map(|vertex| match vertex {
+                    VertexId::Variable(id) => CachedVertex::Variable(variable(id)),
+                    VertexId::Pattern(id) => CachedVertex::Pattern(*id),
+                })
+                .collect(),
+            metadata: complete_plan.pattern_metadata.clone(),
+            compound_joins: (complete_plan.compound_joins.iter())
+                .map(|(join, secondary)| (variable(join), variable(secondary)))
+                .collect(),
+            cost: complete_plan.cumulative_cost,
+            element_count: self.graph.elements.len(),
+            is_fallback_plan: self.planner_statistics.is_fallback_plan(),
+        }
+    }
+
+    fn mark_restored_plan(&mut self, cached: &CachedPlan) {
+        self.planner_statistics.mark_cached_plan();
+        if cached.is_fallback_plan {
+            self.planner_statistics.mark_fallback_plan();
+        }
+    }
+
+    // Returns None if the cached plan does not fit this builder's graph, in which case it must be replanned.
+    fn restore_cached_plan(&self, cached: &CachedPlan) -> Option<CompleteCostPlan> {
+        if cached.element_count != self.graph.elements.len() {
+            return None;
+        }
+        let variable = |var: &Variable| self.graph.variable_index.get(var).copied();
+        let vertex_ordering = (cached.ordering.iter())
+            .map(|vertex| match vertex {
+                CachedVertex::Variable(var) => variable(var).map(VertexId::Variable),
+                CachedVertex::Pattern(id) => {
+                    self.graph.elements.contains_key(&VertexId::Pattern(*id)).then_some(VertexId::Pattern(*id))
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let compound_joins = (cached.compound_joins.iter())
+            .map(|(join, secondary)| Some((variable(join)?, variable(secondary)?)))
+            .collect::<Option<HashMap<_, _>>>()?;
+        Some(CompleteCostPlan {
+            vertex_ordering,
+            pattern_metadata: cached.metadata.clone(),
+            compound_joins,
+            cumulative_cost: cached.cost,
+        })
+    }
+
+    fn into_plan(self, complete_plan: CompleteCostPlan) -> ConjunctionPlan<'a> {
+        let CompleteCostPlan {
+            vertex_ordering: ordering,
+            pattern_metadata: metadata,
+            compound_joins,
+            cumulative_cost: cost,
+        } = complete_plan;
+
+        let element_to_order = ordering.iter().copied().enumerate().map(|(order, index)| (index, order)).collect();
+
+        let Self {
+            shared_variables,
+            graph,
+            local_annotations: type_annotations,
+            mut planner_statistics,
+            planning_notes,
+            config,
+            anonymous_relations,
+            ..
+        } = self;
+
+        planner_statistics.finalize(cost);
+        if config.statistics_freshness == StatisticsFreshness::Stale {
+            planner_statistics.mark_estimated_statistics();
+        }
+        ConjunctionPlan {
+            shared_variables,
+            graph,
+            local_annotations: type_annotations,
+            ordering,
+            metadata,
+            compound_joins,
+            element_to_order,
+            planner_statistics,
+            planning_notes,
+            anonymous_relations,
+            negation_semantics: config.negation_semantics,
+            single_instruction_fast_path: config.single_instruction_fast_path,
+            interleave_branches: config.interleave_branches,
+        }
+    }
+}
+
+// The value of a numeric constant operand of a comparison, which statistics may estimate the selectivity of.
+// The estimate is baked into the plan, which is then reused for other values of the constant by the query cache.
+fn constant_numeric_value(vertex: &Vertex<Variable>, value_parameters: &ParameterRegistry) -> Option<f64> {
+    match value_parameters.value(vertex.as_parameter()?)? {
+        encoding::value::value::Value::Integer(integer) => Some(*integer as f64),
+        encoding::value::value::Value::Double(double) => Some(*double).filter(|double| double.is_finite()),
+        _ => None,
+    }
+}
+
+// Capacity kept by the beam search buffers beyond what the current widths need, as a multiple of that need
+const PLANNING_BUFFER_SLACK: usize = 2;
+
+// The buffers of one beam search, reused across its iterations.
+#[derive(Debug, Default)]
+struct BeamBuffers {
+    extension_heap: BinaryHeap<Reverse<StepExtension>>,
+    new_plans_heap: BinaryHeap<Reverse<PartialCostPlan>>,
+    new_plans_index: HashMap<AStarStateKey, Vec<usize>>,
+}
+
+impl BeamBuffers {
+    fn with_capacity(beam_width: usize, extension_width: usize) -> Self {
+        Self {
+            extension_heap: BinaryHeap::with_capacity(extension_width),
+            new_plans_heap: BinaryHeap::with_capacity(beam_width.saturating_mul(extension_width)),
+            new_plans_index: HashMap::with_capacity(beam_width),
+        }
+    }
+
+    // Releases the capacity left over from the widest iteration so far, which the narrowing beam no longer needs.
+    fn shrink(&mut self, beam_width: usize, extension_width: usize) {
+        self.extension_heap.shrink_to(extension_width.saturating_mul(PLANNING_BUFFER_SLACK));
+        self.new_plans_heap.shrink_to(beam_width.saturating_mul(extension_width).saturating_mul(PLANNING_BUFFER_SLACK));
+        self.new_plans_index.shrink_to(beam_width.saturating_mul(PLANNING_BUFFER_SLACK));
+    }
+
+    fn retained_bytes(&self) -> usize {
+        self.extension_heap.capacity() * mem::size_of::<Reverse<StepExtension>>()
+            + self.new_plans_heap.capacity() * mem::size_of::<Reverse<PartialCostPlan>>()
+            + self.new_plans_index.capacity() * mem::size_of::<(AStarStateKey, Vec<usize>)>()
+    }
+}
+
+/// Beam search buffers shared by all conjunctions planned for one query, nested ones included, so that each does not
+/// allocate its own. A search started while another is ongoing, such as that of a disjunction branch being costed,
+/// takes a second set of buffers, which is pooled alike once it finishes.
+#[derive(Debug, Default)]
+pub(super) struct PlanningBuffers {
+    pool: Mutex<BeamBufferPool>,
+}
+
+#[derive(Debug, Default)]
+struct BeamBufferPool {
+    idle: Vec<BeamBuffers>,
+    allocated: u64,
+}
+
+impl PlanningBuffers {
+    fn take(&self, beam_width: usize, extension_width: usize) -> BeamBuffers {
+        let mut pool = self.pool.lock().unwrap();
+        match pool.idle.pop() {
+            Some(buffers) => buffers,
+            None => {
+                pool.allocated += 1;
+                BeamBuffers::with_capacity(beam_width, extension_width)
+            }
+        }
+    }
+
+    fn give_back(&self, mut buffers: BeamBuffers) {
+        // a finished search leaves nothing behind, so only the capacity for a greedy search is kept
+        buffers.shrink(1, 1);
+        self.pool.lock().unwrap().idle.push(buffers);
+    }
+
+    // The number of buffer sets allocated, and the bytes they retain while idle
+    fn usage(&self) -> (u64, usize) {
+        let pool = self.pool.lock().unwrap();
+        (pool.allocated, pool.idle.iter().map(BeamBuffers::retained_bytes).sum())
+    }
+}
+
+struct DrainSorted<'a, T: Ord> {
+    heap: &'a mut BinaryHeap<T>,
+}
+
+fn drain_sorted<T: Ord>(heap: &mut BinaryHeap<T>) -> impl Iterator<Item = T> + '_ {
+    DrainSorted { heap }
+}
+
+impl<'a, T: Ord> Iterator for DrainSorted<'a, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.heap.pop()
+    }
+}
+
+impl<'a, T: Ord> Drop for DrainSorted<'a, T> {
+    fn drop(&mut self) {
+        self.heap.clear();
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlannerStatistics {
+    links_count: (f64, f64), // vertex count, key count
+    has_count: (f64, f64),
+    var_count: (f64, f64),
+    pub(crate) query_cost: Cost,
+    is_fallback_plan: bool,
+    is_degraded_plan: bool, // the time budget ran out and part of the plan was searched greedily
+    is_cached_plan: bool,
+    is_estimated_statistics: bool, // planned against statistics estimated for types they do not record
+    plan_cache_hits: u64,          // totals over the plan cache's lifetime at the time of planning
+    plan_cache_misses: u64,
+    disjunction_memo_hits: u64, // totals over the compilation of the query, at the time of planning
+    disjunction_memo_misses: u64,
+    planning_buffer_sets: u64, // beam search buffers allocated while planning the query, and the bytes they retained
+    planning_buffer_bytes: usize,
+    // TODO: pass info about individual steps
+}
+
+impl PlannerStatistics {
+    pub fn new() -> PlannerStatistics {
+        PlannerStatistics {
+            links_count: (0.0, 0.0),
+            has_count: (0.0, 0.0),
+            var_count: (0.0, 0.0),
+            query_cost: Cost::NOOP,
+            is_fallback_plan: false,
+            is_degraded_plan: false,
+            is_cached_plan: false,
+            is_estimated_statistics: false,
+            plan_cache_hits: 0,
+            plan_cache_misses: 0,
+            disjunction_memo_hits: 0,
+            disjunction_memo_misses: 0,
+            planning_buffer_sets: 0,
+            planning_buffer_bytes: 0,
+        }
+    }
+
+    pub fn is_fallback_plan(&self) -> bool {
+        self.is_fallback_plan
+    }
+
+    pub(super) fn mark_fallback_plan(&mut self) {
+        self.is_fallback_plan = true;
+    }
+
+    pub fn is_degraded_plan(&self) -> bool {
+        self.is_degraded_plan
+    }
+
+    pub(super) fn mark_degraded_plan(&mut self) {
+        self.is_degraded_plan = true;
+    }
+
+    pub fn is_cached_plan(&self) -> bool {
+        self.is_cached_plan
+    }
+
+    pub(super) fn mark_cached_plan(&mut self) {
+        self.is_cached_plan = true;
+    }
+
+    pub fn is_estimated_statistics(&self) -> bool {
+        self.is_estimated_statistics
+    }
+
+    pub(super) fn mark_estimated_statistics(&mut self) {
+        self.is_estimated_statistics = true;
+    }
+
+    /// The number of rows the plan is expected to produce for each input row, unless the statistics it was planned
+    /// against were estimated and so say little about the data.
+    pub fn estimated_output_rows(&self) -> Option<f64> {
+        let rows = self.query_cost.io_ratio;
+        (!self.is_estimated_statistics && rows.is_finite() && rows > 0.0).then_some(rows)
+    }
+
+    pub fn plan_cache_hits(&self) -> u64 {
+        self.plan_cache_hits
+    }
+
+    pub fn plan_cache_misses(&self) -> u64 {
+        self.plan_cache_misses
+    }
+
+    pub(super) fn set_plan_cache_lookups(&mut self, hits: u64, misses: u64) {
+        self.plan_cache_hits = hits;
+        self.plan_cache_misses = misses;
+    }
+
+    pub fn disjunction_memo_hits(&self) -> u64 {
+        self.disjunction_memo_hits
+    }
+
+    pub fn disjunction_memo_misses(&self) -> u64 {
+        self.disjunction_memo_misses
+    }
+
+    pub(super) fn set_disjunction_memo_lookups(&mut self, hits: u64, misses: u64) {
+        self.disjunction_memo_hits = hits;
+        self.disjunction_memo_misses = misses;
+    }
+
+    /// The number of beam search buffer sets allocated while planning the query. Conjunctions planned one after the
+    /// other, such as nested negations, share one set.
+    pub fn planning_buffer_sets(&self) -> u64 {
+        self.planning_buffer_sets
+    }
+
+    /// The bytes still retained by the beam search buffers once the query was planned.
+    pub fn planning_buffer_bytes(&self) -> usize {
+        self.planning_buffer_bytes
+    }
+
+    pub(super) fn set_planning_buffer_usage(&mut self, sets: u64, bytes: usize) {
+        self.planning_buffer_sets = sets;
+        self.planning_buffer_bytes = bytes;
+    }
+
+    pub(crate) fn increment_var(&mut self, count: f64) {
+        self.var_count.0 += 1.0;
+        self.var_count.1 += count;
+    }
+
+    pub(crate) fn increment_has(&mut self, count: f64) {
+        self.has_count.0 += 1.0;
+        self.has_count.1 += count;
+    }
+
+    pub(crate) fn increment_links(&mut self, count: f64) {
+        self.links_count.0 += 1.0;
+        self.links_count.1 += count;
+    }
 
     pub(super) fn finalize(&mut self, cost: Cost) {
         self.query_cost = cost;
     }
+
+    // The average output size is the mean expected size of the registered thing variables, and the average step
+    // cost is the mean expected size of the registered has and links constraints per such variable, i.e. their
+    // average fan-out from a bound input.
+    pub(super) fn completion_estimate(&self) -> CompletionEstimate {
+        let (var_count, var_size) = self.var_count;
+        let average_output_size =
+            if var_count > 0.0 && var_size > 0.0 { var_size / var_count } else { AVERAGE_QUERY_OUTPUT_SIZE };
+        let constraint_count = self.has_count.0 + self.links_count.0;
+        let constraint_size = self.has_count.1 + self.links_count.1;
+        let average_step_cost = if constraint_count > 0.0 && constraint_size > 0.0 {
+            constraint_size / constraint_count / average_output_size
+        } else {
+            AVERAGE_STEP_COST
+        };
+        CompletionEstimate { average_step_cost, average_output_size }
+    }
+}
+
+/// Per-conjunction averages used by the planner's heuristic for the cost of completing a partial plan.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct CompletionEstimate {
+    average_step_cost: f64,
+    average_output_size: f64,
 }
 
 impl Default for PlannerStatistics {
@@ -839,7 +2090,184 @@ impl fmt::Display for PlannerStatistics {
             self.has_count.1,
             self.var_count.0,
             self.var_count.1,
-        )
+        )?;
+        if self.is_fallback_plan {
+            write!(f, " [fallback plan]")?;
+        }
+        if self.is_degraded_plan {
+            write!(f, " [degraded plan]")?;
+        }
+        if self.is_cached_plan {
+            write!(f, " [cached plan]")?;
+        }
+        if self.is_estimated_statistics {
+            write!(f, " [estimated statistics]")?;
+        }
+        Ok(())
+    }
+}
+
+/// The metadata of the patterns planned so far, most recent first. The entries are shared with the plans this one was
+/// extended from, so recording the metadata of an extension does not copy that of the earlier patterns.
+#[derive(Clone, Debug, Default)]
+struct PatternMetadataList {
+    head: Option<Arc<PatternMetadataEntry>>,
+}
+
+#[derive(Debug)]
+struct PatternMetadataEntry {
+    pattern: PatternVertexId,
+    metadata: CostMetaData,
+    parent: Option<Arc<PatternMetadataEntry>>,
+}
+
+impl PatternMetadataList {
+    fn with(&self, pattern: PatternVertexId, metadata: CostMetaData) -> Self {
+        Self { head: Some(Arc::new(PatternMetadataEntry { pattern, metadata, parent: self.head.clone() })) }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &PatternMetadataEntry> {
+        iter::successors(self.head.as_deref(), |entry| entry.parent.as_deref())
+    }
+
+    // Looks among the `recent` patterns planned last, such as those of the ongoing step, so lookups stay independent
+    // of the length of the plan
+    fn get_recent(&self, pattern: &PatternVertexId, recent: usize) -> Option<&CostMetaData> {
+        self.iter().take(recent).find(|entry| entry.pattern == *pattern).map(|entry| &entry.metadata)
+    }
+
+    fn to_map(&self) -> HashMap<PatternVertexId, CostMetaData> {
+        self.iter().map(|entry| (entry.pattern, entry.metadata)).collect()
+    }
+}
+
+/// The decided vertex ordering of a partial plan, as a chain of its finished steps, most recent first. The steps are
+/// shared with the plans this one was extended from, so finishing a step does not copy the ordering before it: the
+/// ordering is only materialized when a plan's extensions are costed, and when it is completed.
+#[derive(Clone, Default)]
+struct VertexOrdering {
+    last_step: Option<Arc<OrderedStep>>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct OrderedStep {
+    vertices: Vec<VertexId>,
+    parent: Option<Arc<OrderedStep>>,
+}
+
+impl VertexOrdering {
+    fn with_step(&self, vertices: Vec<VertexId>) -> Self {
+        if vertices.is_empty() {
+            return self.clone();
+        }
+        let len = self.len + vertices.len();
+        Self { last_step: Some(Arc::new(OrderedStep { vertices, parent: self.last_step.clone() })), len }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn to_vec(&self) -> Vec<VertexId> {
+        let steps = iter::successors(self.last_step.as_deref(), |step| step.parent.as_deref()).collect_vec();
+        let mut vertices = Vec::with_capacity(self.len);
+        steps.into_iter().rev().for_each(|step| vertices.extend_from_slice(&step.vertices));
+        vertices
+    }
+
+    fn contains(&self, vertex: &VertexId) -> bool {
+        iter::successors(self.last_step.as_deref(), |step| step.parent.as_deref())
+            .any(|step| step.vertices.contains(vertex))
+    }
+}
+
+impl fmt::Debug for VertexOrdering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.to_vec()).finish()
+    }
+}
+
+impl PartialEq for VertexOrdering {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for VertexOrdering {}
+
+impl PartialOrd for VertexOrdering {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VertexOrdering {
+    // Only compared to break ties between equally costed plans, so the orderings are materialized for it
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.last_step, &other.last_step) {
+            (Some(step), Some(other_step)) if Arc::ptr_eq(step, other_step) => Ordering::Equal,
+            (None, None) => Ordering::Equal,
+            _ => self.to_vec().cmp(&other.to_vec()),
+        }
+    }
+}
+
+/// A set of patterns as a bitmap over their ids, which is cheaper to copy than a hash set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct PatternSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PatternSet {
+    fn insert(&mut self, pattern: PatternVertexId) -> bool {
+        let PatternVertexId(index) = pattern;
+        let word_index = index / u64::BITS as usize;
+        if self.words.len() <= word_index {
+            self.words.resize(word_index + 1, 0);
+        }
+        let mask = 1 << (index % u64::BITS as usize);
+        let is_absent = self.words[word_index] & mask == 0;
+        self.words[word_index] |= mask;
+        self.len += is_absent as usize;
+        is_absent
+    }
+
+    fn remove(&mut self, pattern: PatternVertexId) -> bool {
+        let PatternVertexId(index) = pattern;
+        let Some(word) = self.words.get_mut(index / u64::BITS as usize) else { return false };
+        let mask = 1 << (index % u64::BITS as usize);
+        let is_present = *word & mask != 0;
+        *word &= !mask;
+        self.len -= is_present as usize;
+        is_present
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn iter(&self) -> impl Iterator<Item = PatternVertexId> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_index, &word)| {
+            (0..u64::BITS as usize)
+                .filter(move |&bit| word & (1 << bit) != 0)
+                .map(move |bit| PatternVertexId(word_index * u64::BITS as usize + bit))
+        })
+    }
+}
+
+impl FromIterator<PatternVertexId> for PatternSet {
+    fn from_iter<T: IntoIterator<Item = PatternVertexId>>(patterns: T) -> Self {
+        let mut set = Self::default();
+        for pattern in patterns {
+            set.insert(pattern);
+        }
+        set
     }
 }
 
@@ -847,13 +2275,53 @@ impl fmt::Display for PlannerStatistics {
 pub(super) struct CompleteCostPlan {
     vertex_ordering: Vec<VertexId>,
     pattern_metadata: HashMap<PatternVertexId, CostMetaData>,
+    compound_joins: HashMap<VariableVertexId, VariableVertexId>,
     cumulative_cost: Cost,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+impl CompleteCostPlan {
+    // The search appends checks to the step in which they became valid, or costs them like any other step: a check
+    // that only filters may so end up after steps it could have reduced the input of. Moves each such check to directly
+    // after the step producing the last of its variables. Only the ordering changes, so the answers are the same.
+    fn hoist_checks(&mut self, graph: &Graph<'_>) {
+        let checks = (self.vertex_ordering.iter())
+            .filter_map(VertexId::as_pattern_id)
+            .filter(|pattern| {
+                matches!(
+                    graph.elements[&VertexId::Pattern(*pattern)],
+                    PlannerVertex::Comparison(_) | PlannerVertex::Is(_) | PlannerVertex::LinksDeduplication(_)
+                )
+            })
+            .collect_vec();
+        for check in checks {
+            let position = self.vertex_ordering.iter().position(|&vertex| vertex == VertexId::Pattern(check)).unwrap();
+            let variable_positions = graph.pattern_to_variable[&check]
+                .iter()
+                .map(|&var| self.vertex_ordering.iter().position(|&vertex| vertex == VertexId::Variable(var)).unwrap());
+            let Some(last_input) = variable_positions.max() else { continue };
+            if last_input > position {
+                continue; // the pattern produces a variable, so it is not a pure check
+            }
+            // the variables produced by a step follow its patterns: the step ends at the next pattern
+            let mut target = last_input + 1;
+            while target < position && self.vertex_ordering[target].as_variable_id().is_some() {
+                target += 1;
+            }
+            if target < position {
+                self.vertex_ordering.remove(position);
+                self.vertex_ordering.insert(target, VertexId::Pattern(check));
+            }
+        }
+    }
+}
+
+// Extending a partial plan shares the decided ordering, the metadata and the finished compound joins with the plan it
+// extends, since every plan in the beam is extended many times but only one is completed.
+#[derive(Clone, Debug)]
 pub(super) struct PartialCostPlan {
-    vertex_ordering: Vec<VertexId>, // the part of the plan that has been decided upon
-    cumulative_cost: Cost,          // the cost of the part of the plan that has been decided upon
+    vertex_ordering: VertexOrdering, // the part of the plan that has been decided upon
+    planned_patterns: PatternSet,    // the patterns in the decided ordering
+    cumulative_cost: Cost,           // the cost of the part of the plan that has been decided upon
 
     ongoing_step: HashSet<PatternVertexId>, // the set of non-trivial patterns in the ongoing step
     ongoing_step_stash: Vec<PatternVertexId>, // the set of trivial patterns in the ongoing step
@@ -861,11 +2329,14 @@ pub(super) struct PartialCostPlan {
     ongoing_step_produced_vars: HashSet<VariableVertexId>, // variables produced in this step
     ongoing_step_stash_produced_vars: HashSet<VariableVertexId>, // variables produced in this step
     ongoing_step_join_var: Option<VariableVertexId>, // the join variable of the ongoing step
+    ongoing_step_secondary_join_var: Option<VariableVertexId>, // the second join variable of a compound join
+    compound_joins: Arc<HashMap<VariableVertexId, VariableVertexId>>, // the secondary join variables of finished steps
 
     all_produced_vars: HashSet<VariableVertexId>, // the set of all variables produced (incl. in ongoing step, excl. stash)
-    remaining_patterns: HashSet<PatternVertexId>, // the set of remaining patterns to be searched
-    pattern_metadata: HashMap<PatternVertexId, CostMetaData>, // metadata, like pattern directions
+    remaining_patterns: PatternSet,               // the set of remaining patterns to be searched
+    pattern_metadata: PatternMetadataList,        // metadata, like pattern directions
     heuristic: Cost,                              // the heuristic that plans are sorted by
+    completion_estimate: CompletionEstimate,      // the averages the heuristic extrapolates from
 }
 
 impl PartialCostPlan {
@@ -873,6 +2344,7 @@ impl PartialCostPlan {
         total_plan_len: usize,
         remaining_patterns: HashSet<PatternVertexId>,
         inputs: impl Iterator<Item = VariableVertexId> + Sized,
+        completion_estimate: CompletionEstimate,
     ) -> Self {
         let mut vertex_ordering = Vec::with_capacity(total_plan_len);
         let mut produced_vars = HashSet::new();
@@ -881,26 +2353,36 @@ impl PartialCostPlan {
             produced_vars.insert(v);
         }
         Self {
-            vertex_ordering,
-            pattern_metadata: HashMap::new(),
+            vertex_ordering: VertexOrdering::default().with_step(vertex_ordering),
+            planned_patterns: PatternSet::default(),
+            pattern_metadata: PatternMetadataList::default(),
             all_produced_vars: produced_vars,
             cumulative_cost: Cost::NOOP,
-            remaining_patterns,
+            remaining_patterns: PatternSet::from_iter(remaining_patterns),
             ongoing_step: HashSet::new(),
             ongoing_step_stash: Vec::new(),
             ongoing_step_cost: Cost::NOOP,
             ongoing_step_produced_vars: HashSet::new(),
             ongoing_step_stash_produced_vars: HashSet::new(),
             ongoing_step_join_var: None,
+            ongoing_step_secondary_join_var: None,
+            compound_joins: Arc::new(HashMap::new()),
             heuristic: Cost::INFINITY,
+            completion_estimate,
         }
     }
 
+    fn available_variables(&self) -> impl Iterator<Item = VariableVertexId> + '_ {
+        let ordered = self.vertex_ordering.to_vec().into_iter().filter_map(|vertex| vertex.as_variable_id());
+        ordered.chain(chain(&self.ongoing_step_produced_vars, &self.ongoing_step_stash_produced_vars).copied())
+    }
+
     fn extensions_iter<'a>(
         &'a self,
         graph: &'a Graph<'_>,
     ) -> impl Iterator<Item = Result<StepExtension, QueryPlanningError>> + 'a {
-        let mut all_available_vars = self.vertex_ordering.clone();
+        let mut all_available_vars = self.vertex_ordering.to_vec();
+        let ordered_len = all_available_vars.len();
         all_available_vars.extend(
             chain(&self.ongoing_step_produced_vars, &self.ongoing_step_stash_produced_vars)
                 .map(|&var| VertexId::Variable(var)),
@@ -910,30 +2392,31 @@ impl PartialCostPlan {
             .iter()
             .filter({
                 let all_available_vars = all_available_vars.clone();
-                move |&&extension| {
+                move |&extension| {
                     let pattern_id = VertexId::Pattern(extension);
                     graph.elements[&pattern_id].is_valid(pattern_id, &all_available_vars, graph)
                 }
             })
-            .flat_map(move |&extension| {
-                let join_var = self.determine_joinability(graph, extension);
+            .flat_map(move |extension| {
+                let join = self.determine_joinability(graph, extension);
 
-                if join_var.is_none() {
-                    vec![(extension, join_var)].into_iter()
+                if join.is_none() {
+                    vec![(extension, None)].into_iter()
                 } else {
-                    vec![(extension, None), (extension, join_var)].into_iter()
+                    vec![(extension, None), (extension, join)].into_iter()
                 }
             })
-            .map(move |(extension, join_var)| {
+            .map(move |(extension, join)| {
                 let added_cost: Cost;
                 let meta_data: CostMetaData;
+                let join_var = join.map(|(join_var, _)| join_var);
+                let secondary_join_var = join.and_then(|(_, secondary_join_var)| secondary_join_var);
 
                 if join_var.is_none() {
-                    (added_cost, meta_data) =
-                        self.compute_added_cost(graph, extension, &all_available_vars, join_var)?;
+                    (added_cost, meta_data) = self.compute_added_cost(graph, extension, &all_available_vars, None)?;
                 } else {
-                    (added_cost, meta_data) =
-                        self.compute_added_cost(graph, extension, &self.vertex_ordering, join_var)?;
+                    let ordered_vars = &all_available_vars[..ordered_len];
+                    (added_cost, meta_data) = self.compute_added_cost(graph, extension, ordered_vars, join)?;
                 }
 
                 let mut cost_before_extension = self.cumulative_cost;
@@ -951,42 +2434,92 @@ impl PartialCostPlan {
                     pattern_metadata: meta_data,
                     step_cost: added_cost,
                     step_join_var: join_var,
+                    step_secondary_join_var: secondary_join_var,
                     heuristic,
                 })
             })
     }
 
-    pub(crate) fn extend_with(&self, graph: &Graph<'_>, extension: StepExtension) -> PartialCostPlan {
+    // The plan extended with each of its best k extensions (k = extension_width). A trivial extension is always taken,
+    // and on its own.
+    fn best_extensions(
+        &self,
+        graph: &Graph<'_>,
+        extension_width: usize,
+        extension_heap: &mut BinaryHeap<Reverse<StepExtension>>,
+    ) -> Result<Vec<PartialCostPlan>, QueryPlanningError> {
         const INDENT: &str = "";
-        if extension.is_trivial(graph) {
+        if tracing::enabled!(Level::TRACE) {
             event!(
                 Level::TRACE,
-                "{INDENT:12}Stash {:?} = {} <-- cost: {:?} heuristic: {:?}",
-                extension.pattern_id,
-                graph.elements[&VertexId::Pattern(extension.pattern_id)],
-                extension.step_cost.cost,
-                extension.heuristic
+                "{INDENT:8}PLAN: {:?} ONGOING: {:?} STASH: {:?} COST: {:?} + {:?} = {:?} HEURISTIC: {:?}",
+                self.vertex_ordering,
+                self.ongoing_step,
+                self.ongoing_step_stash,
+                self.cumulative_cost,
+                self.ongoing_step_cost,
+                self.cumulative_cost.chain(self.ongoing_step_cost),
+                self.heuristic
             );
+        }
+
+        debug_assert!(extension_heap.is_empty());
+        for extension in self.extensions_iter(graph) {
+            let extension = extension?;
+            if extension.is_trivial(graph) {
+                extension_heap.clear();
+                extension_heap.push(Reverse(extension));
+                break;
+            } else {
+                extension_heap.push(Reverse(extension));
+            }
+        }
+        let extended_plans = drain_sorted(extension_heap)
+            .take(extension_width)
+            .map(|Reverse(extension)| self.extend_with(graph, extension))
+            .collect();
+        Ok(extended_plans)
+    }
+
+    pub(crate) fn extend_with(&self, graph: &Graph<'_>, extension: StepExtension) -> PartialCostPlan {
+        const INDENT: &str = "";
+        // runs once per extension of every plan in the beam: the graph lookups are only made when tracing
+        let trace = tracing::enabled!(Level::TRACE);
+        if extension.is_trivial(graph) {
+            if trace {
+                event!(
+                    Level::TRACE,
+                    "{INDENT:12}Stash {:?} = {} <-- cost: {:?} heuristic: {:?}",
+                    extension.pattern_id,
+                    graph.elements[&VertexId::Pattern(extension.pattern_id)],
+                    extension.step_cost.cost,
+                    extension.heuristic
+                );
+            }
             let mut new_plan = self.clone();
             new_plan.add_to_stash(extension.pattern_id, graph);
             new_plan
         } else {
-            event!(
-                Level::TRACE,
-                "{INDENT:12}Choice {:?} = {} <-- join: {:?}, cost: {:?}, heuristic: {:?} metadata: {:?}",
-                extension.pattern_id,
-                graph.elements[&VertexId::Pattern(extension.pattern_id)],
-                extension
-                    .step_join_var
-                    .map(|v| graph.elements[&VertexId::Variable(v)].as_variable().unwrap().variable()),
-                extension.step_cost,
-                extension.heuristic,
-                extension.pattern_metadata
-            );
+            if trace {
+                event!(
+                    Level::TRACE,
+                    "{INDENT:12}Choice {:?} = {} <-- join: {:?}, cost: {:?}, heuristic: {:?} metadata: {:?}",
+                    extension.pattern_id,
+                    graph.elements[&VertexId::Pattern(extension.pattern_id)],
+                    extension
+                        .step_join_var
+                        .map(|v| graph.elements[&VertexId::Variable(v)].as_variable().unwrap().variable()),
+                    extension.step_cost,
+                    extension.heuristic,
+                    extension.pattern_metadata
+                );
+            }
             if !extension.is_constraint(graph) {
                 self.clone_and_extend_with_new_step(extension, graph)
             } else if extension.step_join_var.is_some()
-                && (self.ongoing_step_join_var.is_none() || self.ongoing_step_join_var == extension.step_join_var)
+                && (self.ongoing_step_join_var.is_none()
+                    || (self.ongoing_step_join_var == extension.step_join_var
+                        && self.ongoing_step_secondary_join_var == extension.step_secondary_join_var))
             {
                 self.clone_and_extend_with_continued_step(extension, graph)
             } else {
@@ -995,7 +2528,12 @@ impl PartialCostPlan {
         }
     }
 
-    fn determine_joinability(&self, graph: &Graph<'_>, pattern: PatternVertexId) -> Option<VariableVertexId> {
+    // Returns the join variable, and the secondary join variable if the pattern joins the ongoing step on both
+    fn determine_joinability(
+        &self,
+        graph: &Graph<'_>,
+        pattern: PatternVertexId,
+    ) -> Option<(VariableVertexId, Option<VariableVertexId>)> {
         let &prev_pattern = self.ongoing_step.iter().next()?;
         // We only join constraint patterns, so let's extract constraints
         let prev_planner = &graph.elements[&VertexId::Pattern(prev_pattern)];
@@ -1003,43 +2541,74 @@ impl PartialCostPlan {
         let planner = &graph.elements[&VertexId::Pattern(pattern)];
         let PlannerVertex::Constraint(constraint) = planner else { return None };
         // Determine whether there are any candidate join variables:
-        let candidate_join_var = constraint
+        let candidate_join_vars = constraint
             .variables()
             .filter(|var| self.ongoing_step_produced_vars.contains(var) && constraint.can_join_on(*var))
-            .exactly_one()
-            .ok()?;
+            .collect_vec();
         // Only direct-able patterns are join-able:
-        let Some(CostMetaData::Direction(prev_dir)) = self.pattern_metadata.get(&prev_pattern) else { return None };
-        // If no join var is set yet, only join when we are on the "non-inverted join var" of the previous constraint based on its direction
-        if (self.ongoing_step_join_var.is_none()
-            && Some(candidate_join_var)
-                == prev_constraint.join_from_direction_and_inputs(
-                    prev_dir,
+        let ongoing_step_len = self.ongoing_step.len() + self.ongoing_step_stash.len();
+        let Some(CostMetaData::Direction(prev_dir)) = self.pattern_metadata.get_recent(&prev_pattern, ongoing_step_len)
+        else {
+            return None;
+        };
+        let prev_join_var = || {
+            prev_constraint.join_from_direction_and_inputs(
+                prev_dir,
+                &self.ongoing_step_produced_vars,
+                &self.all_produced_vars,
+            )
+        };
+        match candidate_join_vars[..] {
+            [candidate_join_var] => {
+                // If no join var is set yet, only join when we are on the "non-inverted join var" of the previous constraint based on its direction
+                if (self.ongoing_step_join_var.is_none() && Some(candidate_join_var) == prev_join_var())
+                    || (self.ongoing_step_join_var == Some(candidate_join_var)
+                        && self.ongoing_step_secondary_join_var.is_none())
+                {
+                    return Some((candidate_join_var, None));
+                }
+                None
+            }
+            [_, _] => {
+                // Both constraints must lead their tuples with the same pair of variables, in the same order
+                let (join_var, secondary_join_var) = prev_constraint.join_key_from_direction(prev_dir)?;
+                let dir = constraint.direction_from_join_var(
+                    join_var,
                     &self.ongoing_step_produced_vars,
                     &self.all_produced_vars,
-                ))
-            || self.ongoing_step_join_var == Some(candidate_join_var)
-        {
-            return Some(candidate_join_var);
+                )?;
+                if constraint.join_key_from_direction(&dir) != Some((join_var, secondary_join_var)) {
+                    return None;
+                }
+                if (self.ongoing_step_join_var.is_none() && Some(join_var) == prev_join_var())
+                    || (self.ongoing_step_join_var == Some(join_var)
+                        && self.ongoing_step_secondary_join_var == Some(secondary_join_var))
+                {
+                    return Some((join_var, Some(secondary_join_var)));
+                }
+                None
+            }
+            _ => None,
         }
-        None
     }
 
+    // When joining, the inputs are the decided ordering only, excluding the variables of the ongoing step
     fn compute_added_cost(
         &self,
         graph: &Graph<'_>,
         pattern: PatternVertexId,
         input_vars: &[VertexId],
-        join_var: Option<VariableVertexId>,
+        join: Option<(VariableVertexId, Option<VariableVertexId>)>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
         let planner = &graph.elements[&VertexId::Pattern(pattern)];
+        let join_size = |var: VariableVertexId| {
+            graph.elements[&VertexId::Variable(var)].as_variable().unwrap().restricted_expected_output_size(input_vars)
+        };
         let (updated_cost, extension_metadata) = match planner {
             PlannerVertex::Constraint(constraint) => {
-                if let Some(join_var) = join_var {
-                    let total_join_size = graph.elements[&VertexId::Variable(join_var)]
-                        .as_variable()
-                        .unwrap()
-                        .restricted_expected_output_size(&self.vertex_ordering);
+                if let Some((join_var, secondary_join_var)) = join {
+                    // a compound join matches on pairs of values, of which there are at most the product of both sizes
+                    let total_join_size = join_size(join_var) * secondary_join_var.map_or(1.0, join_size);
                     let fixed_direction = constraint.direction_from_join_var(
                         join_var,
                         &self.ongoing_step_produced_vars,
@@ -1068,17 +2637,18 @@ impl PartialCostPlan {
                     .variables()
                     .filter(|v| !self.ongoing_step_produced_vars.contains(v) && !self.all_produced_vars.contains(v))
                     .count();
-            let cost_estimate = AVERAGE_STEP_COST
+            let CompletionEstimate { average_step_cost, average_output_size } = self.completion_estimate;
+            let cost_estimate = average_step_cost
                 * (num_remaining as f64)
                 * (1.0 - VARIABLE_PRODUCTION_ADVANTAGE).powi(num_produced_vars as i32);
-            Cost { cost: cost_estimate, io_ratio: AVERAGE_QUERY_OUTPUT_SIZE }
+            Cost { cost: cost_estimate, io_ratio: average_output_size }
         }
     }
 
     fn add_to_stash(&mut self, pattern: PatternVertexId, graph: &Graph<'_>) {
         self.ongoing_step_stash.push(pattern);
-        self.remaining_patterns.remove(&pattern);
-        self.pattern_metadata.insert(pattern, CostMetaData::None);
+        self.remaining_patterns.remove(pattern);
+        self.pattern_metadata = self.pattern_metadata.with(pattern, CostMetaData::None);
         self.ongoing_step_stash_produced_vars.extend(graph.elements[&VertexId::Pattern(pattern)].variables());
     }
 
@@ -1090,19 +2660,28 @@ impl PartialCostPlan {
             debug_assert!(!self.vertex_ordering.contains(&VertexId::Pattern(pattern)));
         }
         if let Some(join_var) = self.ongoing_step_join_var {
+            // lowering intersects the producers of the join variable, which must be all the patterns of the step
+            debug_assert!(self
+                .ongoing_step
+                .iter()
+                .all(|pattern| graph.pattern_to_variable[pattern].contains(&join_var)));
             current_step.push(VertexId::Variable(join_var));
-            for var in self.ongoing_step_produced_vars.clone() {
-                if var != join_var && !self.vertex_ordering.contains(&VertexId::Variable(var)) {
-                    current_step.push(VertexId::Variable(var));
-                }
+            if let Some(secondary_join_var) = self.ongoing_step_secondary_join_var {
+                current_step.push(VertexId::Variable(secondary_join_var));
             }
-        } else {
             for var in self.ongoing_step_produced_vars.clone() {
-                if !self.vertex_ordering.contains(&VertexId::Variable(var)) {
+                if var != join_var && Some(var) != self.ongoing_step_secondary_join_var {
                     current_step.push(VertexId::Variable(var));
                 }
             }
+        } else {
+            current_step.extend(self.ongoing_step_produced_vars.iter().map(|&var| VertexId::Variable(var)));
         }
+        // the variables of the ongoing step were not produced before it, so they are not in the decided ordering
+        debug_assert!(self
+            .ongoing_step_produced_vars
+            .iter()
+            .all(|&var| !self.vertex_ordering.contains(&VertexId::Variable(var))));
         for &pattern in self.ongoing_step_stash.iter() {
             current_step.push(VertexId::Pattern(pattern));
             for var in graph.elements[&VertexId::Pattern(pattern)].variables() {
@@ -1120,11 +2699,10 @@ impl PartialCostPlan {
         let mut new_ongoing_step = self.ongoing_step.clone();
         new_ongoing_step.insert(extension.pattern_id);
 
-        let mut new_pattern_metadata = self.pattern_metadata.clone();
-        new_pattern_metadata.insert(extension.pattern_id, extension.pattern_metadata);
+        let new_pattern_metadata = self.pattern_metadata.with(extension.pattern_id, extension.pattern_metadata);
 
         let mut new_remaining_patterns = self.remaining_patterns.clone();
-        new_remaining_patterns.remove(&extension.pattern_id);
+        new_remaining_patterns.remove(extension.pattern_id);
 
         let mut new_ongoing_produced_vars = self.ongoing_step_produced_vars.clone();
         new_ongoing_produced_vars.extend(
@@ -1138,6 +2716,7 @@ impl PartialCostPlan {
 
         PartialCostPlan {
             vertex_ordering: self.vertex_ordering.clone(),
+            planned_patterns: self.planned_patterns.clone(),
             pattern_metadata: new_pattern_metadata,
             remaining_patterns: new_remaining_patterns,
             cumulative_cost: self.cumulative_cost,
@@ -1147,16 +2726,22 @@ impl PartialCostPlan {
             ongoing_step_produced_vars: new_ongoing_produced_vars,
             ongoing_step_stash_produced_vars: self.ongoing_step_stash_produced_vars.clone(),
             ongoing_step_join_var: extension.step_join_var,
+            ongoing_step_secondary_join_var: extension.step_secondary_join_var,
+            compound_joins: self.compound_joins.clone(),
             heuristic: extension.heuristic,
             all_produced_vars: new_produced_vars,
+            completion_estimate: self.completion_estimate,
         }
     }
 
     fn clone_and_extend_with_new_step(&self, extension: StepExtension, graph: &Graph<'_>) -> PartialCostPlan {
         // First finalize the current step
-        let mut new_vertex_ordering = self.vertex_ordering.clone();
         let (current_step, current_stash_produced_vars) = self.finalize_current_step(graph);
-        new_vertex_ordering.extend(current_step);
+        let mut new_planned_patterns = self.planned_patterns.clone();
+        current_step.iter().filter_map(VertexId::as_pattern_id).for_each(|pattern| {
+            new_planned_patterns.insert(pattern);
+        });
+        let new_vertex_ordering = self.vertex_ordering.with_step(current_step);
 
         let new_cumulative_cost = self
             .cumulative_cost
@@ -1167,11 +2752,10 @@ impl PartialCostPlan {
         let mut new_ongoing_step = HashSet::new();
         new_ongoing_step.insert(extension.pattern_id);
 
-        let mut new_pattern_metadata = self.pattern_metadata.clone();
-        new_pattern_metadata.insert(extension.pattern_id, extension.pattern_metadata);
+        let new_pattern_metadata = self.pattern_metadata.with(extension.pattern_id, extension.pattern_metadata);
 
         let mut new_remaining_patterns = self.remaining_patterns.clone();
-        new_remaining_patterns.remove(&extension.pattern_id);
+        new_remaining_patterns.remove(extension.pattern_id);
 
         let mut new_ongoing_produced_vars = HashSet::new();
         new_ongoing_produced_vars.extend(
@@ -1186,6 +2770,7 @@ impl PartialCostPlan {
 
         PartialCostPlan {
             vertex_ordering: new_vertex_ordering,
+            planned_patterns: new_planned_patterns,
             cumulative_cost: new_cumulative_cost,
             ongoing_step: new_ongoing_step,
             ongoing_step_stash: Vec::new(),
@@ -1193,40 +2778,127 @@ impl PartialCostPlan {
             ongoing_step_produced_vars: new_ongoing_produced_vars,
             ongoing_step_stash_produced_vars: HashSet::new(),
             ongoing_step_join_var: None,
+            ongoing_step_secondary_join_var: None,
+            compound_joins: self.compound_joins_including_current_step(),
             all_produced_vars: new_produced_vars,
             pattern_metadata: new_pattern_metadata,
             remaining_patterns: new_remaining_patterns,
             heuristic: extension.heuristic,
+            completion_estimate: self.completion_estimate,
         }
     }
 
     fn into_complete_plan(self, graph: &Graph<'_>) -> CompleteCostPlan {
-        let mut final_vertex_ordering = self.vertex_ordering.clone();
+        let mut final_vertex_ordering = self.vertex_ordering.to_vec();
         let (new_step, _stash_produced_vars) = self.finalize_current_step(graph);
         final_vertex_ordering.extend(new_step);
 
-        let final_cumulative_cost = self
-            .cumulative_cost
-            .chain(self.ongoing_step_cost)
-            .chain(Cost { cost: (self.ongoing_step_stash.len() as f64) * Cost::TRIVIAL_COST, io_ratio: 1.0 });
-
         CompleteCostPlan {
             vertex_ordering: final_vertex_ordering,
-            pattern_metadata: self.pattern_metadata.clone(),
-            cumulative_cost: final_cumulative_cost,
+            pattern_metadata: self.pattern_metadata.to_map(),
+            compound_joins: HashMap::clone(&self.compound_joins_including_current_step()),
+            cumulative_cost: self.completed_cost(),
+        }
+    }
+
+    // The cost of the plan once the ongoing step is finished
+    fn completed_cost(&self) -> Cost {
+        self.cumulative_cost
+            .chain(self.ongoing_step_cost)
+            .chain(Cost { cost: (self.ongoing_step_stash.len() as f64) * Cost::TRIVIAL_COST, io_ratio: 1.0 })
+    }
+
+    fn compound_joins_including_current_step(&self) -> Arc<HashMap<VariableVertexId, VariableVertexId>> {
+        match (self.ongoing_step_join_var, self.ongoing_step_secondary_join_var) {
+            (Some(join_var), Some(secondary_join_var)) => {
+                let mut compound_joins = HashMap::clone(&self.compound_joins);
+                compound_joins.insert(join_var, secondary_join_var);
+                Arc::new(compound_joins)
+            }
+            _ => self.compound_joins.clone(),
+        }
+    }
+
+    // The cost of everything planned so far, including the ongoing step
+    fn committed_cost(&self) -> Cost {
+        self.cumulative_cost.chain(self.ongoing_step_cost)
+    }
+
+    // A lower bound on the cost of every completion of the plan. Each pattern left to plan costs at least its own lower
+    // bound, or its share of a step joining it, which costs at least two seeks and joins at most all the joinable
+    // patterns. Its cost is chained after the io ratios of the steps before it, which are at least the lower bounds of
+    // the io ratios of the other patterns, divided by the size of any join (clipped to 1: larger ratios only add cost).
+    fn completion_lower_bound(&self, lower_bounds: &HashMap<PatternVertexId, PatternLowerBound>) -> f64 {
+        let remaining = self.remaining_patterns.iter().map(|pattern| lower_bounds[&pattern]).collect_vec();
+        let num_joinable = remaining.iter().filter(|bound| bound.join_size.is_some()).count();
+        let max_step_len = usize::max(self.ongoing_step.len() + num_joinable, 1) as f64;
+        let min_join_share = 2.0 * SEEK_ITERATOR_RELATIVE_COST / max_step_len;
+
+        let ongoing_step_cost = if num_joinable > 0 && !self.ongoing_step.is_empty() {
+            f64::min(self.ongoing_step_cost.cost, min_join_share * self.ongoing_step.len() as f64)
+        } else {
+            self.ongoing_step_cost.cost
+        };
+        // the product of the io ratios of all remaining patterns but one, from the products before and after it
+        let io_ratios = remaining.iter().map(PatternLowerBound::step_io_ratio).collect_vec();
+        let mut io_ratios_after = vec![1.0; remaining.len() + 1];
+        for (i, io_ratio) in io_ratios.iter().enumerate().rev() {
+            io_ratios_after[i] = io_ratio * io_ratios_after[i + 1];
+        }
+        let mut io_ratios_before = 1.0;
+        let mut remaining_cost = 0.0;
+        for (i, bound) in remaining.iter().enumerate() {
+            let mut share = bound.cost;
+            if bound.can_be_trivial {
+                share = f64::min(share, Cost::TRIVIAL_COST);
+            }
+            if bound.join_size.is_some() {
+                share = f64::min(share, min_join_share);
+            }
+            remaining_cost += share * io_ratios_before * io_ratios_after[i + 1];
+            io_ratios_before *= io_ratios[i];
         }
+        let stash_cost = (self.ongoing_step_stash.len() as f64) * Cost::TRIVIAL_COST * io_ratios_after[0];
+
+        let ongoing_io_ratio = f64::min(self.ongoing_step_cost.io_ratio, 1.0);
+        self.cumulative_cost.cost
+            + self.cumulative_cost.io_ratio * (ongoing_step_cost + ongoing_io_ratio * (stash_cost + remaining_cost))
+    }
+
+    fn astar_state_key(&self) -> AStarStateKey {
+        (self.hash(), self.ongoing_step_stash.iter().copied().sorted().collect())
+    }
+
+    // Plans in the same state are completed by the same extensions, at the same cost on top of their own. The
+    // ongoing step may still be joined, so it must be the same step for that cost to be the same.
+    fn is_dominated_by(&self, other: &PartialCostPlan) -> bool {
+        let ongoing_step_len = self.ongoing_step.len() + self.ongoing_step_stash.len();
+        other.cumulative_cost.cost <= self.cumulative_cost.cost
+            && other.cumulative_cost.io_ratio <= self.cumulative_cost.io_ratio
+            && other.ongoing_step_cost == self.ongoing_step_cost
+            && self.ongoing_step.iter().all(|pattern| {
+                self.pattern_metadata.get_recent(pattern, ongoing_step_len)
+                    == other.pattern_metadata.get_recent(pattern, ongoing_step_len)
+            })
     }
 
     fn hash(&self) -> PartialPlanHash {
         PartialPlanHash {
             n_remaining_patterns: self.remaining_patterns.len() as u32,
-            planned_patterns: self.vertex_ordering.iter().filter_map(|v| v.as_pattern_id()).collect::<BTreeSet<_>>(),
+            planned_patterns: self.planned_patterns.clone(),
             ongoing_step_join_var: self.ongoing_step_join_var,
+            ongoing_step_secondary_join_var: self.ongoing_step_secondary_join_var,
             ongoing_non_trivial_patterns: self.ongoing_step.iter().copied().collect::<BTreeSet<_>>(),
         }
     }
 }
 
+impl PartialEq for PartialCostPlan {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
 impl Eq for PartialCostPlan {}
 
 impl PartialOrd for PartialCostPlan {
@@ -1236,17 +2908,69 @@ impl PartialOrd for PartialCostPlan {
 }
 
 impl Ord for PartialCostPlan {
+    // Plans that tie on cost are ordered by what they planned, so that the chosen plan does not depend on the
+    // iteration order of the hash sets the candidates were generated from.
     fn cmp(&self, other: &Self) -> Ordering {
-        self.heuristic.cost.partial_cmp(&other.heuristic.cost).unwrap_or(Ordering::Greater)
+        (self.heuristic.cost.total_cmp(&other.heuristic.cost))
+            .then_with(|| self.heuristic.io_ratio.total_cmp(&other.heuristic.io_ratio))
+            .then_with(|| self.vertex_ordering.cmp(&other.vertex_ordering))
+            .then_with(|| self.ongoing_step.iter().sorted().cmp(other.ongoing_step.iter().sorted()))
+            .then_with(|| self.ongoing_step_stash.cmp(&other.ongoing_step_stash))
+            .then_with(|| self.ongoing_step_join_var.cmp(&other.ongoing_step_join_var))
+            .then_with(|| self.ongoing_step_secondary_join_var.cmp(&other.ongoing_step_secondary_join_var))
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub(super) struct PartialPlanHash {
     n_remaining_patterns: u32, // Needed for continuous search (A*), but not step-based (beam)
-    planned_patterns: BTreeSet<PatternVertexId>,
+    planned_patterns: PatternSet,
     ongoing_non_trivial_patterns: BTreeSet<PatternVertexId>,
     ongoing_step_join_var: Option<VariableVertexId>,
+    ongoing_step_secondary_join_var: Option<VariableVertexId>,
+}
+
+// The partial plan hash does not tell apart which of the patterns that are not planned yet were stashed
+type AStarStateKey = (PartialPlanHash, Vec<PatternVertexId>);
+
+struct AStarEntry {
+    bound: f64,
+    plan: PartialCostPlan,
+}
+
+impl PartialEq for AStarEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AStarEntry {}
+
+impl PartialOrd for AStarEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AStarEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bound.total_cmp(&other.bound).then_with(|| self.plan.cmp(&other.plan))
+    }
+}
+
+/// The lowest cost and io ratio a pattern may be planned with, and the largest join it may take part in.
+#[derive(Clone, Copy, Debug)]
+struct PatternLowerBound {
+    cost: f64,
+    io_ratio: f64,
+    can_be_trivial: bool,
+    join_size: Option<f64>,
+}
+
+impl PatternLowerBound {
+    fn step_io_ratio(&self) -> f64 {
+        f64::min(self.io_ratio, 1.0) / self.join_size.unwrap_or(1.0)
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -1255,6 +2979,7 @@ pub(super) struct StepExtension {
     pattern_metadata: CostMetaData,
     step_cost: Cost,
     step_join_var: Option<VariableVertexId>,
+    step_secondary_join_var: Option<VariableVertexId>,
     heuristic: Cost,
 }
 
@@ -1278,8 +3003,10 @@ impl PartialOrd for StepExtension {
 
 impl Ord for StepExtension {
     fn cmp(&self, other: &Self) -> Ordering {
-        (self.heuristic.cost.partial_cmp(&other.heuristic.cost).unwrap_or(Ordering::Equal))
+        (self.heuristic.cost.total_cmp(&other.heuristic.cost))
             .then_with(|| self.pattern_id.cmp(&other.pattern_id))
+            .then_with(|| self.step_join_var.cmp(&other.step_join_var))
+            .then_with(|| self.step_secondary_join_var.cmp(&other.step_secondary_join_var))
     }
 }
 
@@ -1290,8 +3017,14 @@ pub(crate) struct ConjunctionPlan<'a> {
     local_annotations: &'a TypeAnnotations,
     ordering: Vec<VertexId>,
     metadata: HashMap<PatternVertexId, CostMetaData>,
+    compound_joins: HashMap<VariableVertexId, VariableVertexId>, // join variable to its secondary join variable
     element_to_order: HashMap<VertexId, usize>,
     pub(crate) planner_statistics: PlannerStatistics,
+    planning_notes: Vec<String>,            // explains patterns left out of the plan
+    anonymous_relations: HashSet<Variable>, // counted towards the multiplicity of answers, but never materialised
+    negation_semantics: ResultSemantics,
+    single_instruction_fast_path: bool,
+    interleave_branches: bool,
 }
 
 impl fmt::Debug for ConjunctionPlan<'_> {
@@ -1300,6 +3033,7 @@ impl fmt::Debug for ConjunctionPlan<'_> {
             .field("shared_variables", &self.shared_variables)
             .field("graph", &self.graph)
             .field("ordering", &self.ordering)
+            .field("planning_notes", &self.planning_notes)
             .finish()
     }
 }
@@ -1311,22 +3045,26 @@ impl ConjunctionPlan<'_> {
         input_variables: impl IntoIterator<Item = Variable> + Clone,
         selected_variables: impl IntoIterator<Item = Variable> + Clone,
         already_assigned_positions: &HashMap<Variable, ExecutorVariable>,
+        reserved_positions: u32,
         variable_registry: &VariableRegistry,
         branch_id: Option<BranchID>,
+        result_semantics: ResultSemantics,
     ) -> Result<MatchExecutableBuilder, QueryPlanningError> {
         let mut match_builder = MatchExecutableBuilder::new(
             branch_id,
             already_assigned_positions,
+            reserved_positions,
             selected_variables.clone().into_iter().collect(),
             input_variables.clone().into_iter().collect(),
             self.planner_statistics,
+            result_semantics,
+            self.single_instruction_fast_path,
         );
-        self.may_make_input_check_step(
-            &mut match_builder,
-            input_variables.into_iter(),
-            input_variable_annotations,
-            variable_registry,
-        );
+        for &relation in &self.anonymous_relations {
+            match_builder.count_multiplicity_of(relation);
+        }
+        let input_checks = self.required_input_checks(input_variables.into_iter(), input_variable_annotations);
+        self.may_make_input_check_step(&mut match_builder, input_checks, variable_registry);
         for &index in &self.ordering {
             match index {
                 VertexId::Variable(var) => {
@@ -1355,6 +3093,8 @@ impl ConjunctionPlan<'_> {
                         }
                     }
                     if self.outputs_of_pattern(pattern).next().is_none() {
+                        let vertex = &self.graph.elements()[&VertexId::Pattern(pattern)];
+                        match_builder.set_lowered_pattern(vertex.planner_name(), vertex.source_spans());
                         self.may_make_check_step(&mut match_builder, pattern, variable_registry)?;
                     }
                 }
@@ -1411,9 +3151,14 @@ impl ConjunctionPlan<'_> {
             return Ok(());
         }
 
+        // The producers of a variable are exactly the patterns the planner put in its step, and all have the variable.
+        // Whatever step is still open was built for earlier variables, so they must not be intersected with it.
+        match_builder.finish_one();
         let is_join = self.producers_of_var(var).nth(1).is_some();
         for producer in self.producers_of_var(var) {
-            match &self.graph.elements()[&VertexId::Pattern(producer)] {
+            let vertex = &self.graph.elements()[&VertexId::Pattern(producer)];
+            match_builder.set_lowered_pattern(vertex.planner_name(), vertex.source_spans());
+            match vertex {
                 PlannerVertex::Variable(_) => unreachable!("encountered variable @ pattern id {producer:?}"),
                 PlannerVertex::Negation(_) => unreachable!("encountered negation registered as producing variable"),
                 PlannerVertex::LinksDeduplication(_) => {
@@ -1437,7 +3182,7 @@ impl ConjunctionPlan<'_> {
                     let inputs =
                         self.inputs_of_pattern(producer).map(|var| self.graph.index_to_variable[&var]).collect_vec();
                     let sort_variable = is_join.then_some(variable); // otherwise use metadata
-                    self.lower_constraint(match_builder, constraint, self.metadata[&producer], inputs, sort_variable)
+                    self.lower_constraint(match_builder, constraint, self.metadata[&producer], inputs, sort_variable)?
                 }
                 PlannerVertex::Expression(expression) => {
                     let output = match_builder.position_mapping()[&self.graph.index_to_variable[&expression.output]];
@@ -1466,6 +3211,8 @@ impl ConjunctionPlan<'_> {
                             match_builder.current_outputs.iter().copied(),
                             match_builder.position_mapping(),
                             variable_registry,
+                            match_builder.result_semantics,
+                            self.interleave_branches,
                         )?;
                     let variable_positions =
                         step_builder.branches.iter().flat_map(|x| x.index.iter().map(|(&k, &v)| (k, v))).collect();
@@ -1494,6 +3241,9 @@ impl ConjunctionPlan<'_> {
                 }
             }
         }
+        if let Some(secondary_join_var) = self.compound_joins.get(&var) {
+            match_builder.set_secondary_sort_variable(self.graph.index_to_variable[secondary_join_var]);
+        }
         match_builder.finish_one();
         Ok(())
     }
@@ -1537,8 +3287,10 @@ impl ConjunctionPlan<'_> {
                     match_builder.row_variables().iter().copied(),
                     match_builder.selected_variables.iter().copied(),
                     match_builder.position_mapping(),
+                    0,
                     variable_registry,
                     None,
+                    self.negation_semantics,
                 )?;
                 let variable_positions: HashMap<Variable, ExecutorVariable> = negation
                     .index
@@ -1563,13 +3315,20 @@ impl ConjunctionPlan<'_> {
                 let player1 = deduplication.links_deduplication().links1().player().as_variable().unwrap();
                 let role2 = deduplication.links_deduplication().links2().role_type().as_variable().unwrap();
                 let player2 = deduplication.links_deduplication().links2().player().as_variable().unwrap();
+                // Deduplication distinguishes answers, not just their multiplicities, so it may only be dropped under
+                // exists semantics when the two role players can never coincide.
+                if match_builder.result_semantics == ResultSemantics::Exists
+                    && (self.are_disjointly_annotated(role1, role2) || self.are_disjointly_annotated(player1, player2))
+                {
+                    return Ok(());
+                }
                 let check = CheckInstruction::LinksDeduplication { role1, player1, role2, player2 }
                     .map(match_builder.position_mapping());
                 match_builder.push_check(&[role1, player1, role2, player2], check)
             }
 
-            PlannerVertex::Comparison(comparison) => {
-                let comparison = comparison.comparison();
+            PlannerVertex::Comparison(comparison_planner) => {
+                let comparison = comparison_planner.comparison();
                 let lhs = comparison.lhs();
                 let rhs = comparison.rhs();
                 let comparator = comparison.comparator();
@@ -1595,6 +3354,7 @@ impl ConjunctionPlan<'_> {
                     lhs: CheckVertex::resolve(lhs_pos, self.local_annotations),
                     rhs: CheckVertex::resolve(rhs_pos, self.local_annotations),
                     comparator,
+                    matcher: comparison_planner.matcher().cloned(),
                 };
 
                 let vars = [lhs_var, rhs_var].into_iter().flatten().collect_vec();
@@ -1620,6 +3380,8 @@ impl ConjunctionPlan<'_> {
                         match_builder.current_outputs.iter().copied(),
                         match_builder.position_mapping(),
                         variable_registry,
+                        match_builder.result_semantics,
+                        self.interleave_branches,
                     )?;
                 let variable_positions = step_builder.branches.iter().flat_map(|x| x.index.clone()).collect();
                 match_builder.push_step(&variable_positions, StepInstructionsBuilder::Disjunction(step_builder).into())
@@ -1628,6 +3390,14 @@ impl ConjunctionPlan<'_> {
         Ok(())
     }
 
+    fn are_disjointly_annotated(&self, lhs: Variable, rhs: Variable) -> bool {
+        let annotations = |var| self.local_annotations.vertex_annotations_of(&Vertex::Variable(var));
+        match (annotations(lhs), annotations(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.is_disjoint(rhs),
+            _ => false,
+        }
+    }
+
     fn lower_constraint(
         &self,
         match_builder: &mut MatchExecutableBuilder,
@@ -1635,65 +3405,20 @@ impl ConjunctionPlan<'_> {
         metadata: CostMetaData,
         inputs: Vec<Variable>,
         sort_variable: Option<Variable>,
-    ) {
-        if let Some(StepBuilder {
-            builder:
-                StepInstructionsBuilder::Intersection(IntersectionBuilder { sort_variable: Some(sort_variable), .. }),
-            ..
-        }) = match_builder.current.as_deref()
-        {
-            if !constraint.variables().contains(&self.graph.variable_index[sort_variable]) {
-                match_builder.finish_one();
-                event!(Level::WARN, "Ignoring planned join (incompatible join variables found)");
-            }
-        }
-
-        macro_rules! binary {
-            ($((with $with:ident))? $lhs:ident $con:ident $rhs:ident, $fw:ident($fwi:ident), $bw:ident($bwi:ident)) => {{
-                let lhs_var = $con.$lhs().as_variable();
-                let rhs_var = $con.$rhs().as_variable();
-
-                let lhs_input = lhs_var.filter(|lhs| inputs.contains(&lhs));
-                let rhs_input = rhs_var.filter(|rhs| inputs.contains(&rhs));
-
-                let inputs = match (lhs_input, rhs_input) {
-                    (Some(lhs), Some(rhs)) => Inputs::Dual([lhs, rhs]), // useful for links
-                    (Some(var), None) | (None, Some(var)) => Inputs::Single([var]),
-                    (None, None) => Inputs::None([]),
-                };
-
-                let direction = if matches!(inputs, Inputs::None([])) {
-                    let CostMetaData::Direction(unbound_direction) = metadata else {
-                        unreachable!("expected metadata for constraint")
-                    };
-                    unbound_direction
-                } else if rhs_var.is_some_and(|rhs| inputs.contains(rhs)) {
-                    Direction::Reverse
-                } else {
-                    Direction::Canonical
-                };
-
-                let con = $con.clone();
-                let instruction = match direction {
-                    Direction::Canonical => ConstraintInstruction::$fw($fwi::new(con, inputs, self.local_annotations)),
-                    Direction::Reverse => ConstraintInstruction::$bw($bwi::new(con, inputs, self.local_annotations)),
-                };
-
-                let lhs_produced = lhs_var.xor(lhs_input);
-                let rhs_produced = rhs_var.xor(rhs_input);
-
-                #[allow(unused)]
-                let mut tag: Option<Variable> = None;
-                $(tag = $con.$with().as_variable();)?
-
-                let sort_variable = sort_variable.or_else(|| match direction {
-                    Direction::Canonical => lhs_produced.or(rhs_produced),
-                    Direction::Reverse => rhs_produced.or(lhs_produced),
-                }.or(tag)).unwrap();
-
-                match_builder.push_instruction(sort_variable, instruction);
-            }};
-        }
+    ) -> Result<(), QueryPlanningError> {
+        debug_assert!(
+            !matches!(
+                match_builder.current.as_deref(),
+                Some(StepBuilder {
+                    builder: StepInstructionsBuilder::Intersection(IntersectionBuilder {
+                        sort_variable: Some(sort_variable),
+                        ..
+                    }),
+                    ..
+                }) if !constraint.variables().contains(&self.graph.variable_index[sort_variable])
+            ),
+            "the ongoing intersection is sorted on a variable the constraint does not have"
+        );
 
         match constraint {
             ConstraintVertex::TypeList(type_list) => {
@@ -1709,35 +3434,34 @@ impl ConjunctionPlan<'_> {
                 match_builder.push_instruction(var, instruction);
             }
 
-            ConstraintVertex::Sub(planner) => {
-                let sub = planner.sub();
-                binary!(subtype sub supertype, Sub(SubInstruction), SubReverse(SubReverseInstruction))
-            }
-            ConstraintVertex::Owns(planner) => {
-                let owns = planner.owns();
-                binary!(owner owns attribute, Owns(OwnsInstruction), OwnsReverse(OwnsReverseInstruction))
-            }
-            ConstraintVertex::Relates(planner) => {
-                let relates = planner.relates();
-                binary!(relation relates role_type, Relates(RelatesInstruction), RelatesReverse(RelatesReverseInstruction))
-            }
-            ConstraintVertex::Plays(planner) => {
-                let plays = planner.plays();
-                binary!(player plays role_type, Plays(PlaysInstruction), PlaysReverse(PlaysReverseInstruction))
-            }
-
-            ConstraintVertex::Isa(planner) => {
-                let isa = planner.isa();
-                binary!(thing isa type_, Isa(IsaInstruction), IsaReverse(IsaReverseInstruction))
-            }
-            ConstraintVertex::Has(planner) => {
-                let has = planner.has();
-                binary!(owner has attribute, Has(HasInstruction), HasReverse(HasReverseInstruction))
+            ConstraintVertex::Sub(_)
+            | ConstraintVertex::Owns(_)
+            | ConstraintVertex::Relates(_)
+            | ConstraintVertex::Plays(_)
+            | ConstraintVertex::Isa(_)
+            | ConstraintVertex::Has(_) => {
+                let mode = BinaryLoweringMode::Produce { metadata, inputs: &inputs, sort_variable };
+                self.lower_binary_constraint(match_builder, constraint, mode)
             }
             ConstraintVertex::Links(planner) => {
                 let links = planner.links();
-                // binary!() works here even though links is ostensibly ternary
-                binary!((with role_type) relation links player, Links(LinksInstruction), LinksReverse(LinksReverseInstruction))
+                let relation = links.relation().as_variable().unwrap();
+                let player = links.player().as_variable().unwrap();
+                if inputs.contains(&relation) && inputs.contains(&player) {
+                    // either direction only verifies the edge: let the executor pick the cheaper side per row
+                    let instruction = BoundLinksInstruction::new(
+                        links.clone(),
+                        relation,
+                        player,
+                        planner.bound_check_hint(),
+                        self.local_annotations,
+                    );
+                    let sort_variable = sort_variable.unwrap_or(links.role_type().as_variable().unwrap());
+                    match_builder.push_instruction(sort_variable, ConstraintInstruction::BoundLinks(instruction));
+                } else {
+                    let mode = BinaryLoweringMode::Produce { metadata, inputs: &inputs, sort_variable };
+                    self.lower_binary_constraint(match_builder, constraint, mode)
+                }
             }
             ConstraintVertex::IndexedRelation(planner) => {
                 assert_ne!(inputs.len(), 5);
@@ -1747,10 +3471,14 @@ impl ConjunctionPlan<'_> {
                 let player_1_role = planner.indexed_relation().role_type_1().as_variable().unwrap();
                 let player_2_role = planner.indexed_relation().role_type_2().as_variable().unwrap();
 
+                let constraint = Constraint::from(planner.indexed_relation().clone());
                 let annotations = self
                     .local_annotations
-                    .constraint_annotations_of(planner.indexed_relation().clone().into())
-                    .unwrap()
+                    .constraint_annotations_of(constraint.clone())
+                    .ok_or_else(|| QueryPlanningError::MissingConstraintAnnotations {
+                        constraint: constraint.to_string(),
+                        source_span: constraint.source_span(),
+                    })?
                     .as_indexed_relation();
                 let array_inputs = Inputs::build_from(&inputs);
 
@@ -1823,47 +3551,63 @@ impl ConjunctionPlan<'_> {
                 match_builder.push_instruction(sort_variable, instruction);
             }
         }
+        Ok(())
     }
 
-    fn lower_constraint_check(&self, match_builder: &mut MatchExecutableBuilder, constraint: &ConstraintVertex<'_>) {
+    /// Lowers a constraint between two vertices, and possibly a tag such as the role of a `links`, either to the
+    /// instruction producing the unbound ones or to a check once all of them are bound.
+    fn lower_binary_constraint(
+        &self,
+        match_builder: &mut MatchExecutableBuilder,
+        constraint: &ConstraintVertex<'_>,
+        mode: BinaryLoweringMode<'_>,
+    ) {
         macro_rules! binary {
-            ($((with $with:ident))? $lhs:ident $con:ident $rhs:ident, $fw:ident($fwi:ident), $bw:ident($bwi:ident)) => {{
-                let lhs = $con.$lhs();
-                let rhs = $con.$rhs();
-
-                let lhs_var = lhs.as_variable();
-                let rhs_var = rhs.as_variable();
-
-                let num_input_variables = [lhs_var, rhs_var].into_iter().filter(|x| x.is_some()).count();
-
-                assert!(num_input_variables > 0);
-
-                let lhs_pos = lhs.clone().map(match_builder.position_mapping());
-                let rhs_pos = rhs.clone().map(match_builder.position_mapping());
-                let check = CheckInstruction::$fw {
-                    $lhs: CheckVertex::resolve(lhs_pos, self.local_annotations),
-                    $rhs: CheckVertex::resolve(rhs_pos, self.local_annotations),
-                    $($with: $con.$with(),)?
+            (
+                $((with $with:ident))? $((tag $tag_field:ident: $tag:ident))?
+                $lhs:ident $con:ident $rhs:ident, $fw:ident($fwi:ident), $bw:ident($bwi:ident)
+            ) => {{
+                let lowering = BinaryConstraintLowering {
+                    lhs: $con.$lhs().as_variable(),
+                    rhs: $con.$rhs().as_variable(),
+                    tag: None $(.or($con.$tag().as_variable()))?,
                 };
-
-                let vars = [lhs_var, rhs_var].into_iter().flatten().collect_vec();
-                match_builder.push_check(&vars, check);
+                match mode {
+                    BinaryLoweringMode::Produce { metadata, inputs, sort_variable } => {
+                        let (instruction_inputs, direction) = lowering.direction(inputs, metadata);
+                        let con = $con.clone();
+                        let instruction = match direction {
+                            Direction::Canonical => {
+                                ConstraintInstruction::$fw($fwi::new(con, instruction_inputs, self.local_annotations))
+                            }
+                            Direction::Reverse => {
+                                ConstraintInstruction::$bw($bwi::new(con, instruction_inputs, self.local_annotations))
+                            }
+                        };
+                        let sort_variable =
+                            sort_variable.or_else(|| lowering.sort_variable(inputs, direction)).unwrap();
+                        match_builder.push_instruction(sort_variable, instruction);
+                    }
+                    BinaryLoweringMode::Check => {
+                        let variables = lowering.variables();
+                        assert!(!variables.is_empty());
+                        let resolve = |vertex: &Vertex<Variable>| {
+                            let position = vertex.clone().map(match_builder.position_mapping());
+                            CheckVertex::resolve(position, self.local_annotations)
+                        };
+                        let check = CheckInstruction::$fw {
+                            $lhs: resolve($con.$lhs()),
+                            $rhs: resolve($con.$rhs()),
+                            $($with: $con.$with(),)?
+                            $($tag_field: resolve($con.$tag()),)?
+                        };
+                        match_builder.push_check(&variables, check);
+                    }
+                }
             }};
         }
 
         match constraint {
-            ConstraintVertex::TypeList(type_list) => {
-                let var = type_list.constraint().var();
-                let instruction = type_list.lower_check();
-                match_builder.push_check(&[var], instruction.map(match_builder.position_mapping()));
-            }
-
-            ConstraintVertex::Iid(iid) => {
-                let var = iid.iid().var().as_variable().unwrap();
-                let instruction = CheckInstruction::Iid { var, iid: iid.iid().iid().as_parameter().unwrap() };
-                match_builder.push_check(&[var], instruction.map(match_builder.position_mapping()));
-            }
-
             ConstraintVertex::Sub(planner) => {
                 let sub = planner.sub();
                 binary!((with sub_kind) subtype sub supertype, Sub(SubInstruction), SubReverse(SubReverseInstruction))
@@ -1880,7 +3624,6 @@ impl ConjunctionPlan<'_> {
                 let plays = planner.plays();
                 binary!(player plays role_type, Plays(PlaysInstruction), PlaysReverse(PlaysReverseInstruction))
             }
-
             ConstraintVertex::Isa(planner) => {
                 let isa = planner.isa();
                 binary!((with isa_kind) thing isa type_, Isa(IsaInstruction), IsaReverse(IsaReverseInstruction))
@@ -1890,23 +3633,38 @@ impl ConjunctionPlan<'_> {
                 binary!(owner has attribute, Has(HasInstruction), HasReverse(HasReverseInstruction))
             }
             ConstraintVertex::Links(planner) => {
+                // links is ternary, but its role is only ever produced alongside the relation or the player
                 let links = planner.links();
+                binary!((tag role: role_type) relation links player, Links(LinksInstruction), LinksReverse(LinksReverseInstruction))
+            }
+            ConstraintVertex::TypeList(_) | ConstraintVertex::Iid(_) | ConstraintVertex::IndexedRelation(_) => {
+                unreachable!("not a binary constraint: {constraint:?}")
+            }
+        }
+    }
 
-                let relation = links.relation().as_variable().unwrap();
-                let player = links.player().as_variable().unwrap();
-                let role = links.role_type().as_variable().unwrap();
-
-                let relation_pos = match_builder.position(relation).into();
-                let player_pos = match_builder.position(player).into();
-                let role_pos = match_builder.position(role).into();
+    fn lower_constraint_check(&self, match_builder: &mut MatchExecutableBuilder, constraint: &ConstraintVertex<'_>) {
+        match constraint {
+            ConstraintVertex::TypeList(type_list) => {
+                let var = type_list.constraint().var();
+                let instruction = type_list.lower_check();
+                match_builder.push_check(&[var], instruction.map(match_builder.position_mapping()));
+            }
 
-                let check = CheckInstruction::Links {
-                    relation: CheckVertex::resolve(relation_pos, self.local_annotations),
-                    player: CheckVertex::resolve(player_pos, self.local_annotations),
-                    role: CheckVertex::resolve(role_pos, self.local_annotations),
-                };
+            ConstraintVertex::Iid(iid) => {
+                let var = iid.iid().var().as_variable().unwrap();
+                let instruction = CheckInstruction::Iid { var, iid: iid.iid().iid().as_parameter().unwrap() };
+                match_builder.push_check(&[var], instruction.map(match_builder.position_mapping()));
+            }
 
-                match_builder.push_check(&[relation, player, role], check);
+            ConstraintVertex::Sub(_)
+            | ConstraintVertex::Owns(_)
+            | ConstraintVertex::Relates(_)
+            | ConstraintVertex::Plays(_)
+            | ConstraintVertex::Isa(_)
+            | ConstraintVertex::Has(_)
+            | ConstraintVertex::Links(_) => {
+                self.lower_binary_constraint(match_builder, constraint, BinaryLoweringMode::Check)
             }
             ConstraintVertex::IndexedRelation(planner) => {
                 let player_1 = planner.indexed_relation().player_1().as_variable().unwrap();
@@ -1941,72 +3699,350 @@ impl ConjunctionPlan<'_> {
         self.planner_statistics.query_cost
     }
 
-    fn may_make_input_check_step(
+    /// The range of query text covered by the patterns of the planned conjunction.
+    pub(super) fn source_span(&self) -> Option<Span> {
+        enclosing_span(self.graph.elements.values().flat_map(PlannerVertex::source_spans))
+    }
+
+    /// Renders the planner graph in Graphviz DOT format, annotating each pattern with its step in the chosen ordering
+    /// and its chosen direction.
+    pub(crate) fn to_dot(&self) -> String {
+        self.graph.to_dot_annotated(|pattern| {
+            let order = self.element_to_order.get(&VertexId::Pattern(pattern))?;
+            match self.metadata.get(&pattern) {
+                Some(CostMetaData::Direction(direction)) => Some(format!("step {order} ({direction:?})")),
+                _ => Some(format!("step {order}")),
+            }
+        })
+    }
+
+    /// Explains the chosen plan step by step. Each step reports the ranges of `query_text` executed by it, merged
+    /// across the whitespace and separators between them. Without the query text, only overlapping ranges are merged.
+    pub(super) fn explain_with_source(
+        &self,
+        variable_registry: &VariableRegistry,
+        query_text: Option<&str>,
+    ) -> Result<PlanExplanation, QueryPlanningError> {
+        let variable_name = |var: VariableVertexId| {
+            let variable = self.graph.index_to_variable[&var];
+            match variable_registry.get_variable_name(variable) {
+                Some(name) => format!("${name}"),
+                None => variable.to_string(),
+            }
+        };
+
+        let mut steps = Vec::new();
+        for (order, &index) in self.ordering.iter().enumerate() {
+            let VertexId::Pattern(pattern) = index else { continue };
+            let element = &self.graph.elements[&index];
+            let direction = match self.metadata.get(&pattern) {
+                Some(&CostMetaData::Direction(direction)) => Some(direction),
+                _ => None,
+            };
+            let (cost, _) = element.cost_and_metadata(&self.ordering[..order], direction, &self.graph)?;
+
+            let produced = self.outputs_of_pattern(pattern).collect_vec();
+            let join_variable = produced.iter().find(|&&var| {
+                self.producers_of_var(var).nth(1).is_some() && !self.compound_joins.values().contains(&var)
+            });
+            let nested = match element {
+                PlannerVertex::Negation(negation) => {
+                    vec![negation.plan().explain_with_source(variable_registry, query_text)?]
+                }
+                PlannerVertex::Disjunction(disjunction) => {
+                    let inputs = self.ordering[..order].iter().filter_map(|vertex| match vertex {
+                        VertexId::Variable(var) => Some(self.graph.index_to_variable[var]),
+                        VertexId::Pattern(_) => None,
+                    });
+                    let plan = disjunction.builder().clone().plan(inputs)?;
+                    (plan.branches.iter())
+                        .map(|branch| branch.explain_with_source(variable_registry, query_text))
+                        .try_collect()?
+                }
+                _ => Vec::new(),
+            };
+
+            let bound_check = match element {
+                PlannerVertex::Constraint(constraint) => constraint.bound_check_description(&self.ordering[..order]),
+                _ => None,
+            };
+            let annotation_sizes = match element {
+                PlannerVertex::Constraint(constraint) => (constraint.annotation_sizes().into_iter())
+                    .flat_map(|sizes| sizes.entries())
+                    .map(|(label, size)| AnnotationSize { label, size })
+                    .collect(),
+                _ => Vec::new(),
+            };
+
+            // the producers of a join variable are all executed by the one intersection step
+            let source_spans: Vec<Span> = match join_variable {
+                Some(&var) => (self.producers_of_var(var))
+                    .flat_map(|producer| self.graph.elements[&VertexId::Pattern(producer)].source_spans())
+                    .collect(),
+                None => element.source_spans(),
+            };
+            let source_ranges = (source_spans.into_iter())
+                .map(|span| SourceRange { begin: span.begin_offset, end: span.end_offset })
+                .collect();
+
+            steps.push(PlanStepExplanation {
+                order,
+                pattern: element.to_string(),
+                direction: bound_check.or_else(|| direction.map(|direction| format!("{direction:?}"))),
+                join_variable: join_variable.map(|var| match self.compound_joins.get(var) {
+                    Some(&secondary_join_var) => {
+                        format!("{}, {}", variable_name(*var), variable_name(secondary_join_var))
+                    }
+                    None => variable_name(*var),
+                }),
+                cost: cost.cost,
+                io_ratio: cost.io_ratio,
+                produced_variables: produced.into_iter().map(variable_name).sorted().collect(),
+                source_ranges: SourceRange::coalesce(source_ranges, query_text),
+                annotation_sizes,
+                nested,
+            });
+        }
+
+        let Cost { cost, io_ratio } = self.cost();
+        Ok(PlanExplanation { steps, notes: self.planning_notes.clone(), cost, io_ratio })
+    }
+
+    /// The input variables whose upstream annotations admit types this conjunction does not, with the types to check
+    /// them against. Computed once per lowering, as the input annotations differ between the lowerings of a plan.
+    fn required_input_checks(
         &self,
-        match_builder: &mut MatchExecutableBuilder,
         input_variables: impl Iterator<Item = Variable>,
         input_variable_annotations: &BTreeMap<Vertex<Variable>, Arc<BTreeSet<answer::Type>>>,
-        variable_registry: &VariableRegistry,
-    ) {
-        let mut pushed_any = false;
+    ) -> Vec<(Variable, Arc<BTreeSet<answer::Type>>)> {
         input_variables
             .filter_map(|variable| {
                 let vertex = variable.into();
                 let local_annotations = self.local_annotations.vertex_annotations_of(&vertex)?;
-                input_variable_annotations
-                    .get(&vertex)? // Functions don't have any
-                    .iter()
-                    .any(|type_| !local_annotations.contains(type_))
-                    .then(|| (variable, local_annotations.clone()))
+                let input_annotations = input_variable_annotations.get(&vertex)?; // Functions don't have any
+                let is_subset = input_annotations.len() <= local_annotations.len()
+                    && input_annotations.iter().all(|type_| local_annotations.contains(type_));
+                (!is_subset).then(|| (variable, local_annotations.clone()))
             })
-            .for_each(|(variable, types)| {
-                let category = variable_registry.get_variable_category(variable).unwrap();
-                debug_assert!(category.is_category_thing() || category.is_category_type());
-                let executor_var = match_builder.position(variable);
-                let check = match category.is_category_thing() {
-                    true => CheckInstruction::ThingTypeList { thing_var: executor_var, types },
-                    false => CheckInstruction::TypeList { type_var: executor_var, types },
+            .collect()
+    }
+
+    fn may_make_input_check_step(
+        &self,
+        match_builder: &mut MatchExecutableBuilder,
+        input_checks: Vec<(Variable, Arc<BTreeSet<answer::Type>>)>,
+        variable_registry: &VariableRegistry,
+    ) {
+        if input_checks.is_empty() {
+            return;
+        }
+        for (variable, types) in input_checks {
+            let category = variable_registry.get_variable_category(variable).unwrap();
+            debug_assert!(category.is_category_thing() || category.is_category_type());
+            let executor_var = match_builder.position(variable);
+            let check = match category.is_category_thing() {
+                true => CheckInstruction::ThingTypeList { thing_var: executor_var, types },
+                false => CheckInstruction::TypeList { type_var: executor_var, types },
+            };
+            match_builder.push_check(&[variable], check);
+        }
+        match_builder.finish_one();
+    }
+}
+
+/// Whether a constraint is lowered to the instruction producing its unbound variables, or to a check of its bound ones.
+#[derive(Clone, Copy, Debug)]
+enum BinaryLoweringMode<'a> {
+    Produce { metadata: CostMetaData, inputs: &'a [Variable], sort_variable: Option<Variable> },
+    Check,
+}
+
+/// The variables of a constraint lowered as though it relates two vertices, from which the direction of its
+/// instruction is decided. The tag is a further variable, such as the role of a `links`, that is produced by either
+/// direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BinaryConstraintLowering {
+    lhs: Option<Variable>,
+    rhs: Option<Variable>,
+    tag: Option<Variable>,
+}
+
+impl BinaryConstraintLowering {
+    /// The bound variables of the two vertices, and the direction to lower the constraint in: from the bound side,
+    /// preferring the right-hand side when both are bound, or as the planner chose when neither is.
+    fn direction(&self, inputs: &[Variable], metadata: CostMetaData) -> (Inputs<Variable>, Direction) {
+        let lhs_input = self.lhs.filter(|lhs| inputs.contains(lhs));
+        let rhs_input = self.rhs.filter(|rhs| inputs.contains(rhs));
+        match (lhs_input, rhs_input) {
+            (Some(lhs), Some(rhs)) => (Inputs::Dual([lhs, rhs]), Direction::Reverse), // useful for links
+            (None, Some(rhs)) => (Inputs::Single([rhs]), Direction::Reverse),
+            (Some(lhs), None) => (Inputs::Single([lhs]), Direction::Canonical),
+            (None, None) => {
+                let CostMetaData::Direction(unbound_direction) = metadata else {
+                    unreachable!("expected metadata for constraint")
                 };
-                match_builder.push_check(&[variable], check);
-                pushed_any = true;
-            });
-        if pushed_any {
-            match_builder.finish_one();
+                (Inputs::None([]), unbound_direction)
+            }
         }
     }
+
+    /// The variable the instruction's output is sorted on: the first it produces in its direction, or the tag.
+    fn sort_variable(&self, inputs: &[Variable], direction: Direction) -> Option<Variable> {
+        let lhs_produced = self.lhs.filter(|lhs| !inputs.contains(lhs));
+        let rhs_produced = self.rhs.filter(|rhs| !inputs.contains(rhs));
+        match direction {
+            Direction::Canonical => lhs_produced.or(rhs_produced),
+            Direction::Reverse => rhs_produced.or(lhs_produced),
+        }
+        .or(self.tag)
+    }
+
+    fn variables(&self) -> Vec<Variable> {
+        [self.lhs, self.rhs, self.tag].into_iter().flatten().collect()
+    }
 }
 
 #[derive(Clone, Debug)]
 pub(super) struct DisjunctionPlanBuilder<'a> {
+    disjunction: &'a Disjunction,
+    memo: Arc<DisjunctionPlanMemo>,
+    memo_hash: u64,
     branch_ids: Vec<BranchID>,
     branches: Vec<ConjunctionPlanBuilder<'a>>,
     required_inputs: Vec<Variable>,
+    variable_categories: HashMap<Variable, VariableCategory>,
 }
 
 impl<'a> DisjunctionPlanBuilder<'a> {
     fn new(
+        disjunction: &'a Disjunction,
+        memo: Arc<DisjunctionPlanMemo>,
+        variable_registry: &VariableRegistry,
         branch_ids: Vec<BranchID>,
         branches: Vec<ConjunctionPlanBuilder<'a>>,
         required_inputs: Vec<Variable>,
     ) -> Self {
-        Self { branch_ids, branches, required_inputs }
+        // the plans also depend on the categories and annotations of the variables the branches are planned with
+        let mut hasher = DefaultHasher::new();
+        let mut variable_categories = HashMap::new();
+        StructuralEquality::hash(disjunction).hash(&mut hasher);
+        for branch in &branches {
+            for &var in branch.graph.variable_index.keys().sorted() {
+                var.hash(&mut hasher);
+                let category = variable_registry.get_variable_category(var);
+                category.hash(&mut hasher);
+                variable_categories.extend(category.map(|category| (var, category)));
+            }
+            branch.local_annotations.vertex_annotations().hash(&mut hasher);
+        }
+        let memo_hash = hasher.finish();
+        Self { disjunction, memo, memo_hash, branch_ids, branches, required_inputs, variable_categories }
     }
 
     pub(super) fn branches(&self) -> &[ConjunctionPlanBuilder<'a>] {
         &self.branches
     }
 
-    fn plan(
+    pub(super) fn disjunction(&self) -> &'a Disjunction {
+        self.disjunction
+    }
+
+    pub(super) fn plan(
         self,
         input_variables: impl Iterator<Item = Variable> + Clone,
     ) -> Result<DisjunctionPlan<'a>, QueryPlanningError> {
-        let Self { branch_ids, branches, .. } = self;
-        let branches = branches
-            .into_iter()
-            .map(|branch| branch.with_inputs(input_variables.clone()).plan())
-            .collect::<Result<Vec<_>, _>>()?;
-        let cost = branches.iter().map(ConjunctionPlan::cost).fold(Cost::EMPTY, Cost::combine_parallel);
-        Ok(DisjunctionPlan { branch_ids, branches, _cost: cost })
+        let Self { disjunction, memo, memo_hash, branch_ids, branches, required_inputs, variable_categories } = self;
+        let inputs = input_variables
+            .filter(|var| branches.iter().any(|branch| branch.graph.variable_index.contains_key(var)))
+            .sorted()
+            .dedup()
+            .collect_vec();
+        let mut hasher = DefaultHasher::new();
+        inputs.hash(&mut hasher);
+        let key = DisjunctionMemoKey::new(memo_hash, hasher.finish());
+        let mut branches = (branches.into_iter())
+            .map(|branch| branch.with_inputs(inputs.iter().copied(), |var| variable_categories.get(&var).copied()))
+            .collect_vec();
+        debug_assert!(branches.iter().all(|branch| branch.plans_as_inputs(&required_inputs)));
+        // the branches of a very wide disjunction share the effort a single conjunction is planned with
+        if branches.len() > WIDE_DISJUNCTION_BRANCHES {
+            let branch_count = branches.len();
+            for branch in &mut branches {
+                let beam_width = branch.config.max_beam_width.saturating_mul(WIDE_DISJUNCTION_BRANCHES) / branch_count;
+                branch.config.max_beam_width = usize::max(beam_width, MIN_BEAM_WIDTH);
+            }
+        }
+
+        if let Some(cached) = memo.get(key, disjunction, &inputs) {
+            let restored = (cached.iter())
+                .map(|(index, cached)| branches[*index].restore_cached_plan(cached))
+                .collect::<Option<Vec<_>>>();
+            if let Some(restored) = restored {
+                let mut branches = branches.into_iter().map(Some).collect_vec();
+                let planned_branches = cached.iter().zip(restored).map(|((index, cached), complete_plan)| {
+                    let mut branch = branches[*index].take().unwrap();
+                    branch.mark_restored_plan(cached);
+                    (branch_ids[*index], branch.into_plan(complete_plan))
+                });
+                return Ok(DisjunctionPlan::new(planned_branches.collect()));
+            }
+        }
+
+        // structurally equal branches planned in the same context have the same plan, so only the first is searched
+        let conjunctions = disjunction.conjunctions();
+        debug_assert_eq!(conjunctions.len(), branches.len());
+        let mut equal_branches: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+        let representatives = (branches.iter().enumerate())
+            .map(|(index, branch)| {
+                let key = (StructuralEquality::hash(&conjunctions[index]), branch.plan_context_hash());
+                let equal = equal_branches.entry(key).or_default();
+                let representative = equal
+                    .iter()
+                    .copied()
+                    .find(|&other| StructuralEquality::equals(&conjunctions[other], &conjunctions[index]));
+                if representative.is_none() {
+                    equal.push(index);
+                }
+                representative.unwrap_or(index)
+            })
+            .collect_vec();
+        let is_searched = representatives.iter().enumerate().map(|(index, &repr)| index == repr).collect_vec();
+        let mut complete_plans = complete_branch_plans(&mut branches, &is_searched);
+        for (index, &representative) in representatives.iter().enumerate() {
+            if representative != index {
+                let representative_plan = complete_plans[representative].as_ref().unwrap();
+                let restored = representative_plan.as_ref().ok().and_then(|complete_plan| {
+                    let cached = branches[representative].make_cached_plan(complete_plan);
+                    branches[index].restore_cached_plan(&cached)
+                });
+                complete_plans[index] = Some(match restored {
+                    Some(complete_plan) => Ok(complete_plan),
+                    None => branches[index].complete_plan(),
+                });
+            }
+        }
+
+        let mut planned_branches = zip(branches, complete_plans)
+            .enumerate()
+            .map(|(index, (branch, complete_plan))| {
+                let complete_plan =
+                    complete_plan.unwrap().map_err(|err| QueryPlanningError::DisjunctionBranchPlanningFailed {
+                        scope: branch.scope,
+                        branch_id: branch_ids[index],
+                        inputs: inputs.iter().join(", "),
+                        typedb_source: Box::new(err),
+                    })?;
+                let cached = branch.make_cached_plan(&complete_plan);
+                Ok((index, cached, branch.into_plan(complete_plan)))
+            })
+            .collect::<Result<Vec<_>, QueryPlanningError>>()?;
+        // branches are executed in order, so the cheap ones start producing answers before the expensive ones are opened
+        planned_branches.sort_by(|(_, _, lhs), (_, _, rhs)| lhs.cost().cost.total_cmp(&rhs.cost().cost));
+        let (cached, planned_branches): (Vec<_>, Vec<_>) = (planned_branches.into_iter())
+            .map(|(index, cached, plan)| ((index, cached), (branch_ids[index], plan)))
+            .unzip();
+        memo.insert(key, disjunction, inputs, cached);
+        Ok(DisjunctionPlan::new(planned_branches))
     }
 
     pub(crate) fn required_inputs(&self) -> &[Variable] {
@@ -2014,11 +4050,54 @@ impl<'a> DisjunctionPlanBuilder<'a> {
     }
 }
 
+// Completes the plans of the searched branches, on a scoped thread per chunk of the branches if there are enough of them.
+// The branches are planned independently given their shared inputs, so the plans are the same as when planned serially.
+fn complete_branch_plans(
+    branches: &mut [ConjunctionPlanBuilder<'_>],
+    is_searched: &[bool],
+) -> Vec<Option<Result<CompleteCostPlan, QueryPlanningError>>> {
+    fn complete_plans(
+        branches: &mut [ConjunctionPlanBuilder<'_>],
+        is_searched: &[bool],
+    ) -> Vec<Option<Result<CompleteCostPlan, QueryPlanningError>>> {
+        zip(branches, is_searched).map(|(branch, &is_searched)| is_searched.then(|| branch.complete_plan())).collect()
+    }
+
+    let searched_count = is_searched.iter().filter(|&&is_searched| is_searched).count();
+    if searched_count < PARALLEL_BRANCH_PLANNING_MIN_BRANCHES {
+        return complete_plans(branches, is_searched);
+    }
+    let threads = usize::min(thread::available_parallelism().map_or(1, NonZeroUsize::get), searched_count);
+    let chunk_size = branches.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles = zip(branches.chunks_mut(chunk_size), is_searched.chunks(chunk_size))
+            .map(|(chunk, is_searched)| scope.spawn(move || complete_plans(chunk, is_searched)))
+            .collect_vec();
+        let mut complete_plans = Vec::with_capacity(is_searched.len());
+        for handle in handles {
+            complete_plans.extend(handle.join().unwrap_or_else(|payload| panic::resume_unwind(payload)));
+        }
+        complete_plans
+    })
+}
+
 #[derive(Clone, Debug)]
 pub(super) struct DisjunctionPlan<'a> {
     branch_ids: Vec<BranchID>,
     branches: Vec<ConjunctionPlan<'a>>,
-    _cost: Cost,
+    cost: Cost,
+}
+
+impl<'a> DisjunctionPlan<'a> {
+    fn new(planned_branches: Vec<(BranchID, ConjunctionPlan<'a>)>) -> Self {
+        let (branch_ids, branches): (Vec<_>, Vec<_>) = planned_branches.into_iter().unzip();
+        let cost = branches.iter().map(ConjunctionPlan::cost).fold(Cost::EMPTY, Cost::combine_parallel);
+        Self { branch_ids, branches, cost }
+    }
+
+    pub(super) fn cost(&self) -> Cost {
+        self.cost
+    }
 }
 
 impl DisjunctionPlan<'_> {
@@ -2029,22 +4108,42 @@ impl DisjunctionPlan<'_> {
         selected_variables: impl IntoIterator<Item = Variable> + Clone,
         assigned_positions: &HashMap<Variable, ExecutorVariable>,
         variable_registry: &VariableRegistry,
+        result_semantics: ResultSemantics,
+        interleave_branches: bool,
     ) -> Result<DisjunctionBuilder, QueryPlanningError> {
+        // Each branch is lowered with the positions assigned outside the disjunction and those of its own variables
+        // assigned by earlier branches, rather than with every position assigned so far. Positions it assigns itself
+        // come after all those of earlier branches, so no two variables of the disjunction share a position.
         let mut branches: Vec<_> = Vec::with_capacity(self.branches.len());
-        let mut assigned_positions = assigned_positions.clone();
+        let mut branch_assigned_positions: HashMap<Variable, ExecutorVariable> = HashMap::new();
+        let mut reserved_positions = 0;
         for (branch_id, branch) in self.branch_ids.iter().zip(self.branches.iter()) {
+            let mut positions = assigned_positions.clone();
+            positions.extend(
+                (branch.graph.variable_index.keys())
+                    .filter_map(|var| Some((*var, *branch_assigned_positions.get(var)?))),
+            );
             let lowered_branch = branch.lower(
                 input_variable_annotations,
                 disjunction_inputs.clone(),
                 selected_variables.clone(),
-                &assigned_positions,
+                &positions,
+                reserved_positions,
                 variable_registry,
                 Some(*branch_id),
+                result_semantics,
             )?;
-            assigned_positions = lowered_branch.position_mapping().clone();
+            for (&var, &position) in lowered_branch.position_mapping() {
+                if !assigned_positions.contains_key(&var) {
+                    branch_assigned_positions.entry(var).or_insert(position);
+                }
+                if let Some(position) = position.as_position() {
+                    reserved_positions = u32::max(reserved_positions, position.position + 1);
+                }
+            }
             branches.push(lowered_branch);
         }
-        Ok(DisjunctionBuilder::new(self.branch_ids.clone(), branches))
+        Ok(DisjunctionBuilder::new(self.branch_ids.clone(), branches, interleave_branches))
     }
 }
 
@@ -2091,7 +4190,70 @@ impl fmt::Display for Graph<'_> {
     }
 }
 
+impl Graph<'_> {
+    /// Renders the graph in Graphviz DOT format, with variables as ellipses and patterns as boxes.
+    pub(super) fn to_dot(&self) -> String {
+        self.to_dot_annotated(|_| None)
+    }
+
+    fn to_dot_annotated(&self, annotate_pattern: impl Fn(PatternVertexId) -> Option<String>) -> String {
+        fn escape(label: &str) -> String {
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        let mut dot = String::from("graph planner {\n");
+        for (index, variable) in self.index_to_variable.iter().sorted_by_key(|(&index, _)| index) {
+            dot.push_str(&format!("  v{} [shape=ellipse, label=\"{}\"];\n", index.0, escape(&variable.to_string())));
+        }
+        let patterns = self.elements.iter().filter_map(|(id, element)| Some((id.as_pattern_id()?, element)));
+        for (pattern, element) in patterns.sorted_by_key(|&(pattern, _)| pattern) {
+            let mut label = format!("{}\\n{}", element.planner_name(), escape(&element.to_string()));
+            if let Some(size) = element.unbound_expected_size() {
+                label.push_str(&format!("\\nunbound size: {size:.2}"));
+            }
+            if let Some(annotation) = annotate_pattern(pattern) {
+                label.push_str(&format!("\\n{}", escape(&annotation)));
+            }
+            dot.push_str(&format!("  p{} [shape=box, label=\"{label}\"];\n", pattern.0));
+            for variable in self.pattern_to_variable.get(&pattern).into_iter().flatten().sorted() {
+                dot.push_str(&format!("  p{} -- v{};\n", pattern.0, variable.0));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
 impl<'a> Graph<'a> {
+    /// The patterns of each connected component, in which patterns are connected through the variables they share.
+    /// Input variables are bound before any pattern is executed, so they do not connect the patterns using them.
+    fn connected_components(&self) -> Vec<HashSet<PatternVertexId>> {
+        let mut components = Vec::new();
+        let mut visited = HashSet::new();
+        for &start in self.pattern_to_variable.keys().sorted() {
+            if !visited.insert(start) {
+                continue;
+            }
+            let mut component = HashSet::from([start]);
+            let mut stack = vec![start];
+            while let Some(pattern) = stack.pop() {
+                for var in &self.pattern_to_variable[&pattern] {
+                    if self.elements[&VertexId::Variable(*var)].as_variable().is_some_and(|var| var.is_input()) {
+                        continue;
+                    }
+                    for &neighbour in self.variable_to_pattern.get(var).into_iter().flatten() {
+                        if visited.insert(neighbour) {
+                            component.insert(neighbour);
+                            stack.push(neighbour);
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
     fn push_variable(&mut self, variable: Variable, vertex: VariableVertex) {
         let index = self.next_variable_index();
         self.elements.insert(VertexId::Variable(index), PlannerVertex::Variable(vertex));
@@ -2100,6 +4262,11 @@ impl<'a> Graph<'a> {
     }
 
     fn push_constraint(&mut self, constraint: ConstraintVertex<'a>) {
+        debug_assert_eq!(
+            constraint.join_contract_violation(&constraint.variables().collect_vec()),
+            None,
+            "{constraint} breaks the join contract"
+        );
         let pattern_index = self.next_pattern_index();
         self.pattern_to_variable.entry(pattern_index).or_default().extend(constraint.variables());
         for var in constraint.variables() {
@@ -2201,3 +4368,688 @@ impl<'a> Graph<'a> {
         &self.elements
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
+        iter,
+        sync::Arc,
+    };
+
+    use answer::{variable::Variable, Type};
+    use concept::{
+        thing::statistics::Statistics,
+        type_::{attribute_type::AttributeType, entity_type::EntityType, object_type::ObjectType},
+    };
+    use encoding::graph::type_::vertex::{PrefixedTypeVertexEncoding, TypeID};
+    use error::TypeDBError;
+    use ir::{
+        pattern::{constraint::Constraint, BranchID, Scope, ScopeId, Vertex},
+        pipeline::{
+            function_signature::{FunctionID, HashMapFunctionSignatureIndex},
+            ParameterRegistry,
+        },
+        translation::{match_::translate_match, PipelineTranslationContext},
+    };
+    use storage::sequence_number::SequenceNumber;
+    use test_utils_concept::StatisticsBuilder;
+    use typeql::common::Span;
+
+    use super::{
+        BinaryConstraintLowering, ConjunctionPlanBuilder, Graph, PartialCostPlan, PatternSet, PlannerConfig,
+        PlannerVertex, PlanningErrorClassification, QueryPlanningError, VertexId,
+    };
+    use crate::{
+        annotation::type_annotations::TypeAnnotations,
+        executable::{
+            function::{FunctionCallCost, FunctionCallCostProvider},
+            match_::{
+                instructions::{CheckInstruction, Inputs},
+                planner::{
+                    conjunction_executable::ResultSemantics,
+                    explain::SourceRange,
+                    vertex::{Cost, CostMetaData, Costed, Direction},
+                    StepInstructionsBuilder,
+                },
+            },
+        },
+        ExecutorVariable,
+    };
+
+    #[test]
+    fn unplannable_conjunction_error_names_the_unplanned_patterns() {
+        let query = "match let $a = 1; let $b = 2; $a > $b;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let conjunction = block.conjunction();
+        let comparison = conjunction.constraints().iter().find_map(|constraint| constraint.as_comparison()).unwrap();
+        let type_annotations = TypeAnnotations::new(BTreeMap::new(), HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+
+        // without the assignments of its operands, the comparison can never be planned
+        let mut builder = ConjunctionPlanBuilder::new(
+            conjunction.scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+        for operand in [comparison.lhs(), comparison.rhs()] {
+            builder.register_value_var(operand.as_variable().unwrap());
+        }
+        builder.register_comparison(comparison, &parameters).unwrap();
+        let search_patterns = builder.graph.pattern_to_variable.keys().copied().collect();
+        let error = builder.beam_search_plan(search_patterns, None).unwrap_err();
+
+        assert!(
+            matches!(
+                error,
+                QueryPlanningError::ExpectedPlannableConjunction { branch_id: None, completed_iterations: 0, .. }
+            ),
+            "{error:?}"
+        );
+        let description = error.format_description();
+        assert!(description.contains(&comparison.to_string()), "{description}");
+        assert!(description.contains(&conjunction.scope_id().to_string()), "{description}");
+    }
+
+    #[test]
+    fn unbound_dependencies_are_diagnosed_as_user_errors() {
+        let query = "match let $a = 1; let $b = 2; $a > $b;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let conjunction = block.conjunction();
+        let comparison = conjunction.constraints().iter().find_map(|constraint| constraint.as_comparison()).unwrap();
+        let type_annotations = TypeAnnotations::new(BTreeMap::new(), HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+
+        // without the assignments of its operands, nothing binds the variables the comparison depends on
+        let mut builder = ConjunctionPlanBuilder::new(
+            conjunction.scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+        let operands = [comparison.lhs(), comparison.rhs()].map(|operand| operand.as_variable().unwrap());
+        builder.register_variables(iter::empty(), iter::empty(), operands.into_iter(), &context.variable_registry);
+        builder.register_comparison(comparison, &parameters).unwrap();
+        let error = builder.complete_plan().unwrap_err();
+
+        assert!(matches!(error, QueryPlanningError::UnresolvableDependencies { branch_id: None, .. }), "{error:?}");
+        assert_eq!(error.classification(), PlanningErrorClassification::UserError);
+        assert_eq!(error.scope(), Some(conjunction.scope_id()));
+        assert_eq!(error.variables(), ["a", "b"]);
+        assert_eq!(error.source_span(), comparison.source_span());
+        let description = error.format_description();
+        assert!(description.contains(&comparison.to_string()), "{description}");
+    }
+
+    #[test]
+    fn planning_errors_are_classified() {
+        let span = Some(Span { begin_offset: 6, end_offset: 12 });
+        let unresolvable = QueryPlanningError::UnresolvableDependencies {
+            scope: ScopeId::ROOT,
+            branch_id: Some(BranchID(1)),
+            unplanned_patterns: String::from("$a > $b"),
+            variables: vec![String::from("a")],
+            source_span: span,
+        };
+        let cases = [
+            (
+                QueryPlanningError::ExpectedPlannableConjunction {
+                    scope: ScopeId::ROOT,
+                    branch_id: None,
+                    unplanned_patterns: String::new(),
+                    completed_iterations: 0,
+                },
+                PlanningErrorClassification::Internal,
+            ),
+            (
+                QueryPlanningError::ExpressionInputNotInScope { variable: String::from("a"), source_span: span },
+                PlanningErrorClassification::UserError,
+            ),
+            (
+                QueryPlanningError::MissingAnnotations { scope: ScopeId::ROOT, branch_id: None, source_span: span },
+                PlanningErrorClassification::Internal,
+            ),
+            (
+                QueryPlanningError::MissingConstraintAnnotations { constraint: String::new(), source_span: span },
+                PlanningErrorClassification::Internal,
+            ),
+            (unresolvable.clone(), PlanningErrorClassification::UserError),
+            (
+                QueryPlanningError::DisjunctionBranchPlanningFailed {
+                    scope: ScopeId::ROOT,
+                    branch_id: BranchID(1),
+                    inputs: String::new(),
+                    typedb_source: Box::new(unresolvable.clone()),
+                },
+                PlanningErrorClassification::UserError,
+            ),
+            (
+                QueryPlanningError::DisjunctionBranchPlanningFailed {
+                    scope: ScopeId::ROOT,
+                    branch_id: BranchID(1),
+                    inputs: String::new(),
+                    typedb_source: Box::new(QueryPlanningError::MissingAnnotations {
+                        scope: ScopeId::ROOT,
+                        branch_id: Some(BranchID(1)),
+                        source_span: None,
+                    }),
+                },
+                PlanningErrorClassification::Internal,
+            ),
+        ];
+        for (error, classification) in &cases {
+            assert_eq!(error.classification(), *classification, "{error:?}");
+        }
+
+        let (branch_failure, _) = &cases[5];
+        assert_eq!(branch_failure.branch_id(), Some(BranchID(1)));
+        assert_eq!(branch_failure.variables(), ["a"]);
+        assert!(matches!(branch_failure.root_cause(), QueryPlanningError::UnresolvableDependencies { .. }));
+    }
+
+    #[test]
+    fn equality_class_members_are_bounded_by_the_smallest_member() {
+        let query = "match $a isa name; $b isa name; $c isa name; $a == $b; $b == $c;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let conjunction = block.conjunction();
+        let comparisons =
+            conjunction.constraints().iter().filter_map(|constraint| constraint.as_comparison()).collect::<Vec<_>>();
+        let [a_equals_b, b_equals_c] = comparisons[..] else { panic!("expected two comparisons") };
+        let [a, b, c] =
+            [a_equals_b.lhs(), a_equals_b.rhs(), b_equals_c.rhs()].map(|vertex| vertex.as_variable().unwrap());
+
+        let name = AttributeType::build_from_type_id(TypeID::new(0));
+        let name_types = Arc::new(BTreeSet::from([Type::Attribute(name)]));
+        let vertex_annotations = [a, b, c].map(|var| (Vertex::Variable(var), name_types.clone()));
+        let type_annotations = TypeAnnotations::new(BTreeMap::from(vertex_annotations), HashMap::new());
+        let statistics = StatisticsBuilder::new().attributes(name, 1000).build();
+
+        let mut builder = ConjunctionPlanBuilder::new(
+            conjunction.scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+        for var in [a, b, c] {
+            builder.register_thing_var(var);
+        }
+        // `$c` is as tightly bound as an iid would make it
+        let [a, b, c] = [a, b, c].map(|var| builder.graph.variable_index[&var]);
+        builder.graph.elements.get_mut(&VertexId::Variable(c)).unwrap().as_variable_mut().unwrap().set_exact_bound();
+        builder.register_comparison(a_equals_b, &parameters).unwrap();
+        builder.register_comparison(b_equals_c, &parameters).unwrap();
+        builder.unify_equality_classes();
+
+        let a_vertex = builder.graph.elements[&VertexId::Variable(a)].as_variable().unwrap();
+        assert_eq!(a_vertex.restricted_expected_output_size(&[]), 1000.0);
+        // `$a` is only related to `$c` through `$b`, yet is expected to be as small once `$c` is produced
+        assert_eq!(a_vertex.restricted_expected_output_size(&[VertexId::Variable(c)]), 1.0);
+        let b_vertex = builder.graph.elements[&VertexId::Variable(b)].as_variable().unwrap();
+        assert_eq!(b_vertex.restricted_expected_output_size(&[VertexId::Variable(c)]), 1.0);
+    }
+
+    #[test]
+    fn inputs_only_used_by_nested_patterns_are_planned_as_inputs() {
+        let query = "match $p isa person; not { $q isa person; not { $p has name $n; }; };";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let registry = &context.variable_registry;
+        let p = registry.variable_names().iter().find_map(|(&var, name)| (name == "p").then_some(var)).unwrap();
+        let negation = block.conjunction().nested_patterns()[0].as_negation().unwrap();
+        let required_inputs = negation.required_inputs(block.block_context()).collect::<Vec<_>>();
+        assert_eq!(required_inputs, vec![p]);
+
+        // `$p` is only used by the innermost negation, so the outer negation registers no vertex for it itself
+        let type_annotations = TypeAnnotations::new(BTreeMap::new(), HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+        let builder = ConjunctionPlanBuilder::new(
+            negation.conjunction().scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        )
+        .with_inputs(required_inputs.iter().copied(), |var| registry.get_variable_category(var));
+
+        assert!(builder.plans_as_inputs(&required_inputs));
+        assert_eq!(builder.shared_variables(), [p]);
+    }
+
+    #[test]
+    fn source_ranges_are_merged_across_separators_only_given_the_query_text() {
+        let query = "match $p isa purchase, links (order: $order);  $x has name $n;";
+        let range_of = |text: &str| {
+            let begin = query.find(text).unwrap();
+            SourceRange { begin, end: begin + text.len() }
+        };
+        let ranges = vec![range_of("order: $order"), range_of("isa purchase"), range_of("has name $n")];
+
+        let without_text = SourceRange::coalesce(ranges.clone(), None);
+        assert_eq!(without_text, vec![range_of("isa purchase"), range_of("order: $order"), range_of("has name $n")]);
+
+        // `, links (` is not a separator, whereas `);  $x ` holds a variable
+        let with_text = SourceRange::coalesce(ranges, Some(query));
+        assert_eq!(with_text, without_text);
+
+        let adjacent = vec![range_of("$p isa purchase"), range_of("links (order: $order)"), range_of("$x has name $n")];
+        let with_text = SourceRange::coalesce(adjacent, Some(query));
+        assert_eq!(with_text, vec![range_of("$p isa purchase, links (order: $order);  $x has name $n")]);
+    }
+
+    #[test]
+    fn binary_constraints_are_lowered_from_their_bound_side() {
+        let [lhs, rhs, tag] = [0, 1, 2].map(Variable::new);
+        let lowering = BinaryConstraintLowering { lhs: Some(lhs), rhs: Some(rhs), tag: Some(tag) };
+        for planned in [Direction::Canonical, Direction::Reverse] {
+            let metadata = CostMetaData::Direction(planned);
+            assert_eq!(lowering.direction(&[], metadata), (Inputs::None([]), planned));
+            assert_eq!(lowering.direction(&[lhs], metadata), (Inputs::Single([lhs]), Direction::Canonical));
+            assert_eq!(lowering.direction(&[rhs], metadata), (Inputs::Single([rhs]), Direction::Reverse));
+            assert_eq!(lowering.direction(&[lhs, rhs], metadata), (Inputs::Dual([lhs, rhs]), Direction::Reverse));
+            // a bound tag does not decide the direction
+            assert_eq!(lowering.direction(&[tag], metadata), (Inputs::None([]), planned));
+            assert_eq!(lowering.direction(&[lhs, tag], metadata), (Inputs::Single([lhs]), Direction::Canonical));
+        }
+
+        // the output is sorted on the first variable produced in the direction of the instruction
+        assert_eq!(lowering.sort_variable(&[], Direction::Canonical), Some(lhs));
+        assert_eq!(lowering.sort_variable(&[], Direction::Reverse), Some(rhs));
+        assert_eq!(lowering.sort_variable(&[lhs], Direction::Canonical), Some(rhs));
+        assert_eq!(lowering.sort_variable(&[rhs], Direction::Reverse), Some(lhs));
+        assert_eq!(lowering.sort_variable(&[lhs, rhs], Direction::Reverse), Some(tag));
+        assert_eq!(lowering.variables(), vec![lhs, rhs, tag]);
+
+        // a constant vertex, such as a label, is never bound nor produced
+        let labelled = BinaryConstraintLowering { lhs: Some(lhs), rhs: None, tag: None };
+        let metadata = CostMetaData::Direction(Direction::Reverse);
+        assert_eq!(labelled.direction(&[], metadata), (Inputs::None([]), Direction::Reverse));
+        assert_eq!(labelled.direction(&[lhs], CostMetaData::None), (Inputs::Single([lhs]), Direction::Canonical));
+        assert_eq!(labelled.sort_variable(&[], Direction::Reverse), Some(lhs));
+        assert_eq!(labelled.sort_variable(&[lhs], Direction::Canonical), None);
+        assert_eq!(labelled.variables(), vec![lhs]);
+    }
+
+    #[test]
+    fn input_checks_follow_the_input_annotations_of_each_lowering() {
+        let query = "match $p isa person;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let registry = &context.variable_registry;
+        let p = registry.variable_names().iter().find_map(|(&var, name)| (name == "p").then_some(var)).unwrap();
+
+        let [person, organisation] = [0, 1].map(|id| Type::Entity(EntityType::build_from_type_id(TypeID::new(id))));
+        let local_types = Arc::new(BTreeSet::from([person]));
+        let type_annotations =
+            TypeAnnotations::new(BTreeMap::from([(Vertex::Variable(p), local_types.clone())]), HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+        let mut builder = ConjunctionPlanBuilder::new(
+            block.conjunction().scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+        builder.register_variables(iter::once(p), iter::empty(), iter::empty(), registry);
+        let plan = builder.plan().unwrap();
+
+        let assigned_positions = HashMap::from([(p, ExecutorVariable::new_position(0))]);
+        let lowered_checks = |input_types: BTreeSet<Type>| {
+            let input_annotations = BTreeMap::from([(Vertex::Variable(p), Arc::new(input_types))]);
+            let match_builder = plan
+                .lower(&input_annotations, [p], [p], &assigned_positions, 1, registry, None, ResultSemantics::Bag)
+                .unwrap();
+            (match_builder.steps.iter().chain(match_builder.current.as_deref()))
+                .filter_map(|step| match &step.builder {
+                    StepInstructionsBuilder::Check(check) => Some(check.instructions.clone()),
+                    _ => None,
+                })
+                .flatten()
+                .collect::<Vec<_>>()
+        };
+
+        // the same plan is lowered against inputs that may and may not be of types the conjunction excludes
+        let widened = lowered_checks(BTreeSet::from([person, organisation]));
+        let [CheckInstruction::ThingTypeList { types, .. }] = &widened[..] else { panic!("{widened:?}") };
+        assert_eq!(types, &local_types);
+        assert!(lowered_checks(BTreeSet::from([person])).is_empty());
+        assert_eq!(lowered_checks(BTreeSet::from([person, organisation])), widened);
+    }
+    struct CallCosts;
+
+    impl FunctionCallCostProvider for CallCosts {
+        fn get_call_cost(&self, _function_id: &FunctionID) -> FunctionCallCost {
+            FunctionCallCost::from_body_cost(Cost { cost: 2.0, io_ratio: 10.0 })
+        }
+
+        fn function_version(&self, _function_id: &FunctionID) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn single_row_function_calls_are_planned_like_expressions() {
+        // a reduction always returns one row, while `return first` returns none when the body has no answers
+        let queries = [("return sum($age);", true), ("return first $age;", false)];
+        for (return_statement, is_single_row) in queries {
+            let query = format!(
+                "with fun total_age($owner: person) -> integer: match $owner has age $age; {return_statement}
+                match $p isa person; let $total = total_age($p);"
+            );
+            let mut pipeline = typeql::parse_query(&query).unwrap().into_structure().into_pipeline();
+            let function_index = HashMapFunctionSignatureIndex::build(iter::once((
+                FunctionID::Preamble(0),
+                &pipeline.preambles[0].function,
+            )));
+            let parsed = pipeline.stages.remove(0).into_match();
+            let mut context = PipelineTranslationContext::new();
+            let mut parameters = ParameterRegistry::new();
+            let block =
+                translate_match(&mut context, &mut parameters, &function_index, &parsed).unwrap().finish().unwrap();
+            let conjunction = block.conjunction();
+            let isa = (conjunction.constraints().iter())
+                .find_map(|constraint| match constraint {
+                    Constraint::Isa(isa) => Some(isa),
+                    _ => None,
+                })
+                .unwrap();
+            let call =
+                conjunction.constraints().iter().find_map(|constraint| constraint.as_function_call_binding()).unwrap();
+            assert_eq!(call.returns_single_row(), is_single_row);
+            let p = isa.thing().as_variable().unwrap();
+            let total = call.assigned()[0].as_variable().unwrap();
+
+            let person = EntityType::build_from_type_id(TypeID::new(0));
+            let person_types = Arc::new(BTreeSet::from([Type::Entity(person)]));
+            let vertex_annotations =
+                BTreeMap::from([(isa.thing().clone(), person_types.clone()), (isa.type_().clone(), person_types)]);
+            let type_annotations = TypeAnnotations::new(vertex_annotations, HashMap::new());
+            let statistics = StatisticsBuilder::new().entities(person, 100).build();
+
+            let mut builder = ConjunctionPlanBuilder::new(
+                conjunction.scope_id(),
+                None,
+                Vec::new(),
+                &type_annotations,
+                &statistics,
+                PlannerConfig::default(),
+            );
+            builder.register_thing_var(p);
+            builder.register_value_var(total);
+            builder.register_isa(isa);
+            builder.register_function_call_binding(call, &CallCosts);
+            let [p, total] = [p, total].map(|var| VertexId::Variable(builder.graph.variable_index[&var]));
+            let (&call, _) = (builder.graph.elements.iter())
+                .find(|(_, vertex)| matches!(vertex, PlannerVertex::FunctionCall(_)))
+                .unwrap();
+
+            let graph = &builder.graph;
+            let (call_cost, _) = graph.elements[&call].cost_and_metadata(&[p], None, graph).unwrap();
+            assert_eq!(call_cost.cost, 2.0);
+            let total_vertex = graph.elements[&total].as_variable().unwrap();
+            let unrestricted = total_vertex.restriction_based_selectivity(&[]);
+            if is_single_row {
+                // one row per call, whose value is determined by the argument like the output of an expression
+                assert_eq!(call_cost.io_ratio, 1.0);
+                assert!(total_vertex.restriction_based_selectivity(&[p]) < unrestricted);
+            } else {
+                assert_eq!(call_cost.io_ratio, 10.0);
+                assert_eq!(total_vertex.restriction_based_selectivity(&[p]), unrestricted);
+            }
+
+            // the call is placed once its argument is produced, and produces the returned variable
+            let ordering = builder.complete_plan().unwrap().vertex_ordering;
+            let position = |vertex: VertexId| ordering.iter().position(|&ordered| ordered == vertex).unwrap();
+            assert!(position(p) < position(call));
+            assert!(position(call) < position(total));
+        }
+    }
+
+    #[test]
+    fn isa_constraints_implied_by_type_annotations_are_not_planned() {
+        let query = "match $p isa person, has name $n; $q isa person;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let conjunction = block.conjunction();
+        let isas = (conjunction.constraints().iter())
+            .filter_map(|constraint| match constraint {
+                Constraint::Isa(isa) => Some((constraint, isa)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let [(p_isa, p_isa_constraint), (q_isa, q_isa_constraint)] = isas[..] else { panic!("expected two isas") };
+        let has = conjunction.constraints().iter().find_map(|constraint| constraint.as_has()).unwrap();
+
+        let person = Arc::new(BTreeSet::from([Type::Entity(EntityType::build_from_type_id(TypeID::new(0)))]));
+        let name = Arc::new(BTreeSet::from([Type::Attribute(AttributeType::build_from_type_id(TypeID::new(1)))]));
+        let vertex_annotations = BTreeMap::from([
+            (p_isa_constraint.thing().clone(), person.clone()),
+            (p_isa_constraint.type_().clone(), person.clone()),
+            (q_isa_constraint.thing().clone(), person.clone()),
+            (has.attribute().clone(), name),
+        ]);
+        let type_annotations = TypeAnnotations::new(vertex_annotations, HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+        let builder = ConjunctionPlanBuilder::new(
+            conjunction.scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+
+        // `$p` is produced by its `has`, and inferred to be of no type but the label's, so its `isa` is redundant
+        assert!(builder.is_implied_by_type_annotations(p_isa, conjunction));
+        // no other constraint produces `$q`, so its `isa` is planned
+        assert!(!builder.is_implied_by_type_annotations(q_isa, conjunction));
+    }
+
+    // Registers the `has` constraints of the query, whose `$n` and `$m` are names, `$a` and `$b` ages, and other
+    // variables persons, and hands over the builder
+    fn with_has_constraints_registered(query: &str, test: impl FnOnce(ConjunctionPlanBuilder<'_>)) {
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let registry = &context.variable_registry;
+
+        let person = EntityType::build_from_type_id(TypeID::new(0));
+        let [name, age] = [1, 2].map(|id| AttributeType::build_from_type_id(TypeID::new(id)));
+        let mut variables =
+            registry.variable_names().iter().map(|(&var, var_name)| (var, var_name.clone())).collect::<Vec<_>>();
+        variables.sort();
+        let vertex_annotations = variables.iter().map(|(var, var_name)| {
+            let types = match var_name.as_str() {
+                "n" | "m" => Type::Attribute(name),
+                "a" | "b" => Type::Attribute(age),
+                _ => Type::Entity(person),
+            };
+            (Vertex::Variable(*var), Arc::new(BTreeSet::from([types])))
+        });
+        let type_annotations = TypeAnnotations::new(vertex_annotations.collect(), HashMap::new());
+        let statistics = StatisticsBuilder::new()
+            .entities(person, 100)
+            .attributes(name, 80)
+            .attributes(age, 20)
+            .has(ObjectType::Entity(person), name, 150)
+            .has(ObjectType::Entity(person), age, 100)
+            .build();
+
+        let mut builder = ConjunctionPlanBuilder::new(
+            block.conjunction().scope_id(),
+            None,
+            Vec::new(),
+            &type_annotations,
+            &statistics,
+            PlannerConfig::default(),
+        );
+        for (var, _) in &variables {
+            builder.register_thing_var(*var);
+        }
+        for constraint in block.conjunction().constraints() {
+            builder.register_has(constraint.as_has().unwrap());
+        }
+        test(builder)
+    }
+
+    #[test]
+    fn greedy_fallback_plans_every_pattern_once_its_inputs_are_produced() {
+        let query = "match $p has name $n; $p has age $a; $q has name $n; $q has age $b; $r has age $a;";
+        with_has_constraints_registered(query, |builder| {
+            let complete_plan = builder.greedy_plan().unwrap();
+            let graph = &builder.graph;
+            let ordering = &complete_plan.vertex_ordering;
+            assert_eq!(ordering.len(), graph.elements.len());
+            assert_eq!(ordering.iter().collect::<HashSet<_>>().len(), ordering.len());
+            for (position, &vertex) in ordering.iter().enumerate() {
+                if vertex.as_pattern_id().is_some() {
+                    assert!(graph.elements[&vertex].is_valid(vertex, &ordering[..position], graph), "{ordering:?}");
+                }
+            }
+            // the fallback never joins patterns into one step
+            assert!(complete_plan.compound_joins.is_empty());
+        });
+    }
+
+    #[test]
+    fn partial_plans_share_the_ordering_they_extend() {
+        let query = "match $p has name $n; $p has age $a; $q has name $n; $q has age $b; $r has name $m;
+            $r has age $a; $q has name $m;";
+        with_has_constraints_registered(query, |builder| {
+            let graph = &builder.graph;
+            let patterns = graph.pattern_to_variable.keys().copied().collect();
+            let mut plan = PartialCostPlan::new(
+                graph.elements.len(),
+                patterns,
+                iter::empty(),
+                builder.planner_statistics.completion_estimate(),
+            );
+
+            // the ordering is checked against one appended to a vector on every finished step
+            let mut expected_ordering = Vec::new();
+            let mut extension_heap = BinaryHeap::new();
+            while !plan.remaining_patterns.is_empty() {
+                let extended_plans = plan.best_extensions(graph, 3, &mut extension_heap).unwrap();
+                let (finished_step, _) = plan.finalize_current_step(graph);
+                for extended in &extended_plans {
+                    let extended_ordering = extended.vertex_ordering.to_vec();
+                    if extended_ordering.len() == expected_ordering.len() {
+                        assert_eq!(extended_ordering, expected_ordering);
+                    } else {
+                        assert_eq!(extended_ordering, [&expected_ordering[..], &finished_step[..]].concat());
+                    }
+                }
+                // extending a plan leaves its own ordering as it was
+                assert_eq!(plan.vertex_ordering.to_vec(), expected_ordering);
+
+                plan = extended_plans.into_iter().next().unwrap();
+                if plan.vertex_ordering.len() != expected_ordering.len() {
+                    expected_ordering.extend(finished_step);
+                }
+                let expected_patterns =
+                    PatternSet::from_iter(expected_ordering.iter().filter_map(VertexId::as_pattern_id));
+                assert_eq!(plan.planned_patterns, expected_patterns);
+            }
+
+            let (final_step, _) = plan.finalize_current_step(graph);
+            expected_ordering.extend(final_step);
+            let complete_plan = plan.into_complete_plan(graph);
+            assert_eq!(complete_plan.vertex_ordering, expected_ordering);
+            assert_eq!(
+                complete_plan.vertex_ordering.iter().filter(|vertex| vertex.as_pattern_id().is_some()).count(),
+                7
+            );
+        });
+    }
+
+    // The cost of the cheapest complete plan reachable from the plan, taking the same extensions as the planner
+    fn exhaustive_search_cost(plan: PartialCostPlan, graph: &Graph<'_>) -> f64 {
+        if plan.remaining_patterns.is_empty() {
+            return plan.completed_cost().cost;
+        }
+        let extensions = plan.extensions_iter(graph).map(Result::unwrap).collect::<Vec<_>>();
+        let extensions = match extensions.iter().find(|extension| extension.is_trivial(graph)) {
+            Some(trivial) => vec![trivial.clone()],
+            None => extensions,
+        };
+        (extensions.into_iter())
+            .map(|extension| exhaustive_search_cost(plan.extend_with(graph, extension), graph))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn astar_plans_cost_as_little_as_the_cheapest_plan() {
+        let queries = [
+            "match $p has name $n; $p has age $a;",
+            "match $p has name $n; $p has age $a; $q has name $n;",
+            "match $p has name $n; $p has age $a; $q has name $n; $q has age $b;",
+            "match $p has name $n; $p has age $a; $q has name $n; $q has age $b; $r has age $a;",
+            "match $p has name $n; $p has age $a; $q has name $m; $q has age $a; $r has name $n; $r has name $m;",
+        ];
+        for query in queries {
+            with_has_constraints_registered(query, |mut builder| {
+                let patterns: HashSet<_> = builder.graph.pattern_to_variable.keys().copied().collect();
+                let start = PartialCostPlan::new(
+                    builder.graph.elements.len(),
+                    patterns.clone(),
+                    iter::empty(),
+                    builder.planner_statistics.completion_estimate(),
+                );
+                let cheapest_cost = exhaustive_search_cost(start, &builder.graph);
+
+                let astar_plan = builder.astar_plan(patterns.clone(), None).unwrap().expect("within the node budget");
+                let astar_cost = astar_plan.cumulative_cost.cost;
+                let tolerance = 1e-9 * f64::max(cheapest_cost, 1.0);
+                assert!((astar_cost - cheapest_cost).abs() <= tolerance, "{query}: {astar_cost} vs {cheapest_cost}");
+
+                let beam_plan = builder.beam_search_plan(patterns, None).unwrap();
+                assert!(astar_cost <= beam_plan.cumulative_cost.cost + tolerance, "{query}");
+            });
+        }
+    }
+}
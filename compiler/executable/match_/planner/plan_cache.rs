@@ -0,0 +1,223 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use answer::variable::Variable;
+use ir::pattern::{conjunction::Conjunction, disjunction::Disjunction};
+use storage::sequence_number::SequenceNumber;
+use structural_equality::StructuralEquality;
+
+use crate::executable::match_::planner::{
+    plan::PatternVertexId,
+    vertex::{Cost, CostMetaData},
+};
+
+pub const DEFAULT_PLAN_CACHE_SIZE: usize = 1024;
+
+/// LRU cache of the conjunction plans of a database, owned by its query cache and passed into the compiler. Entries
+/// are keyed on the version of the statistics they were planned against, the structural hash of the planned
+/// conjunction and a hash of the planning context: inputs, annotations, versions of the called functions and planner
+/// configuration. Those capture the parts of the schema a plan depends on, and the cache is cleared whenever the
+/// schema of the database changes regardless.
+#[derive(Debug)]
+pub struct PlanCache {
+    state: Mutex<PlanCacheState>,
+    capacity: usize,
+}
+
+#[derive(Debug, Default)]
+struct PlanCacheState {
+    entries: HashMap<PlanCacheKey, PlanCacheEntry>,
+    recency: BTreeMap<u64, PlanCacheKey>, // the keys of the entries by the time they were last used
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl PlanCache {
+    /// A cache keeping at most `capacity` plans, evicting the least recently used. A capacity of 0 caches nothing.
+    pub fn new(capacity: usize) -> Self {
+        Self { state: Mutex::new(PlanCacheState::default()), capacity }
+    }
+
+    /// Drops every cached plan, for when the schema or the data of the database is replaced.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    /// Returns the cached plan for the conjunction. A hash collision with a structurally different conjunction is
+    /// treated as a miss.
+    pub(super) fn get(&self, key: PlanCacheKey, conjunction: &Conjunction) -> Option<Arc<CachedPlan>> {
+        let mut state = self.state.lock().unwrap();
+        let PlanCacheState { entries, recency, clock, .. } = &mut *state;
+        let entry = entries.get_mut(&key).filter(|entry| entry.conjunction.equals(conjunction))?;
+        *clock += 1;
+        recency.remove(&entry.last_used);
+        recency.insert(*clock, key);
+        entry.last_used = *clock;
+        Some(entry.plan.clone())
+    }
+
+    pub(super) fn insert(&self, key: PlanCacheKey, conjunction: &Conjunction, plan: CachedPlan) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.remove(key);
+        while state.entries.len() >= self.capacity {
+            let Some((_, evicted)) = state.recency.pop_first() else { break };
+            state.entries.remove(&evicted);
+        }
+        state.clock += 1;
+        let entry = PlanCacheEntry { conjunction: conjunction.clone(), plan: Arc::new(plan), last_used: state.clock };
+        state.entries.insert(key, entry);
+        let clock = state.clock;
+        state.recency.insert(clock, key);
+    }
+
+    pub(super) fn remove(&self, key: PlanCacheKey) {
+        self.state.lock().unwrap().remove(key)
+    }
+
+    /// Records the outcome of a lookup, returning the total (hits, misses) so far.
+    pub(super) fn record_lookup(&self, is_hit: bool) -> (u64, u64) {
+        let mut state = self.state.lock().unwrap();
+        match is_hit {
+            true => state.hits += 1,
+            false => state.misses += 1,
+        }
+        (state.hits, state.misses)
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PLAN_CACHE_SIZE)
+    }
+}
+
+impl PlanCacheState {
+    fn remove(&mut self, key: PlanCacheKey) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(super) struct PlanCacheKey {
+    statistics_sequence_number: SequenceNumber,
+    conjunction_hash: u64,
+    context_hash: u64,
+}
+
+impl PlanCacheKey {
+    pub(super) fn new(
+        statistics_sequence_number: SequenceNumber,
+        conjunction: &Conjunction,
+        context_hash: u64,
+    ) -> Self {
+        Self { statistics_sequence_number, conjunction_hash: StructuralEquality::hash(conjunction), context_hash }
+    }
+}
+
+#[derive(Debug)]
+struct PlanCacheEntry {
+    conjunction: Conjunction,
+    plan: Arc<CachedPlan>,
+    last_used: u64,
+}
+
+/// A complete plan detached from the planner graph it was computed on. Variable vertices are recorded by variable,
+/// as their vertex ids depend on the order in which the graph was built.
+#[derive(Debug)]
+pub(super) struct CachedPlan {
+    pub(super) ordering: Vec<CachedVertex>,
+    pub(super) metadata: HashMap<PatternVertexId, CostMetaData>,
+    pub(super) compound_joins: Vec<(Variable, Variable)>,
+    pub(super) cost: Cost,
+    pub(super) element_count: usize,
+    pub(super) is_fallback_plan: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) enum CachedVertex {
+    Variable(Variable),
+    Pattern(PatternVertexId),
+}
+
+/// Memo of the disjunction plans computed while compiling a single query. A disjunction is replanned whenever the
+/// search of its parent conjunction costs it, and again when it is lowered or explained, so the plans are memoized
+/// on the disjunction and the inputs bound before it. Only the detached plans are kept, as the planner graphs borrow
+/// the annotations of the compilation.
+#[derive(Debug, Default)]
+pub(super) struct DisjunctionPlanMemo {
+    entries: Mutex<HashMap<DisjunctionMemoKey, DisjunctionMemoEntry>>,
+    lookups: Mutex<(u64, u64)>,
+}
+
+impl DisjunctionPlanMemo {
+    /// Returns the memoized branch plans, in their execution order. A hash collision is treated as a miss.
+    pub(super) fn get(
+        &self,
+        key: DisjunctionMemoKey,
+        disjunction: &Disjunction,
+        inputs: &[Variable],
+    ) -> Option<Arc<Vec<(usize, CachedPlan)>>> {
+        let entries = self.entries.lock().unwrap();
+        let cached = entries
+            .get(&key)
+            .filter(|entry| entry.inputs == inputs && entry.disjunction.equals(disjunction))
+            .map(|entry| entry.branches.clone());
+        let mut lookups = self.lookups.lock().unwrap();
+        match cached.is_some() {
+            true => lookups.0 += 1,
+            false => lookups.1 += 1,
+        }
+        cached
+    }
+
+    pub(super) fn insert(
+        &self,
+        key: DisjunctionMemoKey,
+        disjunction: &Disjunction,
+        inputs: Vec<Variable>,
+        branches: Vec<(usize, CachedPlan)>,
+    ) {
+        let entry = DisjunctionMemoEntry { disjunction: disjunction.clone(), inputs, branches: Arc::new(branches) };
+        self.entries.lock().unwrap().insert(key, entry);
+    }
+
+    /// The (hits, misses) of the lookups so far.
+    pub(super) fn lookups(&self) -> (u64, u64) {
+        *self.lookups.lock().unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(super) struct DisjunctionMemoKey {
+    disjunction_hash: u64,
+    inputs_hash: u64,
+}
+
+impl DisjunctionMemoKey {
+    pub(super) fn new(disjunction_hash: u64, inputs_hash: u64) -> Self {
+        Self { disjunction_hash, inputs_hash }
+    }
+}
+
+#[derive(Debug)]
+struct DisjunctionMemoEntry {
+    disjunction: Disjunction,
+    inputs: Vec<Variable>,
+    branches: Arc<Vec<(usize, CachedPlan)>>,
+}
@@ -0,0 +1,155 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{any::Any, cell::RefCell, sync::Arc};
+
+use error::typedb_error;
+use serde::{Deserialize, Serialize};
+
+use crate::executable::match_::planner::conjunction_executable::ConjunctionExecutable;
+
+/// The layout of serialized executables. Bump whenever any serialized type changes shape: executables serialized in
+/// another format are rejected rather than decoded into the wrong layout.
+pub const PLAN_FORMAT_VERSION: u32 = 7;
+
+/// The schema and statistics an executable was compiled against. Type ids and variable positions in an executable are
+/// only meaningful against the schema it was compiled with, and its plan only reflects the statistics it was costed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanVersions {
+    pub schema_version: u64,
+    pub statistics_version: u64,
+}
+
+// The envelope must keep its layout across plan formats, so that any reader can decode it and check the versions
+// before decoding the executable itself.
+#[derive(Serialize, Deserialize)]
+struct SerializedExecutable {
+    plan_format_version: u32,
+    versions: PlanVersions,
+    executable: Vec<u8>,
+}
+
+typedb_error! {
+    pub PlanSerializationError(component = "Plan serialization", prefix = "PSR") {
+        Encoding(2, "Failed to serialize the executable: {reason}.", reason: String),
+        Decoding(3, "Failed to deserialize the executable: {reason}.", reason: String),
+        PlanFormatVersionMismatch(
+            4,
+            "The executable was serialized in plan format version {found}, but this server reads version {expected}.",
+            expected: u32,
+            found: u32,
+        ),
+        SchemaVersionMismatch(
+            5,
+            "The executable was compiled against schema version {found}, but is being executed against version {expected}.",
+            expected: u64,
+            found: u64,
+        ),
+        StatisticsVersionMismatch(
+            6,
+            "The executable was planned with statistics version {found}, but is being executed with version {expected}.",
+            expected: u64,
+            found: u64,
+        ),
+    }
+}
+
+pub fn serialize_executable(
+    executable: &ConjunctionExecutable,
+    versions: PlanVersions,
+) -> Result<Vec<u8>, PlanSerializationError> {
+    let executable = encode(executable)?;
+    encode(&SerializedExecutable { plan_format_version: PLAN_FORMAT_VERSION, versions, executable })
+}
+
+/// Decodes an executable serialized by `serialize_executable`, provided it was serialized in this plan format and
+/// compiled against the given versions. Type sets shared between instructions of the original are shared again.
+pub fn deserialize_executable(
+    bytes: &[u8],
+    versions: PlanVersions,
+) -> Result<ConjunctionExecutable, PlanSerializationError> {
+    let serialized: SerializedExecutable = decode(bytes)?;
+    if serialized.plan_format_version != PLAN_FORMAT_VERSION {
+        return Err(PlanSerializationError::PlanFormatVersionMismatch {
+            expected: PLAN_FORMAT_VERSION,
+            found: serialized.plan_format_version,
+        });
+    }
+    if serialized.versions.schema_version != versions.schema_version {
+        return Err(PlanSerializationError::SchemaVersionMismatch {
+            expected: versions.schema_version,
+            found: serialized.versions.schema_version,
+        });
+    }
+    if serialized.versions.statistics_version != versions.statistics_version {
+        return Err(PlanSerializationError::StatisticsVersionMismatch {
+            expected: versions.statistics_version,
+            found: serialized.versions.statistics_version,
+        });
+    }
+    let _sharing = shared::SharingScope::open();
+    decode(&serialized.executable)
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, PlanSerializationError> {
+    bincode::serialize(value).map_err(|err| PlanSerializationError::Encoding { reason: err.to_string() })
+}
+
+fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, PlanSerializationError> {
+    bincode::deserialize(bytes).map_err(|err| PlanSerializationError::Decoding { reason: err.to_string() })
+}
+
+/// Serializes `Arc`-shared annotation sets by value. While an executable is being deserialized, equal sets are
+/// deserialized into the same `Arc`, as they were shared when the executable was compiled.
+pub(crate) mod shared {
+    use super::*;
+
+    thread_local! {
+        static SHARED: RefCell<Option<Vec<Arc<dyn Any + Send + Sync>>>> = const { RefCell::new(None) };
+    }
+
+    pub(super) struct SharingScope;
+
+    impl SharingScope {
+        pub(super) fn open() -> Self {
+            SHARED.with(|shared| *shared.borrow_mut() = Some(Vec::new()));
+            Self
+        }
+    }
+
+    impl Drop for SharingScope {
+        fn drop(&mut self) {
+            SHARED.with(|shared| *shared.borrow_mut() = None);
+        }
+    }
+
+    pub(crate) fn serialize<T: Serialize, S: serde::Serializer>(
+        value: &Arc<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        T::serialize(value, serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Arc<T>, D::Error>
+    where
+        T: Deserialize<'de> + PartialEq + Send + Sync + 'static,
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            let Some(shared) = shared.as_mut() else { return Arc::new(value) };
+            match shared.iter().find(|existing| existing.downcast_ref::<T>() == Some(&value)) {
+                Some(existing) => existing.clone().downcast::<T>().unwrap(),
+                None => {
+                    let value = Arc::new(value);
+                    shared.push(value.clone());
+                    value
+                }
+            }
+        }))
+    }
+}
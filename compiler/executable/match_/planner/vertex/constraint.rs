@@ -16,24 +16,26 @@ use ir::pattern::constraint::{
     Has, Iid, IndexedRelation, Isa, Kind, Label, Links, Owns, Plays, Relates, RoleName, Sub, Value,
 };
 use itertools::Itertools;
+use typeql::common::Span;
 
 use crate::{
     annotation::type_annotations::TypeAnnotations,
     executable::match_::{
-        instructions::{type_::TypeListInstruction, CheckInstruction, ConstraintInstruction},
+        instructions::{thing::BoundLinksHint, type_::TypeListInstruction, CheckInstruction, ConstraintInstruction},
         planner::{
             plan::{Graph, QueryPlanningError, VariableVertexId, VertexId},
             vertex::{
-                instance_count, variable::VariableVertex, Cost, CostMetaData, Costed, Direction, Input,
-                ADVANCE_ITERATOR_RELATIVE_COST, OPEN_ITERATOR_RELATIVE_COST,
+                cost::{
+                    has_cost, isa_cost, links_cost, preferred_bound_check_direction, BoundSide, EdgeFanOut,
+                    EdgeSelectivity, EdgeStatistics, MAX_SCAN_SIZE, MIN_SCAN_SIZE,
+                },
+                instance_count, Cost, CostMetaData, Costed, Direction, Input, ADVANCE_ITERATOR_RELATIVE_COST,
+                OPEN_ITERATOR_RELATIVE_COST,
             },
         },
     },
 };
 
-const MIN_SCAN_SIZE: f64 = 1.0;
-const MAX_SCAN_SIZE: f64 = 10e15;
-
 #[derive(Clone, Debug)]
 pub(crate) enum ConstraintVertex<'a> {
     TypeList(TypeListPlanner<'a>),
@@ -72,84 +74,231 @@ impl ConstraintVertex<'_> {
         }
     }
 
-    pub(crate) fn can_join_on(&self, var: VariableVertexId) -> bool {
+    pub(super) fn planner_name(&self) -> &'static str {
         match self {
-            Self::Links(inner) => inner.relation == var || inner.player == var,
-            Self::Has(inner) => inner.owner == var || inner.attribute == var,
-            Self::IndexedRelation(inner) => inner.player_1 == var || inner.player_2 == var,
-            _ => false,
+            Self::TypeList(_) => "TypeList",
+            Self::Iid(_) => "Iid",
+
+            Self::Isa(_) => "Isa",
+            Self::Has(_) => "Has",
+            Self::Links(_) => "Links",
+            Self::IndexedRelation(_) => "IndexedRelation",
+
+            Self::Sub(_) => "Sub",
+            Self::Owns(_) => "Owns",
+            Self::Relates(_) => "Relates",
+            Self::Plays(_) => "Plays",
         }
     }
 
-    pub(crate) fn join_from_direction_and_inputs(
-        &self,
-        dir: &Direction,
-        include: &HashSet<VariableVertexId>,
-        exclude: &HashSet<VariableVertexId>,
-    ) -> Option<VariableVertexId> {
-        // Check whether we have unbound vars for join candidates
+    pub(super) fn unbound_expected_size(&self) -> Option<f64> {
+        match self {
+            Self::Isa(inner) => Some(inner.unrestricted_expected_size),
+            Self::Has(inner) => Some(inner.ownerships.edge_count),
+            Self::Links(inner) => Some(inner.role_players.edge_count),
+            Self::IndexedRelation(inner) => Some(inner.unbound_typed_expected_size),
+            Self::TypeList(_) | Self::Iid(_) | Self::Sub(_) | Self::Owns(_) | Self::Relates(_) | Self::Plays(_) => None,
+        }
+    }
+
+    /// The sizes of the annotation sets this constraint was costed from, if it consulted any.
+    pub(crate) fn annotation_sizes(&self) -> Option<&AnnotationSizes> {
         match self {
-            Self::Links(_) | Self::Has(_) | Self::IndexedRelation(_) => {
-                let unbound_join_variables: Vec<VariableVertexId> = self
-                    .variables()
-                    .filter(|&var| self.can_join_on(var) && (!exclude.contains(&var) || include.contains(&var)))
+            Self::TypeList(inner) => Some(&inner.annotation_sizes),
+            Self::Isa(inner) => Some(&inner.annotation_sizes),
+            Self::Has(inner) => Some(&inner.annotation_sizes),
+            Self::Links(inner) => Some(&inner.annotation_sizes),
+            Self::IndexedRelation(inner) => Some(&inner.annotation_sizes),
+            Self::Iid(_) | Self::Sub(_) | Self::Owns(_) | Self::Relates(_) | Self::Plays(_) => None,
+        }
+    }
+
+    pub(super) fn source_spans(&self) -> Vec<Span> {
+        let span = match self {
+            Self::TypeList(inner) => match *inner.constraint() {
+                TypeListConstraint::Label(label) => label.source_span(),
+                TypeListConstraint::RoleName(role_name) => role_name.source_span(),
+                TypeListConstraint::Kind(kind) => kind.source_span(),
+                TypeListConstraint::Value(value) => value.source_span(),
+            },
+            Self::Iid(inner) => inner.iid().source_span(),
+            Self::Isa(inner) => inner.isa().source_span(),
+            Self::Has(inner) => inner.has().source_span(),
+            Self::Links(inner) => inner.links().source_span(),
+            Self::IndexedRelation(inner) => {
+                // the index stands in for both links constraints of the relation
+                let indexed_relation = inner.indexed_relation();
+                return [indexed_relation.source_span_1(), indexed_relation.source_span_2()]
+                    .into_iter()
+                    .flatten()
                     .collect();
-                if unbound_join_variables.len() == 1 {
-                    return unbound_join_variables.get(0).cloned();
-                }
-                if unbound_join_variables.len() == 0 {
-                    return None;
-                }
             }
-            _ => return None,
+            Self::Sub(inner) => inner.sub().source_span(),
+            Self::Owns(inner) => inner.owns().source_span(),
+            Self::Relates(inner) => inner.relates().source_span(),
+            Self::Plays(inner) => inner.plays().source_span(),
+        };
+        span.into_iter().collect()
+    }
+
+    /// Describes which side is probed to verify a links whose relation and player are both bound by `inputs`, while
+    /// its role is still produced. Any other fully bound edge, such as a has, is checked by a single point lookup.
+    pub(crate) fn bound_check_description(&self, inputs: &[VertexId]) -> Option<String> {
+        let Self::Links(inner) = self else { return None };
+        let is_bound = |var| inputs.contains(&VertexId::Variable(var));
+        if !is_bound(inner.relation) || !is_bound(inner.player) || is_bound(inner.role) {
+            return None;
         }
-        // Pick join candidate based on direction
+        let side = match preferred_bound_check_direction(&inner.role_players) {
+            Direction::Canonical => "relation",
+            Direction::Reverse => "player",
+        };
+        Some(format!("bound check ({side}-side preferred)"))
+    }
+
+    /// The pair of variables leading the tuples of an unbound iteration in the given direction,
+    /// which a compound join can intersect on.
+    pub(crate) fn join_key_from_direction(&self, dir: &Direction) -> Option<(VariableVertexId, VariableVertexId)> {
         let is_canonical = *dir == Direction::Canonical;
-        if is_canonical {
-            match self {
-                Self::Links(inner) => Some(inner.relation),
-                Self::Has(inner) => Some(inner.owner),
-                Self::IndexedRelation(inner) => Some(inner.player_1),
-                _ => None,
-            }
-        } else {
-            match self {
-                Self::Links(inner) => Some(inner.player),
-                Self::Has(inner) => Some(inner.attribute),
-                Self::IndexedRelation(inner) => Some(inner.player_2),
-                _ => None,
-            }
+        match self {
+            Self::Links(inner) if is_canonical => Some((inner.relation, inner.player)),
+            Self::Links(inner) => Some((inner.player, inner.relation)),
+            Self::Has(inner) if is_canonical => Some((inner.owner, inner.attribute)),
+            Self::Has(inner) => Some((inner.attribute, inner.owner)),
+            _ => None,
         }
     }
+}
 
-    pub(crate) fn direction_from_join_var(
+impl JoinContract for ConstraintVertex<'_> {
+    fn join_variables(&self) -> Option<JoinVariables> {
+        match self {
+            Self::Has(inner) => inner.join_variables(),
+            Self::Links(inner) => inner.join_variables(),
+            Self::IndexedRelation(inner) => inner.join_variables(),
+            Self::TypeList(_)
+            | Self::Iid(_)
+            | Self::Isa(_)
+            | Self::Sub(_)
+            | Self::Owns(_)
+            | Self::Relates(_)
+            | Self::Plays(_) => None,
+        }
+    }
+}
+
+/// The variables leading the tuples of an unbound iteration of a constraint, in each direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct JoinVariables {
+    pub(crate) canonical: VariableVertexId,
+    pub(crate) reverse: VariableVertexId,
+}
+
+impl JoinVariables {
+    fn leading(&self, dir: Direction) -> VariableVertexId {
+        match dir {
+            Direction::Canonical => self.canonical,
+            Direction::Reverse => self.reverse,
+        }
+    }
+}
+
+/// How a constraint can be intersected with its neighbours in a join step.
+///
+/// A variable is unbound for a step if it is not in `exclude` (produced by earlier steps),
+/// or if it is in `include` (produced by the ongoing step). The derived methods uphold:
+///  - `join_from_direction_and_inputs` only returns an unbound variable that `can_join_on` accepts.
+///  - With a single unbound join variable, that variable is the join regardless of direction,
+///    and `direction_from_join_var` returns `None`: the bound side decides the direction.
+///  - With both join variables unbound, `direction_from_join_var` inverts `join_from_direction_and_inputs`,
+///    i.e. the join variable for a direction leads the tuples iterated in that direction.
+///  - Constraints without join variables return `None` from both.
+pub(crate) trait JoinContract {
+    fn join_variables(&self) -> Option<JoinVariables>;
+
+    fn can_join_on(&self, var: VariableVertexId) -> bool {
+        self.join_variables().is_some_and(|join| join.canonical == var || join.reverse == var)
+    }
+
+    fn unbound_join_variables(
+        &self,
+        include: &HashSet<VariableVertexId>,
+        exclude: &HashSet<VariableVertexId>,
+    ) -> Vec<VariableVertexId> {
+        let Some(join) = self.join_variables() else { return Vec::new() };
+        [join.canonical, join.reverse]
+            .into_iter()
+            .dedup()
+            .filter(|var| !exclude.contains(var) || include.contains(var))
+            .collect()
+    }
+
+    fn join_from_direction_and_inputs(
+        &self,
+        dir: &Direction,
+        include: &HashSet<VariableVertexId>,
+        exclude: &HashSet<VariableVertexId>,
+    ) -> Option<VariableVertexId> {
+        match self.unbound_join_variables(include, exclude)[..] {
+            [] => None,
+            [var] => Some(var),
+            _ => Some(self.join_variables()?.leading(*dir)),
+        }
+    }
+
+    fn direction_from_join_var(
         &self,
         var: VariableVertexId,
         include: &HashSet<VariableVertexId>,
         exclude: &HashSet<VariableVertexId>,
     ) -> Option<Direction> {
-        // First check if we are in a bound case, in which case we don't care about directions
-        match self {
-            Self::Links(_) | Self::Has(_) | Self::IndexedRelation(_) => {
-                let unbound_join_variables: Vec<VariableVertexId> = self
-                    .variables()
-                    .filter(|&var| self.can_join_on(var) && (!exclude.contains(&var) || include.contains(&var)))
-                    .collect();
-                if unbound_join_variables.len() < 2 {
-                    return None;
+        // in a bound case the direction is chosen by the bound side, not by the join
+        if !self.can_join_on(var) || self.unbound_join_variables(include, exclude).len() < 2 {
+            return None;
+        }
+        Some(Direction::canonical_if(self.join_variables()?.canonical == var))
+    }
+
+    /// Checks the documented invariants over every input configuration of the constraint's `variables`,
+    /// returning a description of the first violation.
+    fn join_contract_violation(&self, variables: &[VariableVertexId]) -> Option<String> {
+        // each variable is in neither, either or both of `include` and `exclude`
+        let configurations = 1usize.checked_shl(2 * variables.len() as u32)?;
+        for configuration in 0..configurations {
+            let mut include = HashSet::new();
+            let mut exclude = HashSet::new();
+            for (i, &var) in variables.iter().enumerate() {
+                if configuration & (1 << (2 * i)) != 0 {
+                    include.insert(var);
+                }
+                if configuration & (1 << (2 * i + 1)) != 0 {
+                    exclude.insert(var);
                 }
             }
-            _ => {
-                return None;
+            let unbound = self.unbound_join_variables(&include, &exclude);
+            for dir in [Direction::Canonical, Direction::Reverse] {
+                let join = self.join_from_direction_and_inputs(&dir, &include, &exclude);
+                let violation = match (&unbound[..], join) {
+                    ([], None) => None,
+                    ([var], Some(join)) if *var == join => None,
+                    ([_, _], Some(join)) if self.direction_from_join_var(join, &include, &exclude) == Some(dir) => None,
+                    _ => Some(format!("join variable {join:?} in direction {dir:?} with unbound {unbound:?}")),
+                };
+                if violation.is_some() {
+                    return violation.map(|violation| format!("{violation}, include {include:?}, exclude {exclude:?}"));
+                }
+            }
+            if unbound.len() < 2 {
+                if let Some(&var) =
+                    variables.iter().find(|&&var| self.direction_from_join_var(var, &include, &exclude).is_some())
+                {
+                    return Some(format!(
+                        "direction from {var:?} with unbound {unbound:?}, include {include:?}, exclude {exclude:?}"
+                    ));
+                }
             }
         }
-        // If unbounded, we choose direction based on the provided join variable
-        match self {
-            Self::Links(inner) => Some(Direction::canonical_if(inner.relation == var)),
-            Self::Has(inner) => Some(Direction::canonical_if(inner.owner == var)),
-            Self::IndexedRelation(inner) => Some(Direction::canonical_if(inner.player_1 == var)),
-            _ => None,
-        }
+        None
     }
 }
 
@@ -235,11 +384,49 @@ impl TypeListConstraint<'_> {
     }
 }
 
+/// The cardinalities of the type annotation sets, and the statistics estimates derived from them, that a constraint
+/// planner consulted when it was created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct AnnotationSizes {
+    type_sets: Vec<(&'static str, usize)>,
+    estimates: Vec<(&'static str, f64)>,
+}
+
+impl AnnotationSizes {
+    fn with_types(mut self, label: &'static str, types: &BTreeSet<Type>) -> Self {
+        self.type_sets.push((label, types.len()));
+        self
+    }
+
+    fn with_estimate(mut self, label: &'static str, estimate: f64) -> Self {
+        self.estimates.push((label, estimate));
+        self
+    }
+
+    pub(crate) fn type_sets(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        self.type_sets.iter().copied()
+    }
+
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (String, f64)> + '_ {
+        let type_sets = self.type_sets.iter().map(|&(label, size)| (format!("{label} types"), size as f64));
+        let estimates = self.estimates.iter().map(|&(label, estimate)| (format!("est. {label}"), estimate));
+        type_sets.chain(estimates)
+    }
+}
+
+impl fmt::Display for AnnotationSizes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries().map(|(label, size)| format!("{label}: {size}"));
+        write!(f, "{}", entries.format(", "))
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct TypeListPlanner<'a> {
     constraint: TypeListConstraint<'a>,
     var: VariableVertexId,
     types: Arc<BTreeSet<Type>>,
+    annotation_sizes: AnnotationSizes,
 }
 
 impl fmt::Debug for TypeListPlanner<'_> {
@@ -262,6 +449,7 @@ impl<'a> TypeListPlanner<'a> {
         Self {
             constraint: TypeListConstraint::Label(label),
             var: variable_index[&label.type_().as_variable().unwrap()],
+            annotation_sizes: AnnotationSizes::default().with_types("listed", &types),
             types,
         }
     }
@@ -275,6 +463,7 @@ impl<'a> TypeListPlanner<'a> {
         Self {
             constraint: TypeListConstraint::RoleName(role_name),
             var: variable_index[&role_name.type_().as_variable().unwrap()],
+            annotation_sizes: AnnotationSizes::default().with_types("listed", &types),
             types,
         }
     }
@@ -288,6 +477,7 @@ impl<'a> TypeListPlanner<'a> {
         Self {
             constraint: TypeListConstraint::Kind(kind),
             var: variable_index[&kind.type_().as_variable().unwrap()],
+            annotation_sizes: AnnotationSizes::default().with_types("listed", &types),
             types,
         }
     }
@@ -301,6 +491,7 @@ impl<'a> TypeListPlanner<'a> {
         Self {
             constraint: TypeListConstraint::Value(value),
             var: variable_index[&value.attribute_type().as_variable().unwrap()],
+            annotation_sizes: AnnotationSizes::default().with_types("listed", &types),
             types,
         }
     }
@@ -384,6 +575,7 @@ pub(crate) struct IsaPlanner<'a> {
     thing: VariableVertexId,
     type_: Input,
     pub(crate) unrestricted_expected_size: f64,
+    annotation_sizes: AnnotationSizes,
 }
 
 impl fmt::Debug for IsaPlanner<'_> {
@@ -401,13 +593,13 @@ impl<'a> IsaPlanner<'a> {
     ) -> Self {
         let thing = variable_index[&isa.thing().as_variable().unwrap()];
         let type_ = Input::from_vertex(isa.type_(), variable_index);
-        let unrestricted_expected_size = type_annotations
-            .vertex_annotations_of(isa.thing())
-            .map(|thing_types| {
-                thing_types.iter().map(|thing_type| instance_count(thing_type, statistics)).sum::<u64>() as f64
-            })
-            .unwrap_or(0.0);
-        Self { isa, thing, type_, unrestricted_expected_size }
+        let thing_types = type_annotations.vertex_annotations_of(isa.thing()).cloned().unwrap_or_default();
+        let unrestricted_expected_size =
+            thing_types.iter().map(|thing_type| instance_count(thing_type, statistics)).sum::<u64>() as f64;
+        let annotation_sizes = AnnotationSizes::default()
+            .with_types("thing", &thing_types)
+            .with_estimate("instances", unrestricted_expected_size);
+        Self { isa, thing, type_, unrestricted_expected_size, annotation_sizes }
     }
 
     fn variables(&self) -> impl Iterator<Item = VariableVertexId> {
@@ -418,50 +610,19 @@ impl<'a> IsaPlanner<'a> {
         self.isa
     }
 
-    pub(crate) fn thing_estimates(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64, f64) {
-        let thing_id = VertexId::Variable(self.thing);
-        let thing = graph.elements()[&thing_id].as_variable().unwrap();
-        let is_thing_bound = inputs.contains(&thing_id);
-        let thing_size = self.unrestricted_expected_size;
-        let thing_selectivity = thing.restriction_based_selectivity(inputs);
-        (is_thing_bound, thing_size, thing_selectivity)
-    }
-
-    pub(crate) fn type_estimate(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64) {
-        let is_type_bound = match &self.type_ {
-            Input::Fixed => true,
-            Input::Variable(var) => inputs.contains(&VertexId::Variable(*var)),
-        };
-        let num_types = match &self.type_ {
-            Input::Fixed => 1.0,
+    fn bound_type_count(&self, inputs: &[VertexId], graph: &Graph<'_>) -> Option<f64> {
+        match &self.type_ {
+            Input::Fixed => Some(1.0),
             Input::Variable(var) => {
                 let type_id = VertexId::Variable(*var);
-                let type_ = graph.elements()[&type_id].as_variable().unwrap();
-                type_.restricted_expected_output_size(inputs)
+                if inputs.contains(&type_id) {
+                    let type_ = graph.elements()[&type_id].as_variable().unwrap();
+                    Some(type_.restricted_expected_output_size(inputs))
+                } else {
+                    None
+                }
             }
-        };
-        (is_type_bound, num_types)
-    }
-
-    pub(crate) fn output_size_estimate(
-        &self,
-        is_thing_bound: bool,
-        thing_size: f64,
-        thing_selectivity: f64,
-        is_type_bound: bool,
-        num_types: f64,
-    ) -> f64 {
-        let mut scan_size = self.unrestricted_expected_size;
-        if is_type_bound {
-            scan_size /= num_types; // account for narrowed prefix
         }
-        if is_thing_bound {
-            scan_size /= thing_size;
-        } else {
-            scan_size *= thing_selectivity; // account for restrictions (like iid), which (we assume) can be used to reduce scan size
-        }
-        scan_size = f64::max(scan_size, VariableVertex::OUTPUT_SIZE_MIN); // TODO: verify if this is useful (part of previous model)
-        scan_size.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
     }
 }
 
@@ -472,17 +633,10 @@ impl Costed for IsaPlanner<'_> {
         _fix_dir: Option<Direction>,
         graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        let (is_thing_bound, thing_size, thing_selectivity) = self.thing_estimates(inputs, graph);
-        let (is_type_bound, num_types) = self.type_estimate(inputs, graph);
-
-        let scan_size =
-            self.output_size_estimate(is_thing_bound, thing_size, thing_selectivity, is_type_bound, num_types);
-        let cost = match is_thing_bound {
-            true => 0.0,
-            false => OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size,
-        };
-        let io_ratio = scan_size;
-        Ok((Cost { cost, io_ratio }, CostMetaData::Direction(Direction::Reverse)))
+        let (is_thing_bound, thing_selectivity) = bound_and_selectivity(self.thing, inputs, graph);
+        let bound_type_count = self.bound_type_count(inputs, graph);
+        let cost = isa_cost(self.unrestricted_expected_size, is_thing_bound, thing_selectivity, bound_type_count);
+        Ok((cost, CostMetaData::Direction(Direction::Reverse)))
     }
 }
 
@@ -491,11 +645,8 @@ pub(crate) struct HasPlanner<'a> {
     has: &'a Has<Variable>,
     pub owner: VariableVertexId,
     pub attribute: VariableVertexId,
-    pub unbound_typed_expected_size: f64,
-    pub unbound_typed_expected_size_canonical: f64,
-    pub unbound_typed_expected_size_reverse: f64,
-    pub owner_size: f64,
-    pub attribute_size: f64,
+    pub(crate) ownerships: EdgeStatistics,
+    annotation_sizes: AnnotationSizes,
 }
 
 impl fmt::Debug for HasPlanner<'_> {
@@ -516,53 +667,18 @@ impl<'a> HasPlanner<'a> {
 
         let owner_types = &**type_annotations.vertex_annotations_of(owner).unwrap();
         let attribute_types = &**type_annotations.vertex_annotations_of(attribute).unwrap();
-
-        let unbound_typed_expected_size = itertools::iproduct!(owner_types, attribute_types)
-            .filter_map(|(owner, attribute)| {
-                statistics.has_attribute_counts.get(&owner.as_object_type())?.get(&attribute.as_attribute_type())
-            })
-            .sum::<u64>() as f64;
-
-        //  We should compute that we are doing multiple seeks() and merge-sorting.
-        //  in general, we assume the cardinality is small, so we just open 1 iterator and post-filter
-        let unbound_typed_expected_size_canonical = owner_types
-            .iter()
-            .filter_map(|owner| statistics.has_attribute_counts.get(&owner.as_object_type()))
-            .flat_map(|counts| counts.values())
-            .sum::<u64>() as f64;
-
-        let owner_size = owner_types
-            .iter()
-            .filter_map(|type_| match type_ {
-                answer::Type::Entity(type_) => statistics.entity_counts.get(type_),
-                answer::Type::Relation(type_) => statistics.relation_counts.get(type_),
-                _ => None,
-            })
-            .sum::<u64>() as f64;
-
-        let unbound_typed_expected_size_reverse = attribute_types
-            .iter()
-            .filter_map(|attribute| statistics.attribute_owner_counts.get(&attribute.as_attribute_type()))
-            .flat_map(|counts| counts.values())
-            .sum::<u64>() as f64;
-
-        let attribute_size = attribute_types
-            .iter()
-            .filter_map(|type_| match type_ {
-                answer::Type::Attribute(type_) => statistics.attribute_counts.get(type_),
-                _ => None,
-            })
-            .sum::<u64>() as f64;
+        let ownerships = EdgeStatistics::for_has(owner_types, attribute_types, statistics);
+        let annotation_sizes = AnnotationSizes::default()
+            .with_types("owner", owner_types)
+            .with_types("attribute", attribute_types)
+            .with_estimate("ownerships", ownerships.edge_count);
 
         Self {
             has,
             owner: variable_index[&owner.as_variable().unwrap()],
             attribute: variable_index[&attribute.as_variable().unwrap()],
-            unbound_typed_expected_size,
-            unbound_typed_expected_size_canonical,
-            unbound_typed_expected_size_reverse,
-            owner_size,
-            attribute_size,
+            ownerships,
+            annotation_sizes,
         }
     }
 
@@ -573,86 +689,11 @@ impl<'a> HasPlanner<'a> {
     pub(crate) fn has(&self) -> &Has<Variable> {
         self.has
     }
+}
 
-    pub(crate) fn owner_estimates(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64, f64) {
-        let owner_id = VertexId::Variable(self.owner);
-        let owner = &graph.elements()[&owner_id].as_variable().unwrap();
-        let is_owner_bound = inputs.contains(&owner_id);
-        let owner_size = self.owner_size;
-        let owner_selectivity = owner.restriction_based_selectivity(inputs);
-        (is_owner_bound, owner_size, owner_selectivity)
-    }
-
-    pub(crate) fn attribute_estimates(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64, f64) {
-        let attribute_id = VertexId::Variable(self.attribute);
-        let attribute = &graph.elements()[&attribute_id].as_variable().unwrap();
-        let is_attribute_bound = inputs.contains(&attribute_id);
-        let attribute_size = self.attribute_size;
-        let attribute_selectivity = attribute.restriction_based_selectivity(inputs);
-        (is_attribute_bound, attribute_size, attribute_selectivity)
-    }
-
-    pub(crate) fn canonical_scan_size_estimate(
-        &self,
-        is_owner_bound: bool,
-        owner_size: f64,
-        owner_selectivity: f64,
-        is_attribute_bound: bool,
-        attribute_size: f64,
-    ) -> f64 {
-        let mut scan_size_canonical = self.unbound_typed_expected_size_canonical;
-        if is_owner_bound {
-            scan_size_canonical = self.unbound_typed_expected_size / owner_size; // If owner is bound, assume we only scan correct attribute types
-            if is_attribute_bound {
-                scan_size_canonical /= attribute_size;
-            }
-        } else {
-            scan_size_canonical *= owner_selectivity; // restrictions (like iid) apply if var still unbound
-        }
-        scan_size_canonical.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
-    }
-
-    pub(crate) fn reverse_scan_size_estimate(
-        &self,
-        is_owner_bound: bool,
-        owner_size: f64,
-        is_attribute_bound: bool,
-        attribute_size: f64,
-        attribute_selectivity: f64,
-    ) -> f64 {
-        let mut scan_size_reverse = self.unbound_typed_expected_size_reverse;
-        if is_attribute_bound {
-            scan_size_reverse = self.unbound_typed_expected_size / attribute_size; // If attribute is bound, assume we only scan correct owner types
-            if is_owner_bound {
-                scan_size_reverse /= owner_size;
-            }
-        } else {
-            scan_size_reverse *= attribute_selectivity; // restrictions (like iid) apply if var still unbound
-        }
-        scan_size_reverse.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
-    }
-
-    pub(crate) fn output_size_estimate(
-        &self,
-        is_owner_bound: bool,
-        owner_size: f64,
-        owner_selectivity: f64,
-        is_attribute_bound: bool,
-        attribute_size: f64,
-        attribute_selectivity: f64,
-    ) -> f64 {
-        let mut scan_size = self.unbound_typed_expected_size;
-        if is_owner_bound {
-            scan_size /= owner_size;
-        } else {
-            scan_size *= owner_selectivity;
-        }
-        if is_attribute_bound {
-            scan_size /= attribute_size;
-        } else {
-            scan_size *= attribute_selectivity;
-        }
-        scan_size.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
+impl JoinContract for HasPlanner<'_> {
+    fn join_variables(&self) -> Option<JoinVariables> {
+        Some(JoinVariables { canonical: self.owner, reverse: self.attribute })
     }
 }
 
@@ -663,39 +704,15 @@ impl Costed for HasPlanner<'_> {
         fix_dir: Option<Direction>,
         graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        let (is_owner_bound, owner_size, owner_selectivity) = self.owner_estimates(inputs, graph);
-        let (is_attribute_bound, attribute_size, attribute_selectivity) = self.attribute_estimates(inputs, graph);
-
-        let scan_size_canonical = self.canonical_scan_size_estimate(
-            is_owner_bound,
-            owner_size,
-            owner_selectivity,
-            is_attribute_bound,
-            attribute_size,
-        );
-        let scan_size_reverse = self.reverse_scan_size_estimate(
-            is_owner_bound,
-            owner_size,
-            is_attribute_bound,
-            attribute_size,
-            attribute_selectivity,
-        );
-        let io_ratio = self.output_size_estimate(
-            is_owner_bound,
-            owner_size,
-            owner_selectivity,
-            is_attribute_bound,
-            attribute_size,
-            attribute_selectivity,
+        let (is_owner_bound, owner_selectivity) = bound_and_selectivity(self.owner, inputs, graph);
+        let (is_attribute_bound, attribute_selectivity) = bound_and_selectivity(self.attribute, inputs, graph);
+        let (cost, direction) = has_cost(
+            &self.ownerships,
+            BoundSide::new(is_owner_bound, is_attribute_bound),
+            EdgeSelectivity { canonical: owner_selectivity, reverse: attribute_selectivity },
+            fix_dir,
         );
-
-        let direction = fix_dir.unwrap_or(Direction::canonical_if(scan_size_canonical <= scan_size_reverse));
-        let cost = if direction == Direction::Canonical {
-            OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size_canonical
-        } else {
-            OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size_reverse
-        };
-        Ok((Cost { cost, io_ratio }, CostMetaData::Direction(direction)))
+        Ok((cost, CostMetaData::Direction(direction)))
     }
 }
 
@@ -705,11 +722,10 @@ pub(crate) struct LinksPlanner<'a> {
     pub relation: VariableVertexId,
     pub player: VariableVertexId,
     pub role: VariableVertexId,
-    pub(crate) unbound_typed_expected_size: f64,
-    unbound_typed_expected_size_canonical: f64,
-    unbound_typed_expected_size_reverse: f64,
-    relation_size: f64,
-    player_size: f64,
+    pub(crate) role_players: EdgeStatistics,
+    fan_out: EdgeFanOut,
+    is_relation_anonymous: bool,
+    annotation_sizes: AnnotationSizes,
 }
 
 impl fmt::Debug for LinksPlanner<'_> {
@@ -733,62 +749,13 @@ impl<'a> LinksPlanner<'a> {
         let player_types = &**type_annotations.vertex_annotations_of(player).unwrap();
 
         let constraint_types = type_annotations.constraint_annotations_of(links.clone().into()).unwrap().as_links();
-
-        let unbound_typed_expected_size = constraint_types
-            .relation_to_role()
-            .iter()
-            .flat_map(|(relation, roles)| {
-                roles.iter().cartesian_product(player_types).flat_map(|(role, player)| {
-                    statistics
-                        .relation_role_player_counts
-                        .get(&relation.as_relation_type())?
-                        .get(&role.as_role_type())?
-                        .get(&player.as_object_type())
-                })
-            })
-            .sum::<u64>() as f64;
-
-        let unbound_typed_expected_size_canonical = relation_types
-            .iter()
-            .filter_map(|relation| {
-                Some(statistics.relation_role_player_counts.get(&relation.as_relation_type())?.values().flat_map(
-                    |player_to_count| {
-                        player_types.iter().filter_map(|player| player_to_count.get(&player.as_object_type()))
-                    },
-                ))
-            })
-            .flatten()
-            .sum::<u64>() as f64;
-
-        let relation_size = relation_types
-            .iter()
-            .filter_map(|type_| match type_ {
-                answer::Type::Entity(type_) => statistics.entity_counts.get(type_),
-                answer::Type::Relation(type_) => statistics.relation_counts.get(type_),
-                _ => None,
-            })
-            .sum::<u64>() as f64;
-
-        let unbound_typed_expected_size_reverse = player_types
-            .iter()
-            .filter_map(|player| {
-                Some(statistics.player_role_relation_counts.get(&player.as_object_type())?.values().flat_map(
-                    |relation_to_count| {
-                        relation_types.iter().filter_map(|relation| relation_to_count.get(&relation.as_relation_type()))
-                    },
-                ))
-            })
-            .flatten()
-            .sum::<u64>() as f64;
-
-        let player_size = player_types
-            .iter()
-            .filter_map(|type_| match type_ {
-                answer::Type::Entity(type_) => statistics.entity_counts.get(type_),
-                answer::Type::Relation(type_) => statistics.relation_counts.get(type_),
-                _ => None,
-            })
-            .sum::<u64>() as f64;
+        let role_players =
+            EdgeStatistics::for_links(&constraint_types.relation_to_role(), relation_types, player_types, statistics);
+        let fan_out = EdgeFanOut::for_links(relation_types, player_types, statistics);
+        let annotation_sizes = AnnotationSizes::default()
+            .with_types("relation", relation_types)
+            .with_types("player", player_types)
+            .with_estimate("role players", role_players.edge_count);
 
         let relation = relation.as_variable().unwrap();
         let player = player.as_variable().unwrap();
@@ -799,14 +766,18 @@ impl<'a> LinksPlanner<'a> {
             relation: variable_index[&relation],
             player: variable_index[&player],
             role: variable_index[&role],
-            unbound_typed_expected_size,
-            unbound_typed_expected_size_canonical,
-            unbound_typed_expected_size_reverse,
-            relation_size,
-            player_size,
+            role_players,
+            fan_out,
+            is_relation_anonymous: false,
+            annotation_sizes,
         }
     }
 
+    /// Marks the relation as an anonymous relation that is only used by this constraint.
+    pub(crate) fn with_anonymous_relation(self) -> Self {
+        Self { is_relation_anonymous: true, ..self }
+    }
+
     fn variables(&self) -> impl Iterator<Item = VariableVertexId> {
         [self.relation, self.player, self.role].into_iter()
     }
@@ -815,85 +786,18 @@ impl<'a> LinksPlanner<'a> {
         self.links
     }
 
-    pub(crate) fn relation_estimates(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64, f64) {
-        let relation_id = VertexId::Variable(self.relation);
-        let relation = &graph.elements()[&relation_id].as_variable().unwrap();
-        let is_relation_bound = inputs.contains(&relation_id);
-        let relation_size = self.relation_size;
-        let relation_selectivity = relation.restriction_based_selectivity(inputs);
-        (is_relation_bound, relation_size, relation_selectivity)
-    }
-
-    pub(crate) fn player_estimates(&self, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64, f64) {
-        let player_id = VertexId::Variable(self.player);
-        let player = &graph.elements()[&player_id].as_variable().unwrap();
-        let is_player_bound = inputs.contains(&player_id);
-        let player_size = self.player_size;
-        let player_selectivity = player.restriction_based_selectivity(inputs);
-        (is_player_bound, player_size, player_selectivity)
-    }
-
-    pub(crate) fn canonical_scan_size_estimate(
-        &self,
-        is_relation_bound: bool,
-        relation_size: f64,
-        relation_selectivity: f64,
-        is_player_bound: bool,
-        player_size: f64,
-    ) -> f64 {
-        let mut scan_size_canonical = self.unbound_typed_expected_size_canonical;
-        if is_relation_bound {
-            scan_size_canonical = self.unbound_typed_expected_size / relation_size; // If relation is bound, assume we only scan correct player types
-            if is_player_bound {
-                scan_size_canonical /= player_size;
-            } // Ignore nested selectivity for now
-        } else {
-            scan_size_canonical *= relation_selectivity; // restrictions (like iid) apply if var still unbound
-        }
-        scan_size_canonical.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
-    }
-
-    pub(crate) fn reverse_scan_size_estimate(
-        &self,
-        is_relation_bound: bool,
-        relation_size: f64,
-        is_player_bound: bool,
-        player_size: f64,
-        player_selectivity: f64,
-    ) -> f64 {
-        let mut scan_size_reverse = self.unbound_typed_expected_size_reverse;
-        if is_player_bound {
-            scan_size_reverse = self.unbound_typed_expected_size / player_size; // If player is bound, assume we only scan correct relation types
-            if is_relation_bound {
-                scan_size_reverse /= relation_size;
-            } // Ignore nested selectivity for now
-        } else {
-            scan_size_reverse *= player_selectivity; // restrictions (like iid) apply if var still unbound
+    pub(crate) fn bound_check_hint(&self) -> BoundLinksHint {
+        BoundLinksHint {
+            prefer_relation_side: preferred_bound_check_direction(&self.role_players) == Direction::Canonical,
+            relation_fan_out: self.fan_out.canonical.clone(),
+            player_fan_out: self.fan_out.reverse.clone(),
         }
-        scan_size_reverse.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
     }
+}
 
-    pub(crate) fn output_size_estimate(
-        &self,
-        is_relation_bound: bool,
-        relation_size: f64,
-        relation_selectivity: f64,
-        is_player_bound: bool,
-        player_size: f64,
-        player_selectivity: f64,
-    ) -> f64 {
-        let mut scan_size = self.unbound_typed_expected_size;
-        if is_relation_bound {
-            scan_size /= relation_size;
-        } else {
-            scan_size *= relation_selectivity;
-        }
-        if is_player_bound {
-            scan_size /= player_size;
-        } else {
-            scan_size *= player_selectivity;
-        }
-        scan_size.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
+impl JoinContract for LinksPlanner<'_> {
+    fn join_variables(&self) -> Option<JoinVariables> {
+        Some(JoinVariables { canonical: self.relation, reverse: self.player })
     }
 }
 
@@ -904,43 +808,29 @@ impl Costed for LinksPlanner<'_> {
         fix_dir: Option<Direction>,
         graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        let (is_relation_bound, relation_size, relation_selectivity) = self.relation_estimates(inputs, graph);
-        let (is_player_bound, player_size, player_selectivity) = self.player_estimates(inputs, graph);
-
-        let scan_size_canonical = self.canonical_scan_size_estimate(
-            is_relation_bound,
-            relation_size,
-            relation_selectivity,
-            is_player_bound,
-            player_size,
-        );
-        let scan_size_reverse = self.reverse_scan_size_estimate(
-            is_relation_bound,
-            relation_size,
-            is_player_bound,
-            player_size,
-            player_selectivity,
-        );
-        let io_ratio = self.output_size_estimate(
-            is_relation_bound,
-            relation_size,
-            relation_selectivity,
-            is_player_bound,
-            player_size,
-            player_selectivity,
+        let (is_relation_bound, relation_selectivity) = bound_and_selectivity(self.relation, inputs, graph);
+        let (is_player_bound, player_selectivity) = bound_and_selectivity(self.player, inputs, graph);
+        // an anonymous relation is never joined on, so the relations are best found from the player
+        let fix_dir = match fix_dir {
+            None if self.is_relation_anonymous && !is_relation_bound => Some(Direction::Reverse),
+            fix_dir => fix_dir,
+        };
+        let (cost, direction) = links_cost(
+            &self.role_players,
+            BoundSide::new(is_relation_bound, is_player_bound),
+            EdgeSelectivity { canonical: relation_selectivity, reverse: player_selectivity },
+            fix_dir,
         );
-        let cost: f64;
-        let direction = fix_dir.unwrap_or(Direction::canonical_if(scan_size_canonical <= scan_size_reverse));
-
-        if direction == Direction::Canonical {
-            cost = OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size_canonical;
-        } else {
-            cost = OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size_reverse;
-        }
-        Ok((Cost { cost, io_ratio }, CostMetaData::Direction(direction)))
+        Ok((cost, CostMetaData::Direction(direction)))
     }
 }
 
+fn bound_and_selectivity(var: VariableVertexId, inputs: &[VertexId], graph: &Graph<'_>) -> (bool, f64) {
+    let var_id = VertexId::Variable(var);
+    let variable = graph.elements()[&var_id].as_variable().unwrap();
+    (inputs.contains(&var_id), variable.restriction_based_selectivity(inputs))
+}
+
 #[derive(Clone)]
 pub(crate) struct IndexedRelationPlanner<'a> {
     indexed_relation: &'a IndexedRelation<Variable>,
@@ -952,6 +842,7 @@ pub(crate) struct IndexedRelationPlanner<'a> {
     unbound_typed_expected_size: f64,
     player_1_size: f64,
     player_2_size: f64,
+    annotation_sizes: AnnotationSizes,
 }
 
 impl fmt::Debug for IndexedRelationPlanner<'_> {
@@ -975,7 +866,7 @@ impl<'a> IndexedRelationPlanner<'a> {
 
         let player_1_types = &**type_annotations.vertex_annotations_of(player_1).unwrap();
         let player_2_types = &**type_annotations.vertex_annotations_of(player_2).unwrap();
-        let _relation_types = &**type_annotations.vertex_annotations_of(relation).unwrap();
+        let relation_types = &**type_annotations.vertex_annotations_of(relation).unwrap();
 
         // let constraint_types =
         //     type_annotations.constraint_annotations_of(indexed_relation.clone().into()).unwrap().as_links();
@@ -1007,6 +898,12 @@ impl<'a> IndexedRelationPlanner<'a> {
             })
             .sum::<u64>() as f64;
 
+        let annotation_sizes = AnnotationSizes::default()
+            .with_types("player 1", player_1_types)
+            .with_types("player 2", player_2_types)
+            .with_types("relation", relation_types)
+            .with_estimate("indexed pairs", unbound_typed_expected_size);
+
         let player_1 = player_1.as_variable().unwrap();
         let player_2 = player_2.as_variable().unwrap();
         let relation = relation.as_variable().unwrap();
@@ -1023,6 +920,7 @@ impl<'a> IndexedRelationPlanner<'a> {
             unbound_typed_expected_size,
             player_1_size,
             player_2_size,
+            annotation_sizes,
         }
     }
 
@@ -1120,6 +1018,12 @@ impl<'a> IndexedRelationPlanner<'a> {
     }
 }
 
+impl JoinContract for IndexedRelationPlanner<'_> {
+    fn join_variables(&self) -> Option<JoinVariables> {
+        Some(JoinVariables { canonical: self.player_1, reverse: self.player_2 })
+    }
+}
+
 impl Costed for IndexedRelationPlanner<'_> {
     fn cost_and_metadata(
         &self,
@@ -1314,3 +1218,182 @@ impl Costed for PlaysPlanner<'_> {
         Ok((Cost::in_mem_complex_with_ratio(1.0), CostMetaData::Direction(Direction::Canonical)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    use answer::variable::Variable;
+    use concept::thing::statistics::Statistics;
+    use ir::{
+        pattern::{
+            constraint::{Constraint, IndexedRelation},
+            Vertex,
+        },
+        pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+        translation::{match_::translate_match, PipelineTranslationContext},
+    };
+    use itertools::Itertools;
+    use storage::sequence_number::SequenceNumber;
+
+    use super::{
+        AnnotationSizes, ConstraintVertex, HasPlanner, IndexedRelationPlanner, IsaPlanner, JoinContract, JoinVariables,
+        LinksPlanner, OwnsPlanner, PlaysPlanner, RelatesPlanner, SubPlanner,
+    };
+    use crate::{
+        annotation::type_annotations::TypeAnnotations,
+        executable::match_::planner::{
+            plan::VariableVertexId,
+            vertex::{
+                cost::{EdgeFanOut, EdgeStatistics},
+                Direction, Input,
+            },
+        },
+    };
+
+    impl JoinContract for JoinVariables {
+        fn join_variables(&self) -> Option<JoinVariables> {
+            Some(*self)
+        }
+    }
+
+    #[test]
+    fn join_contract_holds_for_all_constraint_kinds() {
+        let query = "match
+            $x isa $t; $x has $a; $r links ($role: $p);
+            $t sub $s; $o owns $at; $rel relates $rl; $pl plays $pr;";
+        let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut context = PipelineTranslationContext::new();
+        let mut parameters = ParameterRegistry::new();
+        let block = translate_match(&mut context, &mut parameters, &HashMapFunctionSignatureIndex::empty(), &parsed)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let constraints = block.conjunction().constraints();
+        let variable_index: HashMap<Variable, VariableVertexId> = constraints
+            .iter()
+            .flat_map(|constraint| constraint.ids())
+            .unique()
+            .enumerate()
+            .map(|(i, variable)| (variable, VariableVertexId::new(i)))
+            .collect();
+        let var = |vertex: &Vertex<Variable>| variable_index[&vertex.as_variable().unwrap()];
+        let type_annotations = TypeAnnotations::new(BTreeMap::new(), HashMap::new());
+        let statistics = Statistics::new(SequenceNumber::new(0));
+        let edges = EdgeStatistics::new(1, 1, 1, 1, 1);
+
+        let links = constraints.iter().find_map(|constraint| constraint.as_links()).unwrap();
+        let has = constraints.iter().find_map(|constraint| constraint.as_has()).unwrap();
+        let indexed_relation = IndexedRelation::new(
+            links.player().as_variable().unwrap(),
+            has.owner().as_variable().unwrap(),
+            links.relation().as_variable().unwrap(),
+            links.role_type().as_variable().unwrap(),
+            has.attribute().as_variable().unwrap(),
+            None,
+            None,
+        );
+
+        let mut table = Vec::new();
+        for constraint in constraints {
+            let (vertex, expected) = match constraint {
+                Constraint::Isa(isa) => {
+                    let thing = var(isa.thing());
+                    let type_ = Input::from_vertex(isa.type_(), &variable_index);
+                    (
+                        ConstraintVertex::Isa(IsaPlanner {
+                            isa,
+                            thing,
+                            type_,
+                            unrestricted_expected_size: 1.0,
+                            annotation_sizes: AnnotationSizes::default(),
+                        }),
+                        None,
+                    )
+                }
+                Constraint::Has(has) => {
+                    let (owner, attribute) = (var(has.owner()), var(has.attribute()));
+                    let planner = HasPlanner {
+                        has,
+                        owner,
+                        attribute,
+                        ownerships: edges,
+                        annotation_sizes: AnnotationSizes::default(),
+                    };
+                    (ConstraintVertex::Has(planner), Some(JoinVariables { canonical: owner, reverse: attribute }))
+                }
+                Constraint::Links(links) => {
+                    let (relation, player, role) = (var(links.relation()), var(links.player()), var(links.role_type()));
+                    let fan_out = EdgeFanOut::default();
+                    let planner = LinksPlanner {
+                        links,
+                        relation,
+                        player,
+                        role,
+                        role_players: edges,
+                        fan_out,
+                        is_relation_anonymous: false,
+                        annotation_sizes: AnnotationSizes::default(),
+                    };
+                    (ConstraintVertex::Links(planner), Some(JoinVariables { canonical: relation, reverse: player }))
+                }
+                Constraint::Sub(sub) => {
+                    (ConstraintVertex::Sub(SubPlanner::from_constraint(sub, &variable_index, &type_annotations)), None)
+                }
+                Constraint::Owns(owns) => {
+                    let planner = OwnsPlanner::from_constraint(owns, &variable_index, &type_annotations, &statistics);
+                    (ConstraintVertex::Owns(planner), None)
+                }
+                Constraint::Relates(relates) => {
+                    let planner =
+                        RelatesPlanner::from_constraint(relates, &variable_index, &type_annotations, &statistics);
+                    (ConstraintVertex::Relates(planner), None)
+                }
+                Constraint::Plays(plays) => {
+                    let planner = PlaysPlanner::from_constraint(plays, &variable_index, &type_annotations, &statistics);
+                    (ConstraintVertex::Plays(planner), None)
+                }
+                _ => continue,
+            };
+            table.push((vertex, expected));
+        }
+        let (player_1, player_2) = (var(indexed_relation.player_1()), var(indexed_relation.player_2()));
+        let planner = IndexedRelationPlanner {
+            indexed_relation: &indexed_relation,
+            player_1,
+            player_2,
+            relation: var(indexed_relation.relation()),
+            role_1: var(indexed_relation.role_type_1()),
+            role_2: var(indexed_relation.role_type_2()),
+            unbound_typed_expected_size: 1.0,
+            player_1_size: 1.0,
+            player_2_size: 1.0,
+            annotation_sizes: AnnotationSizes::default(),
+        };
+        table.push((
+            ConstraintVertex::IndexedRelation(planner),
+            Some(JoinVariables { canonical: player_1, reverse: player_2 }),
+        ));
+
+        let kinds = table.iter().map(|(vertex, _)| vertex.planner_name()).sorted().collect_vec();
+        assert_eq!(kinds, ["Has", "IndexedRelation", "Isa", "Links", "Owns", "Plays", "Relates", "Sub"]);
+        for (vertex, expected) in &table {
+            assert_eq!(vertex.join_variables(), *expected, "{}", vertex.planner_name());
+            let variables = vertex.variables().collect_vec();
+            assert_eq!(vertex.join_contract_violation(&variables), None, "{}", vertex.planner_name());
+        }
+    }
+
+    #[test]
+    fn links_with_bound_role_joins_on_relation_and_player() {
+        let (relation, player, role) = (VariableVertexId::new(0), VariableVertexId::new(1), VariableVertexId::new(2));
+        let links = JoinVariables { canonical: relation, reverse: player };
+        let bound_role = HashSet::from([role]);
+        for dir in [Direction::Canonical, Direction::Reverse] {
+            let join = links.join_from_direction_and_inputs(&dir, &HashSet::new(), &bound_role).unwrap();
+            assert_ne!(join, role);
+            assert_eq!(links.direction_from_join_var(join, &HashSet::new(), &bound_role), Some(dir));
+        }
+        assert_eq!(links.direction_from_join_var(role, &HashSet::new(), &bound_role), None);
+    }
+}
@@ -0,0 +1,551 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use answer::Type;
+use concept::thing::statistics::Statistics;
+use itertools::Itertools;
+
+use crate::executable::match_::planner::vertex::{
+    instance_count, variable::VariableVertex, Cost, Direction, ADVANCE_ITERATOR_RELATIVE_COST,
+    OPEN_ITERATOR_RELATIVE_COST,
+};
+
+pub(super) const MIN_SCAN_SIZE: f64 = 1.0;
+pub(super) const MAX_SCAN_SIZE: f64 = 10e15;
+
+/// Which ends of a thing edge (`has`, `links`) are bound when the constraint is executed. The canonical end is the
+/// one the canonical direction iterates from: the owner of a `has`, the relation of a `links`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BoundSide {
+    Neither,
+    Canonical,
+    Reverse,
+    Both,
+}
+
+impl BoundSide {
+    pub(crate) fn new(is_canonical_bound: bool, is_reverse_bound: bool) -> Self {
+        match (is_canonical_bound, is_reverse_bound) {
+            (false, false) => Self::Neither,
+            (true, false) => Self::Canonical,
+            (false, true) => Self::Reverse,
+            (true, true) => Self::Both,
+        }
+    }
+
+    fn is_canonical_bound(self) -> bool {
+        matches!(self, Self::Canonical | Self::Both)
+    }
+
+    fn is_reverse_bound(self) -> bool {
+        matches!(self, Self::Reverse | Self::Both)
+    }
+}
+
+/// Instance counts of a thing edge restricted to the annotated types of its ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct EdgeStatistics {
+    /// Edges between any of the annotated types of both ends.
+    pub(crate) edge_count: f64,
+    /// Edges scanned from the annotated types of the canonical end, to any type at the other end.
+    pub(crate) canonical_scan_count: f64,
+    /// Edges scanned from the annotated types of the reverse end, to any type at the other end.
+    pub(crate) reverse_scan_count: f64,
+    pub(crate) canonical_count: f64,
+    pub(crate) reverse_count: f64,
+}
+
+impl EdgeStatistics {
+    pub(crate) fn new(
+        edge_count: u64,
+        canonical_scan_count: u64,
+        reverse_scan_count: u64,
+        canonical_count: u64,
+        reverse_count: u64,
+    ) -> Self {
+        Self {
+            edge_count: edge_count as f64,
+            canonical_scan_count: canonical_scan_count as f64,
+            reverse_scan_count: reverse_scan_count as f64,
+            canonical_count: canonical_count as f64,
+            reverse_count: reverse_count as f64,
+        }
+    }
+
+    pub(crate) fn for_has(
+        owner_types: &BTreeSet<Type>,
+        attribute_types: &BTreeSet<Type>,
+        statistics: &Statistics,
+    ) -> Self {
+        let edge_count = itertools::iproduct!(owner_types, attribute_types)
+            .filter_map(|(owner, attribute)| {
+                statistics.has_attribute_counts.get(&owner.as_object_type())?.get(&attribute.as_attribute_type())
+            })
+            .sum();
+
+        //  We should compute that we are doing multiple seeks() and merge-sorting.
+        //  in general, we assume the cardinality is small, so we just open 1 iterator and post-filter
+        let canonical_scan_count = owner_types
+            .iter()
+            .filter_map(|owner| statistics.has_attribute_counts.get(&owner.as_object_type()))
+            .flat_map(|counts| counts.values())
+            .sum();
+
+        let reverse_scan_count = attribute_types
+            .iter()
+            .filter_map(|attribute| statistics.attribute_owner_counts.get(&attribute.as_attribute_type()))
+            .flat_map(|counts| counts.values())
+            .sum();
+
+        let attribute_count = attribute_types
+            .iter()
+            .filter_map(|type_| match type_ {
+                Type::Attribute(type_) => statistics.attribute_counts.get(type_),
+                _ => None,
+            })
+            .sum();
+
+        Self::new(
+            edge_count,
+            canonical_scan_count,
+            reverse_scan_count,
+            object_count(owner_types, statistics),
+            attribute_count,
+        )
+    }
+
+    pub(crate) fn for_links(
+        relation_to_role: &BTreeMap<Type, BTreeSet<Type>>,
+        relation_types: &BTreeSet<Type>,
+        player_types: &BTreeSet<Type>,
+        statistics: &Statistics,
+    ) -> Self {
+        let edge_count = relation_to_role
+            .iter()
+            .flat_map(|(relation, roles)| {
+                roles.iter().cartesian_product(player_types).flat_map(|(role, player)| {
+                    statistics
+                        .relation_role_player_counts
+                        .get(&relation.as_relation_type())?
+                        .get(&role.as_role_type())?
+                        .get(&player.as_object_type())
+                })
+            })
+            .sum();
+
+        let canonical_scan_count = relation_types
+            .iter()
+            .filter_map(|relation| {
+                Some(statistics.relation_role_player_counts.get(&relation.as_relation_type())?.values().flat_map(
+                    |player_to_count| {
+                        player_types.iter().filter_map(|player| player_to_count.get(&player.as_object_type()))
+                    },
+                ))
+            })
+            .flatten()
+            .sum();
+
+        let reverse_scan_count = player_types
+            .iter()
+            .filter_map(|player| {
+                Some(statistics.player_role_relation_counts.get(&player.as_object_type())?.values().flat_map(
+                    |relation_to_count| {
+                        relation_types.iter().filter_map(|relation| relation_to_count.get(&relation.as_relation_type()))
+                    },
+                ))
+            })
+            .flatten()
+            .sum();
+
+        Self::new(
+            edge_count,
+            canonical_scan_count,
+            reverse_scan_count,
+            object_count(relation_types, statistics),
+            object_count(player_types, statistics),
+        )
+    }
+}
+
+/// Average number of edges per instance of each annotated type at either end of a thing edge, i.e. how many edges are
+/// scanned to verify one edge from a bound instance of that type. Types without instances are omitted.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct EdgeFanOut {
+    pub(crate) canonical: Arc<HashMap<Type, f64>>,
+    pub(crate) reverse: Arc<HashMap<Type, f64>>,
+}
+
+impl EdgeFanOut {
+    pub(crate) fn for_links(
+        relation_types: &BTreeSet<Type>,
+        player_types: &BTreeSet<Type>,
+        statistics: &Statistics,
+    ) -> Self {
+        let canonical = relation_types
+            .iter()
+            .filter_map(|relation| {
+                let edges = statistics
+                    .relation_role_player_counts
+                    .get(&relation.as_relation_type())?
+                    .values()
+                    .flat_map(|player_to_count| {
+                        player_types.iter().filter_map(|player| player_to_count.get(&player.as_object_type()))
+                    })
+                    .sum::<u64>();
+                Some((*relation, fan_out(edges, instance_count(relation, statistics))?))
+            })
+            .collect();
+
+        let reverse = player_types
+            .iter()
+            .filter_map(|player| {
+                let edges = statistics
+                    .player_role_relation_counts
+                    .get(&player.as_object_type())?
+                    .values()
+                    .flat_map(|relation_to_count| {
+                        relation_types.iter().filter_map(|relation| relation_to_count.get(&relation.as_relation_type()))
+                    })
+                    .sum::<u64>();
+                Some((*player, fan_out(edges, instance_count(player, statistics))?))
+            })
+            .collect();
+
+        Self { canonical: Arc::new(canonical), reverse: Arc::new(reverse) }
+    }
+}
+
+fn fan_out(edge_count: u64, instance_count: u64) -> Option<f64> {
+    (instance_count != 0).then(|| edge_count as f64 / instance_count as f64)
+}
+
+fn object_count(types: &BTreeSet<Type>, statistics: &Statistics) -> u64 {
+    types
+        .iter()
+        .filter_map(|type_| match type_ {
+            Type::Entity(type_) => statistics.entity_counts.get(type_),
+            Type::Relation(type_) => statistics.relation_counts.get(type_),
+            _ => None,
+        })
+        .sum()
+}
+
+/// The fraction of the instances of each end of an edge that can satisfy the restrictions on it (e.g. comparisons).
+/// Only applies to ends that are not yet bound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct EdgeSelectivity {
+    pub(crate) canonical: f64,
+    pub(crate) reverse: f64,
+}
+
+impl EdgeSelectivity {
+    pub(crate) const NONE: Self = Self { canonical: 1.0, reverse: 1.0 };
+}
+
+pub(crate) fn has_cost(
+    ownerships: &EdgeStatistics,
+    bound: BoundSide,
+    selectivity: EdgeSelectivity,
+    fix_dir: Option<Direction>,
+) -> (Cost, Direction) {
+    edge_cost(ownerships, bound, selectivity, fix_dir)
+}
+
+pub(crate) fn links_cost(
+    role_players: &EdgeStatistics,
+    bound: BoundSide,
+    selectivity: EdgeSelectivity,
+    fix_dir: Option<Direction>,
+) -> (Cost, Direction) {
+    edge_cost(role_players, bound, selectivity, fix_dir)
+}
+
+fn edge_cost(
+    edges: &EdgeStatistics,
+    bound: BoundSide,
+    selectivity: EdgeSelectivity,
+    fix_dir: Option<Direction>,
+) -> (Cost, Direction) {
+    // a bound end has at least one instance, even if its types have none recorded in the statistics
+    let canonical_count = f64::max(edges.canonical_count, 1.0);
+    let reverse_count = f64::max(edges.reverse_count, 1.0);
+
+    let mut scan_size_canonical = edges.canonical_scan_count;
+    if bound.is_canonical_bound() {
+        scan_size_canonical = edges.edge_count / canonical_count; // If the canonical end is bound, assume we only scan correct types at the other end
+        if bound.is_reverse_bound() {
+            scan_size_canonical /= reverse_count;
+        } // Ignore nested selectivity for now
+    } else {
+        scan_size_canonical *= selectivity.canonical; // restrictions (like iid) apply if var still unbound
+    }
+    let scan_size_canonical = clamp_scan_size(scan_size_canonical);
+
+    let mut scan_size_reverse = edges.reverse_scan_count;
+    if bound.is_reverse_bound() {
+        scan_size_reverse = edges.edge_count / reverse_count; // If the reverse end is bound, assume we only scan correct types at the other end
+        if bound.is_canonical_bound() {
+            scan_size_reverse /= canonical_count;
+        } // Ignore nested selectivity for now
+    } else {
+        scan_size_reverse *= selectivity.reverse; // restrictions (like iid) apply if var still unbound
+    }
+    let scan_size_reverse = clamp_scan_size(scan_size_reverse);
+
+    let mut output_size = edges.edge_count;
+    if bound.is_canonical_bound() {
+        output_size /= canonical_count;
+    } else {
+        output_size *= selectivity.canonical;
+    }
+    if bound.is_reverse_bound() {
+        output_size /= reverse_count;
+    } else {
+        output_size *= selectivity.reverse;
+    }
+    let io_ratio = clamp_scan_size(output_size);
+
+    let direction = fix_dir.unwrap_or(Direction::canonical_if(scan_size_canonical <= scan_size_reverse));
+    let scan_size = match direction {
+        Direction::Canonical => scan_size_canonical,
+        Direction::Reverse => scan_size_reverse,
+    };
+    let cost = OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size;
+    (Cost { cost, io_ratio }, direction)
+}
+
+/// The direction that verifies an edge whose ends are both bound with the smaller expected scan: from a bound end, the
+/// scan covers the edges of that one instance, so the end with more instances per edge is the cheaper one to probe.
+pub(crate) fn preferred_bound_check_direction(edges: &EdgeStatistics) -> Direction {
+    Direction::canonical_if(edges.canonical_count >= edges.reverse_count)
+}
+
+/// A negation runs its body once for each input row, and passes the row only when the body has no answers. The body is
+/// planned with its inputs bound, so its cost is already per input row: chaining it onto a partial plan scales it by the
+/// rows expected at that point, so an expensive negation is cheapest after the patterns that filter its inputs.
+/// The more answers the body is expected to have, the fewer rows pass the negation.
+pub(crate) fn negation_cost(body: Cost) -> Cost {
+    Cost { cost: body.cost, io_ratio: f64::max(1.0 / (1.0 + body.io_ratio), Cost::MIN_IO_RATIO) }
+}
+
+/// `bound_type_count` is the number of types the type end is restricted to, if it is bound.
+pub(crate) fn isa_cost(
+    instance_count: f64,
+    is_thing_bound: bool,
+    thing_selectivity: f64,
+    bound_type_count: Option<f64>,
+) -> Cost {
+    let mut scan_size = instance_count;
+    if let Some(num_types) = bound_type_count {
+        scan_size /= num_types; // account for narrowed prefix
+    }
+    if is_thing_bound {
+        scan_size /= instance_count;
+    } else {
+        scan_size *= thing_selectivity; // account for restrictions (like iid), which (we assume) can be used to reduce scan size
+    }
+    scan_size = f64::max(scan_size, VariableVertex::OUTPUT_SIZE_MIN); // TODO: verify if this is useful (part of previous model)
+    let scan_size = clamp_scan_size(scan_size);
+    let cost = match is_thing_bound {
+        true => 0.0,
+        false => OPEN_ITERATOR_RELATIVE_COST + ADVANCE_ITERATOR_RELATIVE_COST * scan_size,
+    };
+    Cost { cost, io_ratio: scan_size }
+}
+
+pub(super) fn clamp_scan_size(scan_size: f64) -> f64 {
+    scan_size.max(MIN_SCAN_SIZE).min(MAX_SCAN_SIZE) // protect against an inf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use answer::Type;
+    use concept::{
+        thing::statistics::Statistics,
+        type_::{
+            attribute_type::AttributeType, entity_type::EntityType, object_type::ObjectType,
+            relation_type::RelationType, role_type::RoleType,
+        },
+    };
+    use encoding::graph::type_::vertex::{PrefixedTypeVertexEncoding, TypeID};
+    use test_utils_concept::StatisticsBuilder;
+
+    use super::{
+        has_cost, isa_cost, links_cost, negation_cost, preferred_bound_check_direction, BoundSide, EdgeSelectivity,
+        EdgeStatistics,
+    };
+    use crate::executable::match_::planner::vertex::{Cost, Direction};
+
+    const PERSON: TypeID = TypeID::new(0);
+    const COMPANY: TypeID = TypeID::new(1);
+    const NAME: TypeID = TypeID::new(2);
+    const EMAIL: TypeID = TypeID::new(3);
+    const EMPLOYMENT: TypeID = TypeID::new(4);
+    const EMPLOYEE: TypeID = TypeID::new(5);
+
+    fn entity(type_id: TypeID) -> EntityType {
+        EntityType::build_from_type_id(type_id)
+    }
+
+    fn attribute(type_id: TypeID) -> AttributeType {
+        AttributeType::build_from_type_id(type_id)
+    }
+
+    fn relation(type_id: TypeID) -> RelationType {
+        RelationType::build_from_type_id(type_id)
+    }
+
+    fn types(types: impl IntoIterator<Item = Type>) -> BTreeSet<Type> {
+        types.into_iter().collect()
+    }
+
+    // 10 people own 100 names and 50 emails, 5 companies own 20 names; 50 names and 30 emails in total.
+    // 20 employments have 40 people and 10 companies as employees.
+    fn statistics() -> Statistics {
+        let (person, company) = (ObjectType::Entity(entity(PERSON)), ObjectType::Entity(entity(COMPANY)));
+        let employee = RoleType::build_from_type_id(EMPLOYEE);
+        StatisticsBuilder::new()
+            .entities(entity(PERSON), 10)
+            .entities(entity(COMPANY), 5)
+            .attributes(attribute(NAME), 50)
+            .attributes(attribute(EMAIL), 30)
+            .relations(relation(EMPLOYMENT), 20)
+            .has(person, attribute(NAME), 100)
+            .has(person, attribute(EMAIL), 50)
+            .has(company, attribute(NAME), 20)
+            .role_players(person, employee, relation(EMPLOYMENT), 40)
+            .role_players(company, employee, relation(EMPLOYMENT), 10)
+            .build()
+    }
+
+    fn assert_cost_eq(actual: Cost, expected: Cost, case: &str) {
+        assert!(
+            (actual.cost - expected.cost).abs() < 1e-9 && (actual.io_ratio - expected.io_ratio).abs() < 1e-9,
+            "{case}: expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn has_cost_formula() {
+        // 10 owners with 100 ownerships of 50 attributes; 150 ownerships from the owner types, 120 to the attribute types
+        let owner_types = types([Type::Entity(entity(PERSON))]);
+        let ownerships =
+            EdgeStatistics::for_has(&owner_types, &types([Type::Attribute(attribute(NAME))]), &statistics());
+        assert_eq!(ownerships, EdgeStatistics::new(100, 150, 120, 10, 50));
+        let restricted_owner = EdgeSelectivity { canonical: 0.01, reverse: 1.0 };
+        let cases = [
+            ("unbound", BoundSide::Neither, EdgeSelectivity::NONE, None, 125.0, 100.0, Direction::Reverse),
+            ("owner bound", BoundSide::Canonical, EdgeSelectivity::NONE, None, 15.0, 10.0, Direction::Canonical),
+            ("attribute bound", BoundSide::Reverse, EdgeSelectivity::NONE, None, 7.0, 2.0, Direction::Reverse),
+            ("both bound", BoundSide::Both, EdgeSelectivity::NONE, None, 6.0, 1.0, Direction::Canonical),
+            ("restricted owner", BoundSide::Neither, restricted_owner, None, 6.5, 1.0, Direction::Canonical),
+            (
+                "fixed canonical",
+                BoundSide::Neither,
+                EdgeSelectivity::NONE,
+                Some(Direction::Canonical),
+                155.0,
+                100.0,
+                Direction::Canonical,
+            ),
+        ];
+        for (case, bound, selectivity, fix_dir, cost, io_ratio, expected_direction) in cases {
+            let (actual, direction) = has_cost(&ownerships, bound, selectivity, fix_dir);
+            assert_cost_eq(actual, Cost { cost, io_ratio }, case);
+            assert_eq!(direction, expected_direction, "{case}");
+        }
+    }
+
+    #[test]
+    fn edge_statistics_from_instance_counts() {
+        let (person, company) = (Type::Entity(entity(PERSON)), Type::Entity(entity(COMPANY)));
+        let (name, email) = (Type::Attribute(attribute(NAME)), Type::Attribute(attribute(EMAIL)));
+        let statistics = statistics();
+
+        let has_cases = [
+            ("person name", types([person]), types([name]), EdgeStatistics::new(100, 150, 120, 10, 50)),
+            ("any owner name", types([person, company]), types([name]), EdgeStatistics::new(120, 170, 120, 15, 50)),
+            ("company email", types([company]), types([email]), EdgeStatistics::new(0, 20, 50, 5, 30)),
+        ];
+        for (case, owner_types, attribute_types, expected) in has_cases {
+            assert_eq!(EdgeStatistics::for_has(&owner_types, &attribute_types, &statistics), expected, "{case}");
+        }
+
+        let employment = Type::Relation(relation(EMPLOYMENT));
+        let relation_to_role =
+            BTreeMap::from([(employment, types([Type::RoleType(RoleType::build_from_type_id(EMPLOYEE))]))]);
+        let links_cases = [
+            ("person employee", types([person]), EdgeStatistics::new(40, 40, 40, 20, 10)),
+            ("any employee", types([person, company]), EdgeStatistics::new(50, 50, 50, 20, 15)),
+        ];
+        for (case, player_types, expected) in links_cases {
+            let actual = EdgeStatistics::for_links(&relation_to_role, &types([employment]), &player_types, &statistics);
+            assert_eq!(actual, expected, "{case}");
+        }
+    }
+
+    #[test]
+    fn links_cost_formula() {
+        // 20 relations with 40 role players out of 1000 players; 60 from the relation types, 45 to the player types
+        let role_players = EdgeStatistics::new(40, 60, 45, 20, 1000);
+        let cases = [
+            ("unbound", BoundSide::Neither, 50.0, 40.0, Direction::Reverse),
+            ("relation bound", BoundSide::Canonical, 7.0, 2.0, Direction::Canonical),
+            ("player bound", BoundSide::Reverse, 6.0, 1.0, Direction::Reverse),
+            ("both bound", BoundSide::Both, 6.0, 1.0, Direction::Canonical),
+        ];
+        for (case, bound, cost, io_ratio, expected_direction) in cases {
+            let (actual, direction) = links_cost(&role_players, bound, EdgeSelectivity::NONE, None);
+            assert_cost_eq(actual, Cost { cost, io_ratio }, case);
+            assert_eq!(direction, expected_direction, "{case}");
+        }
+    }
+
+    #[test]
+    fn isa_cost_formula() {
+        let cases = [
+            ("unbound", false, 1.0, None, 105.0, 100.0),
+            ("type bound", false, 1.0, Some(4.0), 30.0, 25.0),
+            ("thing bound", true, 1.0, None, 0.0, 1.0),
+            ("restricted thing", false, 0.001, None, 6.0, 1.0),
+        ];
+        for (case, is_thing_bound, thing_selectivity, bound_type_count, cost, io_ratio) in cases {
+            let actual = isa_cost(100.0, is_thing_bound, thing_selectivity, bound_type_count);
+            assert_cost_eq(actual, Cost { cost, io_ratio }, case);
+        }
+    }
+
+    #[test]
+    fn negation_cost_scales_with_input_rows() {
+        let body = Cost { cost: 50.0, io_ratio: 3.0 };
+        assert_cost_eq(negation_cost(body), Cost { cost: 50.0, io_ratio: 0.25 }, "per input row");
+
+        // the body runs once per row: after a scan of 100 rows it costs 100 times more than after a filter to 1 row
+        let scan = Cost { cost: 10.0, io_ratio: 100.0 };
+        let filter = Cost { cost: 10.0, io_ratio: 0.01 };
+        let late = scan.chain(filter).chain(negation_cost(body));
+        let early = scan.chain(negation_cost(body)).chain(filter);
+        assert_cost_eq(late, Cost { cost: 1060.0, io_ratio: 0.25 }, "negation after filter");
+        assert_cost_eq(early, Cost { cost: 5260.0, io_ratio: 0.25 }, "negation before filter");
+    }
+
+    #[test]
+    fn bound_check_prefers_end_with_more_instances() {
+        // few owners with many attributes each: verify from the attribute, which has few owners
+        let ownerships = EdgeStatistics::new(100, 100, 100, 10, 50);
+        assert_eq!(preferred_bound_check_direction(&ownerships), Direction::Reverse);
+        // many relations with few players each: verify from the relation
+        let role_players = EdgeStatistics::new(40, 40, 40, 20, 5);
+        assert_eq!(preferred_bound_check_direction(&role_players), Direction::Canonical);
+    }
+}
@@ -16,16 +16,26 @@ use ir::pattern::{
     Vertex,
 };
 use itertools::chain;
+use typeql::common::Span;
 
 use crate::{
     annotation::{expression::compiled_expression::ExecutableExpression, type_annotations::TypeAnnotations},
-    executable::match_::planner::{
-        plan::{ConjunctionPlan, DisjunctionPlanBuilder, Graph, QueryPlanningError, VariableVertexId, VertexId},
-        vertex::{constraint::ConstraintVertex, variable::VariableVertex},
+    executable::{
+        function::FunctionCallCost,
+        match_::{
+            instructions::StringMatcher,
+            planner::{
+                plan::{
+                    ConjunctionPlan, DisjunctionPlanBuilder, Graph, QueryPlanningError, VariableVertexId, VertexId,
+                },
+                vertex::{constraint::ConstraintVertex, variable::VariableVertex},
+            },
+        },
     },
 };
 
 pub(super) mod constraint;
+pub(super) mod cost;
 pub(super) mod variable;
 
 pub(super) const OPEN_ITERATOR_RELATIVE_COST: f64 = 5.0;
@@ -118,9 +128,57 @@ impl PlannerVertex<'_> {
             _ => None,
         }
     }
+
+    pub(super) fn planner_name(&self) -> &'static str {
+        match self {
+            Self::Variable(_) => "Variable",
+            Self::Constraint(inner) => inner.planner_name(),
+            Self::Is(_) => "Is",
+            Self::LinksDeduplication(_) => "LinksDeduplication",
+            Self::Comparison(_) => "Comparison",
+            Self::Unsatisfiable(_) => "Unsatisfiable",
+            Self::Expression(_) => "Expression",
+            Self::FunctionCall(_) => "FunctionCall",
+            Self::Negation(_) => "Negation",
+            Self::Disjunction(_) => "Disjunction",
+        }
+    }
+
+    /// The expected number of answers when none of the pattern's variables are bound, where the planner tracks it.
+    pub(super) fn unbound_expected_size(&self) -> Option<f64> {
+        match self {
+            Self::Constraint(inner) => inner.unbound_expected_size(),
+            _ => None,
+        }
+    }
+
+    /// The ranges of query text this pattern executes. Nested patterns report the range of their whole block,
+    /// and synthesised links deduplications report the links constraints they were derived from.
+    pub(super) fn source_spans(&self) -> Vec<Span> {
+        let span = match self {
+            Self::Variable(_) => None,
+            Self::Constraint(inner) => return inner.source_spans(),
+            Self::Is(inner) => inner.is.source_span(),
+            Self::LinksDeduplication(inner) => {
+                let deduplication = inner.links_deduplication;
+                return [deduplication.links1().source_span(), deduplication.links2().source_span()]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+            }
+            Self::Comparison(inner) => inner.comparison.source_span(),
+            Self::Unsatisfiable(inner) => inner.unsatisfiable.source_span(),
+            Self::Expression(inner) => inner.source_span,
+            Self::FunctionCall(inner) => inner.call_binding.source_span(),
+            Self::Negation(inner) => inner.plan.source_span(),
+            Self::Disjunction(inner) => inner.builder.disjunction().source_span(),
+        };
+        span.into_iter().collect()
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Cost {
     pub cost: f64, // per input
     pub io_ratio: f64,
@@ -135,14 +193,14 @@ impl<'a> fmt::Display for PlannerVertex<'a> {
             PlannerVertex::Constraint(v) => {
                 write!(f, "{}", v)
             }
-            PlannerVertex::Is(_) => {
-                write!(f, "|Is|")
-            } //TODO
+            PlannerVertex::Is(v) => {
+                write!(f, "|{}|", v.is)
+            }
             PlannerVertex::LinksDeduplication(_) => {
                 write!(f, "|LinksDeduplication|")
             }
             PlannerVertex::Comparison(v) => {
-                write!(f, "|{:?} comp {:?}|", v.comparison.lhs(), v.comparison.rhs())
+                write!(f, "|{}|", v.comparison)
             }
             PlannerVertex::Expression(v) => {
                 write!(f, "|Expr of {:?}|", v.expression.variables)
@@ -192,6 +250,8 @@ impl Cost {
     }
 
     pub(crate) fn join(self, other: Self, join_size: f64) -> Self {
+        // the joined variables have at least one value whenever both sides produce any rows
+        let join_size = f64::max(join_size, 1.0);
         let io_ratio = f64::max(self.io_ratio * other.io_ratio / join_size, Cost::MIN_IO_RATIO);
         let num_seeks_each = f64::min(self.io_ratio, other.io_ratio); // FIXME detect when seeks can be replaced by advancing
         let self_out_cost = self.cost / self.io_ratio; // if cost = Ci + Co * io, then cost / io ~ Co
@@ -296,6 +356,7 @@ pub(crate) struct ExpressionPlanner<'a> {
     inputs: Vec<VariableVertexId>,
     pub output: VariableVertexId,
     cost: Cost,
+    source_span: Option<Span>, // of the binding, as compiled expressions do not keep one
 }
 
 impl<'a> ExpressionPlanner<'a> {
@@ -303,9 +364,10 @@ impl<'a> ExpressionPlanner<'a> {
         expression: &'a ExecutableExpression<Variable>,
         inputs: Vec<VariableVertexId>,
         output: VariableVertexId,
+        source_span: Option<Span>,
     ) -> Self {
         let cost = Cost::MEM_COMPLEX_OUTPUT_1;
-        Self { inputs, output, cost, expression }
+        Self { inputs, output, cost, expression, source_span }
     }
 
     fn is_valid(&self, ordered: &[VertexId], _graph: &Graph<'_>) -> bool {
@@ -322,9 +384,14 @@ impl Costed for ExpressionPlanner<'_> {
         &self,
         _vertex_ordering: &[VertexId],
         _fix_dir: Option<Direction>,
-        _graph: &Graph<'_>,
+        graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        Ok((self.cost, CostMetaData::None))
+        // evaluating over input lists takes time proportional to their length
+        let list_length: f64 = (self.inputs.iter())
+            .filter_map(|input| graph.elements()[&VertexId::Variable(*input)].as_variable()?.expected_list_length())
+            .sum();
+        let cost = Cost { cost: self.cost.cost * f64::max(list_length, 1.0), ..self.cost };
+        Ok((cost, CostMetaData::None))
     }
 }
 
@@ -333,7 +400,7 @@ pub(crate) struct FunctionCallPlanner<'a> {
     pub call_binding: &'a FunctionCallBinding<Variable>,
     pub(super) arguments: Vec<VariableVertexId>,
     pub(super) assigned: Vec<VariableVertexId>,
-    cost: Cost,
+    cost: FunctionCallCost,
 }
 
 impl<'a> FunctionCallPlanner<'a> {
@@ -341,7 +408,7 @@ impl<'a> FunctionCallPlanner<'a> {
         call_binding: &'a FunctionCallBinding<Variable>,
         arguments: Vec<VariableVertexId>,
         assigned: Vec<VariableVertexId>,
-        cost: Cost,
+        cost: FunctionCallCost,
     ) -> Self {
         Self { call_binding, arguments, assigned, cost }
     }
@@ -358,11 +425,13 @@ impl<'a> FunctionCallPlanner<'a> {
 impl Costed for FunctionCallPlanner<'_> {
     fn cost_and_metadata(
         &self,
-        _vertex_ordering: &[VertexId],
+        vertex_ordering: &[VertexId],
         _fix_dir: Option<Direction>,
         _graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        Ok((self.cost, CostMetaData::None))
+        let is_check = self.assigned.iter().all(|&var| vertex_ordering.contains(&VertexId::Variable(var)));
+        let cost = if is_check { self.cost.checking } else { self.cost.producing };
+        Ok((cost, CostMetaData::None))
     }
 }
 
@@ -401,11 +470,22 @@ impl<'a> IsPlanner<'a> {
 impl Costed for IsPlanner<'_> {
     fn cost_and_metadata(
         &self,
-        _vertex_ordering: &[VertexId],
+        vertex_ordering: &[VertexId],
         _fix_dir: Option<Direction>,
-        _graph: &Graph<'_>,
+        graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        Ok((Cost::MEM_COMPLEX_OUTPUT_1, CostMetaData::None))
+        let is_produced = |var: VariableVertexId| vertex_ordering.contains(&VertexId::Variable(var));
+        if is_produced(self.lhs) && is_produced(self.rhs) {
+            // as a check over independently produced sides, only the diagonal of their cross product passes
+            let expected_size = |var: VariableVertexId| {
+                graph.elements()[&VertexId::Variable(var)].as_variable().unwrap().unrestricted_expected_output_size()
+            };
+            let selectivity = 1.0 / f64::max(expected_size(self.lhs), expected_size(self.rhs));
+            Ok((Cost::in_mem_simple_with_ratio(selectivity), CostMetaData::None))
+        } else {
+            // produces the one value of the other side
+            Ok((Cost::MEM_COMPLEX_OUTPUT_1, CostMetaData::None))
+        }
     }
 }
 #[derive(Clone, Debug)]
@@ -464,6 +544,7 @@ impl Costed for LinksDeduplicationPlanner<'_> {
 #[derive(Clone, Debug)]
 pub(super) struct ComparisonPlanner<'a> {
     comparison: &'a Comparison<Variable>,
+    matcher: Option<StringMatcher>,
     pub lhs: Input,
     pub rhs: Input,
 }
@@ -471,12 +552,14 @@ pub(super) struct ComparisonPlanner<'a> {
 impl<'a> ComparisonPlanner<'a> {
     pub(crate) fn from_constraint(
         comparison: &'a Comparison<Variable>,
+        matcher: Option<StringMatcher>,
         variable_index: &HashMap<Variable, VariableVertexId>,
         _type_annotations: &TypeAnnotations,
         _statistics: &Statistics,
     ) -> Self {
         Self {
             comparison,
+            matcher,
             lhs: Input::from_vertex(comparison.lhs(), variable_index),
             rhs: Input::from_vertex(comparison.rhs(), variable_index),
         }
@@ -503,6 +586,11 @@ impl<'a> ComparisonPlanner<'a> {
     pub(super) fn comparison(&self) -> &Comparison<Variable> {
         self.comparison
     }
+
+    /// The matcher prepared from the constant right-hand side of a `like` or `contains` comparison.
+    pub(super) fn matcher(&self) -> Option<&StringMatcher> {
+        self.matcher.as_ref()
+    }
 }
 
 impl Costed for ComparisonPlanner<'_> {
@@ -518,17 +606,17 @@ impl Costed for ComparisonPlanner<'_> {
 
 #[derive(Clone, Debug)]
 pub(super) struct UnsatisfiablePlanner<'a> {
-    _unsatisfiable: &'a Unsatisfiable,
+    unsatisfiable: &'a Unsatisfiable,
 }
 
 impl<'a> UnsatisfiablePlanner<'a> {
     pub(crate) fn from_constraint(
-        _unsatisfiable: &'a Unsatisfiable,
+        unsatisfiable: &'a Unsatisfiable,
         _variable_index: &HashMap<Variable, VariableVertexId>,
         _type_annotations: &TypeAnnotations,
         _statistics: &Statistics,
     ) -> Self {
-        Self { _unsatisfiable }
+        Self { unsatisfiable }
     }
 
     fn is_valid(&self, _ordered: &[VertexId], _graph: &Graph<'_>) -> bool {
@@ -583,7 +671,7 @@ impl Costed for NegationPlanner<'_> {
         _fix_dir: Option<Direction>,
         _graph: &Graph<'_>,
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
-        Ok((self.plan.planner_statistics.query_cost, CostMetaData::None))
+        Ok((cost::negation_cost(self.plan.cost()), CostMetaData::None))
     }
 }
 
@@ -627,13 +715,7 @@ impl Costed for DisjunctionPlanner<'_> {
     ) -> Result<(Cost, CostMetaData), QueryPlanningError> {
         let input_variables =
             vertex_ordering.iter().filter_map(|id| graph.elements()[id].as_variable()).map(|var| var.variable());
-        let cost = self
-            .builder()
-            .branches()
-            .iter()
-            .map(|branch| branch.clone().with_inputs(input_variables.clone()).plan().map(|plan| plan.cost()))
-            .collect::<Result<Vec<_>, _>>()
-            .map(|costs| costs.into_iter().fold(Cost::EMPTY, |acc_cost, cost| acc_cost.combine_parallel(cost)))?;
+        let cost = self.builder().clone().plan(input_variables)?.cost();
         Ok((cost, CostMetaData::None))
     }
 }
@@ -7,7 +7,7 @@
 use std::{collections::HashSet, fmt};
 
 use answer::variable::Variable;
-use concept::thing::statistics::Statistics;
+use concept::thing::statistics::{Statistics, ValueDistribution};
 use ir::pattern::Vertex;
 
 use crate::{
@@ -25,6 +25,7 @@ pub(crate) enum VariableVertex {
     Type(TypePlanner),
     Thing(ThingPlanner),
     Value(ValuePlanner),
+    List(ListPlanner),
 }
 
 impl VariableVertex {
@@ -36,8 +37,8 @@ impl VariableVertex {
         let unrestricted_size = match self {
             Self::Input(_) => 1.0,
             Self::Type(inner) => inner.unrestricted_expected_size,
-            Self::Thing(inner) => inner.unrestricted_expected_size,
-            Self::Value(_) => 1.0,
+            Self::Thing(inner) => inner.expected_size(),
+            Self::Value(_) | Self::List(_) => 1.0,
         };
         f64::max(unrestricted_size * self.restriction_based_selectivity(inputs), Self::OUTPUT_SIZE_MIN)
     }
@@ -46,19 +47,46 @@ impl VariableVertex {
         let unrestricted_size = match self {
             Self::Input(_) => 1.0,
             Self::Type(inner) => inner.unrestricted_expected_size,
-            Self::Thing(inner) => inner.unrestricted_expected_size,
-            Self::Value(_) => 1.0,
+            Self::Thing(inner) => inner.expected_size(),
+            Self::Value(_) | Self::List(_) => 1.0,
         };
         f64::max(unrestricted_size, Self::OUTPUT_SIZE_MIN)
     }
 
     pub(crate) fn restriction_based_selectivity(&self, inputs: &[VertexId]) -> f64 {
         // the fraction of possible actual outputs (based on type information) when restricted (for example, by comparators)
-        match self {
+        let selectivity = match self {
             VariableVertex::Input(_) => Self::RESTRICTION_NONE,
             VariableVertex::Type(inner) => inner.restriction_based_selectivity(inputs),
             VariableVertex::Thing(inner) => inner.restriction_based_selectivity(inputs),
             VariableVertex::Value(inner) => inner.restriction_based_selectivity(inputs),
+            VariableVertex::List(inner) => inner.restriction_based_selectivity(inputs),
+        };
+        match self.equality_class() {
+            Some(class) if class.is_any_member_available(inputs) => {
+                // no more values than the smallest member of the class
+                let class_selectivity = class.expected_size / self.unrestricted_expected_output_size();
+                f64::max(f64::min(selectivity, class_selectivity), Self::SELECTIVITY_MIN)
+            }
+            _ => selectivity,
+        }
+    }
+
+    fn equality_class(&self) -> Option<&EqualityClass> {
+        match self {
+            Self::Input(_) | Self::List(_) => None,
+            Self::Type(inner) => inner.equality_class.as_ref(),
+            Self::Thing(inner) => inner.equality_class.as_ref(),
+            Self::Value(inner) => inner.equality_class.as_ref(),
+        }
+    }
+
+    pub(crate) fn set_equality_class(&mut self, equality_class: EqualityClass) {
+        match self {
+            Self::Input(_) | Self::List(_) => (),
+            Self::Type(inner) => inner.equality_class = Some(equality_class),
+            Self::Thing(inner) => inner.equality_class = Some(equality_class),
+            Self::Value(inner) => inner.equality_class = Some(equality_class),
         }
     }
 
@@ -68,6 +96,7 @@ impl VariableVertex {
             Self::Type(inner) => inner.binding,
             Self::Thing(inner) => inner.binding,
             Self::Value(inner) => inner.binding,
+            Self::List(inner) => inner.binding,
         }
     }
 
@@ -77,6 +106,7 @@ impl VariableVertex {
             Self::Type(inner) => inner.set_binding(binding_pattern),
             Self::Thing(inner) => inner.set_binding(binding_pattern),
             Self::Value(inner) => inner.set_binding(binding_pattern),
+            Self::List(inner) => inner.set_binding(binding_pattern),
         }
     }
 
@@ -85,7 +115,29 @@ impl VariableVertex {
             Self::Input(_inner) => (),
             Self::Type(inner) => inner.add_is(other),
             Self::Thing(inner) => inner.add_is(other),
-            Self::Value(_inner) => unreachable!(),
+            Self::Value(_) | Self::List(_) => unreachable!(),
+        }
+    }
+
+    /// Records that the value of this variable is fully determined once `other` is available.
+    pub(crate) fn add_exact(&mut self, other: VariableVertexId) {
+        match self {
+            Self::Input(_inner) => (),
+            Self::Type(inner) => inner.add_is(other),
+            Self::Thing(inner) => inner.add_is(other),
+            Self::Value(inner) => inner.add_exact(other),
+            Self::List(inner) => inner.add_exact(other),
+        }
+    }
+
+    /// Records that the variable can take at most one value regardless of the plan, e.g. because it is bound to an IID.
+    pub(crate) fn set_exact_bound(&mut self) {
+        match self {
+            Self::Input(_) => (),
+            Self::Type(_) | Self::Value(_) | Self::List(_) => {
+                unreachable!("only thing variables can be bound to an IID")
+            }
+            Self::Thing(inner) => inner.set_exact_bound(),
         }
     }
 
@@ -95,6 +147,7 @@ impl VariableVertex {
             Self::Type(_) => unreachable!(),
             Self::Thing(inner) => inner.add_equal(other),
             Self::Value(inner) => inner.add_equal(other),
+            Self::List(_) => (), // comparisons between lists are not used to restrict them
         }
     }
 
@@ -104,6 +157,7 @@ impl VariableVertex {
             Self::Type(_) => unreachable!(),
             Self::Thing(inner) => inner.add_lower_bound(other),
             Self::Value(inner) => inner.add_lower_bound(other),
+            Self::List(_) => (), // comparisons between lists are not used to restrict them
         }
     }
 
@@ -113,6 +167,48 @@ impl VariableVertex {
             Self::Type(_) => unreachable!(),
             Self::Thing(inner) => inner.add_upper_bound(other),
             Self::Value(inner) => inner.add_upper_bound(other),
+            Self::List(_) => (), // comparisons between lists are not used to restrict them
+        }
+    }
+
+    /// Records the numeric value of a constant lower bound, in addition to the `Input::Fixed` bound itself.
+    pub(crate) fn add_constant_lower_bound(&mut self, value: f64) {
+        match self {
+            Self::Thing(inner) => inner.add_constant_lower_bound(value),
+            Self::Input(_) | Self::Type(_) | Self::Value(_) | Self::List(_) => (),
+        }
+    }
+
+    /// Records the numeric value of a constant upper bound, in addition to the `Input::Fixed` bound itself.
+    pub(crate) fn add_constant_upper_bound(&mut self, value: f64) {
+        match self {
+            Self::Thing(inner) => inner.add_constant_upper_bound(value),
+            Self::Input(_) | Self::Type(_) | Self::Value(_) | Self::List(_) => (),
+        }
+    }
+
+    /// The strictest constant lower and upper bounds of the variable, if their values are known.
+    pub(crate) fn constant_bounds(&self) -> (Option<f64>, Option<f64>) {
+        match self {
+            Self::Thing(inner) => (inner.constant_lower_bound, inner.constant_upper_bound),
+            Self::Input(_) | Self::Type(_) | Self::Value(_) | Self::List(_) => (None, None),
+        }
+    }
+
+    /// The number of elements expected in each value of a list variable.
+    pub(crate) fn expected_list_length(&self) -> Option<f64> {
+        match self {
+            Self::List(inner) => Some(inner.expected_length),
+            Self::Input(_) | Self::Type(_) | Self::Thing(_) | Self::Value(_) => None,
+        }
+    }
+
+    pub(crate) fn set_expected_list_length(&mut self, expected_length: f64) {
+        match self {
+            Self::List(inner) => inner.expected_length = expected_length,
+            Self::Input(_) | Self::Type(_) | Self::Thing(_) | Self::Value(_) => {
+                unreachable!("only list variables have a length")
+            }
         }
     }
 
@@ -130,10 +226,28 @@ impl VariableVertex {
             VariableVertex::Type(var) => var.variable,
             VariableVertex::Thing(var) => var.variable,
             VariableVertex::Value(var) => var.variable,
+            VariableVertex::List(var) => var.variable,
         }
     }
 }
 
+/// Variables related by `==` or `is`, which take the same values in every answer.
+#[derive(Clone, Debug)]
+pub(crate) struct EqualityClass {
+    members: Vec<VariableVertexId>,
+    expected_size: f64, // of the smallest member, before any member is produced
+}
+
+impl EqualityClass {
+    pub(crate) fn new(members: Vec<VariableVertexId>, expected_size: f64) -> Self {
+        Self { members, expected_size }
+    }
+
+    fn is_any_member_available(&self, inputs: &[VertexId]) -> bool {
+        self.members.iter().any(|&member| is_input_available(&Input::Variable(member), inputs))
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct InputPlanner {
     variable: Variable,
@@ -157,6 +271,7 @@ pub(crate) struct TypePlanner {
     binding: Option<PatternVertexId>,
     restriction_exact: HashSet<VariableVertexId>, // Is constraint
     unrestricted_expected_size: f64,
+    equality_class: Option<EqualityClass>,
 }
 
 impl fmt::Debug for TypePlanner {
@@ -173,6 +288,7 @@ impl TypePlanner {
             binding: None,
             restriction_exact: HashSet::new(),
             unrestricted_expected_size: num_types as f64,
+            equality_class: None,
         }
     }
 
@@ -209,10 +325,18 @@ pub(crate) struct ThingPlanner {
     unrestricted_expected_attribute_types: usize,
 
     restriction_exact: HashSet<VariableVertexId>, // IID or exact Type + Value
+    exact_bound: bool,                            // constant IID
 
     restriction_equal: HashSet<Input>,
     restriction_from_below: HashSet<Input>,
     restriction_from_above: HashSet<Input>,
+
+    // only set if the values of every instance the variable can take are accounted for
+    value_distributions: Option<Vec<ValueDistribution>>,
+    constant_lower_bound: Option<f64>,
+    constant_upper_bound: Option<f64>,
+
+    equality_class: Option<EqualityClass>,
 }
 
 impl fmt::Debug for ThingPlanner {
@@ -232,6 +356,7 @@ impl ThingPlanner {
     ) -> Self {
         let mut unrestricted_expected_size: f64 = 0.0;
         let mut unrestricted_expected_attribute_types: usize = 0;
+        let mut value_distributions = Some(Vec::new());
         for type_ in type_annotations
             .vertex_annotations_of(&Vertex::Variable(variable))
             .expect("expected thing variable to have been annotated with types")
@@ -241,17 +366,26 @@ impl ThingPlanner {
                 answer::Type::Entity(type_) => {
                     if let Some(count) = statistics.entity_counts.get(type_) {
                         unrestricted_expected_size += *count as f64;
+                        value_distributions = None;
                     }
                 }
                 answer::Type::Relation(type_) => {
                     if let Some(count) = statistics.relation_counts.get(type_) {
                         unrestricted_expected_size += *count as f64;
+                        value_distributions = None;
                     }
                 }
                 answer::Type::Attribute(type_) => {
                     if let Some(count) = statistics.attribute_counts.get(type_) {
                         unrestricted_expected_size += *count as f64;
                         unrestricted_expected_attribute_types += 1;
+                        if *count > 0 {
+                            match (value_distributions.as_mut(), statistics.attribute_value_distribution(*type_)) {
+                                (Some(distributions), Some(distribution)) => distributions.push(distribution.clone()),
+                                (None, Some(_)) => (),
+                                (_, None) => value_distributions = None,
+                            }
+                        }
                     }
                 }
                 answer::Type::RoleType(type_) => {
@@ -266,9 +400,14 @@ impl ThingPlanner {
             unrestricted_expected_size,
             unrestricted_expected_attribute_types,
             restriction_exact: HashSet::new(),
+            exact_bound: false,
             restriction_equal: HashSet::new(),
             restriction_from_below: HashSet::new(),
             restriction_from_above: HashSet::new(),
+            value_distributions: value_distributions.filter(|distributions| !distributions.is_empty()),
+            constant_lower_bound: None,
+            constant_upper_bound: None,
+            equality_class: None,
         }
     }
 
@@ -276,6 +415,18 @@ impl ThingPlanner {
         self.restriction_exact.insert(other);
     }
 
+    pub(crate) fn set_exact_bound(&mut self) {
+        self.exact_bound = true;
+    }
+
+    /// The number of instances the variable can take: at most one when bound to an IID.
+    fn expected_size(&self) -> f64 {
+        match self.exact_bound {
+            true => f64::min(self.unrestricted_expected_size, 1.0),
+            false => self.unrestricted_expected_size,
+        }
+    }
+
     pub(crate) fn add_equal(&mut self, other: Input) {
         self.restriction_equal.insert(other);
     }
@@ -288,10 +439,35 @@ impl ThingPlanner {
         self.restriction_from_above.insert(other);
     }
 
+    fn add_constant_lower_bound(&mut self, value: f64) {
+        self.constant_lower_bound = Some(self.constant_lower_bound.map_or(value, |bound| f64::max(bound, value)));
+    }
+
+    fn add_constant_upper_bound(&mut self, value: f64) {
+        self.constant_upper_bound = Some(self.constant_upper_bound.map_or(value, |bound| f64::min(bound, value)));
+    }
+
     fn set_binding(&mut self, binding_pattern: PatternVertexId) {
         self.binding = Some(binding_pattern);
     }
 
+    /// The fraction of the instances whose values lie within the constant bounds, estimated from the distributions
+    /// of the values of the attribute types. `None` if there are no constant bounds or no usable distributions.
+    fn constant_bounds_fraction(&self) -> Option<f64> {
+        if self.constant_lower_bound.is_none() && self.constant_upper_bound.is_none() {
+            return None;
+        }
+        let distributions = self.value_distributions.as_ref()?;
+        let total = distributions.iter().map(|distribution| distribution.count() as f64).sum::<f64>();
+        let selected = (distributions.iter())
+            .map(|distribution| {
+                let fraction = distribution.fraction_within(self.constant_lower_bound, self.constant_upper_bound);
+                distribution.count() as f64 * fraction
+            })
+            .sum::<f64>();
+        (total > 0.0).then(|| selected / total)
+    }
+
     fn restriction_based_selectivity(&self, inputs: &[VertexId]) -> f64 {
         // decrease selectivity whenever we have any matching restrictions
         let bias: f64 = 1.0; // TODO: revisit and tune
@@ -311,12 +487,23 @@ impl ThingPlanner {
                 selected = self.unrestricted_expected_attribute_types as f64;
                 any_restrictions = true;
             }
-            if self.restriction_from_below.iter().any(|restriction| is_input_available(restriction, inputs)) {
+            // constant bounds are estimated from the value distributions when there are any, others by a fixed fraction
+            let constant_bounds_fraction = self.constant_bounds_fraction();
+            let is_unestimated_restriction = |restriction: &Input| {
+                is_input_available(restriction, inputs)
+                    && (constant_bounds_fraction.is_none() || *restriction != Input::Fixed)
+            };
+            if let Some(fraction) = constant_bounds_fraction {
+                // the estimated fraction of the selected will pass the constant filters
+                selected *= fraction;
+                any_restrictions = true;
+            }
+            if self.restriction_from_below.iter().any(is_unestimated_restriction) {
                 // some fraction of the selected will pass the strictest below filter
                 selected *= Self::RESTRICTION_BELOW_SELECTIVITY;
                 any_restrictions = true;
             }
-            if self.restriction_from_above.iter().any(|restriction| is_input_available(restriction, inputs)) {
+            if self.restriction_from_above.iter().any(is_unestimated_restriction) {
                 // some fraction of the selected will pass the strictest above filter
                 selected *= Self::RESTRICTION_ABOVE_SELECTIVITY;
                 any_restrictions = true;
@@ -337,9 +524,12 @@ pub(crate) struct ValuePlanner {
     variable: Variable,
     binding: Option<PatternVertexId>,
 
+    restriction_value_exact: HashSet<VariableVertexId>, // single-row function call
     restriction_value_equal: HashSet<Input>,
     restriction_value_below: HashSet<Input>,
     restriction_value_above: HashSet<Input>,
+
+    equality_class: Option<EqualityClass>,
 }
 
 impl fmt::Debug for ValuePlanner {
@@ -357,9 +547,11 @@ impl ValuePlanner {
         Self {
             variable,
             binding: None,
+            restriction_value_exact: HashSet::new(),
             restriction_value_equal: HashSet::new(),
             restriction_value_below: HashSet::new(),
             restriction_value_above: HashSet::new(),
+            equality_class: None,
         }
     }
 
@@ -367,6 +559,10 @@ impl ValuePlanner {
         self.binding = Some(binding_pattern);
     }
 
+    pub(crate) fn add_exact(&mut self, other: VariableVertexId) {
+        self.restriction_value_exact.insert(other);
+    }
+
     pub(crate) fn add_equal(&mut self, other: Input) {
         self.restriction_value_equal.insert(other);
     }
@@ -382,6 +578,13 @@ impl ValuePlanner {
     fn restriction_based_selectivity(&self, inputs: &[VertexId]) -> f64 {
         // since there's no "expected size" of a value variable (we will always assign exactly 1 value)
         // we arbitrarily set some thresholds for selectivity of predicates
+        if self
+            .restriction_value_exact
+            .iter()
+            .any(|restriction| is_input_available(&Input::Variable(*restriction), inputs))
+        {
+            return VariableVertex::SELECTIVITY_MIN;
+        }
         let mut selectivity = VariableVertex::RESTRICTION_NONE;
         if self.restriction_value_equal.iter().any(|restriction| is_input_available(restriction, inputs)) {
             selectivity *= Self::RESTRICTION_EQUAL_SELECTIVITY;
@@ -396,6 +599,52 @@ impl ValuePlanner {
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct ListPlanner {
+    variable: Variable,
+    binding: Option<PatternVertexId>,
+    expected_length: f64,
+
+    restriction_exact: HashSet<VariableVertexId>, // single-row function call
+}
+
+impl fmt::Debug for ListPlanner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListPlanner").field("variable", &self.variable).field("binding", &self.binding).finish()
+    }
+}
+
+impl ListPlanner {
+    /// Assumed length of a list whose producer gives no better estimate.
+    pub(crate) const DEFAULT_EXPECTED_LENGTH: f64 = 8.0;
+
+    pub(crate) fn from_variable(variable: Variable) -> Self {
+        Self {
+            variable,
+            binding: None,
+            expected_length: Self::DEFAULT_EXPECTED_LENGTH,
+            restriction_exact: HashSet::new(),
+        }
+    }
+
+    fn set_binding(&mut self, binding_pattern: PatternVertexId) {
+        self.binding = Some(binding_pattern);
+    }
+
+    pub(crate) fn add_exact(&mut self, other: VariableVertexId) {
+        self.restriction_exact.insert(other);
+    }
+
+    fn restriction_based_selectivity(&self, inputs: &[VertexId]) -> f64 {
+        // as with a value variable, exactly one list is assigned per row
+        if self.restriction_exact.iter().any(|restriction| is_input_available(&Input::Variable(*restriction), inputs)) {
+            VariableVertex::SELECTIVITY_MIN
+        } else {
+            VariableVertex::RESTRICTION_NONE
+        }
+    }
+}
+
 fn is_input_available(input: &Input, available_inputs: &[VertexId]) -> bool {
     match input {
         Input::Fixed => true,
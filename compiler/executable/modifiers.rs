@@ -34,11 +34,17 @@ pub struct SortExecutable {
     pub executable_id: u64,
     pub sort_on: Vec<SortVariable>,
     pub output_row_mapping: HashMap<Variable, VariablePosition>,
+    /// The rows reach the sort already ordered on `sort_on`, so they are emitted as they are
+    pub is_presorted: bool,
 }
 
 impl SortExecutable {
-    pub(crate) fn new(sort_on: Vec<SortVariable>, output_row_mapping: HashMap<Variable, VariablePosition>) -> Self {
-        Self { executable_id: next_executable_id(), sort_on, output_row_mapping }
+    pub(crate) fn new(
+        sort_on: Vec<SortVariable>,
+        output_row_mapping: HashMap<Variable, VariablePosition>,
+        is_presorted: bool,
+    ) -> Self {
+        Self { executable_id: next_executable_id(), sort_on, output_row_mapping, is_presorted }
     }
 }
 
@@ -68,6 +74,20 @@ impl LimitExecutable {
     }
 }
 
+/// Turns each row carrying a multiplicity above one into that many rows, inserted before a stage that counts rows
+/// rather than answers. Whether the rows are expanded is decided when executing, by the multiplicity policy.
+#[derive(Debug)]
+pub struct ExpandExecutable {
+    pub executable_id: u64,
+    pub output_row_mapping: HashMap<Variable, VariablePosition>,
+}
+
+impl ExpandExecutable {
+    pub(crate) fn new(output_row_mapping: HashMap<Variable, VariablePosition>) -> Self {
+        Self { executable_id: next_executable_id(), output_row_mapping }
+    }
+}
+
 #[derive(Debug)]
 pub struct RequireExecutable {
     pub executable_id: u64,
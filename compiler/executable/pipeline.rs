@@ -11,14 +11,20 @@ use std::{
 };
 
 use answer::{variable::Variable, Type};
-use concept::thing::statistics::Statistics;
+use concept::thing::statistics::{Statistics, StatisticsFreshness};
 use ir::{
     pattern::{conjunction::Conjunction, nested_pattern::NestedPattern, Vertex},
-    pipeline::{function_signature::FunctionID, reduce::AssignedReduction, VariableRegistry},
+    pipeline::{
+        block::Block, function_signature::FunctionID, modifier::SortVariable, reduce::AssignedReduction,
+        ParameterRegistry, VariableRegistry,
+    },
 };
+use storage::sequence_number::SequenceNumber;
+use structural_equality::StructuralEquality;
 
 use crate::{
     annotation::{
+        expression::compiled_expression::Monotonicity,
         fetch::{AnnotatedFetch, AnnotatedFetchObject, AnnotatedFetchSome},
         function::{AnnotatedPreambleFunctions, AnnotatedSchemaFunctions},
         pipeline::AnnotatedStage,
@@ -26,19 +32,27 @@ use crate::{
     executable::{
         delete::executable::DeleteExecutable,
         fetch::executable::{compile_fetch, ExecutableFetch},
-        function::{executable::compile_functions, ExecutableFunctionRegistry, FunctionCallCostProvider},
+        function::{
+            executable::compile_functions, ExecutableFunctionRegistry, FunctionCallCostProvider, FunctionVersions,
+        },
         insert::{self, executable::InsertExecutable},
-        match_::planner::conjunction_executable::ConjunctionExecutable,
+        match_::planner::{
+            conjunction_executable::{ConjunctionExecutable, ExecutionStep},
+            plan::PlannerConfig,
+            plan_cache::PlanCache,
+        },
         modifiers::{
-            DistinctExecutable, LimitExecutable, OffsetExecutable, RequireExecutable, SelectExecutable, SortExecutable,
+            DistinctExecutable, ExpandExecutable, LimitExecutable, OffsetExecutable, RequireExecutable,
+            SelectExecutable, SortExecutable,
         },
+        next_executable_id,
         put::PutExecutable,
-        reduce::{ReduceExecutable, ReduceRowsExecutable},
+        reduce::{ReduceExecutable, ReduceInstruction, ReduceRowsExecutable},
         update::executable::UpdateExecutable,
         ExecutableCompilationError,
     },
     query_structure::ParametrisedQueryStructure,
-    VariablePosition,
+    ExecutorVariable, VariablePosition,
 };
 
 #[derive(Debug, Default, Clone)]
@@ -80,11 +94,17 @@ pub struct ExecutablePipeline {
     pub executable_fetch: Option<Arc<ExecutableFetch>>,
     pub query_structure: Option<Arc<ParametrisedQueryStructure>>,
     pub type_populations: TypePopulations,
+    /// The version of the statistics that the functions, stages and fetch of the pipeline were all planned against.
+    pub statistics_sequence_number: SequenceNumber,
 }
 
 #[derive(Debug, Clone)]
 pub enum ExecutableStage {
-    Match(Arc<ConjunctionExecutable>),
+    /// Match stages with equal compilation keys share an executable, but each is profiled under its own stage id.
+    Match {
+        stage_id: u64,
+        executable: Arc<ConjunctionExecutable>,
+    },
     Insert(Arc<InsertExecutable>),
     Update(Arc<UpdateExecutable>),
     Put(Arc<PutExecutable>),
@@ -97,12 +117,13 @@ pub enum ExecutableStage {
     Require(Arc<RequireExecutable>),
     Distinct(Arc<DistinctExecutable>),
     Reduce(Arc<ReduceExecutable>),
+    Expand(Arc<ExpandExecutable>),
 }
 
 impl ExecutableStage {
     pub fn output_row_mapping(&self) -> HashMap<Variable, VariablePosition> {
         match self {
-            ExecutableStage::Match(executable) => executable
+            ExecutableStage::Match { executable, .. } => executable
                 .variable_positions()
                 .iter()
                 .filter(|(_, pos)| executable.selected_variables().contains(pos))
@@ -123,6 +144,7 @@ impl ExecutableStage {
             ExecutableStage::Require(executable) => executable.output_row_mapping.clone(),
             ExecutableStage::Distinct(executable) => executable.output_row_mapping.clone(),
             ExecutableStage::Reduce(executable) => executable.output_row_mapping.clone(),
+            ExecutableStage::Expand(executable) => executable.output_row_mapping.clone(),
             ExecutableStage::Put(executable) => executable.output_row_mapping().clone(),
         }
     }
@@ -141,8 +163,12 @@ fn insert_row_schema_to_mapping(
 
 pub fn compile_pipeline_and_functions(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
     variable_registry: &VariableRegistry,
+    value_parameters: &ParameterRegistry,
     annotated_schema_functions: &AnnotatedSchemaFunctions,
+    function_versions: &FunctionVersions,
+    plan_cache: Option<&PlanCache>,
     annotated_preamble: AnnotatedPreambleFunctions,
     annotated_stages: Vec<AnnotatedStage>,
     annotated_fetch: Option<AnnotatedFetch>,
@@ -161,8 +187,14 @@ pub fn compile_pipeline_and_functions(
         .filter(|&(fid, _)| referenced_functions.contains(&fid.clone().into()))
         .map(|(fid, function)| (fid.clone(), function.clone()))
         .collect();
-    let arced_executable_schema_functions =
-        Arc::new(compile_functions(statistics, &ExecutableFunctionRegistry::empty(), referenced_schema_functions)?);
+    let arced_executable_schema_functions = Arc::new(compile_functions(
+        statistics,
+        statistics_freshness,
+        plan_cache,
+        &ExecutableFunctionRegistry::empty(),
+        function_versions,
+        referenced_schema_functions,
+    )?);
     let schema_function_registry =
         ExecutableFunctionRegistry::new(arced_executable_schema_functions.clone(), HashMap::new());
 
@@ -171,14 +203,23 @@ pub fn compile_pipeline_and_functions(
         .enumerate()
         .filter(|&(fid, _)| referenced_functions.contains(&fid.into()))
         .collect();
-    let executable_preamble_functions =
-        compile_functions(statistics, &schema_function_registry, referenced_preamble_functions)?;
+    let executable_preamble_functions = compile_functions(
+        statistics,
+        statistics_freshness,
+        plan_cache,
+        &schema_function_registry,
+        function_versions,
+        referenced_preamble_functions,
+    )?;
 
     let schema_and_preamble_functions: ExecutableFunctionRegistry =
         ExecutableFunctionRegistry::new(arced_executable_schema_functions, executable_preamble_functions);
     let (_input_positions, executable_stages, executable_fetch, type_populations) = compile_stages_and_fetch(
         statistics,
+        statistics_freshness,
+        plan_cache,
         variable_registry,
+        value_parameters,
         &schema_and_preamble_functions,
         &annotated_stages,
         annotated_fetch,
@@ -191,12 +232,16 @@ pub fn compile_pipeline_and_functions(
         executable_stages,
         executable_fetch,
         type_populations,
+        statistics_sequence_number: statistics.sequence_number,
     })
 }
 
 pub fn compile_stages_and_fetch(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     variable_registry: &VariableRegistry,
+    value_parameters: &ParameterRegistry,
     available_functions: &ExecutableFunctionRegistry,
     annotated_stages: &[AnnotatedStage],
     annotated_fetch: Option<AnnotatedFetch>,
@@ -207,7 +252,10 @@ pub fn compile_stages_and_fetch(
 > {
     let (input_positions, executable_stages, mut type_populations) = compile_pipeline_stages(
         statistics,
+        statistics_freshness,
+        plan_cache,
         variable_registry,
+        value_parameters,
         available_functions,
         annotated_stages,
         input_variables.iter().copied(),
@@ -217,9 +265,16 @@ pub fn compile_stages_and_fetch(
         executable_stages.last().map(|stage: &ExecutableStage| stage.output_row_mapping()).unwrap_or(HashMap::new());
 
     if let Some(fetch) = annotated_fetch {
-        let (executable_fetch, fetch_type_populations) =
-            compile_fetch(statistics, available_functions, fetch, &stages_variable_positions)
-                .map_err(|err| ExecutableCompilationError::FetchCompilation { typedb_source: err })?;
+        let (executable_fetch, fetch_type_populations) = compile_fetch(
+            statistics,
+            statistics_freshness,
+            plan_cache,
+            value_parameters,
+            available_functions,
+            fetch,
+            &stages_variable_positions,
+        )
+        .map_err(|err| ExecutableCompilationError::FetchCompilation { typedb_source: err })?;
         type_populations.extend(fetch_type_populations);
         Ok((input_positions, executable_stages, Some(Arc::new(executable_fetch)), type_populations))
     } else {
@@ -229,65 +284,345 @@ pub fn compile_stages_and_fetch(
 
 pub(crate) fn compile_pipeline_stages(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     variable_registry: &VariableRegistry,
+    value_parameters: &ParameterRegistry,
     call_cost_provider: &impl FunctionCallCostProvider,
     annotated_stages: &[AnnotatedStage],
     input_variables: impl Iterator<Item = Variable>,
     function_return: Option<&[Variable]>,
 ) -> Result<(HashMap<Variable, VariablePosition>, Vec<ExecutableStage>, TypePopulations), ExecutableCompilationError> {
     let mut executable_stages: Vec<ExecutableStage> = Vec::with_capacity(annotated_stages.len());
-    let input_variable_positions =
+    let input_variable_positions: HashMap<_, _> =
         input_variables.enumerate().map(|(i, var)| (var, VariablePosition::new(i as u32))).collect();
     let mut last_match_annotations = None;
     let mut type_populations = TypePopulations::default();
-    for stage in annotated_stages {
+    let mut compiled_matches: Vec<(MatchStageKey<'_>, Arc<ConjunctionExecutable>)> = Vec::new();
+    let mut row_orders: Vec<Vec<SortVariable>> = Vec::new();
+    let mut may_carry_multiplicities = false;
+    for (index, stage) in annotated_stages.iter().enumerate() {
         // TODO: We can filter out the variables that are no longer needed in the future stages, but are carried as selected variables from the previous one
-        let (executable_stage, referenced_types) =
-            match executable_stages.last().map(|stage| stage.output_row_mapping()) {
-                Some(row_mapping) => compile_stage(
-                    statistics,
-                    variable_registry,
-                    call_cost_provider,
-                    &row_mapping,
-                    last_match_annotations.unwrap_or(&BTreeMap::new()),
-                    function_return,
-                    stage,
-                )?,
-                None => compile_stage(
-                    statistics,
-                    variable_registry,
-                    call_cost_provider,
-                    &input_variable_positions,
-                    last_match_annotations.unwrap_or(&BTreeMap::new()),
-                    function_return,
-                    stage,
-                )?,
-            };
+        let counted_groupby = match function_return {
+            None => count_only_reduction_groupby(annotated_stages.get(index + 1)),
+            Some(_) => None,
+        };
+        let (distinct_variables, limit) = match function_return {
+            None => (
+                downstream_distinct_variables(&annotated_stages[index + 1..]),
+                downstream_limit(&annotated_stages[index + 1..]),
+            ),
+            Some(_) => (None, None),
+        };
+        let is_presorted = match stage {
+            AnnotatedStage::Sort(sort) => is_presorted(&row_orders, &sort.variables),
+            _ => false,
+        };
+        let row_mapping = executable_stages.last().map(|stage| stage.output_row_mapping());
+        let input_positions = row_mapping.as_ref().unwrap_or(&input_variable_positions);
+        let match_key = MatchStageKey::new(
+            stage,
+            input_positions,
+            last_match_annotations,
+            counted_groupby,
+            distinct_variables.as_ref(),
+            limit,
+        );
+        let compiled_match = match_key.as_ref().and_then(|key| {
+            compiled_matches
+                .iter()
+                .find(|(compiled, _)| compiled.matches(key))
+                .map(|(_, executable)| executable.clone())
+        });
+        let is_compiled = compiled_match.is_some();
+        let (executable_stage, referenced_types) = match compiled_match {
+            // the types referenced by the stage were already counted when the executable was compiled
+            Some(executable) => {
+                (ExecutableStage::Match { stage_id: next_executable_id(), executable }, BTreeSet::new())
+            }
+            None => compile_stage(
+                statistics,
+                statistics_freshness,
+                plan_cache,
+                variable_registry,
+                value_parameters,
+                call_cost_provider,
+                input_positions,
+                last_match_annotations.unwrap_or(&BTreeMap::new()),
+                function_return,
+                counted_groupby,
+                distinct_variables.as_ref(),
+                limit,
+                is_presorted,
+                stage,
+            )?,
+        };
+        // the batches of a function body are executed together, which does not keep the order of the input rows
+        if function_return.is_none() {
+            row_orders = next_row_orders(row_orders, &executable_stage);
+        }
+        if let (Some(key), false, ExecutableStage::Match { executable, .. }) =
+            (match_key, is_compiled, &executable_stage)
+        {
+            compiled_matches.push((key, executable.clone()));
+        }
         if let AnnotatedStage::Match { block, block_annotations, .. } = stage {
             last_match_annotations =
                 Some(block_annotations.type_annotations_of(block.conjunction()).unwrap().vertex_annotations())
         }
         type_populations.update(&referenced_types, statistics);
+        let counts_rows = matches!(executable_stage, ExecutableStage::Offset(_) | ExecutableStage::Limit(_));
+        if counts_rows && may_carry_multiplicities && function_return.is_none() {
+            let expand = ExpandExecutable::new(executable_stage.output_row_mapping());
+            executable_stages.push(ExecutableStage::Expand(Arc::new(expand)));
+            may_carry_multiplicities = false;
+        }
+        may_carry_multiplicities = may_emit_multiplicities(&executable_stage, may_carry_multiplicities);
         executable_stages.push(executable_stage);
     }
     Ok((input_variable_positions, executable_stages, type_populations))
 }
 
+/// Whether the rows a stage emits may carry a multiplicity above one, given whether the rows it receives may. Offsets
+/// and limits count rows rather than the answers they stand for, so such rows are expanded before reaching them.
+fn may_emit_multiplicities(stage: &ExecutableStage, input_may_carry_multiplicities: bool) -> bool {
+    match stage {
+        ExecutableStage::Match { executable, .. } => {
+            input_may_carry_multiplicities || executable.may_emit_multiplicities()
+        }
+        // an insert writes and emits a row for each answer, and a reduction emits a single row for each group
+        ExecutableStage::Insert(_) | ExecutableStage::Reduce(_) | ExecutableStage::Expand(_) => false,
+        _ => input_may_carry_multiplicities,
+    }
+}
+
+/// Everything a match stage's executable is compiled from. Match stages of the same pipeline with equal keys, such
+/// as a pattern repeated to re-read what an intermediate stage wrote, share one executable. Executors are still
+/// instantiated separately for each stage, and profiled under the stage's own id.
+struct MatchStageKey<'a> {
+    block: &'a Block,
+    annotations: &'a BTreeMap<Vertex<Variable>, Arc<BTreeSet<Type>>>,
+    input_variables: HashMap<Variable, VariablePosition>,
+    input_variable_annotations: BTreeMap<Variable, Option<Arc<BTreeSet<Type>>>>,
+    counted_groupby: Option<&'a [Variable]>,
+    distinct_variables: Option<HashSet<Variable>>,
+    limit: Option<u64>,
+}
+
+impl<'a> MatchStageKey<'a> {
+    fn new(
+        stage: &'a AnnotatedStage,
+        input_variables: &HashMap<Variable, VariablePosition>,
+        input_variable_annotations: Option<&BTreeMap<Vertex<Variable>, Arc<BTreeSet<Type>>>>,
+        counted_groupby: Option<&'a [Variable]>,
+        distinct_variables: Option<&HashSet<Variable>>,
+        limit: Option<u64>,
+    ) -> Option<Self> {
+        let AnnotatedStage::Match { block, block_annotations, .. } = stage else {
+            return None;
+        };
+        // the executable only depends on the annotations of the inputs, not on those of the previous stage's labels
+        let input_variable_annotations = (input_variables.keys())
+            .map(|&var| {
+                let annotations =
+                    input_variable_annotations.and_then(|annotations| annotations.get(&Vertex::Variable(var)));
+                (var, annotations.cloned())
+            })
+            .collect();
+        Some(Self {
+            block,
+            annotations: block_annotations.type_annotations_of(block.conjunction())?.vertex_annotations(),
+            input_variables: input_variables.clone(),
+            input_variable_annotations,
+            counted_groupby,
+            distinct_variables: distinct_variables.cloned(),
+            limit,
+        })
+    }
+
+    fn matches(&self, other: &Self) -> bool {
+        self.block.equals(other.block)
+            && self.annotations == other.annotations
+            && self.input_variables == other.input_variables
+            && self.input_variable_annotations == other.input_variable_annotations
+            && self.counted_groupby == other.counted_groupby
+            && self.distinct_variables == other.distinct_variables
+            && self.limit == other.limit
+    }
+}
+
+/// The variables a `distinct` following the stage deduplicates on, if only selections separate it from the stage.
+/// Without a selection, the `distinct` covers every variable of the stage.
+fn downstream_distinct_variables(stages: &[AnnotatedStage]) -> Option<HashSet<Variable>> {
+    let mut distinct_variables: Option<HashSet<Variable>> = None;
+    for stage in stages {
+        match stage {
+            AnnotatedStage::Select(select) => {
+                let selected = select.variables.iter().copied();
+                distinct_variables = Some(match distinct_variables {
+                    None => selected.collect(),
+                    Some(variables) => selected.filter(|var| variables.contains(var)).collect(),
+                });
+            }
+            AnnotatedStage::Distinct(_) => return distinct_variables,
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The number of rows a `limit` following the stage keeps, if only selections separate it from the stage.
+fn downstream_limit(stages: &[AnnotatedStage]) -> Option<u64> {
+    for stage in stages {
+        match stage {
+            AnnotatedStage::Select(_) => (),
+            AnnotatedStage::Limit(limit) => return Some(limit.limit()),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Whether the rows are already ordered on `sort_on`: a sort on a prefix of any of their orders is a no-op.
+fn is_presorted(row_orders: &[Vec<SortVariable>], sort_on: &[SortVariable]) -> bool {
+    row_orders.iter().any(|order| {
+        order.len() >= sort_on.len() && zip(order, sort_on).all(|(ordered, sort)| is_same_sort_variable(ordered, sort))
+    })
+}
+
+fn is_same_sort_variable(lhs: &SortVariable, rhs: &SortVariable) -> bool {
+    matches!(
+        (lhs, rhs),
+        (SortVariable::Ascending(lhs), SortVariable::Ascending(rhs))
+            | (SortVariable::Descending(lhs), SortVariable::Descending(rhs))
+        if lhs == rhs
+    )
+}
+
+/// The orders of the rows emitted by a stage, given the orders of its input rows. Each order is a sequence of sort
+/// variables the rows are lexicographically sorted on.
+fn next_row_orders(mut row_orders: Vec<Vec<SortVariable>>, stage: &ExecutableStage) -> Vec<Vec<SortVariable>> {
+    match stage {
+        ExecutableStage::Match { executable, .. } => {
+            // every input row is matched on its own and its answers are emitted together, so the rows keep their
+            // order and the input variables keep their values across the answers of a row
+            let derived_orders = executable
+                .steps()
+                .iter()
+                .filter_map(|step| match step {
+                    ExecutionStep::Assignment(assignment) => Some(assignment),
+                    _ => None,
+                })
+                .flat_map(|assignment| {
+                    let positions = executable.variable_reverse_map();
+                    let (Some(&assigned), &[input]) =
+                        (positions.get(&assignment.unbound), assignment.input_positions.as_slice())
+                    else {
+                        return Vec::new();
+                    };
+                    match assignment.expression.monotonicity_in(input) {
+                        Some(monotonicity) => assigned_orders(
+                            &row_orders,
+                            positions[&ExecutorVariable::RowPosition(input)],
+                            assigned,
+                            monotonicity,
+                        ),
+                        None => Vec::new(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            row_orders.extend(derived_orders);
+            row_orders
+        }
+        ExecutableStage::Sort(executable) => match executable.is_presorted {
+            true => row_orders,
+            false => vec![executable.sort_on.clone()],
+        },
+        ExecutableStage::Select(executable) => row_orders
+            .into_iter()
+            .map(|order| {
+                let retained = |sort_variable: &SortVariable| {
+                    executable.output_row_mapping.contains_key(&sort_variable.variable())
+                };
+                order.into_iter().take_while(retained).collect::<Vec<_>>()
+            })
+            .filter(|order| !order.is_empty())
+            .collect(),
+        ExecutableStage::Offset(_)
+        | ExecutableStage::Limit(_)
+        | ExecutableStage::Require(_)
+        | ExecutableStage::Distinct(_)
+        | ExecutableStage::Expand(_) => row_orders,
+        _ => Vec::new(),
+    }
+}
+
+/// The orders the rows are sorted on by a variable assigned from `input`: within the rows that agree on the sort
+/// variables preceding `input`, the assigned value follows the value of `input`.
+fn assigned_orders(
+    row_orders: &[Vec<SortVariable>],
+    input: Variable,
+    assigned: Variable,
+    monotonicity: Monotonicity,
+) -> Vec<Vec<SortVariable>> {
+    row_orders
+        .iter()
+        .filter_map(|order| {
+            let index = order.iter().position(|sort_variable| sort_variable.variable() == input)?;
+            let is_ascending =
+                matches!(order[index], SortVariable::Ascending(_)) == (monotonicity == Monotonicity::NonDecreasing);
+            let assigned = match is_ascending {
+                true => SortVariable::Ascending(assigned),
+                false => SortVariable::Descending(assigned),
+            };
+            Some(order[..index].iter().copied().chain([assigned]).collect())
+        })
+        .collect()
+}
+
+/// The variables grouped on by a reduction that only counts rows, which needs neither the values of the other
+/// variables nor the distinctness of the rows it consumes: the stage before it may emit the grouped variables with the
+/// multiplicity of each group, or bare multiplicities if nothing is grouped on.
+fn count_only_reduction_groupby(stage: Option<&AnnotatedStage>) -> Option<&[Variable]> {
+    match stage {
+        Some(AnnotatedStage::Reduce(reduce, typed_reducers))
+            if typed_reducers.iter().all(|reducer| matches!(reducer, ReduceInstruction::Count)) =>
+        {
+            Some(&reduce.groupby)
+        }
+        _ => None,
+    }
+}
+
 fn compile_stage(
     statistics: &Statistics,
+    statistics_freshness: StatisticsFreshness,
+    plan_cache: Option<&PlanCache>,
     variable_registry: &VariableRegistry,
+    value_parameters: &ParameterRegistry,
     call_cost_provider: &impl FunctionCallCostProvider,
     input_variables: &HashMap<Variable, VariablePosition>,
     input_variable_annotations: &BTreeMap<Vertex<Variable>, Arc<BTreeSet<answer::Type>>>,
     function_return: Option<&[Variable]>,
+    counted_groupby: Option<&[Variable]>,
+    distinct_variables: Option<&HashSet<Variable>>,
+    limit: Option<u64>,
+    is_presorted: bool,
     annotated_stage: &AnnotatedStage,
 ) -> Result<(ExecutableStage, BTreeSet<Type>), ExecutableCompilationError> {
     match annotated_stage {
         AnnotatedStage::Match { block, block_annotations, executable_expressions, .. } => {
-            let mut selected_variables: HashSet<_> = function_return.unwrap_or(&[]).iter().copied().collect();
-            selected_variables.extend(input_variables.keys().copied());
-            selected_variables.extend(block.conjunction().named_producible_variables(block.block_context()));
-            let plan = crate::executable::match_::planner::compile(
+            let mut selected_variables: HashSet<_> = HashSet::new();
+            match counted_groupby {
+                // the variables that are not selected are counted towards the multiplicity of the rows instead
+                Some(groupby) => selected_variables.extend(groupby.iter().copied()),
+                None => {
+                    selected_variables.extend(function_return.unwrap_or(&[]).iter().copied());
+                    selected_variables.extend(input_variables.keys().copied());
+                    selected_variables.extend(block.conjunction().named_producible_variables(block.block_context()));
+                }
+            }
+            let mut plan = crate::executable::match_::planner::compile(
                 block,
                 input_variable_annotations,
                 input_variables,
@@ -295,11 +630,27 @@ fn compile_stage(
                 block_annotations,
                 variable_registry,
                 executable_expressions,
+                value_parameters,
                 statistics,
                 call_cost_provider,
+                &PlannerConfig { statistics_freshness, ..PlannerConfig::default() },
+                plan_cache,
             )
             .map_err(|source| ExecutableCompilationError::MatchCompilation { typedb_source: source })?;
-            Ok((ExecutableStage::Match(Arc::new(plan)), block_annotations.referenced_types()))
+            match counted_groupby {
+                Some([]) => plan = plan.with_row_count_only(),
+                Some(_) => plan = plan.with_grouped_count_only(),
+                None => (),
+            }
+            if let Some(distinct_variables) = distinct_variables {
+                plan = plan.with_distinct_outputs(distinct_variables);
+            }
+            if let Some(limit) = limit {
+                // no single input row can contribute more rows than the whole stage may emit
+                plan = plan.with_per_input_row_limit(limit);
+            }
+            let stage = ExecutableStage::Match { stage_id: plan.executable_id(), executable: Arc::new(plan) };
+            Ok((stage, block_annotations.referenced_types()))
         }
         AnnotatedStage::Insert { block, annotations, source_span } => {
             let plan = crate::executable::insert::executable::compile(
@@ -336,8 +687,11 @@ fn compile_stage(
                 match_annotations,
                 variable_registry,
                 &HashMap::new(),
+                value_parameters,
                 statistics,
                 call_cost_provider,
+                &PlannerConfig { statistics_freshness, ..PlannerConfig::default() },
+                plan_cache,
             )
             .map_err(|source| ExecutableCompilationError::PutMatchCompilation { typedb_source: source })?;
             let insert_plan = crate::executable::insert::executable::compile(
@@ -389,7 +743,11 @@ fn compile_stage(
             ))
         }
         AnnotatedStage::Sort(sort) => Ok((
-            ExecutableStage::Sort(Arc::new(SortExecutable::new(sort.variables.clone(), input_variables.clone()))),
+            ExecutableStage::Sort(Arc::new(SortExecutable::new(
+                sort.variables.clone(),
+                input_variables.clone(),
+                is_presorted,
+            ))),
             BTreeSet::new(),
         )),
         AnnotatedStage::Offset(offset) => Ok((
@@ -24,6 +24,7 @@ macro_rules! filter_variants {
 pub(crate) use filter_variants;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExecutorVariable {
     // TODO: rename VariablePosition to ExecutorVariablePosition
     RowPosition(VariablePosition),
@@ -88,6 +89,7 @@ impl fmt::Display for ExecutorVariable {
 impl IrID for ExecutorVariable {}
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serialization", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariablePosition {
     position: u32,
 }
@@ -11,6 +11,7 @@ use compiler::{
         function::EmptyAnnotatedFunctionSignatures, match_inference::infer_types, type_annotations::BlockAnnotations,
     },
     transformation::{
+        disjunction_factoring::factor_common_disjunction_constraints,
         redundant_constraints::optimize_away_statically_unsatisfiable_conjunctions,
         relation_index::relation_index_transformation,
     },
@@ -19,7 +20,7 @@ use concept::type_::{type_manager::TypeManager, Ordering, OwnerAPI, PlayerAPI};
 use encoding::value::label::Label;
 use ir::{
     pattern::{conjunction::Conjunction, constraint::Constraint, Vertex},
-    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    pipeline::{block::Block, function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
     translation::{match_::translate_match, PipelineTranslationContext},
 };
 use itertools::Itertools;
@@ -355,11 +356,70 @@ fn test_optimise_away() {
     }
 }
 
+#[test]
+fn test_factor_common_disjunction_constraints() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+    let (type_manager, _thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    fn count_has(conjunction: &Conjunction) -> usize {
+        conjunction.constraints().iter().filter(|constraint| matches!(constraint, Constraint::Has(_))).count()
+    }
+
+    {
+        let query = "match
+            $r isa dog-ownership;
+            { $r has start-time $t; $r links ($x); } or { $r has start-time $t; $r links ($y); };";
+        let (block, mut block_annotations) = translate_and_annotate_block(&snapshot, &type_manager, query);
+        let block_context = block.block_context().clone();
+        let mut conjunction = block.into_conjunction();
+        factor_common_disjunction_constraints(&mut conjunction, &block_context, &mut block_annotations);
+
+        assert_eq!(count_has(&conjunction), 1);
+        let has = conjunction.constraints().iter().find(|constraint| matches!(constraint, Constraint::Has(_))).unwrap();
+        assert!(block_annotations
+            .type_annotations_of(&conjunction)
+            .unwrap()
+            .constraint_annotations_of(has.clone())
+            .is_some());
+        let disjunction = conjunction.nested_patterns().iter().exactly_one().unwrap().as_disjunction().unwrap();
+        for branch in disjunction.conjunctions() {
+            assert_eq!(count_has(branch), 0);
+            assert!(branch.constraints().iter().any(|constraint| matches!(constraint, Constraint::Links(_))));
+        }
+    }
+
+    {
+        // hoisting would leave the first branch empty
+        let query = "match
+            $r isa dog-ownership;
+            { $r has start-time $t; } or { $r has start-time $t; $r links ($y); };";
+        let (block, mut block_annotations) = translate_and_annotate_block(&snapshot, &type_manager, query);
+        let block_context = block.block_context().clone();
+        let mut conjunction = block.into_conjunction();
+        factor_common_disjunction_constraints(&mut conjunction, &block_context, &mut block_annotations);
+
+        assert_eq!(count_has(&conjunction), 0);
+        let disjunction = conjunction.nested_patterns().iter().exactly_one().unwrap().as_disjunction().unwrap();
+        assert!(disjunction.conjunctions().iter().all(|branch| count_has(branch) == 1));
+    }
+}
+
 fn translate_and_annotate(
     snapshot: &impl ReadableSnapshot,
     type_manager: &TypeManager,
     query: &str,
 ) -> (Conjunction, BlockAnnotations) {
+    let (block, type_annotations) = translate_and_annotate_block(snapshot, type_manager, query);
+    (block.into_conjunction(), type_annotations)
+}
+
+fn translate_and_annotate_block(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    query: &str,
+) -> (Block, BlockAnnotations) {
     let parsed = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
     let mut context = PipelineTranslationContext::new();
     let mut parameters = ParameterRegistry::new();
@@ -377,6 +437,5 @@ fn translate_and_annotate(
         false,
     )
     .unwrap();
-    let conjunction = block.into_conjunction();
-    (conjunction, type_annotations)
+    (block, type_annotations)
 }
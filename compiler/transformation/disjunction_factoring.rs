@@ -0,0 +1,196 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use answer::{variable::Variable, Type};
+use ir::{
+    pattern::{
+        conjunction::Conjunction, constraint::Constraint, disjunction::Disjunction, nested_pattern::NestedPattern,
+        Scope, ScopeId, Vertex,
+    },
+    pipeline::block::BlockContext,
+};
+use itertools::Itertools;
+use structural_equality::is_structurally_equivalent;
+
+use crate::annotation::type_annotations::{BlockAnnotations, ConstraintTypeAnnotations, TypeAnnotations};
+
+/// Hoists constraints repeated in every branch of a disjunction into the enclosing conjunction, so they are
+/// planned and executed once rather than once per branch:
+///
+///   { $p has name $n; $p has age 10; } or { $p has name $n; $p has email $e; };
+/// becomes
+///   $p has name $n; { $p has age 10; } or { $p has email $e; };
+///
+/// A constraint is only hoisted when all its variables are available in the enclosing scope, and it was annotated
+/// identically in every branch, so that its type annotations still hold in the enclosing conjunction.
+/// The rewrite never leaves a branch without any patterns.
+pub fn factor_common_disjunction_constraints(
+    conjunction: &mut Conjunction,
+    block_context: &BlockContext,
+    block_annotations: &mut BlockAnnotations,
+) {
+    for nested in conjunction.nested_patterns_mut() {
+        match nested {
+            NestedPattern::Disjunction(disjunction) => {
+                for branch in disjunction.conjunctions_mut() {
+                    factor_common_disjunction_constraints(branch, block_context, block_annotations);
+                }
+            }
+            NestedPattern::Negation(negation) => {
+                factor_common_disjunction_constraints(negation.conjunction_mut(), block_context, block_annotations)
+            }
+            NestedPattern::Optional(optional) => {
+                factor_common_disjunction_constraints(optional.conjunction_mut(), block_context, block_annotations)
+            }
+        }
+    }
+
+    let Some(local_annotations) = block_annotations.type_annotations_of(conjunction) else { return };
+    let common_constraints = conjunction
+        .nested_patterns()
+        .iter()
+        .map(|nested| match nested {
+            NestedPattern::Disjunction(disjunction) => common_constraints(
+                conjunction.scope_id(),
+                disjunction,
+                block_context,
+                local_annotations,
+                block_annotations,
+            ),
+            NestedPattern::Negation(_) | NestedPattern::Optional(_) => Vec::new(),
+        })
+        .collect_vec();
+
+    for (nested, common) in conjunction.nested_patterns_mut().iter_mut().zip(&common_constraints) {
+        let NestedPattern::Disjunction(disjunction) = nested else { continue };
+        for branch in disjunction.conjunctions_mut() {
+            let hoisted = common.iter().map(|(constraint, _, _)| constraint);
+            branch
+                .constraints_mut()
+                .constraints_mut()
+                .retain(|constraint| !is_any_equivalent(constraint, hoisted.clone()));
+        }
+    }
+
+    for (constraint, constraint_annotations, vertex_annotations) in common_constraints.into_iter().flatten() {
+        let local_annotations = block_annotations.type_annotations_mut_of(conjunction).unwrap();
+        if let Some(constraint_annotations) = constraint_annotations {
+            local_annotations.constraint_annotations_mut().insert(constraint.clone(), constraint_annotations);
+        }
+        for (vertex, annotations) in vertex_annotations {
+            local_annotations.vertex_annotations_mut().entry(vertex).or_insert(annotations);
+        }
+        if !is_any_equivalent(&constraint, conjunction.constraints()) {
+            conjunction.constraints_mut().constraints_mut().push(constraint);
+        }
+    }
+}
+
+// a constraint to hoist, with the annotations it had in every branch
+type HoistedConstraint =
+    (Constraint<Variable>, Option<ConstraintTypeAnnotations>, Vec<(Vertex<Variable>, Arc<BTreeSet<Type>>)>);
+
+fn common_constraints(
+    scope: ScopeId,
+    disjunction: &Disjunction,
+    block_context: &BlockContext,
+    local_annotations: &TypeAnnotations,
+    block_annotations: &BlockAnnotations,
+) -> Vec<HoistedConstraint> {
+    let Some((first, _)) = disjunction.conjunctions().split_first() else { return Vec::new() };
+    let Some(branch_annotations) = disjunction
+        .conjunctions()
+        .iter()
+        .map(|branch| block_annotations.type_annotations_of(branch))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Vec::new();
+    };
+
+    let common = first
+        .constraints()
+        .iter()
+        .filter(|constraint| is_hoistable(constraint, scope, block_context))
+        .filter(|constraint| {
+            disjunction.conjunctions().iter().all(|branch| is_any_equivalent(constraint, branch.constraints()))
+        })
+        .filter(|constraint| is_annotated_alike(constraint, local_annotations, &branch_annotations))
+        .collect_vec();
+
+    let empties_a_branch = disjunction.conjunctions().iter().any(|branch| {
+        branch.nested_patterns().is_empty()
+            && branch.constraints().iter().all(|constraint| is_any_equivalent(constraint, common.iter().copied()))
+    });
+    if empties_a_branch {
+        return Vec::new();
+    }
+
+    common
+        .into_iter()
+        .map(|constraint| {
+            let annotations = branch_annotations[0];
+            let vertex_annotations = constraint
+                .vertices()
+                .filter_map(|vertex| Some((vertex.clone(), annotations.vertex_annotations_of(vertex)?.clone())))
+                .collect();
+            (constraint.clone(), annotations.constraint_annotations_of(constraint.clone()).cloned(), vertex_annotations)
+        })
+        .collect()
+}
+
+fn is_hoistable(constraint: &Constraint<Variable>, scope: ScopeId, block_context: &BlockContext) -> bool {
+    // bindings are compiled against the scope that declares them, and deduplications refer to their sibling links
+    let is_hoistable_kind = match constraint {
+        Constraint::Is(_)
+        | Constraint::Kind(_)
+        | Constraint::Label(_)
+        | Constraint::RoleName(_)
+        | Constraint::Sub(_)
+        | Constraint::Isa(_)
+        | Constraint::Iid(_)
+        | Constraint::Links(_)
+        | Constraint::Has(_)
+        | Constraint::Comparison(_)
+        | Constraint::Owns(_)
+        | Constraint::Relates(_)
+        | Constraint::Plays(_)
+        | Constraint::Value(_) => true,
+        Constraint::IndexedRelation(_)
+        | Constraint::ExpressionBinding(_)
+        | Constraint::FunctionCallBinding(_)
+        | Constraint::LinksDeduplication(_)
+        | Constraint::Unsatisfiable(_) => false,
+    };
+    is_hoistable_kind && constraint.ids().all(|var| block_context.is_variable_available(scope, var))
+}
+
+fn is_annotated_alike(
+    constraint: &Constraint<Variable>,
+    local_annotations: &TypeAnnotations,
+    branch_annotations: &[&TypeAnnotations],
+) -> bool {
+    let constraint_annotations = branch_annotations[0].constraint_annotations_of(constraint.clone());
+    let constraints_alike = branch_annotations
+        .iter()
+        .all(|annotations| annotations.constraint_annotations_of(constraint.clone()) == constraint_annotations);
+    constraints_alike
+        && constraint.vertices().all(|vertex| {
+            let vertex_annotations = branch_annotations[0].vertex_annotations_of(vertex);
+            branch_annotations.iter().all(|annotations| annotations.vertex_annotations_of(vertex) == vertex_annotations)
+                && local_annotations
+                    .vertex_annotations_of(vertex)
+                    .map_or(true, |local| Some(local) == vertex_annotations)
+        })
+}
+
+fn is_any_equivalent<'a>(
+    constraint: &Constraint<Variable>,
+    others: impl IntoIterator<Item = &'a Constraint<Variable>>,
+) -> bool {
+    others.into_iter().any(|other| is_structurally_equivalent(constraint, other))
+}
@@ -10,9 +10,11 @@ use ir::pattern::conjunction::Conjunction;
 
 use crate::annotation::pipeline::AnnotatedPipeline;
 
+pub mod disjunction_factoring;
 pub mod redundant_constraints;
 pub mod relation_index;
 pub mod transform;
+pub mod type_negation;
 
 pub(crate) trait ConjunctionTransformation {
     fn apply(conjunction: &mut Conjunction);
@@ -10,10 +10,12 @@ use storage::snapshot::ReadableSnapshot;
 use crate::{
     annotation::pipeline::{AnnotatedPipeline, AnnotatedStage},
     transformation::{
+        disjunction_factoring::factor_common_disjunction_constraints,
         redundant_constraints::{
             optimize_away_statically_unsatisfiable_conjunctions, prune_redundant_roleplayer_deduplication,
         },
         relation_index::relation_index_transformation,
+        type_negation::resolve_type_negations,
         StaticOptimiserError,
     },
 };
@@ -25,8 +27,11 @@ pub fn apply_transformations(
 ) -> Result<(), StaticOptimiserError> {
     for stage in &mut pipeline.annotated_stages {
         if let AnnotatedStage::Match { block, block_annotations, .. } = stage {
+            let block_context = block.block_context().clone();
+            resolve_type_negations(block.conjunction_mut(), &block_context, block_annotations);
             optimize_away_statically_unsatisfiable_conjunctions(block.conjunction_mut(), block_annotations);
             prune_redundant_roleplayer_deduplication(block.conjunction_mut(), block_annotations);
+            factor_common_disjunction_constraints(block.conjunction_mut(), &block_context, block_annotations);
             relation_index_transformation(block.conjunction_mut(), block_annotations, type_manager, snapshot)?;
         }
     }
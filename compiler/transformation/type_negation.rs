@@ -0,0 +1,225 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use answer::{variable::Variable, Type};
+use ir::{
+    pattern::{
+        conjunction::Conjunction, constraint::Constraint, negation::Negation, nested_pattern::NestedPattern, Scope,
+        Vertex,
+    },
+    pipeline::block::BlockContext,
+};
+use itertools::Itertools;
+
+use crate::annotation::type_annotations::{
+    BlockAnnotations, ConstraintTypeAnnotations, LeftRightAnnotations, LinksAnnotations,
+};
+
+/// Resolves negations of type-level constraints at compile time, instead of probing them for every row:
+///
+///   $x isa person; not { $x isa employee; };
+/// becomes
+///   $x isa person;
+/// with the types of employee removed from the annotations of `$x`, and of the constraints that produce it.
+///
+/// A negation is only resolved when it consists of `isa`, `sub`, label and kind constraints over a single variable,
+/// so that its annotations are exactly the types for which it matches. The variable must be declared by the enclosing
+/// conjunction and only be produced by its constraints, so that narrowing their annotations narrows its values.
+/// If no type remains, the enclosing conjunction is unsatisfiable.
+pub fn resolve_type_negations(
+    conjunction: &mut Conjunction,
+    block_context: &BlockContext,
+    block_annotations: &mut BlockAnnotations,
+) {
+    for nested in conjunction.nested_patterns_mut() {
+        match nested {
+            NestedPattern::Disjunction(disjunction) => {
+                for branch in disjunction.conjunctions_mut() {
+                    resolve_type_negations(branch, block_context, block_annotations);
+                }
+            }
+            NestedPattern::Negation(negation) => {
+                resolve_type_negations(negation.conjunction_mut(), block_context, block_annotations)
+            }
+            NestedPattern::Optional(optional) => {
+                resolve_type_negations(optional.conjunction_mut(), block_context, block_annotations)
+            }
+        }
+    }
+
+    let resolvable = conjunction
+        .nested_patterns()
+        .iter()
+        .filter_map(|nested| match nested {
+            NestedPattern::Negation(negation) => {
+                let (variable, negated_types) = negated_types(conjunction, negation, block_context, block_annotations)?;
+                Some((negation.scope_id(), variable, negated_types))
+            }
+            NestedPattern::Disjunction(_) | NestedPattern::Optional(_) => None,
+        })
+        .collect_vec();
+    if resolvable.is_empty() {
+        return;
+    }
+
+    let mut is_unsatisfiable = false;
+    for (_, variable, negated_types) in &resolvable {
+        let vertex = Vertex::Variable(*variable);
+        let local_annotations = block_annotations.type_annotations_mut_of(conjunction).unwrap();
+        let remaining_types: BTreeSet<Type> = local_annotations
+            .vertex_annotations_of(&vertex)
+            .unwrap()
+            .iter()
+            .filter(|type_| !negated_types.contains(type_))
+            .copied()
+            .collect();
+        for constraint in conjunction.constraints().iter().filter(|constraint| constraint.ids().contains(variable)) {
+            let Some(annotations) = local_annotations.constraint_annotations_mut().get_mut(constraint) else {
+                continue;
+            };
+            *annotations = restrict_annotations(constraint, annotations, &vertex, &remaining_types);
+        }
+        is_unsatisfiable |= remaining_types.is_empty();
+        local_annotations.vertex_annotations_mut().insert(vertex, Arc::new(remaining_types));
+    }
+    conjunction.optimise_away_negations(&resolvable.into_iter().map(|(scope_id, _, _)| scope_id).collect_vec());
+    if is_unsatisfiable {
+        conjunction.set_unsatisfiable();
+    }
+}
+
+fn negated_types(
+    conjunction: &Conjunction,
+    negation: &Negation,
+    block_context: &BlockContext,
+    block_annotations: &BlockAnnotations,
+) -> Option<(Variable, Arc<BTreeSet<Type>>)> {
+    let negated = negation.conjunction();
+    if !negated.nested_patterns().is_empty() || negated.is_set_to_unsatisfiable() {
+        return None;
+    }
+    let variable = negated.referenced_variables().exactly_one().ok()?;
+    let is_type_level = negated.constraints().iter().all(|constraint| {
+        matches!(constraint, Constraint::Isa(_) | Constraint::Sub(_) | Constraint::Label(_) | Constraint::Kind(_))
+    });
+    if !is_type_level || block_context.get_scope(&variable) != Some(conjunction.scope_id()) {
+        return None;
+    }
+
+    // any other pattern producing the variable would not be narrowed by the annotations of this conjunction
+    let is_produced_by_nested = conjunction.nested_patterns().iter().any(|nested| match nested {
+        NestedPattern::Disjunction(disjunction) => disjunction.referenced_variables().contains(&variable),
+        NestedPattern::Optional(_) => true,
+        NestedPattern::Negation(_) => false,
+    });
+    let vertex = Vertex::Variable(variable);
+    let producers =
+        conjunction.constraints().iter().filter(|constraint| constraint.ids().contains(&variable)).collect_vec();
+    if is_produced_by_nested
+        || producers.is_empty()
+        || !producers.iter().all(|constraint| is_restrictable(constraint, &vertex))
+    {
+        return None;
+    }
+
+    block_annotations.type_annotations_of(negated)?.vertex_annotations_of(&vertex).cloned()
+}
+
+fn is_restrictable(constraint: &Constraint<Variable>, vertex: &Vertex<Variable>) -> bool {
+    match constraint {
+        Constraint::Label(_) | Constraint::Kind(_) => true,
+        Constraint::Links(links) => {
+            (links.relation() == vertex) != (links.player() == vertex) && links.role_type() != vertex
+        }
+        _ => left_right_vertices(constraint).is_some_and(|(left, right)| (left == vertex) != (right == vertex)),
+    }
+}
+
+fn left_right_vertices(constraint: &Constraint<Variable>) -> Option<(&Vertex<Variable>, &Vertex<Variable>)> {
+    match constraint {
+        Constraint::Isa(isa) => Some((isa.thing(), isa.type_())),
+        Constraint::Has(has) => Some((has.owner(), has.attribute())),
+        Constraint::Sub(sub) => Some((sub.subtype(), sub.supertype())),
+        Constraint::Owns(owns) => Some((owns.owner(), owns.attribute())),
+        Constraint::Relates(relates) => Some((relates.relation(), relates.role_type())),
+        Constraint::Plays(plays) => Some((plays.player(), plays.role_type())),
+        _ => None,
+    }
+}
+
+fn restrict_annotations(
+    constraint: &Constraint<Variable>,
+    annotations: &ConstraintTypeAnnotations,
+    vertex: &Vertex<Variable>,
+    types: &BTreeSet<Type>,
+) -> ConstraintTypeAnnotations {
+    match (constraint, annotations) {
+        (Constraint::Links(links), ConstraintTypeAnnotations::Links(annotations)) => {
+            let annotations = if links.relation() == vertex {
+                let relation_to_player = restrict_keys(&annotations.relation_to_player, types);
+                let player_to_relation = restrict_values(&annotations.player_to_relation, types);
+                LinksAnnotations {
+                    relation_to_role: Arc::new(restrict_keys(
+                        &annotations.relation_to_role,
+                        &key_set(&relation_to_player),
+                    )),
+                    player_to_role: Arc::new(restrict_keys(&annotations.player_to_role, &key_set(&player_to_relation))),
+                    relation_to_player: Arc::new(relation_to_player),
+                    player_to_relation: Arc::new(player_to_relation),
+                }
+            } else {
+                let player_to_relation = restrict_keys(&annotations.player_to_relation, types);
+                let relation_to_player = restrict_values(&annotations.relation_to_player, types);
+                LinksAnnotations {
+                    relation_to_role: Arc::new(restrict_keys(
+                        &annotations.relation_to_role,
+                        &key_set(&relation_to_player),
+                    )),
+                    player_to_role: Arc::new(restrict_keys(&annotations.player_to_role, &key_set(&player_to_relation))),
+                    relation_to_player: Arc::new(relation_to_player),
+                    player_to_relation: Arc::new(player_to_relation),
+                }
+            };
+            ConstraintTypeAnnotations::Links(annotations)
+        }
+        (_, ConstraintTypeAnnotations::LeftRight(annotations)) => {
+            let (left, _) = left_right_vertices(constraint).unwrap();
+            let annotations = if left == vertex {
+                LeftRightAnnotations::new(
+                    restrict_keys(&annotations.left_to_right(), types),
+                    restrict_values(&annotations.right_to_left(), types),
+                )
+            } else {
+                LeftRightAnnotations::new(
+                    restrict_values(&annotations.left_to_right(), types),
+                    restrict_keys(&annotations.right_to_left(), types),
+                )
+            };
+            ConstraintTypeAnnotations::LeftRight(annotations)
+        }
+        _ => annotations.clone(),
+    }
+}
+
+fn restrict_keys<V: Clone>(map: &BTreeMap<Type, V>, types: &BTreeSet<Type>) -> BTreeMap<Type, V> {
+    map.iter().filter(|(type_, _)| types.contains(type_)).map(|(type_, value)| (*type_, value.clone())).collect()
+}
+
+fn restrict_values(map: &BTreeMap<Type, Vec<Type>>, types: &BTreeSet<Type>) -> BTreeMap<Type, Vec<Type>> {
+    map.iter()
+        .map(|(type_, values)| (*type_, values.iter().filter(|value| types.contains(value)).copied().collect_vec()))
+        .filter(|(_, values)| !values.is_empty())
+        .collect()
+}
+
+fn key_set<V>(map: &BTreeMap<Type, V>) -> BTreeSet<Type> {
+    map.keys().copied().collect()
+}
@@ -360,3 +360,43 @@ fn put_plays() {
 
     assert_statistics_eq!(synchronised, read_statistics(storage, &thing_manager));
 }
+
+#[test]
+fn attribute_value_distribution() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+
+    let age_label = Label::build("age", None);
+
+    let mut snapshot = storage.clone().open_snapshot_schema();
+    let age_type = type_manager.create_attribute_type(&mut snapshot, &age_label).unwrap();
+    age_type.set_value_type(&mut snapshot, &type_manager, &thing_manager, ValueType::Integer).unwrap();
+    age_type
+        .set_annotation(
+            &mut snapshot,
+            &type_manager,
+            &thing_manager,
+            AttributeTypeAnnotation::Independent(AnnotationIndependent),
+            StorageCounters::DISABLED,
+        )
+        .unwrap();
+    for age in 0..100 {
+        thing_manager.create_attribute(&mut snapshot, age_type, Value::Integer(age)).unwrap();
+    }
+    thing_manager.finalise(&mut snapshot, StorageCounters::DISABLED).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap().unwrap();
+
+    let mut synchronised = Statistics::new(SequenceNumber::MIN);
+    synchronised.may_synchronise(&storage).unwrap();
+
+    let distribution = synchronised.attribute_value_distribution(age_type).unwrap();
+    assert_eq!(distribution.count(), 100);
+    assert_eq!(distribution.fraction_within(None, None), 1.0);
+    assert_eq!(distribution.fraction_within(Some(100.0), None), 0.0);
+    let oldest = distribution.fraction_within(Some(90.0), None);
+    assert!((0.05..0.15).contains(&oldest), "{oldest}");
+    let youngest = distribution.fraction_within(None, Some(9.0));
+    assert!((0.05..0.15).contains(&youngest), "{youngest}");
+    assert!(distribution.fraction_within(Some(1.0), None) > 0.95);
+}
@@ -8,7 +8,14 @@ use std::sync::Arc;
 
 use concept::{
     thing::{statistics::Statistics, thing_manager::ThingManager},
-    type_::type_manager::{type_cache::TypeCache, TypeManager},
+    type_::{
+        attribute_type::AttributeType,
+        entity_type::EntityType,
+        object_type::ObjectType,
+        relation_type::RelationType,
+        role_type::RoleType,
+        type_manager::{type_cache::TypeCache, TypeManager},
+    },
 };
 use durability::DurabilitySequenceNumber;
 use encoding::graph::{
@@ -44,3 +51,70 @@ pub fn load_managers(
     ));
     (type_manager, thing_manager)
 }
+
+/// Builds `Statistics` directly from instance counts, so planner tests need no storage or inserted data.
+pub struct StatisticsBuilder {
+    statistics: Statistics,
+}
+
+impl StatisticsBuilder {
+    pub fn new() -> Self {
+        Self { statistics: Statistics::new(SequenceNumber::MIN) }
+    }
+
+    pub fn entities(mut self, entity_type: EntityType, count: u64) -> Self {
+        self.statistics.update_entities(entity_type, count as i64);
+        self
+    }
+
+    pub fn relations(mut self, relation_type: RelationType, count: u64) -> Self {
+        self.statistics.update_relations(relation_type, count as i64);
+        self
+    }
+
+    /// Attribute instances are distinct values, so this is also the number of distinct values of the type.
+    pub fn attributes(mut self, attribute_type: AttributeType, count: u64) -> Self {
+        self.statistics.update_attributes(attribute_type, count as i64);
+        self
+    }
+
+    /// One instance of the numeric attribute type per value, recorded in its value distribution as well.
+    pub fn attribute_values(mut self, attribute_type: AttributeType, values: impl IntoIterator<Item = f64>) -> Self {
+        for value in values {
+            self.statistics.update_attributes(attribute_type, 1);
+            self.statistics.update_attribute_values(attribute_type, value, 1);
+        }
+        self
+    }
+
+    pub fn has(mut self, owner_type: ObjectType, attribute_type: AttributeType, count: u64) -> Self {
+        self.statistics.update_has(owner_type, attribute_type, count as i64);
+        self
+    }
+
+    pub fn role_players(
+        mut self,
+        player_type: ObjectType,
+        role_type: RoleType,
+        relation_type: RelationType,
+        count: u64,
+    ) -> Self {
+        self.statistics.update_role_player(player_type, role_type, relation_type, count as i64);
+        self
+    }
+
+    pub fn indexed_players(mut self, player_1_type: ObjectType, player_2_type: ObjectType, count: u64) -> Self {
+        self.statistics.update_indexed_player(player_1_type, player_2_type, count as i64);
+        self
+    }
+
+    pub fn build(self) -> Statistics {
+        self.statistics
+    }
+}
+
+impl Default for StatisticsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
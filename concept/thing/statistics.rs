@@ -18,7 +18,7 @@ use durability::{DurabilityRecordType, DurabilitySequenceNumber};
 use encoding::graph::{
     thing::{
         edge::{ThingEdgeHas, ThingEdgeIndexedRelation, ThingEdgeLinks},
-        vertex_attribute::AttributeVertex,
+        vertex_attribute::{AttributeID, AttributeVertex},
         vertex_object::ObjectVertex,
         ThingVertex,
     },
@@ -91,11 +91,14 @@ pub struct Statistics {
 
     // TODO: adding role types is possible, but won't help with filtering before reading storage since roles are not in the prefix
     pub links_index_counts: HashMap<ObjectType, HashMap<ObjectType, u64>>,
-    // future: attribute value distributions, attribute value ownership distributions, etc.
+
+    pub attribute_value_distributions: HashMap<AttributeType, ValueDistribution>,
+    // future: attribute value ownership distributions, etc.
 }
 
 impl Statistics {
-    const ENCODING_VERSION: StatisticsEncodingVersion = 0;
+    // version 1 adds the attribute value distributions
+    const ENCODING_VERSION: StatisticsEncodingVersion = 1;
     const COMMIT_CONTEXT_SIZE: u64 = 8;
 
     pub fn new(sequence_number: SequenceNumber) -> Self {
@@ -122,6 +125,21 @@ impl Statistics {
             relation_role_player_counts: HashMap::new(),
             player_role_relation_counts: HashMap::new(),
             links_index_counts: HashMap::new(),
+            attribute_value_distributions: HashMap::new(),
+        }
+    }
+
+    /// Whether these statistics account for all the data visible to a snapshot opened at the given sequence number,
+    /// which may have uncommitted writes of its own.
+    pub fn freshness_at(
+        &self,
+        snapshot_sequence_number: SequenceNumber,
+        has_uncommitted_writes: bool,
+    ) -> StatisticsFreshness {
+        if self.sequence_number >= snapshot_sequence_number && !has_uncommitted_writes {
+            StatisticsFreshness::Current
+        } else {
+            StatisticsFreshness::Stale
         }
     }
 
@@ -243,8 +261,12 @@ impl Statistics {
                 self.update_relations(type_, delta);
                 total_delta += delta;
             } else if AttributeVertex::is_attribute_vertex(StorageKeyReference::from(&key)) {
-                let type_ = Attribute::new(AttributeVertex::decode(key.bytes())).type_();
+                let vertex = AttributeVertex::decode(key.bytes());
+                let type_ = Attribute::new(vertex).type_();
                 self.update_attributes(type_, delta);
+                if let Some(value) = ValueDistribution::numeric_value(vertex.attribute_id()) {
+                    self.update_attribute_values(type_, value, delta);
+                }
             } else if ThingEdgeHas::is_has(&key) {
                 let edge = ThingEdgeHas::decode(Bytes::Reference(key.bytes()));
                 self.update_has(Object::new(edge.from()).type_(), Attribute::new(edge.to()).type_(), delta);
@@ -283,6 +305,7 @@ impl Statistics {
                 let type_ = AttributeType::read_from(Bytes::Reference(key.bytes()).into_owned());
                 if matches!(write, Write::Delete) {
                     self.attribute_counts.remove(&type_);
+                    self.attribute_value_distributions.remove(&type_);
                     self.attribute_owner_counts.remove(&type_);
                     for map in self.has_attribute_counts.values_mut() {
                         map.remove(&type_);
@@ -325,28 +348,47 @@ impl Statistics {
         self.links_index_counts.retain(|_, map| !map.is_empty());
     }
 
-    fn update_entities(&mut self, entity_type: EntityType, delta: i64) {
+    /// Counts `delta` more instances of the entity type. Like the other `update_` methods, this is how commits are
+    /// accounted for, and lets statistics be built directly from counts without synchronising with storage.
+    pub fn update_entities(&mut self, entity_type: EntityType, delta: i64) {
         let count = self.entity_counts.entry(entity_type).or_default();
         *count = count.checked_add_signed(delta).unwrap();
         self.total_entity_count = self.total_entity_count.checked_add_signed(delta).unwrap();
         self.total_thing_count = self.total_thing_count.checked_add_signed(delta).unwrap();
     }
 
-    fn update_relations(&mut self, relation_type: RelationType, delta: i64) {
+    pub fn update_relations(&mut self, relation_type: RelationType, delta: i64) {
         let count = self.relation_counts.entry(relation_type).or_default();
         *count = count.checked_add_signed(delta).unwrap();
         self.total_relation_count = self.total_relation_count.checked_add_signed(delta).unwrap();
         self.total_thing_count = self.total_thing_count.checked_add_signed(delta).unwrap();
     }
 
-    fn update_attributes(&mut self, attribute_type: AttributeType, delta: i64) {
+    pub fn update_attributes(&mut self, attribute_type: AttributeType, delta: i64) {
         let count = self.attribute_counts.entry(attribute_type).or_default();
         *count = count.checked_add_signed(delta).unwrap();
         self.total_attribute_count = self.total_attribute_count.checked_add_signed(delta).unwrap();
         self.total_thing_count = self.total_thing_count.checked_add_signed(delta).unwrap();
     }
 
-    fn update_has(&mut self, owner_type: ObjectType, attribute_type: AttributeType, delta: i64) {
+    /// Counts `delta` more instances with the given value in the value distribution of the numeric attribute type. The
+    /// instances themselves are counted by `update_attributes`.
+    pub fn update_attribute_values(&mut self, attribute_type: AttributeType, value: f64, delta: i64) {
+        self.attribute_value_distributions.entry(attribute_type).or_default().update(value, delta);
+        if self.attribute_value_distributions[&attribute_type].count == 0 {
+            self.attribute_value_distributions.remove(&attribute_type);
+        }
+    }
+
+    /// The distribution of the values of a numeric attribute type, if it accounts for every instance of the type.
+    /// Instances written before the distributions were recorded are not accounted for, so the distributions of their
+    /// types are unusable until statistics are recomputed.
+    pub fn attribute_value_distribution(&self, attribute_type: AttributeType) -> Option<&ValueDistribution> {
+        let distribution = self.attribute_value_distributions.get(&attribute_type)?;
+        (Some(&distribution.count) == self.attribute_counts.get(&attribute_type)).then_some(distribution)
+    }
+
+    pub fn update_has(&mut self, owner_type: ObjectType, attribute_type: AttributeType, delta: i64) {
         let attribute_count =
             self.has_attribute_counts.entry(owner_type).or_default().entry(attribute_type).or_default();
         *attribute_count = attribute_count.checked_add_signed(delta).unwrap();
@@ -355,7 +397,7 @@ impl Statistics {
         self.total_has_count = self.total_has_count.checked_add_signed(delta).unwrap();
     }
 
-    fn update_role_player(
+    pub fn update_role_player(
         &mut self,
         player_type: ObjectType,
         role_type: RoleType,
@@ -390,7 +432,7 @@ impl Statistics {
         *player_role_relation_count = player_role_relation_count.checked_add_signed(delta).unwrap();
     }
 
-    fn update_indexed_player(&mut self, player_1_type: ObjectType, player_2_type: ObjectType, delta: i64) {
+    pub fn update_indexed_player(&mut self, player_1_type: ObjectType, player_2_type: ObjectType, delta: i64) {
         let player_1_to_2_index_count =
             self.links_index_counts.entry(player_1_type).or_default().entry(player_2_type).or_default();
         *player_1_to_2_index_count = match player_1_to_2_index_count.checked_add_signed(delta) {
@@ -504,6 +546,116 @@ impl Statistics {
         self.role_player_counts.clear();
         self.relation_role_counts.clear();
         self.links_index_counts.clear();
+        self.attribute_value_distributions.clear();
+    }
+}
+
+/// Whether statistics account for all the data a snapshot can read. A type without recorded instances is only known to
+/// be empty when they do: otherwise, its instances may have been written since the statistics were synchronised.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StatisticsFreshness {
+    #[default]
+    Current,
+    Stale,
+}
+
+/// The distribution of the values of the instances of a numeric attribute type: a histogram with one bucket per sign and
+/// power of two, so that it needs no fixed range. Buckets are counted exactly, while the range of the values only ever
+/// widens and is used to clip the outermost buckets.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValueDistribution {
+    count: u64,
+    range: Option<(f64, f64)>,
+    buckets: BTreeMap<i16, u64>,
+}
+
+impl ValueDistribution {
+    // offsets binary exponents, which are at least -1074 for doubles, to keep non-zero values away from bucket 0
+    const EXPONENT_OFFSET: i16 = 1100;
+
+    fn numeric_value(attribute_id: AttributeID) -> Option<f64> {
+        match attribute_id {
+            AttributeID::Integer(id) => Some(id.read().as_i64() as f64),
+            AttributeID::Double(id) => Some(id.read().as_f64()).filter(|value| value.is_finite()),
+            _ => None,
+        }
+    }
+
+    fn update(&mut self, value: f64, delta: i64) {
+        let bucket = self.buckets.entry(Self::bucket_of(value)).or_default();
+        *bucket = bucket.saturating_add_signed(delta);
+        if *bucket == 0 {
+            self.buckets.remove(&Self::bucket_of(value));
+        }
+        self.count = self.count.saturating_add_signed(delta);
+        if delta > 0 {
+            self.range = match self.range {
+                None => Some((value, value)),
+                Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+            };
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The estimated fraction of the values that lie within the given bounds, assuming values are spread evenly within
+    /// each bucket.
+    pub fn fraction_within(&self, lower: Option<f64>, upper: Option<f64>) -> f64 {
+        let Some((min, max)) = self.range else { return 0.0 };
+        if self.count == 0 {
+            return 0.0;
+        }
+        let selected: f64 = self
+            .buckets
+            .iter()
+            .map(|(&bucket, &count)| {
+                let (bucket_low, bucket_high) = Self::bucket_bounds(bucket);
+                let (low, high) = (f64::max(bucket_low, min), f64::min(bucket_high, max));
+                let (from, to) = (
+                    lower.map_or(low, |lower| f64::max(lower, low)),
+                    upper.map_or(high, |upper| f64::min(upper, high)),
+                );
+                if high <= low {
+                    // all the values of the bucket are at the edge of the range
+                    let is_selected =
+                        !matches!(lower, Some(lower) if low < lower) && !matches!(upper, Some(upper) if low > upper);
+                    if is_selected {
+                        count as f64
+                    } else {
+                        0.0
+                    }
+                } else if from > to {
+                    0.0
+                } else {
+                    count as f64 * (to - from) / (high - low)
+                }
+            })
+            .sum();
+        f64::clamp(selected / self.count as f64, 0.0, 1.0)
+    }
+
+    fn bucket_of(value: f64) -> i16 {
+        if value == 0.0 {
+            0
+        } else {
+            let bucket = value.abs().log2().floor() as i16 + Self::EXPONENT_OFFSET;
+            if value < 0.0 {
+                -bucket
+            } else {
+                bucket
+            }
+        }
+    }
+
+    fn bucket_bounds(bucket: i16) -> (f64, f64) {
+        let exponent = (bucket.abs() - Self::EXPONENT_OFFSET) as i32;
+        match bucket.signum() {
+            0 => (0.0, 0.0),
+            1 => (2f64.powi(exponent), 2f64.powi(exponent + 1)),
+            _ => (-(2f64.powi(exponent + 1)), -(2f64.powi(exponent))),
+        }
     }
 }
 
@@ -645,6 +797,7 @@ impl fmt::Debug for Statistics {
         write_hashmap!("relation_role_player_counts", self.relation_role_player_counts);
         write_hashmap!("player_role_relation_counts", self.player_role_relation_counts);
         write_hashmap!("links_index_counts", self.links_index_counts);
+        write_hashmap!("attribute_value_distributions", self.attribute_value_distributions);
 
         if pretty {
             write!(f, "}}")?;
@@ -769,7 +922,7 @@ mod serialise {
     };
 
     use crate::{
-        thing::statistics::{SerialisableType, Statistics},
+        thing::statistics::{SerialisableType, Statistics, ValueDistribution},
         type_::{
             attribute_type::AttributeType, entity_type::EntityType, object_type::ObjectType,
             relation_type::RelationType, role_type::RoleType,
@@ -798,10 +951,11 @@ mod serialise {
         RelationRolePlayerCounts,
         PlayerRoleRelationCounts,
         LinksIndexCounts,
+        AttributeValueDistributions,
     }
 
     impl Field {
-        const NAMES: [&'static str; 21] = [
+        const NAMES: [&'static str; 22] = [
             Self::StatisticsVersion.name(),
             Self::OpenSequenceNumber.name(),
             Self::LastDurableWriteTotalCount.name(),
@@ -823,6 +977,7 @@ mod serialise {
             Self::RelationRolePlayerCounts.name(),
             Self::PlayerRoleRelationCounts.name(),
             Self::LinksIndexCounts.name(),
+            Self::AttributeValueDistributions.name(),
         ];
 
         const fn name(&self) -> &str {
@@ -848,6 +1003,7 @@ mod serialise {
                 Field::RelationRolePlayerCounts => "RelationRolePlayerCounts",
                 Field::PlayerRoleRelationCounts => "RolePlayerRelationCounts",
                 Field::LinksIndexCounts => "PlayerIndexCounts",
+                Field::AttributeValueDistributions => "AttributeValueDistributions",
             }
         }
 
@@ -874,6 +1030,7 @@ mod serialise {
                 "RelationRolePlayerCounts" => Some(Field::RelationRolePlayerCounts),
                 "RolePlayerRelationCounts" => Some(Field::PlayerRoleRelationCounts),
                 "PlayerIndexCounts" => Some(Field::LinksIndexCounts),
+                "AttributeValueDistributions" => Some(Field::AttributeValueDistributions),
                 _ => None,
             }
         }
@@ -885,7 +1042,7 @@ mod serialise {
             S: Serializer,
         {
             let mut state = serializer.serialize_struct("Statistics", Field::NAMES.len())?;
-            state.serialize_field(Field::StatisticsVersion.name(), &self.encoding_version)?;
+            state.serialize_field(Field::StatisticsVersion.name(), &Statistics::ENCODING_VERSION)?;
 
             state.serialize_field(Field::OpenSequenceNumber.name(), &self.sequence_number)?;
             state.serialize_field(Field::LastDurableWriteTotalCount.name(), &self.last_durable_write_total_count)?;
@@ -934,6 +1091,13 @@ mod serialise {
             state
                 .serialize_field(Field::LinksIndexCounts.name(), &to_serialisable_map_map(&self.links_index_counts))?;
 
+            let value_distributions: HashMap<SerialisableType, &ValueDistribution> = self
+                .attribute_value_distributions
+                .iter()
+                .map(|(&type_, distribution)| (type_.into(), distribution))
+                .collect();
+            state.serialize_field(Field::AttributeValueDistributions.name(), &value_distributions)?;
+
             state.end()
         }
     }
@@ -980,6 +1144,12 @@ mod serialise {
         map.into_iter().map(|(type_, value)| (type_.into_object_type(), value)).collect()
     }
 
+    fn into_value_distributions(
+        map: HashMap<SerialisableType, ValueDistribution>,
+    ) -> HashMap<AttributeType, ValueDistribution> {
+        map.into_iter().map(|(type_, distribution)| (type_.into_attribute_type(), distribution)).collect()
+    }
+
     impl<'de> Deserialize<'de> for Statistics {
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
@@ -1024,7 +1194,8 @@ mod serialise {
                 where
                     V: SeqAccess<'de>,
                 {
-                    let statistics_version = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                    let statistics_version: u64 =
+                        seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
                     let sequence_number = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
                     let last_durable_write_total_count =
                         seq.next_element()?.ok_or_else(|| de::Error::invalid_length(2, &self))?;
@@ -1109,8 +1280,17 @@ mod serialise {
                         .into_iter()
                         .map(|(type_1, map)| (type_1.into_object_type(), into_object_map(map)))
                         .collect();
+                    // statistics written before version 1 end here, and have no value distributions
+                    let attribute_value_distributions = match statistics_version {
+                        0 => HashMap::new(),
+                        _ => {
+                            let encoded: HashMap<SerialisableType, ValueDistribution> =
+                                seq.next_element()?.ok_or_else(|| de::Error::invalid_length(21, &self))?;
+                            into_value_distributions(encoded)
+                        }
+                    };
                     Ok(Statistics {
-                        encoding_version: statistics_version,
+                        encoding_version: Statistics::ENCODING_VERSION,
                         sequence_number,
                         last_durable_write_sequence_number: sequence_number,
                         last_durable_write_total_count,
@@ -1132,6 +1312,7 @@ mod serialise {
                         relation_role_player_counts,
                         player_role_relation_counts,
                         links_index_counts,
+                        attribute_value_distributions,
                     })
                 }
 
@@ -1139,7 +1320,7 @@ mod serialise {
                 where
                     V: MapAccess<'de>,
                 {
-                    let mut statistics_version = None;
+                    let mut statistics_version: Option<u64> = None;
                     let mut open_sequence_number = None;
                     let mut last_durable_write_total_count = None;
                     let mut total_count = None;
@@ -1160,6 +1341,7 @@ mod serialise {
                     let mut relation_role_player_counts = None;
                     let mut player_role_relation_counts = None;
                     let mut links_indexs_counts = None;
+                    let mut attribute_value_distributions = None;
                     while let Some(key) = map.next_key()? {
                         match key {
                             Field::StatisticsVersion => {
@@ -1359,12 +1541,18 @@ mod serialise {
                                         .collect(),
                                 );
                             }
+                            Field::AttributeValueDistributions => {
+                                if attribute_value_distributions.is_some() {
+                                    return Err(de::Error::duplicate_field(Field::AttributeValueDistributions.name()));
+                                }
+                                attribute_value_distributions = Some(into_value_distributions(map.next_value()?));
+                            }
                         }
                     }
 
+                    statistics_version.ok_or_else(|| de::Error::missing_field(Field::StatisticsVersion.name()))?;
                     Ok(Statistics {
-                        encoding_version: statistics_version
-                            .ok_or_else(|| de::Error::missing_field(Field::StatisticsVersion.name()))?,
+                        encoding_version: Statistics::ENCODING_VERSION,
                         sequence_number: open_sequence_number
                             .ok_or_else(|| de::Error::missing_field(Field::OpenSequenceNumber.name()))?,
                         last_durable_write_total_count: last_durable_write_total_count
@@ -1405,6 +1593,8 @@ mod serialise {
                             .ok_or_else(|| de::Error::missing_field(Field::PlayerRoleRelationCounts.name()))?,
                         links_index_counts: links_indexs_counts
                             .ok_or_else(|| de::Error::missing_field(Field::LinksIndexCounts.name()))?,
+                        // statistics written before version 1 have no value distributions
+                        attribute_value_distributions: attribute_value_distributions.unwrap_or_default(),
                     })
                 }
             }
@@ -732,6 +732,30 @@ pub trait PlayerAPI: TypeAPI {
     }
 }
 
+// Types are serialized as their type ID alone: the kind is implied by the type being deserialized, and the ID is only
+// meaningful against the schema it was read from.
+macro_rules! serialize_as_type_id {
+    ($($type_:ty),+ $(,)?) => {
+        $(
+            impl Serialize for $type_ {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    use encoding::graph::Typed;
+                    self.vertex().type_id_().as_u16().serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $type_ {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    use encoding::graph::type_::vertex::{PrefixedTypeVertexEncoding, TypeID};
+                    u16::deserialize(deserializer).map(|type_id| Self::build_from_type_id(TypeID::new(type_id)))
+                }
+            }
+        )+
+    };
+}
+
+serialize_as_type_id!(entity_type::EntityType, relation_type::RelationType, AttributeType, RoleType);
+
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
 pub enum Ordering {
     // ##########################################################################
@@ -20,6 +20,7 @@ use std::{
 };
 
 use cache::CACHE_DB_NAME_PREFIX;
+use compiler::executable::function::FunctionVersions;
 use concept::{
     thing::statistics::{Statistics, StatisticsError},
     type_::type_manager::{
@@ -64,6 +65,7 @@ pub(super) struct Schema {
     pub(super) thing_statistics: Arc<Statistics>,
     pub(super) type_cache: Arc<TypeCache>,
     pub(super) function_cache: Arc<FunctionCache>,
+    pub(super) function_versions: Arc<FunctionVersions>,
 }
 
 type SchemaWriteTransactionState = (bool, usize, VecDeque<TransactionReservationRequest>);
@@ -283,7 +285,12 @@ impl Database<WALClient> {
             .map_err(|error| FunctionCacheInitialise { typedb_source: error })?,
         );
 
-        let schema = Arc::new(RwLock::new(Schema { thing_statistics, type_cache, function_cache }));
+        let schema = Arc::new(RwLock::new(Schema {
+            thing_statistics,
+            type_cache,
+            function_cache,
+            function_versions: Arc::default(),
+        }));
         let schema_txn_lock = Arc::new(RwLock::default());
 
         let query_cache = Arc::new(QueryCache::new());
@@ -369,7 +376,12 @@ impl Database<WALClient> {
             .map_err(|error| FunctionCacheInitialise { typedb_source: error })?,
         );
 
-        let schema = Arc::new(RwLock::new(Schema { thing_statistics, type_cache, function_cache }));
+        let schema = Arc::new(RwLock::new(Schema {
+            thing_statistics,
+            type_cache,
+            function_cache,
+            function_versions: Arc::default(),
+        }));
         let schema_txn_lock = Arc::new(RwLock::default());
 
         let checkpoint_sequence_number = match checkpoint {
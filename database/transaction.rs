@@ -68,6 +68,7 @@ impl<D: DurabilityClient> TransactionRead<D> {
         let function_manager = Arc::new(FunctionManager::new(
             database.definition_key_generator.clone(),
             Some(schema.function_cache.clone()),
+            schema.function_versions.clone(),
         ));
         let query_manager = Arc::new(QueryManager::new(Some(database.query_cache.clone())));
 
@@ -125,6 +126,7 @@ impl<D: DurabilityClient> TransactionWrite<D> {
         let function_manager = Arc::new(FunctionManager::new(
             database.definition_key_generator.clone(),
             Some(schema.function_cache.clone()),
+            schema.function_versions.clone(),
         ));
         let query_manager = Arc::new(QueryManager::new(Some(database.query_cache.clone())));
         drop(schema);
@@ -234,15 +236,17 @@ impl<D: DurabilityClient> TransactionSchema<D> {
             database.type_vertex_generator.clone(),
             None,
         ));
-        let thing_manager = {
+        let (thing_manager, function_manager) = {
             let schema = database.schema.read().unwrap();
-            ThingManager::new(
+            let thing_manager = ThingManager::new(
                 database.thing_vertex_generator.clone(),
                 type_manager.clone(),
                 schema.thing_statistics.clone(),
-            )
+            );
+            let function_manager =
+                FunctionManager::new(database.definition_key_generator.clone(), None, schema.function_versions.clone());
+            (thing_manager, Arc::new(function_manager))
         };
-        let function_manager = Arc::new(FunctionManager::new(database.definition_key_generator.clone(), None));
         let query_manager = Arc::new(QueryManager::new(None));
 
         Ok(Self {
@@ -315,6 +319,7 @@ impl<D: DurabilityClient> TransactionSchema<D> {
         drop(self.thing_manager);
 
         let function_manager = Arc::into_inner(self.function_manager).expect("Failed to unwrap Arc<FunctionManager>");
+        let invalidated_functions = function_manager.take_invalidated_functions();
         if let Err(typedb_source) = function_manager.finalise(&snapshot, &self.type_manager) {
             return (profile, Err(FunctionError { typedb_source }));
         }
@@ -344,6 +349,10 @@ impl<D: DurabilityClient> TransactionSchema<D> {
             Ok(sequence_number) => sequence_number,
             Err(typedb_source) => return (profile, Err(SnapshotError { typedb_source })),
         };
+        // executables compiled against the previous definitions are only rejected once the new ones are visible
+        for function_id in &invalidated_functions {
+            schema.function_versions.invalidate(function_id);
+        }
 
         // `None` means empty commit
         if let Some(sequence_number) = sequence_number {
@@ -14,6 +14,7 @@ use resource::constants::{
     encoding::{LABEL_NAME_STRING_INLINE, LABEL_SCOPED_NAME_STRING_INLINE, LABEL_SCOPE_STRING_INLINE},
     snapshot::BUFFER_VALUE_INLINE,
 };
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use structural_equality::StructuralEquality;
 use typeql::common::Span;
 
@@ -161,6 +162,25 @@ impl StructuralEquality for Label {
     }
 }
 
+impl Serialize for Label {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.scoped_name().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Label {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let scoped_name = String::deserialize(deserializer)?;
+        Ok(Label::parse_from(&scoped_name, None))
+    }
+}
+
 impl fmt::Debug for Label {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Label[{}]", self.scoped_name())
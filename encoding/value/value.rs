@@ -14,6 +14,7 @@ use std::{
 
 use bytes::byte_array::ByteArray;
 use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use resource::constants::encoding::AD_HOC_BYTES_INLINE;
 
 use crate::value::{
     boolean_bytes::BooleanBytes,
@@ -116,6 +117,19 @@ impl Hash for Value<'_> {
 }
 
 impl<'a> Value<'a> {
+    /// A total order over values, unlike `partial_cmp`: values of different value types are ordered by their value
+    /// type, as the attributes of each value type are in storage. Values of one value type are ordered as by
+    /// `partial_cmp`, or by their encoding where it does not order them, e.g. for a `NaN` double or durations.
+    pub fn total_cmp(&self, other: &Value<'_>) -> Ordering {
+        let self_category = self.value_type().category() as u8;
+        let other_category = other.value_type().category() as u8;
+        self_category.cmp(&other_category).then_with(|| {
+            self.as_reference().partial_cmp(&other.as_reference()).unwrap_or_else(|| {
+                self.encode_bytes::<AD_HOC_BYTES_INLINE>().cmp(&other.encode_bytes::<AD_HOC_BYTES_INLINE>())
+            })
+        })
+    }
+
     pub fn as_reference(&self) -> Value<'_> {
         match *self {
             Value::Boolean(boolean) => Value::Boolean(boolean),
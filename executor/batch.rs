@@ -5,7 +5,6 @@
  */
 
 use std::{
-    array,
     borrow::Cow,
     cmp::Ordering,
     iter::{Map, Take, Zip},
@@ -17,7 +16,7 @@ use encoding::value::value::Value;
 use error::unimplemented_feature;
 use itertools::Itertools;
 use lending_iterator::LendingIterator;
-use resource::{constants::traversal::FIXED_BATCH_ROWS_MAX, profile::StorageCounters};
+use resource::profile::StorageCounters;
 use storage::snapshot::ReadableSnapshot;
 
 use crate::{
@@ -32,38 +31,29 @@ pub struct FixedBatch {
     width: u32,
     entries: u32,
     data: Vec<VariableValue<'static>>,
-    multiplicities: [u64; FIXED_BATCH_ROWS_MAX as usize],
-    provenance: [Provenance; FIXED_BATCH_ROWS_MAX as usize],
+    // one entry per row the batch can hold, so their length is the capacity of the batch
+    multiplicities: Vec<u64>,
+    provenance: Vec<Provenance>,
 }
 
 impl FixedBatch {
-    pub(crate) const INIT_MULTIPLICITIES: [u64; FIXED_BATCH_ROWS_MAX as usize] = [1; FIXED_BATCH_ROWS_MAX as usize];
-    pub(crate) const INIT_PROVENANCES: [Provenance; FIXED_BATCH_ROWS_MAX as usize] =
-        [Provenance(0); FIXED_BATCH_ROWS_MAX as usize];
-    pub(crate) const SINGLE_EMPTY_ROW: FixedBatch = FixedBatch {
-        width: 0,
-        entries: 1,
-        data: Vec::new(),
-        multiplicities: FixedBatch::INIT_MULTIPLICITIES,
-        provenance: FixedBatch::INIT_PROVENANCES,
-    };
-
-    pub(crate) const EMPTY: FixedBatch = FixedBatch {
-        width: 0,
-        entries: 0,
-        data: Vec::new(),
-        multiplicities: FixedBatch::INIT_MULTIPLICITIES,
-        provenance: FixedBatch::INIT_PROVENANCES,
-    };
-
-    pub(crate) fn new(width: u32) -> Self {
-        let size = width * FIXED_BATCH_ROWS_MAX;
+    pub(crate) fn single_empty_row() -> Self {
+        FixedBatch { width: 0, entries: 1, data: Vec::new(), multiplicities: vec![1], provenance: vec![Provenance(0)] }
+    }
+
+    pub(crate) fn empty() -> Self {
+        FixedBatch { width: 0, entries: 0, data: Vec::new(), multiplicities: Vec::new(), provenance: Vec::new() }
+    }
+
+    /// A batch of rows of `width` that holds up to `capacity` rows. The executors of a pipeline all fill batches of
+    /// the capacity set in its `ExecutionContext`.
+    pub(crate) fn new(width: u32, capacity: u32) -> Self {
         FixedBatch {
             width,
-            data: vec![VariableValue::None; size as usize],
+            data: vec![VariableValue::None; (width * capacity) as usize],
             entries: 0,
-            multiplicities: FixedBatch::INIT_MULTIPLICITIES,
-            provenance: FixedBatch::INIT_PROVENANCES,
+            multiplicities: vec![1; capacity as usize],
+            provenance: vec![Provenance(0); capacity as usize],
         }
     }
 
@@ -71,6 +61,10 @@ impl FixedBatch {
         self.width
     }
 
+    pub(crate) fn capacity(&self) -> u32 {
+        self.multiplicities.len() as u32
+    }
+
     pub(crate) fn len(&self) -> u32 {
         self.entries
     }
@@ -80,7 +74,7 @@ impl FixedBatch {
     }
 
     pub(crate) fn is_full(&self) -> bool {
-        self.entries == FIXED_BATCH_ROWS_MAX
+        self.entries == self.capacity()
     }
 
     pub(crate) fn get_row(&self, index: u32) -> MaybeOwnedRow<'_> {
@@ -89,6 +83,11 @@ impl FixedBatch {
         MaybeOwnedRow::new_borrowed(slice, &self.multiplicities[index as usize], &self.provenance[index as usize])
     }
 
+    pub(crate) fn multiplicity(&self, index: u32) -> u64 {
+        debug_assert!(index < self.entries);
+        self.multiplicities[index as usize]
+    }
+
     pub(crate) fn get_row_mut(&mut self, index: u32) -> Row<'_> {
         debug_assert!(index < self.entries);
         self.row_internal_mut(index)
@@ -102,6 +101,29 @@ impl FixedBatch {
         result
     }
 
+    /// Discards the last row appended, restoring its slot to that of an empty batch.
+    pub(crate) fn remove_last(&mut self) {
+        debug_assert!(self.entries > 0);
+        self.entries -= 1;
+        let index = self.entries as usize;
+        self.data[row_range(index, self.width)].fill(VariableValue::None);
+        self.multiplicities[index] = 1;
+        self.provenance[index] = Provenance(0);
+    }
+
+    /// Collapses the rows of a width-0 batch into a single row carrying their total multiplicity.
+    pub(crate) fn into_multiplicity_only(self) -> FixedBatch {
+        debug_assert_eq!(self.width, 0);
+        let total: u64 = self.multiplicities[..self.entries as usize].iter().sum();
+        FixedBatch {
+            width: 0,
+            entries: u32::from(total > 0),
+            data: Vec::new(),
+            multiplicities: vec![total],
+            provenance: vec![Provenance(0)],
+        }
+    }
+
     fn row_internal_mut(&mut self, index: u32) -> Row<'_> {
         let slice = &mut self.data[row_range(index as usize, self.width)];
         Row::new(slice, &mut self.multiplicities[index as usize], &mut self.provenance[index as usize])
@@ -110,26 +132,19 @@ impl FixedBatch {
 
 impl<'a> From<MaybeOwnedRow<'a>> for FixedBatch {
     fn from(row: MaybeOwnedRow<'a>) -> Self {
-        let width = row.len() as u32;
-        let mut multiplicities = FixedBatch::INIT_MULTIPLICITIES;
-        multiplicities[0] = row.multiplicity();
-        let mut branch_provenance = FixedBatch::INIT_PROVENANCES;
-        branch_provenance[0] = row.provenance();
-        FixedBatch { width, data: row.row().to_owned(), entries: 1, multiplicities, provenance: branch_provenance }
+        FixedBatch {
+            width: row.len() as u32,
+            data: row.row().to_owned(),
+            entries: 1,
+            multiplicities: vec![row.multiplicity()],
+            provenance: vec![row.provenance()],
+        }
     }
 }
 
 impl IntoIterator for FixedBatch {
     type IntoIter = Map<
-        Take<
-            Zip<
-                vec::IntoIter<Vec<VariableValue<'static>>>,
-                Zip<
-                    array::IntoIter<u64, { FIXED_BATCH_ROWS_MAX as usize }>,
-                    array::IntoIter<Provenance, { FIXED_BATCH_ROWS_MAX as usize }>,
-                >,
-            >,
-        >,
+        Take<Zip<vec::IntoIter<Vec<VariableValue<'static>>>, Zip<vec::IntoIter<u64>, vec::IntoIter<Provenance>>>>,
         fn((Vec<VariableValue<'static>>, (u64, Provenance))) -> MaybeOwnedRow<'static>,
     >;
 
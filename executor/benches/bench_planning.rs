@@ -0,0 +1,232 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#![deny(unused_must_use)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use compiler::{
+    annotation::{function::EmptyAnnotatedFunctionSignatures, match_inference::infer_types},
+    executable::{function::ExecutableFunctionRegistry, match_::planner::plan::PlannerConfig},
+};
+use concept::{thing::statistics::Statistics, type_::type_manager::TypeManager};
+use criterion::{criterion_group, criterion_main, Criterion};
+use encoding::graph::definition::definition_key_generator::DefinitionKeyGenerator;
+use executor::{pipeline::stage::ExecutionContext, ExecutionInterrupt};
+use function::function_manager::FunctionManager;
+use ir::{
+    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    translation::{match_::translate_match, PipelineTranslationContext},
+};
+use lending_iterator::LendingIterator;
+use logger::initialise_logging_at;
+use query::query_manager::QueryManager;
+use resource::profile::CommitProfile;
+use storage::{
+    durability_client::WALClient,
+    sequence_number::SequenceNumber,
+    snapshot::{CommittableSnapshot, ReadableSnapshot},
+    MVCCStorage,
+};
+use test_utils_concept::{load_managers, setup_concept_storage};
+use test_utils_encoding::create_core_storage;
+use tracing::Level;
+
+const SCHEMA: &str = "define
+    attribute name value string;
+    attribute email value string;
+    attribute age value integer;
+    attribute height value integer;
+    attribute city value string;
+    attribute country value string;
+    attribute employer value string;
+    entity person owns name, owns email, owns age, owns height, owns city, owns country, owns employer;
+";
+
+const DATA: &str = "insert
+    $_ isa person, has name 'Alice', has email 'alice@a', has age 30, has height 160, has city 'London', has country 'UK', has employer 'Acme';
+    $_ isa person, has name 'Bob', has email 'bob@b', has age 31, has height 170, has city 'London', has country 'UK', has employer 'Acme';
+    $_ isa person, has name 'Carol', has email 'carol@c', has age 32, has height 180, has city 'Paris', has country 'France', has employer 'Initech';
+    $_ isa person, has name 'Dave', has age 33, has city 'Paris', has country 'France';
+";
+
+// two people related through five shared attributes: 15 patterns, enough to extend the beam in parallel
+const QUERY: &str = "match
+    $x isa person, has name $xn, has email $xe, has age $a, has height $h, has city $c, has country $k, has employer $m;
+    $y isa person, has name $yn, has age $a, has height $h, has city $c, has country $k, has employer $m;
+";
+
+// four people related through shared attributes: 30 constraints, for measuring the planner's per-extension overhead
+const THIRTY_CONSTRAINT_QUERY: &str = "match
+    $x isa person, has name $xn, has email $xe, has age $a, has height $h, has city $c, has country $k, has employer $m;
+    $y isa person, has name $yn, has email $ye, has age $a, has height $h, has city $c, has country $k, has employer $m;
+    $z isa person, has name $zn, has email $ze, has age $za, has height $h, has city $c, has country $k, has employer $m;
+    $w isa person, has name $wn, has age $za, has city $c, has country $k, has employer $m;
+";
+
+// five people related through shared attributes: 40 patterns, for measuring how extending a partial plan scales with
+// the length of the ordering it has decided upon
+const FORTY_PATTERN_QUERY: &str = "match
+    $x isa person, has name $xn, has email $xe, has age $a, has height $h, has city $c, has country $k, has employer $m;
+    $y isa person, has name $yn, has email $ye, has age $a, has height $h, has city $c, has country $k, has employer $m;
+    $z isa person, has name $zn, has email $ze, has age $za, has height $h, has city $c, has country $k, has employer $m;
+    $w isa person, has name $wn, has email $we, has age $za, has height $wh, has city $c, has country $k, has employer $m;
+    $v isa person, has name $vn, has email $ve, has age $za, has height $wh, has city $vc, has country $k, has employer $m;
+";
+
+// how much slower planning may be with a subscriber that disables TRACE events than with no subscriber at all
+const DISABLED_TRACING_SLOWDOWN_MAX: f64 = 1.25;
+const TIMING_RUNS: usize = 20;
+
+fn setup(storage: &Arc<MVCCStorage<WALClient>>) -> Statistics {
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let query_manager = QueryManager::new(None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
+    let mut snapshot = storage.clone().open_snapshot_schema();
+    let define = typeql::parse_query(SCHEMA).unwrap().into_structure().into_schema();
+    query_manager
+        .execute_schema(&mut snapshot, &type_manager, &thing_manager, &function_manager, define, SCHEMA)
+        .unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let snapshot = storage.clone().open_snapshot_write();
+    let query = typeql::parse_query(DATA).unwrap().into_structure().into_pipeline();
+    let pipeline = query_manager
+        .prepare_write_pipeline(snapshot, &type_manager, thing_manager.clone(), &function_manager, &query, DATA)
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+    }
+    drop(iterator);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let mut statistics = Statistics::new(SequenceNumber::new(0));
+    statistics.may_synchronise(storage).unwrap();
+    statistics
+}
+
+fn plan(
+    query: &str,
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    statistics: &Statistics,
+    config: &PlannerConfig,
+) {
+    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let block = translate_match(&mut translation_context, &mut value_parameters, &empty_function_index, &match_)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let entry_annotations = infer_types(
+        snapshot,
+        &block,
+        &translation_context.variable_registry,
+        type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &entry_annotations,
+        &translation_context.variable_registry,
+        &HashMap::new(),
+        &value_parameters,
+        statistics,
+        &ExecutableFunctionRegistry::empty(),
+        config,
+        None,
+    )
+    .unwrap();
+}
+
+fn fastest_planning_time(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    statistics: &Statistics,
+    config: &PlannerConfig,
+) -> Duration {
+    (0..TIMING_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            plan(THIRTY_CONSTRAINT_QUERY, snapshot, type_manager, statistics, config);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// The planner traces every extension it considers. With the TRACE level disabled by the subscriber, none of the
+// arguments of those events may be built, so planning must take as long as when no subscriber is installed.
+fn assert_disabled_tracing_is_free(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    statistics: &Statistics,
+    config: &PlannerConfig,
+) {
+    let untraced = fastest_planning_time(snapshot, type_manager, statistics, config);
+    let info = {
+        let _guard = initialise_logging_at(Level::INFO);
+        fastest_planning_time(snapshot, type_manager, statistics, config)
+    };
+    assert!(
+        info.as_secs_f64() <= untraced.as_secs_f64() * DISABLED_TRACING_SLOWDOWN_MAX,
+        "planning took {info:?} with tracing at INFO, but {untraced:?} with no subscriber"
+    );
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+    let statistics = setup(&storage);
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, _) = load_managers(storage.clone(), None);
+
+    let mut group = c.benchmark_group("plan_wide_conjunction");
+    let serial = PlannerConfig { parallel_extension_min_patterns: usize::MAX, ..PlannerConfig::default() };
+    group
+        .bench_function("serial_extension", |b| b.iter(|| plan(QUERY, &snapshot, &type_manager, &statistics, &serial)));
+    let parallel = PlannerConfig { parallel_extension_min_patterns: 0, ..PlannerConfig::default() };
+    group.bench_function("parallel_extension", |b| {
+        b.iter(|| plan(QUERY, &snapshot, &type_manager, &statistics, &parallel))
+    });
+    group.finish();
+
+    let config = PlannerConfig::default();
+    assert_disabled_tracing_is_free(&snapshot, &type_manager, &statistics, &config);
+    let mut group = c.benchmark_group("plan_thirty_constraints");
+    group.bench_function("no_subscriber", |b| {
+        b.iter(|| plan(THIRTY_CONSTRAINT_QUERY, &snapshot, &type_manager, &statistics, &config))
+    });
+    group.bench_function("info_subscriber", |b| {
+        let _guard = initialise_logging_at(Level::INFO);
+        b.iter(|| plan(THIRTY_CONSTRAINT_QUERY, &snapshot, &type_manager, &statistics, &config))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("plan_forty_patterns");
+    group.bench_function("default_config", |b| {
+        b.iter(|| plan(FORTY_PATTERN_QUERY, &snapshot, &type_manager, &statistics, &config))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
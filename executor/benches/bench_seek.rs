@@ -0,0 +1,126 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+#![deny(unused_must_use)]
+
+use std::sync::Arc;
+
+use concept::{thing::thing_manager::ThingManager, type_::type_manager::TypeManager};
+use criterion::{criterion_group, criterion_main, Criterion, SamplingMode};
+use encoding::graph::definition::definition_key_generator::DefinitionKeyGenerator;
+use executor::{pipeline::stage::ExecutionContext, ExecutionInterrupt};
+use function::function_manager::FunctionManager;
+use lending_iterator::LendingIterator;
+use query::query_manager::QueryManager;
+use resource::profile::CommitProfile;
+use storage::{durability_client::WALClient, snapshot::CommittableSnapshot, MVCCStorage};
+use test_utils_concept::{load_managers, setup_concept_storage};
+use test_utils_encoding::create_core_storage;
+
+const SCHEMA: &str = "define
+    attribute age value integer;
+    attribute name value string;
+    entity person owns age @card(0..), owns name @card(0..);
+";
+
+// every person owns two ages and two names: one million ownerships in total
+const PERSON_COUNT: usize = 250_000;
+const PERSONS_PER_INSERT: usize = 1_000;
+
+// intersects the ages and names of each person, and forms the 2x2 cartesian product at every intersection: each
+// product reopens the iterators at the current person and moves the intersection past it afterwards, which is
+// linear in the number of persons unless the iterators seek
+const QUERY: &str = "match $p isa person, has age $a, has name $n;";
+
+fn insert_query(first_person: usize) -> String {
+    let mut query = String::from("insert\n");
+    for person in first_person..first_person + PERSONS_PER_INSERT {
+        query.push_str(&format!(
+            "    $p{person} isa person, has age {}, has age {}, has name 'a{person}', has name 'b{person}';\n",
+            2 * person,
+            2 * person + 1
+        ));
+    }
+    query
+}
+
+fn setup(storage: &Arc<MVCCStorage<WALClient>>, query_manager: &QueryManager, function_manager: &FunctionManager) {
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_schema();
+    let define = typeql::parse_query(SCHEMA).unwrap().into_structure().into_schema();
+    query_manager
+        .execute_schema(&mut snapshot, &type_manager, &thing_manager, function_manager, define, SCHEMA)
+        .unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    for first_person in (0..PERSON_COUNT).step_by(PERSONS_PER_INSERT) {
+        let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+        let source = insert_query(first_person);
+        let query = typeql::parse_query(&source).unwrap().into_structure().into_pipeline();
+        let pipeline = query_manager
+            .prepare_write_pipeline(
+                storage.clone().open_snapshot_write(),
+                &type_manager,
+                thing_manager,
+                function_manager,
+                &query,
+                &source,
+            )
+            .unwrap();
+        let (mut iterator, ExecutionContext { snapshot, .. }) =
+            pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+        while let Some(row) = iterator.next() {
+            row.unwrap();
+        }
+        drop(iterator);
+        let snapshot = Arc::into_inner(snapshot).unwrap();
+        snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+    }
+}
+
+fn execute_query(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    type_manager: &TypeManager,
+    thing_manager: &Arc<ThingManager>,
+    query_manager: &QueryManager,
+    function_manager: &FunctionManager,
+) -> usize {
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let query = typeql::parse_query(QUERY).unwrap().into_structure().into_pipeline();
+    let pipeline = query_manager
+        .prepare_read_pipeline(snapshot, type_manager, thing_manager.clone(), function_manager, &query, QUERY)
+        .unwrap();
+    let (mut iterator, _) = pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    let mut count = 0;
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+        count += 1;
+    }
+    count
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+    let query_manager = QueryManager::new(None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
+    setup(&storage, &query_manager, &function_manager);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    assert_eq!(
+        execute_query(&storage, &type_manager, &thing_manager, &query_manager, &function_manager),
+        4 * PERSON_COUNT
+    );
+
+    let mut group = c.benchmark_group("cartesian_product_per_owner");
+    group.sampling_mode(SamplingMode::Flat).sample_size(10);
+    group.bench_function("million_ownerships", |b| {
+        b.iter(|| execute_query(&storage, &type_manager, &thing_manager, &query_manager, &function_manager))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
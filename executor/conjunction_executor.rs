@@ -4,13 +4,16 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use compiler::executable::{
-    function::ExecutableFunctionRegistry, match_::planner::conjunction_executable::ConjunctionExecutable,
+    function::ExecutableFunctionRegistry,
+    match_::planner::conjunction_executable::{ConjunctionExecutable, ParameterKind},
 };
-use concept::{error::ConceptReadError, thing::thing_manager::ThingManager};
-use lending_iterator::{adaptors::FlatMap, AsLendingIterator, LendingIterator};
+use concept::thing::thing_manager::ThingManager;
+use encoding::value::ValueEncodable;
+use ir::{pattern::ParameterID, pipeline::ParameterRegistry};
+use lending_iterator::{LendingIterator, Peekable};
 use resource::profile::QueryProfile;
 use storage::snapshot::ReadableSnapshot;
 
@@ -27,8 +30,12 @@ use crate::{
 
 pub struct ConjunctionExecutor {
     entry: PatternExecutor,
-    input: Option<MaybeOwnedRow<'static>>,
+    input: MaybeOwnedRow<'static>,
+    is_prepared: bool,
+    row_count_only: bool,
     tabled_functions: TabledFunctions,
+    parameter_kinds: HashMap<ParameterID, ParameterKind>,
+    estimated_output_rows: Option<f64>,
 }
 
 impl ConjunctionExecutor {
@@ -39,17 +46,64 @@ impl ConjunctionExecutor {
         input: MaybeOwnedRow<'_>,
         function_registry: Arc<ExecutableFunctionRegistry>,
         profile: &QueryProfile,
-    ) -> Result<Self, Box<ConceptReadError>> {
+    ) -> Result<Self, Box<ReadExecutionError>> {
+        let profile_id = conjunction_executable.executable_id();
+        Self::new_profiled_as(
+            profile_id,
+            conjunction_executable,
+            snapshot,
+            thing_manager,
+            input,
+            function_registry,
+            profile,
+        )
+    }
+
+    /// Records the steps under the stage profile of the given id, rather than of the executable's own id. Stages
+    /// sharing an executable are profiled separately this way.
+    pub fn new_profiled_as(
+        profile_id: u64,
+        conjunction_executable: &ConjunctionExecutable,
+        snapshot: &Arc<impl ReadableSnapshot + 'static>,
+        thing_manager: &Arc<ThingManager>,
+        input: MaybeOwnedRow<'_>,
+        function_registry: Arc<ExecutableFunctionRegistry>,
+        profile: &QueryProfile,
+    ) -> Result<Self, Box<ReadExecutionError>> {
+        for (function_id, &compiled_version) in conjunction_executable.function_versions() {
+            match function_registry.function_version(function_id) {
+                Some(registry_version) if registry_version == compiled_version => (),
+                Some(registry_version) => {
+                    return Err(Box::new(ReadExecutionError::FunctionVersionMismatch {
+                        function: function_id.to_string(),
+                        compiled_version,
+                        registry_version,
+                    }))
+                }
+                None => {
+                    return Err(Box::new(ReadExecutionError::FunctionNotInRegistry {
+                        function: function_id.to_string(),
+                    }))
+                }
+            }
+        }
+        let entry = create_pattern_executor_for_conjunction(
+            snapshot,
+            thing_manager,
+            &function_registry,
+            conjunction_executable,
+            profile,
+            profile_id,
+        )
+        .map_err(|typedb_source| Box::new(ReadExecutionError::ConceptRead { typedb_source }))?;
         Ok(Self {
-            entry: create_pattern_executor_for_conjunction(
-                snapshot,
-                thing_manager,
-                &function_registry,
-                conjunction_executable,
-                profile,
-            )?,
+            entry,
             tabled_functions: TabledFunctions::new(function_registry),
-            input: Some(input.into_owned()),
+            input: input.into_owned(),
+            is_prepared: false,
+            row_count_only: conjunction_executable.is_row_count_only(),
+            parameter_kinds: conjunction_executable.parameter_kinds().clone(),
+            estimated_output_rows: conjunction_executable.planner_statistics().estimated_output_rows(),
         })
     }
 
@@ -58,9 +112,70 @@ impl ConjunctionExecutor {
         context: ExecutionContext<Snapshot>,
         interrupt: ExecutionInterrupt,
     ) -> PatternIterator<Snapshot> {
-        PatternIterator::new(
-            AsLendingIterator::new(BatchIterator::new(self, context, interrupt)).flat_map(FixedBatchRowIterator::new),
-        )
+        PatternIterator::new(BatchIterator::new(self, context, interrupt))
+    }
+
+    /// Discards all per-execution state, so the executable can be run again from the original input without
+    /// recompiling. The next execution reads from the snapshot of the execution context it is given.
+    pub fn restart(&mut self) {
+        self.entry.restart();
+        self.tabled_functions.clear();
+        self.is_prepared = false;
+    }
+
+    /// Whether the executor holds no state read from the snapshot of a previous execution, as after a restart:
+    /// no open intersection iterators, prefetched cartesian tuples, cached instances or tabled function tables.
+    pub fn is_snapshot_independent(&self) -> bool {
+        !self.is_prepared && self.entry.is_snapshot_independent() && self.tabled_functions.is_empty()
+    }
+
+    /// Prepares the executor for its first batch, after checking that the parameters it is executed with are those
+    /// it was compiled against: before anything is read, rather than when a step first looks a parameter up.
+    fn may_prepare(&mut self, parameters: &ParameterRegistry) -> Result<(), Box<ReadExecutionError>> {
+        if !self.is_prepared {
+            self.validate_parameters(parameters)?;
+            self.entry.prepare(FixedBatch::from(self.input.as_reference()));
+            self.is_prepared = true;
+        }
+        Ok(())
+    }
+
+    fn validate_parameters(&self, parameters: &ParameterRegistry) -> Result<(), Box<ReadExecutionError>> {
+        let mut missing = Vec::new();
+        let mut mismatched = Vec::new();
+        for (&parameter, kind) in &self.parameter_kinds {
+            match kind {
+                ParameterKind::Iid => {
+                    if parameters.iid(parameter).is_none() {
+                        missing.push(parameter)
+                    }
+                }
+                ParameterKind::Value(value_type) => match parameters.value(parameter) {
+                    None => missing.push(parameter),
+                    Some(value) if &value.value_type() != value_type => mismatched.push(parameter),
+                    Some(_) => (),
+                },
+            }
+        }
+        if missing.is_empty() && mismatched.is_empty() {
+            Ok(())
+        } else {
+            missing.sort();
+            mismatched.sort();
+            Err(Box::new(ReadExecutionError::ParametersIncompatible { missing, mismatched }))
+        }
+    }
+
+    /// Hands the input to the first step ahead of pulling any rows, so the caller can overlap this work with other
+    /// request processing. Only a first step that is a sorted join does work here: it opens its storage iterators for
+    /// the first input row. Later input rows and later steps still open theirs when rows are pulled, and no prefetch
+    /// hints are passed to storage. Idempotent, and does not consume any rows.
+    pub fn prewarm(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), Box<ReadExecutionError>> {
+        self.may_prepare(context.parameters())?;
+        self.entry.prewarm(context).map_err(Box::new)
     }
 
     pub(super) fn compute_next_batch(
@@ -68,10 +183,12 @@ impl ConjunctionExecutor {
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         interrupt: &mut ExecutionInterrupt,
     ) -> Result<Option<FixedBatch>, Box<ReadExecutionError>> {
-        if let Some(input) = self.input.take() {
-            self.entry.prepare(FixedBatch::from(input.into_owned()));
+        self.may_prepare(context.parameters())?;
+        let batch = self.entry.compute_next_batch(context, interrupt, &mut self.tabled_functions).map_err(Box::new)?;
+        match self.row_count_only {
+            true => Ok(batch.map(FixedBatch::into_multiplicity_only)),
+            false => Ok(batch),
         }
-        self.entry.compute_next_batch(context, interrupt, &mut self.tabled_functions).map_err(|err| Box::new(err))
     }
 }
 
@@ -100,20 +217,44 @@ impl<Snapshot: ReadableSnapshot + 'static> Iterator for BatchIterator<Snapshot>
     }
 }
 
-// Wrappers around
-type PatternRowIterator<Snapshot> = FlatMap<
-    AsLendingIterator<BatchIterator<Snapshot>>,
-    FixedBatchRowIterator,
-    fn(Result<FixedBatch, Box<ReadExecutionError>>) -> FixedBatchRowIterator,
->;
-
 pub struct PatternIterator<Snapshot: ReadableSnapshot + 'static> {
-    iterator: PatternRowIterator<Snapshot>,
+    batches: BatchIterator<Snapshot>,
+    rows: Option<Peekable<FixedBatchRowIterator>>,
+    produced_rows: u64,
+    progress: f32,
 }
 
 impl<Snapshot: ReadableSnapshot> PatternIterator<Snapshot> {
-    fn new(iterator: PatternRowIterator<Snapshot>) -> Self {
-        Self { iterator }
+    // the estimate may be exceeded, so only the end of the answers completes the progress
+    const MAX_UNFINISHED_PROGRESS: f32 = 0.99;
+
+    fn new(batches: BatchIterator<Snapshot>) -> Self {
+        Self { batches, rows: None, produced_rows: 0, progress: 0.0 }
+    }
+
+    /// Re-runs the same executable from the beginning against `snapshot`, for example to retry after a conflict.
+    pub fn restart_with_snapshot(&mut self, snapshot: Arc<Snapshot>) {
+        self.batches.executor.restart();
+        debug_assert!(self.batches.executor.is_snapshot_independent());
+        self.batches.context.snapshot = snapshot;
+        self.rows = None;
+        self.produced_rows = 0;
+        self.progress = 0.0;
+    }
+
+    /// A rough estimate of the fraction of the answers produced so far, from the rows the plan expects to produce.
+    /// Never decreases, and is `None` when the plan was made against estimated statistics.
+    pub fn progress(&self) -> Option<f32> {
+        self.batches.executor.estimated_output_rows.map(|_| self.progress)
+    }
+
+    fn record_produced(&mut self, batch: &FixedBatch) {
+        // a row may stand for many answers, as in a row-count-only conjunction
+        self.produced_rows += (0..batch.len()).map(|index| batch.multiplicity(index)).sum::<u64>();
+        if let Some(estimated_rows) = self.batches.executor.estimated_output_rows {
+            let fraction = (self.produced_rows as f64 / estimated_rows) as f32;
+            self.progress = f32::max(self.progress, f32::min(fraction, Self::MAX_UNFINISHED_PROGRESS));
+        }
     }
 }
 
@@ -121,6 +262,16 @@ impl<Snapshot: ReadableSnapshot + 'static> LendingIterator for PatternIterator<S
     type Item<'a> = Result<MaybeOwnedRow<'a>, &'a ReadExecutionError>;
 
     fn next(&mut self) -> Option<Self::Item<'_>> {
-        self.iterator.next()
+        while !self.rows.as_mut().is_some_and(|rows| rows.peek().is_some()) {
+            let Some(batch) = self.batches.next() else {
+                self.progress = 1.0;
+                return None;
+            };
+            if let Ok(batch) = &batch {
+                self.record_produced(batch);
+            }
+            self.rows = Some(Peekable::new(FixedBatchRowIterator::new(batch)));
+        }
+        self.rows.as_mut().unwrap().next()
     }
 }
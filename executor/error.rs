@@ -7,6 +7,7 @@
 use compiler::annotation::expression::instructions::ExpressionEvaluationError;
 use concept::error::ConceptReadError;
 use error::typedb_error;
+use ir::pattern::ParameterID;
 
 use crate::InterruptType;
 
@@ -17,5 +18,21 @@ typedb_error! {
         CreatingIterator(3, "Error creating iterator from {instruction_name} instruction.", instruction_name: String, typedb_source: Box<ConceptReadError>),
         AdvancingIteratorTo(4, "Error moving iterator (by steps or seek) to target value.", typedb_source: Box<ConceptReadError>),
         ExpressionEvaluate(5, "Error evaluating expression.", typedb_source: ExpressionEvaluationError),
+        CartesianGroupTooLarge(6, "Intersection step '{step}' would produce at least {estimated} answers for the intersection value '{intersection_value}', exceeding the limit of {limit} answers per intersection value.", step: String, intersection_value: String, estimated: u64, limit: u64),
+        FunctionVersionMismatch(7, "The executable was compiled against version {compiled_version} of function '{function}', but the function registry holds version {registry_version}. The query must be recompiled.", function: String, compiled_version: u64, registry_version: u64),
+        FunctionNotInRegistry(8, "The executable calls function '{function}', which is not in the function registry. The query must be recompiled.", function: String),
+        ParametersIncompatible(9, "The executable references parameters that the parameter registry does not hold as they were compiled: missing {missing:?}, of a different kind {mismatched:?}. The query must be recompiled.", missing: Vec<ParameterID>, mismatched: Vec<ParameterID>),
+    }
+}
+
+impl ReadExecutionError {
+    /// The storage read error that caused this error, if any.
+    pub(crate) fn concept_read_error(&self) -> Option<&ConceptReadError> {
+        match self {
+            Self::ConceptRead { typedb_source }
+            | Self::CreatingIterator { typedb_source, .. }
+            | Self::AdvancingIteratorTo { typedb_source } => Some(typedb_source),
+            _ => None,
+        }
     }
 }
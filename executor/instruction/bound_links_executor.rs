@@ -0,0 +1,102 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{collections::HashMap, fmt};
+
+use answer::{variable_value::VariableValue, Type};
+use compiler::{
+    executable::match_::instructions::thing::{BoundLinksHint, BoundLinksInstruction},
+    ExecutorVariable, VariablePosition,
+};
+use concept::{error::ConceptReadError, thing::thing_manager::ThingManager};
+use resource::profile::StorageCounters;
+use storage::snapshot::ReadableSnapshot;
+
+use crate::{
+    instruction::{
+        iterator::TupleIterator, links_executor::LinksExecutor, links_reverse_executor::LinksReverseExecutor,
+        VariableModes,
+    },
+    pipeline::stage::ExecutionContext,
+    row::MaybeOwnedRow,
+};
+
+pub(crate) struct BoundLinksExecutor {
+    relation: VariablePosition,
+    player: VariablePosition,
+    links: LinksExecutor,
+    links_reverse: LinksReverseExecutor,
+    hint: BoundLinksHint,
+}
+
+impl fmt::Debug for BoundLinksExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BoundLinksExecutor")
+    }
+}
+
+impl BoundLinksExecutor {
+    pub(crate) fn new(
+        bound_links: BoundLinksInstruction<ExecutorVariable>,
+        variable_modes: VariableModes,
+        sort_by: ExecutorVariable,
+        snapshot: &impl ReadableSnapshot,
+        thing_manager: &ThingManager,
+    ) -> Result<Self, Box<ConceptReadError>> {
+        let BoundLinksInstruction { canonical, reverse, hint } = bound_links;
+        let relation = canonical.links.relation().as_variable().unwrap().as_position().unwrap();
+        let player = canonical.links.player().as_variable().unwrap().as_position().unwrap();
+        let links = LinksExecutor::new(canonical, variable_modes.clone(), sort_by, snapshot, thing_manager)?;
+        let links_reverse = LinksReverseExecutor::new(reverse, variable_modes, sort_by, snapshot, thing_manager)?;
+        Ok(Self { relation, player, links, links_reverse, hint })
+    }
+
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.links.clear_instance_cache();
+        self.links_reverse.clear_instance_cache();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.links.has_instance_cache() || self.links_reverse.has_instance_cache()
+    }
+
+    pub(crate) fn get_iterator(
+        &self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        row: MaybeOwnedRow<'_>,
+        storage_counters: StorageCounters,
+    ) -> Result<TupleIterator, Box<ConceptReadError>> {
+        if self.probe_relation_side(&row) {
+            self.links.get_iterator(context, row, storage_counters)
+        } else {
+            self.links_reverse.get_iterator(context, row, storage_counters)
+        }
+    }
+
+    fn probe_relation_side(&self, row: &MaybeOwnedRow<'_>) -> bool {
+        let relation_fan_out = fan_out_of(&self.hint.relation_fan_out, row.get(self.relation));
+        let player_fan_out = fan_out_of(&self.hint.player_fan_out, row.get(self.player));
+        match (relation_fan_out, player_fan_out) {
+            (Some(relation), Some(player)) if relation == player => self.hint.prefer_relation_side,
+            (Some(relation), Some(player)) => relation < player,
+            // without an estimate for one side, probe the side we can reason about
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => self.hint.prefer_relation_side,
+        }
+    }
+}
+
+fn fan_out_of(fan_outs: &HashMap<Type, f64>, value: &VariableValue<'_>) -> Option<f64> {
+    fan_outs.get(&value.get_thing()?.type_()).copied()
+}
+
+impl fmt::Display for BoundLinksExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = if self.hint.prefer_relation_side { "relation" } else { "player" };
+        write!(f, "bound ({side}-side preferred): {}", &self.links)
+    }
+}
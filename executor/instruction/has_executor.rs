@@ -10,7 +10,7 @@ use std::{
     fmt,
     iter::Iterator,
     ops::Bound,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use answer::{variable_value::VariableValue, Thing, Type};
@@ -57,7 +57,7 @@ pub(crate) struct HasExecutor {
     attribute_type_range: Bounds<AttributeType>,
     ordered_value_type_categories: Vec<ValueTypeCategory>,
     filter_fn: Arc<HasFilterFn>,
-    owner_cache: Option<Vec<Object>>,
+    owner_cache: OnceLock<Vec<Object>>,
     checker: Checker<(Has, u64)>,
 }
 
@@ -121,25 +121,6 @@ impl HasExecutor {
             Bound::Included(owner_attribute_types.last_key_value().unwrap().0.as_object_type()),
         );
 
-        let owner_cache = if iterate_mode == BinaryIterateMode::UnboundInverted {
-            let mut cache = Vec::new();
-            for type_ in owner_attribute_types.keys() {
-                let instances: Vec<_> = Itertools::try_collect(thing_manager.get_objects_in(
-                    snapshot,
-                    type_.as_object_type(),
-                    StorageCounters::DISABLED,
-                ))?;
-                cache.extend(instances);
-            }
-            #[cfg(debug_assertions)]
-            if cache.len() < CONSTANT_CONCEPT_LIMIT {
-                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
-            }
-            Some(cache)
-        } else {
-            None
-        };
-
         let possible_attribute_value_categories = thing_manager
             .type_manager()
             .get_attribute_types(snapshot)?
@@ -163,17 +144,42 @@ impl HasExecutor {
             attribute_type_range,
             ordered_value_type_categories: possible_attribute_value_categories,
             filter_fn,
-            owner_cache,
+            owner_cache: OnceLock::new(),
             checker,
         })
     }
 
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.owner_cache.take();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.owner_cache.is_some()
+    }
+
     pub(crate) fn get_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         row: MaybeOwnedRow<'_>,
         storage_counters: StorageCounters,
     ) -> Result<TupleIterator, Box<ConceptReadError>> {
+        if self.iterate_mode == BinaryIterateMode::UnboundInverted && self.owner_cache.get().is_none() {
+            let mut cache = Vec::new();
+            for type_ in self.owner_attribute_types.keys() {
+                let instances: Vec<_> = Itertools::try_collect(context.thing_manager.get_objects_in(
+                    context.snapshot.as_ref(),
+                    type_.as_object_type(),
+                    storage_counters.clone(),
+                ))?;
+                cache.extend(instances);
+            }
+            #[cfg(debug_assertions)]
+            if cache.len() < CONSTANT_CONCEPT_LIMIT {
+                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
+            }
+            self.owner_cache.get_or_init(|| cache);
+        }
+
         let filter = self.filter_fn.clone();
         let check = self.checker.filter_fn_for_row(context, &row, storage_counters.clone());
         let filter_for_row: Arc<HasFilterMapFn> = Arc::new(move |item| match filter(&item) {
@@ -225,8 +231,8 @@ impl HasExecutor {
                 )))
             }
             BinaryIterateMode::UnboundInverted => {
-                debug_assert!(self.owner_cache.is_some());
-                if let Some([owner]) = self.owner_cache.as_deref() {
+                debug_assert!(self.owner_cache.get().is_some());
+                if let Some([owner]) = self.owner_cache.get().map(Vec::as_slice) {
                     // no heap allocs needed if there is only 1 iterator
                     let iterator = owner.get_has_types_range_unordered_in_value_types(
                         snapshot,
@@ -252,7 +258,7 @@ impl HasExecutor {
                 } else {
                     // TODO: we could create a reusable space for these temporarily held iterators
                     //       so we don't have allocate again before the merging iterator
-                    let owners = self.owner_cache.as_ref().unwrap().iter();
+                    let owners = self.owner_cache.get().unwrap().iter();
                     let mut iterators = Vec::new();
                     for owner in owners {
                         let iterator = owner.get_has_types_range_unordered_in_value_types(
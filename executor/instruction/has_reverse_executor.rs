@@ -127,6 +127,14 @@ impl HasReverseExecutor {
         })
     }
 
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.attribute_cache.take();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.attribute_cache.is_some()
+    }
+
     pub(crate) fn get_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
@@ -9,7 +9,7 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
     ops::Bound,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use answer::{variable_value::VariableValue, Thing, Type};
@@ -94,7 +94,7 @@ pub(crate) struct IndexedRelationExecutor {
     pub(crate) role_end_types: Arc<BTreeSet<RoleType>>,
 
     filter_fn: Arc<IndexedRelationFilterFn>,
-    start_player_cache: Option<Vec<Object>>,
+    start_player_cache: OnceLock<Vec<Object>>,
 
     checker: Checker<(IndexedRelationPlayers, u64)>,
 }
@@ -110,8 +110,8 @@ impl IndexedRelationExecutor {
         indexed_relation: IndexedRelationInstruction<ExecutorVariable>,
         variable_modes: VariableModes,
         sort_by: ExecutorVariable,
-        snapshot: &impl ReadableSnapshot,
-        thing_manager: &ThingManager,
+        _snapshot: &impl ReadableSnapshot,
+        _thing_manager: &ThingManager,
     ) -> Result<Self, Box<ConceptReadError>> {
         debug_assert!(!variable_modes.all_inputs());
 
@@ -191,25 +191,6 @@ impl IndexedRelationExecutor {
             ]),
         );
 
-        let start_player_cache = if iterate_mode == IndexedRelationIterateMode::UnboundInvertedToPlayer {
-            let mut cache = Vec::new();
-            for type_ in player_start_to_player_end_types.keys() {
-                let instances: Vec<Object> = Itertools::try_collect(thing_manager.get_objects_in(
-                    snapshot,
-                    type_.as_object_type(),
-                    StorageCounters::DISABLED,
-                ))?;
-                cache.extend(instances);
-            }
-            #[cfg(debug_assertions)]
-            if cache.len() < CONSTANT_CONCEPT_LIMIT {
-                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
-            }
-            Some(cache)
-        } else {
-            None
-        };
-
         Ok(Self {
             player_start,
             player_end,
@@ -228,17 +209,44 @@ impl IndexedRelationExecutor {
             role_end_types,
             filter_fn,
 
-            start_player_cache,
+            start_player_cache: OnceLock::new(),
             checker,
         })
     }
 
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.start_player_cache.take();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.start_player_cache.is_some()
+    }
+
     pub(crate) fn get_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         row: MaybeOwnedRow<'_>,
         storage_counters: StorageCounters,
     ) -> Result<TupleIterator, Box<ConceptReadError>> {
+        if self.iterate_mode == IndexedRelationIterateMode::UnboundInvertedToPlayer
+            && self.start_player_cache.get().is_none()
+        {
+            let mut cache = Vec::new();
+            for type_ in self.player_start_to_player_end_types.keys() {
+                let instances: Vec<Object> = Itertools::try_collect(context.thing_manager.get_objects_in(
+                    context.snapshot.as_ref(),
+                    type_.as_object_type(),
+                    storage_counters.clone(),
+                ))?;
+                cache.extend(instances);
+            }
+            #[cfg(debug_assertions)]
+            if cache.len() < CONSTANT_CONCEPT_LIMIT {
+                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
+            }
+            self.start_player_cache.get_or_init(|| cache);
+        }
+
         let filter = self.filter_fn.clone();
         let check = self.checker.filter_fn_for_row(context, &row, storage_counters.clone());
 
@@ -320,9 +328,9 @@ impl IndexedRelationExecutor {
                 }
             }
             IndexedRelationIterateMode::UnboundInvertedToPlayer => {
-                debug_assert!(self.start_player_cache.is_some());
+                debug_assert!(self.start_player_cache.get().is_some());
                 let mut iterators = Vec::new();
-                self.start_player_cache.as_ref().into_iter().flat_map(|start_players| start_players.iter()).for_each(
+                self.start_player_cache.get().into_iter().flat_map(|start_players| start_players.iter()).for_each(
                     |start_player| {
                         for relation_type in self.relation_to_player_start_types.keys() {
                             let iterator = start_player
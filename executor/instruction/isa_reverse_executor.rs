@@ -4,7 +4,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{cmp::Ordering, collections::BTreeMap, fmt, iter, ops::Bound, sync::Arc, vec};
+use std::{collections::BTreeMap, fmt, iter, ops::Bound, sync::Arc, vec};
 
 use answer::{variable_value::VariableValue, Thing, Type};
 use compiler::{executable::match_::instructions::thing::IsaReverseInstruction, ExecutorVariable};
@@ -28,7 +28,9 @@ use crate::{
     instruction::{
         isa_executor::{IsaFilterMapFn, EXTRACT_THING, EXTRACT_TYPE},
         iterator::{SortedTupleIterator, TupleIterator, TupleSeekable},
-        tuple::{isa_to_tuple_thing_type, isa_to_tuple_type_thing, Tuple, TuplePositions, TupleResult},
+        tuple::{
+            canonical_type_cmp, isa_to_tuple_thing_type, isa_to_tuple_type_thing, Tuple, TuplePositions, TupleResult,
+        },
         type_from_row_or_annotations, BinaryIterateMode, Checker, VariableModes,
     },
     pipeline::stage::ExecutionContext,
@@ -236,9 +238,10 @@ impl LendingIterator for IsaReverseBoundedSortedThing {
 
 impl TupleSeekable for IsaReverseBoundedSortedThing {
     fn seek(&mut self, target: &Tuple<'_>) -> Result<(), Box<ConceptReadError>> {
-        let target_type = target.values().get(0);
-        let target_thing = target.values().get(1);
-        self.inner.seek(target_type, target_thing)?;
+        // tuples are laid out as (thing, type), but the type is fixed by the input
+        let target_type = VariableValue::Type(self.type_);
+        let target_thing = target.values().get(0);
+        self.inner.seek(Some(&target_type), target_thing)?;
         Ok(())
     }
 }
@@ -280,6 +283,7 @@ impl TupleSeekable for IsaReverseUnboundedSortedType {
 pub(super) struct MultipleTypeIsaReverseIterator {
     object_iters: Vec<IsaReverseObjectIterator>,
     attribute_iters: Vec<IsaReverseAttributeIterator>,
+    pending: Option<Result<(Thing, Type), Box<ConceptReadError>>>,
 }
 
 impl MultipleTypeIsaReverseIterator {
@@ -287,9 +291,20 @@ impl MultipleTypeIsaReverseIterator {
         mut objects: Vec<IsaReverseObjectIterator>,
         mut attributes: Vec<IsaReverseAttributeIterator>,
     ) -> Self {
+        // iterators are consumed from the back, and must be visited in the canonical type order
+        debug_assert!(objects
+            .iter()
+            .map(|iter| iter.type_)
+            .tuple_windows()
+            .all(|(a, b)| canonical_type_cmp(&a, &b).is_le()));
+        debug_assert!(attributes
+            .iter()
+            .map(|iter| iter.iterator_type)
+            .tuple_windows()
+            .all(|(a, b)| canonical_type_cmp(&a, &b).is_le()));
         objects.reverse();
         attributes.reverse();
-        Self { object_iters: objects, attribute_iters: attributes }
+        Self { object_iters: objects, attribute_iters: attributes, pending: None }
     }
 
     fn seek(
@@ -297,40 +312,53 @@ impl MultipleTypeIsaReverseIterator {
         target_type: Option<&VariableValue<'_>>,
         target_thing: Option<&VariableValue<'_>>,
     ) -> Result<(), Box<ConceptReadError>> {
-        // TODO!!!!
-        todo!()
-        //
-        // let Some(target_type) = target_type else { return Ok(Some(Ordering::Greater)) };
-        // let &VariableValue::Type(target_type) = target_type else {
-        //     unreachable!("seeking to type {:?} which is not a `Type`", target_type)
-        // };
-        // match target_type {
-        //     Type::Entity(_) | Type::Relation(_) => {
-        //         while let Some(object_iter) = self.object_iters.last_mut() {
-        //             if object_iter.type_ < target_type {
-        //                 self.object_iters.pop();
-        //             } else {
-        //                 return object_iter.seek(target_thing);
-        //             }
-        //         }
-        //         if self.attribute_iters.is_empty() {
-        //             Ok(None)
-        //         } else {
-        //             Ok(Some(Ordering::Greater))
-        //         }
-        //     }
-        //     Type::Attribute(_) => {
-        //         while let Some(attribute_iter) = self.attribute_iters.last_mut() {
-        //             if attribute_iter.iterator_type < target_type {
-        //                 self.attribute_iters.pop();
-        //             } else {
-        //                 return attribute_iter.seek(target_thing);
-        //             }
-        //         }
-        //         Ok(None)
-        //     }
-        //     Type::RoleType(_) => unreachable!("encountered role type during isa reverse seek"),
-        // }
+        let Some(&VariableValue::Type(target_type)) = target_type else { return Ok(()) };
+        let target_thing = match target_thing {
+            Some(VariableValue::Thing(thing)) => Some(thing),
+            _ => None,
+        };
+
+        if let Some(Ok((_, type_))) = &self.pending {
+            if canonical_type_cmp(type_, &target_type).is_lt() {
+                self.pending = None;
+            }
+        }
+        if self.pending.is_none() {
+            // skip whole iterators that precede the target type, objects sorting before attributes
+            while self.object_iters.last().is_some_and(|iter| canonical_type_cmp(&iter.type_, &target_type).is_lt()) {
+                self.object_iters.pop();
+            }
+            if self.object_iters.is_empty() {
+                while self
+                    .attribute_iters
+                    .last()
+                    .is_some_and(|iter| canonical_type_cmp(&iter.iterator_type, &target_type).is_lt())
+                {
+                    self.attribute_iters.pop();
+                }
+            }
+        }
+
+        let Some(target_thing) = target_thing else { return Ok(()) };
+        loop {
+            let next = match self.pending.take() {
+                Some(pending) => pending,
+                None => match self.next() {
+                    Some(next) => next,
+                    None => return Ok(()),
+                },
+            };
+            match &next {
+                Ok((thing, type_)) => {
+                    let ordering = canonical_type_cmp(type_, &target_type).then_with(|| thing.cmp(target_thing));
+                    if ordering.is_ge() {
+                        self.pending = Some(next);
+                        return Ok(());
+                    }
+                }
+                Err(err) => return Err(err.clone()),
+            }
+        }
     }
 }
 
@@ -338,6 +366,9 @@ impl Iterator for MultipleTypeIsaReverseIterator {
     type Item = Result<(Thing, Type), Box<ConceptReadError>>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
         while let Some(object_iter) = self.object_iters.last_mut() {
             if let Some(item) = object_iter.next() {
                 return Some(item);
@@ -365,22 +396,6 @@ impl IsaReverseObjectIterator {
     fn new(objects: InstanceIterator<Object>, iterator_type: Type) -> Self {
         Self { objects, type_: iterator_type }
     }
-
-    fn seek(&mut self, target_thing: Option<&VariableValue<'_>>) -> Result<(), Box<ConceptReadError>> {
-        // let Some(target_thing) = target_thing else { return Ok(Some(Ordering::Greater)) };
-        // let VariableValue::Thing(target_thing) = target_thing else {
-        //     unreachable!("seeking to thing {:?} which is not a `Thing`", target_thing)
-        // };
-        // match self.objects.seek(&target_thing.as_object())? {
-        //     None => return Ok(None),
-        //     Some(Ordering::Greater) => return Ok(Some(Ordering::Greater)),
-        //     Some(Ordering::Equal) => (),
-        //     Some(Ordering::Less) => unreachable!(),
-        // }
-        //
-        // Ok(Some(Ordering::Equal))
-        todo!()
-    }
 }
 
 impl Iterator for IsaReverseObjectIterator {
@@ -404,26 +419,6 @@ impl IsaReverseAttributeIterator {
     fn new(attributes: AttributeIterator<InstanceIterator<Attribute>>, iterator_type: Type) -> Self {
         Self { attributes, iterator_type }
     }
-
-    fn seek(&mut self, target_thing: Option<&VariableValue<'_>>) -> Result<Option<Ordering>, Box<ConceptReadError>> {
-        let Some(target_thing) = target_thing else { return Ok(Some(Ordering::Greater)) };
-        let VariableValue::Thing(target_thing) = target_thing else {
-            unreachable!("seeking to thing {:?} which is not a `Thing`", target_thing)
-        };
-        self.attributes.seek(&target_thing.as_attribute());
-        let peek = self.attributes.peek().transpose()?;
-        let peek = match peek {
-            None => return Ok(None),
-            Some(peek) => peek,
-        };
-        match peek.cmp(target_thing.as_attribute()) {
-            Ordering::Greater => return Ok(Some(Ordering::Greater)),
-            Ordering::Equal => (),
-            Ordering::Less => unreachable!(),
-        }
-
-        Ok(Some(Ordering::Equal))
-    }
 }
 
 impl Iterator for IsaReverseAttributeIterator {
@@ -38,7 +38,7 @@ use crate::{
         relates_reverse_executor::{RelatesReverseBoundedSortedRelation, RelatesReverseUnboundedSortedRole},
         sub_executor::{SubBoundedSortedSuper, SubUnboundedSortedSub},
         sub_reverse_executor::{SubReverseBoundedSortedSub, SubReverseUnboundedSortedSuper},
-        tuple::{Tuple, TupleIndex, TupleOrderingFn, TuplePositions, TupleResult},
+        tuple::{self, Tuple, TupleIndex, TupleOrderingFn, TuplePositions, TupleResult},
         type_list_executor::TypeIterator,
     },
     row::Row,
@@ -69,12 +69,9 @@ impl<I: for<'a> LendingIterator<Item<'a> = TupleResult<'static>>> TupleSeekable
     fn seek(&mut self, target: &Tuple<'_>) -> Result<(), Box<ConceptReadError>> {
         loop {
             match self.iter.peek() {
-                Some(Ok(tuple)) => match tuple.partial_cmp(&target) {
-                    Some(Ordering::Less) => (),
-                    Some(_ordering) => return Ok(()),
-                    None => {
-                        unreachable!("seeking toward incomparable tuple")
-                    }
+                Some(Ok(tuple)) => match tuple.total_cmp(target) {
+                    Ordering::Less => (),
+                    _ordering => return Ok(()),
                 },
                 Some(Err(err)) => return Err(err.clone()),
                 None => return Ok(()),
@@ -89,22 +86,15 @@ impl<I: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekable
         // TODO: this is close to a copy-paste of the Seek() implementation for Peekable<I> where I is seekable
         if self.item.is_some() {
             let item = self.item.as_ref().unwrap().as_ref().map_err(|err| err.clone())?;
-            let ordering = match PartialOrd::partial_cmp(item, &target) {
-                None => Err(Box::new(ConceptReadError::InternalIncomparableTypes {}))?,
-                Some(ordering) => ordering,
-            };
-            match ordering {
+            match item.total_cmp(target) {
                 Ordering::Less => {
                     // fallthrough to seek operation
                     ()
                 }
-                Ordering::Equal => {
-                    // do nothing
+                Ordering::Equal | Ordering::Greater => {
+                    // already at or past the target: seeking never moves an iterator backwards
                     return Ok(());
                 }
-                Ordering::Greater => {
-                    unreachable!("Key behind the stored item in a Peekable iterator")
-                }
             }
         }
         self.item = None;
@@ -176,6 +166,9 @@ macro_rules! dispatch_tuple_iterator {
 }
 
 dispatch_tuple_iterator! {
+/// Every variant yields tuples in ascending order. Any tuple position holding a type is ordered by
+/// `canonical_type_cmp`, whichever instruction produced it, so that a type variable may be used as the sort
+/// variable of an intersection mixing type-level instructions (e.g. `Sub`) with thing-level ones (e.g. `IsaReverse`).
 #[allow(clippy::large_enum_variant)]
 pub(crate) enum TupleIterator {
     Is(SortedTupleIterator<IsIterator>),
@@ -236,19 +229,43 @@ impl {
         &mut self,
         value: &VariableValue<'_>,
     ) -> Result<Option<Ordering>, Box<ConceptReadError>>;
+    fn skip_until_join_key(
+        &mut self,
+        key: &[VariableValue<'_>],
+    ) -> Result<Option<Ordering>, Box<ConceptReadError>>;
     pub(crate) fn advance_single(&mut self) -> Result<(), Box<ConceptReadError>>;
     pub(crate) fn peek_first_unbound_value(&mut self) -> Option<Result<&VariableValue<'_>, Box<ConceptReadError>>>;
+    pub(crate) fn peek_join_key(&mut self, width: usize) -> Option<Result<&[VariableValue<'_>], Box<ConceptReadError>>>;
     pub(crate) fn first_unbound_index(&self) -> TupleIndex ;
+    pub(crate) fn positions(&self) -> &TuplePositions;
 }
 }
 
 impl TupleIterator {
-    pub(crate) fn advance_until_first_unbound_is(
+    /// Moves the iterator forward until the value at the first unbound position is at least `value`, and returns how
+    /// the value it stopped at compares to `value`, or `None` if the iterator is exhausted.
+    ///
+    /// Seeks the underlying storage iterator where it supports seeking, and falls back to advancing linearly
+    /// otherwise. The iterator never moves backwards: if it is already at or past `value`, it stays put and reports
+    /// `Ordering::Equal` or `Ordering::Greater`. `Ordering::Less` is never returned.
+    pub(crate) fn seek_first_unbound(
         &mut self,
         value: &VariableValue<'_>,
     ) -> Result<Option<Ordering>, Box<ConceptReadError>> {
         self.skip_until_first_unbound_value(value)
     }
+
+    /// Moves the iterator forward until the values starting at the first unbound position are at least `key`,
+    /// comparing as many values as the key is wide. Follows the same contract as `seek_first_unbound`.
+    pub(crate) fn seek_join_key(
+        &mut self,
+        key: &[VariableValue<'_>],
+    ) -> Result<Option<Ordering>, Box<ConceptReadError>> {
+        match key {
+            [value] => self.seek_first_unbound(value),
+            _ => self.skip_until_join_key(key),
+        }
+    }
 }
 
 impl Display for TupleIterator {
@@ -357,6 +374,15 @@ impl<It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekabl
                             "{prev:?} <= {tuple:?}: sortedness check fail in {}",
                             std::any::type_name::<Self>()
                         );
+                        if let (VariableValue::Type(prev_type), VariableValue::Type(type_)) =
+                            (&prev.values()[0], &tuple.values()[0])
+                        {
+                            debug_assert!(
+                                tuple::canonical_type_cmp(prev_type, type_).is_le(),
+                                "{prev_type:?} <= {type_:?}: canonical type order violated in {}",
+                                std::any::type_name::<Self>()
+                            );
+                        }
                     }
                     prev = Some(tuple.clone().into_owned());
                 }
@@ -439,56 +465,46 @@ impl<It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekabl
         &mut self,
         target: &VariableValue<'_>,
     ) -> Result<Option<Ordering>, Box<ConceptReadError>> {
-        // TODO: this should use seek if index == self.first_unbound()
-        // let index = self.first_unbound_index();
-        // loop {
-        //     match self.peek() {
-        //         None => return Ok(None),
-        //         Some(Ok(tuple)) => {
-        //             let value = &tuple.values()[index as usize];
-        //             match value.partial_cmp(target).unwrap() {
-        //                 Ordering::Less => self.advance_single()?,
-        //                 Ordering::Equal => return Ok(Some(Ordering::Equal)),
-        //                 Ordering::Greater => return Ok(Some(Ordering::Greater)),
-        //             }
-        //         }
-        //         Some(Err(err)) => return Err(err.clone()),
-        //     }
-        // }
-        self.seek_to_first_unbound_value(target)
-    }
-
-    fn seek_to_first_unbound_value(
-        &mut self,
-        target: &VariableValue<'_>,
-    ) -> Result<Option<Ordering>, Box<ConceptReadError>> {
-        // create target tuple using [0..index]
+        self.seek_to_join_key(std::slice::from_ref(target))
+    }
+
+    fn skip_until_join_key(&mut self, key: &[VariableValue<'_>]) -> Result<Option<Ordering>, Box<ConceptReadError>> {
+        self.seek_to_join_key(key)
+    }
+
+    fn seek_to_join_key(&mut self, key: &[VariableValue<'_>]) -> Result<Option<Ordering>, Box<ConceptReadError>> {
         let first_unbound_index = self.first_unbound_index() as usize;
+        let key_range = first_unbound_index..first_unbound_index + key.len();
+        debug_assert!(key_range.end <= self.tuple_length);
         let current = match self.peek() {
             None => return Ok(None),
             Some(Err(err)) => return Err(err.clone()),
             Some(Ok(peek)) => peek,
         };
+        match tuple::total_cmp_values(&current.values()[key_range.clone()], key) {
+            Ordering::Less => (),
+            // already at or past the key, e.g. after an interleaved `advance_single`: stay put
+            ordering => return Ok(Some(ordering)),
+        }
+
+        // create target tuple using [0..index]
         let mut target_tuple = current.clone().into_owned();
-        target_tuple.values_mut()[first_unbound_index] = target.clone();
+        target_tuple.values_mut()[key_range.clone()].clone_from_slice(key);
         // zero out the rest of the values
-        for i in (first_unbound_index + 1)..target_tuple.values().len() {
+        for i in key_range.end..target_tuple.values().len() {
             target_tuple.values_mut()[i] = VariableValue::None;
         }
-        if target_tuple > *current {
-            self.iterator.seek(&target_tuple)?;
+        self.iterator.seek(&target_tuple)?;
+        // a seek may fall short of the target (e.g. if the storage moved underneath us), so finish the job linearly
+        loop {
             match self.iterator.peek() {
-                None => Ok(None),
-                Some(Ok(peek)) => {
-                    match peek.values()[first_unbound_index].partial_cmp(&target_tuple.values()[first_unbound_index]) {
-                        None => return Err(Box::new(ConceptReadError::InternalIncomparableTypes {})),
-                        Some(ordering) => return Ok(Some(ordering)),
-                    }
-                }
+                None => return Ok(None),
+                Some(Ok(peek)) => match tuple::total_cmp_values(&peek.values()[key_range.clone()], key) {
+                    Ordering::Less => self.advance_single()?,
+                    ordering => return Ok(Some(ordering)),
+                },
                 Some(Err(err)) => return Err(err.clone()),
             }
-        } else {
-            Ok(Some(Ordering::Greater))
         }
     }
 
@@ -496,6 +512,11 @@ impl<It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekabl
         self.peek_current_value_at(self.first_unbound)
     }
 
+    fn peek_join_key(&mut self, width: usize) -> Option<Result<&[VariableValue<'_>], Box<ConceptReadError>>> {
+        let key_range = self.first_unbound as usize..self.first_unbound as usize + width;
+        self.peek().map(|result| result.as_ref().map(|tuple| &tuple.values()[key_range]).map_err(|err| err.clone()))
+    }
+
     fn peek_current_value_at(
         &mut self,
         index: TupleIndex,
@@ -528,19 +549,7 @@ impl<It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekabl
         debug_assert!(self.peek().is_some() && self.peek().unwrap().is_ok());
         // note: can't use self.peek() since it will cause mut and immutable reference to self
         let tuple = self.iterator.peek().unwrap().as_ref().unwrap();
-
-        fn relevant_values<'a, 'b>(
-            (&pos, value): (&Option<ExecutorVariable>, &'a VariableValue<'b>),
-        ) -> Option<(VariablePosition, &'a VariableValue<'b>)> {
-            Some((pos?.as_position()?, value))
-        }
-
-        for (pos, value) in zip_eq(self.positions.positions(), tuple.values()).filter_map(relevant_values) {
-            if pos.as_usize() < row.len() {
-                // TODO either keep this or used selected varables
-                row.set(pos, value.clone().into_owned());
-            }
-        }
+        write_tuple_values(&self.positions, tuple, row);
     }
 
     fn peek(&mut self) -> Option<&Result<Tuple<'_>, Box<ConceptReadError>>> {
@@ -580,6 +589,22 @@ impl<It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekabl
     }
 }
 
+/// Writes the values of a tuple produced by an iterator with the given positions into the row.
+pub(crate) fn write_tuple_values(positions: &TuplePositions, tuple: &Tuple<'_>, row: &mut Row<'_>) {
+    fn relevant_values<'a, 'b>(
+        (&pos, value): (&Option<ExecutorVariable>, &'a VariableValue<'b>),
+    ) -> Option<(VariablePosition, &'a VariableValue<'b>)> {
+        Some((pos?.as_position()?, value))
+    }
+
+    for (pos, value) in zip_eq(positions.positions(), tuple.values()).filter_map(relevant_values) {
+        if pos.as_usize() < row.len() {
+            // TODO either keep this or used selected varables
+            row.set(pos, value.clone().into_owned());
+        }
+    }
+}
+
 fn first_unbound(variable_modes: &VariableModes, positions: &TuplePositions) -> TupleIndex {
     for (i, position) in positions.iter().enumerate() {
         if let Some(position) = position {
@@ -611,3 +636,130 @@ fn last_enumerated_or_counted(variable_modes: &VariableModes, positions: &TupleP
         .map(|(i, _)| i as TupleIndex)
         .last()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp::Ordering, vec};
+
+    use answer::variable_value::VariableValue;
+    use compiler::{
+        executable::match_::instructions::{VariableMode, VariableModes},
+        ExecutorVariable,
+    };
+    use concept::error::ConceptReadError;
+    use encoding::value::value::Value;
+    use lending_iterator::{AsLendingIterator, LendingIterator};
+
+    use super::{NaiiveSeekable, SortedTupleIterator, TupleIteratorAPI, TupleSeekable};
+    use crate::instruction::tuple::{Tuple, TuplePositions, TupleResult};
+
+    type MockTuples = AsLendingIterator<vec::IntoIter<TupleResult<'static>>>;
+
+    /// Stands in for a storage iterator whose seek falls short of the target, by not seeking at all.
+    struct LaggingSeekable(MockTuples);
+
+    impl LendingIterator for LaggingSeekable {
+        type Item<'a> = TupleResult<'static>;
+
+        fn next(&mut self) -> Option<Self::Item<'_>> {
+            self.0.next()
+        }
+    }
+
+    impl TupleSeekable for LaggingSeekable {
+        fn seek(&mut self, _target: &Tuple<'_>) -> Result<(), Box<ConceptReadError>> {
+            Ok(())
+        }
+    }
+
+    fn integer(value: i64) -> VariableValue<'static> {
+        VariableValue::Value(Value::Integer(value))
+    }
+
+    fn mock_tuples(keys: &[i64]) -> MockTuples {
+        AsLendingIterator::new(
+            keys.iter().map(|&key| Ok(Tuple::Pair([integer(key), integer(key * 10)]))).collect::<Vec<_>>(),
+        )
+    }
+
+    fn sorted<It>(iterator: It) -> SortedTupleIterator<It>
+    where
+        It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekable,
+    {
+        let (key, value) = (ExecutorVariable::new_position(0), ExecutorVariable::new_position(1));
+        let modes: VariableModes = [(key, VariableMode::Output), (value, VariableMode::Output)].into_iter().collect();
+        SortedTupleIterator::new(iterator, TuplePositions::Pair([Some(key), Some(value)]), &modes)
+    }
+
+    fn peek_key<It>(iterator: &mut SortedTupleIterator<It>) -> Option<VariableValue<'static>>
+    where
+        It: for<'a> LendingIterator<Item<'a> = TupleResult<'static>> + TupleSeekable,
+    {
+        iterator.peek_first_unbound_value().map(|value| value.unwrap().clone().into_owned())
+    }
+
+    #[test]
+    fn advancing_to_a_key_behind_the_current_position_does_not_move() {
+        let mut iterator = sorted(NaiiveSeekable::new(mock_tuples(&[1, 3, 5])));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(3)).unwrap(), Some(Ordering::Equal));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(2)).unwrap(), Some(Ordering::Greater));
+        assert_eq!(peek_key(&mut iterator), Some(integer(3)));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(3)).unwrap(), Some(Ordering::Equal));
+        assert_eq!(peek_key(&mut iterator), Some(integer(3)));
+    }
+
+    #[test]
+    fn advancing_after_an_interleaved_advance_single_reports_greater() {
+        let mut iterator = sorted(NaiiveSeekable::new(mock_tuples(&[1, 3, 5])));
+        assert_eq!(iterator.skip_until_join_key(&[integer(3)]).unwrap(), Some(Ordering::Equal));
+        iterator.advance_single().unwrap();
+        assert_eq!(iterator.skip_until_join_key(&[integer(3)]).unwrap(), Some(Ordering::Greater));
+        assert_eq!(peek_key(&mut iterator), Some(integer(5)));
+        iterator.advance_single().unwrap();
+        assert_eq!(iterator.skip_until_join_key(&[integer(3)]).unwrap(), None);
+    }
+
+    #[test]
+    fn advancing_to_a_pair_compares_lexicographically() {
+        let tuples = [(1, 5), (1, 9), (2, 1), (3, 0)]
+            .into_iter()
+            .map(|(first, second)| Ok(Tuple::Pair([integer(first), integer(second)])))
+            .collect::<Vec<_>>();
+        let mut iterator = sorted(LaggingSeekable(AsLendingIterator::new(tuples)));
+        let peek_pair = |iterator: &mut SortedTupleIterator<LaggingSeekable>| -> Option<Vec<VariableValue<'static>>> {
+            iterator.peek_join_key(2).map(|key| key.unwrap().iter().map(|value| value.clone().into_owned()).collect())
+        };
+        assert_eq!(iterator.skip_until_join_key(&[integer(1), integer(7)]).unwrap(), Some(Ordering::Greater));
+        assert_eq!(peek_pair(&mut iterator), Some(vec![integer(1), integer(9)]));
+        assert_eq!(iterator.skip_until_join_key(&[integer(2), integer(0)]).unwrap(), Some(Ordering::Greater));
+        assert_eq!(iterator.skip_until_join_key(&[integer(2), integer(1)]).unwrap(), Some(Ordering::Equal));
+        assert_eq!(peek_pair(&mut iterator), Some(vec![integer(2), integer(1)]));
+        assert_eq!(iterator.skip_until_join_key(&[integer(3), integer(1)]).unwrap(), None);
+    }
+
+    #[test]
+    fn advancing_across_value_types_follows_their_total_order() {
+        // as the attributes of each value type are in storage: integers, then doubles
+        let double = |value: f64| VariableValue::Value(Value::Double(value));
+        let keys = [integer(1), integer(3), double(0.5), double(2.5)];
+        let tuples = keys.iter().map(|key| Ok(Tuple::Pair([key.clone(), integer(0)]))).collect::<Vec<_>>();
+        let mut iterator = sorted(NaiiveSeekable::new(AsLendingIterator::new(tuples)));
+        assert_eq!(iterator.skip_until_first_unbound_value(&double(0.5)).unwrap(), Some(Ordering::Equal));
+        assert_eq!(peek_key(&mut iterator), Some(double(0.5)));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(3)).unwrap(), Some(Ordering::Greater));
+        assert_eq!(iterator.skip_until_first_unbound_value(&double(1.0)).unwrap(), Some(Ordering::Greater));
+        assert_eq!(peek_key(&mut iterator), Some(double(2.5)));
+        let string = VariableValue::Value(Value::String("a".into()));
+        assert_eq!(iterator.skip_until_first_unbound_value(&string).unwrap(), None);
+    }
+
+    #[test]
+    fn advancing_finishes_a_seek_that_falls_short() {
+        let mut iterator = sorted(LaggingSeekable(mock_tuples(&[1, 2, 3, 5])));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(3)).unwrap(), Some(Ordering::Equal));
+        assert_eq!(peek_key(&mut iterator), Some(integer(3)));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(4)).unwrap(), Some(Ordering::Greater));
+        assert_eq!(peek_key(&mut iterator), Some(integer(5)));
+        assert_eq!(iterator.skip_until_first_unbound_value(&integer(6)).unwrap(), None);
+    }
+}
@@ -9,7 +9,7 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
     ops::Bound,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use answer::{variable_value::VariableValue, Thing, Type};
@@ -59,7 +59,7 @@ pub(crate) struct LinksExecutor {
     player_type_range: Bounds<ObjectType>,
 
     filter_fn: Arc<LinksFilterFn>,
-    relation_cache: Option<Vec<Relation>>,
+    relation_cache: OnceLock<Vec<Relation>>,
 
     checker: Checker<(Links, u64)>,
 }
@@ -93,8 +93,8 @@ impl LinksExecutor {
         links: LinksInstruction<ExecutorVariable>,
         variable_modes: VariableModes,
         sort_by: ExecutorVariable,
-        snapshot: &impl ReadableSnapshot,
-        thing_manager: &ThingManager,
+        _snapshot: &impl ReadableSnapshot,
+        _thing_manager: &ThingManager,
     ) -> Result<Self, Box<ConceptReadError>> {
         debug_assert!(!variable_modes.all_inputs());
         let relation_player_types = links.relation_to_player_types().clone();
@@ -132,25 +132,6 @@ impl LinksExecutor {
         let (min_player_type, max_player_type) = min_max_types(player_types.iter());
         let player_type_range =
             (Bound::Included(min_player_type.as_object_type()), Bound::Included(max_player_type.as_object_type()));
-        let relation_cache = if iterate_mode == LinksIterateMode::UnboundInverted {
-            let mut cache = Vec::new();
-            for type_ in relation_player_types.keys() {
-                let instances: Vec<Relation> = Itertools::try_collect(thing_manager.get_relations_in(
-                    snapshot,
-                    type_.as_relation_type(),
-                    StorageCounters::DISABLED,
-                ))?;
-                cache.extend(instances);
-            }
-            #[cfg(debug_assertions)]
-            if cache.len() < CONSTANT_CONCEPT_LIMIT {
-                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
-            }
-            Some(cache)
-        } else {
-            None
-        };
-
         Ok(Self {
             links,
             iterate_mode,
@@ -160,17 +141,42 @@ impl LinksExecutor {
             relation_type_range,
             player_type_range,
             filter_fn,
-            relation_cache,
+            relation_cache: OnceLock::new(),
             checker,
         })
     }
 
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.relation_cache.take();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.relation_cache.is_some()
+    }
+
     pub(crate) fn get_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         row: MaybeOwnedRow<'_>,
         storage_counters: StorageCounters,
     ) -> Result<TupleIterator, Box<ConceptReadError>> {
+        if self.iterate_mode == LinksIterateMode::UnboundInverted && self.relation_cache.get().is_none() {
+            let mut cache = Vec::new();
+            for type_ in self.relation_player_types.keys() {
+                let instances: Vec<Relation> = Itertools::try_collect(context.thing_manager.get_relations_in(
+                    context.snapshot.as_ref(),
+                    type_.as_relation_type(),
+                    storage_counters.clone(),
+                ))?;
+                cache.extend(instances);
+            }
+            #[cfg(debug_assertions)]
+            if cache.len() < CONSTANT_CONCEPT_LIMIT {
+                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
+            }
+            self.relation_cache.get_or_init(|| cache);
+        }
+
         let filter = self.filter_fn.clone();
         let check = self.checker.filter_fn_for_row(context, &row, storage_counters.clone());
 
@@ -214,8 +220,8 @@ impl LinksExecutor {
             }
 
             LinksIterateMode::UnboundInverted => {
-                debug_assert!(self.relation_cache.is_some());
-                if let Some([relation]) = self.relation_cache.as_deref() {
+                debug_assert!(self.relation_cache.get().is_some());
+                if let Some([relation]) = self.relation_cache.get().map(Vec::as_slice) {
                     // no heap allocs needed if there is only 1 iterator
                     let iterator = thing_manager.get_links_by_relation_and_player_type_range(
                         snapshot,
@@ -239,7 +245,7 @@ impl LinksExecutor {
                 } else {
                     // TODO: we could create a reusable space for these temporarily held iterators
                     //       so we don't have allocate again before the merging iterator
-                    let relations = self.relation_cache.as_ref().unwrap().iter();
+                    let relations = self.relation_cache.get().unwrap().iter();
                     let tuple_iterators = relations
                         .map(|&relation| {
                             let iterator = thing_manager.get_links_by_relation_and_player_type_range(
@@ -9,7 +9,7 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     fmt,
     ops::Bound,
-    sync::Arc,
+    sync::{Arc, OnceLock},
 };
 
 use answer::Type;
@@ -62,7 +62,7 @@ pub(crate) struct LinksReverseExecutor {
     relation_type_range: Bounds<RelationType>,
 
     filter_fn: Arc<LinksFilterFn>,
-    player_cache: Option<Vec<Object>>,
+    player_cache: OnceLock<Vec<Object>>,
 
     checker: Checker<(Links, u64)>,
 }
@@ -81,8 +81,8 @@ impl LinksReverseExecutor {
         links_reverse: LinksReverseInstruction<ExecutorVariable>,
         variable_modes: VariableModes,
         sort_by: ExecutorVariable,
-        snapshot: &impl ReadableSnapshot,
-        thing_manager: &ThingManager,
+        _snapshot: &impl ReadableSnapshot,
+        _thing_manager: &ThingManager,
     ) -> Result<Self, Box<ConceptReadError>> {
         debug_assert!(!variable_modes.all_inputs());
         let player_relation_types = links_reverse.player_to_relation_types().clone();
@@ -124,25 +124,6 @@ impl LinksReverseExecutor {
             Bound::Included(max_relation_type.as_relation_type()),
         );
 
-        let player_cache = if iterate_mode == LinksIterateMode::UnboundInverted {
-            let mut cache = Vec::new();
-            for type_ in player_relation_types.keys() {
-                let instances: Vec<Object> = Itertools::try_collect(thing_manager.get_objects_in(
-                    snapshot,
-                    type_.as_object_type(),
-                    StorageCounters::DISABLED,
-                ))?;
-                cache.extend(instances);
-            }
-            #[cfg(debug_assertions)]
-            if cache.len() < CONSTANT_CONCEPT_LIMIT {
-                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
-            }
-            Some(cache)
-        } else {
-            None
-        };
-
         Ok(Self {
             links,
             iterate_mode,
@@ -152,17 +133,42 @@ impl LinksReverseExecutor {
             player_type_range,
             relation_type_range,
             filter_fn,
-            player_cache,
+            player_cache: OnceLock::new(),
             checker,
         })
     }
 
+    pub(crate) fn clear_instance_cache(&mut self) {
+        self.player_cache.take();
+    }
+
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        self.player_cache.is_some()
+    }
+
     pub(crate) fn get_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         row: MaybeOwnedRow<'_>,
         storage_counters: StorageCounters,
     ) -> Result<TupleIterator, Box<ConceptReadError>> {
+        if self.iterate_mode == LinksIterateMode::UnboundInverted && self.player_cache.get().is_none() {
+            let mut cache = Vec::new();
+            for type_ in self.player_relation_types.keys() {
+                let instances: Vec<Object> = Itertools::try_collect(context.thing_manager.get_objects_in(
+                    context.snapshot.as_ref(),
+                    type_.as_object_type(),
+                    storage_counters.clone(),
+                ))?;
+                cache.extend(instances);
+            }
+            #[cfg(debug_assertions)]
+            if cache.len() < CONSTANT_CONCEPT_LIMIT {
+                eprintln!("DEBUG_ASSERT_FAILURE: cache.len() > CONSTANT_CONCEPT_LIMIT");
+            }
+            self.player_cache.get_or_init(|| cache);
+        }
+
         let filter = self.filter_fn.clone();
         let check = self.checker.filter_fn_for_row(context, &row, storage_counters.clone());
 
@@ -206,8 +212,8 @@ impl LinksReverseExecutor {
             }
 
             LinksIterateMode::UnboundInverted => {
-                debug_assert!(self.player_cache.is_some());
-                if let Some([player]) = self.player_cache.as_deref() {
+                debug_assert!(self.player_cache.get().is_some());
+                if let Some([player]) = self.player_cache.get().map(Vec::as_slice) {
                     // no heap allocs needed if there is only 1 iterator
                     let filtered = thing_manager.get_links_reverse_by_player_and_relation_type_range(
                         snapshot,
@@ -231,7 +237,7 @@ impl LinksReverseExecutor {
                 } else {
                     // TODO: we could create a reusable space for these temporarily held iterators
                     //       so we don't have allocate again before the merging iterator
-                    let players = self.player_cache.as_ref().unwrap().iter();
+                    let players = self.player_cache.get().unwrap().iter();
                     let tuple_iterators = players
                         .map(|&player| {
                             let iterator = thing_manager.get_links_reverse_by_player_and_relation_type_range(
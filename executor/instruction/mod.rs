@@ -4,13 +4,14 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{collections::HashMap, fmt, marker::PhantomData, ops::Bound};
+use std::{collections::HashMap, fmt, iter::zip, marker::PhantomData, ops::Bound, sync::atomic};
 
 use ::iterator::minmax_or;
 use answer::{variable_value::VariableValue, Thing, Type};
 use compiler::{
     executable::match_::instructions::{
-        CheckInstruction, CheckVertex, ConstraintInstruction, VariableMode, VariableModes,
+        pushdown::PushdownCandidate, CheckInstruction, CheckVertex, ConstraintInstruction, StringMatcher, VariableMode,
+        VariableModes,
     },
     ExecutorVariable,
 };
@@ -34,14 +35,13 @@ use ir::{
 use itertools::Itertools;
 use resource::profile::StorageCounters;
 use storage::snapshot::ReadableSnapshot;
-use unicase::UniCase;
 
 use crate::{
     instruction::{
-        has_executor::HasExecutor, has_reverse_executor::HasReverseExecutor, iid_executor::IidExecutor,
-        indexed_relation_executor::IndexedRelationExecutor, is_executor::IsExecutor, isa_executor::IsaExecutor,
-        isa_reverse_executor::IsaReverseExecutor, iterator::TupleIterator, links_executor::LinksExecutor,
-        links_reverse_executor::LinksReverseExecutor, owns_executor::OwnsExecutor,
+        bound_links_executor::BoundLinksExecutor, has_executor::HasExecutor, has_reverse_executor::HasReverseExecutor,
+        iid_executor::IidExecutor, indexed_relation_executor::IndexedRelationExecutor, is_executor::IsExecutor,
+        isa_executor::IsaExecutor, isa_reverse_executor::IsaReverseExecutor, iterator::TupleIterator,
+        links_executor::LinksExecutor, links_reverse_executor::LinksReverseExecutor, owns_executor::OwnsExecutor,
         owns_reverse_executor::OwnsReverseExecutor, plays_executor::PlaysExecutor,
         plays_reverse_executor::PlaysReverseExecutor, relates_executor::RelatesExecutor,
         relates_reverse_executor::RelatesReverseExecutor, sub_executor::SubExecutor,
@@ -51,6 +51,7 @@ use crate::{
     row::MaybeOwnedRow,
 };
 
+mod bound_links_executor;
 mod has_executor;
 mod has_reverse_executor;
 mod iid_executor;
@@ -100,6 +101,7 @@ pub(crate) enum InstructionExecutor {
 
     Links(LinksExecutor),
     LinksReverse(LinksReverseExecutor),
+    BoundLinks(BoundLinksExecutor),
 
     IndexedRelation(IndexedRelationExecutor),
 }
@@ -160,6 +162,13 @@ impl InstructionExecutor {
                 snapshot,
                 thing_manager,
             )?)),
+            ConstraintInstruction::BoundLinks(bound_links) => Ok(Self::BoundLinks(BoundLinksExecutor::new(
+                bound_links,
+                variable_modes,
+                sort_by,
+                snapshot,
+                thing_manager,
+            )?)),
             ConstraintInstruction::IndexedRelation(indexed_relation) => Ok(Self::IndexedRelation(
                 IndexedRelationExecutor::new(indexed_relation, variable_modes, sort_by, snapshot, thing_manager)?,
             )),
@@ -190,10 +199,62 @@ impl InstructionExecutor {
             Self::HasReverse(executor) => executor.get_iterator(context, row, storage_counters),
             Self::Links(executor) => executor.get_iterator(context, row, storage_counters),
             Self::LinksReverse(executor) => executor.get_iterator(context, row, storage_counters),
+            Self::BoundLinks(executor) => executor.get_iterator(context, row, storage_counters),
             Self::IndexedRelation(executor) => executor.get_iterator(context, row, storage_counters),
         }
     }
 
+    /// Drops any instances cached from the snapshot of a previous execution, so the next iterator
+    /// is built from the snapshot in its execution context.
+    pub(crate) fn clear_instance_cache(&mut self) {
+        match self {
+            Self::Has(executor) => executor.clear_instance_cache(),
+            Self::HasReverse(executor) => executor.clear_instance_cache(),
+            Self::Links(executor) => executor.clear_instance_cache(),
+            Self::LinksReverse(executor) => executor.clear_instance_cache(),
+            Self::BoundLinks(executor) => executor.clear_instance_cache(),
+            Self::IndexedRelation(executor) => executor.clear_instance_cache(),
+            Self::Is(_)
+            | Self::Iid(_)
+            | Self::TypeList(_)
+            | Self::Sub(_)
+            | Self::SubReverse(_)
+            | Self::Owns(_)
+            | Self::OwnsReverse(_)
+            | Self::Relates(_)
+            | Self::RelatesReverse(_)
+            | Self::Plays(_)
+            | Self::PlaysReverse(_)
+            | Self::Isa(_)
+            | Self::IsaReverse(_) => {}
+        }
+    }
+
+    /// Whether instances were cached from the snapshot of an execution since the cache was last cleared.
+    pub(crate) fn has_instance_cache(&self) -> bool {
+        match self {
+            Self::Has(executor) => executor.has_instance_cache(),
+            Self::HasReverse(executor) => executor.has_instance_cache(),
+            Self::Links(executor) => executor.has_instance_cache(),
+            Self::LinksReverse(executor) => executor.has_instance_cache(),
+            Self::BoundLinks(executor) => executor.has_instance_cache(),
+            Self::IndexedRelation(executor) => executor.has_instance_cache(),
+            Self::Is(_)
+            | Self::Iid(_)
+            | Self::TypeList(_)
+            | Self::Sub(_)
+            | Self::SubReverse(_)
+            | Self::Owns(_)
+            | Self::OwnsReverse(_)
+            | Self::Relates(_)
+            | Self::RelatesReverse(_)
+            | Self::Plays(_)
+            | Self::PlaysReverse(_)
+            | Self::Isa(_)
+            | Self::IsaReverse(_) => false,
+        }
+    }
+
     pub(crate) const fn name(&self) -> &'static str {
         match self {
             Self::Is(_) => "is",
@@ -204,6 +265,7 @@ impl InstructionExecutor {
             Self::HasReverse(_) => "has_reverse",
             Self::Links(_) => "links",
             Self::LinksReverse(_) => "links_reverse",
+            Self::BoundLinks(_) => "bound_links",
             Self::TypeList(_) => "[internal]type_list",
             Self::Sub(_) => "sub",
             Self::SubReverse(_) => "sub_reverse",
@@ -238,6 +300,7 @@ impl fmt::Display for InstructionExecutor {
             InstructionExecutor::HasReverse(inner) => fmt::Display::fmt(inner, f),
             InstructionExecutor::Links(inner) => fmt::Display::fmt(inner, f),
             InstructionExecutor::LinksReverse(inner) => fmt::Display::fmt(inner, f),
+            InstructionExecutor::BoundLinks(inner) => fmt::Display::fmt(inner, f),
             InstructionExecutor::IndexedRelation(inner) => fmt::Display::fmt(inner, f),
         }
     }
@@ -372,6 +435,8 @@ type FilterFn<T> = dyn Fn(&Result<T, Box<ConceptReadError>>) -> Result<bool, Box
 pub(crate) struct Checker<T: 'static> {
     extractors: HashMap<ExecutorVariable, fn(&T) -> VariableValue<'_>>,
     pub checks: Vec<CheckInstruction<ExecutorVariable>>,
+    /// For each check, what storage would have to apply to spare the executor from evaluating it.
+    pushdown_candidates: Vec<Option<PushdownCandidate<ExecutorVariable>>>,
     _phantom_data: PhantomData<T>,
 }
 
@@ -386,10 +451,16 @@ macro_rules! unwrap_or_bail {
 
 impl<T> Checker<T> {
     pub(crate) fn new(
-        checks: Vec<CheckInstruction<ExecutorVariable>>,
+        mut checks: Vec<CheckInstruction<ExecutorVariable>>,
         extractors: HashMap<ExecutorVariable, fn(&T) -> VariableValue<'_>>,
     ) -> Self {
-        Self { extractors, checks, _phantom_data: PhantomData }
+        // an IID check admits at most one instance, so evaluate it before any of the more expensive checks
+        checks.sort_by_key(|check| !matches!(check, CheckInstruction::Iid { .. }));
+        let pushdown_candidates = checks
+            .iter()
+            .map(|check| PushdownCandidate::from_check(check, |var| extractors.contains_key(&var)))
+            .collect();
+        Self { extractors, checks, pushdown_candidates, _phantom_data: PhantomData }
     }
 
     pub(crate) fn value_range_for(
@@ -426,7 +497,7 @@ impl<T> Checker<T> {
         for i in 0..self.checks.len() {
             let check = &self.checks[i];
             match check {
-                CheckInstruction::Comparison { lhs, rhs, comparator } => {
+                CheckInstruction::Comparison { lhs, rhs, comparator, .. } => {
                     if lhs.as_variable() == Some(target_variable) {
                         let rhs_variable_value = get_vertex_value(rhs, row.as_ref(), &context.parameters);
                         let rhs_value = Self::read_value(
@@ -539,7 +610,12 @@ impl<T> Checker<T> {
         let mut filters: Vec<Box<dyn Fn(&T) -> Result<bool, Box<ConceptReadError>>>> =
             Vec::with_capacity(self.checks.len());
 
-        for check in &self.checks {
+        for (check, candidate) in zip(&self.checks, &self.pushdown_candidates) {
+            let is_claimed = candidate.as_ref().is_some_and(|candidate| context.storage_pushdown.claims(candidate));
+            let claim_verification = context.storage_pushdown.claim_verification();
+            if is_claimed && claim_verification.is_none() {
+                continue;
+            }
             let filter = match check {
                 &CheckInstruction::Iid { var, iid } => self.filter_iid(context, row, var, iid),
                 &CheckInstruction::TypeList { type_var, ref types } => {
@@ -580,12 +656,30 @@ impl<T> Checker<T> {
                     self.filter_links_dedup(row, role1, player1, role2, player2)
                 }
                 &CheckInstruction::Is { lhs, rhs } => self.filter_is(row, lhs, rhs),
-                CheckInstruction::Comparison { lhs, rhs, comparator } => {
-                    self.filter_comparison(context, row, lhs, rhs, comparator, storage_counters.clone())
-                }
+                CheckInstruction::Comparison { lhs, rhs, comparator, matcher } => self.filter_comparison(
+                    context,
+                    row,
+                    lhs,
+                    rhs,
+                    comparator,
+                    matcher.as_ref(),
+                    storage_counters.clone(),
+                ),
                 CheckInstruction::Unsatisfiable => Box::new(|_: &T| Ok(false)),
             };
-            filters.push(filter);
+            match claim_verification {
+                Some(violations) if is_claimed => {
+                    let violations = violations.clone();
+                    filters.push(Box::new(move |value: &T| {
+                        let is_satisfied = filter(value)?;
+                        if !is_satisfied {
+                            violations.fetch_add(1, atomic::Ordering::Relaxed);
+                        }
+                        Ok(is_satisfied)
+                    }))
+                }
+                _ => filters.push(filter),
+            }
         }
 
         Box::new(move |res| {
@@ -1015,6 +1109,7 @@ impl<T> Checker<T> {
         lhs: &CheckVertex<ExecutorVariable>,
         rhs: &CheckVertex<ExecutorVariable>,
         comparator: &Comparator,
+        matcher: Option<&StringMatcher>,
         storage_counters: StorageCounters,
     ) -> Box<dyn Fn(&T) -> Result<bool, Box<ConceptReadError>>> {
         let maybe_lhs_extractor = lhs.as_variable().and_then(|var| self.extractors.get(&var));
@@ -1047,17 +1142,18 @@ impl<T> Checker<T> {
             Comparator::Greater => |a, b| a > b,
             Comparator::LessOrEqual => |a, b| a <= b,
             Comparator::GreaterOrEqual => |a, b| a >= b,
-            Comparator::Like => |a, b| {
-                // TODO: Avoid recompiling the regex every time.
-                regex::Regex::new(b.unwrap_string_ref())
-                    .expect("Invalid regex should have been caught at compile time")
-                    .is_match(a.unwrap_string_ref())
-            },
-            Comparator::Contains => |a, b| {
-                let a_unicase = UniCase::new(a.unwrap_string_ref()).to_folded_case();
-                let b_unicase = UniCase::new(b.unwrap_string_ref()).to_folded_case();
-                a_unicase.contains(b_unicase.as_str())
-            },
+            Comparator::Like | Comparator::Contains => |_, _| unreachable!("Strings are matched by a prepared matcher"),
+        };
+        let matcher = match &rhs {
+            Ok(Value::String(pattern)) if matches!(comparator, Comparator::Like | Comparator::Contains) => {
+                // a cached executable may be executed with another pattern than the one its matcher was prepared from
+                match matcher.filter(|matcher| matcher.source() == pattern.as_ref()) {
+                    Some(matcher) => Some(matcher.clone()),
+                    None => StringMatcher::prepare(*comparator, pattern)
+                        .expect("Invalid regex should have been caught at compile time"),
+                }
+            }
+            _ => None,
         };
         Box::new(move |value: &T| {
             // NOTE: Empty <op> Empty never matches
@@ -1070,6 +1166,9 @@ impl<T> Checker<T> {
                 VariableValue::ThingList(_) | VariableValue::ValueList(_) => unimplemented_feature!(Lists),
                 VariableValue::None | VariableValue::Type(_) | VariableValue::Thing(_) => unreachable!(),
             };
+            if let Some(matcher) = &matcher {
+                return Ok(matches!(&lhs, Value::String(lhs) if matcher.is_match(lhs)));
+            }
             let rhs = rhs.clone()?;
             if rhs.value_type().is_trivially_castable_to(lhs.value_type().category()) {
                 Ok(cmp(&lhs, &rhs.cast(lhs.value_type().category()).unwrap()))
@@ -35,6 +35,13 @@ use lending_iterator::higher_order::Hkt;
 
 use crate::instruction::{has_executor::FixedHasBounds, links_executor::FixedLinksBounds};
 
+/// The single ordering of types shared by every tuple position that holds a type, across all instruction families:
+/// by kind (entity, relation, attribute, role), then by type ID. This is the order of type vertices in storage, so
+/// type-level and thing-level iterators sorted on the same type variable can be intersected directly.
+pub(crate) fn canonical_type_cmp(first: &Type, second: &Type) -> Ordering {
+    first.cmp(second)
+}
+
 pub(crate) type TupleOrderingFn = for<'a, 'b> fn((&'a TupleResult<'static>, &'b TupleResult<'static>)) -> Ordering;
 
 pub(crate) fn unsafe_compare_result_tuple<'a, 'b>(
@@ -54,7 +61,14 @@ pub(crate) fn unsafe_compare_result_tuple<'a, 'b>(
             return Ordering::Equal;
         }
     };
-    first_tuple.partial_cmp(second_tuple).unwrap()
+    first_tuple.total_cmp(second_tuple)
+}
+
+/// Compares the values of tuples, or join keys, in the total order instruction iterators produce them in. Unlike
+/// `partial_cmp`, this also orders values of different kinds or value types, such as integers and doubles.
+pub(crate) fn total_cmp_values(first: &[VariableValue<'_>], second: &[VariableValue<'_>]) -> Ordering {
+    let mismatch = first.iter().zip(second).map(|(lhs, rhs)| lhs.total_cmp(rhs)).find(|ordering| ordering.is_ne());
+    mismatch.unwrap_or_else(|| first.len().cmp(&second.len()))
 }
 
 #[derive(Debug, Clone)]
@@ -78,13 +92,11 @@ impl PartialEq<Tuple<'_>> for Tuple<'_> {
     }
 }
 
-impl PartialOrd<Tuple<'_>> for Tuple<'_> {
-    fn partial_cmp(&self, other: &Tuple<'_>) -> Option<std::cmp::Ordering> {
-        self.values().partial_cmp(other.values())
+impl<'a> Tuple<'a> {
+    pub(crate) fn total_cmp(&self, other: &Tuple<'_>) -> Ordering {
+        total_cmp_values(self.values(), other.values())
     }
-}
 
-impl<'a> Tuple<'a> {
     pub(crate) fn values(&self) -> &[VariableValue<'a>] {
         match self {
             Tuple::Single(values) => values,
@@ -107,6 +107,13 @@ impl Clone for ExecutionInterrupt {
     }
 }
 
+/// The set of disjunction branches a row was produced through, as one bit per `BranchID`.
+/// Steps combine provenance as follows:
+///  - steps that extend or filter rows (intersections, cartesian expansion, checks, assignments) carry over the
+///    provenance of the input row they extend,
+///  - negations emit their input row unchanged, so the branches taken inside the negation never leak out,
+///  - disjunctions keep the provenance of the branch output and OR in the branch's own bit,
+///  - function calls replace the provenance of the returned row with that of the calling row.
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub struct Provenance(pub u64);
 
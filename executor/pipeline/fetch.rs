@@ -35,7 +35,7 @@ use crate::{
     error::ReadExecutionError,
     pipeline::{
         pipeline::{Pipeline, PipelineError},
-        stage::{ExecutionContext, StageAPI},
+        stage::{ExecutionContext, MultiplicityPolicy, StageAPI},
         PipelineExecutionError,
     },
     read::{
@@ -73,7 +73,7 @@ impl<Snapshot: ReadableSnapshot + 'static> FetchStageExecutor<Snapshot> {
         context: ExecutionContext<Snapshot>,
         interrupt: ExecutionInterrupt,
     ) -> (impl Iterator<Item = Result<ConceptDocument, Box<PipelineExecutionError>>>, ExecutionContext<Snapshot>) {
-        let ExecutionContext { snapshot, thing_manager, parameters, profile } = context.clone();
+        let ExecutionContext { snapshot, thing_manager, parameters, profile, .. } = context.clone();
         let executable = self.executable;
         let functions = self.functions;
         let stage_profile = profile.profile_stage(|| String::from("Fetch"), executable.executable_id);
@@ -365,6 +365,7 @@ fn execute_list_subfetch(
             parameters,
             None,
             query_profile,
+            MultiplicityPolicy::default(),
         )
     } else {
         let max_position = input_position_mapping.values().max().map(|pos| pos.as_usize()).unwrap();
@@ -385,6 +386,7 @@ fn execute_list_subfetch(
             parameters,
             Some(initial_row),
             query_profile,
+            MultiplicityPolicy::default(),
         )
     }
     .map_err(|typedb_source| FetchExecutionError::Pipeline { typedb_source })?;
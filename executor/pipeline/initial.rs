@@ -23,7 +23,7 @@ pub struct InitialStage<Snapshot> {
 
 impl<Snapshot> InitialStage<Snapshot> {
     pub fn new_empty(context: ExecutionContext<Snapshot>) -> Self {
-        Self { context, initial_batch: FixedBatch::SINGLE_EMPTY_ROW }
+        Self { context, initial_batch: FixedBatch::single_empty_row() }
     }
 
     pub fn new_with(context: ExecutionContext<Snapshot>, initial_row: MaybeOwnedRow<'_>) -> Self {
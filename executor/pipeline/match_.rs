@@ -17,7 +17,7 @@ use crate::{
     conjunction_executor::{ConjunctionExecutor, PatternIterator},
     error::ReadExecutionError,
     pipeline::{
-        stage::{ExecutionContext, StageAPI},
+        stage::{ExecutionContext, MultiplicityPolicy, RowLimit, StageAPI},
         PipelineExecutionError, StageIterator,
     },
     row::MaybeOwnedRow,
@@ -25,18 +25,26 @@ use crate::{
 };
 
 pub struct MatchStageExecutor<PreviousStage> {
+    stage_id: u64,
     executable: Arc<ConjunctionExecutable>,
     previous: PreviousStage,
     function_registry: Arc<ExecutableFunctionRegistry>,
+    row_limit: Option<u64>,
 }
 
 impl<PreviousStage> MatchStageExecutor<PreviousStage> {
     pub fn new(
+        stage_id: u64,
         executable: Arc<ConjunctionExecutable>,
         previous: PreviousStage,
         function_registry: Arc<ExecutableFunctionRegistry>,
     ) -> Self {
-        Self { executable, previous, function_registry }
+        Self { stage_id, executable, previous, function_registry, row_limit: None }
+    }
+
+    /// Bounds the batches of the match to the rows a downstream limit takes from this stage.
+    pub fn with_row_limit(self, row_limit: Option<u64>) -> Self {
+        Self { row_limit, ..self }
     }
 }
 
@@ -54,31 +62,48 @@ where
         (Self::OutputIterator, ExecutionContext<Snapshot>),
         (Box<PipelineExecutionError>, ExecutionContext<Snapshot>),
     > {
-        let Self { previous: previous_stage, executable, function_registry, .. } = self;
+        let Self { stage_id, previous: previous_stage, executable, function_registry, row_limit } = self;
         let (previous_iterator, context) = previous_stage.into_iterator(interrupt.clone())?;
         let iterator = previous_iterator;
-        Ok((MatchStageIterator::new(iterator, executable, function_registry, context.clone(), interrupt), context))
+        // later stages receive the context without the row limit, which only concerns the rows of this stage
+        let match_context = match row_limit {
+            None => context.clone(),
+            Some(rows) => context.clone().with_row_limit(RowLimit::new(rows)),
+        };
+        let iterator =
+            MatchStageIterator::new(iterator, stage_id, executable, function_registry, match_context, interrupt);
+        Ok((iterator, context))
     }
 }
 
 pub struct MatchStageIterator<Snapshot: ReadableSnapshot + 'static, Iterator> {
     context: ExecutionContext<Snapshot>,
+    stage_id: u64,
     executable: Arc<ConjunctionExecutable>,
     function_registry: Arc<ExecutableFunctionRegistry>,
     source_iterator: Iterator,
-    current_iterator: Option<Peekable<UniqueRows<AsOwnedRows<PatternIterator<Snapshot>>>>>,
+    current_iterator: Option<Peekable<MatchRows<PatternIterator<Snapshot>>>>,
     interrupt: ExecutionInterrupt,
 }
 
 impl<Snapshot: ReadableSnapshot + 'static, Iterator> MatchStageIterator<Snapshot, Iterator> {
     fn new(
         iterator: Iterator,
+        stage_id: u64,
         executable: Arc<ConjunctionExecutable>,
         function_registry: Arc<ExecutableFunctionRegistry>,
         context: ExecutionContext<Snapshot>,
         interrupt: ExecutionInterrupt,
     ) -> Self {
-        Self { context, executable, function_registry, source_iterator: iterator, current_iterator: None, interrupt }
+        Self {
+            context,
+            stage_id,
+            executable,
+            function_registry,
+            source_iterator: iterator,
+            current_iterator: None,
+            interrupt,
+        }
     }
 }
 
@@ -98,7 +123,8 @@ where
                 Err(err) => return Some(Err(err)),
             };
 
-            let executor = ConjunctionExecutor::new(
+            let executor = ConjunctionExecutor::new_profiled_as(
+                self.stage_id,
                 &self.executable,
                 snapshot,
                 thing_manager,
@@ -106,21 +132,31 @@ where
                 self.function_registry.clone(),
                 profile,
             )
-            .map_err(|err| Box::new(PipelineExecutionError::InitialisingMatchIterator { typedb_source: err }));
+            .map_err(PipelineExecutionError::initialising_match_iterator);
 
             match executor {
                 Ok(executor) => {
-                    self.current_iterator = Some(
-                        unique_rows(as_owned_rows(
-                            executor.into_iterator(self.context.clone(), self.interrupt.clone()),
-                        ))
-                        .peekable(),
-                    );
+                    let rows = as_owned_rows(executor.into_iterator(self.context.clone(), self.interrupt.clone()));
+                    let is_counted = self.executable.is_row_count_only() || self.executable.is_grouped_count_only();
+                    let rows = match is_counted {
+                        true => MatchRows::Counted(rows),
+                        false => MatchRows::Unique(unique_rows(rows)),
+                    };
+                    self.current_iterator = Some(rows.peekable());
                 }
                 Err(err) => return Some(Err(err)),
             };
         }
         self.current_iterator.as_mut().unwrap().next().map(|result| {
+            if let (Some(row_limit), Ok(row)) = (&self.context.row_limit, &result) {
+                // once expanded, a row standing for several answers, such as one without the unselected variables,
+                // takes as many rows from the limit
+                let rows = match self.context.multiplicity_policy {
+                    MultiplicityPolicy::Preserve => 1,
+                    MultiplicityPolicy::ExpandUpTo(_) => row.multiplicity(),
+                };
+                row_limit.record_rows(rows);
+            }
             result.map_err(|err| Box::new(PipelineExecutionError::ReadPatternExecution { typedb_source: err.clone() }))
         })
     }
@@ -133,6 +169,28 @@ where
 {
 }
 
+/// Rows of a row-count-only executable all have width 0 and are told apart only by their multiplicity, and rows of a
+/// grouped-count-only executable may repeat a group whose answers were counted separately, so neither must be
+/// deduplicated.
+enum MatchRows<I> {
+    Unique(UniqueRows<AsOwnedRows<I>>),
+    Counted(AsOwnedRows<I>),
+}
+
+impl<I> Iterator for MatchRows<I>
+where
+    I: for<'a> LendingIterator<Item<'a> = Result<MaybeOwnedRow<'a>, &'a ReadExecutionError>>,
+{
+    type Item = Result<MaybeOwnedRow<'static>, ReadExecutionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            MatchRows::Unique(rows) => rows.next(),
+            MatchRows::Counted(rows) => rows.next(),
+        }
+    }
+}
+
 type AsOwnedRows<I> = IntoIter<
     Map<
         I,
@@ -77,5 +77,23 @@ typedb_error! {
         WriteError(6, "Error executing write operation.", typedb_source: Box<WriteError>),
         ReadPatternExecution(7, "Error executing a read pattern.", typedb_source: ReadExecutionError),
         FetchError(8, "Error executing fetch operation.", typedb_source: FetchExecutionError),
+        MultiplicityExpansionLimitExceeded(
+            9,
+            "A row standing for {multiplicity} answers exceeds the limit of {limit} answers a row may be expanded into.",
+            multiplicity: u64,
+            limit: u64
+        ),
+    }
+}
+
+impl PipelineExecutionError {
+    // Concept read errors keep being reported as failures to initialise the iterator.
+    pub(crate) fn initialising_match_iterator(error: Box<ReadExecutionError>) -> Box<Self> {
+        match *error {
+            ReadExecutionError::ConceptRead { typedb_source } => {
+                Box::new(Self::InitialisingMatchIterator { typedb_source })
+            }
+            error => Box::new(Self::ReadPatternExecution { typedb_source: error }),
+        }
     }
 }
@@ -8,7 +8,8 @@ use std::{collections::HashSet, sync::Arc};
 use answer::variable_value::VariableValue;
 use compiler::{
     executable::modifiers::{
-        DistinctExecutable, LimitExecutable, OffsetExecutable, RequireExecutable, SelectExecutable, SortExecutable,
+        DistinctExecutable, ExpandExecutable, LimitExecutable, OffsetExecutable, RequireExecutable, SelectExecutable,
+        SortExecutable,
     },
     VariablePosition,
 };
@@ -20,11 +21,11 @@ use storage::snapshot::ReadableSnapshot;
 use crate::{
     batch::Batch,
     pipeline::{
-        stage::{ExecutionContext, StageAPI},
+        stage::{ExecutionContext, MultiplicityPolicy, StageAPI},
         PipelineExecutionError, StageIterator,
     },
     row::MaybeOwnedRow,
-    ExecutionInterrupt,
+    ExecutionInterrupt, Provenance,
 };
 
 // Sort
@@ -92,7 +93,11 @@ impl SortStageIterator {
                 SortVariable::Descending(v) => (sort_executable.output_row_mapping.get(v).unwrap().as_usize(), false),
             })
             .collect();
-        let sorted_indices = unsorted.indices_sorted_by(context, &sort_by, storage_counters);
+        let sorted_indices = if sort_executable.is_presorted {
+            (0..unsorted.len()).collect()
+        } else {
+            unsorted.indices_sorted_by(context, &sort_by, storage_counters)
+        };
         Self { unsorted, sorted_indices, next_index_index: 0 }
     }
 }
@@ -238,6 +243,86 @@ where
     }
 }
 
+// Expand
+pub struct ExpandStageExecutor<PreviousStage> {
+    expand_executable: Arc<ExpandExecutable>,
+    previous: PreviousStage,
+}
+
+impl<PreviousStage> ExpandStageExecutor<PreviousStage> {
+    pub fn new(expand_executable: Arc<ExpandExecutable>, previous: PreviousStage) -> Self {
+        Self { expand_executable, previous }
+    }
+}
+
+impl<Snapshot, PreviousStage> StageAPI<Snapshot> for ExpandStageExecutor<PreviousStage>
+where
+    Snapshot: ReadableSnapshot + 'static,
+    PreviousStage: StageAPI<Snapshot>,
+{
+    type OutputIterator = ExpandStageIterator<PreviousStage::OutputIterator>;
+
+    fn into_iterator(
+        self,
+        interrupt: ExecutionInterrupt,
+    ) -> Result<
+        (Self::OutputIterator, ExecutionContext<Snapshot>),
+        (Box<PipelineExecutionError>, ExecutionContext<Snapshot>),
+    > {
+        let Self { previous, .. } = self;
+        let (previous_iterator, context) = previous.into_iterator(interrupt)?;
+        Ok((ExpandStageIterator::new(previous_iterator, context.multiplicity_policy), context))
+    }
+}
+
+pub struct ExpandStageIterator<PreviousIterator> {
+    policy: MultiplicityPolicy,
+    previous: PreviousIterator,
+    current: Option<(MaybeOwnedRow<'static>, Provenance)>,
+    remaining_copies: u64,
+}
+
+impl<PreviousIterator> ExpandStageIterator<PreviousIterator> {
+    fn new(previous: PreviousIterator, policy: MultiplicityPolicy) -> Self {
+        Self { policy, previous, current: None, remaining_copies: 0 }
+    }
+}
+
+impl<PreviousIterator> StageIterator for ExpandStageIterator<PreviousIterator> where PreviousIterator: StageIterator {}
+
+impl<PreviousIterator> LendingIterator for ExpandStageIterator<PreviousIterator>
+where
+    PreviousIterator: StageIterator,
+{
+    type Item<'a> = Result<MaybeOwnedRow<'a>, Box<PipelineExecutionError>>;
+
+    fn next(&mut self) -> Option<Self::Item<'_>> {
+        let max_multiplicity = match self.policy {
+            MultiplicityPolicy::Preserve => return self.previous.next(),
+            MultiplicityPolicy::ExpandUpTo(max_multiplicity) => max_multiplicity,
+        };
+        while self.remaining_copies == 0 {
+            let row = match self.previous.next()? {
+                Ok(row) => row,
+                Err(err) => return Some(Err(err)),
+            };
+            let multiplicity = row.multiplicity();
+            if multiplicity > max_multiplicity {
+                return Some(Err(Box::new(PipelineExecutionError::MultiplicityExpansionLimitExceeded {
+                    multiplicity,
+                    limit: max_multiplicity,
+                })));
+            }
+            let provenance = row.provenance();
+            self.current = Some((row.into_owned(), provenance));
+            self.remaining_copies = multiplicity;
+        }
+        self.remaining_copies -= 1;
+        let (row, provenance) = self.current.as_ref().unwrap();
+        Some(Ok(MaybeOwnedRow::new_borrowed(row.row(), &1, provenance)))
+    }
+}
+
 // Select
 pub struct SelectStageExecutor<PreviousStage> {
     select_executable: Arc<SelectExecutable>,
@@ -27,12 +27,12 @@ use crate::{
         insert::InsertStageExecutor,
         match_::MatchStageExecutor,
         modifiers::{
-            DistinctStageExecutor, LimitStageExecutor, OffsetStageExecutor, RequireStageExecutor, SelectStageExecutor,
-            SortStageExecutor,
+            DistinctStageExecutor, ExpandStageExecutor, LimitStageExecutor, OffsetStageExecutor, RequireStageExecutor,
+            SelectStageExecutor, SortStageExecutor,
         },
         put::PutStageExecutor,
         reduce::ReduceStageExecutor,
-        stage::{ExecutionContext, ReadPipelineStage, StageAPI, WritePipelineStage},
+        stage::{ExecutionContext, MultiplicityPolicy, ReadPipelineStage, StageAPI, WritePipelineStage},
         update::UpdateStageExecutor,
         PipelineExecutionError,
     },
@@ -127,22 +127,27 @@ impl<Snapshot: ReadableSnapshot + 'static> Pipeline<Snapshot, ReadPipelineStage<
         parameters: Arc<ParameterRegistry>,
         input: Option<MaybeOwnedRow<'_>>,
         query_profile: Arc<QueryProfile>,
+        multiplicity_policy: MultiplicityPolicy,
     ) -> Result<Self, Box<PipelineError>> {
         let output_variable_positions = executable_stages.last().unwrap().output_row_mapping();
-        let context = ExecutionContext::new_with_profile(snapshot, thing_manager, parameters.clone(), query_profile);
+        let context = ExecutionContext::new_with_profile(snapshot, thing_manager, parameters.clone(), query_profile)
+            .with_multiplicity_policy(multiplicity_policy);
         let mut last_stage = ReadPipelineStage::Initial(Box::new(
             input
                 .map(|row| InitialStage::new_with(context.clone(), row))
                 .unwrap_or_else(|| InitialStage::new_empty(context)),
         ));
-        for executable_stage in executable_stages {
+        let row_limits = match_stage_row_limits(executable_stages);
+        for (executable_stage, row_limit) in executable_stages.iter().zip(row_limits) {
             match executable_stage {
-                ExecutableStage::Match(conjunction_executable) => {
+                ExecutableStage::Match { stage_id, executable } => {
                     let match_stage = MatchStageExecutor::new(
-                        conjunction_executable.clone(),
+                        *stage_id,
+                        executable.clone(),
                         last_stage,
                         executable_functions.clone(),
-                    );
+                    )
+                    .with_row_limit(row_limit);
                     last_stage = ReadPipelineStage::Match(Box::new(match_stage));
                 }
                 ExecutableStage::Insert(_) => {
@@ -177,6 +182,10 @@ impl<Snapshot: ReadableSnapshot + 'static> Pipeline<Snapshot, ReadPipelineStage<
                     let limit_stage = LimitStageExecutor::new(limit_executable.clone(), last_stage);
                     last_stage = ReadPipelineStage::Limit(Box::new(limit_stage));
                 }
+                ExecutableStage::Expand(expand_executable) => {
+                    let expand_stage = ExpandStageExecutor::new(expand_executable.clone(), last_stage);
+                    last_stage = ReadPipelineStage::Expand(Box::new(expand_stage));
+                }
                 ExecutableStage::Require(require_executable) => {
                     let require_stage = RequireStageExecutor::new(require_executable.clone(), last_stage);
                     last_stage = ReadPipelineStage::Require(Box::new(require_stage));
@@ -209,16 +218,20 @@ impl<Snapshot: WritableSnapshot + 'static> Pipeline<Snapshot, WritePipelineStage
         executable_fetch: Option<Arc<ExecutableFetch>>,
         parameters: Arc<ParameterRegistry>,
         query_profile: Arc<QueryProfile>,
+        multiplicity_policy: MultiplicityPolicy,
     ) -> Self {
         let output_variable_positions = executable_stages.last().unwrap().output_row_mapping();
         let context =
-            ExecutionContext::new_with_profile(Arc::new(snapshot), thing_manager, parameters.clone(), query_profile);
+            ExecutionContext::new_with_profile(Arc::new(snapshot), thing_manager, parameters.clone(), query_profile)
+                .with_multiplicity_policy(multiplicity_policy);
         let mut last_stage = WritePipelineStage::Initial(Box::new(InitialStage::new_empty(context)));
-        for executable_stage in executable_stages {
+        let row_limits = match_stage_row_limits(&executable_stages);
+        for (executable_stage, row_limit) in executable_stages.into_iter().zip(row_limits) {
             match executable_stage {
-                ExecutableStage::Match(conjunction_executable) => {
+                ExecutableStage::Match { stage_id, executable } => {
                     let match_stage =
-                        MatchStageExecutor::new(conjunction_executable, last_stage, executable_functions.clone());
+                        MatchStageExecutor::new(stage_id, executable, last_stage, executable_functions.clone())
+                            .with_row_limit(row_limit);
                     last_stage = WritePipelineStage::Match(Box::new(match_stage));
                 }
                 ExecutableStage::Insert(insert_executable) => {
@@ -253,6 +266,10 @@ impl<Snapshot: WritableSnapshot + 'static> Pipeline<Snapshot, WritePipelineStage
                     let limit_stage = LimitStageExecutor::new(limit_executable, last_stage);
                     last_stage = WritePipelineStage::Limit(Box::new(limit_stage));
                 }
+                ExecutableStage::Expand(expand_executable) => {
+                    let expand_stage = ExpandStageExecutor::new(expand_executable, last_stage);
+                    last_stage = WritePipelineStage::Expand(Box::new(expand_stage));
+                }
                 ExecutableStage::Require(require_executable) => {
                     let require_stage = RequireStageExecutor::new(require_executable, last_stage);
                     last_stage = WritePipelineStage::Require(Box::new(require_stage));
@@ -278,6 +295,30 @@ impl<Snapshot: WritableSnapshot + 'static> Pipeline<Snapshot, WritePipelineStage
     }
 }
 
+/// The rows each match stage must produce for a later limit, when only stages that pass rows on one by one lie
+/// between the two. Rows skipped by an offset in between still count against the match stage. A match stage followed
+/// by any other stage, such as a sort or a reduction that consumes every row, has no row limit.
+fn match_stage_row_limits(executable_stages: &[ExecutableStage]) -> Vec<Option<u64>> {
+    (0..executable_stages.len())
+        .map(|index| {
+            if !matches!(executable_stages[index], ExecutableStage::Match { .. }) {
+                return None;
+            }
+            let mut skipped_rows: u64 = 0;
+            for stage in &executable_stages[index + 1..] {
+                match stage {
+                    // an expansion only ever turns a row into several, so the match needs no more rows than the limit
+                    ExecutableStage::Select(_) | ExecutableStage::Expand(_) => (),
+                    ExecutableStage::Offset(offset) => skipped_rows = skipped_rows.saturating_add(offset.offset),
+                    ExecutableStage::Limit(limit) => return Some(limit.limit.saturating_add(skipped_rows)),
+                    _ => return None,
+                }
+            }
+            None
+        })
+        .collect()
+}
+
 typedb_error! {
     pub PipelineError(component = "Pipeline", prefix = "PIP") {
         InvalidReadPipelineStage(1, "{stage} clause cannot exist in a read pipeline.", stage: String ),
@@ -134,7 +134,7 @@ fn match_iterator_for_row<Snapshot: ReadableSnapshot + 'static>(
         function_registry,
         &context.profile,
     )
-    .map_err(|err| Box::new(PipelineExecutionError::InitialisingMatchIterator { typedb_source: err }))?;
+    .map_err(PipelineExecutionError::initialising_match_iterator)?;
     Ok(crate::pipeline::match_::unique_rows(crate::pipeline::match_::as_owned_rows(
         executor.into_iterator(context.clone(), interrupt.clone()),
     ))
@@ -4,12 +4,18 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use concept::{thing::thing_manager::ThingManager, type_::type_manager::TypeManager};
 use ir::pipeline::ParameterRegistry;
 use lending_iterator::LendingIterator;
-use resource::{constants::traversal::BATCH_DEFAULT_CAPACITY, profile::QueryProfile};
+use resource::{
+    constants::traversal::{BATCH_DEFAULT_CAPACITY, FIXED_BATCH_ROWS_MAX},
+    profile::QueryProfile,
+};
 use storage::snapshot::{ReadableSnapshot, WritableSnapshot};
 
 use crate::{
@@ -20,15 +26,16 @@ use crate::{
         insert::InsertStageExecutor,
         match_::{MatchStageExecutor, MatchStageIterator},
         modifiers::{
-            DistinctStageExecutor, DistinctStageIterator, LimitStageExecutor, LimitStageIterator, OffsetStageExecutor,
-            OffsetStageIterator, RequireStageExecutor, RequireStageIterator, SelectStageExecutor, SelectStageIterator,
-            SortStageExecutor, SortStageIterator,
+            DistinctStageExecutor, DistinctStageIterator, ExpandStageExecutor, ExpandStageIterator, LimitStageExecutor,
+            LimitStageIterator, OffsetStageExecutor, OffsetStageIterator, RequireStageExecutor, RequireStageIterator,
+            SelectStageExecutor, SelectStageIterator, SortStageExecutor, SortStageIterator,
         },
         put::PutStageExecutor,
         reduce::ReduceStageExecutor,
         update::UpdateStageExecutor,
         PipelineExecutionError, WrittenRowsIterator,
     },
+    read::{pushdown::StoragePushdown, retry::RetryPolicy},
     row::MaybeOwnedRow,
     ExecutionInterrupt,
 };
@@ -39,8 +46,31 @@ pub struct ExecutionContext<Snapshot> {
     pub thing_manager: Arc<ThingManager>,
     pub parameters: Arc<ParameterRegistry>,
     pub profile: Arc<QueryProfile>,
+    /// Upper bound on the number of cartesian answers an intersection may produce for a single intersection value.
+    pub max_cartesian_group_product: u64,
+    /// Upper bound on the number of tuples each instruction of an intersection buffers in memory to enumerate a
+    /// cartesian product. Instructions with more tuples for the intersection value are re-read from storage instead.
+    pub max_cartesian_prefetch_rows: usize,
+    /// Retries storage reads that fail transiently, instead of failing the query.
+    pub retry_policy: RetryPolicy,
+    /// The number of rows in each batch the executors pass between each other. Larger batches amortise the overhead
+    /// of computing each batch, smaller ones bound the memory held by the executors.
+    pub batch_capacity: u32,
+    /// The checks of the instructions that storage applies while it scans, which the executor skips.
+    pub storage_pushdown: StoragePushdown,
+    /// The rows a downstream limit still takes from the stage executing this context, if any.
+    pub row_limit: Option<RowLimit>,
+    /// Whether rows standing for several answers are expanded before stages that count rows, such as a limit.
+    pub multiplicity_policy: MultiplicityPolicy,
+    /// Upper bound on the work units (intersections sought, combinations assembled, rows checked) an executor spends
+    /// in one call. Once spent, it returns the rows found so far, possibly none, and resumes from the same point on
+    /// the next call, so a single input row cannot hold the thread for long.
+    pub yield_budget: Option<u64>,
 }
 
+pub const DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT: u64 = 1_000_000_000;
+pub const DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS: usize = 1024;
+
 impl<Snapshot> ExecutionContext<Snapshot> {
     pub fn new(snapshot: Arc<Snapshot>, thing_manager: Arc<ThingManager>, parameters: Arc<ParameterRegistry>) -> Self {
         Self::new_with_profile(snapshot, thing_manager, parameters, Arc::new(QueryProfile::new(false)))
@@ -52,7 +82,63 @@ impl<Snapshot> ExecutionContext<Snapshot> {
         parameters: Arc<ParameterRegistry>,
         query_profile: Arc<QueryProfile>,
     ) -> Self {
-        Self { snapshot, thing_manager, parameters, profile: query_profile }
+        Self {
+            snapshot,
+            thing_manager,
+            parameters,
+            profile: query_profile,
+            max_cartesian_group_product: DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT,
+            max_cartesian_prefetch_rows: DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS,
+            retry_policy: RetryPolicy::default(),
+            batch_capacity: FIXED_BATCH_ROWS_MAX,
+            storage_pushdown: StoragePushdown::none(),
+            row_limit: None,
+            multiplicity_policy: MultiplicityPolicy::Preserve,
+            yield_budget: None,
+        }
+    }
+
+    pub fn with_max_cartesian_group_product(self, max_cartesian_group_product: u64) -> Self {
+        Self { max_cartesian_group_product, ..self }
+    }
+
+    pub fn with_max_cartesian_prefetch_rows(self, max_cartesian_prefetch_rows: usize) -> Self {
+        Self { max_cartesian_prefetch_rows, ..self }
+    }
+
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self { retry_policy, ..self }
+    }
+
+    pub fn with_batch_capacity(self, batch_capacity: u32) -> Self {
+        assert!(batch_capacity > 0, "Batches must hold at least one row");
+        Self { batch_capacity, ..self }
+    }
+
+    pub fn with_storage_pushdown(self, storage_pushdown: StoragePushdown) -> Self {
+        Self { storage_pushdown, ..self }
+    }
+
+    pub fn with_multiplicity_policy(self, multiplicity_policy: MultiplicityPolicy) -> Self {
+        Self { multiplicity_policy, ..self }
+    }
+
+    pub fn with_yield_budget(self, yield_budget: u64) -> Self {
+        assert!(yield_budget > 0, "Executors must be able to spend at least one work unit per call");
+        Self { yield_budget: Some(yield_budget), ..self }
+    }
+
+    pub fn with_row_limit(self, row_limit: RowLimit) -> Self {
+        Self { row_limit: Some(row_limit), ..self }
+    }
+
+    /// The number of rows executors fill a batch with: the batch capacity, reduced to the rows still taken by a
+    /// downstream limit. A batch always has room for one row, so that executors keep making progress.
+    pub(crate) fn batch_row_capacity(&self) -> u32 {
+        match &self.row_limit {
+            None => self.batch_capacity,
+            Some(row_limit) => row_limit.remaining().clamp(1, self.batch_capacity as u64) as u32,
+        }
     }
 
     pub(crate) fn clone_with_replaced_parameters(&self, parameters: Arc<ParameterRegistry>) -> Self {
@@ -61,6 +147,15 @@ impl<Snapshot> ExecutionContext<Snapshot> {
             thing_manager: self.thing_manager.clone(),
             parameters,
             profile: self.profile.clone(),
+            max_cartesian_group_product: self.max_cartesian_group_product,
+            max_cartesian_prefetch_rows: self.max_cartesian_prefetch_rows,
+            retry_policy: self.retry_policy.clone(),
+            batch_capacity: self.batch_capacity,
+            storage_pushdown: self.storage_pushdown.clone(),
+            // the rows of a function body are not the rows taken by the limit of the calling pipeline
+            row_limit: None,
+            multiplicity_policy: self.multiplicity_policy,
+            yield_budget: self.yield_budget,
         }
     }
 
@@ -83,16 +178,73 @@ impl<Snapshot> ExecutionContext<Snapshot> {
 
 impl<Snapshot> Clone for ExecutionContext<Snapshot> {
     fn clone(&self) -> Self {
-        let Self { snapshot, thing_manager, parameters, profile } = self;
+        let Self {
+            snapshot,
+            thing_manager,
+            parameters,
+            profile,
+            max_cartesian_group_product,
+            max_cartesian_prefetch_rows,
+            retry_policy,
+            batch_capacity,
+            storage_pushdown,
+            row_limit,
+            multiplicity_policy,
+            yield_budget,
+        } = self;
         Self {
             snapshot: snapshot.clone(),
             thing_manager: thing_manager.clone(),
             parameters: parameters.clone(),
             profile: profile.clone(),
+            max_cartesian_group_product: *max_cartesian_group_product,
+            max_cartesian_prefetch_rows: *max_cartesian_prefetch_rows,
+            retry_policy: retry_policy.clone(),
+            batch_capacity: *batch_capacity,
+            storage_pushdown: storage_pushdown.clone(),
+            row_limit: row_limit.clone(),
+            multiplicity_policy: *multiplicity_policy,
+            yield_budget: *yield_budget,
         }
     }
 }
 
+/// How a pipeline treats rows whose multiplicity stands for several answers when they reach a stage that counts rows,
+/// such as an offset or a limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiplicityPolicy {
+    /// Rows keep their multiplicity, and are counted once each.
+    #[default]
+    Preserve,
+    /// Rows are repeated once for each answer they stand for, failing on a row standing for more than the given
+    /// number of answers.
+    ExpandUpTo(u64),
+}
+
+/// Counts down the rows a pipeline's limit still takes from the match stage feeding it. The count is shared by the
+/// match stage, which records each row it emits as the rows it becomes once its multiplicity is expanded, and the
+/// executors computing those rows, which stop filling a batch once it holds the remaining rows. Executors stay
+/// resumable when they stop early, since an answer may still be dropped before it reaches the limit.
+#[derive(Clone, Debug)]
+pub struct RowLimit {
+    remaining: Arc<AtomicU64>,
+}
+
+impl RowLimit {
+    pub fn new(rows: u64) -> Self {
+        Self { remaining: Arc::new(AtomicU64::new(rows)) }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_rows(&self, count: u64) {
+        let _ =
+            self.remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |rows| Some(rows.saturating_sub(count)));
+    }
+}
+
 pub trait StageAPI<Snapshot> {
     type OutputIterator: StageIterator;
 
@@ -142,6 +294,7 @@ pub enum ReadPipelineStage<Snapshot: ReadableSnapshot + 'static> {
     Distinct(Box<DistinctStageExecutor<ReadPipelineStage<Snapshot>>>),
     Limit(Box<LimitStageExecutor<ReadPipelineStage<Snapshot>>>),
     Offset(Box<OffsetStageExecutor<ReadPipelineStage<Snapshot>>>),
+    Expand(Box<ExpandStageExecutor<ReadPipelineStage<Snapshot>>>),
     Require(Box<RequireStageExecutor<ReadPipelineStage<Snapshot>>>),
     Reduce(Box<ReduceStageExecutor<ReadPipelineStage<Snapshot>>>),
 }
@@ -153,6 +306,7 @@ pub enum ReadStageIterator<Snapshot: ReadableSnapshot + 'static> {
     Distinct(Box<DistinctStageIterator<ReadStageIterator<Snapshot>>>),
     Limit(Box<LimitStageIterator<ReadStageIterator<Snapshot>>>),
     Offset(Box<OffsetStageIterator<ReadStageIterator<Snapshot>>>),
+    Expand(Box<ExpandStageIterator<ReadStageIterator<Snapshot>>>),
     Select(Box<SelectStageIterator<ReadStageIterator<Snapshot>>>),
     Require(Box<RequireStageIterator<ReadStageIterator<Snapshot>>>),
     Reduce(Box<WrittenRowsIterator>), // TODO: ReduceStageIterator<ReadStageIterator<Snapshot>>>
@@ -189,6 +343,10 @@ impl<Snapshot: ReadableSnapshot + 'static> StageAPI<Snapshot> for ReadPipelineSt
                 let (iterator, snapshot) = stage.into_iterator(interrupt)?;
                 Ok((ReadStageIterator::Offset(Box::new(iterator)), snapshot))
             }
+            ReadPipelineStage::Expand(stage) => {
+                let (iterator, snapshot) = stage.into_iterator(interrupt)?;
+                Ok((ReadStageIterator::Expand(Box::new(iterator)), snapshot))
+            }
             ReadPipelineStage::Limit(stage) => {
                 let (iterator, snapshot) = stage.into_iterator(interrupt)?;
                 Ok((ReadStageIterator::Limit(Box::new(iterator)), snapshot))
@@ -219,6 +377,7 @@ impl<Snapshot: ReadableSnapshot + 'static> LendingIterator for ReadStageIterator
             ReadStageIterator::Sort(iterator) => iterator.next(),
             ReadStageIterator::Distinct(iterator) => iterator.next(),
             ReadStageIterator::Offset(iterator) => iterator.next(),
+            ReadStageIterator::Expand(iterator) => iterator.next(),
             ReadStageIterator::Limit(iterator) => iterator.next(),
             ReadStageIterator::Select(iterator) => iterator.next(),
             ReadStageIterator::Require(iterator) => iterator.next(),
@@ -235,6 +394,7 @@ impl<Snapshot: ReadableSnapshot + 'static> StageIterator for ReadStageIterator<S
             ReadStageIterator::Sort(iterator) => iterator.collect_owned(),
             ReadStageIterator::Distinct(iterator) => iterator.collect_owned(),
             ReadStageIterator::Offset(iterator) => iterator.collect_owned(),
+            ReadStageIterator::Expand(iterator) => iterator.collect_owned(),
             ReadStageIterator::Limit(iterator) => iterator.collect_owned(),
             ReadStageIterator::Select(iterator) => iterator.collect_owned(),
             ReadStageIterator::Require(iterator) => iterator.collect_owned(),
@@ -253,6 +413,7 @@ pub enum WritePipelineStage<Snapshot: WritableSnapshot + 'static> {
     Sort(Box<SortStageExecutor<WritePipelineStage<Snapshot>>>),
     Limit(Box<LimitStageExecutor<WritePipelineStage<Snapshot>>>),
     Offset(Box<OffsetStageExecutor<WritePipelineStage<Snapshot>>>),
+    Expand(Box<ExpandStageExecutor<WritePipelineStage<Snapshot>>>),
     Select(Box<SelectStageExecutor<WritePipelineStage<Snapshot>>>),
     Require(Box<RequireStageExecutor<WritePipelineStage<Snapshot>>>),
     Distinct(Box<DistinctStageExecutor<WritePipelineStage<Snapshot>>>),
@@ -310,6 +471,10 @@ impl<Snapshot: WritableSnapshot + 'static> StageAPI<Snapshot> for WritePipelineS
                 let (iterator, snapshot) = stage.into_iterator(interrupt)?;
                 Ok((WriteStageIterator::Offset(Box::new(iterator)), snapshot))
             }
+            WritePipelineStage::Expand(stage) => {
+                let (iterator, snapshot) = stage.into_iterator(interrupt)?;
+                Ok((WriteStageIterator::Expand(Box::new(iterator)), snapshot))
+            }
             WritePipelineStage::Select(stage) => {
                 let (iterator, snapshot) = stage.into_iterator(interrupt)?;
                 Ok((WriteStageIterator::Select(Box::new(iterator)), snapshot))
@@ -334,6 +499,7 @@ pub enum WriteStageIterator<Snapshot: WritableSnapshot + 'static> {
     Distinct(Box<DistinctStageIterator<WriteStageIterator<Snapshot>>>),
     Limit(Box<LimitStageIterator<WriteStageIterator<Snapshot>>>),
     Offset(Box<OffsetStageIterator<WriteStageIterator<Snapshot>>>),
+    Expand(Box<ExpandStageIterator<WriteStageIterator<Snapshot>>>),
     Select(Box<SelectStageIterator<WriteStageIterator<Snapshot>>>),
     Require(Box<RequireStageIterator<WriteStageIterator<Snapshot>>>),
     Reduce(WrittenRowsIterator),
@@ -351,6 +517,7 @@ impl<Snapshot: WritableSnapshot + 'static> LendingIterator for WriteStageIterato
             WriteStageIterator::Distinct(iterator) => iterator.next(),
             WriteStageIterator::Limit(iterator) => iterator.next(),
             WriteStageIterator::Offset(iterator) => iterator.next(),
+            WriteStageIterator::Expand(iterator) => iterator.next(),
             WriteStageIterator::Select(iterator) => iterator.next(),
             WriteStageIterator::Require(iterator) => iterator.next(),
             WriteStageIterator::Reduce(iterator) => iterator.next(),
@@ -368,6 +535,7 @@ impl<Snapshot: WritableSnapshot + 'static> StageIterator for WriteStageIterator<
             WriteStageIterator::Distinct(iterator) => iterator.collect_owned(),
             WriteStageIterator::Limit(iterator) => iterator.collect_owned(),
             WriteStageIterator::Offset(iterator) => iterator.collect_owned(),
+            WriteStageIterator::Expand(iterator) => iterator.collect_owned(),
             WriteStageIterator::Select(iterator) => iterator.collect_owned(),
             WriteStageIterator::Require(iterator) => iterator.collect_owned(),
             WriteStageIterator::Reduce(iterator) => iterator.collect_owned(),
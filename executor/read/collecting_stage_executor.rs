@@ -56,6 +56,13 @@ impl CollectingStageExecutor {
         self.pattern_mut().reset()
     }
 
+    pub(crate) fn is_snapshot_independent(&self) -> bool {
+        match self {
+            CollectingStageExecutor::Reduce { pattern, .. } => pattern.is_snapshot_independent(),
+            CollectingStageExecutor::Sort { pattern, .. } => pattern.is_snapshot_independent(),
+        }
+    }
+
     pub(crate) fn prepare(&mut self, batch: FixedBatch) {
         debug_assert!({
             match self {
@@ -150,10 +157,11 @@ impl CollectorTrait for ReduceCollector {
         }
     }
 
-    fn into_iterator(self, _context: &ExecutionContext<impl ReadableSnapshot>) -> CollectedStageIterator {
+    fn into_iterator(self, context: &ExecutionContext<impl ReadableSnapshot>) -> CollectedStageIterator {
         CollectedStageIterator::Reduce(ReduceStageIterator::new(
             self.active_reducer.finalise().into_iterator(),
             self.output_width,
+            context.batch_capacity,
         ))
     }
 }
@@ -162,17 +170,18 @@ impl CollectorTrait for ReduceCollector {
 pub(super) struct ReduceStageIterator {
     batch_row_iterator: BatchRowIterator,
     output_width: u32,
+    batch_capacity: u32,
 }
 
 impl ReduceStageIterator {
-    fn new(batch: BatchRowIterator, output_width: u32) -> Self {
-        Self { batch_row_iterator: batch, output_width }
+    fn new(batch: BatchRowIterator, output_width: u32, batch_capacity: u32) -> Self {
+        Self { batch_row_iterator: batch, output_width, batch_capacity }
     }
 }
 
 impl CollectedStageIteratorTrait for ReduceStageIterator {
     fn batch_continue(&mut self) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        let mut next_batch = FixedBatch::new(self.output_width);
+        let mut next_batch = FixedBatch::new(self.output_width, self.batch_capacity);
         while !next_batch.is_full() {
             if let Some(row) = self.batch_row_iterator.next() {
                 next_batch.append(|mut output_row| output_row.copy_from_row(row));
@@ -219,7 +228,11 @@ impl CollectorTrait for SortCollector {
         let step_profile = profile.extend_or_get(0, || String::from("Sort execution"));
         let sorted_indices =
             unsorted.indices_sorted_by(context, &sort_on, step_profile.storage_counters()).into_iter().peekable();
-        CollectedStageIterator::Sort(SortStageIterator { unsorted, sorted_indices })
+        CollectedStageIterator::Sort(SortStageIterator {
+            unsorted,
+            sorted_indices,
+            batch_capacity: context.batch_capacity,
+        })
     }
 }
 
@@ -227,14 +240,15 @@ impl CollectorTrait for SortCollector {
 pub struct SortStageIterator {
     unsorted: Batch,
     sorted_indices: Peekable<std::vec::IntoIter<usize>>,
+    batch_capacity: u32,
 }
 
 impl CollectedStageIteratorTrait for SortStageIterator {
     fn batch_continue(&mut self) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        let Self { unsorted, sorted_indices } = self;
+        let Self { unsorted, sorted_indices, batch_capacity } = self;
         if sorted_indices.peek().is_some() {
             let width = unsorted.get_row(0).len();
-            let mut next_batch = FixedBatch::new(width as u32);
+            let mut next_batch = FixedBatch::new(width as u32, *batch_capacity);
             while !next_batch.is_full() && sorted_indices.peek().is_some() {
                 let index = sorted_indices.next().unwrap();
                 next_batch.append(|mut copy_to_row| {
@@ -27,7 +27,7 @@ use compiler::annotation::expression::{
     },
 };
 use encoding::value::value::{NativeValueConvertible, Value};
-use ir::{pattern::ParameterID, pipeline::ParameterRegistry};
+use ir::pipeline::ParameterRegistry;
 use resource::profile::StorageCounters;
 use storage::snapshot::ReadableSnapshot;
 
@@ -86,27 +86,24 @@ impl From<ExpressionValue> for VariableValue<'static> {
 
 pub struct ExpressionExecutorState<'this> {
     stack: Vec<ExpressionValue>,
-    variables: Box<[ExpressionValue]>,
+    variables: Vec<ExpressionValue>,
     next_variable_index: usize,
-    constants: &'this [ParameterID],
+    constants: &'this [Value<'static>],
     next_constant_index: usize,
-    parameter_registry: &'this ParameterRegistry,
 }
 
 impl<'this> ExpressionExecutorState<'this> {
-    fn new(
-        variables: Box<[ExpressionValue]>,
-        constants: &'this [ParameterID],
-        parameter_registry: &'this ParameterRegistry,
-    ) -> Self {
-        Self {
-            stack: Vec::new(),
-            variables,
-            next_variable_index: 0,
-            constants,
-            next_constant_index: 0,
-            parameter_registry,
-        }
+    fn new(variables: Vec<ExpressionValue>, constants: &'this [Value<'static>]) -> Self {
+        Self { stack: Vec::new(), variables, next_variable_index: 0, constants, next_constant_index: 0 }
+    }
+
+    /// Discards the state of the previous evaluation, so the expression can be evaluated again over `variables`.
+    fn reset(&mut self, variables: impl IntoIterator<Item = ExpressionValue>) {
+        self.stack.clear();
+        self.variables.clear();
+        self.variables.extend(variables);
+        self.next_variable_index = 0;
+        self.next_constant_index = 0;
     }
 
     fn push_value(&mut self, value: Value<'static>) {
@@ -138,12 +135,16 @@ impl<'this> ExpressionExecutorState<'this> {
     }
 
     fn next_constant(&mut self) -> Value<'static> {
-        let constant = self.parameter_registry.value_unchecked(self.constants[self.next_constant_index]).clone();
+        let constant = self.constants[self.next_constant_index].clone();
         self.next_constant_index += 1;
         constant
     }
 }
 
+fn resolve_constants<ID>(compiled: &ExecutableExpression<ID>, parameters: &ParameterRegistry) -> Vec<Value<'static>> {
+    compiled.constants().iter().map(|&constant| parameters.value_unchecked(constant).clone()).collect()
+}
+
 pub fn evaluate_expression<ID: Hash + Eq>(
     compiled: &ExecutableExpression<ID>,
     input: HashMap<ID, ExpressionValue>,
@@ -154,13 +155,39 @@ pub fn evaluate_expression<ID: Hash + Eq>(
         variables.push(input.get(v).unwrap().clone());
     }
 
-    let mut state = ExpressionExecutorState::new(variables.into_boxed_slice(), compiled.constants(), parameters);
+    let constants = resolve_constants(compiled, parameters);
+    let mut state = ExpressionExecutorState::new(variables, &constants);
     for instr in compiled.instructions() {
         evaluate_instruction(instr, &mut state)?;
     }
     Ok(state.stack.pop().unwrap())
 }
 
+/// Evaluates the expression for each of `row_count` rows, given a column holding the value of each variable in every
+/// row. The constants are resolved and the evaluation state is allocated once for the whole batch, rather than for
+/// every row. Returns the column of results, or the error of the first row that fails to evaluate.
+pub fn evaluate_expression_batch<ID: Hash + Eq>(
+    compiled: &ExecutableExpression<ID>,
+    input: HashMap<ID, Vec<ExpressionValue>>,
+    row_count: usize,
+    parameters: &ParameterRegistry,
+) -> Result<Vec<ExpressionValue>, ExpressionEvaluationError> {
+    let columns = compiled.variables().iter().map(|v| input.get(v).unwrap().as_slice()).collect::<Vec<_>>();
+    debug_assert!(columns.iter().all(|column| column.len() == row_count));
+
+    let constants = resolve_constants(compiled, parameters);
+    let mut state = ExpressionExecutorState::new(Vec::with_capacity(columns.len()), &constants);
+    let mut outputs = Vec::with_capacity(row_count);
+    for row in 0..row_count {
+        state.reset(columns.iter().map(|column| column[row].clone()));
+        for instr in compiled.instructions() {
+            evaluate_instruction(instr, &mut state)?;
+        }
+        outputs.push(state.stack.pop().unwrap());
+    }
+    Ok(outputs)
+}
+
 fn evaluate_instruction(
     op_code: &ExpressionOpCode,
     state: &mut ExpressionExecutorState<'_>,
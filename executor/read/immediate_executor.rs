@@ -4,31 +4,36 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{cmp::Ordering, collections::HashMap, fmt, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, fmt, iter::zip, mem, slice, sync::Arc};
 
 use answer::variable_value::VariableValue;
 use compiler::{
     annotation::expression::compiled_expression::ExecutableExpression,
     executable::match_::{
-        instructions::{CheckInstruction, ConstraintInstruction, VariableModes},
-        planner::conjunction_executable::{AssignmentStep, CheckStep, IntersectionStep, UnsortedJoinStep},
+        instructions::{CheckInstruction, ConstraintInstruction, VariableMode, VariableModes},
+        planner::conjunction_executable::{
+            AssignmentStep, CheckStep, IntersectionStep, ResultSemantics, UnsortedJoinStep,
+        },
     },
     ExecutorVariable, VariablePosition,
 };
 use concept::{error::ConceptReadError, thing::thing_manager::ThingManager};
-use error::{unimplemented_feature, UnimplementedFeature};
 use itertools::Itertools;
 use lending_iterator::{LendingIterator, Peekable};
-use resource::profile::StepProfile;
+use resource::profile::{InstructionProfile, StepProfile};
 use storage::snapshot::ReadableSnapshot;
 
 use crate::{
     batch::{FixedBatch, FixedBatchRowIterator},
     error::ReadExecutionError,
-    instruction::{iterator::TupleIterator, Checker, InstructionExecutor},
+    instruction::{
+        iterator::{write_tuple_values, TupleIterator},
+        tuple::{self, Tuple},
+        Checker, InstructionExecutor,
+    },
     pipeline::stage::ExecutionContext,
     read::{
-        expression_executor::{evaluate_expression, ExpressionValue},
+        expression_executor::{evaluate_expression, evaluate_expression_batch, ExpressionValue},
         step_executor::StepExecutors,
     },
     row::{MaybeOwnedRow, Row},
@@ -52,17 +57,34 @@ impl From<ImmediateExecutor> for StepExecutors {
 impl ImmediateExecutor {
     pub(crate) fn new_intersection(
         step: &IntersectionStep,
+        result_semantics: ResultSemantics,
         snapshot: &Arc<impl ReadableSnapshot + 'static>,
         thing_manager: &Arc<ThingManager>,
         profile: Arc<StepProfile>,
     ) -> Result<Self, Box<ConceptReadError>> {
-        let IntersectionStep { sort_variable, instructions, selected_variables, output_width, .. } = step;
+        let IntersectionStep {
+            sort_variable,
+            secondary_sort_variable,
+            instructions,
+            selected_variables,
+            output_width,
+            dedup_on_sort_variable,
+            per_group_limit,
+            checks,
+            ..
+        } = step;
 
         let executor = IntersectionExecutor::new(
             *sort_variable,
+            *secondary_sort_variable,
             instructions.clone(),
+            checks.clone(),
             *output_width,
             selected_variables.clone(),
+            step.uses_single_instruction_fast_path(),
+            *dedup_on_sort_variable,
+            per_group_limit.clone().map(|(group_positions, limit)| GroupLimiter::new(group_positions, limit)),
+            result_semantics,
             snapshot,
             thing_manager,
             profile,
@@ -72,18 +94,28 @@ impl ImmediateExecutor {
 
     pub(crate) fn new_unsorted_join(
         step: &UnsortedJoinStep,
+        snapshot: &Arc<impl ReadableSnapshot + 'static>,
+        thing_manager: &Arc<ThingManager>,
         step_profile: Arc<StepProfile>,
     ) -> Result<Self, Box<ConceptReadError>> {
-        return Err(Box::new(ConceptReadError::UnimplementedFunctionality {
-            functionality: UnimplementedFeature::UnsortedJoin,
-        }));
-        let UnsortedJoinStep { iterate_instruction, check_instructions, output_width, .. } = step;
+        let UnsortedJoinStep {
+            iterate_instruction,
+            iterate_variable_modes,
+            check_instructions,
+            selected_variables,
+            output_width,
+            ..
+        } = step;
         let executor = UnsortedJoinExecutor::new(
             iterate_instruction.clone(),
+            iterate_variable_modes.clone(),
             check_instructions.clone(),
+            selected_variables.clone(),
             *output_width,
+            snapshot,
+            thing_manager,
             step_profile,
-        );
+        )?;
         Ok(Self::UnsortedJoin(executor))
     }
 
@@ -121,6 +153,25 @@ impl ImmediateExecutor {
         }
     }
 
+    pub(crate) fn restart(&mut self) {
+        match self {
+            ImmediateExecutor::SortedJoin(sorted) => sorted.restart(),
+            ImmediateExecutor::UnsortedJoin(unsorted) => unsorted.restart(),
+            ImmediateExecutor::Assignment(assignment) => assignment.reset(),
+            ImmediateExecutor::Check(check) => check.reset(),
+        }
+    }
+
+    /// Whether the executor holds no iterators, tuples or instances read from the snapshot of a previous execution.
+    pub(crate) fn is_snapshot_independent(&self) -> bool {
+        match self {
+            ImmediateExecutor::SortedJoin(sorted) => sorted.is_snapshot_independent(),
+            ImmediateExecutor::UnsortedJoin(unsorted) => unsorted.is_snapshot_independent(),
+            ImmediateExecutor::Assignment(assignment) => assignment.input.is_none(),
+            ImmediateExecutor::Check(check) => check.input.is_none(),
+        }
+    }
+
     pub(crate) fn prepare(
         &mut self,
         input_batch: FixedBatch,
@@ -151,19 +202,40 @@ impl ImmediateExecutor {
 /// Performs an n-way intersection/join using sorted iterators.
 /// To avoid missing cartesian outputs when multiple variables are unbound, the executor can leverage a
 /// Cartesian sub-program, which generates all cartesian answers within one intersection, if there are any.
+/// When the step joins on a secondary sort variable as well, the iterators are intersected on the pair of their
+/// first two unbound values.
+/// Under exists semantics, each batch holds a single row, so the consumer can stop at the first answer without the
+/// intersection having produced the rest of a batch.
+/// A single instruction without checks has nothing to intersect with, so its tuples are streamed directly into the
+/// output batch instead.
+/// When deduplicating on the sort variable, only the first answer for each sort value is emitted.
+/// With a per-group limit, the remaining answers for an input row are skipped once its group has produced enough.
+/// Checks attached to the step are evaluated on every assembled row, including each cartesian combination, and the
+/// rows they reject are never emitted.
+/// After a transient storage error, the iterators of the current input row are recreated and moved past the answers
+/// already produced for it, which are found again in the same order within the same snapshot.
+/// Once the yield budget of a call is spent, the rows found so far are returned, and the next call continues from the
+/// current intersection or cartesian combination.
 pub(crate) struct IntersectionExecutor {
     instruction_executors: Vec<InstructionExecutor>,
+    instruction_profiles: Vec<Arc<InstructionProfile>>,
+    checker: Checker<()>,
+    check_positions: Vec<VariablePosition>,
+    join_width: usize,
     output_width: u32,
     outputs_selected: SelectedPositions,
+    is_probe: bool,
+    is_single_instruction: bool,
+    dedup_on_sort_variable: bool,
+    unselected_instruction_positions: Vec<VariablePosition>,
+    input_copy_positions: Vec<VariablePosition>,
 
     iterators: Vec<TupleIterator>,
+    intersection: Intersection,
     cartesian_iterator: CartesianIterator,
     input: Option<Peekable<FixedBatchRowIterator>>,
-
-    intersection_value: VariableValue<'static>,
-    intersection_row: Vec<VariableValue<'static>>,
-    intersection_multiplicity: u64,
-    intersection_provenance: Provenance,
+    input_row_progress: InputRowProgress,
+    control: StepControl,
 
     profile: Arc<StepProfile>,
 }
@@ -177,41 +249,107 @@ impl fmt::Debug for IntersectionExecutor {
 impl IntersectionExecutor {
     fn new(
         sort_variable: ExecutorVariable,
+        secondary_sort_variable: Option<ExecutorVariable>,
         instructions: Vec<(ConstraintInstruction<ExecutorVariable>, VariableModes)>,
+        checks: Vec<CheckInstruction<ExecutorVariable>>,
         output_width: u32,
         select_variables: Vec<VariablePosition>,
+        is_single_instruction: bool,
+        dedup_on_sort_variable: bool,
+        group_limiter: Option<GroupLimiter>,
+        result_semantics: ResultSemantics,
         snapshot: &Arc<impl ReadableSnapshot + 'static>,
         thing_manager: &Arc<ThingManager>,
         profile: Arc<StepProfile>,
     ) -> Result<Self, Box<ConceptReadError>> {
         let instruction_count = instructions.len();
+        // the instruction writes all of its variables, but only the selected ones may appear in the output
+        let mut unselected_instruction_positions = Vec::new();
+        if is_single_instruction {
+            instructions[0].0.used_variables_foreach(|var| {
+                if let Some(position) = var.as_position() {
+                    if position.as_usize() < output_width as usize
+                        && !select_variables.contains(&position)
+                        && !unselected_instruction_positions.contains(&position)
+                    {
+                        unselected_instruction_positions.push(position);
+                    }
+                }
+            });
+        }
         let executors: Vec<InstructionExecutor> = instructions
             .into_iter()
             .map(|(instruction, variable_modes)| {
                 InstructionExecutor::new(instruction, variable_modes, &**snapshot, thing_manager, sort_variable)
             })
             .try_collect()?;
+        let instruction_profiles = executors
+            .iter()
+            .enumerate()
+            .map(|(index, executor)| {
+                profile.extend_or_get_instruction(index, || {
+                    format!("{} {}", executor.name(), executor).trim_end().to_owned()
+                })
+            })
+            .collect_vec();
+        let join_width = if secondary_sort_variable.is_some() { 2 } else { 1 };
+        let mut check_positions = Vec::new();
+        for check in &checks {
+            check.variables_foreach(|var| {
+                if let Some(position) = var.as_position() {
+                    if !check_positions.contains(&position) {
+                        check_positions.push(position);
+                    }
+                }
+            });
+        }
+        // checked variables need not be selected, so the assembled rows must be wide enough to hold them too
+        let row_width =
+            check_positions.iter().map(|position| position.as_usize() + 1).fold(output_width as usize, usize::max);
 
         Ok(Self {
             instruction_executors: executors,
+            instruction_profiles: instruction_profiles.clone(),
+            checker: Checker::new(checks, HashMap::new()),
+            check_positions,
+            join_width,
             output_width,
             outputs_selected: SelectedPositions::new(select_variables),
+            is_probe: result_semantics == ResultSemantics::Exists,
+            is_single_instruction,
+            dedup_on_sort_variable,
+            unselected_instruction_positions,
+            input_copy_positions: Vec::new(),
             iterators: Vec::with_capacity(instruction_count),
-            cartesian_iterator: CartesianIterator::new(output_width as usize, instruction_count, profile.clone()),
+            intersection: Intersection::new(join_width, row_width),
+            cartesian_iterator: CartesianIterator::new(row_width, join_width, instruction_profiles, profile.clone()),
             input: None,
-            intersection_value: VariableValue::None,
-            intersection_row: vec![VariableValue::None; output_width as usize],
-            intersection_multiplicity: 1,
-            intersection_provenance: Provenance::INITIAL,
+            input_row_progress: InputRowProgress::new(group_limiter),
+            control: StepControl::default(),
             profile,
         })
     }
 
     fn reset(&mut self) {
         self.input = None;
+        self.input_row_progress.answers = 0;
         self.iterators.clear();
     }
 
+    fn restart(&mut self) {
+        self.reset();
+        self.cartesian_iterator.clear();
+        self.input_row_progress.clear();
+        self.instruction_executors.iter_mut().for_each(InstructionExecutor::clear_instance_cache);
+    }
+
+    fn is_snapshot_independent(&self) -> bool {
+        self.input.is_none()
+            && self.iterators.is_empty()
+            && self.cartesian_iterator.is_cleared()
+            && !self.instruction_executors.iter().any(InstructionExecutor::has_instance_cache)
+    }
+
     fn prepare(
         &mut self,
         input_batch: FixedBatch,
@@ -220,8 +358,20 @@ impl IntersectionExecutor {
         let measurement = self.profile.start_measurement();
         debug_assert!(self.input.is_none() || self.input.as_mut().unwrap().peek().is_none());
         self.input = Some(Peekable::new(FixedBatchRowIterator::new(Ok(input_batch))));
+        self.input_row_progress.answers = 0;
         debug_assert!(self.input.as_mut().unwrap().peek().is_some());
-        self.may_create_intersection_iterators(context)?;
+        let profile = self.profile.clone();
+        retrying(
+            self,
+            context,
+            &profile,
+            |executor| {
+                // no answers were produced for the input row yet, so its iterators are simply created again
+                executor.iterators.clear();
+                Ok(())
+            },
+            |executor| executor.may_create_intersection_iterators(context),
+        )?;
         measurement.end(&self.profile, 0, 0);
         Ok(())
     }
@@ -229,90 +379,275 @@ impl IntersectionExecutor {
     fn batch_continue(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
-        _interrupt: &mut ExecutionInterrupt,
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        self.may_compute_next_batch(context)
+        continue_batch(self, context, interrupt)
     }
 
-    fn may_compute_next_batch(
+    /// Recreates the iterators of the current input row, and moves them past the answers already produced for it.
+    fn restart_input_row(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
-    ) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        let measurement = self.profile.start_measurement();
-        let output = if self.compute_next_row(context)? {
+    ) -> Result<(), ReadExecutionError> {
+        self.iterators.clear();
+        self.cartesian_iterator.clear();
+        self.cartesian_iterator.is_active = false;
+        if self.input.as_mut().unwrap().peek().is_none() {
+            return Ok(());
+        }
+        self.may_create_intersection_iterators(context)?;
+        if self.iterators.is_empty() {
+            return Ok(());
+        }
+        // replaying the answers already produced for one input row is neither interrupted nor yields
+        let mut interrupt = ExecutionInterrupt::new_uninterruptible();
+        let yield_budget = mem::take(&mut self.control.yield_budget);
+        let replayed = self.replay_input_row_answers(context, &mut interrupt);
+        self.control.yield_budget = yield_budget;
+        replayed
+    }
+
+    fn replay_input_row_answers(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        for _ in 0..self.input_row_progress.answers {
+            if !self.is_single_instruction {
+                let found = self.compute_next_row_in_group(context, interrupt)?;
+                debug_assert!(found, "An answer produced for the input row was not found again");
+            } else if self.dedup_on_sort_variable {
+                let sort_value = peek_sort_value(&mut self.iterators[0], &self.instruction_profiles[0])?;
+                skip_sort_value(&mut self.iterators[0], &sort_value, &self.instruction_profiles[0])?;
+            } else {
+                self.instruction_profiles[0].record_advance();
+                self.iterators[0]
+                    .advance_past()
+                    .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compute_next_intersection_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        loop {
+            if batch.as_ref().is_some_and(|batch| self.is_probe || batch.is_full())
+                || !self.compute_next_row(context, interrupt)?
+            {
+                break;
+            }
             // don't allocate batch until 1 answer is confirmed
-            let mut batch = FixedBatch::new(self.output_width);
+            let batch = batch.get_or_insert_with(|| FixedBatch::new(self.output_width, context.batch_row_capacity()));
             batch.append(|mut row| self.write_next_row_into(&mut row));
-            while !batch.is_full() && self.compute_next_row(context)? {
-                batch.append(|mut row| self.write_next_row_into(&mut row));
+        }
+        Ok(())
+    }
+
+    fn compute_next_single_instruction_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        while self.input.as_mut().unwrap().peek().is_some() {
+            if self.control.next_unit(interrupt)? {
+                break;
             }
-            Some(batch)
-        } else {
-            None
-        };
-        measurement.end(&self.profile, 1, output.as_ref().map(|batch| batch.len()).unwrap_or(0) as u64);
-        Ok(output)
+            if self.input_row_progress.is_group_exhausted() {
+                self.advance_input(context)?;
+                continue;
+            }
+            let Some(iterator) = self.iterators.first_mut() else {
+                self.advance_input(context)?;
+                continue;
+            };
+            match iterator.peek() {
+                None => {
+                    self.advance_input(context)?;
+                    continue;
+                }
+                Some(Err(err)) => return Err(ReadExecutionError::ConceptRead { typedb_source: err.clone() }),
+                Some(Ok(_)) => (),
+            }
+            let instruction_profile = &self.instruction_profiles[0];
+            let sort_value = match self.dedup_on_sort_variable {
+                true => Some(peek_sort_value(iterator, instruction_profile)?),
+                false => None,
+            };
+            let input_row = self.input.as_mut().unwrap().peek().unwrap().as_ref().map_err(|&err| err.clone())?;
+            let batch = batch.get_or_insert_with(|| FixedBatch::new(self.output_width, context.batch_row_capacity()));
+            let multiplicity = batch.append(|mut row| {
+                iterator.write_values(&mut row);
+                for &position in &self.unselected_instruction_positions {
+                    row.unset(position);
+                }
+                for &position in &self.input_copy_positions {
+                    // as in `record_intersection`, values produced by the instruction take precedence over inputs
+                    if row.get(position).is_empty() {
+                        row.set(position, input_row.get(position).clone().into_owned());
+                    }
+                }
+                row.set_provenance(self.intersection.provenance);
+                let multiplicity = match &sort_value {
+                    Some(sort_value) => skip_sort_value(iterator, sort_value, instruction_profile).map(|()| 1),
+                    None => {
+                        instruction_profile.record_advance();
+                        iterator.advance_past().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })
+                    }
+                };
+                if let Ok(multiplicity) = multiplicity {
+                    row.set_multiplicity(multiplicity as u64);
+                }
+                multiplicity
+            });
+            if let Err(err) = multiplicity {
+                // the row is produced again once the iterator is restarted
+                batch.remove_last();
+                return Err(err);
+            }
+            self.input_row_progress.record_answer();
+            if self.is_probe || batch.is_full() {
+                break;
+            }
+        }
+        Ok(())
     }
 
     fn write_next_row_into(&mut self, row: &mut Row<'_>) {
         if self.cartesian_iterator.is_active() {
-            self.cartesian_iterator.write_into(row, &self.outputs_selected);
+            self.cartesian_iterator.write_into(row, &self.outputs_selected, &mut self.iterators);
         } else {
-            row.set_multiplicity(self.intersection_multiplicity);
+            // without a cartesian product, the intersection row is recorded afresh before it is read again
+            row.set_multiplicity(self.intersection.multiplicity);
             for &position in &self.outputs_selected.selected {
-                let value = self.intersection_row[position.as_usize()].clone();
+                let value = mem::replace(&mut self.intersection.row[position.as_usize()], VariableValue::None);
                 row.set(position, value);
             }
         }
-        row.set_provenance(self.intersection_provenance);
+        row.set_provenance(self.intersection.provenance);
     }
 
     fn compute_next_row(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<bool, ReadExecutionError> {
+        if self.input_row_progress.is_group_exhausted() && self.input.as_mut().unwrap().peek().is_some() {
+            self.advance_input(context)?;
+        }
+        let found = self.compute_next_row_in_group(context, interrupt)?;
+        if found {
+            self.input_row_progress.record_answer();
+        }
+        Ok(found)
+    }
+
+    fn compute_next_row_in_group(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<bool, ReadExecutionError> {
         if self.cartesian_iterator.is_active() {
-            let found = self.cartesian_iterator.find_next(context, &self.instruction_executors)?;
+            let found = loop {
+                // the cartesian iterator keeps its position, so the product continues from the next combination
+                if self.control.yield_budget.spend() {
+                    return Ok(false);
+                }
+                if !self.cartesian_iterator.find_next(
+                    context,
+                    &self.instruction_executors,
+                    &mut self.iterators,
+                    interrupt,
+                )? {
+                    break false;
+                }
+                let combination = self.cartesian_iterator.assemble_combination(&mut self.iterators);
+                if satisfies_checks(&self.checker, context, combination, &self.profile)? {
+                    break true;
+                }
+            };
             if found {
+                self.profile.record_cartesian_row();
                 Ok(true)
             } else {
                 // advance the first iterator past the intersection point to move to the next intersection
                 let iter = &mut self.iterators[0];
-                while iter
-                    .peek_first_unbound_value()
-                    .transpose()
-                    .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-                    .is_some_and(|value| value == &self.intersection_value)
-                {
+                let instruction_profile = &self.instruction_profiles[0];
+                if let Some(resume_key) = self.cartesian_iterator.peek_join_key_of(0) {
+                    // the cartesian iterator over the same instruction has already stepped past the intersection
+                    // point, so seek straight to where it stopped instead of re-reading the same tuples
+                    let resume_key =
+                        resume_key.map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+                    instruction_profile.record_advance();
+                    iter.seek_join_key(resume_key)
+                        .map_err(|err| ReadExecutionError::AdvancingIteratorTo { typedb_source: err })?;
+                }
+                while is_at_join_key(iter, &self.intersection.key, instruction_profile)? {
+                    instruction_profile.record_advance();
                     iter.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
                 }
-                self.compute_next_row(context)
+                self.compute_next_row_in_group(context, interrupt)
             }
         } else {
             while self.input.as_mut().unwrap().peek().is_some() {
+                if self.control.next_unit(interrupt)? {
+                    return Ok(false);
+                }
                 let found = self.find_intersection()?;
                 if found {
                     self.record_intersection()?;
+                    if !satisfies_checks(&self.checker, context, &self.intersection.row, &self.profile)? {
+                        // other combinations of the values at this intersection may still be accepted
+                        self.advance_intersection_iterators_with_multiplicity()?;
+                        self.may_activate_cartesian(context)?;
+                        if self.cartesian_iterator.is_active() {
+                            return self.compute_next_row_in_group(context, interrupt);
+                        }
+                        continue;
+                    }
+                    if self.dedup_on_sort_variable {
+                        // the remaining answers for this sort value would be discarded by the downstream distinct
+                        skip_sort_value(
+                            &mut self.iterators[0],
+                            &self.intersection.key[0],
+                            &self.instruction_profiles[0],
+                        )?;
+                        return Ok(true);
+                    }
                     self.advance_intersection_iterators_with_multiplicity()?;
                     self.may_activate_cartesian(context)?;
                     return Ok(true);
                 } else {
-                    self.iterators.clear();
-                    self.cartesian_iterator.clear();
-                    while self.iterators.is_empty() {
-                        let _ = self.input.as_mut().unwrap().next().unwrap().map_err(|err| err.clone());
-                        if self.input.as_mut().unwrap().peek().is_some() {
-                            self.may_create_intersection_iterators(context)?;
-                        } else {
-                            break;
-                        }
-                    }
+                    self.advance_input(context)?;
                 }
             }
             Ok(false)
         }
     }
 
+    fn advance_input(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        self.iterators.clear();
+        self.cartesian_iterator.clear();
+        self.input_row_progress.answers = 0;
+        while self.iterators.is_empty() {
+            let _ = self.input.as_mut().unwrap().next().unwrap().map_err(|err| err.clone());
+            if self.input.as_mut().unwrap().peek().is_some() {
+                self.may_create_intersection_iterators(context)?;
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     fn find_intersection(&mut self) -> Result<bool, ReadExecutionError> {
         if self.iterators.is_empty() {
             return Ok(false);
@@ -342,13 +677,15 @@ impl IntersectionExecutor {
                     (containing_i, containing_max, 0, current_max_index)
                 };
                 let iterator = &mut containing_max[max_index];
-                let current_max = iterator.peek_first_unbound_value().unwrap().unwrap();
-                let max_cmp_peek = match containing_i[i_index].peek_first_unbound_value() {
+                self.instruction_profiles[current_max_index].record_peeked_key();
+                let current_max = iterator.peek_join_key(self.join_width).unwrap().unwrap();
+                self.instruction_profiles[i].record_peeked_key();
+                let max_cmp_peek = match containing_i[i_index].peek_join_key(self.join_width) {
                     None => {
                         failed = true;
                         break;
                     }
-                    Some(Ok(value)) => current_max.partial_cmp(value).unwrap(),
+                    Some(Ok(value)) => tuple::total_cmp_values(current_max, value),
                     Some(Err(err)) => return Err(ReadExecutionError::ConceptRead { typedb_source: err.clone() }),
                 };
 
@@ -360,19 +697,19 @@ impl IntersectionExecutor {
                     Ordering::Equal => (),
                     Ordering::Greater => {
                         let iter_i = &mut containing_i[i_index];
+                        self.instruction_profiles[i].record_advance();
                         let next_value_cmp = iter_i
-                            .advance_until_first_unbound_is(current_max)
+                            .seek_join_key(current_max)
                             .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
                         match next_value_cmp {
                             None => {
                                 failed = true;
                                 break;
                             }
-                            Some(Ordering::Less) => {
-                                unreachable!("Skip to should always be empty or equal/greater than the target")
-                            }
                             Some(Ordering::Equal) => {}
-                            Some(Ordering::Greater) => {
+                            Some(Ordering::Greater | Ordering::Less) => {
+                                // the iterator overshot the current max (it never reports falling short, but
+                                // re-entering the merge from it is correct either way)
                                 current_max_index = i;
                                 retry = true;
                             }
@@ -398,14 +735,27 @@ impl IntersectionExecutor {
         let peek = self.input.as_mut().unwrap().peek();
         if let Some(input) = peek {
             let next_row: &MaybeOwnedRow<'_> = input.as_ref().map_err(|err| (*err).clone())?;
-            self.intersection_provenance = next_row.provenance();
-            for executor in &self.instruction_executors {
+            if !self.input_row_progress.enter_group(next_row) {
+                // the group of this input row was exhausted by an earlier one
+                return Ok(());
+            }
+            self.intersection.provenance = next_row.provenance();
+            if self.is_single_instruction {
+                self.input_copy_positions.clear();
+                self.input_copy_positions.extend(
+                    self.outputs_selected.selected.iter().filter(|&&position| {
+                        position.as_usize() < next_row.len() && !next_row.get(position).is_empty()
+                    }),
+                );
+            }
+            for (executor, instruction_profile) in zip(&self.instruction_executors, &self.instruction_profiles) {
                 let mut iterator = executor
                     .get_iterator(context, next_row.as_reference(), self.profile.storage_counters())
                     .map_err(|err| ReadExecutionError::CreatingIterator {
                         instruction_name: executor.name().to_string(),
                         typedb_source: err,
                     })?;
+                instruction_profile.record_open();
                 if iterator.peek().is_none() {
                     self.iterators.clear();
                     return Ok(());
@@ -426,11 +776,12 @@ impl IntersectionExecutor {
         //       If 1 iterator has no more answers after Owner1, then the other also just has to finish the Owner1 count
         //       and we can short-circuit evaluating this set of iterators based on the current input!
         let mut multiplicity: u64 = 1;
-        for iter in &mut self.iterators {
+        for (iter, instruction_profile) in zip(&mut self.iterators, &self.instruction_profiles) {
+            instruction_profile.record_advance();
             multiplicity *=
                 iter.advance_past().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })? as u64;
         }
-        self.intersection_multiplicity = multiplicity;
+        self.intersection.multiplicity = multiplicity;
         Ok(())
     }
 
@@ -440,36 +791,38 @@ impl IntersectionExecutor {
 
     fn all_iterators_intersect(&mut self) -> bool {
         let (first, rest) = self.iterators.split_at_mut(1);
-        let peek_0 = first[0].peek_first_unbound_value().unwrap().unwrap();
-        rest.iter_mut().all(|iter| iter.peek_first_unbound_value().unwrap().unwrap() == peek_0)
+        let peek_0 = first[0].peek_join_key(self.join_width).unwrap().unwrap();
+        rest.iter_mut()
+            .all(|iter| tuple::total_cmp_values(iter.peek_join_key(self.join_width).unwrap().unwrap(), peek_0).is_eq())
     }
 
     fn record_intersection(&mut self) -> Result<(), ReadExecutionError> {
-        self.intersection_value = VariableValue::None;
-        self.intersection_row.fill(VariableValue::None);
+        self.intersection.key.fill(VariableValue::None);
+        self.intersection.row.fill(VariableValue::None);
         let mut provenance = Provenance::INITIAL;
-        let mut row = Row::new(&mut self.intersection_row, &mut self.intersection_multiplicity, &mut provenance);
-        for iter in &mut self.iterators {
-            if !self.intersection_value.is_empty() {
-                iter.peek_first_unbound_value()
+        let mut row = Row::new(&mut self.intersection.row, &mut self.intersection.multiplicity, &mut provenance);
+        for (iter, instruction_profile) in zip(&mut self.iterators, &self.instruction_profiles) {
+            instruction_profile.record_peeked_key();
+            if !self.intersection.key[0].is_empty() {
+                iter.peek_join_key(self.join_width)
                     .transpose()
                     .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-                    .inspect(|&value| assert_eq!(value, &self.intersection_value));
+                    .inspect(|&key| assert_eq!(key, self.intersection.key));
             } else {
-                let value = iter
-                    .peek_first_unbound_value()
+                let key = iter
+                    .peek_join_key(self.join_width)
                     .transpose()
                     .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
-                if let Some(value) = value {
-                    self.intersection_value = value.to_owned();
+                if let Some(key) = key {
+                    self.intersection.key.iter_mut().zip(key).for_each(|(value, key)| *value = key.to_owned());
                 }
             }
             iter.write_values(&mut row)
         }
-        assert!(!self.intersection_value.is_empty());
+        assert!(self.intersection.key.iter().all(|value| !value.is_empty()));
 
         let input_row = self.input.as_mut().unwrap().peek().unwrap().as_ref().map_err(|&err| err.clone())?;
-        for &position in &self.outputs_selected {
+        for &position in self.outputs_selected.selected.iter().chain(&self.check_positions) {
             // note: some input variable positions are re-used across stages, so we should only copy
             //       inputs into the output row if it is not already populated by the intersection
             if position.as_usize() < input_row.len()
@@ -479,7 +832,7 @@ impl IntersectionExecutor {
                 row.set(position, input_row.get(position).clone().into_owned())
             }
         }
-        self.intersection_multiplicity = 1;
+        self.intersection.multiplicity = 1;
         Ok(())
     }
 
@@ -492,13 +845,8 @@ impl IntersectionExecutor {
             return Ok(());
         }
         let mut cartesian = false;
-        for iter in &mut self.iterators {
-            if iter
-                .peek_first_unbound_value()
-                .transpose()
-                .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-                .is_some_and(|value| value == &self.intersection_value)
-            {
+        for (iter, instruction_profile) in zip(&mut self.iterators, &self.instruction_profiles) {
+            if is_at_join_key(iter, &self.intersection.key, instruction_profile)? {
                 cartesian = true;
                 break;
             }
@@ -510,39 +858,430 @@ impl IntersectionExecutor {
             self.cartesian_iterator.activate(
                 context,
                 &self.instruction_executors,
-                &self.intersection_value,
+                &self.intersection.key,
                 input_row,
-                &self.intersection_row,
-                self.intersection_multiplicity,
+                &self.intersection.row,
+                self.intersection.multiplicity,
                 &mut self.iterators,
-            )?
+            )?;
+            if !self.cartesian_iterator.reuses_intersection_iterator() {
+                self.check_cartesian_group_size(context.max_cartesian_group_product)?;
+            }
         }
         Ok(())
     }
+
+    fn check_cartesian_group_size(&mut self, limit: u64) -> Result<(), ReadExecutionError> {
+        // The cartesian iterator owns separate iterators, so we can consume the remainder of the group from the
+        // intersection iterators. We stop counting as soon as the limit is exceeded, to bound the work done.
+        let mut estimated: u64 = 1;
+        for &index in &self.cartesian_iterator.cartesian_executor_indices {
+            let iter = &mut self.iterators[index];
+            let instruction_profile = &self.instruction_profiles[index];
+            let mut group_size: u64 = 1;
+            while estimated.saturating_mul(group_size) <= limit
+                && is_at_join_key(iter, &self.intersection.key, instruction_profile)?
+            {
+                instruction_profile.record_advance();
+                iter.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+                group_size += 1;
+            }
+            estimated = estimated.saturating_mul(group_size);
+            if estimated > limit {
+                return Err(ReadExecutionError::CartesianGroupTooLarge {
+                    step: self.instruction_executors.iter().map(|executor| executor.name()).join(", "),
+                    intersection_value: self.intersection.key.iter().join(", "),
+                    estimated,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BatchComputation for IntersectionExecutor {
+    fn control(&mut self) -> &mut StepControl {
+        &mut self.control
+    }
+
+    fn profile(&self) -> &Arc<StepProfile> {
+        &self.profile
+    }
+
+    fn compute_next_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        if self.is_single_instruction {
+            self.compute_next_single_instruction_rows(context, batch, interrupt)
+        } else {
+            self.compute_next_intersection_rows(context, batch, interrupt)
+        }
+    }
+
+    fn restart_after_error(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        self.restart_input_row(context)
+    }
+}
+
+/// The values the iterators of an intersection agree on, and the row assembled from their tuples and the input row.
+/// A cartesian product over the tuples at the intersection starts from this row.
+struct Intersection {
+    key: Vec<VariableValue<'static>>,
+    row: Vec<VariableValue<'static>>,
+    multiplicity: u64,
+    provenance: Provenance,
+}
+
+impl Intersection {
+    fn new(join_width: usize, row_width: usize) -> Self {
+        Self {
+            key: vec![VariableValue::None; join_width],
+            row: vec![VariableValue::None; row_width],
+            multiplicity: 1,
+            provenance: Provenance::INITIAL,
+        }
+    }
+}
+
+fn satisfies_checks(
+    checker: &Checker<()>,
+    context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    row: &[VariableValue<'static>],
+    profile: &StepProfile,
+) -> Result<bool, ReadExecutionError> {
+    if checker.checks.is_empty() {
+        return Ok(true);
+    }
+    let (multiplicity, provenance) = (1, Provenance::INITIAL);
+    let row = MaybeOwnedRow::new_borrowed(row, &multiplicity, &provenance);
+    checker.filter_fn_for_row(context, &row, profile.storage_counters())(&Ok(()))
+        .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })
+}
+
+fn peek_sort_value(
+    iterator: &mut TupleIterator,
+    instruction_profile: &InstructionProfile,
+) -> Result<VariableValue<'static>, ReadExecutionError> {
+    instruction_profile.record_peeked_key();
+    match iterator.peek_join_key(1) {
+        Some(Ok(key)) => Ok(key[0].clone().into_owned()),
+        Some(Err(err)) => Err(ReadExecutionError::ConceptRead { typedb_source: err }),
+        None => unreachable!("The sort value is only read from an iterator with a next tuple"),
+    }
+}
+
+/// Peeks whether the iterator is at the given join key, recording the peek against the instruction.
+fn is_at_join_key(
+    iterator: &mut TupleIterator,
+    key: &[VariableValue<'_>],
+    instruction_profile: &InstructionProfile,
+) -> Result<bool, ReadExecutionError> {
+    instruction_profile.record_peeked_key();
+    Ok(iterator
+        .peek_join_key(key.len())
+        .transpose()
+        .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
+        .is_some_and(|peeked| peeked == key))
+}
+
+/// Advances the iterator past every tuple with the given value of the sort variable.
+fn skip_sort_value(
+    iterator: &mut TupleIterator,
+    sort_value: &VariableValue<'_>,
+    instruction_profile: &InstructionProfile,
+) -> Result<(), ReadExecutionError> {
+    while is_at_join_key(iterator, slice::from_ref(sort_value), instruction_profile)? {
+        instruction_profile.record_advance();
+        iterator.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+    }
+    Ok(())
+}
+
+/// The answers produced for the current input row, which are replayed after a transient error, and for the group of
+/// input rows it belongs to when the answers of each group are limited.
+#[derive(Debug)]
+struct InputRowProgress {
+    answers: u64,
+    group_limiter: Option<GroupLimiter>,
+}
+
+impl InputRowProgress {
+    fn new(group_limiter: Option<GroupLimiter>) -> Self {
+        Self { answers: 0, group_limiter }
+    }
+
+    fn clear(&mut self) {
+        self.answers = 0;
+        self.group_limiter.iter_mut().for_each(GroupLimiter::clear);
+    }
+
+    /// Moves to the group of the given input row, returning whether that group may still produce answers.
+    fn enter_group(&mut self, row: &MaybeOwnedRow<'_>) -> bool {
+        self.group_limiter.as_mut().is_none_or(|group_limiter| group_limiter.enter(row))
+    }
+
+    fn record_answer(&mut self) {
+        self.answers += 1;
+        self.group_limiter.iter_mut().for_each(GroupLimiter::record_row);
+    }
+
+    fn is_group_exhausted(&self) -> bool {
+        self.group_limiter.as_ref().is_some_and(GroupLimiter::is_exhausted)
+    }
+}
+
+/// Counts the rows produced for the current group of input rows, which share their values at the group positions.
+#[derive(Debug)]
+struct GroupLimiter {
+    group_positions: Vec<VariablePosition>,
+    limit: u64,
+    group: Option<Vec<VariableValue<'static>>>,
+    produced: u64,
+}
+
+impl GroupLimiter {
+    fn new(group_positions: Vec<VariablePosition>, limit: u64) -> Self {
+        Self { group_positions, limit, group: None, produced: 0 }
+    }
+
+    fn clear(&mut self) {
+        self.group = None;
+        self.produced = 0;
+    }
+
+    /// Moves to the group of the given input row, returning whether that group may still produce rows.
+    fn enter(&mut self, row: &MaybeOwnedRow<'_>) -> bool {
+        let value_at = |position: VariablePosition| match position.as_usize() < row.len() {
+            true => row.get(position).as_reference(),
+            false => VariableValue::None,
+        };
+        let is_same_group = self.group.as_ref().is_some_and(|group| {
+            self.group_positions.iter().zip(group).all(|(&position, value)| &value_at(position) == value)
+        });
+        if !is_same_group {
+            self.group = Some(self.group_positions.iter().map(|&position| value_at(position).into_owned()).collect());
+            self.produced = 0;
+        }
+        !self.is_exhausted()
+    }
+
+    fn record_row(&mut self) {
+        self.produced += 1;
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.produced >= self.limit
+    }
+}
+
+/// Checks for an interrupt once every `CHECK_INTERVAL` polls, since each check may take a lock: loops that can run over
+/// many rows without producing any poll on every iteration.
+#[derive(Debug, Default)]
+struct InterruptPoller {
+    polls_since_check: usize,
+}
+
+impl InterruptPoller {
+    const CHECK_INTERVAL: usize = 1024;
+
+    fn poll(&mut self, interrupt: &mut ExecutionInterrupt) -> Result<(), ReadExecutionError> {
+        self.polls_since_check += 1;
+        if self.polls_since_check < Self::CHECK_INTERVAL {
+            return Ok(());
+        }
+        self.polls_since_check = 0;
+        match interrupt.check() {
+            Some(interrupt) => Err(ReadExecutionError::Interrupted { interrupt }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Counts the work units an executor spends within one call against the `yield_budget` of the execution context. Once
+/// the budget is spent, the executor stops at a point it resumes from on the next call, and returns the rows found so
+/// far.
+#[derive(Debug, Default)]
+struct YieldBudget {
+    remaining: Option<u64>,
+    is_spent: bool,
+}
+
+impl YieldBudget {
+    fn start<Snapshot>(&mut self, context: &ExecutionContext<Snapshot>) {
+        self.remaining = context.yield_budget;
+        self.is_spent = false;
+    }
+
+    /// Spends a work unit, or returns `true` if the budget of this call is already spent and the executor must yield.
+    fn spend(&mut self) -> bool {
+        match &mut self.remaining {
+            None => false,
+            Some(0) => {
+                self.is_spent = true;
+                true
+            }
+            Some(remaining) => {
+                *remaining -= 1;
+                false
+            }
+        }
+    }
+
+    /// The output of a call that may have yielded. A yield without any rows returns an empty batch rather than `None`,
+    /// which would end the executor's output for its input.
+    fn finish(&self, batch: Option<FixedBatch>, profile: &StepProfile) -> Option<FixedBatch> {
+        if !self.is_spent {
+            return batch;
+        }
+        profile.record_yield();
+        Some(batch.unwrap_or_else(FixedBatch::empty))
+    }
+}
+
+/// The interrupt polling and yield budget of an immediate executor, which it consults before each unit of work.
+#[derive(Debug, Default)]
+struct StepControl {
+    interrupt_poller: InterruptPoller,
+    yield_budget: YieldBudget,
+}
+
+impl StepControl {
+    /// Polls for an interrupt and spends a unit of work. Returns `true` if the budget of this call is already spent and
+    /// the executor must yield.
+    fn next_unit(&mut self, interrupt: &mut ExecutionInterrupt) -> Result<bool, ReadExecutionError> {
+        self.interrupt_poller.poll(interrupt)?;
+        Ok(self.yield_budget.spend())
+    }
+}
+
+/// An immediate executor that computes the rows of a batch a unit of work at a time, within a call that
+/// `continue_batch` profiles, budgets and retries.
+trait BatchComputation {
+    fn control(&mut self) -> &mut StepControl;
+
+    fn profile(&self) -> &Arc<StepProfile>;
+
+    /// Appends the next rows to `batch`, consulting the `StepControl` before each unit of work and returning once it
+    /// must yield. After an error, `batch` holds the rows computed before it.
+    fn compute_next_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError>;
+
+    /// Restores the executor after a transient error, so that computing the next rows continues from the failed read
+    /// without producing any row twice.
+    fn restart_after_error(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError>;
+}
+
+/// Computes the next batch of `executor`, retrying the transient errors the retry policy allows. The rows of a batch
+/// interrupted by an error are kept, to be completed after the retry.
+fn continue_batch(
+    executor: &mut impl BatchComputation,
+    context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    interrupt: &mut ExecutionInterrupt,
+) -> Result<Option<FixedBatch>, ReadExecutionError> {
+    let profile = executor.profile().clone();
+    let measurement = profile.start_measurement();
+    executor.control().yield_budget.start(context);
+    let mut batch = None;
+    retrying(
+        executor,
+        context,
+        &profile,
+        |executor| executor.restart_after_error(context),
+        |executor| executor.compute_next_rows(context, &mut batch, interrupt),
+    )?;
+    let batch = batch.filter(|batch| !batch.is_empty());
+    measurement.end(&profile, 1, batch.as_ref().map(|batch| batch.len()).unwrap_or(0) as u64);
+    Ok(executor.control().yield_budget.finish(batch, &profile))
+}
+
+/// Runs `compute`, and again after `restart` for each transient error the retry policy allows.
+fn retrying<Executor, T>(
+    executor: &mut Executor,
+    context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    profile: &StepProfile,
+    mut restart: impl FnMut(&mut Executor) -> Result<(), ReadExecutionError>,
+    mut compute: impl FnMut(&mut Executor) -> Result<T, ReadExecutionError>,
+) -> Result<T, ReadExecutionError> {
+    let mut result = compute(executor);
+    let mut attempt = 1;
+    loop {
+        match result {
+            Err(err) if context.retry_policy.may_retry(&err, attempt) => {
+                context.retry_policy.wait_before_retry(attempt, profile);
+                attempt += 1;
+                result = restart(executor).and_then(|()| compute(executor));
+            }
+            result => return result,
+        }
+    }
 }
 
-// TODO: prefetch all data involved in the cartesian instead of pinging Rocks
+/// Enumerates the cartesian product of the tuples that the instructions of an intersection produce for one intersection
+/// value. The tuples of each instruction are buffered in memory when there are at most
+/// `max_cartesian_prefetch_rows` of them, so the product is enumerated without further storage reads. Instructions
+/// with more tuples are enumerated by re-opening their iterators at the intersection value instead. When a single
+/// instruction has more tuples, its intersection iterator is advanced through them without opening any other.
 struct CartesianIterator {
     is_active: bool,
-    intersection_value: VariableValue<'static>,
+    intersection_key: Vec<VariableValue<'static>>,
     input_row: Vec<VariableValue<'static>>,
     intersection_source: Vec<VariableValue<'static>>,
     intersection_multiplicity: u64,
+    combination: Vec<VariableValue<'static>>,
     cartesian_executor_indices: Vec<usize>,
     iterators: Vec<Option<TupleIterator>>,
+    prefetched: Vec<Option<PrefetchedTuples>>,
+    reuses_intersection_iterator: bool,
+    is_at_next_combination: bool,
+    combinations: u64,
+    interrupt_poller: InterruptPoller,
+    instruction_profiles: Vec<Arc<InstructionProfile>>,
     profile: Arc<StepProfile>,
 }
 
+/// The tuples of one instruction at the intersection value, and the one in the current combination.
+struct PrefetchedTuples {
+    tuples: Vec<Tuple<'static>>,
+    cursor: usize,
+}
+
 impl CartesianIterator {
-    fn new(width: usize, iterator_executor_count: usize, profile: Arc<StepProfile>) -> Self {
+    fn new(
+        width: usize,
+        join_width: usize,
+        instruction_profiles: Vec<Arc<InstructionProfile>>,
+        profile: Arc<StepProfile>,
+    ) -> Self {
+        let iterator_executor_count = instruction_profiles.len();
         CartesianIterator {
             is_active: false,
-            intersection_value: VariableValue::None,
+            intersection_key: vec![VariableValue::None; join_width],
             input_row: vec![VariableValue::None; width],
             intersection_source: vec![VariableValue::None; width],
             intersection_multiplicity: 1,
+            combination: vec![VariableValue::None; width],
             cartesian_executor_indices: Vec::with_capacity(iterator_executor_count),
             iterators: (0..iterator_executor_count).map(|_| Option::None).collect_vec(),
+            prefetched: (0..iterator_executor_count).map(|_| Option::None).collect_vec(),
+            reuses_intersection_iterator: false,
+            is_at_next_combination: false,
+            combinations: 0,
+            interrupt_poller: InterruptPoller::default(),
+            instruction_profiles,
             profile,
         }
     }
@@ -551,110 +1290,226 @@ impl CartesianIterator {
         self.is_active
     }
 
+    /// Whether the product is enumerated by the intersection iterator of its only instruction, which then cannot be
+    /// counted ahead of the enumeration.
+    fn reuses_intersection_iterator(&self) -> bool {
+        self.reuses_intersection_iterator
+    }
+
     fn clear(&mut self) {
         self.iterators.iter_mut().for_each(|iter| drop(iter.take()));
+        self.prefetched.iter_mut().for_each(|prefetched| drop(prefetched.take()));
+    }
+
+    fn is_cleared(&self) -> bool {
+        self.iterators.iter().all(Option::is_none) && self.prefetched.iter().all(Option::is_none)
     }
 
     fn activate(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         iterator_executors: &[InstructionExecutor],
-        source_intersection_value: &VariableValue<'static>,
+        source_intersection_key: &[VariableValue<'static>],
         input_row: &[VariableValue<'static>],
         source_intersection: &[VariableValue<'static>],
         source_multiplicity: u64,
         intersection_iterators: &mut [TupleIterator],
     ) -> Result<(), ReadExecutionError> {
-        // TODO: there's room for an optimisation here: we don't have to re-open a new iterator when only have 1 cartesian iterator!
-        //       we can just advance it linearly through the answers, and not cost another lookup
         debug_assert!(source_intersection.len() == self.intersection_source.len());
         self.is_active = true;
+        self.profile.record_cartesian_activation();
         self.input_row[..input_row.len()].clone_from_slice(input_row);
         self.intersection_source.clone_from_slice(source_intersection);
-        self.intersection_value = source_intersection_value.clone();
+        self.intersection_key.clone_from_slice(source_intersection_key);
         self.intersection_multiplicity = source_multiplicity;
 
         // we are able to re-use existing iterators since they should only move forward. We only reset the indices
         self.cartesian_executor_indices.clear();
-
         for (index, iter) in intersection_iterators.iter_mut().enumerate() {
-            if iter
-                .peek_first_unbound_value()
-                .transpose()
-                .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-                .is_some_and(|value| value == source_intersection_value)
-            {
+            if is_at_join_key(iter, source_intersection_key, &self.instruction_profiles[index])? {
                 self.cartesian_executor_indices.push(index);
+            }
+        }
 
-                // reopen/move existing cartesian iterators forward to the intersection point if we can
-                let preexisting_iterator = self.iterators[index].take();
-                let iterator = match preexisting_iterator {
-                    None => self.reopen_iterator(context, &iterator_executors[index])?,
-                    Some(mut iter) => match iter.peek_first_unbound_value() {
-                        None => self.reopen_iterator(context, &iterator_executors[index])?,
-                        Some(Ok(value)) => {
-                            if value < source_intersection_value {
-                                iter.advance_until_first_unbound_is(source_intersection_value)
-                                    .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
-                                debug_assert_eq!(
-                                    iter.peek_first_unbound_value().unwrap().unwrap(),
-                                    source_intersection_value
-                                );
-                                iter
-                            } else if value == source_intersection_value {
-                                iter
-                            } else {
-                                self.reopen_iterator(context, &iterator_executors[index])?
+        // with a single instruction left at the intersection point there is nothing to combine: its intersection
+        // iterator already points at the next answer, and is advanced linearly instead of reopening another one
+        self.reuses_intersection_iterator = self.cartesian_executor_indices.len() == 1;
+        if self.reuses_intersection_iterator {
+            self.is_at_next_combination = true;
+            self.combinations = 1;
+            return Ok(());
+        }
+
+        let cartesian_executor_indices = mem::take(&mut self.cartesian_executor_indices);
+        for &index in &cartesian_executor_indices {
+            // reopen/move existing cartesian iterators forward to the intersection point if we can
+            let preexisting_iterator = self.iterators[index].take();
+            let instruction_profile = self.instruction_profiles[index].clone();
+            if preexisting_iterator.is_some() {
+                instruction_profile.record_peeked_key();
+            }
+            let mut iterator = match preexisting_iterator {
+                None => self.reopen_iterator(context, iterator_executors, index)?,
+                Some(mut iter) => match iter.peek_join_key(source_intersection_key.len()) {
+                    None => self.reopen_iterator(context, iterator_executors, index)?,
+                    Some(Ok(key)) => {
+                        if key < source_intersection_key {
+                            instruction_profile.record_advance();
+                            let ordering = iter
+                                .seek_join_key(source_intersection_key)
+                                .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+                            match ordering {
+                                Some(Ordering::Equal) => iter,
+                                // the intersection key is no longer where the intersection iterator saw it
+                                _ => self.reopen_iterator(context, iterator_executors, index)?,
                             }
+                        } else if key == source_intersection_key {
+                            iter
+                        } else {
+                            self.reopen_iterator(context, iterator_executors, index)?
                         }
-                        Some(Err(err)) => {
-                            return Err(ReadExecutionError::ConceptRead { typedb_source: err });
-                        }
-                    },
-                };
-                self.iterators[index] = Some(iterator);
+                    }
+                    Some(Err(err)) => {
+                        return Err(ReadExecutionError::ConceptRead { typedb_source: err });
+                    }
+                },
+            };
+            let prefetched = self.prefetch(&mut iterator, &instruction_profile, context.max_cartesian_prefetch_rows)?;
+            if prefetched.is_none() && context.max_cartesian_prefetch_rows > 0 {
+                // the limit was exceeded after consuming part of the intersection, so stream it from the start
+                iterator = self.reopen_iterator(context, iterator_executors, index)?;
             }
+            self.prefetched[index] = prefetched;
+            self.iterators[index] = Some(iterator);
         }
+        self.cartesian_executor_indices = cartesian_executor_indices;
         Ok(())
     }
 
+    /// Reads the tuples of the iterator at the intersection value into memory, unless there are more than `limit`.
+    fn prefetch(
+        &self,
+        iterator: &mut TupleIterator,
+        instruction_profile: &InstructionProfile,
+        limit: usize,
+    ) -> Result<Option<PrefetchedTuples>, ReadExecutionError> {
+        let mut tuples = Vec::new();
+        while is_at_join_key(iterator, &self.intersection_key, instruction_profile)? {
+            if tuples.len() == limit {
+                return Ok(None);
+            }
+            let tuple = match iterator.peek() {
+                Some(Ok(tuple)) => tuple.clone().into_owned(),
+                Some(Err(err)) => return Err(ReadExecutionError::ConceptRead { typedb_source: err.clone() }),
+                None => unreachable!("The iterator was just peeked at the intersection value"),
+            };
+            tuples.push(tuple);
+            instruction_profile.record_advance();
+            iterator.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+        }
+        Ok(Some(PrefetchedTuples { tuples, cursor: 0 }))
+    }
+
     fn find_next(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
         executors: &[InstructionExecutor],
+        intersection_iterators: &mut [TupleIterator],
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<bool, ReadExecutionError> {
         debug_assert!(self.is_active);
+        if self.reuses_intersection_iterator {
+            return self.find_next_in_intersection_iterator(context, executors, intersection_iterators);
+        }
         // precondition: all required iterators are open to the intersection point
 
         let mut executor_index = self.cartesian_executor_indices.len() - 1;
         loop {
+            self.interrupt_poller.poll(interrupt)?;
             let iterator_index = self.cartesian_executor_indices[executor_index];
-            let iter = self.iterators[iterator_index].as_mut().unwrap();
-            iter.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
-            if !iter
-                .peek_first_unbound_value()
-                .transpose()
-                .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-                .is_some_and(|value| value == &self.intersection_value)
-            {
-                if executor_index == 0 {
-                    self.is_active = false;
-                    return Ok(false);
-                } else {
-                    let reopened = self.reopen_iterator(context, &executors[executor_index])?;
-                    self.iterators[iterator_index] = Some(reopened);
-                    executor_index -= 1;
+            let has_next = match &mut self.prefetched[iterator_index] {
+                Some(prefetched) => {
+                    prefetched.cursor += 1;
+                    if prefetched.cursor >= prefetched.tuples.len() {
+                        prefetched.cursor = 0;
+                    }
+                    prefetched.cursor != 0
                 }
-            } else {
+                None => {
+                    let iter = self.iterators[iterator_index].as_mut().unwrap();
+                    let instruction_profile = &self.instruction_profiles[iterator_index];
+                    instruction_profile.record_advance();
+                    iter.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+                    is_at_join_key(iter, &self.intersection_key, instruction_profile)?
+                }
+            };
+            if has_next {
                 return Ok(true);
+            } else if executor_index == 0 {
+                self.is_active = false;
+                return Ok(false);
+            } else {
+                if self.prefetched[iterator_index].is_none() {
+                    let reopened = self.reopen_iterator(context, executors, iterator_index)?;
+                    self.iterators[iterator_index] = Some(reopened);
+                }
+                executor_index -= 1;
             }
         }
     }
 
+    fn find_next_in_intersection_iterator(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        executors: &[InstructionExecutor],
+        intersection_iterators: &mut [TupleIterator],
+    ) -> Result<bool, ReadExecutionError> {
+        let iter = &mut intersection_iterators[self.cartesian_executor_indices[0]];
+        let instruction_profile = &self.instruction_profiles[self.cartesian_executor_indices[0]];
+        if self.is_at_next_combination {
+            self.is_at_next_combination = false;
+        } else {
+            instruction_profile.record_advance();
+            iter.advance_single().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+        }
+        if !is_at_join_key(iter, &self.intersection_key, instruction_profile)? {
+            self.is_active = false;
+            return Ok(false);
+        }
+        // the answers of the product are counted as they are enumerated, since counting them first would consume them
+        self.combinations += 1;
+        let limit = context.max_cartesian_group_product;
+        if self.combinations > limit {
+            return Err(ReadExecutionError::CartesianGroupTooLarge {
+                step: executors.iter().map(|executor| executor.name()).join(", "),
+                intersection_value: self.intersection_key.iter().join(", "),
+                estimated: self.combinations,
+                limit,
+            });
+        }
+        Ok(true)
+    }
+
+    /// Peeks the join key of the cartesian iterator opened for the instruction at `iterator_index`, if there is one.
+    fn peek_join_key_of(
+        &mut self,
+        iterator_index: usize,
+    ) -> Option<Result<&[VariableValue<'_>], Box<ConceptReadError>>> {
+        if self.reuses_intersection_iterator {
+            return None;
+        }
+        let join_width = self.intersection_key.len();
+        let iterator = self.iterators.get_mut(iterator_index)?.as_mut()?;
+        self.instruction_profiles[iterator_index].record_peeked_key();
+        iterator.peek_join_key(join_width)
+    }
+
+    /// Reopens the iterator of the instruction at `index` at the intersection value, recording the reopen against it.
     fn reopen_iterator(
         &self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
-        executor: &InstructionExecutor,
+        executors: &[InstructionExecutor],
+        index: usize,
     ) -> Result<TupleIterator, ReadExecutionError> {
         /*
         TODO: this re-opens an iterator to contribute towards a cartesian product.
@@ -684,32 +1539,65 @@ impl CartesianIterator {
 
               Ideally, we could use the bound Person1 as input to the getIterator to make sure we stick in the right range.
          */
-        let mut reopened = executor
+        let mut reopened = executors[index]
             .get_iterator(
                 context,
                 MaybeOwnedRow::new_borrowed(&self.input_row, &1, &Provenance::INITIAL),
                 self.profile.storage_counters(),
             )
             .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
-        // TODO: use seek()
+        let instruction_profile = &self.instruction_profiles[index];
+        instruction_profile.record_reopen();
+        instruction_profile.record_advance();
+        self.profile.record_cartesian_reopen();
         reopened
-            .advance_until_first_unbound_is(&self.intersection_value)
+            .seek_join_key(&self.intersection_key)
             .map_err(|err| ReadExecutionError::AdvancingIteratorTo { typedb_source: err })?;
         Ok(reopened)
     }
 
-    fn write_into(&mut self, row: &mut Row<'_>, outputs_selected: &SelectedPositions) {
+    /// Assembles the full row of the current combination, including the variables that are not selected.
+    fn assemble_combination(&mut self, intersection_iterators: &mut [TupleIterator]) -> &[VariableValue<'static>] {
+        self.combination.clone_from_slice(&self.intersection_source);
+        let mut multiplicity = self.intersection_multiplicity;
+        let mut provenance = Provenance::INITIAL;
+        let mut row = Row::new(&mut self.combination, &mut multiplicity, &mut provenance);
         for &executor_index in &self.cartesian_executor_indices {
-            let iterator = self.iterators[executor_index].as_mut().unwrap();
-            iterator.write_values(row);
+            let (iterator, prefetched) = match self.reuses_intersection_iterator {
+                true => (&mut intersection_iterators[executor_index], None),
+                false => (self.iterators[executor_index].as_mut().unwrap(), self.prefetched[executor_index].as_ref()),
+            };
+            write_combination_values(iterator, prefetched, &mut row);
         }
-        for pos in (0..self.intersection_source.len() as u32)
-            .map(VariablePosition::new)
-            .filter(|i| !outputs_selected.selected.contains(i))
-        {
+        &self.combination
+    }
+
+    fn write_into(
+        &mut self,
+        row: &mut Row<'_>,
+        outputs_selected: &SelectedPositions,
+        intersection_iterators: &mut [TupleIterator],
+    ) {
+        if self.reuses_intersection_iterator && self.is_at_next_combination {
+            // the intersection itself is the first answer, and the intersection iterator has already moved past it
+            for &position in &outputs_selected.selected {
+                row.set(position, self.intersection_source[position.as_usize()].clone());
+            }
+            row.set_multiplicity(self.intersection_multiplicity);
+            return;
+        }
+        for &executor_index in &self.cartesian_executor_indices {
+            let (iterator, prefetched) = match self.reuses_intersection_iterator {
+                true => (&mut intersection_iterators[executor_index], None),
+                false => (self.iterators[executor_index].as_mut().unwrap(), self.prefetched[executor_index].as_ref()),
+            };
+            write_combination_values(iterator, prefetched, row);
+        }
+        for pos in (0..row.len() as u32).map(VariablePosition::new).filter(|i| !outputs_selected.selected.contains(i)) {
             row.unset(pos);
         }
-        for (index, value) in self.intersection_source.iter().enumerate() {
+        // the intersection may be wider than the output, to hold the variables read by the step's checks
+        for (index, value) in self.intersection_source.iter().take(row.len()).enumerate() {
             if *row.get(VariablePosition::new(index as u32)) == VariableValue::None {
                 row.set(VariablePosition::new(index as u32), value.clone());
             }
@@ -718,44 +1606,223 @@ impl CartesianIterator {
     }
 }
 
-#[derive(Debug)]
-pub(crate) struct UnsortedJoinExecutor {
-    iterate: ConstraintInstruction<ExecutorVariable>,
-    checks: Vec<ConstraintInstruction<ExecutorVariable>>,
+/// Writes the values an instruction contributes to the current combination, from memory if its tuples were prefetched.
+fn write_combination_values(iterator: &mut TupleIterator, prefetched: Option<&PrefetchedTuples>, row: &mut Row<'_>) {
+    match prefetched {
+        Some(PrefetchedTuples { tuples, cursor }) => write_tuple_values(iterator.positions(), &tuples[*cursor], row),
+        None => iterator.write_values(row),
+    }
+}
 
+/// Iterates a single instruction for each input row, without merging it with any other iterator. The rows produced for
+/// each tuple are emitted if they satisfy the checks of the step.
+/// Tuples are consumed as they are emitted, so a batch that fills up partway through the tuples of an input row is
+/// continued from the next tuple of the same row. After a transient storage error, the iterator of the current input
+/// row is recreated and moved past the tuples already consumed for it.
+pub(crate) struct UnsortedJoinExecutor {
+    iterate: InstructionExecutor,
+    checker: Checker<()>,
     output_width: u32,
-    output: Option<FixedBatch>,
+    outputs_selected: SelectedPositions,
+
+    input: Option<Peekable<FixedBatchRowIterator>>,
+    iterator: Option<TupleIterator>,
+    input_row_tuples: u64,
+    row: Vec<VariableValue<'static>>,
+    control: StepControl,
     profile: Arc<StepProfile>,
 }
 
+impl fmt::Debug for UnsortedJoinExecutor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UnsortedJoinExecutor (instruction = {:?}, checks = {:?})", self.iterate, self.checker.checks)
+    }
+}
+
 impl UnsortedJoinExecutor {
     fn new(
         iterate: ConstraintInstruction<ExecutorVariable>,
-        checks: Vec<ConstraintInstruction<ExecutorVariable>>,
-        total_vars: u32,
+        variable_modes: VariableModes,
+        checks: Vec<CheckInstruction<ExecutorVariable>>,
+        selected_variables: Vec<VariablePosition>,
+        output_width: u32,
+        snapshot: &Arc<impl ReadableSnapshot + 'static>,
+        thing_manager: &Arc<ThingManager>,
         profile: Arc<StepProfile>,
-    ) -> Self {
-        Self { iterate, checks, output_width: total_vars, output: None, profile }
+    ) -> Result<Self, Box<ConceptReadError>> {
+        // tuples are not merged with any other iterator, so any variable the instruction produces may order them
+        let mut sort_variable = None;
+        iterate.used_variables_foreach(|var| {
+            if variable_modes.get(var) != Some(VariableMode::Input) {
+                sort_variable.get_or_insert(var);
+            }
+        });
+        let sort_variable = sort_variable.expect("An unsorted join must produce a variable");
+        let iterate = InstructionExecutor::new(iterate, variable_modes, &**snapshot, thing_manager, sort_variable)?;
+
+        // checked variables need not be selected, so the assembled rows must be wide enough to hold them too
+        let mut row_width = output_width as usize;
+        for check in &checks {
+            check.variables_foreach(|var| {
+                if let Some(position) = var.as_position() {
+                    row_width = usize::max(row_width, position.as_usize() + 1);
+                }
+            });
+        }
+
+        Ok(Self {
+            iterate,
+            checker: Checker::new(checks, HashMap::new()),
+            output_width,
+            outputs_selected: SelectedPositions::new(selected_variables),
+            input: None,
+            iterator: None,
+            input_row_tuples: 0,
+            row: vec![VariableValue::None; row_width],
+            control: StepControl::default(),
+            profile,
+        })
     }
 
     fn reset(&mut self) {
-        unimplemented_feature!(UnsortedJoin)
+        self.input = None;
+        self.iterator = None;
+        self.input_row_tuples = 0;
+    }
+
+    fn restart(&mut self) {
+        self.reset();
+        self.iterate.clear_instance_cache();
+    }
+
+    fn is_snapshot_independent(&self) -> bool {
+        self.input.is_none() && self.iterator.is_none() && !self.iterate.has_instance_cache()
     }
 
     fn prepare(
         &mut self,
-        _input_batch: FixedBatch,
-        _context: &ExecutionContext<impl ReadableSnapshot + Sized>,
+        input_batch: FixedBatch,
+        _context: &ExecutionContext<impl ReadableSnapshot + 'static>,
     ) -> Result<(), ReadExecutionError> {
-        unimplemented_feature!(UnsortedJoin)
+        debug_assert!(self.input.is_none() || self.input.as_mut().unwrap().peek().is_none());
+        self.input = Some(Peekable::new(FixedBatchRowIterator::new(Ok(input_batch))));
+        self.iterator = None;
+        self.input_row_tuples = 0;
+        Ok(())
     }
 
     fn batch_continue(
         &mut self,
-        _context: &ExecutionContext<impl ReadableSnapshot + Sized>,
-        _interrupt: &mut ExecutionInterrupt,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        unimplemented_feature!(UnsortedJoin)
+        if self.input.is_none() {
+            return Ok(None);
+        }
+        continue_batch(self, context, interrupt)
+    }
+
+    fn create_iterator(
+        iterate: &InstructionExecutor,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        input_row: &MaybeOwnedRow<'_>,
+        profile: &StepProfile,
+    ) -> Result<TupleIterator, ReadExecutionError> {
+        iterate.get_iterator(context, input_row.as_reference(), profile.storage_counters()).map_err(|err| {
+            ReadExecutionError::CreatingIterator { instruction_name: iterate.name().to_string(), typedb_source: err }
+        })
+    }
+}
+
+impl BatchComputation for UnsortedJoinExecutor {
+    fn control(&mut self) -> &mut StepControl {
+        &mut self.control
+    }
+
+    fn profile(&self) -> &Arc<StepProfile> {
+        &self.profile
+    }
+
+    fn compute_next_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        let output = batch.get_or_insert_with(|| FixedBatch::new(self.output_width, context.batch_row_capacity()));
+        while !output.is_full() {
+            if self.control.next_unit(interrupt)? {
+                break;
+            }
+            if self.input.as_mut().unwrap().peek().is_none() {
+                self.input = None;
+                break;
+            }
+            let input_row = self.input.as_mut().unwrap().peek().unwrap().as_ref().map_err(|&err| err.clone())?;
+            let iterator = match &mut self.iterator {
+                Some(iterator) => iterator,
+                None => {
+                    let iterator = Self::create_iterator(&self.iterate, context, input_row, &self.profile)?;
+                    self.iterator.insert(iterator)
+                }
+            };
+            match iterator.peek() {
+                None => {
+                    self.iterator = None;
+                    self.input_row_tuples = 0;
+                    let _ = self.input.as_mut().unwrap().next();
+                    continue;
+                }
+                Some(Err(err)) => return Err(ReadExecutionError::ConceptRead { typedb_source: err.clone() }),
+                Some(Ok(_)) => (),
+            }
+
+            self.row.fill(VariableValue::None);
+            let (mut multiplicity, mut provenance) = (1, Provenance::INITIAL);
+            let mut row = Row::new(&mut self.row, &mut multiplicity, &mut provenance);
+            iterator.write_values(&mut row);
+            for position in (0..input_row.len().min(row.len()) as u32).map(VariablePosition::new) {
+                // values produced by the instruction take precedence over inputs reusing their positions
+                if row.get(position).is_empty() && !input_row.get(position).is_empty() {
+                    row.set(position, input_row.get(position).clone().into_owned());
+                }
+            }
+            let tuple_multiplicity =
+                iterator.advance_past().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })? as u64;
+            let is_satisfied = satisfies_checks(&self.checker, context, &self.row, &self.profile)?;
+            // the tuple is only counted as consumed once its row is decided, so a failed one is read again on a retry
+            self.input_row_tuples += 1;
+            if !is_satisfied {
+                continue;
+            }
+            let input_row = self.input.as_mut().unwrap().peek().unwrap().as_ref().map_err(|&err| err.clone())?;
+            output.append(|mut row| {
+                for &position in &self.outputs_selected.selected {
+                    row.set(position, self.row[position.as_usize()].clone());
+                }
+                row.set_multiplicity(input_row.multiplicity() * tuple_multiplicity);
+                row.set_provenance(input_row.provenance());
+            });
+        }
+        Ok(())
+    }
+
+    fn restart_after_error(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        self.iterator = None;
+        if self.input_row_tuples == 0 {
+            // the iterator is created afresh when the input row is next read
+            return Ok(());
+        }
+        let input_row = self.input.as_mut().unwrap().peek().unwrap().as_ref().map_err(|&err| err.clone())?;
+        let mut iterator = Self::create_iterator(&self.iterate, context, input_row, &self.profile)?;
+        for _ in 0..self.input_row_tuples {
+            iterator.advance_past().map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+        }
+        self.iterator = Some(iterator);
+        Ok(())
     }
 }
 
@@ -768,7 +1835,12 @@ pub(crate) struct AssignExecutor {
     output_width: u32,
     profile: Arc<StepProfile>,
 
-    prepared_input: Option<FixedBatch>,
+    input: Option<Peekable<FixedBatchRowIterator>>,
+    /// The value of an expression reading no variables, evaluated once for all the rows of the input batch.
+    constant_output: Option<ExpressionValue>,
+    /// The input values of the rows assigned so far in the current call, in a column per input variable.
+    columns: HashMap<VariablePosition, Vec<ExpressionValue>>,
+    control: StepControl,
 }
 
 impl AssignExecutor {
@@ -780,70 +1852,130 @@ impl AssignExecutor {
         output_width: u32,
         profile: Arc<StepProfile>,
     ) -> Self {
-        Self { expression, inputs, output, selected_variables, output_width, profile, prepared_input: None }
+        Self {
+            expression,
+            inputs,
+            output,
+            selected_variables,
+            output_width,
+            profile,
+            input: None,
+            constant_output: None,
+            columns: HashMap::new(),
+            control: StepControl::default(),
+        }
     }
 
     fn reset(&mut self) {
-        self.prepared_input = None;
+        self.input = None;
+        self.constant_output = None;
+        self.columns.clear();
     }
 
     fn prepare(
         &mut self,
         input_batch: FixedBatch,
-        _context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
     ) -> Result<(), ReadExecutionError> {
-        self.prepared_input = Some(input_batch);
+        debug_assert!(self.input.is_none() || self.input.as_mut().unwrap().peek().is_none());
+        self.constant_output = None;
+        if self.expression.variables().is_empty() && input_batch.len() > 0 {
+            let value = evaluate_expression(&self.expression, HashMap::new(), &context.parameters)
+                .map_err(|typedb_source| ReadExecutionError::ExpressionEvaluate { typedb_source })?;
+            self.constant_output = Some(value);
+        }
+        self.input = Some(Peekable::new(FixedBatchRowIterator::new(Ok(input_batch))));
         Ok(())
     }
 
     fn batch_continue(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
-        _interrupt: &mut ExecutionInterrupt,
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        if self.prepared_input.is_none() {
+        if self.input.is_none() {
             return Ok(None);
         }
-        let measurement = self.profile.start_measurement();
-        let mut input = Peekable::new(FixedBatchRowIterator::new(Ok(self.prepared_input.take().unwrap())));
-        debug_assert!(input.peek().is_some());
-        let mut output = FixedBatch::new(self.output_width);
+        continue_batch(self, context, interrupt)
+    }
+}
+
+impl BatchComputation for AssignExecutor {
+    fn control(&mut self) -> &mut StepControl {
+        &mut self.control
+    }
+
+    fn profile(&self) -> &Arc<StepProfile> {
+        &self.profile
+    }
+
+    fn compute_next_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        let input = self.input.as_mut().unwrap();
+        let output = batch.get_or_insert_with(|| FixedBatch::new(self.output_width, context.batch_row_capacity()));
 
+        // the input values are gathered into columns while the rows are copied, and the expression is then evaluated
+        // over the whole output batch at once
+        // the input is kept between calls, so rows left over once the output is full or the yield budget is spent are
+        // assigned by the next call
         while !output.is_full() {
-            let Some(row) = input.next() else { break };
-            let input_row = row.map_err(|err| err.clone())?;
-            let input_variables = self
-                .inputs
-                .iter()
-                .map(|&pos| {
-                    let value = input_row.get(pos).to_owned();
-                    let expression_value =
-                        ExpressionValue::try_from_value(value, context, self.profile.storage_counters())
-                            .map_err(|typedb_source| ReadExecutionError::ExpressionEvaluate { typedb_source })?;
-                    Ok((pos, expression_value))
-                })
-                .try_collect()?;
-            let output_value = evaluate_expression(&self.expression, input_variables, &context.parameters)
-                .map_err(|typedb_source| ReadExecutionError::ExpressionEvaluate { typedb_source })?;
+            if self.control.next_unit(interrupt)? {
+                break;
+            }
+            let Some(row) = input.peek() else { break };
+            let input_row = row.as_ref().map_err(|&err| err.clone())?;
+            for (index, &position) in self.inputs.iter().enumerate() {
+                let value = input_row.get(position).to_owned();
+                match ExpressionValue::try_from_value(value, context, self.profile.storage_counters()) {
+                    Ok(expression_value) => self.columns.entry(position).or_default().push(expression_value),
+                    Err(typedb_source) => {
+                        // the row stays in the input, and its values are gathered again
+                        for position in &self.inputs[..index] {
+                            self.columns.get_mut(position).unwrap().pop();
+                        }
+                        return Err(ReadExecutionError::ExpressionEvaluate { typedb_source });
+                    }
+                }
+            }
             output.append(|mut row| {
                 row.set_multiplicity(input_row.multiplicity());
+                row.set_provenance(input_row.provenance());
                 for &position in &self.selected_variables {
                     if position.as_usize() < input_row.len() {
                         row.set(position, input_row.get(position).clone().into_owned());
                     }
                 }
-                if let Some(position) = self.output.as_position() {
-                    row.set(position, output_value.into());
-                }
-            })
+            });
+            let _ = input.next();
+        }
+        if input.peek().is_none() {
+            self.input = None;
         }
-        measurement.end(&self.profile, 1, output.len() as u64);
 
-        if output.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(output))
+        let columns = mem::take(&mut self.columns);
+        let output_values = match &self.constant_output {
+            Some(value) => vec![value.clone(); output.len() as usize],
+            None => evaluate_expression_batch(&self.expression, columns, output.len() as usize, &context.parameters)
+                .map_err(|typedb_source| ReadExecutionError::ExpressionEvaluate { typedb_source })?,
+        };
+        if let Some(position) = self.output.as_position() {
+            for (index, value) in output_values.into_iter().enumerate() {
+                output.get_row_mut(index as u32).set(position, value.into());
+            }
         }
+        Ok(())
+    }
+
+    fn restart_after_error(
+        &mut self,
+        _context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        // rows are only consumed from the input once assigned, so the next call continues from the failed one
+        Ok(())
     }
 }
 
@@ -851,7 +1983,8 @@ pub(crate) struct CheckExecutor {
     checker: Checker<()>,
     selected_variables: Vec<VariablePosition>,
     output_width: u32,
-    input: Option<FixedBatch>,
+    input: Option<Peekable<FixedBatchRowIterator>>,
+    control: StepControl,
     profile: Arc<StepProfile>,
 }
 
@@ -869,7 +2002,7 @@ impl CheckExecutor {
         profile: Arc<StepProfile>,
     ) -> Self {
         let checker = Checker::new(checks, HashMap::new());
-        Self { checker, selected_variables, output_width, input: None, profile }
+        Self { checker, selected_variables, output_width, input: None, control: StepControl::default(), profile }
     }
 
     fn reset(&mut self) {
@@ -881,39 +2014,94 @@ impl CheckExecutor {
         input_batch: FixedBatch,
         _context: &ExecutionContext<impl ReadableSnapshot + 'static>,
     ) -> Result<(), ReadExecutionError> {
-        self.input = Some(input_batch);
+        debug_assert!(self.input.is_none() || self.input.as_mut().unwrap().peek().is_none());
+        self.input = Some(Peekable::new(FixedBatchRowIterator::new(Ok(input_batch))));
         Ok(())
     }
 
     fn batch_continue(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
-        _interrupt: &mut ExecutionInterrupt,
+        interrupt: &mut ExecutionInterrupt,
     ) -> Result<Option<FixedBatch>, ReadExecutionError> {
-        let Some(input_batch) = self.input.take() else {
+        if self.input.is_none() {
             return Ok(None);
-        };
-        let measurement = self.profile.start_measurement();
-        let mut input = Peekable::new(FixedBatchRowIterator::new(Ok(input_batch)));
-        debug_assert!(input.peek().is_some());
+        }
+        continue_batch(self, context, interrupt)
+    }
+}
 
-        let mut output = FixedBatch::new(self.output_width);
+impl BatchComputation for CheckExecutor {
+    fn control(&mut self) -> &mut StepControl {
+        &mut self.control
+    }
 
-        while let Some(row) = input.next() {
-            let input_row = row.map_err(|err| err.clone())?;
-            if self.checker.filter_fn_for_row(context, &input_row, self.profile.storage_counters())(&Ok(()))
-                .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?
-            {
+    fn profile(&self) -> &Arc<StepProfile> {
+        &self.profile
+    }
+
+    fn compute_next_rows(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+        batch: &mut Option<FixedBatch>,
+        interrupt: &mut ExecutionInterrupt,
+    ) -> Result<(), ReadExecutionError> {
+        let input = self.input.as_mut().unwrap();
+        // unchecked input rows stay buffered when a downstream limit fills the batch early
+        let output = batch.get_or_insert_with(|| FixedBatch::new(self.output_width, context.batch_row_capacity()));
+        while !output.is_full() {
+            if self.control.next_unit(interrupt)? {
+                break;
+            }
+            let Some(row) = input.peek() else { break };
+            let input_row = row.as_ref().map_err(|&err| err.clone())?;
+            let is_satisfied =
+                self.checker.filter_fn_for_row(context, input_row, self.profile.storage_counters())(&Ok(()))
+                    .map_err(|err| ReadExecutionError::ConceptRead { typedb_source: err })?;
+            if is_satisfied {
                 output.append(|mut row| {
-                    row.copy_mapped(input_row, self.selected_variables.iter().map(|pos| (*pos, *pos)));
+                    row.copy_mapped(input_row.as_reference(), self.selected_variables.iter().map(|pos| (*pos, *pos)));
                 })
             }
+            let _ = input.next();
         }
-        measurement.end(&self.profile, 1, output.len() as u64);
-        if output.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(output))
+        if input.peek().is_none() {
+            self.input = None;
         }
+        Ok(())
+    }
+
+    fn restart_after_error(
+        &mut self,
+        _context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        // rows are only consumed from the input once checked, so the next call checks the failed one again
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InterruptPoller;
+    use crate::{error::ReadExecutionError, ExecutionInterrupt, InterruptType};
+
+    #[test]
+    fn interrupt_poller_stops_within_one_check_interval() {
+        let (sender, receiver) = tokio::sync::broadcast::channel(1);
+        let mut interrupt = ExecutionInterrupt::new(receiver);
+        let mut poller = InterruptPoller::default();
+        for _ in 0..InterruptPoller::CHECK_INTERVAL * 3 / 2 {
+            poller.poll(&mut interrupt).unwrap();
+        }
+
+        // interrupted part way through a check interval: at most one more interval of rows is computed
+        sender.send(InterruptType::TransactionClosed).unwrap();
+        let interrupted_after = (1..=InterruptPoller::CHECK_INTERVAL).find(|_| {
+            matches!(
+                poller.poll(&mut interrupt),
+                Err(ReadExecutionError::Interrupted { interrupt: InterruptType::TransactionClosed })
+            )
+        });
+        assert_eq!(interrupted_after, Some(InterruptPoller::CHECK_INTERVAL / 2));
     }
 }
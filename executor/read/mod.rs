@@ -21,6 +21,8 @@ pub mod expression_executor;
 mod immediate_executor;
 pub(crate) mod nested_pattern_executor;
 pub(crate) mod pattern_executor;
+pub mod pushdown;
+pub mod retry;
 pub(crate) mod step_executor;
 mod stream_modifier;
 pub(super) mod suspension;
@@ -60,6 +62,7 @@ pub(super) fn create_pattern_executor_for_conjunction(
     function_registry: &ExecutableFunctionRegistry,
     conjunction_executable: &ConjunctionExecutable,
     profile: &QueryProfile,
+    profile_id: u64,
 ) -> Result<PatternExecutor, Box<ConceptReadError>> {
     let executors = step_executor::create_executors_for_conjunction(
         snapshot,
@@ -67,6 +70,7 @@ pub(super) fn create_pattern_executor_for_conjunction(
         function_registry,
         profile,
         conjunction_executable,
+        profile_id,
     )?;
     Ok(PatternExecutor::new(conjunction_executable.executable_id(), executors))
 }
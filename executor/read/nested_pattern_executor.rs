@@ -22,6 +22,7 @@ pub struct DisjunctionExecutor {
     pub branch_ids: Vec<BranchID>,
     pub selected_variables: Vec<VariablePosition>,
     pub output_width: u32,
+    pub interleave_branches: bool,
 }
 
 impl DisjunctionExecutor {
@@ -30,9 +31,10 @@ impl DisjunctionExecutor {
         branches: Vec<PatternExecutor>,
         selected_variables: Vec<VariablePosition>,
         output_width: u32,
+        interleave_branches: bool,
     ) -> Self {
         debug_assert!(branch_ids.len() == branches.len());
-        Self { branches, branch_ids, selected_variables, output_width }
+        Self { branches, branch_ids, selected_variables, output_width, interleave_branches }
     }
 
     pub(crate) fn reset(&mut self) {
@@ -40,7 +42,7 @@ impl DisjunctionExecutor {
     }
 
     pub(crate) fn map_output(&self, source_branch_index: BranchIndex, unmapped: FixedBatch) -> FixedBatch {
-        let mut uniform_batch = FixedBatch::new(self.output_width);
+        let mut uniform_batch = FixedBatch::new(self.output_width, unmapped.capacity());
         unmapped.into_iter().for_each(|row| {
             uniform_batch.append(|mut output_row| {
                 output_row.copy_mapped(row, self.selected_variables.iter().map(|&pos| (pos, pos)));
@@ -57,6 +59,10 @@ pub struct NegationExecutor {
 }
 
 impl NegationExecutor {
+    /// A negation only needs to know whether its pattern has a single answer, so every step inside it stops computing
+    /// as soon as it has one row to pass on, instead of filling a whole batch first.
+    pub(crate) const WITNESS_BATCH_CAPACITY: u32 = 1;
+
     pub(crate) fn new(inner: PatternExecutor) -> Self {
         Self { inner }
     }
@@ -95,7 +101,7 @@ impl InlinedCallExecutor {
     }
 
     pub(crate) fn map_output(&self, input: MaybeOwnedRow<'_>, batch: FixedBatch) -> FixedBatch {
-        let mut output_batch = FixedBatch::new(self.output_width);
+        let mut output_batch = FixedBatch::new(self.output_width, batch.capacity());
         let check_indices: Vec<_> = self
             .assignment_positions
             .iter()
@@ -75,6 +75,22 @@ impl PatternExecutor {
         self.control_stack.push(PatternStart { input_batch }.into());
     }
 
+    /// Eagerly prepares the first step with the input batch, if the pattern has been prepared but not yet started. A
+    /// sorted join opens its iterators for the first input row when prepared; other steps defer all work to the first
+    /// batch. No rows are consumed, and calling this more than once (or after execution started) is a no-op.
+    pub(crate) fn prewarm(
+        &mut self,
+        context: &ExecutionContext<impl ReadableSnapshot + 'static>,
+    ) -> Result<(), ReadExecutionError> {
+        if !matches!(self.control_stack.last(), Some(ControlInstruction::PatternStart(_))) {
+            return Ok(());
+        }
+        let Some(ControlInstruction::PatternStart(PatternStart { input_batch })) = self.control_stack.pop() else {
+            unreachable!("Expected PatternStart at the top of the control stack")
+        };
+        self.push_next_instruction(context, ExecutorIndex(0), input_batch)
+    }
+
     pub(crate) fn prepare_to_restore_from_suspension(&mut self, depth: usize) {
         debug_assert!(self.control_stack.is_empty());
         self.reset();
@@ -85,6 +101,41 @@ impl PatternExecutor {
         self.control_stack.clear();
     }
 
+    /// Discards all per-execution state, including anything derived from the snapshot of a previous execution.
+    pub(crate) fn restart(&mut self) {
+        self.reset();
+        for executor in &mut self.executors {
+            match executor {
+                StepExecutors::Immediate(inner) => inner.restart(),
+                StepExecutors::Negation(inner) => inner.inner.restart(),
+                StepExecutors::Disjunction(inner) => inner.branches.iter_mut().for_each(PatternExecutor::restart),
+                StepExecutors::InlinedCall(inner) => inner.inner.restart(),
+                StepExecutors::StreamModifier(inner) => inner.inner().restart(),
+                StepExecutors::CollectingStage(inner) => inner.pattern_mut().restart(),
+                StepExecutors::TabledCall(inner) => inner.restart(),
+                StepExecutors::ReshapeForReturn(_) => {}
+            }
+        }
+    }
+
+    /// Whether no step holds state read from the snapshot of a previous execution: open iterators, prefetched
+    /// tuples, cached instances or calls into the tables of tabled functions.
+    pub(crate) fn is_snapshot_independent(&self) -> bool {
+        self.control_stack.is_empty()
+            && self.executors.iter().all(|executor| match executor {
+                StepExecutors::Immediate(inner) => inner.is_snapshot_independent(),
+                StepExecutors::Negation(inner) => inner.inner.is_snapshot_independent(),
+                StepExecutors::Disjunction(inner) => {
+                    inner.branches.iter().all(PatternExecutor::is_snapshot_independent)
+                }
+                StepExecutors::InlinedCall(inner) => inner.inner.is_snapshot_independent(),
+                StepExecutors::StreamModifier(inner) => inner.is_snapshot_independent(),
+                StepExecutors::CollectingStage(inner) => inner.is_snapshot_independent(),
+                StepExecutors::TabledCall(inner) => inner.is_snapshot_independent(),
+                StepExecutors::ReshapeForReturn(_) => true,
+            })
+    }
+
     pub(super) fn batch_continue(
         &mut self,
         context: &ExecutionContext<impl ReadableSnapshot + 'static>,
@@ -117,6 +168,9 @@ impl PatternExecutor {
                     let executor = executors[*index].unwrap_immediate();
                     if let Some(batch) = executor.batch_continue(context, interrupt)? {
                         control_stack.push(ExecuteImmediate { index }.into());
+                        if is_yield(&batch) {
+                            return Ok(Some(batch));
+                        }
                         self.push_next_instruction(context, index.next(), batch)?;
                     }
                 }
@@ -129,11 +183,17 @@ impl PatternExecutor {
                 }
                 ControlInstruction::ExecuteNegation(ExecuteNegation { index, input }) => {
                     let NegationExecutor { inner } = &mut executors[*index].unwrap_negation();
-                    let result = inner.compute_next_batch(context, interrupt, tabled_functions)?;
+                    let witness_context = context.clone().with_batch_capacity(NegationExecutor::WITNESS_BATCH_CAPACITY);
+                    let result = inner.compute_next_batch(&witness_context, interrupt, tabled_functions)?;
                     match result {
                         None => {
                             self.push_next_instruction(context, index.next(), FixedBatch::from(input.as_reference()))?
                         }
+                        Some(batch) if is_yield(&batch) => {
+                            // the negated pattern is resumed until it finds a witness or runs out of answers
+                            control_stack.push(ExecuteNegation { index, input }.into());
+                            return Ok(Some(batch));
+                        }
                         Some(batch) => {
                             debug_assert!(!batch.is_empty());
                             inner.reset()
@@ -150,10 +210,26 @@ impl PatternExecutor {
                     let batch_opt = may_push_nested(suspensions, index, branch_index, &input, |suspensions| {
                         branch.batch_continue(context, interrupt, tabled_functions, suspensions)
                     })?;
+                    let yielded = batch_opt.as_ref().is_some_and(is_yield);
                     if let Some(mapped) = batch_opt.map(|unmapped| disjunction.map_output(branch_index, unmapped)) {
-                        control_stack.push(ExecuteDisjunctionBranch { index, branch_index, input }.into());
+                        let resumed: ControlInstruction =
+                            ExecuteDisjunctionBranch { index, branch_index, input }.into();
+                        if disjunction.interleave_branches {
+                            // the branch yields its turn: it resumes after the other unfinished branches of this input
+                            let is_sibling = |instruction: &&ControlInstruction| match instruction {
+                                ControlInstruction::ExecuteDisjunctionBranch(sibling) => *sibling.index == *index,
+                                _ => false,
+                            };
+                            let siblings = control_stack.iter().rev().take_while(is_sibling).count();
+                            control_stack.insert(control_stack.len() - siblings, resumed);
+                        } else {
+                            control_stack.push(resumed);
+                        }
                         self.push_next_instruction(context, index.next(), mapped)?;
                     }
+                    if yielded {
+                        return Ok(Some(FixedBatch::empty()));
+                    }
                 }
                 ControlInstruction::ExecuteInlinedFunction(ExecuteInlinedFunction { index, input }) => {
                     let executor = &mut executors[*index].unwrap_inlined_call();
@@ -161,20 +237,28 @@ impl PatternExecutor {
                     let batch_opt = may_push_nested(suspensions, index, BranchIndex(0), &input, |suspensions| {
                         executor.inner.batch_continue(func_context, interrupt, tabled_functions, suspensions)
                     })?;
+                    let yielded = batch_opt.as_ref().is_some_and(is_yield);
                     if let Some(mapped) = batch_opt.map(|batch| executor.map_output(input.as_reference(), batch)) {
                         control_stack.push(ExecuteInlinedFunction { index, input: input.into_owned() }.into());
                         self.push_next_instruction(context, index.next(), mapped)?;
                     }
+                    if yielded {
+                        return Ok(Some(FixedBatch::empty()));
+                    }
                 }
                 ControlInstruction::ExecuteStreamModifier(ExecuteStreamModifier { index, mut mapper, input }) => {
                     let inner = &mut executors[*index].unwrap_stream_modifier().inner();
                     let unmapped = may_push_nested(suspensions, index, BranchIndex(0), &input, |suspensions| {
                         inner.batch_continue(context, interrupt, tabled_functions, suspensions)
                     })?;
+                    let yielded = unmapped.as_ref().is_some_and(is_yield);
                     if let Some(batch) = mapper.map_output(unmapped) {
                         control_stack.push(ExecuteStreamModifier { index, mapper, input: input.into_owned() }.into());
                         self.push_next_instruction(context, index.next(), batch)?;
                     }
+                    if yielded {
+                        return Ok(Some(FixedBatch::empty()));
+                    }
                 }
                 ControlInstruction::ExecuteTabledCall(ExecuteTabledCall { index, last_seen_table_size }) => {
                     self.execute_tabled_call(
@@ -257,7 +341,8 @@ impl PatternExecutor {
                 tabled_call.prepare(input.clone().into_owned());
                 self.control_stack.push(ExecuteTabledCall { index, last_seen_table_size: None }.into());
             }
-            StepExecutors::Disjunction(DisjunctionExecutor { branches, .. }) => {
+            StepExecutors::Disjunction(DisjunctionExecutor { branches, interleave_branches, .. }) => {
+                let start = self.control_stack.len();
                 for (idx, branch) in branches.iter_mut().enumerate() {
                     let branch_index = BranchIndex(idx);
                     branch.prepare(FixedBatch::from(input.as_reference()));
@@ -265,6 +350,10 @@ impl PatternExecutor {
                         ExecuteDisjunctionBranch { index, branch_index, input: input.clone().into_owned() }.into(),
                     )
                 }
+                if *interleave_branches {
+                    // interleaved branches take their turns in order, starting from the first branch
+                    self.control_stack[start..].reverse();
+                }
             }
             StepExecutors::Negation(NegationExecutor { inner }) => {
                 inner.prepare(FixedBatch::from(input.as_reference()));
@@ -300,7 +389,7 @@ impl PatternExecutor {
         let executor = self.executors[*index].unwrap_tabled_call();
         let call_key = executor.active_call_key().unwrap();
         let function_state = tabled_functions.get_or_create_function_state(context, call_key)?;
-        let found = match executor.try_read_next_batch(&function_state) {
+        let found = match executor.try_read_next_batch(&function_state, context.batch_capacity) {
             TabledCallResult::RetrievedFromTable(batch) => Some(batch),
             TabledCallResult::Suspend => {
                 caller_suspensions.push_tabled_call(index, executor);
@@ -312,12 +401,7 @@ impl PatternExecutor {
                     suspensions: function_suspensions,
                     parameters,
                 } = pattern_state_mutex_guard.deref_mut();
-                let context_with_function_parameters = ExecutionContext::new_with_profile(
-                    context.snapshot.clone(),
-                    context.thing_manager.clone(),
-                    parameters.clone(),
-                    context.profile.clone(),
-                );
+                let context_with_function_parameters = context.clone_with_replaced_parameters(parameters.clone());
                 let batch_opt = pattern_executor.batch_continue(
                     &context_with_function_parameters,
                     interrupt,
@@ -358,6 +442,12 @@ impl PatternExecutor {
     }
 }
 
+/// Whether an executor returned early, having spent its yield budget without producing a row. Every other batch an
+/// executor returns holds at least one row, and the instruction that yielded stays on the control stack to be resumed.
+fn is_yield(batch: &FixedBatch) -> bool {
+    batch.is_empty()
+}
+
 fn restore_suspension(
     control_stack: &mut Vec<ControlInstruction>,
     executors: &mut [StepExecutors],
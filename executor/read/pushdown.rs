@@ -0,0 +1,68 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use compiler::{executable::match_::instructions::pushdown::PushdownCandidate, ExecutorVariable};
+
+/// A storage backend able to apply some filters while it scans. Claiming a candidate promises that every tuple the
+/// scan returns satisfies it.
+pub trait PushdownBackend: Send + Sync {
+    fn claims(&self, candidate: &PushdownCandidate<ExecutorVariable>) -> bool;
+}
+
+/// Decides which checks of the instructions the executor leaves to storage, see [`PushdownCandidate`].
+#[derive(Clone, Default)]
+pub struct StoragePushdown {
+    backend: Option<Arc<dyn PushdownBackend>>,
+    verification: Option<Arc<AtomicU64>>,
+}
+
+impl StoragePushdown {
+    /// Evaluates every check in the executor.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Skips the checks the backend claims.
+    pub fn new(backend: Arc<dyn PushdownBackend>) -> Self {
+        Self { backend: Some(backend), verification: None }
+    }
+
+    /// Evaluates the claimed checks anyway, counting the tuples storage returned that fail them instead of skipping
+    /// the checks. Meant for testing backends.
+    pub fn verifying_claims(self) -> Self {
+        Self { verification: Some(Arc::new(AtomicU64::new(0))), ..self }
+    }
+
+    /// The number of tuples that failed a claimed check, while verifying claims.
+    pub fn claim_violations(&self) -> u64 {
+        self.verification.as_ref().map_or(0, |violations| violations.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn claims(&self, candidate: &PushdownCandidate<ExecutorVariable>) -> bool {
+        self.backend.as_ref().is_some_and(|backend| backend.claims(candidate))
+    }
+
+    pub(crate) fn claim_verification(&self) -> Option<&Arc<AtomicU64>> {
+        self.verification.as_ref()
+    }
+}
+
+impl fmt::Debug for StoragePushdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoragePushdown")
+            .field("has_backend", &self.backend.is_some())
+            .field("claim_violations", &self.verification.as_ref().map(|violations| violations.load(Ordering::Relaxed)))
+            .finish()
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{fmt, sync::Arc, thread, time::Duration};
+
+use concept::error::ConceptReadError;
+use resource::profile::StepProfile;
+
+use crate::error::ReadExecutionError;
+
+/// Decides which storage read errors are transient, and how often and how patiently the reads that fail with them are
+/// retried. Any other error fails the query immediately.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    is_transient: Arc<dyn Fn(&ConceptReadError) -> bool + Send + Sync>,
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts at each read, waiting `initial_backoff` before the first retry and twice as
+    /// long before each further retry, up to `max_backoff`.
+    pub fn new(
+        max_attempts: usize,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        is_transient: impl Fn(&ConceptReadError) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self { max_attempts: max_attempts.max(1), initial_backoff, max_backoff, is_transient: Arc::new(is_transient) }
+    }
+
+    /// Never retries: every error fails the query.
+    pub fn never() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO, |_| false)
+    }
+
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Whether an execution step may recover from the error of its given attempt by redoing its reads.
+    pub(crate) fn may_retry(&self, error: &ReadExecutionError, attempt: usize) -> bool {
+        attempt < self.max_attempts && error.concept_read_error().is_some_and(|err| (self.is_transient)(err))
+    }
+
+    pub(crate) fn wait_before_retry(&self, attempt: usize, profile: &StepProfile) {
+        let exponent = (attempt - 1).min(u32::BITS as usize - 1) as u32;
+        let backoff = self.initial_backoff.saturating_mul(1 << exponent).min(self.max_backoff);
+        if !backoff.is_zero() {
+            thread::sleep(backoff);
+        }
+        profile.record_retry();
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::never()
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .finish_non_exhaustive()
+    }
+}
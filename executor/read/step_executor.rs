@@ -112,7 +112,7 @@ pub struct ReshapeForReturnExecutor(Vec<VariablePosition>);
 
 impl ReshapeForReturnExecutor {
     pub(super) fn map_output(&self, batch: FixedBatch) -> FixedBatch {
-        let mut output_batch = FixedBatch::new(self.0.len() as u32);
+        let mut output_batch = FixedBatch::new(self.0.len() as u32, batch.capacity());
         batch.into_iter().for_each(|row| output_batch.append(|mut out| out.copy_mapped(row, self.as_mapping())));
         output_batch
     }
@@ -128,33 +128,39 @@ pub(crate) fn create_executors_for_conjunction(
     function_registry: &ExecutableFunctionRegistry,
     query_profile: &QueryProfile,
     conjunction_executable: &ConjunctionExecutable,
+    profile_id: u64,
 ) -> Result<Vec<StepExecutors>, Box<ConceptReadError>> {
-    let stage_profile = query_profile.profile_stage(
-        || format!("Match\n  ~ {}", conjunction_executable.planner_statistics()),
-        conjunction_executable.executable_id(),
-    );
+    let stage_profile = query_profile
+        .profile_stage(|| format!("Match\n  ~ {}", conjunction_executable.planner_statistics()), profile_id);
     let mut steps = Vec::with_capacity(conjunction_executable.steps().len());
     for (index, step) in conjunction_executable.steps().iter().enumerate() {
+        let label = conjunction_executable.step_label(index).map(|label| label.to_string());
         match step {
             ExecutionStep::Intersection(inner) => {
-                let step_profile = stage_profile.extend_or_get(index, || {
+                let step_profile = stage_profile.extend_or_get_labelled(index, label.clone(), || {
                     format!("{}", inner.make_var_mapped(conjunction_executable.variable_reverse_map()))
                 });
-                let step = ImmediateExecutor::new_intersection(inner, snapshot, thing_manager, step_profile)?;
+                let step = ImmediateExecutor::new_intersection(
+                    inner,
+                    conjunction_executable.result_semantics(),
+                    snapshot,
+                    thing_manager,
+                    step_profile,
+                )?;
                 steps.push(step.into());
             }
             ExecutionStep::UnsortedJoin(inner) => {
-                let step_profile = stage_profile.extend_or_get(index, || format!("{}", inner));
-                let step = ImmediateExecutor::new_unsorted_join(inner, step_profile)?;
+                let step_profile = stage_profile.extend_or_get_labelled(index, label.clone(), || format!("{}", inner));
+                let step = ImmediateExecutor::new_unsorted_join(inner, snapshot, thing_manager, step_profile)?;
                 steps.push(step.into());
             }
             ExecutionStep::Assignment(inner) => {
-                let step_profile = stage_profile.extend_or_get(index, || format!("{}", inner));
+                let step_profile = stage_profile.extend_or_get_labelled(index, label.clone(), || format!("{}", inner));
                 let step = ImmediateExecutor::new_assignment(inner, step_profile)?;
                 steps.push(step.into());
             }
             ExecutionStep::Check(inner) => {
-                let step_profile = stage_profile.extend_or_get(index, || {
+                let step_profile = stage_profile.extend_or_get_labelled(index, label.clone(), || {
                     format!("{}", inner.make_var_mapped(conjunction_executable.variable_reverse_map()))
                 });
                 let step = ImmediateExecutor::new_check(inner, step_profile)?;
@@ -162,13 +168,15 @@ pub(crate) fn create_executors_for_conjunction(
             }
             ExecutionStep::Negation(negation_step) => {
                 // NOTE: still create the profile so each step has an entry in the profile, even if unused
-                let _step_profile = stage_profile.extend_or_get(index, || format!("{}", negation_step));
+                let _step_profile =
+                    stage_profile.extend_or_get_labelled(index, label.clone(), || format!("{}", negation_step));
                 let inner = create_executors_for_conjunction(
                     snapshot,
                     thing_manager,
                     function_registry,
                     query_profile,
                     &negation_step.negation,
+                    negation_step.negation.executable_id(),
                 )?;
                 // I shouldn't need to pass recursive here since it's stratified
                 steps.push(
@@ -177,7 +185,8 @@ pub(crate) fn create_executors_for_conjunction(
             }
             ExecutionStep::FunctionCall(function_call) => {
                 // NOTE: still create the profile so each step has an entry in the profile, even if unused
-                let _step_profile = stage_profile.extend_or_get(index, || format!("{}", function_call));
+                let _step_profile =
+                    stage_profile.extend_or_get_labelled(index, label.clone(), || format!("{}", function_call));
 
                 let function = function_registry.get(&function_call.function_id).unwrap();
                 if let FunctionTablingType::Tabled(_) = function.tabling_type {
@@ -203,7 +212,7 @@ pub(crate) fn create_executors_for_conjunction(
             }
             ExecutionStep::Disjunction(step) => {
                 // NOTE: still create the profile so each step has an entry in the profile, even if unused
-                let _step_profile = stage_profile.extend_or_get(index, || format!("{}", step));
+                let _step_profile = stage_profile.extend_or_get_labelled(index, label.clone(), || format!("{}", step));
 
                 // I shouldn't need to pass recursive here since it's stratified
                 let branches: Vec<PatternExecutor> = step
@@ -216,6 +225,7 @@ pub(crate) fn create_executors_for_conjunction(
                             function_registry,
                             query_profile,
                             branch_executable,
+                            branch_executable.executable_id(),
                         )?;
                         Ok::<_, Box<_>>(PatternExecutor::new(branch_executable.executable_id(), executors))
                     })
@@ -225,6 +235,7 @@ pub(crate) fn create_executors_for_conjunction(
                     branches,
                     step.selected_variables.clone(),
                     step.output_width,
+                    step.interleave_branches,
                 )
                 .into();
                 // Hack: wrap it in a distinct
@@ -307,13 +318,14 @@ pub(super) fn create_executors_for_function_pipeline_stages(
     };
 
     match &executable_stages[at_index] {
-        ExecutableStage::Match(conjunction_executable) => {
+        ExecutableStage::Match { stage_id, executable } => {
             let mut executors = create_executors_for_conjunction(
                 snapshot,
                 thing_manager,
                 function_registry,
                 query_profile,
-                conjunction_executable,
+                executable,
+                *stage_id,
             )?;
             previous_stage_steps.append(&mut executors);
             Ok(previous_stage_steps)
@@ -342,6 +354,8 @@ pub(super) fn create_executors_for_function_pipeline_stages(
             );
             Ok(vec![step.into()])
         }
+        // function bodies are compiled without expansions: their rows keep their multiplicities
+        ExecutableStage::Expand(_) => Ok(previous_stage_steps),
         ExecutableStage::Require(_) => Err(Box::new(ConceptReadError::UnimplementedFunctionality {
             functionality: UnimplementedFeature::PipelineStageInFunction("require"),
         })),
@@ -74,6 +74,17 @@ impl StreamModifierExecutor {
         }
     }
 
+    pub(crate) fn is_snapshot_independent(&self) -> bool {
+        match self {
+            Self::Select { inner, .. }
+            | Self::Offset { inner, .. }
+            | Self::Limit { inner, .. }
+            | Self::Distinct { inner, .. }
+            | Self::Last { inner, .. }
+            | Self::Check { inner, .. } => inner.is_snapshot_independent(),
+        }
+    }
+
     pub(crate) fn create_mapper(&self) -> StreamModifierResultMapper {
         match self {
             Self::Select { removed_positions, .. } => {
@@ -167,10 +178,10 @@ impl StreamModifierResultMapperTrait for OffsetMapper {
                 Some(input_batch)
             } else if (self.required - self.current) >= input_batch.len() as u64 {
                 self.current += input_batch.len() as u64;
-                Some(FixedBatch::EMPTY) // Retry this instruction without returning any rows
+                Some(FixedBatch::empty()) // Retry this instruction without returning any rows
             } else {
                 let offset_in_batch = (self.required - self.current) as u32;
-                let mut output_batch = FixedBatch::new(input_batch.width());
+                let mut output_batch = FixedBatch::new(input_batch.width(), input_batch.capacity());
                 for row_index in offset_in_batch..input_batch.len() {
                     output_batch.append(|mut output_row| output_row.copy_from_row(input_batch.get_row(row_index)));
                 }
@@ -204,7 +215,7 @@ impl StreamModifierResultMapperTrait for LimitMapper {
                 self.current += input_batch.len() as u64;
                 Some(input_batch)
             } else {
-                let mut output_batch = FixedBatch::new(input_batch.width());
+                let mut output_batch = FixedBatch::new(input_batch.width(), input_batch.capacity());
                 let mut i = 0;
                 while self.current < self.required {
                     output_batch.append(|mut output_row| {
@@ -266,8 +277,11 @@ impl LastMapper {
 impl StreamModifierResultMapperTrait for LastMapper {
     fn map_output(&mut self, subquery_result: Option<FixedBatch>) -> Option<FixedBatch> {
         if let Some(input_batch) = subquery_result {
-            self.last_row = Some(input_batch.get_row(input_batch.len() - 1).into_owned());
-            Some(FixedBatch::EMPTY) // Retry this instruction without returning any rows
+            // an empty batch is returned by a subquery that yielded without finding a row
+            if !input_batch.is_empty() {
+                self.last_row = Some(input_batch.get_row(input_batch.len() - 1).into_owned());
+            }
+            Some(FixedBatch::empty()) // Retry this instruction without returning any rows
         } else {
             self.last_row.take().map(FixedBatch::from)
         }
@@ -302,7 +316,7 @@ impl StreamModifierResultMapperTrait for CheckMapper {
                 }
                 Some(batch) => {
                     if batch.is_empty() {
-                        Some(FixedBatch::EMPTY)
+                        Some(FixedBatch::empty())
                     } else {
                         self.returned = Some(true);
                         let true_row = MaybeOwnedRow::new_owned(
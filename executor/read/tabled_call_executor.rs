@@ -76,13 +76,22 @@ impl TabledCallExecutor {
         self.active_executor = Some(TabledCallExecutorState { call_key, input, next_table_row });
     }
 
+    /// Forgets the active call, whose table belonged to the tabled functions of a previous execution.
+    pub(crate) fn restart(&mut self) {
+        self.active_executor = None;
+    }
+
+    pub(crate) fn is_snapshot_independent(&self) -> bool {
+        self.active_executor.is_none()
+    }
+
     pub(crate) fn active_call_key(&self) -> Option<&CallKey> {
         self.active_executor.as_ref().map(|active| &active.call_key)
     }
 
     pub(crate) fn map_output(&self, returned_batch: FixedBatch) -> FixedBatch {
         let input = &self.active_executor.as_ref().unwrap().input;
-        let mut output_batch = FixedBatch::new(self.output_width);
+        let mut output_batch = FixedBatch::new(self.output_width, returned_batch.capacity());
         let check_indices: Vec<_> = self
             .assignment_positions
             .iter()
@@ -123,12 +132,13 @@ impl TabledCallExecutor {
     pub(crate) fn try_read_next_batch<'a>(
         &mut self,
         tabled_function_state: &'a TabledFunctionState,
+        batch_capacity: u32,
     ) -> TabledCallResult<'a> {
         // Maybe return a batch?
         let executor = self.active_executor.as_mut().unwrap();
         let table_read = tabled_function_state.table.read().unwrap();
         if *executor.next_table_row < table_read.len() {
-            let batch = table_read.read_batch_starting(executor.next_table_row);
+            let batch = table_read.read_batch_starting(executor.next_table_row, batch_capacity);
             *executor.next_table_row += batch.len() as usize;
             TabledCallResult::RetrievedFromTable(batch)
         } else {
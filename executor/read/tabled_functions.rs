@@ -75,6 +75,14 @@ impl TabledFunctions {
         Ok(self.state.get(call_key).unwrap().clone())
     }
 
+    pub(crate) fn clear(&mut self) {
+        self.state.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
     pub(crate) fn iterate_states(&self) -> impl Iterator<Item = Arc<TabledFunctionState>> + '_ {
         self.state.values().cloned()
     }
@@ -138,7 +146,7 @@ impl TabledFunctionState {
 
     pub(crate) fn add_batch_to_table(&self, batch: FixedBatch) -> FixedBatch {
         if !batch.is_empty() {
-            let mut deduplicated_batch = FixedBatch::new(batch.get_row(0).len() as u32);
+            let mut deduplicated_batch = FixedBatch::new(batch.get_row(0).len() as u32, batch.capacity());
             let mut table = self.table.write().unwrap();
             for row in batch {
                 if table.try_add_row(row.as_reference()) {
@@ -164,9 +172,9 @@ impl AnswerTable {
         self.answers.len()
     }
 
-    pub(crate) fn read_batch_starting(&self, start_index: TableIndex) -> FixedBatch {
+    pub(crate) fn read_batch_starting(&self, start_index: TableIndex, batch_capacity: u32) -> FixedBatch {
         let mut read_index = *start_index;
-        let mut batch = FixedBatch::new(self.width);
+        let mut batch = FixedBatch::new(self.width, batch_capacity);
         while !batch.is_full() && read_index < self.len() {
             batch.append(|mut write_to| {
                 write_to
@@ -0,0 +1,263 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use compiler::{
+    annotation::{
+        expression::block_compiler::compile_expressions, function::EmptyAnnotatedFunctionSignatures,
+        match_inference::infer_types,
+    },
+    executable::{
+        function::ExecutableFunctionRegistry,
+        match_::planner::{conjunction_executable::ConjunctionExecutable, plan::PlannerConfig},
+    },
+};
+use concept::thing::statistics::Statistics;
+use executor::{
+    conjunction_executor::ConjunctionExecutor, pipeline::stage::ExecutionContext, row::MaybeOwnedRow,
+    ExecutionInterrupt,
+};
+use ir::{
+    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    translation::{match_::translate_match, PipelineTranslationContext},
+};
+use lending_iterator::LendingIterator;
+use resource::profile::QueryProfile;
+use storage::{durability_client::WALClient, MVCCStorage};
+use test_utils_concept::load_managers;
+use test_utils_executor::create_database;
+
+// Counts the allocations made by the current thread while counting is enabled, so that concurrently running tests
+// and background threads do not disturb the count. Likewise tracks the live and peak bytes while tracking is enabled.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<Option<u64>> = const { Cell::new(None) };
+    static LIVE_BYTES: Cell<Option<(usize, usize)>> = const { Cell::new(None) }; // live, peak
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get().map(|count| count + 1)));
+        let _ = LIVE_BYTES.try_with(|bytes| {
+            bytes.set(bytes.get().map(|(live, peak)| (live + layout.size(), usize::max(peak, live + layout.size()))))
+        });
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // memory allocated before tracking started may be freed while tracking
+        let _ = LIVE_BYTES
+            .try_with(|bytes| bytes.set(bytes.get().map(|(live, peak)| (live.saturating_sub(layout.size()), peak))));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    ALLOCATIONS.with(|count| count.set(Some(0)));
+    let result = f();
+    let count = ALLOCATIONS.with(|count| count.replace(None)).unwrap();
+    (result, count)
+}
+
+fn track_peak_bytes<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    LIVE_BYTES.with(|bytes| bytes.set(Some((0, 0))));
+    let result = f();
+    let (_, peak) = LIVE_BYTES.with(|bytes| bytes.replace(None)).unwrap();
+    (result, peak)
+}
+
+// Returns the number of answers, and the allocations made while producing them (excluding compilation)
+fn count_execution_allocations(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+) -> (usize, u64) {
+    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let builder =
+        translate_match(&mut translation_context, &mut value_parameters, &empty_function_index, &match_).unwrap();
+    let block = builder.finish().unwrap();
+
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let entry_annotations = infer_types(
+        &*snapshot,
+        &block,
+        &translation_context.variable_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let expressions = compile_expressions(
+        &*snapshot,
+        &type_manager,
+        &block,
+        &mut translation_context.variable_registry,
+        &value_parameters,
+        &entry_annotations,
+        &mut BTreeMap::new(),
+    )
+    .unwrap();
+    let conjunction_executable = compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &entry_annotations,
+        &translation_context.variable_registry,
+        &expressions,
+        &value_parameters,
+        statistics,
+        &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
+    )
+    .unwrap();
+
+    let executor = ConjunctionExecutor::new(
+        &conjunction_executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters));
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    count_allocations(|| {
+        let mut answers = 0;
+        while let Some(row) = iterator.next() {
+            row.unwrap();
+            answers += 1;
+        }
+        answers
+    })
+}
+
+#[test]
+fn intersection_hands_off_string_values_without_cloning() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age, owns name;
+    ";
+    let mut data = String::from("insert");
+    for i in 0..100 {
+        data.push_str(&format!(" $_ isa person, has name 'person {i}', has age {i};"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // the intersection of the person's name and age copies the input value into every answer: the allocations beyond
+    // those for an integer input are the clones of the string
+    let (answers, string_allocations) = count_execution_allocations(
+        &storage,
+        &statistics,
+        "match let $s = \"a string value long enough to be allocated\"; $p isa person, has name $n, has age $a;",
+    );
+    let (integer_answers, integer_allocations) = count_execution_allocations(
+        &storage,
+        &statistics,
+        "match let $s = 12; $p isa person, has name $n, has age $a;",
+    );
+    assert_eq!(answers, 100);
+    assert_eq!(integer_answers, 100);
+
+    // the value is cloned from the input into the intersection, and then moved into the answer
+    let string_clones = string_allocations.saturating_sub(integer_allocations);
+    assert!(string_clones < 2 * answers as u64, "{string_clones} clones of the string value for {answers} answers");
+}
+
+// Returns the compiled conjunction, and the peak of the bytes allocated while planning it
+fn compile_tracking_peak_bytes(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+) -> (ConjunctionExecutable, usize) {
+    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let builder =
+        translate_match(&mut translation_context, &mut value_parameters, &empty_function_index, &match_).unwrap();
+    let block = builder.finish().unwrap();
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, _) = load_managers(storage.clone(), None);
+    let entry_annotations = infer_types(
+        &snapshot,
+        &block,
+        &translation_context.variable_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    track_peak_bytes(|| {
+        compiler::executable::match_::planner::compile(
+            &block,
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &block.conjunction().named_producible_variables(block.block_context()).collect(),
+            &entry_annotations,
+            &translation_context.variable_registry,
+            &HashMap::new(),
+            &value_parameters,
+            statistics,
+            &ExecutableFunctionRegistry::empty(),
+            &PlannerConfig::default(),
+            None,
+        )
+        .unwrap()
+    })
+}
+
+#[test]
+fn nested_negations_share_planning_buffers() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name;
+    ";
+    let mut data = String::from("insert");
+    for i in 0..20 {
+        data.push_str(&format!(" $_ isa person, has name 'person {i}', has age {i};"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let (nested, nested_peak_bytes) = compile_tracking_peak_bytes(
+        &storage,
+        &statistics,
+        "match $p isa person, has name $n;
+        not { $p has age 1; not { $p has age 2; not { $p has age 3; not { $p has age 4; not { $p has age 5; }; }; }; }; };",
+    );
+    let (flat, _) = compile_tracking_peak_bytes(&storage, &statistics, "match $p isa person, has name $n;");
+
+    // the six conjunctions are planned one after the other, each reusing the buffers of the previous one
+    let planner_statistics = nested.planner_statistics();
+    assert_eq!(planner_statistics.planning_buffer_sets(), 1);
+    assert_eq!(flat.planner_statistics().planning_buffer_sets(), 1);
+
+    // once planned, the buffers only retain what a greedy search needs, regardless of how many conjunctions used them
+    let retained_bytes = planner_statistics.planning_buffer_bytes();
+    assert_eq!(retained_bytes, flat.planner_statistics().planning_buffer_bytes());
+    assert!(16 * retained_bytes < nested_peak_bytes, "{retained_bytes} bytes retained of {nested_peak_bytes} peak");
+}
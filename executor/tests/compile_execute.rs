@@ -5,27 +5,54 @@
  */
 
 use std::{
-    collections::{BTreeMap, HashMap},
-    sync::Arc,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    iter::zip,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
+use answer::variable_value::VariableValue;
+use bytes::byte_array::ByteArray;
+use compiler::VariablePosition;
 use compiler::{
     annotation::{
         expression::block_compiler::compile_expressions, function::EmptyAnnotatedFunctionSignatures,
         match_inference::infer_types,
     },
     executable::{
-        function::ExecutableFunctionRegistry, match_::planner::conjunction_executable::ConjunctionExecutable,
+        function::ExecutableFunctionRegistry,
+        match_::planner::{
+            conjunction_executable::{ConjunctionExecutable, ExecutionStep, ParameterKind, ResultSemantics},
+            explain::{PlanExplanation, PlanStepExplanation},
+            plan::{PlannerConfig, PlanningErrorClassification, QueryPlanningError},
+            plan_cache::PlanCache,
+            serialization::{deserialize_executable, serialize_executable, PlanSerializationError, PlanVersions},
+            MatchCompilationError,
+        },
+    },
+    transformation::{
+        disjunction_factoring::factor_common_disjunction_constraints, type_negation::resolve_type_negations,
     },
 };
 use concept::{
-    thing::{statistics::Statistics, thing_manager::ThingManager},
+    error::ConceptReadError,
+    thing::{
+        statistics::{Statistics, StatisticsFreshness},
+        thing_manager::ThingManager,
+    },
     type_::type_manager::TypeManager,
 };
-use encoding::graph::definition::definition_key_generator::DefinitionKeyGenerator;
+use encoding::{
+    graph::definition::definition_key_generator::DefinitionKeyGenerator,
+    value::{value::Value, value_type::ValueType},
+};
 use executor::{
-    conjunction_executor::ConjunctionExecutor, pipeline::stage::ExecutionContext, row::MaybeOwnedRow,
-    ExecutionInterrupt,
+    conjunction_executor::ConjunctionExecutor, error::ReadExecutionError, pipeline::stage::ExecutionContext,
+    read::retry::RetryPolicy, row::MaybeOwnedRow, ExecutionInterrupt, InterruptType, Provenance,
 };
 use function::function_manager::FunctionManager;
 use ir::{
@@ -35,16 +62,32 @@ use ir::{
 use itertools::Itertools;
 use lending_iterator::LendingIterator;
 use query::query_manager::QueryManager;
-use resource::profile::{CommitProfile, QueryProfile};
+use resource::{
+    constants::{snapshot::BUFFER_KEY_INLINE, traversal::FIXED_BATCH_ROWS_MAX},
+    profile::{CommitProfile, QueryProfile, StorageCounters},
+};
 use storage::{
     durability_client::WALClient,
+    key_range::KeyRange,
+    key_value::{StorageKey, StorageKeyArray, StorageKeyReference},
+    keyspace::IteratorPool,
     sequence_number::SequenceNumber,
-    snapshot::{CommittableSnapshot, ReadableSnapshot},
+    snapshot::{
+        buffer::BufferRangeIterator, iterator::SnapshotRangeIterator, write::Write, CommittableSnapshot, ReadSnapshot,
+        ReadableSnapshot, SnapshotGetError,
+    },
     MVCCStorage,
 };
 use test_utils::assert_matches;
 use test_utils_concept::{load_managers, setup_concept_storage};
 use test_utils_encoding::create_core_storage;
+use test_utils_executor::{
+    compile_conjunction, compile_conjunction_in, create_database, create_generated_database, execute_conjunction,
+    DatasetDescription, Distribution, EntityPopulation, RelationPopulation, Selection, ValueKind,
+};
+use typeql::common::Span;
+
+const DATASET_SEED: u64 = 0x7e57;
 
 fn setup(
     storage: &Arc<MVCCStorage<WALClient>>,
@@ -62,7 +105,7 @@ fn setup(
     });
 
     let query_manager = QueryManager::new(None);
-    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
     let mut snapshot = storage.clone().open_snapshot_schema();
     let define = typeql::parse_query(schema).unwrap().into_structure().into_schema();
     query_manager
@@ -147,8 +190,11 @@ fn test_has_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -248,8 +294,11 @@ fn test_expression_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &compiled_expressions,
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -337,8 +386,11 @@ fn test_links_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -372,29 +424,69 @@ fn test_links_planning_traversal() {
 }
 
 #[test]
-fn test_links_intersection() {
-    let (_tmp_dir, mut storage) = create_core_storage();
-    setup_concept_storage(&mut storage);
-    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-
+fn test_anonymous_relations_are_counted_without_being_materialized() {
     let schema = "define
-        entity user plays purchase:buyer;
-        entity order, owns status, owns timestamp, plays purchase:order;
-        relation purchase relates buyer, relates order;
-        attribute status, value string;
-        attribute timestamp, value datetime;
+        entity person owns name @card(0..), plays membership:member;
+        relation membership relates member @card(0..);
+        attribute name value string;
     ";
     let data = "insert
-        $u0 isa user; $u1 isa user; $u2 isa user;
-        $o0 isa order, has status 'canceled', has timestamp 1970-01-01T00:00;
-        $o1 isa order, has status 'dispatched', has timestamp 1970-01-01T00:00;
-        $o2 isa order, has status 'paid', has timestamp 1970-01-01T00:00;
-        (buyer: $u0, order: $o0) isa purchase;
-        (buyer: $u0, order: $o0) isa purchase;
-        (buyer: $u1, order: $o1) isa purchase;
+        $p0 isa person, has name 'John';
+        $p1 isa person, has name 'Alice';
+        $p2 isa person, has name 'Leila';
+        (member: $p0) isa membership;
+        (member: $p0) isa membership;
+        (member: $p2) isa membership;
     ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
 
-    let statistics = setup(&storage, type_manager, thing_manager, schema, data);
+    let query = "match $person isa person, has name $name; ($person) isa membership;";
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    assert_eq!(executable.variable_positions().len(), 2, "{executable}");
+    assert_eq!(executable.steps().last().unwrap().output_width(), 2, "{executable}");
+
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let mut multiplicities: HashMap<Vec<VariableValue<'static>>, u64> = HashMap::new();
+    for row in iterator.map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone())).into_iter() {
+        let row = row.unwrap();
+        *multiplicities.entry(row.row().to_vec()).or_default() += row.multiplicity();
+    }
+
+    // John is a member twice and Leila once, while Alice is not a member
+    assert_eq!(multiplicities.into_values().sorted().collect_vec(), vec![1, 2]);
+}
+
+#[test]
+fn test_links_intersection() {
+    const PURCHASES: u64 = 30;
+    // more purchases than orders, concentrated on a few popular orders
+    let dataset = DatasetDescription::new()
+        .attribute("status", ValueKind::String, Distribution::Uniform { min: 0, max: 3 })
+        .attribute("timestamp", ValueKind::Integer, Distribution::Uniform { min: 0, max: 1_000_000 })
+        .entities(EntityPopulation::new("user", 3))
+        .entities(
+            EntityPopulation::new("order", 10)
+                .owns("status", Distribution::Constant(1))
+                .owns("timestamp", Distribution::Constant(1)),
+        )
+        .relations(
+            RelationPopulation::new("purchase", PURCHASES)
+                .role("buyer", "user", Distribution::Constant(1), Selection::Uniform)
+                .role("order", "order", Distribution::Constant(1), Selection::Zipfian { exponent: 1.5 }),
+        );
+    let (_tmp_dir, storage, statistics) = create_generated_database(&dataset, 1, DATASET_SEED);
 
     let query = "match
     $p isa purchase, links (order: $order, buyer: $buyer);
@@ -433,17 +525,21 @@ fn test_links_intersection() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
+    let profile = QueryProfile::new(true);
     let executor = ConjunctionExecutor::new(
         &conjunction_executable,
         &snapshot,
         &thing_manager,
         MaybeOwnedRow::empty(),
         Arc::new(ExecutableFunctionRegistry::empty()),
-        &QueryProfile::new(false),
+        &profile,
     )
     .unwrap();
 
@@ -457,14 +553,16 @@ fn test_links_intersection() {
         .try_collect::<_, Vec<_>, _>()
         .unwrap();
 
-    for row in &rows {
-        for value in row {
-            print!("{}, ", value);
-        }
-        println!()
-    }
+    // every order has a single status and timestamp, so each purchase is one answer
+    assert_eq!(rows.len() as u64, PURCHASES);
 
-    assert_eq!(rows.len(), 3);
+    // the further purchases of the same order are enumerated by a cartesian product at that order
+    let stage_profiles = profile.stage_profiles().read().unwrap();
+    let stage_profile = &stage_profiles[&conjunction_executable.executable_id()];
+    let step_profiles: Vec<_> = (0..).map_while(|index| stage_profile.step_profile(index)).collect();
+    assert!(step_profiles.iter().map(|step| step.cartesian_activations()).sum::<u64>() > 0);
+    assert!(step_profiles.iter().map(|step| step.cartesian_rows()).sum::<u64>() > 0);
+    assert!(profile.to_string().contains("Cartesian products[activations: "));
 }
 
 #[test]
@@ -520,8 +618,11 @@ fn test_negation_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -554,48 +655,52 @@ fn test_negation_planning_traversal() {
     assert_eq!(rows.len(), 1);
 }
 
+const FORALL_SCHEMA: &str = "define
+    relation set-membership, relates set, relates item;
+    entity set, plays set-membership:set;
+    entity item, plays set-membership:item;
+";
+
+const FORALL_DATA: &str = "insert
+    $a isa item; $b isa item; $c isa item;
+    $a_ isa set;
+    (set: $a_, item: $a) isa set-membership;
+    $ab isa set;
+    (set: $ab, item: $a) isa set-membership;
+    (set: $ab, item: $b) isa set-membership;
+    $ac isa set;
+    (set: $ac, item: $a) isa set-membership;
+    (set: $ac, item: $c) isa set-membership;
+    $abc isa set;
+    (set: $abc, item: $a) isa set-membership;
+    (set: $abc, item: $b) isa set-membership;
+    (set: $abc, item: $c) isa set-membership;
+";
+
+// strict supersets: $sup has an item not in $sub, and all items of $sub are in $sup
+const FORALL_QUERY: &str = "match
+    $sup isa set;
+    $sub isa set;
+
+    (item: $unique, set: $sup) isa set-membership;
+    not { (item: $unique, set: $sub) isa set-membership; };
+
+    not {
+        (item: $element, set: $sub) isa set-membership;
+        not { (item: $element, set: $sup) isa set-membership; };
+    };
+";
+
 #[test]
 fn test_forall_planning_traversal() {
     let (_tmp_dir, mut storage) = create_core_storage();
     setup_concept_storage(&mut storage);
     let (type_manager, thing_manager) = load_managers(storage.clone(), None);
 
-    let schema = "define
-        relation set-membership, relates set, relates item;
-        entity set, plays set-membership:set;
-        entity item, plays set-membership:item;
-    ";
-    let data = "insert
-        $a isa item; $b isa item; $c isa item;
-        $a_ isa set;
-        (set: $a_, item: $a) isa set-membership;
-        $ab isa set;
-        (set: $ab, item: $a) isa set-membership;
-        (set: $ab, item: $b) isa set-membership;
-        $ac isa set;
-        (set: $ac, item: $a) isa set-membership;
-        (set: $ac, item: $c) isa set-membership;
-        $abc isa set;
-        (set: $abc, item: $a) isa set-membership;
-        (set: $abc, item: $b) isa set-membership;
-        (set: $abc, item: $c) isa set-membership;
-    ";
-
-    let statistics = setup(&storage, type_manager, thing_manager, schema, data);
-
-    let query = "match 
-        $sup isa set;
-        $sub isa set;
-
-        (item: $unique, set: $sup) isa set-membership;
-        not { (item: $unique, set: $sub) isa set-membership; };
+    let statistics = setup(&storage, type_manager, thing_manager, FORALL_SCHEMA, FORALL_DATA);
 
-        not {
-            (item: $element, set: $sub) isa set-membership;
-            not { (item: $element, set: $sup) isa set-membership; };
-        };
-    ";
-    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let match_ =
+        typeql::parse_query(FORALL_QUERY).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
 
     // IR
     let empty_function_index = HashMapFunctionSignatureIndex::empty();
@@ -628,8 +733,11 @@ fn test_forall_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
 
@@ -669,6 +777,127 @@ fn test_forall_planning_traversal() {
     assert_eq!(rows.len(), 6);
 }
 
+#[test]
+fn test_exists_semantics_in_negations_reduces_nested_rows() {
+    let (_tmp_dir, storage, statistics) = create_database(FORALL_SCHEMA, FORALL_DATA);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    let execute_with = |negation_semantics| {
+        let config = PlannerConfig { negation_semantics, ..PlannerConfig::default() };
+        let executable = compile_query_with_config(
+            &*snapshot,
+            &type_manager,
+            thing_manager.clone(),
+            &statistics,
+            FORALL_QUERY,
+            &config,
+        );
+        execute_counting_nested_rows(&snapshot, &thing_manager, &executable)
+    };
+    let (bag_answers, bag_nested_rows) = execute_with(ResultSemantics::Bag);
+    let (exists_answers, exists_nested_rows) = execute_with(ResultSemantics::Exists);
+
+    assert_eq!(exists_answers.len(), 6);
+    assert_eq!(bag_answers, exists_answers);
+    assert!(
+        exists_nested_rows < bag_nested_rows,
+        "expected fewer rows in negations under exists semantics ({exists_nested_rows}) than bag ({bag_nested_rows})"
+    );
+}
+
+#[test]
+fn test_negation_stops_at_its_first_witness() {
+    // ten people with many ages each, and five without any
+    let dataset = DatasetDescription::new()
+        .attribute("age", ValueKind::Integer, Distribution::Uniform { min: 0, max: 1_000 })
+        .attribute("name", ValueKind::String, Distribution::Uniform { min: 0, max: 1_000 })
+        .entities(
+            EntityPopulation::new("person", 2)
+                .owns("name", Distribution::Constant(1))
+                .owns("age", Distribution::Constant(200)),
+        )
+        .entities(EntityPopulation::new("person", 1).owns("name", Distribution::Constant(1)));
+    let (_tmp_dir, storage, statistics) = create_generated_database(&dataset, 5, DATASET_SEED);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    let query = "match $p isa person, has name $n; not { $p has age $a; };";
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    let negation = executable
+        .steps()
+        .iter()
+        .find_map(|step| match step {
+            ExecutionStep::Negation(negation) => Some(&negation.negation),
+            _ => None,
+        })
+        .unwrap();
+
+    let profile = QueryProfile::new(true);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &profile,
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot.clone(), thing_manager.clone(), Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let answers = iterator.map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone())).into_iter();
+    assert_eq!(answers.map(|row| row.unwrap()).count(), 5);
+
+    let stage_profiles = profile.stage_profiles().read().unwrap();
+    let negation_profile = &stage_profiles[&negation.executable_id()];
+    let negation_steps: Vec<_> = (0..).map_while(|index| negation_profile.step_profile(index)).collect();
+    assert_eq!(negation_steps.len(), negation.steps().len());
+    // each of the 10 people with ages is a witness after a single row per step, instead of a full batch of its ages
+    for step in &negation_steps {
+        assert!(step.rows() <= 10, "{} rows in a step of the negation", step.rows());
+    }
+    let advances: u64 = negation_steps.iter().map(|step| step.storage_counters().get_raw_advance().unwrap()).sum();
+    assert!(advances < 100, "{advances} storage advances in the negation");
+}
+
+/// Returns the distinct answers and the number of rows produced by the steps of nested executables.
+fn execute_counting_nested_rows(
+    snapshot: &Arc<impl ReadableSnapshot + 'static>,
+    thing_manager: &Arc<ThingManager>,
+    executable: &ConjunctionExecutable,
+) -> (Vec<Vec<VariableValue<'static>>>, u64) {
+    let profile = QueryProfile::new(true);
+    let executor = ConjunctionExecutor::new(
+        executable,
+        snapshot,
+        thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &profile,
+    )
+    .unwrap();
+
+    let context = ExecutionContext::new(snapshot.clone(), thing_manager.clone(), Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let answers = iterator
+        .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+        .into_iter()
+        .map(|row| row.unwrap().row().to_vec())
+        .unique()
+        .collect();
+
+    let nested_rows = profile
+        .stage_profiles()
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(&executable_id, _)| executable_id != executable.executable_id())
+        .flat_map(|(_, stage_profile)| (0..).map_while(|index| stage_profile.step_profile(index)))
+        .map(|step_profile| step_profile.rows())
+        .sum();
+    (answers, nested_rows)
+}
+
 #[test]
 fn test_named_var_select() {
     let (_tmp_dir, mut storage) = create_core_storage();
@@ -723,8 +952,11 @@ fn test_named_var_select() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -817,8 +1049,11 @@ fn test_disjunction_planning_traversal() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -915,8 +1150,11 @@ fn test_disjunction_planning_nested_negations() {
         &entry_annotations,
         &translation_context.variable_registry,
         &HashMap::new(),
+        &value_parameters,
         &statistics,
         &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
     )
     .unwrap();
     let executor = ConjunctionExecutor::new(
@@ -950,107 +1188,730 @@ fn test_disjunction_planning_nested_negations() {
 }
 
 #[test]
-fn test_mismatched_input_types() {
-    let (_tmp_dir, mut storage) = create_core_storage();
-    setup_concept_storage(&mut storage);
-    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+fn test_provenance_through_nested_negations() {
+    let schema = "define
+        relation set-membership, relates set, relates item;
+        entity set, plays set-membership:set;
+        entity item, plays set-membership:item;
+    ";
+    let data = "insert
+        $a isa item; $b isa item;
+        $a_ isa set;
+        (set: $a_, item: $a) isa set-membership;
+        $ab isa set;
+        (set: $ab, item: $a) isa set-membership;
+        (set: $ab, item: $b) isa set-membership;
+    ";
+
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+
+    // negations never contribute branches, even when they contain disjunctions
+    let query = "match
+        $sup isa set;
+        $sub isa set;
+        (item: $unique, set: $sup) isa set-membership;
+        not { (item: $unique, set: $sub) isa set-membership; };
+        not {
+            (item: $element, set: $sub) isa set-membership;
+            not {
+                { (item: $element, set: $sup) isa set-membership; }
+                or { (item: $element, set: $sup) isa set-membership; $element isa item; };
+            };
+        };
+    ";
+    let (rows, _) = execute_match(&storage, &statistics, query);
+
+    // ab ⊃ a
+    assert_eq!(rows.iter().unique_by(|row| row.row().to_vec()).count(), 1);
+    for row in &rows {
+        assert_eq!(row.provenance(), Provenance::INITIAL, "negations must preserve the input provenance");
+    }
+}
 
+#[test]
+fn test_provenance_through_disjunction() {
     let schema = "define
         attribute age value integer;
         attribute name value string;
-        relation friendship, relates friend, owns age @card(0..);
-        entity person, owns age @card(0..), owns name @card(0..), plays friendship:friend;
+        entity person owns age @card(0..), owns name @card(0..);
     ";
     let data = "insert
-        $p1 isa person, has name 'John', has age 25;
-        $p2 isa person, has name 'James', has age 27;
-        $_ isa friendship, links (friend: $p1, friend: $p2), has age 5;
+        $_ isa person, has age 12, has name 'John';
+        $_ isa person, has age 14;
+        $_ isa person, has name 'Leila';
+        $_ isa person;
     ";
-    let statistics = setup(&storage, type_manager, thing_manager, schema, data);
-    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-    {
-        let query = "match
-            $x has age $age;
-            { $x links (friend: $p); }  or
-            { $x has name $n; };
-            select $x;
-        ";
-        let snapshot = Arc::new(storage.clone().open_snapshot_read());
-        let conjunction_executable =
-            compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
-        let executor = ConjunctionExecutor::new(
-            &conjunction_executable,
-            &snapshot,
-            &thing_manager,
-            MaybeOwnedRow::empty(),
-            Arc::new(ExecutableFunctionRegistry::empty()),
-            &QueryProfile::new(false),
-        )
-        .unwrap();
-        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
-        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
-        let rows = iterator
-            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
-            .into_iter()
-            .unique_by(|res| res.as_ref().unwrap().row().to_vec())
-            .try_collect::<_, Vec<_>, _>()
-            .unwrap();
 
-        for row in &rows {
-            for value in row {
-                print!("{}, ", value);
-            }
-            println!()
-        }
-    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
 
-    {
-        let query = "match
-            { $x isa $_; } or { $_ has $x; };
-            select $x;
-            distinct;
-        ";
-        let snapshot = Arc::new(storage.clone().open_snapshot_read());
-        let conjunction_executable =
-            compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
-        let executor = ConjunctionExecutor::new(
-            &conjunction_executable,
-            &snapshot,
-            &thing_manager,
-            MaybeOwnedRow::empty(),
-            Arc::new(ExecutableFunctionRegistry::empty()),
-            &QueryProfile::new(false),
-        )
-        .unwrap();
-        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
-        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
-        let rows = iterator
-            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
-            .into_iter()
-            .unique_by(|res| res.as_ref().unwrap().row().to_vec())
-            .try_collect::<_, Vec<_>, _>()
-            .unwrap();
+    let query = "match
+        $person isa person;
+        { $person has name $n; } or { $person has age $a; not { $person has name $_; }; };
+        { $person has name 'John'; } or { $person has name 'Leila'; } or { $person has age 14; };
+    ";
+    let (rows, positions) = execute_match(&storage, &statistics, query);
 
-        for row in &rows {
-            for value in row {
-                print!("{}, ", value);
-            }
-            println!()
-        }
-        debug_assert_ne!(rows.len(), 5); // Returns the 5 attributes if type-inference considers categories.
-        debug_assert_eq!(rows.len(), 8);
+    // John (name), Leila (name), and the unnamed 14-year-old (age)
+    assert_eq!(rows.len(), 3);
+    let branch_sets = rows.iter().map(|row| row.provenance().branch_ids().collect_vec()).collect_vec();
+    for branch_set in &branch_sets {
+        assert_eq!(branch_set.len(), 2, "each row takes exactly one branch of each disjunction: {branch_set:?}");
     }
+    // rows binding $n came through the same branch of the first disjunction, and never through the one binding $a
+    let (named, aged): (Vec<_>, Vec<_>) =
+        rows.iter().zip(&branch_sets).partition(|(row, _)| !row.get(positions["n"]).is_empty());
+    assert_eq!(named.len(), 2);
+    assert_eq!(aged.len(), 1);
+    let name_branch = named[0].1[0];
+    assert!(named.iter().all(|(_, branch_set)| branch_set.contains(&name_branch)));
+    assert!(!aged[0].1.contains(&name_branch));
+    // the second disjunction distinguishes all three rows
+    let second_branches = branch_sets.iter().map(|branch_set| branch_set[1]).unique().count();
+    assert_eq!(second_branches, 3);
 }
 
-fn compile_query(
-    snapshot: &impl ReadableSnapshot,
-    type_manager: &TypeManager,
-    thing_manager: Arc<ThingManager>,
-    statistics: &Statistics,
-    query: &str,
-) -> ConjunctionExecutable {
-    // IR
+#[test]
+fn test_provenance_through_assignment_after_disjunction() {
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 12;
+        $_ isa person, has age 14;
+    ";
+
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+
+    let query = "match
+        $person isa person;
+        { $person has age $age; $age < 13; } or { $person has age $age; $age > 13; };
+        let $next = $age + 1;
+    ";
+    let (rows, _) = execute_match(&storage, &statistics, query);
+
+    assert_eq!(rows.len(), 2);
+    let branches = rows.iter().map(|row| row.provenance().branch_ids().exactly_one().unwrap()).unique().count();
+    assert_eq!(branches, 2, "assignments must carry over the provenance of their input rows");
+}
+
+#[test]
+fn test_assignment_keeps_every_row_across_batches() {
+    const PERSON_COUNT: i64 = 200;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    // several times more input rows than fit in a single batch
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match
+        $person isa person, has age $age;
+        let $next = $age + 1;
+    ";
+    let (rows, positions) = execute_match(&storage, &statistics, query);
+
+    let nexts = rows.iter().map(|row| row.get(positions["next"]).as_value().clone().unwrap_integer()).sorted();
+    assert_eq!(nexts.collect_vec(), (1..=PERSON_COUNT).collect_vec());
+}
+
+#[test]
+fn test_check_keeps_every_row_across_batches() {
+    const PERSON_COUNT: i64 = 200;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // the comparison reads an assigned variable, so it cannot be inlined into an intersection and becomes a check
+    // step that lets several batches worth of rows through
+    let query = "match
+        $person isa person, has age $age;
+        let $next = $age + 1;
+        $next > 10;
+    ";
+    let (rows, positions) = execute_match(&storage, &statistics, query);
+
+    let nexts = rows.iter().map(|row| row.get(positions["next"]).as_value().clone().unwrap_integer()).sorted();
+    assert_eq!(nexts.collect_vec(), (11..=PERSON_COUNT).collect_vec());
+}
+
+#[test]
+fn test_interrupt_stops_assignment_and_check_steps_promptly() {
+    const PERSON_COUNT: i64 = 5000;
+    // the executors check for an interrupt once every this many rows
+    const CHECK_INTERVAL: usize = 1024;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match
+        $person isa person, has age $age;
+        let $next = $age + 1;
+        $next > 0;
+    ";
+    let (executable, value_parameters, positions) = compile_match(&storage, &statistics, query);
+    assert!(executable.steps().iter().any(|step| matches!(step, ExecutionStep::Assignment(_))), "{executable}");
+    assert!(executable.steps().iter().any(|step| matches!(step, ExecutionStep::Check(_))), "{executable}");
+
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+    let (sender, receiver) = tokio::sync::broadcast::channel(1);
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters));
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new(receiver));
+
+    let mut nexts = HashSet::new();
+    let mut rows_after_interrupt = 0;
+    let error = loop {
+        if nexts.len() == 100 && rows_after_interrupt == 0 {
+            sender.send(InterruptType::TransactionClosed).unwrap();
+        }
+        match iterator.next() {
+            Some(Ok(row)) => {
+                // every emitted row is whole: it binds every variable, and its assigned value belongs to no other row
+                assert!(
+                    row.get(positions["person"]).get_thing().is_some()
+                        && row.get(positions["age"]).get_thing().is_some()
+                );
+                let next = row.get(positions["next"]).as_value().clone().unwrap_integer();
+                assert!((1..=PERSON_COUNT).contains(&next) && nexts.insert(next), "{next} emitted twice");
+                if nexts.len() > 100 {
+                    rows_after_interrupt += 1;
+                }
+            }
+            Some(Err(error)) => break error.clone(),
+            None => panic!("Expected the execution to be interrupted"),
+        }
+    };
+    assert_matches!(error, ReadExecutionError::Interrupted { interrupt: InterruptType::TransactionClosed });
+    assert!(
+        rows_after_interrupt <= CHECK_INTERVAL + 2 * FIXED_BATCH_ROWS_MAX as usize,
+        "{rows_after_interrupt} rows emitted after the interrupt"
+    );
+}
+
+#[test]
+fn test_batch_capacity_does_not_change_answers() {
+    const PERSON_COUNT: usize = 150;
+    const GROUP_COUNT: usize = 4;
+
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        attribute email value string;
+        entity person owns age @card(0..), owns name @card(0..), owns email @card(0..), plays membership:member;
+        entity group plays membership:group;
+        relation membership relates member, relates group;
+    ";
+    let mut data = String::from("insert\n");
+    for group in 0..GROUP_COUNT {
+        data.push_str(&format!("    $g{group} isa group;\n"));
+    }
+    for person in 0..PERSON_COUNT {
+        data.push_str(&format!("    $p{person} isa person, has age {}, has name 'n{}';\n", person % 20, person % 30));
+        if person % 3 == 0 {
+            data.push_str(&format!("    $p{person} has email 'p{person}@x.com';\n"));
+        }
+        data.push_str(&format!("    (member: $p{person}, group: $g{}) isa membership;\n", person % GROUP_COUNT));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let queries = [
+        "match $p isa person, has age $a, has name $n;",
+        "match $p isa person, has age $a; $a > 11;",
+        "match $p isa person, has age $a; $q isa person, has age $b; $a < $b;",
+        "match $p isa person; { $p has name $n; } or { $p has age $a; };",
+        "match $p isa person, has name $n; not { $p has email $e; };",
+        "match $m isa membership, links (member: $p, group: $g); $p has name $n;",
+        "match $p isa person; $g isa group;",
+        "match $p isa person, has age $a; $q isa person, has age $a; $p is $q;",
+        "match $p isa person, has age $a; let $next = $a + 1; $next > 11;",
+    ];
+    // the answers are compared by their values, with the multiplicities of equal answers summed
+    let answers = |batch_capacity| {
+        queries.map(|query| {
+            let (rows, _) = execute_match_with_batch_capacity(&storage, &statistics, query, batch_capacity);
+            let mut answers: HashMap<Vec<VariableValue<'static>>, u64> = HashMap::new();
+            for row in rows {
+                *answers.entry(row.row().to_vec()).or_default() += row.multiplicity();
+            }
+            answers
+        })
+    };
+    let expected = answers(FIXED_BATCH_ROWS_MAX);
+    for batch_capacity in [1, 32, 1024] {
+        for (query, (actual, expected)) in zip(queries, zip(answers(batch_capacity), &expected)) {
+            assert!(!expected.is_empty(), "{query}");
+            assert_eq!(&actual, expected, "{query} with batches of {batch_capacity} rows");
+        }
+    }
+}
+
+#[test]
+fn test_yield_budget_does_not_change_answers() {
+    const PERSON_COUNT: usize = 60;
+    const GROUP_COUNT: usize = 3;
+
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        attribute email value string;
+        entity person owns age @card(0..), owns name @card(0..), owns email @card(0..), plays membership:member;
+        entity group plays membership:group;
+        relation membership relates member, relates group;
+    ";
+    let mut data = String::from("insert\n");
+    for group in 0..GROUP_COUNT {
+        data.push_str(&format!("    $g{group} isa group;\n"));
+    }
+    for person in 0..PERSON_COUNT {
+        // several names per person, so the intersections on a person enumerate cartesian products
+        data.push_str(&format!(
+            "    $p{person} isa person, has age {}, has name 'n{}', has name 'm{}';\n",
+            person % 20,
+            person % 30,
+            person % 7
+        ));
+        if person % 3 == 0 {
+            data.push_str(&format!("    $p{person} has email 'p{person}@x.com';\n"));
+        }
+        data.push_str(&format!("    (member: $p{person}, group: $g{}) isa membership;\n", person % GROUP_COUNT));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let queries = [
+        "match $p isa person, has age $a, has name $n;",
+        "match $p isa person, has age $a; $a > 11;",
+        "match $p isa person, has name $n; not { $p has email $e; };",
+        "match $p isa person; { $p has name $n; } or { $p has age $a; };",
+        "match $m isa membership, links (member: $p, group: $g); $p has name $n;",
+        "match $p isa person; $g isa group;",
+    ];
+    // the answers are compared by their values, with the multiplicities of equal answers summed
+    let execute = |query: &str, yield_budget: Option<u64>| {
+        let (conjunction_executable, value_parameters, _) = compile_match(&storage, &statistics, query);
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let (_, thing_manager) = load_managers(storage.clone(), None);
+        let profile = QueryProfile::new(true);
+        let executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &profile,
+        )
+        .unwrap();
+        let mut context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters));
+        if let Some(yield_budget) = yield_budget {
+            context = context.with_yield_budget(yield_budget);
+        }
+        let rows: Vec<_> = executor
+            .into_iterator(context, ExecutionInterrupt::new_uninterruptible())
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .try_collect()
+            .unwrap();
+        let mut answers: HashMap<Vec<VariableValue<'static>>, u64> = HashMap::new();
+        for row in rows {
+            *answers.entry(row.row().to_vec()).or_default() += row.multiplicity();
+        }
+        let step_profiles = (profile.stage_profiles().read().unwrap().values())
+            .flat_map(|stage_profile| (0..).map_while(|index| stage_profile.step_profile(index)).collect_vec())
+            .collect_vec();
+        let batches: u64 = step_profiles.iter().map(|step_profile| step_profile.batches()).sum();
+        let yields: u64 = step_profiles.iter().map(|step_profile| step_profile.yields()).sum();
+        (answers, batches, yields)
+    };
+
+    for query in queries {
+        let (expected, unbudgeted_batches, unbudgeted_yields) = execute(query, None);
+        assert!(!expected.is_empty(), "{query}");
+        assert_eq!(unbudgeted_yields, 0, "{query}");
+        for yield_budget in [1, 3] {
+            // the executors return after a few work units each, and resume where they left off
+            let (actual, batches, yields) = execute(query, Some(yield_budget));
+            assert_eq!(actual, expected, "{query} with a yield budget of {yield_budget}");
+            assert!(yields > 0, "{query} with a yield budget of {yield_budget}");
+            assert!(batches > unbudgeted_batches, "{query} with a yield budget of {yield_budget}");
+        }
+    }
+}
+
+#[test]
+fn test_assignment_yields_within_its_budget() {
+    const PERSON_COUNT: i64 = 40;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age;
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match $p isa person, has age $a; let $b = $a + 1;";
+    let (conjunction_executable, value_parameters, positions) = compile_match(&storage, &statistics, query);
+    let assignment_index = conjunction_executable
+        .steps()
+        .iter()
+        .position(|step| matches!(step, ExecutionStep::Assignment(_)))
+        .expect("the query assigns $b in an assignment step");
+    let value_parameters = Arc::new(value_parameters);
+    let execute = |yield_budget: Option<u64>| {
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let (_, thing_manager) = load_managers(storage.clone(), None);
+        let profile = QueryProfile::new(true);
+        let executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &profile,
+        )
+        .unwrap();
+        let mut context = ExecutionContext::new(snapshot, thing_manager, value_parameters.clone());
+        if let Some(yield_budget) = yield_budget {
+            context = context.with_yield_budget(yield_budget);
+        }
+        let rows: Vec<_> = executor
+            .into_iterator(context, ExecutionInterrupt::new_uninterruptible())
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .try_collect()
+            .unwrap();
+        let stage_profiles = profile.stage_profiles().read().unwrap();
+        let assignment_profile =
+            stage_profiles[&conjunction_executable.executable_id()].step_profile(assignment_index).unwrap();
+        (rows, assignment_profile.batches(), assignment_profile.yields())
+    };
+
+    let (expected, unbudgeted_batches, unbudgeted_yields) = execute(None);
+    assert_eq!(expected.len(), PERSON_COUNT as usize);
+    assert_eq!(unbudgeted_yields, 0);
+
+    // the assignment returns after each row, and assigns the rows left in its input on the next call
+    let (rows, batches, yields) = execute(Some(1));
+    assert!(yields > 0);
+    assert!(batches > unbudgeted_batches);
+    let assigned = |rows: &[MaybeOwnedRow<'static>]| {
+        rows.iter().map(|row| row.get(positions["b"]).as_value().clone().unwrap_integer()).sorted().collect_vec()
+    };
+    assert_eq!(assigned(&rows), assigned(&expected));
+    assert_eq!(assigned(&rows), (1..=PERSON_COUNT).collect_vec());
+}
+
+#[test]
+fn test_progress_never_decreases_and_completes_with_the_answers() {
+    const PERSON_COUNT: i64 = 200;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match $person isa person, has age $age;";
+    let (conjunction_executable, value_parameters, _) = compile_match(&storage, &statistics, query);
+    let estimated_rows = conjunction_executable.planner_statistics().estimated_output_rows().unwrap();
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let executor = ConjunctionExecutor::new(
+        &conjunction_executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    // several batches, so the progress is updated more than once
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters)).with_batch_capacity(32);
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    assert_eq!(iterator.progress(), Some(0.0));
+    let mut row_count = 0;
+    let mut progress = Vec::new();
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+        row_count += 1;
+        progress.push(iterator.progress().unwrap());
+    }
+    assert_eq!(row_count, PERSON_COUNT);
+    assert!(progress.iter().tuple_windows().all(|(previous, next)| previous <= next), "{progress:?}");
+    assert!(progress.iter().all(|&fraction| fraction < 1.0), "{progress:?}");
+
+    let final_fraction = f64::min(row_count as f64 / estimated_rows, 1.0) as f32;
+    let completed = iterator.progress().unwrap();
+    assert!(completed >= final_fraction && completed >= *progress.last().unwrap());
+}
+
+#[test]
+fn test_progress_counts_the_answers_of_a_row_count_only_match() {
+    const PERSON_COUNT: i64 = 200;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, age| {
+        data.push_str(&format!("    $_ isa person, has age {age};\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // no variable is selected, so each batch collapses into a single row carrying the number of answers
+    let query = "match $_ isa person, has age $_;";
+    let (conjunction_executable, value_parameters, _) = compile_match(&storage, &statistics, query);
+    let conjunction_executable = conjunction_executable.with_row_count_only();
+    let estimated_rows = conjunction_executable.planner_statistics().estimated_output_rows().unwrap();
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let executor = ConjunctionExecutor::new(
+        &conjunction_executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters)).with_batch_capacity(32);
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let mut row_count = 0;
+    let mut answer_count = 0;
+    let mut progress = None;
+    while let Some(row) = iterator.next() {
+        row_count += 1;
+        answer_count += row.unwrap().multiplicity();
+        progress = iterator.progress();
+    }
+    assert_eq!(answer_count, PERSON_COUNT as u64);
+    assert!(row_count < answer_count, "{row_count} rows for {answer_count} answers");
+
+    // the progress before the end of the answers follows the answers rather than the rows
+    let answered_fraction = f32::min((answer_count as f64 / estimated_rows) as f32, 0.99);
+    assert_eq!(progress, Some(answered_fraction));
+}
+
+#[test]
+fn test_intersection_on_attributes_of_several_value_types() {
+    let schema = "define
+        attribute age @abstract;
+        attribute integer-age sub age, value integer;
+        attribute double-age sub age, value double;
+        entity person owns integer-age @card(0..), owns double-age @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has integer-age 1, has double-age 1.5;
+        $_ isa person, has integer-age 1;
+        $_ isa person, has double-age 1.5;
+        $_ isa person, has integer-age 2, has double-age 2.0;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+
+    // the join on $age merges the attributes of both value types, where integer-age 2 and double-age 2.0 differ
+    let query = "match $p isa person, has age $age; $q isa person, has age $age;";
+    let (rows, _) = execute_match(&storage, &statistics, query);
+    let answer_count: u64 = rows.iter().map(|row| row.multiplicity()).sum();
+    // integer-age 1 and double-age 1.5 are each owned by two people, the others by one
+    assert_eq!(answer_count, 4 + 4 + 1 + 1);
+}
+
+#[test]
+fn test_like_and_contains_match_strings_across_many_rows() {
+    const PERSON_COUNT: usize = 600;
+
+    let schema = "define
+        attribute name value string;
+        entity person owns name @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, person| {
+        data.push_str(&format!("    $_ isa person, has name 'name-{person}';\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // the matchers are prepared once when the checks are lowered, and reused for every row
+    let names = (0..PERSON_COUNT).map(|person| format!("name-{person}")).collect_vec();
+    let queries = [
+        (
+            "match $p isa person, has name $n; $n like '^name-1[0-9]{2}$';",
+            names.iter().filter(|name| name.len() == 8 && name.starts_with("name-1")).count(),
+        ),
+        // contains is case insensitive
+        (
+            "match $p isa person, has name $n; $n contains 'ME-5';",
+            names.iter().filter(|name| name.contains("me-5")).count(),
+        ),
+        (
+            "match $p isa person, has name $n; $n contains 'name-'; $n like '0$';",
+            names.iter().filter(|name| name.ends_with('0')).count(),
+        ),
+    ];
+    for (query, expected) in queries {
+        assert!(expected > 0, "{query}");
+        let (rows, _) = execute_match(&storage, &statistics, query);
+        let answer_count: u64 = rows.iter().map(|row| row.multiplicity()).sum();
+        assert_eq!(answer_count, expected as u64, "{query}");
+    }
+}
+
+#[test]
+fn test_constant_bounds_narrow_attribute_range_scans() {
+    const PERSON_COUNT: usize = 500;
+
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = (0..PERSON_COUNT).fold(String::from("insert\n"), |mut data, person| {
+        data.push_str(&format!("    $_ isa person, has age {person};\n"));
+        data
+    });
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // returns the answer count, and the raw storage reads of all steps
+    let execute = |query: &str| {
+        let (conjunction_executable, value_parameters, _) = compile_match(&storage, &statistics, query);
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let (_, thing_manager) = load_managers(storage.clone(), None);
+        let profile = QueryProfile::new(true);
+        let executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &profile,
+        )
+        .unwrap();
+        let context = ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters));
+        let rows: Vec<_> = executor
+            .into_iterator(context, ExecutionInterrupt::new_uninterruptible())
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .try_collect()
+            .unwrap();
+        let answer_count: u64 = rows.iter().map(|row| row.multiplicity()).sum();
+        let reads: u64 = (profile.stage_profiles().read().unwrap().values())
+            .flat_map(|stage_profile| (0..).map_while(|index| stage_profile.step_profile(index)).collect_vec())
+            .map(|step_profile| {
+                let counters = step_profile.storage_counters();
+                counters.get_raw_advance().unwrap_or(0) + counters.get_raw_seek().unwrap_or(0)
+            })
+            .sum();
+        (answer_count, reads)
+    };
+
+    // the comparisons against constants are inlined into the instruction producing the attribute, whose
+    // storage range scan then starts or stops at the bound instead of filtering every age
+    let queries = [
+        ("match $a isa age;", "match $a isa age; $a > 495;"),
+        ("match $a isa age;", "match $a isa age; $a <= 3;"),
+        ("match $p has age $a;", "match $p has age $a; $a > 495;"),
+        ("match $p has age $a;", "match $p has age $a; $a <= 3;"),
+        ("match $p has age $a;", "match $p has age $a; 495 < $a;"),
+    ];
+    for (unbounded_query, bounded_query) in queries {
+        let (unbounded_answers, unbounded_reads) = execute(unbounded_query);
+        assert_eq!(unbounded_answers, PERSON_COUNT as u64, "{unbounded_query}");
+        assert!(unbounded_reads >= PERSON_COUNT as u64, "{unbounded_query}: {unbounded_reads} reads");
+
+        let (bounded_answers, bounded_reads) = execute(bounded_query);
+        assert_eq!(bounded_answers, 4, "{bounded_query}");
+        assert!(
+            bounded_reads < unbounded_reads / 10,
+            "{bounded_query}: {bounded_reads} reads, unbounded: {unbounded_reads}"
+        );
+    }
+}
+
+fn execute_match(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+) -> (Vec<MaybeOwnedRow<'static>>, HashMap<String, VariablePosition>) {
+    execute_match_with_batch_capacity(storage, statistics, query, FIXED_BATCH_ROWS_MAX)
+}
+
+fn execute_match_with_batch_capacity(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+    batch_capacity: u32,
+) -> (Vec<MaybeOwnedRow<'static>>, HashMap<String, VariablePosition>) {
+    let (conjunction_executable, value_parameters, positions) = compile_match(storage, statistics, query);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let executor = ConjunctionExecutor::new(
+        &conjunction_executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    let context =
+        ExecutionContext::new(snapshot, thing_manager, Arc::new(value_parameters)).with_batch_capacity(batch_capacity);
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let rows = iterator
+        .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+        .into_iter()
+        .unique_by(|res| res.as_ref().map(|row| (row.row().to_vec(), row.provenance())).ok())
+        .try_collect::<_, Vec<_>, _>()
+        .unwrap();
+    (rows, positions)
+}
+
+// Returns the executable, the parameters it was compiled with, and the positions of its named variables
+fn compile_match(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+) -> (ConjunctionExecutable, ParameterRegistry, HashMap<String, VariablePosition>) {
     let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+
+    // IR
     let empty_function_index = HashMapFunctionSignatureIndex::empty();
     let mut translation_context = PipelineTranslationContext::new();
     let mut value_parameters = ParameterRegistry::new();
@@ -1059,8 +1920,11 @@ fn compile_query(
     let block = builder.finish().unwrap();
 
     // Executor
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, _) = load_managers(storage.clone(), None);
+
     let entry_annotations = infer_types(
-        snapshot,
+        &snapshot,
         &block,
         &translation_context.variable_registry,
         &type_manager,
@@ -1069,17 +1933,1675 @@ fn compile_query(
         false,
     )
     .unwrap();
+    let expressions = compile_expressions(
+        &*snapshot,
+        &type_manager,
+        &block,
+        &mut translation_context.variable_registry,
+        &value_parameters,
+        &entry_annotations,
+        &mut BTreeMap::new(),
+    )
+    .unwrap();
 
-    compiler::executable::match_::planner::compile(
+    let conjunction_executable = compiler::executable::match_::planner::compile(
         &block,
         &BTreeMap::new(),
         &HashMap::new(),
         &block.conjunction().named_producible_variables(block.block_context()).collect(),
         &entry_annotations,
         &translation_context.variable_registry,
+        &expressions,
+        &value_parameters,
+        statistics,
+        &ExecutableFunctionRegistry::empty(),
+        &PlannerConfig::default(),
+        None,
+    )
+    .unwrap();
+    let positions = conjunction_executable
+        .variable_positions()
+        .iter()
+        .filter_map(|(&var, &pos)| Some((translation_context.variable_registry.get_variable_name(var)?.clone(), pos)))
+        .collect();
+    (conjunction_executable, value_parameters, positions)
+}
+
+#[test]
+fn test_mismatched_input_types() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        relation friendship, relates friend, owns age @card(0..);
+        entity person, owns age @card(0..), owns name @card(0..), plays friendship:friend;
+    ";
+    let data = "insert
+        $p1 isa person, has name 'John', has age 25;
+        $p2 isa person, has name 'James', has age 27;
+        $_ isa friendship, links (friend: $p1, friend: $p2), has age 5;
+    ";
+    let statistics = setup(&storage, type_manager, thing_manager, schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    {
+        let query = "match
+            $x has age $age;
+            { $x links (friend: $p); }  or
+            { $x has name $n; };
+            select $x;
+        ";
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let conjunction_executable =
+            compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+        let executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &QueryProfile::new(false),
+        )
+        .unwrap();
+        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+        let rows = iterator
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .unique_by(|res| res.as_ref().unwrap().row().to_vec())
+            .try_collect::<_, Vec<_>, _>()
+            .unwrap();
+
+        for row in &rows {
+            for value in row {
+                print!("{}, ", value);
+            }
+            println!()
+        }
+    }
+
+    {
+        let query = "match
+            { $x isa $_; } or { $_ has $x; };
+            select $x;
+            distinct;
+        ";
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let conjunction_executable =
+            compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+        let executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &QueryProfile::new(false),
+        )
+        .unwrap();
+        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+        let rows = iterator
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .unique_by(|res| res.as_ref().unwrap().row().to_vec())
+            .try_collect::<_, Vec<_>, _>()
+            .unwrap();
+
+        for row in &rows {
+            for value in row {
+                print!("{}, ", value);
+            }
+            println!()
+        }
+        debug_assert_ne!(rows.len(), 5); // Returns the 5 attributes if type-inference considers categories.
+        debug_assert_eq!(rows.len(), 8);
+    }
+}
+
+#[test]
+fn test_missing_nested_annotations_is_a_planning_error() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert $_ isa person, has age 10, has name 'John';";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, _) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    let translate = |query: &str| {
+        let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let mut translation_context = PipelineTranslationContext::new();
+        let builder = translate_match(
+            &mut translation_context,
+            &mut ParameterRegistry::new(),
+            &HashMapFunctionSignatureIndex::empty(),
+            &match_,
+        )
+        .unwrap();
+        (builder.finish().unwrap(), translation_context.variable_registry)
+    };
+
+    // the annotations only cover the root conjunction, leaving the disjunction branches unannotated
+    let (annotated_block, annotated_registry) = translate("match $p isa person;");
+    let annotations = infer_types(
+        &snapshot,
+        &annotated_block,
+        &annotated_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+
+    let (block, variable_registry) = translate("match $p isa person; { $p has name $n; } or { $p has age $a; };");
+    let result = compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
         &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &annotations,
+        &variable_registry,
+        &HashMap::new(),
+        &ParameterRegistry::new(),
         &statistics,
         &ExecutableFunctionRegistry::empty(),
-    )
-    .unwrap()
+        &PlannerConfig::default(),
+        None,
+    );
+    assert_matches!(
+        result,
+        Err(MatchCompilationError::PlanningError {
+            typedb_source: QueryPlanningError::MissingAnnotations { branch_id: Some(_), .. }
+        })
+    );
+}
+
+#[test]
+fn test_fixture_queries_never_fail_planning_internally() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        attribute email value string;
+        entity person owns age @card(0..), owns name @card(0..), owns email @card(0..), plays membership:member;
+        entity group plays membership:group;
+        relation membership relates member, relates group;
+    ";
+    let data = "insert
+        $p isa person, has age 10, has name 'John', has email 'john@x.com';
+        $q isa person, has age 13, has name 'Jane';
+        $g isa group;
+        (member: $p, group: $g) isa membership;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, _) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    let queries = [
+        "match $p isa person, has age $a, has name $n;",
+        "match $p isa person, has age $a; $a > 11;",
+        "match $p isa person, has age $a; $q isa person, has age $b; $a < $b;",
+        "match $p isa person; { $p has name $n; } or { $p has age $a; };",
+        "match $p isa person, has name $n; not { $p has email $e; };",
+        "match $m isa membership, links (member: $p, group: $g); $p has name $n;",
+        "match $p isa person; $g isa group;",
+        "match $p isa person, has age $a; $q isa person, has age $a; $p is $q;",
+    ];
+    let greedy_config = PlannerConfig { time_budget: Some(Duration::ZERO), ..PlannerConfig::default() };
+    for config in [PlannerConfig::default(), greedy_config] {
+        for query in queries {
+            let match_ =
+                typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+            let mut translation_context = PipelineTranslationContext::new();
+            let mut value_parameters = ParameterRegistry::new();
+            let block = translate_match(
+                &mut translation_context,
+                &mut value_parameters,
+                &HashMapFunctionSignatureIndex::empty(),
+                &match_,
+            )
+            .unwrap()
+            .finish()
+            .unwrap();
+            let annotations = infer_types(
+                &snapshot,
+                &block,
+                &translation_context.variable_registry,
+                &type_manager,
+                &BTreeMap::new(),
+                &EmptyAnnotatedFunctionSignatures,
+                false,
+            )
+            .unwrap();
+            let result = compiler::executable::match_::planner::compile(
+                &block,
+                &BTreeMap::new(),
+                &HashMap::new(),
+                &block.conjunction().named_producible_variables(block.block_context()).collect(),
+                &annotations,
+                &translation_context.variable_registry,
+                &HashMap::new(),
+                &value_parameters,
+                &statistics,
+                &ExecutableFunctionRegistry::empty(),
+                &config,
+                None,
+            );
+            if let Err(MatchCompilationError::PlanningError { typedb_source }) = result {
+                assert_ne!(
+                    typedb_source.classification(),
+                    PlanningErrorClassification::Internal,
+                    "{query}: {typedb_source:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_prewarm_does_not_consume_rows() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has age 11, has name 'John';
+        $_ isa person, has age 13, has name 'Leila';
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let query = "match $person isa person, has name $name, has age $age;";
+
+    let mut results = Vec::new();
+    for prewarm in [false, true] {
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let conjunction_executable =
+            compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+        let profile = QueryProfile::new(true);
+        let mut executor = ConjunctionExecutor::new(
+            &conjunction_executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &profile,
+        )
+        .unwrap();
+        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
+        if prewarm {
+            executor.prewarm(&context).unwrap();
+            let first_step = profile.stage_profiles().read().unwrap()[&conjunction_executable.executable_id()]
+                .step_profile(0)
+                .unwrap();
+            let seeks_after_prewarm = first_step.storage_counters().get_raw_seek().unwrap();
+            assert!(seeks_after_prewarm > 0);
+            executor.prewarm(&context).unwrap();
+            assert_eq!(first_step.storage_counters().get_raw_seek().unwrap(), seeks_after_prewarm);
+        }
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+        let rows = iterator
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .map(|res| res.unwrap().row().to_vec())
+            .collect::<Vec<_>>();
+        results.push(rows);
+    }
+    assert_eq!(results[0].len(), 3);
+    assert_eq!(results[0], results[1]);
+}
+
+#[test]
+fn test_parameters_missing_or_of_another_kind_are_rejected_before_execution() {
+    let schema = "define
+        attribute age value integer;
+        entity person owns age @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10;
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let query = "match $person isa person, has age $age; $age > 10;";
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    let (&parameter, kind) = executable.parameter_kinds().iter().exactly_one().unwrap();
+    assert_eq!(kind, &ParameterKind::Value(ValueType::Integer));
+
+    let execute = |parameters: ParameterRegistry| {
+        let executor = ConjunctionExecutor::new(
+            &executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &QueryProfile::new(false),
+        )
+        .unwrap();
+        let context = ExecutionContext::new(snapshot.clone(), thing_manager.clone(), Arc::new(parameters));
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+        iterator
+            .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let span = Span { begin_offset: 0, end_offset: 0 };
+
+    let mut parameters = ParameterRegistry::new();
+    assert_eq!(parameters.register_value(Value::Integer(10), span), parameter);
+    assert_eq!(execute(parameters).unwrap().len(), 1);
+
+    let Err(error) = execute(ParameterRegistry::new()) else { panic!("Expected the missing parameter to be rejected") };
+    assert_matches!(
+        error,
+        ReadExecutionError::ParametersIncompatible { missing, mismatched }
+            if missing == [parameter] && mismatched.is_empty()
+    );
+
+    let mut parameters = ParameterRegistry::new();
+    assert_eq!(parameters.register_value(Value::String(Cow::Borrowed("10")), span), parameter);
+    let Err(error) = execute(parameters) else { panic!("Expected the mismatched parameter to be rejected") };
+    assert_matches!(
+        error,
+        ReadExecutionError::ParametersIncompatible { missing, mismatched }
+            if missing.is_empty() && mismatched == [parameter]
+    );
+}
+
+#[test]
+fn test_plan_explanation() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let query = "match $person isa person, has age $age; not { $person has name $name; };";
+
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    assert!(executable.explanation().is_none());
+
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().expect("explanation was requested");
+    assert!(!explanation.steps.is_empty());
+    assert!(explanation.steps.windows(2).all(|pair| pair[0].order < pair[1].order));
+    assert!(explanation.steps.iter().any(|step| step.produced_variables.contains(&"$age".to_owned())));
+    assert_eq!(explanation.steps.iter().filter(|step| step.nested.len() == 1).count(), 1);
+}
+
+#[test]
+fn test_fully_bound_has_is_checked_without_a_preferred_side() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    // the negated bodies are planned with both the owner and the attribute bound
+    let queries = [
+        ("match $p isa person; $a isa age; not { $p has $a; };", 2),
+        ("match $p isa person; $a isa age; not { not { $p has $a; }; };", 2),
+    ];
+    for (query, expected_answers) in queries {
+        let (rows, _) = execute_match(&storage, &statistics, query);
+        assert_eq!(rows.len(), expected_answers, "{query}");
+
+        let executable =
+            compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+        let explanation = executable.explanation().unwrap();
+        let mut nested = explanation.steps.iter().flat_map(|step| &step.nested).collect_vec();
+        let mut bound_has = None;
+        while let Some(body) = nested.pop() {
+            bound_has = bound_has.or(body.steps.iter().find(|step| step.pattern.contains(" has ")));
+            nested.extend(body.steps.iter().flat_map(|step| &step.nested));
+        }
+        let bound_has = bound_has.expect("the negated has is planned");
+        assert!(bound_has.produced_variables.is_empty(), "{explanation}");
+        assert!(!explanation.to_string().contains("side preferred"), "{explanation}");
+    }
+}
+
+#[test]
+fn test_plan_explanation_annotation_sizes() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let sizes_of = |step: &PlanStepExplanation| {
+        step.annotation_sizes.iter().map(|size| (size.label.as_str(), size.size)).collect::<Vec<_>>()
+    };
+
+    let query = "match $person has age $age;";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    let has = (explanation.steps.iter()).find(|step| step.pattern.contains(" has ")).unwrap();
+    assert_eq!(
+        sizes_of(has),
+        [("owner types", 1.0), ("attribute types", 1.0), ("est. ownerships", 2.0)],
+        "{explanation}"
+    );
+    assert!(explanation.to_string().contains("sizes: [owner types: 1, attribute types: 1"), "{explanation}");
+    assert!(explanation.notes.iter().all(|note| !note.contains("was costed over")), "{explanation}");
+
+    // an unlabelled attribute ranges over both attribute types the person owns
+    let query = "match $person isa person, has $attribute;";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    let has = (explanation.steps.iter()).find(|step| step.pattern.contains(" has ")).unwrap();
+    assert_eq!(
+        sizes_of(has),
+        [("owner types", 1.0), ("attribute types", 2.0), ("est. ownerships", 3.0)],
+        "{explanation}"
+    );
+}
+
+#[test]
+fn test_plan_explanation_source_ranges() {
+    let schema = "define
+        entity user plays purchase:buyer;
+        entity order, owns status, plays purchase:order;
+        relation purchase relates buyer, relates order;
+        attribute status, value string;
+    ";
+    let data = "insert
+        $u0 isa user; $u1 isa user;
+        $o0 isa order, has status 'canceled';
+        $o1 isa order, has status 'paid';
+        (buyer: $u0, order: $o0) isa purchase;
+        (buyer: $u1, order: $o1) isa purchase;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    let query = "match $p isa purchase, links (order: $order, buyer: $buyer);";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    let step_text = |step: &PlanStepExplanation| {
+        step.source_ranges.iter().map(|range| &query[range.begin..range.end]).collect::<Vec<_>>()
+    };
+
+    for step in &explanation.steps {
+        assert!(!step.source_ranges.is_empty(), "{explanation}");
+        assert!(step.source_ranges.windows(2).all(|pair| pair[0].end < pair[1].begin), "{explanation}");
+    }
+
+    // the relation is produced by intersecting its isa with its role players
+    let intersection = (explanation.steps.iter())
+        .find(|step| step.join_variable.as_deref().is_some_and(|var| var.starts_with("$p")))
+        .unwrap_or_else(|| panic!("expected an intersection on $p:\n{explanation}"));
+    let texts = step_text(intersection);
+    assert!(texts.iter().any(|text| text.contains("isa purchase")), "{texts:?}\n{explanation}");
+    assert!(texts.iter().any(|text| text.contains("$order") || text.contains("$buyer")), "{texts:?}\n{explanation}");
+
+    // the synthesised deduplication of the role players reports both links it was derived from
+    let deduplication = (explanation.steps.iter()).find(|step| step.pattern.contains("LinksDeduplication")).unwrap();
+    let texts = step_text(deduplication);
+    assert!(texts.iter().any(|text| text.contains("order: $order")), "{texts:?}\n{explanation}");
+    assert!(texts.iter().any(|text| text.contains("buyer: $buyer")), "{texts:?}\n{explanation}");
+}
+
+#[test]
+fn test_plan_ordering_follows_skewed_statistics() {
+    let query = "match $person has age $age; $person has name $name;";
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    // many ages and a single name: the plan should start from the name, and the other way around
+    for (rare, common) in [("name", "age"), ("age", "name")] {
+        let dataset = DatasetDescription::new()
+            .attribute("age", ValueKind::Integer, Distribution::Uniform { min: 0, max: 1_000 })
+            .attribute("name", ValueKind::String, Distribution::Uniform { min: 0, max: 1_000 })
+            .entities(
+                EntityPopulation::new("person", 1)
+                    .owns(rare, Distribution::Constant(1))
+                    .owns(common, Distribution::Constant(1)),
+            )
+            .entities(EntityPopulation::new("person", 49).owns(common, Distribution::Constant(1)));
+        let (_tmp_dir, storage, statistics) = create_generated_database(&dataset, 1, DATASET_SEED);
+        let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let executable =
+            compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+        let first_step = &executable.explanation().unwrap().steps[0];
+        assert!(first_step.produced_variables.contains(&format!("${rare}")), "{}", executable.explanation().unwrap());
+    }
+}
+
+#[test]
+fn test_generated_dataset_follows_its_description() {
+    let dataset = DatasetDescription::new()
+        .attribute("age", ValueKind::Integer, Distribution::Zipfian { max: 100, exponent: 1.0 })
+        .entities(EntityPopulation::new("person", 10).owns("age", Distribution::Uniform { min: 0, max: 2 }))
+        .relations(RelationPopulation::new("friendship", 4).role(
+            "friend",
+            "person",
+            Distribution::Constant(2),
+            Selection::Zipfian { exponent: 1.0 },
+        ))
+        .batch_size(16);
+    let generate = || create_generated_database(&dataset, 5, DATASET_SEED);
+
+    let (_tmp_dir, _, statistics) = generate();
+    assert_eq!(statistics.total_entity_count, 50);
+    assert_eq!(statistics.total_relation_count, 20);
+
+    // the same seed generates the same data
+    let (_other_tmp_dir, _, other_statistics) = generate();
+    assert_eq!(statistics.total_has_count, other_statistics.total_has_count);
+    assert_eq!(statistics.total_attribute_count, other_statistics.total_attribute_count);
+    assert_eq!(statistics.total_role_count, other_statistics.total_role_count);
+}
+
+#[test]
+fn test_planning_with_stale_statistics_estimates_unrecorded_types() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let query = "match $person isa person, has age $age, has name $name;";
+
+    let (_tmp_dir, storage, _) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    // statistics not yet synchronised with the inserts record no instances at all
+    let statistics = Statistics::new(SequenceNumber::new(0));
+    let statistics_freshness = statistics.freshness_at(snapshot.open_sequence_number(), false);
+    assert_eq!(statistics_freshness, StatisticsFreshness::Stale);
+
+    let config = PlannerConfig { statistics_freshness, record_explanation: true, ..PlannerConfig::default() };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    assert!(explanation.cost.is_finite() && explanation.cost > 0.0, "{explanation}");
+    for step in &explanation.steps {
+        assert!(step.io_ratio.is_finite() && step.io_ratio > 0.0, "{explanation}");
+    }
+    // the estimated statistics say too little about the data to estimate the progress of execution
+    assert!(executable.planner_statistics().is_estimated_statistics());
+    assert_eq!(executable.planner_statistics().estimated_output_rows(), None);
+
+    // trusting the empty statistics still plans without dividing by the zero counts
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    assert!(explanation.cost.is_finite(), "{explanation}");
+    assert!(explanation.steps.iter().all(|step| step.io_ratio.is_finite()), "{explanation}");
+}
+
+#[test]
+fn test_plan_ordering_follows_selectivity_of_constant_bounds() {
+    // every person has an age, but only a few have a name
+    let dataset = DatasetDescription::new()
+        .attribute("age", ValueKind::Integer, Distribution::Uniform { min: 0, max: 49 })
+        .attribute("name", ValueKind::String, Distribution::Uniform { min: 0, max: 1_000 })
+        .entities(
+            EntityPopulation::new("person", 1)
+                .owns("age", Distribution::Constant(1))
+                .owns("name", Distribution::Constant(1)),
+        )
+        .entities(EntityPopulation::new("person", 4).owns("age", Distribution::Constant(1)));
+    let (_tmp_dir, storage, statistics) = create_generated_database(&dataset, 10, DATASET_SEED);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    // almost every age passes the bound: the plan should start from the name
+    let query = "match $person has age $age; $person has name $name; $age > 0;";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let first_step = &executable.explanation().unwrap().steps[0];
+    assert!(first_step.produced_variables.contains(&"$name".to_owned()), "{}", executable.explanation().unwrap());
+
+    // only the oldest few pass the bound: the plan should start from the age
+    let query = "match $person has age $age; $person has name $name; $age > 47;";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let first_step = &executable.explanation().unwrap().steps[0];
+    assert!(first_step.produced_variables.contains(&"$age".to_owned()), "{}", executable.explanation().unwrap());
+}
+
+#[test]
+fn test_is_check_follows_the_second_of_its_producers() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..50 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has name 'name {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    let query = "match $x has age $age; $y has name $name; $x is $y;";
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    let is_position = explanation.steps.iter().position(|step| step.pattern.contains(" is ")).unwrap();
+    let is_step = &explanation.steps[is_position];
+    if is_step.produced_variables.is_empty() {
+        // as a check, it directly follows the step producing the second of its variables
+        let last_producer = (explanation.steps.iter())
+            .rposition(|step| step.produced_variables.iter().any(|var| var == "$x" || var == "$y"))
+            .unwrap();
+        assert_eq!(last_producer + 1, is_position, "{explanation}");
+        assert!(is_step.io_ratio < 1.0, "{explanation}");
+    }
+}
+
+#[test]
+fn test_disjunction_branches_lowered_cheapest_first() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    // the expensive branch is written first, so it is allocated the lower branch id
+    let query = "match $person isa person; { $person has age $age; } or { $person has name $name; };";
+
+    let mut data = String::from("insert $_ isa person, has age 0, has name 'John';");
+    for i in 1..50 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has age {};", i + 100));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable = compile_conjunction(&storage, &statistics, query, &config);
+
+    let explanation = executable.explanation().unwrap();
+    let branches = &explanation.steps.iter().find(|step| step.nested.len() == 2).unwrap().nested;
+    assert!(branches[0].cost < branches[1].cost, "{explanation}");
+    assert!(
+        branches[0].steps.iter().any(|step| step.produced_variables.contains(&"$name".to_owned())),
+        "{explanation}"
+    );
+
+    let disjunction = executable
+        .steps()
+        .iter()
+        .find_map(|step| match step {
+            ExecutionStep::Disjunction(disjunction) => Some(disjunction),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(disjunction.branch_ids.len(), 2);
+    assert!(disjunction.branch_ids[0].0 > disjunction.branch_ids[1].0, "{:?}", disjunction.branch_ids);
+}
+
+#[test]
+fn test_interleaved_disjunction_branches_stream_their_answers() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..200 {
+        data.push_str(&format!(" $_ isa person, has name 'name {i}';"));
+    }
+    for i in 0..400 {
+        data.push_str(&format!(" $_ isa person, has age {i};"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match { $p has age $a; } or { $p has name $n; };";
+    let mut batches_before_second_branch = Vec::new();
+    let mut answers = Vec::new();
+    for interleave_branches in [false, true] {
+        let config = PlannerConfig { interleave_branches, ..PlannerConfig::default() };
+        let executable = compile_conjunction(&storage, &statistics, query, &config);
+        let disjunction = executable
+            .steps()
+            .iter()
+            .find_map(|step| match step {
+                ExecutionStep::Disjunction(disjunction) => Some(disjunction),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(disjunction.interleave_branches, interleave_branches);
+
+        let profile = QueryProfile::new(true);
+        let mut rows = execute_conjunction(&storage, &executable, &profile);
+
+        // interleaved branches start from the first, cheaper, branch; otherwise the last branch runs first
+        let first_row = rows.next().unwrap();
+        let first_branch_id = first_row.provenance().branch_ids().exactly_one().unwrap();
+        let first_branch_index = match interleave_branches {
+            true => 0,
+            false => disjunction.branch_ids.len() - 1,
+        };
+        assert_eq!(first_branch_id, disjunction.branch_ids[first_branch_index]);
+        let first_branch = disjunction.branches[first_branch_index].executable_id();
+        let mut all_rows = vec![first_row.row().to_vec()];
+        for row in rows.by_ref() {
+            all_rows.push(row.row().to_vec());
+            if row.provenance().branch_ids().exactly_one().unwrap() != first_branch_id {
+                break;
+            }
+        }
+        let first_branch_batches =
+            profile.stage_profiles().read().unwrap()[&first_branch].step_profile(0).unwrap().batches();
+        batches_before_second_branch.push(first_branch_batches);
+
+        all_rows.extend(rows.map(|row| row.row().to_vec()));
+        assert_eq!(all_rows.len(), 600);
+        answers.push(all_rows.into_iter().counts());
+    }
+    assert_eq!(answers[0], answers[1]);
+    // branch order exhausts the 400 ages before the first name, interleaving only produces the first batch of names
+    assert!(batches_before_second_branch[0] >= 4, "{batches_before_second_branch:?}");
+    assert_eq!(batches_before_second_branch[1], 1, "{batches_before_second_branch:?}");
+}
+
+#[test]
+fn test_disjunction_branches_are_not_interleaved_by_default() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..200 {
+        data.push_str(&format!(" $_ isa person, has name 'name {i}';"));
+    }
+    for i in 0..400 {
+        data.push_str(&format!(" $_ isa person, has age {i};"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    let query = "match { $p has age $a; } or { $p has name $n; };";
+    let executable = compile_conjunction(&storage, &statistics, query, &PlannerConfig::default());
+    let disjunction = executable
+        .steps()
+        .iter()
+        .find_map(|step| match step {
+            ExecutionStep::Disjunction(disjunction) => Some(disjunction),
+            _ => None,
+        })
+        .unwrap();
+    assert!(!disjunction.interleave_branches);
+
+    let branch_ids: Vec<_> = execute_conjunction(&storage, &executable, &QueryProfile::new(false))
+        .map(|row| row.provenance().branch_ids().exactly_one().unwrap())
+        .collect();
+
+    // as before branches could be interleaved: each branch emits all of its answers before the next one starts,
+    // beginning with the last branch
+    assert_eq!(branch_ids.len(), 600);
+    let runs: Vec<_> = branch_ids.into_iter().dedup_with_count().collect();
+    assert_eq!(runs, vec![(400, disjunction.branch_ids[1]), (200, disjunction.branch_ids[0])]);
+}
+
+#[test]
+fn test_common_disjunction_constraint_planned_once() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        attribute email value string;
+        entity person owns age @card(0..), owns name @card(0..), owns email @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has name 'Leila', has email 'leila@example.com';
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, _thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    let query = "match
+        $person isa person;
+        { $person has name $name; $person has age 10; } or { $person has name $name; $person has email $email; };
+    ";
+    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let builder =
+        translate_match(&mut translation_context, &mut value_parameters, &empty_function_index, &match_).unwrap();
+    let mut block = builder.finish().unwrap();
+    let mut block_annotations = infer_types(
+        &snapshot,
+        &block,
+        &translation_context.variable_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let block_context = block.block_context().clone();
+    factor_common_disjunction_constraints(block.conjunction_mut(), &block_context, &mut block_annotations);
+
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable = compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &block_annotations,
+        &translation_context.variable_registry,
+        &HashMap::new(),
+        &value_parameters,
+        &statistics,
+        &ExecutableFunctionRegistry::empty(),
+        &config,
+        None,
+    )
+    .unwrap();
+
+    fn steps_producing(explanation: &PlanExplanation, variable: &str) -> usize {
+        let produced_here =
+            explanation.steps.iter().filter(|step| step.produced_variables.iter().any(|var| var == variable));
+        let produced_nested = explanation.steps.iter().flat_map(|step| &step.nested);
+        produced_here.count() + produced_nested.map(|nested| steps_producing(nested, variable)).sum::<usize>()
+    }
+    let explanation = executable.explanation().unwrap();
+    assert_eq!(steps_producing(explanation, "$name"), 1, "{explanation}");
+    assert!(
+        explanation.steps.iter().any(|step| step.produced_variables.contains(&"$name".to_owned())),
+        "{explanation}"
+    );
+}
+
+#[test]
+fn test_type_negations_are_resolved_into_annotations() {
+    let schema = "define
+        attribute name value string;
+        entity person owns name @card(0..);
+        entity employee sub person;
+        entity manager sub employee;
+        entity contractor sub person;
+    ";
+    let data = "insert
+        $_ isa person, has name 'Ann';
+        $_ isa employee, has name 'Bob';
+        $_ isa manager, has name 'Cat';
+        $_ isa contractor, has name 'Dan';
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    let compile_resolving_negations = |query: &str| {
+        let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+        let empty_function_index = HashMapFunctionSignatureIndex::empty();
+        let mut translation_context = PipelineTranslationContext::new();
+        let mut value_parameters = ParameterRegistry::new();
+        let builder =
+            translate_match(&mut translation_context, &mut value_parameters, &empty_function_index, &match_).unwrap();
+        let mut block = builder.finish().unwrap();
+        let mut block_annotations = infer_types(
+            &*snapshot,
+            &block,
+            &translation_context.variable_registry,
+            &type_manager,
+            &BTreeMap::new(),
+            &EmptyAnnotatedFunctionSignatures,
+            false,
+        )
+        .unwrap();
+        let block_context = block.block_context().clone();
+        resolve_type_negations(block.conjunction_mut(), &block_context, &mut block_annotations);
+        compiler::executable::match_::planner::compile(
+            &block,
+            &BTreeMap::new(),
+            &HashMap::new(),
+            &block.conjunction().named_producible_variables(block.block_context()).collect(),
+            &block_annotations,
+            &translation_context.variable_registry,
+            &HashMap::new(),
+            &value_parameters,
+            &statistics,
+            &ExecutableFunctionRegistry::empty(),
+            &PlannerConfig::default(),
+            None,
+        )
+        .unwrap()
+    };
+
+    let executable = compile_resolving_negations("match $x isa person, has name $n; not { $x isa employee; };");
+    assert!(!executable.steps().iter().any(|step| matches!(step, ExecutionStep::Negation(_))), "{executable}");
+    let (rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &executable);
+    assert_eq!(rows.len(), 2); // Ann and Dan: employees and managers are excluded
+
+    let executable = compile_resolving_negations("match $x isa employee, has name $n; not { $x isa! employee; };");
+    assert!(!executable.steps().iter().any(|step| matches!(step, ExecutionStep::Negation(_))), "{executable}");
+    let (rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &executable);
+    assert_eq!(rows.len(), 1); // Cat, the manager
+
+    let executable = compile_resolving_negations("match $x isa employee, has name $n; not { $x isa person; };");
+    let (rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &executable);
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_expensive_negation_planned_after_cheap_filter() {
+    let schema = "define
+        attribute name value string;
+        attribute nickname value string;
+        entity person owns name @card(0..), owns nickname @card(0..);
+    ";
+    // the negation scans every person for each input: it should only run for the single person named Alice
+    let query = "match
+        $p isa person, has name 'Alice';
+        not { $p has nickname $k; $other isa person, has nickname $k; $other has name $n; };
+    ";
+    let mut data = String::from("insert $_ isa person, has name 'Alice', has nickname 'Al';");
+    for i in 0..50 {
+        data.push_str(&format!(" $_ isa person, has name 'name {i}', has nickname 'nick {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+
+    let explanation = executable.explanation().unwrap();
+    let filter_step = explanation.steps.iter().position(|step| step.pattern.contains("has name")).unwrap();
+    let negation_step = explanation.steps.iter().position(|step| !step.nested.is_empty()).unwrap();
+    assert!(filter_step < negation_step, "{explanation}");
+
+    let (rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &executable);
+    assert!(rows.is_empty()); // Alice shares her own nickname
+}
+
+#[test]
+fn test_disconnected_components_planned_one_after_another() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has name 'Ann';
+        $_ isa person, has name 'Bob';
+        $_ isa person, has age 10;
+        $_ isa person, has age 20;
+        $_ isa person, has age 30;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+
+    let query = "match $x isa person, has name $n; $y isa person, has age $a;";
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable = compile_conjunction(&storage, &statistics, query, &config);
+    let explanation = executable.explanation().unwrap();
+    let step_components = (explanation.steps.iter())
+        .map(|step| step.pattern.contains("$x") || step.pattern.contains("$n"))
+        .dedup()
+        .collect_vec();
+    assert_eq!(step_components.len(), 2, "{explanation}");
+
+    let rows = execute_conjunction(&storage, &executable, &QueryProfile::new(false));
+    assert_eq!(rows.map(|row| row.row().to_vec()).unique().count(), 6); // every name with every age
+}
+
+#[test]
+fn test_checks_hoisted_to_after_their_inputs() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..20 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has name 'first {i}', has name 'second {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // the comparison spans two persons, so it can only be checked once both ages are bound
+    let query = "match
+        $x isa person, has age $a, has name $n;
+        $y isa person, has age $b, has name $m;
+        $a > $b;
+    ";
+    let mut orderings = Vec::new();
+    let mut step_rows = Vec::new();
+    let mut answers = Vec::new();
+    let mut answer_counts = Vec::new();
+    for hoist_checks in [false, true] {
+        let config = PlannerConfig { hoist_checks, record_explanation: true, ..PlannerConfig::default() };
+        let executable = compile_conjunction(&storage, &statistics, query, &config);
+        let explanation = executable.explanation().unwrap();
+        orderings.push(explanation.steps.iter().position(|step| step.pattern.contains('>')).unwrap());
+
+        let profile = QueryProfile::new(true);
+        let rows = execute_conjunction(&storage, &executable, &profile).map(|row| row.row().to_vec()).collect_vec();
+        answer_counts.push(rows.len());
+        answers.push(rows.into_iter().counts());
+
+        let stage_profiles = profile.stage_profiles().read().unwrap();
+        let stage_profile = &stage_profiles[&executable.executable_id()];
+        step_rows.push((0..).map_while(|index| stage_profile.step_profile(index)).map(|step| step.rows()).sum::<u64>());
+    }
+    assert_eq!(answers[0], answers[1]);
+    assert_eq!(answer_counts, [20 * 19 / 2 * 4; 2]); // every pair of distinct ages, with either name of each person
+    assert!(orderings[1] <= orderings[0], "the comparison moved from step {} to {}", orderings[0], orderings[1]);
+    assert!(step_rows[1] <= step_rows[0], "{} intermediate rows with hoisting, {} without", step_rows[1], step_rows[0]);
+}
+
+#[test]
+fn test_step_labels_are_stable_across_orderings() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..20 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has name 'first {i}', has name 'second {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+
+    // hoisting moves the comparison to directly after the steps binding its operands, reordering the plan
+    let query = "match
+        $x isa person, has age $a, has name $n;
+        $y isa person, has age $b, has name $m;
+        $a > $b;
+        not { $x has name 'first 0'; };
+    ";
+    let mut labels = Vec::new();
+    let mut nested_labels = Vec::new();
+    for hoist_checks in [false, true] {
+        let config = PlannerConfig { hoist_checks, ..PlannerConfig::default() };
+        let executable = compile_conjunction(&storage, &statistics, query, &config);
+
+        let step_labels =
+            (0..executable.steps().len()).map(|index| executable.step_label(index).unwrap()).collect_vec();
+        assert!(step_labels.iter().all_unique(), "{executable}");
+        let negation = executable.steps().iter().find_map(|step| match step {
+            ExecutionStep::Negation(negation) => Some(&negation.negation),
+            _ => None,
+        });
+        let negation = negation.unwrap();
+        let negation_labels =
+            (0..negation.steps().len()).map(|index| negation.step_label(index).unwrap()).collect::<HashSet<_>>();
+        // the steps of the negation are labelled by the step they are nested in too
+        assert!(negation_labels.iter().all(|label| !step_labels.contains(label)), "{executable}");
+
+        let profile = QueryProfile::new(true);
+        let rows = execute_conjunction(&storage, &executable, &profile).count();
+        assert_eq!(rows, 20 * 19 / 2 * 4); // the person aged 0 is never the older of a pair, so is never excluded
+
+        let profile_string = profile.to_string();
+        let stage_profiles = profile.stage_profiles().read().unwrap();
+        let stage_profile = &stage_profiles[&executable.executable_id()];
+        for (index, label) in step_labels.iter().enumerate() {
+            let label = label.to_string();
+            assert_eq!(stage_profile.step_profile(index).unwrap().label(), Some(label.as_str()));
+            assert!(profile_string.contains(&format!("[step {label}]")), "{profile_string}");
+        }
+        labels.push(step_labels.into_iter().collect::<HashSet<_>>());
+        nested_labels.push(negation_labels);
+    }
+    assert_eq!(labels[0], labels[1]);
+    assert_eq!(nested_labels[0], nested_labels[1]);
+}
+
+#[test]
+fn test_joining_intersections_share_their_sort_variable() {
+    let dataset = DatasetDescription::new()
+        .attribute("age", ValueKind::Integer, Distribution::Uniform { min: 0, max: 3 })
+        .attribute("name", ValueKind::String, Distribution::Uniform { min: 0, max: 4 })
+        .entities(
+            EntityPopulation::new("person", 1)
+                .owns("age", Distribution::Constant(1))
+                .owns("name", Distribution::Constant(1)),
+        );
+    let (_tmp_dir, storage, statistics) = create_generated_database(&dataset, 20, DATASET_SEED);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    let query = "match
+        $x isa person, has name $n, has age $a;
+        $y isa person, has name $n, has age $a;
+    ";
+    let executable = compile_query(&snapshot, &type_manager, thing_manager, &statistics, query);
+    for step in executable.steps() {
+        let ExecutionStep::Intersection(step) = step else { continue };
+        assert!(
+            step.instructions.len() < 2
+                || step.instructions.iter().all(|(instruction, _)| instruction.is_new_variable(step.sort_variable)),
+            "{executable}"
+        );
+    }
+
+    // every person joins each person with the same name and age, including itself
+    let (people, positions) = execute_match(&storage, &statistics, "match $x isa person, has name $n, has age $a;");
+    assert_eq!(people.len(), 20);
+    let expected: usize = (people.iter())
+        .map(|row| (row.get(positions["n"]).clone(), row.get(positions["a"]).clone()))
+        .counts()
+        .into_values()
+        .map(|people| people * people)
+        .sum();
+    let (rows, _) = execute_match(&storage, &statistics, query);
+    assert_eq!(rows.len(), expected);
+}
+
+#[test]
+fn test_disjunction_plans_are_memoized_within_a_compilation() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        attribute email value string;
+        entity person owns age @card(0..), owns name @card(0..), owns email @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has email 'john@example.com';
+        $_ isa person, has name 'Leila', has email 'leila@example.com';
+        $_ isa person, has email 'anon@example.com';
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    // the disjunction is costed against the same bound $person by every partial plan that has bound it
+    let query = "match $person isa person, has email $email; { $person has age $age; } or { $person has name $name; };";
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager, &statistics, query);
+    let planner_statistics = executable.planner_statistics();
+    assert!(planner_statistics.disjunction_memo_misses() > 0);
+    assert!(planner_statistics.disjunction_memo_hits() > 0);
+
+    let (rows, _) = execute_match(&storage, &statistics, query);
+    assert_eq!(rows.len(), 2);
+}
+
+#[test]
+fn test_wide_disjunctions_compile_in_bounded_time() {
+    const BRANCHES: usize = 300;
+    const COMPILATION_TIME_MAX: Duration = Duration::from_secs(30);
+
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert");
+    for i in 0..20 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has name 'name {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    // every other branch binds a variable of its own, which must not share a position with those of other branches
+    let branches = (0..BRANCHES)
+        .map(|i| match i % 2 {
+            0 => format!("{{ $a == {i}; }}"),
+            _ => format!("{{ $a == {i}; $p has name $n{i}; }}"),
+        })
+        .join(" or ");
+    let query = format!("match $p isa person, has age $a; {branches};");
+    let start = Instant::now();
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager, &statistics, &query);
+    let elapsed = start.elapsed();
+    assert!(elapsed < COMPILATION_TIME_MAX, "compiling {BRANCHES} branches took {elapsed:?}");
+    let disjunction = executable.steps().iter().find_map(|step| match step {
+        ExecutionStep::Disjunction(disjunction) => Some(disjunction),
+        _ => None,
+    });
+    assert_eq!(disjunction.unwrap().branches.len(), BRANCHES, "{executable}");
+
+    // each person has a single age, matched by exactly one branch
+    let (rows, _) = execute_match(&storage, &statistics, &query);
+    assert_eq!(rows.len(), 20);
+}
+
+#[test]
+fn test_iid_planned_first() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let mut data = String::from("insert $_ isa person, has age 0, has name 'name 0';");
+    for i in 1..50 {
+        data.push_str(&format!(" $_ isa person, has age {i}, has name 'name {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+
+    let queries = [
+        "match $person iid 0x1e00000000000000000001; $person has name $name;",
+        "match $person has name $name; $person iid 0x1e00000000000000000001;",
+        "match $person has name $name, has age $age; $person iid 0x1e00000000000000000001;",
+        "match $person isa person, has name $name; $person iid 0x1e00000000000000000001;",
+    ];
+    for query in queries {
+        let executable =
+            compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+        let explanation = executable.explanation().unwrap();
+        assert_eq!(explanation.steps[0].pattern, "|ThingId|", "{query}:\n{explanation}");
+    }
+}
+
+#[test]
+fn test_comparison_across_intersection_instructions_filters_each_combination() {
+    let schema = "define
+        attribute age value integer;
+        attribute score value integer;
+        entity person owns age @card(0..), owns score @card(0..);
+    ";
+    // the first person's four combinations of age and score form a cartesian product, of which two are accepted
+    let data = "insert
+        $_ isa person, has age 1, has age 5, has score 2, has score 3;
+        $_ isa person, has age 10, has score 20;
+        $_ isa person, has age 7, has score 4;
+    ";
+    let query = "match $person isa person, has age $age, has score $score; $age > $score;";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager, &statistics, query);
+    assert!(!executable.steps().iter().any(|step| matches!(step, ExecutionStep::Check(_))), "{executable}");
+    assert!(
+        executable
+            .steps()
+            .iter()
+            .any(|step| matches!(step, ExecutionStep::Intersection(step) if !step.checks.is_empty())),
+        "{executable}"
+    );
+
+    let (rows, _) = execute_match(&storage, &statistics, query);
+    assert_eq!(rows.len(), 3, "{rows:?}");
+    assert!(rows.iter().all(|row| row.multiplicity() == 1));
+}
+
+#[test]
+fn test_plan_cache_reuses_plan_until_statistics_change() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, mut statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let query = "match $cached isa person, has age $cached_age, has name $cached_name;";
+    let config = PlannerConfig::default();
+    let plan_cache = PlanCache::default();
+    let compile = |plan_cache: &PlanCache, statistics: &Statistics| {
+        compile_query_with_plan_cache(
+            &*snapshot,
+            &type_manager,
+            thing_manager.clone(),
+            statistics,
+            query,
+            &config,
+            Some(plan_cache),
+        )
+    };
+
+    let first = compile(&plan_cache, &statistics);
+    let second = compile(&plan_cache, &statistics);
+    assert!(!first.planner_statistics().is_cached_plan());
+    assert!(second.planner_statistics().is_cached_plan());
+    assert!(second.planner_statistics().plan_cache_hits() > first.planner_statistics().plan_cache_hits());
+    assert_eq!(first.to_string(), second.to_string());
+
+    // each database owns its plan cache
+    let other_database = PlanCache::default();
+    let other = compile(&other_database, &statistics);
+    assert!(!other.planner_statistics().is_cached_plan());
+
+    statistics.sequence_number = statistics.sequence_number.next();
+    let replanned = compile(&plan_cache, &statistics);
+    assert!(!replanned.planner_statistics().is_cached_plan());
+
+    plan_cache.clear();
+    let cleared = compile(&plan_cache, &statistics);
+    assert!(!cleared.planner_statistics().is_cached_plan());
+}
+
+#[test]
+fn test_exhausted_time_budget_plans_greedily() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let query = "match $person isa person, has age $age, has name $name;";
+
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    assert!(!executable.planner_statistics().is_degraded_plan());
+
+    let config = PlannerConfig { time_budget: Some(Duration::ZERO), ..PlannerConfig::default() };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+    assert!(executable.planner_statistics().is_degraded_plan());
+    assert!(executable.planner_statistics().to_string().contains("[degraded plan]"));
+
+    // the best-first search gives up on the deadline, leaving the beam to plan greedily, as it does on its own
+    let beam_config = PlannerConfig { astar_max_patterns: 0, ..config };
+    let executable =
+        compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &beam_config);
+    assert!(executable.planner_statistics().is_degraded_plan());
+
+    let plan_cache = PlanCache::default();
+    for _ in 0..2 {
+        let executable = compile_query_with_plan_cache(
+            &*snapshot,
+            &type_manager,
+            thing_manager.clone(),
+            &statistics,
+            query,
+            &config,
+            Some(&plan_cache),
+        );
+        assert!(executable.planner_statistics().is_degraded_plan());
+        assert!(!executable.planner_statistics().is_cached_plan());
+    }
+}
+
+#[test]
+fn test_equally_costed_plans_are_chosen_deterministically() {
+    let schema = "define
+        attribute age value integer;
+        attribute name value string;
+        entity person owns age @card(0..), owns name @card(0..);
+    ";
+    let data = "insert
+        $_ isa person, has age 10, has name 'John';
+        $_ isa person, has age 13, has name 'Alice';
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    // symmetric, so every plan has an equally costed mirror image
+    let query = "match $x isa person, has name $n, has age $a; $y isa person, has name $n, has age $a;";
+
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let plan_ordering = || {
+        let executable =
+            compile_query_with_config(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query, &config);
+        let explanation = executable.explanation().unwrap();
+        explanation.steps.iter().map(|step| (step.pattern.clone(), step.direction.clone())).collect_vec()
+    };
+    let first = plan_ordering();
+    for _ in 0..100 {
+        assert_eq!(first, plan_ordering());
+    }
+}
+
+// A read snapshot whose first reads of single keys fail, as reads from remote storage may fail transiently
+struct FlakySnapshot {
+    snapshot: ReadSnapshot<WALClient>,
+    failures: AtomicUsize,
+}
+
+impl FlakySnapshot {
+    fn new(snapshot: ReadSnapshot<WALClient>, failures: usize) -> Self {
+        Self { snapshot, failures: AtomicUsize::new(failures) }
+    }
+
+    fn may_fail(&self) -> Result<(), SnapshotGetError> {
+        match self.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| failures.checked_sub(1)) {
+            Ok(_) => Err(SnapshotGetError::MockError {}),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl ReadableSnapshot for FlakySnapshot {
+    fn open_sequence_number(&self) -> SequenceNumber {
+        self.snapshot.open_sequence_number()
+    }
+
+    fn get<const INLINE_BYTES: usize>(
+        &self,
+        key: StorageKeyReference<'_>,
+        storage_counters: StorageCounters,
+    ) -> Result<Option<ByteArray<INLINE_BYTES>>, SnapshotGetError> {
+        self.may_fail()?;
+        self.snapshot.get(key, storage_counters)
+    }
+
+    fn get_last_existing<const INLINE_BYTES: usize>(
+        &self,
+        key: StorageKeyReference<'_>,
+        storage_counters: StorageCounters,
+    ) -> Result<Option<ByteArray<INLINE_BYTES>>, SnapshotGetError> {
+        self.may_fail()?;
+        self.snapshot.get_last_existing(key, storage_counters)
+    }
+
+    fn iterate_range<const PS: usize>(
+        &self,
+        range: &KeyRange<StorageKey<'_, PS>>,
+        storage_counters: StorageCounters,
+    ) -> SnapshotRangeIterator {
+        self.snapshot.iterate_range(range, storage_counters)
+    }
+
+    fn any_in_range<const PS: usize>(&self, range: &KeyRange<StorageKey<'_, PS>>, buffered_only: bool) -> bool {
+        self.snapshot.any_in_range(range, buffered_only)
+    }
+
+    fn get_write(&self, key: StorageKeyReference<'_>) -> Option<&Write> {
+        self.snapshot.get_write(key)
+    }
+
+    fn iterate_writes(&self) -> impl Iterator<Item = (StorageKeyArray<BUFFER_KEY_INLINE>, Write)> + '_ {
+        self.snapshot.iterate_writes()
+    }
+
+    fn iterate_writes_range<const PS: usize>(&self, range: &KeyRange<StorageKey<'_, PS>>) -> BufferRangeIterator {
+        self.snapshot.iterate_writes_range(range)
+    }
+
+    fn iterate_storage_range<const PS: usize>(
+        &self,
+        range: &KeyRange<StorageKey<'_, PS>>,
+        storage_counters: StorageCounters,
+    ) -> SnapshotRangeIterator {
+        self.snapshot.iterate_storage_range(range, storage_counters)
+    }
+
+    fn iterator_pool(&self) -> &IteratorPool {
+        self.snapshot.iterator_pool()
+    }
+
+    fn close_resources(&self) {
+        self.snapshot.close_resources()
+    }
+}
+
+#[test]
+fn test_transient_storage_errors_are_retried() {
+    let schema = "define
+        attribute name value string;
+        entity person owns name @card(0..);
+    ";
+    // names too long to be inlined into the attribute's id are read from storage to be compared
+    let mut data = String::from("insert");
+    for i in 0..5 {
+        data.push_str(&format!(" $_ isa person, has name 'a name long enough to be stored separately {i}';"));
+    }
+    let (_tmp_dir, storage, statistics) = create_database(schema, &data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+
+    let query = "match $p isa person, has name $n; $q isa person, has name $m; $n > $m;";
+    let executable = compile_query(&snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    let execute = |failures: usize, retry_policy: RetryPolicy| {
+        let snapshot = Arc::new(FlakySnapshot::new(storage.clone().open_snapshot_read(), failures));
+        let profile = QueryProfile::new(true);
+        let executor = ConjunctionExecutor::new(
+            &executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &profile,
+        )
+        .unwrap();
+        let context =
+            ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default()).with_retry_policy(retry_policy);
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+        let rows: Result<Vec<_>, _> = iterator
+            .map_static(|row| row.map(|row| row.row().to_vec()).map_err(|err| err.clone()))
+            .into_iter()
+            .collect();
+        let stage_profile = profile.stage_profiles().read().unwrap()[&executable.executable_id()].clone();
+        let retries: u64 = (0..executable.steps().len())
+            .filter_map(|index| stage_profile.step_profile(index))
+            .map(|step_profile| step_profile.retries())
+            .sum();
+        (rows, retries)
+    };
+    let retry_transient = || {
+        RetryPolicy::new(3, Duration::from_millis(1), Duration::from_millis(10), |err| {
+            matches!(err, ConceptReadError::SnapshotGet { source: SnapshotGetError::MockError { .. } })
+        })
+    };
+
+    let (expected, retries) = execute(0, retry_transient());
+    let expected = expected.unwrap();
+    assert_eq!(expected.len(), 10);
+    assert_eq!(retries, 0);
+
+    // the step restarts from the failed read, and produces every answer exactly once
+    let (rows, retries) = execute(1, retry_transient());
+    assert_eq!(rows.unwrap().into_iter().counts(), expected.iter().cloned().counts());
+    assert_eq!(retries, 1);
+
+    // errors are not retried unless the policy classifies them as transient
+    let (rows, retries) = execute(1, RetryPolicy::default());
+    assert!(rows.is_err());
+    assert_eq!(retries, 0);
+}
+
+#[test]
+fn test_serialized_executable_executes_like_the_original() {
+    let schema = "define
+        entity user plays purchase:buyer;
+        entity order, owns status, plays purchase:order;
+        relation purchase relates buyer, relates order;
+        attribute status, value string;
+    ";
+    let data = "insert
+        $u0 isa user; $u1 isa user; $u2 isa user;
+        $o0 isa order, has status 'canceled';
+        $o1 isa order, has status 'dispatched';
+        $o2 isa order, has status 'paid';
+        (buyer: $u0, order: $o0) isa purchase;
+        (buyer: $u1, order: $o1) isa purchase;
+        (buyer: $u1, order: $o2) isa purchase;
+    ";
+    let (_tmp_dir, storage, statistics) = create_database(schema, data);
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+
+    let query = "match $p isa purchase, links (order: $order, buyer: $buyer); $order has status $status;";
+    let executable = compile_query(&*snapshot, &type_manager, thing_manager.clone(), &statistics, query);
+    let versions = PlanVersions {
+        schema_version: snapshot.open_sequence_number().number(),
+        statistics_version: statistics.sequence_number.number(),
+    };
+    let bytes = serialize_executable(&executable, versions).unwrap();
+    let deserialized = deserialize_executable(&bytes, versions).unwrap();
+    assert_eq!(deserialized.variable_positions(), executable.variable_positions());
+
+    let (rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &executable);
+    let (deserialized_rows, _) = execute_counting_nested_rows(&snapshot, &thing_manager, &deserialized);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows.len(), deserialized_rows.len());
+    assert_eq!(rows.into_iter().collect::<HashSet<_>>(), deserialized_rows.into_iter().collect::<HashSet<_>>());
+
+    let replanned = PlanVersions { statistics_version: versions.statistics_version + 1, ..versions };
+    assert_matches!(
+        deserialize_executable(&bytes, replanned),
+        Err(PlanSerializationError::StatisticsVersionMismatch { .. })
+    );
+}
+
+fn compile_query(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    thing_manager: Arc<ThingManager>,
+    statistics: &Statistics,
+    query: &str,
+) -> ConjunctionExecutable {
+    compile_query_with_config(snapshot, type_manager, thing_manager, statistics, query, &PlannerConfig::default())
+}
+
+fn compile_query_with_config(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    thing_manager: Arc<ThingManager>,
+    statistics: &Statistics,
+    query: &str,
+    planner_config: &PlannerConfig,
+) -> ConjunctionExecutable {
+    compile_query_with_plan_cache(snapshot, type_manager, thing_manager, statistics, query, planner_config, None)
+}
+
+fn compile_query_with_plan_cache(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    thing_manager: Arc<ThingManager>,
+    statistics: &Statistics,
+    query: &str,
+    planner_config: &PlannerConfig,
+    plan_cache: Option<&PlanCache>,
+) -> ConjunctionExecutable {
+    compile_conjunction_in(snapshot, type_manager, statistics, query, planner_config, plan_cache)
 }
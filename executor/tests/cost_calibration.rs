@@ -0,0 +1,328 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// Calibration of the planner's abstract cost unit against measured execution times.
+//
+// Each instruction family is exercised by a micro-query over generated datasets of increasing size. Per dataset we
+// record the planner's estimated cost and the time spent in the match stage's steps, fit a scale factor (time per
+// unit of cost) per family, and compare:
+//   1. how the estimated cost grows with the dataset against how the measured time grows, per family,
+//   2. how closely the estimated cost and the measured time correlate across dataset sizes, per family, and
+//   3. each family's scale factor against the scale factor over all families.
+// A cost model that is consistent across families has similar scale factors everywhere.
+//
+// Timing dependent, so the test is ignored by default. Run it with optimisation:
+//   cargo test --release --test test_cost_calibration -- --ignored --nocapture
+// or via Bazel:
+//   bazel test --compilation_mode=opt //executor/tests:test_cost_calibration --test_arg=--ignored
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Arc,
+    time::Duration,
+};
+
+use compiler::{
+    annotation::{
+        expression::block_compiler::compile_expressions, function::EmptyAnnotatedFunctionSignatures,
+        match_inference::infer_types,
+    },
+    executable::{
+        function::ExecutableFunctionRegistry,
+        match_::planner::{conjunction_executable::ConjunctionExecutable, plan::PlannerConfig},
+    },
+    transformation::relation_index::relation_index_transformation,
+};
+use concept::{thing::statistics::Statistics, type_::type_manager::TypeManager};
+use executor::{
+    conjunction_executor::ConjunctionExecutor, pipeline::stage::ExecutionContext, row::MaybeOwnedRow,
+    ExecutionInterrupt,
+};
+use ir::{
+    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    translation::{match_::translate_match, PipelineTranslationContext},
+};
+use itertools::Itertools;
+use lending_iterator::LendingIterator;
+use resource::profile::QueryProfile;
+use storage::{durability_client::WALClient, snapshot::ReadableSnapshot, MVCCStorage};
+use test_utils_concept::load_managers;
+use test_utils_executor::{
+    create_generated_database, DatasetDescription, Distribution, EntityPopulation, RelationPopulation, Selection,
+    ValueKind,
+};
+
+// In units of one company and ten employed persons
+const DATASET_SIZES: [u64; 4] = [10, 40, 160, 640];
+const SEED: u64 = 0;
+const REPETITIONS: usize = 5;
+
+// Measured and estimated ratios must agree within this factor
+const TOLERANCE: f64 = 8.0;
+
+// Per family, log estimated cost and log measured time must correlate at least this strongly
+const MIN_CORRELATION: f64 = 0.8;
+
+struct MicroQuery {
+    family: &'static str,
+    query: &'static str,
+    use_relation_index: bool,
+}
+
+const MICRO_QUERIES: [MicroQuery; 6] = [
+    MicroQuery { family: "isa scan", query: "match $p isa person;", use_relation_index: false },
+    MicroQuery { family: "has forward", query: "match $p isa person, has name $n;", use_relation_index: false },
+    MicroQuery { family: "has reverse", query: "match $n isa name 'name 7'; $p has $n;", use_relation_index: false },
+    MicroQuery { family: "links", query: "match $e isa employment, links (employee: $p);", use_relation_index: false },
+    MicroQuery {
+        family: "indexed relation",
+        query: "match $e links (employee: $p, employer: $c);",
+        use_relation_index: true,
+    },
+    MicroQuery { family: "comparison", query: "match $p has age $a; $a > 25;", use_relation_index: false },
+];
+
+#[derive(Debug)]
+struct Measurement {
+    dataset_size: u64,
+    estimated_cost: f64,
+    measured: Duration,
+    rows: usize,
+}
+
+impl Measurement {
+    fn nanos_per_cost(&self) -> f64 {
+        self.measured.as_nanos() as f64 / f64::max(self.estimated_cost, f64::EPSILON)
+    }
+}
+
+// Persons each with a name and an age, employed by companies also owning a name. Names are drawn from a fixed set,
+// so the owners of any one name grow with the dataset.
+fn dataset() -> DatasetDescription {
+    DatasetDescription::new()
+        .attribute("name", ValueKind::String, Distribution::Uniform { min: 0, max: 99 })
+        .attribute("age", ValueKind::Integer, Distribution::Uniform { min: 0, max: 49 })
+        .entities(EntityPopulation::new("company", 1).owns("name", Distribution::Constant(1)))
+        .entities(
+            EntityPopulation::new("person", 10)
+                .owns("name", Distribution::Constant(1))
+                .owns("age", Distribution::Constant(1)),
+        )
+        .relations(
+            RelationPopulation::new("employment", 10)
+                .role("employee", "person", Distribution::Constant(1), Selection::Uniform)
+                .role("employer", "company", Distribution::Constant(1), Selection::Uniform),
+        )
+}
+
+fn compile(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    statistics: &Statistics,
+    micro_query: &MicroQuery,
+) -> (ConjunctionExecutable, ParameterRegistry) {
+    let match_ =
+        typeql::parse_query(micro_query.query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let builder = translate_match(
+        &mut translation_context,
+        &mut value_parameters,
+        &HashMapFunctionSignatureIndex::empty(),
+        &match_,
+    )
+    .unwrap();
+    let mut block = builder.finish().unwrap();
+
+    let mut annotations = infer_types(
+        snapshot,
+        &block,
+        &translation_context.variable_registry,
+        type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    if micro_query.use_relation_index {
+        relation_index_transformation(block.conjunction_mut(), &mut annotations, type_manager, snapshot).unwrap();
+    }
+    let expressions = compile_expressions(
+        snapshot,
+        type_manager,
+        &block,
+        &mut translation_context.variable_registry,
+        &value_parameters,
+        &annotations,
+        &mut BTreeMap::new(),
+    )
+    .unwrap();
+
+    let config = PlannerConfig { record_explanation: true, ..PlannerConfig::default() };
+    let executable = compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &annotations,
+        &translation_context.variable_registry,
+        &expressions,
+        &value_parameters,
+        statistics,
+        &ExecutableFunctionRegistry::empty(),
+        &config,
+        None,
+    )
+    .unwrap();
+    (executable, value_parameters)
+}
+
+// Runs the executable to completion, returning the number of rows and the time spent in its steps
+fn execute_profiled(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    executable: &ConjunctionExecutable,
+    value_parameters: Arc<ParameterRegistry>,
+) -> (usize, Duration) {
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let profile = QueryProfile::new(true);
+    let executor = ConjunctionExecutor::new(
+        executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &profile,
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, value_parameters);
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let rows = iterator.map_static(|row| row.map(|row| row.multiplicity()).unwrap()).into_iter().sum::<u64>() as usize;
+
+    let stage_profiles = profile.stage_profiles().read().unwrap();
+    let measured = stage_profiles
+        .values()
+        .flat_map(|stage_profile| (0..).map_while(|index| stage_profile.step_profile(index)))
+        .map(|step_profile| step_profile.duration())
+        .sum();
+    (rows, measured)
+}
+
+fn measure(family: &MicroQuery) -> Vec<Measurement> {
+    DATASET_SIZES
+        .iter()
+        .map(|&dataset_size| {
+            let (_tmp_dir, storage, statistics) = create_generated_database(&dataset(), dataset_size, SEED);
+            let (type_manager, _) = load_managers(storage.clone(), None);
+            let snapshot = storage.clone().open_snapshot_read();
+            let (executable, value_parameters) = compile(&snapshot, &type_manager, &statistics, family);
+            let estimated_cost = executable.explanation().unwrap().cost;
+            let value_parameters = Arc::new(value_parameters);
+
+            // the fastest of several runs is the least disturbed by the environment
+            let (rows, measured) = (0..REPETITIONS)
+                .map(|_| execute_profiled(&storage, &executable, value_parameters.clone()))
+                .min_by_key(|&(_, measured)| measured)
+                .unwrap();
+            Measurement { dataset_size, estimated_cost, measured, rows }
+        })
+        .collect()
+}
+
+fn geometric_mean(values: impl IntoIterator<Item = f64>) -> f64 {
+    let (sum, count) = values.into_iter().fold((0.0, 0), |(sum, count), value| (sum + f64::ln(value), count + 1));
+    f64::exp(sum / count as f64)
+}
+
+// Pearson correlation, or None if either side does not vary
+fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    let count = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let (covariance, variance_x, variance_y) =
+        pairs.iter().fold((0.0, 0.0, 0.0), |(covariance, variance_x, variance_y), (x, y)| {
+            let (dx, dy) = (x - mean_x, y - mean_y);
+            (covariance + dx * dy, variance_x + dx * dx, variance_y + dy * dy)
+        });
+    if variance_x <= f64::EPSILON || variance_y <= f64::EPSILON {
+        None
+    } else {
+        Some(covariance / f64::sqrt(variance_x * variance_y))
+    }
+}
+
+fn within_tolerance(ratio: f64) -> bool {
+    (1.0 / TOLERANCE..=TOLERANCE).contains(&ratio)
+}
+
+#[test]
+#[ignore] // timing dependent, see above
+fn cost_model_is_calibrated() {
+    let measurements = MICRO_QUERIES.iter().map(|micro_query| (micro_query.family, measure(micro_query))).collect_vec();
+
+    let global_scale =
+        geometric_mean(measurements.iter().flat_map(|(_, family)| family).map(Measurement::nanos_per_cost));
+
+    let mut report = format!("Cost model calibration (global scale: {global_scale:.1} ns per unit cost)\n");
+    let mut violations = Vec::new();
+    for (family, family_measurements) in &measurements {
+        let family_scale = geometric_mean(family_measurements.iter().map(Measurement::nanos_per_cost));
+        let relative_scale = family_scale / global_scale;
+        let log_pairs = family_measurements
+            .iter()
+            .map(|measurement| {
+                (
+                    f64::ln(f64::max(measurement.estimated_cost, f64::EPSILON)),
+                    f64::ln(f64::max(measurement.measured.as_secs_f64(), 1e-9)),
+                )
+            })
+            .collect_vec();
+        let family_correlation = correlation(&log_pairs);
+        let correlation_report = match family_correlation {
+            Some(family_correlation) => format!("{family_correlation:.2}"),
+            None => String::from("n/a"),
+        };
+        writeln!(
+            report,
+            "{family}: {family_scale:.1} ns per unit cost ({relative_scale:.2}x global), \
+             cost/time correlation {correlation_report}"
+        )
+        .unwrap();
+        if !within_tolerance(relative_scale) {
+            violations.push(format!("{family}: scale is {relative_scale:.2}x the global scale"));
+        }
+        if family_correlation.is_some_and(|family_correlation| family_correlation < MIN_CORRELATION) {
+            violations.push(format!("{family}: cost/time correlation is {correlation_report}"));
+        }
+
+        let smallest = &family_measurements[0];
+        for measurement in family_measurements {
+            let estimated_growth = measurement.estimated_cost / f64::max(smallest.estimated_cost, f64::EPSILON);
+            let measured_growth = measurement.measured.as_secs_f64() / f64::max(smallest.measured.as_secs_f64(), 1e-9);
+            writeln!(
+                report,
+                "    size {:>5}: rows {:>6}, estimated cost {:>10.2}, measured {:>10.1?}, \
+                 growth estimated {:>6.2}x / measured {:>6.2}x",
+                measurement.dataset_size,
+                measurement.rows,
+                measurement.estimated_cost,
+                measurement.measured,
+                estimated_growth,
+                measured_growth,
+            )
+            .unwrap();
+            if !within_tolerance(measured_growth / estimated_growth) {
+                violations.push(format!(
+                    "{family} at size {}: measured growth {measured_growth:.2}x, estimated {estimated_growth:.2}x",
+                    measurement.dataset_size
+                ));
+            }
+        }
+    }
+    println!("{report}");
+    assert!(violations.is_empty(), "Cost model is out of calibration:\n{}\n\n{report}", violations.join("\n"));
+}
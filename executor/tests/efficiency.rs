@@ -6,12 +6,12 @@
 
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     ops::Bound,
     sync::Arc,
 };
 
-use answer::variable::Variable;
+use answer::{variable::Variable, Type};
 use compiler::{
     annotation::{
         function::EmptyAnnotatedFunctionSignatures, match_inference::infer_types, type_annotations::TypeAnnotations,
@@ -20,12 +20,13 @@ use compiler::{
         function::ExecutableFunctionRegistry,
         match_::{
             instructions::{
-                thing::{HasInstruction, HasReverseInstruction, IsaReverseInstruction},
+                pushdown::{PushdownCandidate, PushdownFilter},
+                thing::{HasInstruction, HasReverseInstruction, IsaReverseInstruction, LinksReverseInstruction},
                 type_::TypeListInstruction,
                 CheckInstruction, CheckVertex, ConstraintInstruction, Inputs,
             },
             planner::{
-                conjunction_executable::{ConjunctionExecutable, ExecutionStep, IntersectionStep},
+                conjunction_executable::{CheckStep, ConjunctionExecutable, ExecutionStep, IntersectionStep},
                 plan::PlannerStatistics,
             },
         },
@@ -42,8 +43,12 @@ use concept::{
 };
 use encoding::value::{label::Label, value::Value, value_type::ValueType};
 use executor::{
-    conjunction_executor::ConjunctionExecutor, error::ReadExecutionError, pipeline::stage::ExecutionContext,
-    row::MaybeOwnedRow, ExecutionInterrupt,
+    conjunction_executor::ConjunctionExecutor,
+    error::ReadExecutionError,
+    pipeline::stage::{ExecutionContext, DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS},
+    read::pushdown::{PushdownBackend, StoragePushdown},
+    row::MaybeOwnedRow,
+    ExecutionInterrupt,
 };
 use ir::{
     pattern::{
@@ -57,7 +62,7 @@ use lending_iterator::LendingIterator;
 use resource::profile::{CommitProfile, QueryProfile, StorageCounters};
 use storage::{
     durability_client::WALClient,
-    snapshot::{CommittableSnapshot, ReadableSnapshot},
+    snapshot::{CommittableSnapshot, ReadSnapshot, ReadableSnapshot},
     MVCCStorage,
 };
 use test_utils_concept::{load_managers, setup_concept_storage};
@@ -449,6 +454,50 @@ fn execute_steps(
     thing_manager: Arc<ThingManager>,
     value_parameters: Arc<ParameterRegistry>,
     profile: &QueryProfile,
+) -> Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> {
+    execute_steps_with_prefetch_rows(
+        steps,
+        variable_positions,
+        row_vars,
+        storage,
+        thing_manager,
+        value_parameters,
+        profile,
+        DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS,
+    )
+}
+
+fn execute_steps_with_prefetch_rows(
+    steps: Vec<ExecutionStep>,
+    variable_positions: HashMap<Variable, VariablePosition>,
+    row_vars: HashMap<ExecutorVariable, Variable>,
+    storage: Arc<MVCCStorage<WALClient>>,
+    thing_manager: Arc<ThingManager>,
+    value_parameters: Arc<ParameterRegistry>,
+    profile: &QueryProfile,
+    max_cartesian_prefetch_rows: usize,
+) -> Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> {
+    execute_steps_in_context(
+        steps,
+        variable_positions,
+        row_vars,
+        storage,
+        thing_manager,
+        value_parameters,
+        profile,
+        |context| context.with_max_cartesian_prefetch_rows(max_cartesian_prefetch_rows),
+    )
+}
+
+fn execute_steps_in_context(
+    steps: Vec<ExecutionStep>,
+    variable_positions: HashMap<Variable, VariablePosition>,
+    row_vars: HashMap<ExecutorVariable, Variable>,
+    storage: Arc<MVCCStorage<WALClient>>,
+    thing_manager: Arc<ThingManager>,
+    value_parameters: Arc<ParameterRegistry>,
+    profile: &QueryProfile,
+    configure: impl FnOnce(ExecutionContext<ReadSnapshot<WALClient>>) -> ExecutionContext<ReadSnapshot<WALClient>>,
 ) -> Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> {
     let executable = ConjunctionExecutable::new(
         next_executable_id(),
@@ -470,7 +519,7 @@ fn execute_steps(
     )
     .unwrap();
 
-    let context = ExecutionContext::new(snapshot, thing_manager.clone(), value_parameters.clone());
+    let context = configure(ExecutionContext::new(snapshot, thing_manager.clone(), value_parameters.clone()));
     let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
 
     iterator
@@ -525,6 +574,7 @@ fn value_int_equality_isa_reads() {
         lhs: CheckVertex::Variable(var_attr),
         rhs: CheckVertex::Parameter(value_int_2_id),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut isa_reverse_instruction =
@@ -570,6 +620,149 @@ fn value_int_equality_isa_reads() {
     assert_eq!(storage_counters.get_raw_advance().unwrap(), 1);
 }
 
+/// Claims the value range filters, which the attribute iterators apply by only scanning the range.
+struct ClaimValueRanges;
+
+impl PushdownBackend for ClaimValueRanges {
+    fn claims(&self, candidate: &PushdownCandidate<ExecutorVariable>) -> bool {
+        matches!(candidate.filter, PushdownFilter::ValueRange { .. })
+    }
+}
+
+/// Claims every filter, whether storage applies it or not.
+struct ClaimEverything;
+
+impl PushdownBackend for ClaimEverything {
+    fn claims(&self, _: &PushdownCandidate<ExecutorVariable>) -> bool {
+        true
+    }
+}
+
+#[test]
+fn claimed_filters_are_skipped_by_the_executor() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $attr isa id; $attr == 2;
+
+    // IR to compute type annotations
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let value_int_2_id = value_parameters.register_value(Value::Integer(2), Span { begin_offset: 0, end_offset: 0 });
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+
+    let var_id_type = conjunction.constraints_mut().get_or_declare_variable("var_id_type", None).unwrap();
+    let var_attr = conjunction.constraints_mut().get_or_declare_variable("attr", None).unwrap();
+
+    let isa =
+        conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_attr, var_id_type.into(), None).unwrap().clone();
+    conjunction.constraints_mut().add_label(var_id_type, ID_LABEL.clone()).unwrap();
+    conjunction
+        .constraints_mut()
+        .add_comparison(Vertex::Variable(var_attr), Vertex::Parameter(value_int_2_id), Comparator::Equal, None)
+        .unwrap();
+
+    let entry = builder.finish().unwrap();
+    let value_parameters = Arc::new(value_parameters);
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let type_annotations = get_type_annotations(&translation_context, &entry, &snapshot, &type_manager);
+    let person_type = type_manager.get_entity_type(&snapshot, &PERSON_LABEL).unwrap().unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) = position_mapping([var_id_type, var_attr], []);
+
+    // Plan
+    //    1. Intersection($id_type label ID;)
+    //    2. Intersection($attr isa $id_type; (VALUE constraints = Eq(value_int_2_id)) and the given checks)
+    let value_check = CheckInstruction::Comparison {
+        lhs: CheckVertex::Variable(var_attr),
+        rhs: CheckVertex::Parameter(value_int_2_id),
+        comparator: Comparator::Equal,
+        matcher: None,
+    }
+    .map(&mapping);
+    // the iterator of attributes does not apply type filters, unlike the value range
+    let person_types = Arc::new(BTreeSet::from([Type::Entity(person_type)]));
+    let type_check = CheckInstruction::ThingTypeList { thing_var: var_attr, types: person_types.clone() }.map(&mapping);
+    let steps_with = |checks: &[CheckInstruction<ExecutorVariable>]| {
+        let mut isa_reverse_instruction =
+            IsaReverseInstruction::new(isa.clone(), Inputs::Single([var_id_type]), &type_annotations).map(&mapping);
+        checks.iter().for_each(|check| isa_reverse_instruction.add_check(check.clone()));
+        vec![
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_id_type],
+                vec![ConstraintInstruction::TypeList(
+                    TypeListInstruction::new(
+                        var_id_type,
+                        type_annotations.vertex_annotations().get(&Vertex::Variable(var_id_type)).unwrap().clone(),
+                    )
+                    .map(&mapping),
+                )],
+                vec![variable_positions[&var_id_type]],
+                &named_variables,
+                1,
+            )),
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_attr],
+                vec![ConstraintInstruction::IsaReverse(isa_reverse_instruction)],
+                vec![variable_positions[&var_id_type], variable_positions[&var_attr]],
+                &named_variables,
+                2,
+            )),
+        ]
+    };
+    let execute = |checks: &[CheckInstruction<ExecutorVariable>], storage_pushdown: &StoragePushdown| {
+        let rows = execute_steps_in_context(
+            steps_with(checks),
+            variable_positions.clone(),
+            row_vars.clone(),
+            storage.clone(),
+            thing_manager.clone(),
+            value_parameters.clone(),
+            &QueryProfile::new(false),
+            |context| context.with_storage_pushdown(storage_pushdown.clone()),
+        );
+        rows.into_iter().map(|row| row.unwrap()).count()
+    };
+
+    let ExecutionStep::Intersection(isa_reverse_step) = &steps_with(&[value_check.clone(), type_check.clone()])[1]
+    else {
+        unreachable!()
+    };
+    let attr = mapping[&var_attr];
+    assert_eq!(
+        isa_reverse_step.pushdown_candidates,
+        vec![vec![
+            PushdownCandidate {
+                variable: attr,
+                filter: PushdownFilter::ValueRange { comparator: Comparator::Equal, value: value_int_2_id },
+            },
+            PushdownCandidate { variable: attr, filter: PushdownFilter::InstanceOfTypes(person_types) },
+        ]]
+    );
+
+    // storage applies the claimed range, so skipping its check keeps the answers
+    let claim_ranges = StoragePushdown::new(Arc::new(ClaimValueRanges));
+    assert_eq!(execute(&[value_check.clone()], &StoragePushdown::none()), 1);
+    assert_eq!(execute(&[value_check.clone()], &claim_ranges), 1);
+    let verified_claim_ranges = claim_ranges.verifying_claims();
+    assert_eq!(execute(&[value_check.clone()], &verified_claim_ranges), 1);
+    assert_eq!(verified_claim_ranges.claim_violations(), 0);
+
+    // storage does not apply the claimed type filter, so only the executor would have rejected the attribute
+    let checks = [value_check, type_check];
+    let claim_everything = StoragePushdown::new(Arc::new(ClaimEverything));
+    assert_eq!(execute(&checks, &StoragePushdown::none()), 0);
+    assert_eq!(execute(&checks, &claim_everything), 1);
+    let verified_claim_everything = claim_everything.verifying_claims();
+    assert_eq!(execute(&checks, &verified_claim_everything), 0);
+    assert_eq!(verified_claim_everything.claim_violations(), 1);
+}
+
 #[test]
 fn value_int_equality_has_reverse_reads() {
     let (_tmp_dir, mut storage) = create_core_storage();
@@ -628,6 +821,7 @@ fn value_int_equality_has_reverse_reads() {
         lhs: CheckVertex::Variable(var_gov_id),
         rhs: CheckVertex::Parameter(value_int_1_id),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_reverse_instruction =
@@ -718,6 +912,7 @@ fn value_int_equality_has_bound_owner() {
         lhs: CheckVertex::Variable(var_gov_id),
         rhs: CheckVertex::Parameter(value_int_1_id),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_instruction = HasInstruction::new(has, Inputs::Single([var_person]), &type_annotations).map(&mapping);
@@ -829,12 +1024,14 @@ fn value_int_inequality_has_bound_owner() {
         lhs: CheckVertex::Variable(var_gov_id),
         rhs: CheckVertex::Parameter(value_int_1_id),
         comparator: Comparator::GreaterOrEqual,
+        matcher: None,
     }
     .map(&mapping);
     let lesser_value_check = CheckInstruction::Comparison {
         lhs: CheckVertex::Variable(var_gov_id),
         rhs: CheckVertex::Parameter(value_int_3_id),
         comparator: Comparator::Less,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_instruction = HasInstruction::new(has, Inputs::Single([var_person]), &type_annotations).map(&mapping);
@@ -933,6 +1130,7 @@ fn value_inline_string_equality_has_bound_owner() {
         lhs: CheckVertex::Variable(var_name),
         rhs: CheckVertex::Parameter(value_string_abby),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_instruction = HasInstruction::new(has, Inputs::Single([var_person]), &entry_annotations).map(&mapping);
@@ -1033,6 +1231,7 @@ fn value_hashed_string_equality_has_bound_owner() {
         lhs: CheckVertex::Variable(var_name),
         rhs: CheckVertex::Parameter(value_string_hashed),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_instruction = HasInstruction::new(has, Inputs::Single([var_person]), &type_annotations).map(&mapping);
@@ -1143,12 +1342,14 @@ fn value_string_inequality_reduces_has_reads_bound_owner() {
         lhs: CheckVertex::Variable(var_name),
         rhs: CheckVertex::Parameter(value_string_bolton),
         comparator: Comparator::GreaterOrEqual,
+        matcher: None,
     }
     .map(&mapping);
     let lesser_value_check = CheckInstruction::Comparison {
         lhs: CheckVertex::Variable(var_name),
         rhs: CheckVertex::Parameter(value_string_willow),
         comparator: Comparator::Less,
+        matcher: None,
     }
     .map(&mapping);
     let mut has_instruction = HasInstruction::new(has, Inputs::Single([var_person]), &type_annotations).map(&mapping);
@@ -1267,6 +1468,7 @@ fn intersection_seeks() {
         lhs: CheckVertex::Variable(var_age),
         rhs: CheckVertex::Parameter(value_int_10),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let mut isa_age = IsaReverseInstruction::new(isa_age, Inputs::None([]), &type_annotations).map(&mapping);
@@ -1321,16 +1523,11 @@ fn intersection_seeks() {
     //      Has[unbound] Person1 advances 1 past age 10 (first attribute type) to skip to GovID attributes
     //      Now have match!
     //  => advance each iterator: 2 advances... HasReverse is at Person 2. Has is on Person 1 + GovId 1
-    //  => Cartesian sub-iterator opened for Has iterator: 1 seek. Has is now back to Person 1, age 10... 1 advance... finds GovID 0
-    //     => TODO: there's room for an optimisation here: we don't have to re-open a new iterator when only have 1 cartesian iterator!
-    //              we can just advance it linearly through the answers!
-    //     Question: will Cartesian re-emit GovID 0?
-    //      => Cartesian iterator then gets 3 more GovIds (GovID 1, 2, 3) in Person 1 intersection: 3 advances, plus 1 advance to go past & fail
-    //      => TODO: since we simply reopen the cartesian Has[unbound] iterator with no further control
-    //               we end up iterating over all Has until we hit Person2.GovId (this hasUnbound filters internally!)
-    //               this induces another 7 advances!! (see CartesianIterator::reopen_iterator)
-    //  => Has[unbound] seeks to HasReverse's value of Person2: 1 seek (does 1 peek = 1 advances first)... ends up at Person 3 (Person 2 has no gov id)
-    //      Has[unbound] at Person 3 will first find age 10, which is skipped with 1 advance. Now at GovId 4.
+    //  => Only Has[unbound] has more answers for Person 1, so it is advanced linearly instead of opening a cartesian
+    //     sub-iterator: it is already on GovID 1, and gets GovID 2, 3: 2 advances
+    //      => TODO: since the Has[unbound] iterator filters internally, going past GovID 3 iterates over all Has until
+    //               we hit Person3.GovId4, through Person 2's age and names and Person 3's age
+    //  => Has[unbound] is already past HasReverse's value of Person2, so it needs no seek
     //  => HasReverse seeks Has's value of Person3: [1 seek] which actually reduces to 1 advance as it checks the iterator. match!
     //  => advance each iterator: 2 advances...
     //      HasReverse is at Person 4.
@@ -1339,11 +1536,10 @@ fn intersection_seeks() {
     //  => HasReverse seeks to Has's value Person 5: [1 seek], which actually reduces to 1 advance as it checks the iterator. match!
     //  => advance both iterators: 2 advances... run out of answers in HasReverse. Finished!
 
-    // total seek: 4
-    // total advance: 25 (24 ? off by one...)
+    // total seek: 2
     // for each person, we should skip directly to the person + owned name
-    assert_eq!(storage_counters.get_raw_seek().unwrap(), 4);
-    assert_eq!(storage_counters.get_raw_advance().unwrap(), 23);
+    assert_eq!(storage_counters.get_raw_seek().unwrap(), 2);
+    assert_eq!(storage_counters.get_raw_advance().unwrap(), 19);
 }
 
 #[test]
@@ -1450,12 +1646,14 @@ fn intersections_seeks_with_extra_values() {
         lhs: CheckVertex::Variable(var_age),
         rhs: CheckVertex::Parameter(value_int_12),
         comparator: Comparator::Equal,
+        matcher: None,
     }
     .map(&mapping);
     let gov_id_gt_2 = CheckInstruction::Comparison {
         lhs: CheckVertex::Variable(var_gov_id),
         rhs: CheckVertex::Parameter(value_int_2),
         comparator: Comparator::Greater,
+        matcher: None,
     }
     .map(&mapping);
     let mut isa_age = IsaReverseInstruction::new(isa_age, Inputs::None([]), &type_annotations).map(&mapping);
@@ -1517,3 +1715,425 @@ fn intersections_seeks_with_extra_values() {
     assert_eq!(storage_counters.get_raw_seek().unwrap(), 3);
     assert_eq!(storage_counters.get_raw_advance().unwrap(), 9)
 }
+
+#[test]
+fn single_cartesian_iterator_is_not_reopened() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $age isa age 20;
+    //    $person has $age;
+    //    $person has gov_id $gov_id;
+
+    // add persons that each have age 20 and several gov ids, so every intersection on a person has more answers
+    // only in the gov id iterator
+    const OWNER_COUNT: i64 = 5;
+    const GOV_IDS_PER_OWNER: i64 = 3;
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_write();
+    let person_type = type_manager.get_entity_type(&mut snapshot, &PERSON_LABEL).unwrap().unwrap();
+    let age_type = type_manager.get_attribute_type(&mut snapshot, &AGE_LABEL).unwrap().unwrap();
+    let gov_id_type = type_manager.get_attribute_type(&mut snapshot, &GOV_ID_LABEL).unwrap().unwrap();
+    let age_20 = thing_manager.create_attribute(&mut snapshot, age_type, Value::Integer(20)).unwrap();
+    for owner in 0..OWNER_COUNT {
+        let person = thing_manager.create_entity(&mut snapshot, person_type).unwrap();
+        person.set_has_unordered(&mut snapshot, &thing_manager, &age_20, StorageCounters::DISABLED).unwrap();
+        for gov_id in 0..GOV_IDS_PER_OWNER {
+            let gov_id = Value::Integer(100 + owner * GOV_IDS_PER_OWNER + gov_id);
+            let gov_id = thing_manager.create_attribute(&mut snapshot, gov_id_type, gov_id).unwrap();
+            person.set_has_unordered(&mut snapshot, &thing_manager, &gov_id, StorageCounters::DISABLED).unwrap();
+        }
+    }
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    // IR to compute type annotations
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let value_int_20 = value_parameters.register_value(Value::Integer(20), Span { begin_offset: 0, end_offset: 0 });
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("var_person", None).unwrap();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("var_person_type", None).unwrap();
+    let var_gov_id = conjunction.constraints_mut().get_or_declare_variable("var_gov_id", None).unwrap();
+    let var_gov_id_type = conjunction.constraints_mut().get_or_declare_variable("var_gov_id_type", None).unwrap();
+    let var_age = conjunction.constraints_mut().get_or_declare_variable("var_age", None).unwrap();
+    let var_age_type = conjunction.constraints_mut().get_or_declare_variable("var_age_type", None).unwrap();
+
+    let has_age = conjunction.constraints_mut().add_has(var_person, var_age, None).unwrap().clone();
+    let has_gov_id = conjunction.constraints_mut().add_has(var_person, var_gov_id, None).unwrap().clone();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_gov_id, var_gov_id_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_gov_id_type, GOV_ID_LABEL.clone()).unwrap();
+    let isa_age =
+        conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_age, var_age_type.into(), None).unwrap().clone();
+    conjunction.constraints_mut().add_label(var_age_type, AGE_LABEL.clone()).unwrap();
+    conjunction
+        .constraints_mut()
+        .add_comparison(Vertex::Variable(var_age), Vertex::Parameter(value_int_20), Comparator::Equal, None)
+        .unwrap();
+
+    let entry = builder.finish().unwrap();
+    let value_parameters = Arc::new(value_parameters);
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let type_annotations = get_type_annotations(&mut translation_context, &entry, &snapshot, &type_manager);
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_age, var_age_type, var_person, var_gov_id], []);
+
+    // plan:
+    // 1. Isa($age, age) value == 20
+    // 2. Intersect:
+    //       ReverseHas($person, $age) ==> produces each new person once
+    //       Has($person, $gov_id) ==> unbound this produces each new person once per gov id
+    // ---> should output every gov id of every new person
+    let age_equal_20 = CheckInstruction::Comparison {
+        lhs: CheckVertex::Variable(var_age),
+        rhs: CheckVertex::Parameter(value_int_20),
+        comparator: Comparator::Equal,
+        matcher: None,
+    }
+    .map(&mapping);
+    let mut isa_age = IsaReverseInstruction::new(isa_age, Inputs::None([]), &type_annotations).map(&mapping);
+    isa_age.add_check(age_equal_20);
+
+    let steps = vec![
+        ExecutionStep::Intersection(IntersectionStep::new(
+            mapping[&var_age_type],
+            vec![ConstraintInstruction::IsaReverse(isa_age)],
+            vec![variable_positions[&var_age], variable_positions[&var_age_type]],
+            &named_variables,
+            2,
+        )),
+        ExecutionStep::Intersection(IntersectionStep::new(
+            mapping[&var_person],
+            vec![
+                ConstraintInstruction::HasReverse(HasReverseInstruction::new(
+                    has_age,
+                    Inputs::Single([var_age]),
+                    &type_annotations,
+                ))
+                .map(&mapping),
+                ConstraintInstruction::Has(HasInstruction::new(has_gov_id, Inputs::None([]), &type_annotations))
+                    .map(&mapping),
+            ],
+            vec![
+                variable_positions[&var_person],
+                variable_positions[&var_gov_id],
+                variable_positions[&var_age],
+                variable_positions[&var_age_type],
+            ],
+            &named_variables,
+            4,
+        )),
+    ];
+
+    let query_profile = QueryProfile::new(true);
+    let rows =
+        execute_steps(steps, variable_positions, row_vars, storage, thing_manager, value_parameters, &query_profile);
+    assert_eq!(rows.len(), (OWNER_COUNT * GOV_IDS_PER_OWNER) as usize);
+    assert!(rows.iter().all(|row| row.is_ok()));
+
+    let stage_profiles = query_profile.stage_profiles().read().unwrap();
+    let (_, match_profile) = stage_profiles.iter().next().unwrap();
+    let intersection_step_profile = match_profile.extend_or_get(1, || String::new());
+    let storage_counters = intersection_step_profile.storage_counters();
+
+    // expected evaluation
+    //  open initial iterators for the input row: 2 seeks
+    //  Has[unbound] seeks from Person 1 to the first new person: 1 seek
+    //  for each new person, the Has[unbound] iterator is the only one with more answers, and is advanced linearly
+    //      through them into the next person, without opening a cartesian sub-iterator (which would cost 1 seek each)
+    assert!(storage_counters.get_raw_seek().unwrap() < OWNER_COUNT as u64);
+}
+
+#[test]
+fn cartesian_product_is_enumerated_from_memory() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $person has name $name;
+    //    $person has gov_id $gov_id;
+
+    // add `match $person_2 isa person, has name "abby"; insert $person_2 has gov_id 7, has gov_id 8, has gov_id 9;`
+    // so that person 2 has a 4 x 3 cartesian product of names and gov ids
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_write();
+    let person_type = type_manager.get_entity_type(&mut snapshot, &PERSON_LABEL).unwrap().unwrap();
+    let name_type = type_manager.get_attribute_type(&mut snapshot, &NAME_LABEL).unwrap().unwrap();
+    let gov_id_type = type_manager.get_attribute_type(&mut snapshot, &GOV_ID_LABEL).unwrap().unwrap();
+    let name_abby = thing_manager
+        .get_attribute_with_value(
+            &snapshot,
+            name_type,
+            Value::String(Cow::Borrowed(VALUE_STRING_ABBY)),
+            StorageCounters::DISABLED,
+        )
+        .unwrap()
+        .unwrap();
+    let person_2 = Iterator::next(&mut thing_manager.get_has_reverse_by_attribute_and_owner_type_range(
+        &snapshot,
+        &name_abby,
+        &(Bound::Included(ObjectType::Entity(person_type)), Bound::Included(ObjectType::Entity(person_type))),
+        StorageCounters::DISABLED,
+    ))
+    .unwrap()
+    .unwrap()
+    .0
+    .owner();
+    for gov_id in 7..10 {
+        let gov_id = thing_manager.create_attribute(&mut snapshot, gov_id_type, Value::Integer(gov_id)).unwrap();
+        person_2.set_has_unordered(&mut snapshot, &thing_manager, &gov_id, StorageCounters::DISABLED).unwrap();
+    }
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    // IR to compute type annotations
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("var_person", None).unwrap();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("var_person_type", None).unwrap();
+    let var_name = conjunction.constraints_mut().get_or_declare_variable("var_name", None).unwrap();
+    let var_name_type = conjunction.constraints_mut().get_or_declare_variable("var_name_type", None).unwrap();
+    let var_gov_id = conjunction.constraints_mut().get_or_declare_variable("var_gov_id", None).unwrap();
+    let var_gov_id_type = conjunction.constraints_mut().get_or_declare_variable("var_gov_id_type", None).unwrap();
+
+    let has_name = conjunction.constraints_mut().add_has(var_person, var_name, None).unwrap().clone();
+    let has_gov_id = conjunction.constraints_mut().add_has(var_person, var_gov_id, None).unwrap().clone();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_name, var_name_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_name_type, NAME_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_gov_id, var_gov_id_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_gov_id_type, GOV_ID_LABEL.clone()).unwrap();
+
+    let entry = builder.finish().unwrap();
+    let value_parameters = Arc::new(value_parameters);
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let type_annotations = get_type_annotations(&mut translation_context, &entry, &snapshot, &type_manager);
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_person, var_name, var_gov_id], []);
+
+    // plan:
+    // 1. Intersect:
+    //       Has($person, $name) ==> unbound this produces many people
+    //       Has($person, $gov_id) ==> unbound this produces many people
+    // ---> should output the 4 x 3 combinations of person 2's names and gov ids
+    let steps = || {
+        vec![ExecutionStep::Intersection(IntersectionStep::new(
+            mapping[&var_person],
+            vec![
+                ConstraintInstruction::Has(HasInstruction::new(has_name.clone(), Inputs::None([]), &type_annotations))
+                    .map(&mapping),
+                ConstraintInstruction::Has(HasInstruction::new(
+                    has_gov_id.clone(),
+                    Inputs::None([]),
+                    &type_annotations,
+                ))
+                .map(&mapping),
+            ],
+            vec![variable_positions[&var_person], variable_positions[&var_name], variable_positions[&var_gov_id]],
+            &named_variables,
+            3,
+        ))]
+    };
+
+    let execute = |max_cartesian_prefetch_rows| {
+        let query_profile = QueryProfile::new(true);
+        let rows = execute_steps_with_prefetch_rows(
+            steps(),
+            variable_positions.clone(),
+            row_vars.clone(),
+            storage.clone(),
+            thing_manager.clone(),
+            value_parameters.clone(),
+            &query_profile,
+            max_cartesian_prefetch_rows,
+        );
+        assert_eq!(rows.len(), 12);
+        let profile_display = query_profile.to_string();
+        let stage_profiles = query_profile.stage_profiles().read().unwrap();
+        let (_, match_profile) = stage_profiles.iter().next().unwrap();
+        let intersection_step_profile = match_profile.extend_or_get(0, || String::new());
+        let storage_counters = intersection_step_profile.storage_counters();
+        let instruction_profiles = intersection_step_profile.instruction_profiles();
+        assert_eq!(instruction_profiles.len(), 2);
+        for instruction_profile in &instruction_profiles {
+            assert!(instruction_profile.description().unwrap().starts_with("has"));
+            assert!(instruction_profile.opens() > 0);
+            assert!(instruction_profile.advances() > 0);
+            assert!(instruction_profile.peeked_keys() > 0);
+        }
+        assert!(profile_display.contains(instruction_profiles[1].description().unwrap()), "{profile_display}");
+        let (name_reopens, gov_id_reopens) = (instruction_profiles[0].reopens(), instruction_profiles[1].reopens());
+        (
+            storage_counters.get_raw_seek().unwrap(),
+            storage_counters.get_raw_advance().unwrap(),
+            name_reopens,
+            gov_id_reopens,
+        )
+    };
+
+    // expected evaluation
+    //  streaming: the cartesian iterators are opened at person 2, and the Has[unbound] gov id iterator is reopened
+    //      once the gov ids are exhausted for each of the 4 names, reading them from storage again every time
+    //  prefetched: each cartesian iterator reads the names and gov ids of person 2 once, and the 12 combinations are
+    //      then enumerated in memory
+    let (streamed_seeks, streamed_advances, streamed_name_reopens, streamed_gov_id_reopens) = execute(0);
+    let (prefetched_seeks, prefetched_advances, prefetched_name_reopens, prefetched_gov_id_reopens) =
+        execute(DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS);
+    // too small to hold the names of person 2, so the product falls back to streaming
+    let (limited_seeks, limited_advances, _, _) = execute(2);
+
+    // the reopens are attributed to the instruction whose iterator was reopened: only the gov id iterator is
+    // reopened once per name when streaming, while prefetching opens each cartesian iterator once per product
+    assert!(
+        streamed_gov_id_reopens >= streamed_name_reopens + 4,
+        "gov id reopens: {streamed_gov_id_reopens}, name reopens: {streamed_name_reopens}"
+    );
+    assert_eq!(prefetched_gov_id_reopens, prefetched_name_reopens);
+
+    // reopening the gov id iterator for each of the 4 names costs 1 seek each
+    assert!(prefetched_seeks + 4 <= streamed_seeks, "{prefetched_seeks} seeks, streaming: {streamed_seeks}");
+    assert!(prefetched_advances < streamed_advances, "{prefetched_advances} advances, streaming: {streamed_advances}");
+    assert!(limited_seeks >= streamed_seeks, "{limited_seeks} seeks, streaming: {streamed_seeks}");
+    assert!(limited_advances >= streamed_advances, "{limited_advances} advances, streaming: {streamed_advances}");
+}
+
+#[test]
+fn cheap_failing_check_skips_links_reads() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $movie has id $id;
+    //    $casting links (movie: $movie);
+    //    $id == 100;
+
+    // IR to compute type annotations
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let value_int_100 = value_parameters.register_value(Value::Integer(100), Span { begin_offset: 0, end_offset: 0 });
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+
+    let var_movie = conjunction.constraints_mut().get_or_declare_variable("movie", None).unwrap();
+    let var_id = conjunction.constraints_mut().get_or_declare_variable("id", None).unwrap();
+    let var_casting = conjunction.constraints_mut().get_or_declare_variable("casting", None).unwrap();
+    let var_casting_movie_type =
+        conjunction.constraints_mut().get_or_declare_variable("casting_movie_type", None).unwrap();
+
+    let movie_has_id = conjunction.constraints_mut().add_has(var_movie, var_id, None).unwrap().clone();
+    let links_casting_movie =
+        conjunction.constraints_mut().add_links(var_casting, var_movie, var_casting_movie_type, None).unwrap().clone();
+    conjunction.constraints_mut().add_label(var_casting_movie_type, CASTING_MOVIE_LABEL.clone()).unwrap();
+    conjunction
+        .constraints_mut()
+        .add_comparison(Vertex::Variable(var_id), Vertex::Parameter(value_int_100), Comparator::Equal, None)
+        .unwrap();
+
+    let entry = builder.finish().unwrap();
+    let value_parameters = Arc::new(value_parameters);
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let type_annotations = get_type_annotations(&translation_context, &entry, &snapshot, &type_manager);
+    let casting_movie_type =
+        *type_annotations.vertex_annotations_of(&Vertex::Variable(var_casting_movie_type)).unwrap().first().unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_movie, var_id, var_casting], [var_casting_movie_type]);
+
+    // plan:
+    // 1. ReverseHas($movie, $id)
+    // 2. ReverseLinks($casting, $movie)
+    // 3. Check: the links are re-checked in storage, and the comparison rejects every row
+    let links_check = CheckInstruction::Links {
+        relation: CheckVertex::Variable(var_casting),
+        player: CheckVertex::Variable(var_movie),
+        role: CheckVertex::Type(casting_movie_type),
+    }
+    .map(&mapping);
+    let id_equal_100 = CheckInstruction::Comparison {
+        lhs: CheckVertex::Variable(var_id),
+        rhs: CheckVertex::Parameter(value_int_100),
+        comparator: Comparator::Equal,
+        matcher: None,
+    }
+    .map(&mapping);
+
+    let make_steps = |checks: Vec<CheckInstruction<ExecutorVariable>>| {
+        vec![
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_id],
+                vec![ConstraintInstruction::HasReverse(
+                    HasReverseInstruction::new(movie_has_id.clone(), Inputs::None([]), &type_annotations).map(&mapping),
+                )],
+                vec![variable_positions[&var_movie], variable_positions[&var_id]],
+                &named_variables,
+                2,
+            )),
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_casting],
+                vec![ConstraintInstruction::LinksReverse(
+                    LinksReverseInstruction::new(
+                        links_casting_movie.clone(),
+                        Inputs::Single([var_movie]),
+                        &type_annotations,
+                    )
+                    .map(&mapping),
+                )],
+                vec![variable_positions[&var_movie], variable_positions[&var_id], variable_positions[&var_casting]],
+                &named_variables,
+                3,
+            )),
+            // the expensive check is listed first, and must be evaluated last
+            ExecutionStep::Check(CheckStep::new(
+                checks,
+                vec![variable_positions[&var_movie], variable_positions[&var_id], variable_positions[&var_casting]],
+                3,
+            )),
+        ]
+    };
+
+    let check_step_seeks = |checks: Vec<CheckInstruction<ExecutorVariable>>, expected_rows: usize| {
+        let query_profile = QueryProfile::new(true);
+        let rows = execute_steps(
+            make_steps(checks),
+            variable_positions.clone(),
+            row_vars.clone(),
+            storage.clone(),
+            thing_manager.clone(),
+            value_parameters.clone(),
+            &query_profile,
+        );
+        assert_eq!(rows.len(), expected_rows);
+        assert!(rows.iter().all(|row| row.is_ok()));
+
+        let stage_profiles = query_profile.stage_profiles().read().unwrap();
+        let (_, match_profile) = stage_profiles.iter().next().unwrap();
+        let check_step_profile = match_profile.extend_or_get(2, || String::new());
+        check_step_profile.storage_counters().get_raw_seek().unwrap()
+    };
+
+    // every casting of a movie is looked up in storage: 1 seek per row
+    let links_only_seeks = check_step_seeks(vec![links_check.clone()], 3);
+    assert_eq!(links_only_seeks, 3);
+
+    // the comparison is evaluated first and rejects every row before the links are looked up
+    let links_and_comparison_seeks = check_step_seeks(vec![links_check, id_equal_100], 0);
+    assert_eq!(links_and_comparison_seeks, 0);
+}
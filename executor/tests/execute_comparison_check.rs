@@ -152,6 +152,7 @@ fn attribute_equality() {
         lhs: CheckVertex::Variable(var_age_b),
         rhs: CheckVertex::Variable(var_age_a),
         comparator: Comparator::Equal,
+        matcher: None,
     });
 
     // Plan
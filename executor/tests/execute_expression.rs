@@ -13,7 +13,7 @@ use compiler::annotation::expression::{
     ExpressionCompileError,
 };
 use encoding::value::{value::Value, value_type::ValueTypeCategory};
-use executor::read::expression_executor::{evaluate_expression, ExpressionValue};
+use executor::read::expression_executor::{evaluate_expression, evaluate_expression_batch, ExpressionValue};
 use ir::{
     pattern::{constraint::Constraint, variable_category::VariableCategory},
     pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
@@ -315,3 +315,40 @@ fn list_ops() {
         assert_eq!(&*as_list!(result), &[Value::Integer(87), Value::Integer(65)]);
     }
 }
+
+#[test]
+fn batch_evaluation_matches_per_row_evaluation() {
+    let integer = || ExpressionValueType::Single(ValueTypeCategory::Integer.try_into_value_type().unwrap());
+    let rows = (-3..5).map(|i| (Value::Integer(i * 7), Value::Integer(i))).collect_vec();
+
+    // the second expression loads `$a` twice, and the last divides by zero in one of the rows
+    for source in ["$a * $b + 3", "$a - $a * $b", "($a + 1.5e0) / 2", "$a / $b"] {
+        let (vars, expr, params) =
+            compile_expression_via_match(source, HashMap::from([("a", integer()), ("b", integer())])).unwrap();
+        let [a, b] = ["a", "b"].map(|name| *vars.get(name).unwrap());
+
+        let per_row = rows
+            .iter()
+            .map(|(a_value, b_value)| {
+                let inputs = HashMap::from([
+                    (a, ExpressionValue::Single(a_value.clone())),
+                    (b, ExpressionValue::Single(b_value.clone())),
+                ]);
+                evaluate_expression(&expr, inputs, &params)
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let columns = HashMap::from([
+            (a, rows.iter().map(|(a_value, _)| ExpressionValue::Single(a_value.clone())).collect()),
+            (b, rows.iter().map(|(_, b_value)| ExpressionValue::Single(b_value.clone())).collect()),
+        ]);
+        let batch = evaluate_expression_batch(&expr, columns, rows.len(), &params);
+        match (per_row, batch) {
+            (Ok(per_row), Ok(batch)) => assert_eq!(per_row, batch, "{source}"),
+            (per_row, batch) => assert!(per_row.is_err() && batch.is_err(), "{source}: {per_row:?} vs {batch:?}"),
+        }
+    }
+
+    let (_, expr, params) = compile_expression_via_match("[12, 34]", HashMap::new()).unwrap();
+    let batch = evaluate_expression_batch(&expr, HashMap::new(), 3, &params).unwrap();
+    assert_eq!(batch, vec![evaluate_expression(&expr, HashMap::new(), &params).unwrap(); 3]);
+}
@@ -4,22 +4,43 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{collections::HashMap, iter, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    iter,
+    sync::{Arc, Mutex},
+};
 
 use answer::variable_value::VariableValue;
-use compiler::VariablePosition;
-use concept::{thing::thing_manager::ThingManager, type_::type_manager::TypeManager};
+use compiler::{
+    annotation::pipeline::{annotate_preamble_and_pipeline, AnnotatedPipeline, AnnotatedStage},
+    executable::{
+        function::{ExecutableFunctionRegistry, FunctionVersions},
+        match_::planner::{conjunction_executable::ConjunctionExecutable, plan::PlannerConfig, plan_cache::PlanCache},
+        pipeline::compile_pipeline_and_functions,
+    },
+    VariablePosition,
+};
+use concept::{
+    thing::{statistics::StatisticsFreshness, thing_manager::ThingManager},
+    type_::type_manager::TypeManager,
+};
 use encoding::{graph::definition::definition_key_generator::DefinitionKeyGenerator, value::value::Value};
 use executor::{
+    conjunction_executor::ConjunctionExecutor,
+    error::ReadExecutionError,
     pipeline::{stage::ExecutionContext, PipelineExecutionError},
     row::MaybeOwnedRow,
     ExecutionInterrupt,
 };
-use function::function_manager::FunctionManager;
-use itertools::Either;
+use function::function_manager::{FunctionManager, ReadThroughFunctionSignatureIndex};
+use ir::{
+    pipeline::function_signature::HashMapFunctionSignatureIndex,
+    translation::pipeline::{translate_pipeline, TranslatedPipeline},
+};
+use itertools::{Either, Itertools};
 use lending_iterator::LendingIterator;
 use query::{query_cache::QueryCache, query_manager::QueryManager};
-use resource::profile::CommitProfile;
+use resource::profile::{CommitProfile, QueryProfile};
 use storage::{durability_client::WALClient, snapshot::CommittableSnapshot, MVCCStorage};
 use test_utils::TempDir;
 use test_utils_concept::{load_managers, setup_concept_storage};
@@ -30,6 +51,7 @@ struct Context {
     type_manager: Arc<TypeManager>,
     thing_manager: Arc<ThingManager>,
     function_manager: FunctionManager,
+    function_versions: Arc<FunctionVersions>,
     query_manager: QueryManager,
     _tmp_dir: TempDir,
 }
@@ -95,7 +117,9 @@ fn setup_common(schema: &str) -> Context {
     setup_concept_storage(&mut storage);
 
     let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+    let function_versions = Arc::new(FunctionVersions::default());
+    let function_manager =
+        FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, function_versions.clone());
     let query_manager = QueryManager::new(None);
 
     let mut snapshot = storage.clone().open_snapshot_schema();
@@ -108,7 +132,7 @@ fn setup_common(schema: &str) -> Context {
     let query_manager = QueryManager::new(Some(Arc::new(QueryCache::new())));
     // reload to obtain latest vertex generators and statistics entries
     let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-    Context { _tmp_dir, storage, type_manager, function_manager, query_manager, thing_manager }
+    Context { _tmp_dir, storage, type_manager, function_manager, function_versions, query_manager, thing_manager }
 }
 
 fn run_read_query(
@@ -173,6 +197,97 @@ fn run_write_query(
     result.map(move |rows| (rows, rows_positions))
 }
 
+fn run_schema_query(context: &Context, query: &str) {
+    let mut snapshot = context.storage.clone().open_snapshot_schema();
+    let schema = typeql::parse_query(query).unwrap().into_structure().into_schema();
+    context
+        .query_manager
+        .execute_schema(
+            &mut snapshot,
+            &context.type_manager,
+            &context.thing_manager,
+            &context.function_manager,
+            schema,
+            query,
+        )
+        .unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+    // as on the commit of a schema transaction, redefined functions are only invalidated once the change is committed
+    for function_id in context.function_manager.take_invalidated_functions() {
+        context.function_versions.invalidate(&function_id);
+    }
+}
+
+// Compiles the match stage through the given plan cache, against the functions compiled for the query
+fn compile_match_calling_functions(
+    context: &Context,
+    query: &str,
+    plan_cache: Option<&PlanCache>,
+) -> (ConjunctionExecutable, Arc<ExecutableFunctionRegistry>) {
+    let snapshot = context.storage.clone().open_snapshot_read();
+    let pipeline = typeql::parse_query(query).unwrap().into_structure().into_pipeline();
+    let function_index = ReadThroughFunctionSignatureIndex::new(
+        &snapshot,
+        &context.function_manager,
+        HashMapFunctionSignatureIndex::empty(),
+    );
+    let TranslatedPipeline {
+        translated_preamble,
+        translated_stages,
+        translated_fetch,
+        mut variable_registry,
+        value_parameters,
+    } = translate_pipeline(&snapshot, &function_index, &pipeline).unwrap();
+    let schema_functions = context.function_manager.get_annotated_functions(&snapshot, &context.type_manager).unwrap();
+    let AnnotatedPipeline { annotated_preamble, annotated_stages, annotated_fetch } = annotate_preamble_and_pipeline(
+        &snapshot,
+        &context.type_manager,
+        schema_functions.clone(),
+        &mut variable_registry,
+        &value_parameters,
+        translated_preamble,
+        translated_stages,
+        translated_fetch,
+    )
+    .unwrap();
+    let function_registry = compile_pipeline_and_functions(
+        context.thing_manager.statistics(),
+        StatisticsFreshness::Current,
+        &variable_registry,
+        &value_parameters,
+        &schema_functions,
+        context.function_manager.function_versions(),
+        None,
+        annotated_preamble,
+        annotated_stages.clone(),
+        annotated_fetch,
+        &HashSet::new(),
+        None,
+    )
+    .unwrap()
+    .executable_functions;
+
+    let AnnotatedStage::Match { block, block_annotations, executable_expressions, .. } = &annotated_stages[0] else {
+        unreachable!("Expected a match stage")
+    };
+    let executable = compiler::executable::match_::planner::compile(
+        block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        block_annotations,
+        &variable_registry,
+        executable_expressions,
+        &value_parameters,
+        context.thing_manager.statistics(),
+        &function_registry,
+        &PlannerConfig::default(),
+        plan_cache,
+    )
+    .unwrap();
+    (executable, Arc::new(function_registry))
+}
+
 #[test]
 fn function_compiles() {
     let context = setup_common(COMMON_SCHEMA);
@@ -748,3 +863,133 @@ fn return_check() {
         assert_eq!(rows[0].get(*positions.get("checked").unwrap()), &VariableValue::Value(Value::Boolean(false)));
     }
 }
+
+#[test]
+fn redefining_function_invalidates_plans_and_executables() {
+    let custom_schema = r#"define
+        attribute age value integer;
+        entity person owns age;
+        fun adults() -> { person }:
+        match
+            $p isa person, has age $a;
+            $a >= 18;
+        return { $p };
+    "#;
+    let context = setup_common(custom_schema);
+    run_write_query(
+        &context,
+        "insert $_ isa person, has age 12; $_ isa person, has age 20; $_ isa person, has age 40;",
+    )
+    .unwrap();
+
+    let invalidated = Arc::new(Mutex::new(Vec::new()));
+    let recorded = invalidated.clone();
+    context.function_versions.on_invalidation(move |function_id| {
+        recorded.lock().unwrap().push(function_id.clone());
+    });
+
+    let query = "match let $p in adults();";
+    let plan_cache = PlanCache::default();
+    let (executable, function_registry) = compile_match_calling_functions(&context, query, Some(&plan_cache));
+    let function_id = executable.function_versions().keys().exactly_one().unwrap().clone();
+    let (recompiled, _) = compile_match_calling_functions(&context, query, Some(&plan_cache));
+    assert!(recompiled.planner_statistics().is_cached_plan());
+
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let profile = QueryProfile::new(false);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &context.thing_manager,
+        MaybeOwnedRow::empty(),
+        function_registry,
+        &profile,
+    );
+    assert!(executor.is_ok());
+
+    run_schema_query(
+        &context,
+        r#"redefine
+        fun adults() -> { person }:
+        match
+            $p isa person, has age $a;
+            $a >= 21;
+        return { $p };
+    "#,
+    );
+    assert!(invalidated.lock().unwrap().contains(&function_id));
+
+    let (replanned, function_registry) = compile_match_calling_functions(&context, query, Some(&plan_cache));
+    assert!(!replanned.planner_statistics().is_cached_plan());
+    assert_ne!(replanned.function_versions()[&function_id], executable.function_versions()[&function_id]);
+
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let stale = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &context.thing_manager,
+        MaybeOwnedRow::empty(),
+        function_registry,
+        &profile,
+    );
+    let Err(error) = stale else { panic!("Expected the stale executable to be rejected") };
+    assert!(matches!(*error, ReadExecutionError::FunctionVersionMismatch { .. }), "{error:?}");
+}
+
+#[test]
+fn uncommitted_redefinition_does_not_invalidate_executables() {
+    let custom_schema = r#"define
+        attribute age value integer;
+        entity person owns age;
+        fun adults() -> { person }:
+        match
+            $p isa person, has age $a;
+            $a >= 18;
+        return { $p };
+    "#;
+    let context = setup_common(custom_schema);
+
+    let query = "match let $p in adults();";
+    let (executable, _) = compile_match_calling_functions(&context, query, None);
+
+    // the redefinition is written to a schema snapshot that is dropped instead of committed
+    let redefine = r#"redefine
+        fun adults() -> { person }:
+        match
+            $p isa person, has age $a;
+            $a >= 21;
+        return { $p };
+    "#;
+    let function_manager =
+        FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, context.function_versions.clone());
+    let mut snapshot = context.storage.clone().open_snapshot_schema();
+    let schema = typeql::parse_query(redefine).unwrap().into_structure().into_schema();
+    context
+        .query_manager
+        .execute_schema(
+            &mut snapshot,
+            &context.type_manager,
+            &context.thing_manager,
+            &function_manager,
+            schema,
+            redefine,
+        )
+        .unwrap();
+    drop(snapshot);
+    drop(function_manager);
+
+    let (recompiled, function_registry) = compile_match_calling_functions(&context, query, None);
+    assert_eq!(recompiled.function_versions(), executable.function_versions());
+
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let profile = QueryProfile::new(false);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &context.thing_manager,
+        MaybeOwnedRow::empty(),
+        function_registry,
+        &profile,
+    );
+    assert!(executor.is_ok());
+}
@@ -21,7 +21,7 @@ use compiler::{
                 ConstraintInstruction, Inputs,
             },
             planner::{
-                conjunction_executable::{ConjunctionExecutable, ExecutionStep, IntersectionStep},
+                conjunction_executable::{ConjunctionExecutable, ExecutionStep, IntersectionStep, UnsortedJoinStep},
                 plan::PlannerStatistics,
             },
         },
@@ -35,8 +35,11 @@ use concept::{
 };
 use encoding::value::{label::Label, value::Value, value_type::ValueType};
 use executor::{
-    conjunction_executor::ConjunctionExecutor, error::ReadExecutionError, pipeline::stage::ExecutionContext,
-    row::MaybeOwnedRow, ExecutionInterrupt,
+    conjunction_executor::ConjunctionExecutor,
+    error::ReadExecutionError,
+    pipeline::stage::{ExecutionContext, DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT},
+    row::MaybeOwnedRow,
+    ExecutionInterrupt,
 };
 use ir::{
     pattern::constraint::IsaKind,
@@ -155,11 +158,10 @@ fn position_mapping<const N: usize, const M: usize>(
     (position_to_var, variable_positions, mapping, named_variables)
 }
 
-#[test]
-fn traverse_has_unbounded_sorted_from() {
-    let (_tmp_dir, mut storage) = create_core_storage();
-    setup_database(&mut storage);
-
+fn execute_has_age(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    configure_step: impl FnOnce(IntersectionStep) -> IntersectionStep,
+) -> Vec<MaybeOwnedRow<'static>> {
     // query:
     //   match
     //    $person isa person, has age $age;
@@ -204,7 +206,7 @@ fn traverse_has_unbounded_sorted_from() {
         position_mapping([var_person, var_age], [var_age_type, var_person_type]);
 
     // Plan
-    let steps = vec![ExecutionStep::Intersection(IntersectionStep::new(
+    let step = IntersectionStep::new(
         mapping[&var_person],
         vec![ConstraintInstruction::Has(
             HasInstruction::new(has_age, Inputs::None([]), &entry_annotations).map(&mapping),
@@ -212,7 +214,8 @@ fn traverse_has_unbounded_sorted_from() {
         vec![variable_positions[&var_person], variable_positions[&var_age]],
         &named_variables,
         2,
-    ))];
+    );
+    let steps = vec![ExecutionStep::Intersection(configure_step(step))];
     let executable =
         ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
 
@@ -234,15 +237,180 @@ fn traverse_has_unbounded_sorted_from() {
     let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
         .map_static(|row| row.map(|row| row.clone().into_owned()).map_err(|err| Box::new(err.clone())))
         .collect();
+    rows.into_iter().map(|row| row.unwrap()).collect()
+}
+
+#[test]
+fn traverse_has_unbounded_sorted_from() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let rows = execute_has_age(&storage, |step| step);
     assert_eq!(rows.len(), 7);
 
     for row in rows {
-        let r = row.unwrap();
-        assert_eq!(r.multiplicity(), 1);
-        print!("{}", r);
+        assert_eq!(row.multiplicity(), 1);
+        print!("{}", row);
     }
 }
 
+#[test]
+fn traverse_has_single_instruction_matches_general_path() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let rows_of = |configure_step: fn(IntersectionStep) -> IntersectionStep| {
+        execute_has_age(&storage, configure_step)
+            .into_iter()
+            .map(|row| (row.row().to_vec(), row.multiplicity(), row.provenance()))
+            .collect::<Vec<_>>()
+    };
+    let fast_path_rows = rows_of(|step| step);
+    assert_eq!(fast_path_rows.len(), 7);
+    assert_eq!(fast_path_rows, rows_of(IntersectionStep::without_single_instruction_fast_path));
+}
+
+#[test]
+fn traverse_has_dedup_on_sort_variable() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let dedup = |mut step: IntersectionStep| {
+        step.dedup_on_sort_variable = true;
+        step
+    };
+    let rows = execute_has_age(&storage, dedup);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows.iter().map(|row| row.get(VariablePosition::new(0))).collect::<HashSet<_>>().len(), 3);
+
+    let rows = execute_has_age(&storage, |step| dedup(step.without_single_instruction_fast_path()));
+    assert_eq!(rows.len(), 3);
+}
+
+/// Runs `$person isa person, has age $age;` with the owner bound by a preceding isa step, returning the rows and
+/// the number of storage advances made by the has step.
+fn execute_has_age_per_person(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    configure_step: impl FnOnce(IntersectionStep) -> IntersectionStep,
+) -> (Vec<MaybeOwnedRow<'static>>, u64) {
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("person_type", None).unwrap();
+    let var_age_type = conjunction.constraints_mut().get_or_declare_variable("age_type", None).unwrap();
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("person", None).unwrap();
+    let var_age = conjunction.constraints_mut().get_or_declare_variable("age", None).unwrap();
+
+    let isa_person = conjunction
+        .constraints_mut()
+        .add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None)
+        .unwrap()
+        .clone();
+    let has_age = conjunction.constraints_mut().add_has(var_person, var_age, None).unwrap().clone();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_age, var_age_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_label(var_age_type, AGE_LABEL.clone()).unwrap();
+    let entry = builder.finish().unwrap();
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        &translation_context.variable_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_person, var_age], [var_age_type, var_person_type]);
+
+    let steps = vec![
+        ExecutionStep::Intersection(IntersectionStep::new(
+            mapping[&var_person],
+            vec![ConstraintInstruction::Isa(
+                IsaInstruction::new(isa_person, Inputs::None([]), &entry_annotations).map(&mapping),
+            )],
+            vec![variable_positions[&var_person]],
+            &named_variables,
+            1,
+        )),
+        ExecutionStep::Intersection(configure_step(IntersectionStep::new(
+            mapping[&var_age],
+            vec![ConstraintInstruction::Has(
+                HasInstruction::new(has_age, Inputs::Single([var_person]), &entry_annotations).map(&mapping),
+            )],
+            vec![variable_positions[&var_person], variable_positions[&var_age]],
+            &named_variables,
+            2,
+        ))),
+    ];
+    let executable =
+        ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
+
+    let snapshot = Arc::new(snapshot);
+    let query_profile = QueryProfile::new(true);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &query_profile,
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
+        .map_static(|row| row.map(|row| row.clone().into_owned()).map_err(|err| Box::new(err.clone())))
+        .collect();
+    let rows = rows.into_iter().map(|row| row.unwrap()).collect();
+
+    let stage_profiles = query_profile.stage_profiles().read().unwrap();
+    let (_, match_profile) = stage_profiles.iter().next().unwrap();
+    let has_step_profile = match_profile.extend_or_get(1, String::new);
+    let advances = has_step_profile.storage_counters().get_raw_advance().unwrap();
+    (rows, advances)
+}
+
+#[test]
+fn traverse_has_per_group_limit() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let ages_per_person = |rows: &[MaybeOwnedRow<'static>]| {
+        let mut ages_per_person: HashMap<_, usize> = HashMap::new();
+        for row in rows {
+            *ages_per_person.entry(row.get(VariablePosition::new(0)).clone()).or_default() += 1;
+        }
+        ages_per_person
+    };
+    let limit_two_per_person = |mut step: IntersectionStep| {
+        step.per_group_limit = Some((vec![VariablePosition::new(0)], 2));
+        step
+    };
+
+    let (rows, unlimited_advances) = execute_has_age_per_person(&storage, |step| step);
+    assert_eq!(rows.len(), 7);
+    assert_eq!(ages_per_person(&rows).into_values().max(), Some(3));
+
+    let (rows, limited_advances) = execute_has_age_per_person(&storage, limit_two_per_person);
+    assert_eq!(rows.len(), 5);
+    assert!(ages_per_person(&rows).into_values().all(|count| count <= 2));
+    assert!(limited_advances < unlimited_advances, "{limited_advances} >= {unlimited_advances}");
+
+    let (rows, general_path_advances) =
+        execute_has_age_per_person(&storage, |step| limit_two_per_person(step.without_single_instruction_fast_path()));
+    assert_eq!(rows.len(), 5);
+    assert!(ages_per_person(&rows).into_values().all(|count| count <= 2));
+    assert!(general_path_advances < unlimited_advances, "{general_path_advances} >= {unlimited_advances}");
+}
+
 #[test]
 fn traverse_has_bounded_sorted_from_chain_intersect() {
     let (_tmp_dir, mut storage) = create_core_storage();
@@ -354,11 +522,10 @@ fn traverse_has_bounded_sorted_from_chain_intersect() {
     }
 }
 
-#[test]
-fn traverse_has_unbounded_sorted_from_intersect() {
-    let (_tmp_dir, mut storage) = create_core_storage();
-    setup_database(&mut storage);
-
+fn execute_has_name_and_age_intersection(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    max_cartesian_group_product: u64,
+) -> Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> {
     // query:
     //   match
     //    $person has name $name, has age $age;
@@ -438,12 +605,29 @@ fn traverse_has_unbounded_sorted_from_intersect() {
     )
     .unwrap();
 
-    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
-    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default())
+        .with_max_cartesian_group_product(max_cartesian_group_product);
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+
+    let mut rows = Vec::new();
+    while let Some(row) = iterator.next() {
+        match row {
+            Ok(row) => rows.push(Ok(row.clone().into_owned())),
+            Err(err) => {
+                rows.push(Err(Box::new(err.clone())));
+                break;
+            }
+        }
+    }
+    rows
+}
 
-    let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
-        .map_static(|row| row.map(|row| row.clone().into_owned()).map_err(|err| Box::new(err.clone())))
-        .collect();
+#[test]
+fn traverse_has_unbounded_sorted_from_intersect() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let rows = execute_has_name_and_age_intersection(&storage, DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT);
     assert_eq!(rows.len(), 7);
 
     for row in rows {
@@ -453,6 +637,33 @@ fn traverse_has_unbounded_sorted_from_intersect() {
     }
 }
 
+#[test]
+fn intersect_cartesian_group_at_limit() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // person_1 has 3 ages and 2 names, producing the largest cartesian group of 6 answers
+    let rows = execute_has_name_and_age_intersection(&storage, 6);
+    assert_eq!(rows.len(), 7);
+    assert!(rows.iter().all(|row| row.is_ok()));
+}
+
+#[test]
+fn intersect_cartesian_group_exceeding_limit() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    let rows = execute_has_name_and_age_intersection(&storage, 5);
+    let error = rows.into_iter().find_map(|row| row.err()).expect("Expected the cartesian group to exceed the limit");
+    match *error {
+        ReadExecutionError::CartesianGroupTooLarge { estimated, limit, .. } => {
+            assert_eq!(estimated, 6);
+            assert_eq!(limit, 5);
+        }
+        other => panic!("Unexpected error: {other:?}"),
+    }
+}
+
 #[test]
 fn traverse_has_unbounded_sorted_to_merged() {
     let (_tmp_dir, mut storage) = create_core_storage();
@@ -642,3 +853,267 @@ fn traverse_has_reverse_unbounded_sorted_from() {
         print!("{}", r);
     }
 }
+
+#[test]
+fn restart_has_unbounded_sorted_to_with_newer_snapshot() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $person has $attribute;
+
+    // IR
+
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("person_type", None).unwrap();
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("person", None).unwrap();
+    let var_attribute = conjunction.constraints_mut().get_or_declare_variable("attr", None).unwrap();
+    let has_attribute = conjunction.constraints_mut().add_has(var_person, var_attribute, None).unwrap().clone();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    let entry = builder.finish().unwrap();
+
+    let snapshot: ReadSnapshot<WALClient> = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let variable_registry = &translation_context.variable_registry;
+    let previous_stage_variable_annotations = &BTreeMap::new();
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        variable_registry,
+        &type_manager,
+        previous_stage_variable_annotations,
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_person, var_attribute], [var_person_type]);
+
+    // Plan: sorted on the attribute, so the owners are read from the snapshot up front
+    let steps = vec![ExecutionStep::Intersection(IntersectionStep::new(
+        mapping[&var_attribute],
+        vec![ConstraintInstruction::Has(
+            HasInstruction::new(has_attribute, Inputs::None([]), &entry_annotations).map(&mapping),
+        )],
+        vec![variable_positions[&var_person], variable_positions[&var_attribute]],
+        &named_variables,
+        2,
+    ))];
+    let executable =
+        ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
+
+    // Executor
+    let snapshot = Arc::new(snapshot);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let mut iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+
+    let mut count = 0;
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+        count += 1;
+    }
+    assert_eq!(count, 10);
+
+    // a new owner with a new attribute, committed from another snapshot
+    {
+        let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+        let mut snapshot = storage.clone().open_snapshot_write();
+        let person_type = type_manager.get_entity_type(&snapshot, &PERSON_LABEL).unwrap().unwrap();
+        let age_type = type_manager.get_attribute_type(&snapshot, &AGE_LABEL).unwrap().unwrap();
+        let person_4 = thing_manager.create_entity(&mut snapshot, person_type).unwrap();
+        let age_6 = thing_manager.create_attribute(&mut snapshot, age_type, Value::Integer(15)).unwrap();
+        person_4.set_has_unordered(&mut snapshot, &thing_manager, &age_6, StorageCounters::DISABLED).unwrap();
+        let finalise_result = thing_manager.finalise(&mut snapshot, StorageCounters::DISABLED);
+        assert!(finalise_result.is_ok());
+        snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+    }
+
+    iterator.restart_with_snapshot(Arc::new(storage.clone().open_snapshot_read()));
+    let mut count = 0;
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+        count += 1;
+    }
+    assert_eq!(count, 11);
+}
+
+/// Creates one person owning `ages_per_person[i]` distinct ages for each `i`.
+fn setup_people_with_ages(storage: &mut Arc<MVCCStorage<WALClient>>, ages_per_person: &[usize]) {
+    setup_concept_storage(storage);
+
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_write();
+
+    let person_type = type_manager.create_entity_type(&mut snapshot, &PERSON_LABEL).unwrap();
+    let age_type = type_manager.create_attribute_type(&mut snapshot, &AGE_LABEL).unwrap();
+    age_type.set_value_type(&mut snapshot, &type_manager, &thing_manager, ValueType::Integer).unwrap();
+    let person_owns_age = person_type
+        .set_owns(
+            &mut snapshot,
+            &type_manager,
+            &thing_manager,
+            age_type,
+            Ordering::Unordered,
+            StorageCounters::DISABLED,
+        )
+        .unwrap();
+    person_owns_age
+        .set_annotation(
+            &mut snapshot,
+            &type_manager,
+            &thing_manager,
+            OwnsAnnotation::Cardinality(AnnotationCardinality::new(0, None)),
+        )
+        .unwrap();
+
+    let max_ages = ages_per_person.iter().copied().max().unwrap_or(0);
+    let ages = (0..max_ages)
+        .map(|age| thing_manager.create_attribute(&mut snapshot, age_type, Value::Integer(age as i64)).unwrap())
+        .collect::<Vec<_>>();
+    for &age_count in ages_per_person {
+        let person = thing_manager.create_entity(&mut snapshot, person_type).unwrap();
+        for age in &ages[..age_count] {
+            person.set_has_unordered(&mut snapshot, &thing_manager, age, StorageCounters::DISABLED).unwrap();
+        }
+    }
+
+    let finalise_result = thing_manager.finalise(&mut snapshot, StorageCounters::DISABLED);
+    assert!(finalise_result.is_ok());
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+}
+
+/// Runs `$person isa person, has age $age;` with the has iterated by an unsorted join for each person.
+fn execute_has_age_unsorted(storage: &Arc<MVCCStorage<WALClient>>) -> Vec<MaybeOwnedRow<'static>> {
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("person_type", None).unwrap();
+    let var_age_type = conjunction.constraints_mut().get_or_declare_variable("age_type", None).unwrap();
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("person", None).unwrap();
+    let var_age = conjunction.constraints_mut().get_or_declare_variable("age", None).unwrap();
+
+    let isa_person = conjunction
+        .constraints_mut()
+        .add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None)
+        .unwrap()
+        .clone();
+    let has_age = conjunction.constraints_mut().add_has(var_person, var_age, None).unwrap().clone();
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_age, var_age_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_label(var_age_type, AGE_LABEL.clone()).unwrap();
+    let entry = builder.finish().unwrap();
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        &translation_context.variable_registry,
+        &type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_person, var_age], [var_age_type, var_person_type]);
+
+    let steps = vec![
+        ExecutionStep::Intersection(IntersectionStep::new(
+            mapping[&var_person],
+            vec![ConstraintInstruction::Isa(
+                IsaInstruction::new(isa_person, Inputs::None([]), &entry_annotations).map(&mapping),
+            )],
+            vec![variable_positions[&var_person]],
+            &named_variables,
+            1,
+        )),
+        ExecutionStep::UnsortedJoin(UnsortedJoinStep::new(
+            ConstraintInstruction::Has(
+                HasInstruction::new(has_age, Inputs::Single([var_person]), &entry_annotations).map(&mapping),
+            ),
+            Vec::new(),
+            &[variable_positions[&var_person], variable_positions[&var_age]],
+            &named_variables,
+            2,
+        )),
+    ];
+    let executable =
+        ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
+
+    let snapshot = Arc::new(snapshot);
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
+        .map_static(|row| row.map(|row| row.clone().into_owned()).map_err(|err| Box::new(err.clone())))
+        .collect();
+    rows.into_iter().map(|row| row.unwrap()).collect()
+}
+
+fn ages_per_person(rows: &[MaybeOwnedRow<'static>]) -> Vec<usize> {
+    let mut ages_per_person: HashMap<_, usize> = HashMap::new();
+    for row in rows {
+        assert_eq!(row.multiplicity(), 1);
+        *ages_per_person.entry(row.get(VariablePosition::new(0)).clone()).or_default() += 1;
+    }
+    let mut counts = ages_per_person.into_values().collect::<Vec<_>>();
+    counts.sort();
+    counts
+}
+
+#[test]
+fn unsorted_join_has_across_batches() {
+    // more people than fit in one input batch, and more ages for one person than fit in one output batch
+    let mut ages = (0..100).map(|i| i % 3).collect::<Vec<_>>();
+    ages.push(150);
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_people_with_ages(&mut storage, &ages);
+
+    let rows = execute_has_age_unsorted(&storage);
+    assert_eq!(rows.len(), ages.iter().sum::<usize>());
+    let mut expected = ages.into_iter().filter(|&count| count > 0).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(ages_per_person(&rows), expected);
+    assert_eq!(rows.iter().map(|row| row.row().to_vec()).collect::<HashSet<_>>().len(), rows.len());
+}
+
+#[test]
+fn unsorted_join_has_with_empty_iterators() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_people_with_ages(&mut storage, &[0, 0, 0]);
+    assert!(execute_has_age_unsorted(&storage).is_empty());
+
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_people_with_ages(&mut storage, &[0, 2, 0, 0, 1, 0]);
+    let rows = execute_has_age_unsorted(&storage);
+    assert_eq!(ages_per_person(&rows), vec![1, 2]);
+}
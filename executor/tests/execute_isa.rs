@@ -18,6 +18,7 @@ use compiler::{
         match_::{
             instructions::{
                 thing::{IsaInstruction, IsaReverseInstruction},
+                type_::SubInstruction,
                 ConstraintInstruction, Inputs,
             },
             planner::{
@@ -35,7 +36,10 @@ use executor::{
     row::MaybeOwnedRow, ExecutionInterrupt,
 };
 use ir::{
-    pattern::{constraint::IsaKind, Vertex},
+    pattern::{
+        constraint::{IsaKind, SubKind},
+        Vertex,
+    },
     pipeline::{block::Block, ParameterRegistry},
     translation::PipelineTranslationContext,
 };
@@ -866,3 +870,101 @@ fn traverse_isa_reverse_fixed_type_subtype() {
 
     assert_eq!(rows.len(), 6);
 }
+
+#[test]
+fn intersect_sub_with_isa_reverse_sorted_type() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+
+    // 30 types: one root with 29 direct subtypes, one instance of each
+    const TYPE_COUNT: usize = 30;
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_write();
+    let root_type = type_manager.create_entity_type(&mut snapshot, &Label::build("type_0", None)).unwrap();
+    thing_manager.create_entity(&mut snapshot, root_type).unwrap();
+    for i in 1..TYPE_COUNT {
+        let type_ = type_manager.create_entity_type(&mut snapshot, &Label::build(&format!("type_{i}"), None)).unwrap();
+        type_.set_supertype(&mut snapshot, &type_manager, &thing_manager, root_type).unwrap();
+        thing_manager.create_entity(&mut snapshot, type_).unwrap();
+    }
+    let finalise_result = thing_manager.finalise(&mut snapshot, StorageCounters::DISABLED);
+    assert!(finalise_result.is_ok());
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    // query:
+    //   match $t sub $super; $x isa $t;
+
+    // IR
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_type = conjunction.constraints_mut().get_or_declare_variable("t", None).unwrap();
+    let var_super = conjunction.constraints_mut().get_or_declare_variable("super", None).unwrap();
+    let var_x = conjunction.constraints_mut().get_or_declare_variable("x", None).unwrap();
+
+    let sub = conjunction
+        .constraints_mut()
+        .add_sub(SubKind::Subtype, var_type.into(), var_super.into(), None)
+        .unwrap()
+        .clone();
+    let isa = conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_x, var_type.into(), None).unwrap().clone();
+    let entry = builder.finish().unwrap();
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let variable_registry = &translation_context.variable_registry;
+    let previous_stage_variable_annotations = &BTreeMap::new();
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        variable_registry,
+        &type_manager,
+        previous_stage_variable_annotations,
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) = position_mapping([var_type, var_super, var_x], []);
+
+    // Plan: a single intersection on $t, mixing a type-level and a thing-level instruction
+    let steps = vec![ExecutionStep::Intersection(IntersectionStep::new(
+        mapping[&var_type],
+        vec![
+            ConstraintInstruction::Sub(SubInstruction::new(sub, Inputs::None([]), &entry_annotations).map(&mapping)),
+            ConstraintInstruction::IsaReverse(
+                IsaReverseInstruction::new(isa, Inputs::None([]), &entry_annotations).map(&mapping),
+            ),
+        ],
+        vec![variable_positions[&var_type], variable_positions[&var_super], variable_positions[&var_x]],
+        &named_variables,
+        3,
+    ))];
+
+    let executable =
+        ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
+
+    // Executor
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+
+    let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> =
+        iterator.map_static(|row| row.map(|row| row.into_owned()).map_err(|err| Box::new(err.clone()))).collect();
+    let answer_count: u64 = rows.iter().map(|row| row.as_ref().unwrap().multiplicity()).sum();
+
+    // $t = root: 1 supertype x 30 instances; each other $t: 2 supertypes x 1 instance
+    assert_eq!(answer_count, (TYPE_COUNT + 2 * (TYPE_COUNT - 1)) as u64);
+}
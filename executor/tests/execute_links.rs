@@ -17,7 +17,9 @@ use compiler::{
         function::ExecutableFunctionRegistry,
         match_::{
             instructions::{
-                thing::{IsaInstruction, LinksInstruction, LinksReverseInstruction},
+                thing::{
+                    BoundLinksHint, BoundLinksInstruction, IsaInstruction, LinksInstruction, LinksReverseInstruction,
+                },
                 ConstraintInstruction, Inputs,
             },
             planner::{
@@ -42,7 +44,7 @@ use executor::{
     row::MaybeOwnedRow, ExecutionInterrupt,
 };
 use ir::{
-    pattern::constraint::IsaKind,
+    pattern::{constraint::IsaKind, Vertex},
     pipeline::{block::Block, ParameterRegistry},
     translation::PipelineTranslationContext,
 };
@@ -701,6 +703,112 @@ fn traverse_links_bounded_relation_player() {
     }
 }
 
+#[test]
+fn traverse_links_compound_join_on_relation_and_player() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $membership links (member: $person), links ($role: $person), isa membership;
+    //
+    // joined on the pair ($membership, $person)
+
+    // IR
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_membership_type = conjunction.constraints_mut().get_or_declare_variable("membership_type", None).unwrap();
+    let var_membership_member_type =
+        conjunction.constraints_mut().get_or_declare_variable("membership_member_type", None).unwrap();
+
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("person", None).unwrap();
+    let var_membership = conjunction.constraints_mut().get_or_declare_variable("membership", None).unwrap();
+    let var_role = conjunction.constraints_mut().get_or_declare_variable("role", None).unwrap();
+
+    let links_membership_member = conjunction
+        .constraints_mut()
+        .add_links(var_membership, var_person, var_membership_member_type, None)
+        .unwrap()
+        .clone();
+    let links_membership_role =
+        conjunction.constraints_mut().add_links(var_membership, var_person, var_role, None).unwrap().clone();
+
+    conjunction.constraints_mut().add_isa(IsaKind::Subtype, var_membership, var_membership_type.into(), None).unwrap();
+    conjunction.constraints_mut().add_label(var_membership_type, MEMBERSHIP_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_label(var_membership_member_type, MEMBERSHIP_MEMBER_LABEL.clone()).unwrap();
+
+    let entry = builder.finish().unwrap();
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let variable_registry = &translation_context.variable_registry;
+    let previous_stage_variable_annotations = &BTreeMap::new();
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        variable_registry,
+        &type_manager,
+        previous_stage_variable_annotations,
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let (row_vars, variable_positions, mapping, named_variables) =
+        position_mapping([var_membership, var_person, var_role], [var_membership_type, var_membership_member_type]);
+
+    // Plan
+    let steps = vec![ExecutionStep::Intersection(
+        IntersectionStep::new(
+            mapping[&var_membership],
+            vec![
+                ConstraintInstruction::Links(
+                    LinksInstruction::new(links_membership_member, Inputs::None([]), &entry_annotations).map(&mapping),
+                ),
+                ConstraintInstruction::Links(
+                    LinksInstruction::new(links_membership_role, Inputs::None([]), &entry_annotations).map(&mapping),
+                ),
+            ],
+            vec![variable_positions[&var_membership], variable_positions[&var_person], variable_positions[&var_role]],
+            &named_variables,
+            3,
+        )
+        .with_secondary_sort_variable(mapping[&var_person]),
+    )];
+
+    let executable =
+        ConjunctionExecutable::new(next_executable_id(), steps, variable_positions, row_vars, PlannerStatistics::new());
+
+    // Executor
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let executor = ConjunctionExecutor::new(
+        &executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &QueryProfile::new(false),
+    )
+    .unwrap();
+
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+
+    let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
+        .map_static(|row| row.map(|row| row.as_reference().into_owned()).map_err(|err| Box::new(err.clone())))
+        .collect();
+    assert_eq!(rows.len(), 2);
+
+    for row in rows {
+        let r = row.unwrap();
+        assert_eq!(r.multiplicity(), 1);
+        print!("{}", r);
+        println!()
+    }
+}
+
 #[test]
 fn traverse_links_reverse_unbounded_sorted_from() {
     let (_tmp_dir, mut storage) = create_core_storage();
@@ -1146,3 +1254,168 @@ fn traverse_links_reverse_bounded_player_relation() {
         println!("{}", r);
     }
 }
+
+#[test]
+fn traverse_links_bound_relation_player_probes_either_side() {
+    let (_tmp_dir, mut storage) = create_core_storage();
+    setup_database(&mut storage);
+
+    // query:
+    //   match
+    //    $membership links (member: $person), isa membership;
+    //    $person isa person;
+    //
+    // with the links verified from whichever side the hint expects to be cheaper
+
+    // IR
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let var_person_type = conjunction.constraints_mut().get_or_declare_variable("person_type", None).unwrap();
+    let var_membership_type = conjunction.constraints_mut().get_or_declare_variable("membership_type", None).unwrap();
+    let var_membership_member_type =
+        conjunction.constraints_mut().get_or_declare_variable("membership_member_type", None).unwrap();
+
+    let var_person = conjunction.constraints_mut().get_or_declare_variable("person", None).unwrap();
+    let var_membership = conjunction.constraints_mut().get_or_declare_variable("membership", None).unwrap();
+
+    let links_membership_person = conjunction
+        .constraints_mut()
+        .add_links(var_membership, var_person, var_membership_member_type, None)
+        .unwrap()
+        .clone();
+
+    let isa_person = conjunction
+        .constraints_mut()
+        .add_isa(IsaKind::Subtype, var_person, var_person_type.into(), None)
+        .unwrap()
+        .clone();
+    let isa_membership = conjunction
+        .constraints_mut()
+        .add_isa(IsaKind::Subtype, var_membership, var_membership_type.into(), None)
+        .unwrap()
+        .clone();
+    conjunction.constraints_mut().add_label(var_person_type, PERSON_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_label(var_membership_type, MEMBERSHIP_LABEL.clone()).unwrap();
+    conjunction.constraints_mut().add_label(var_membership_member_type, MEMBERSHIP_MEMBER_LABEL.clone()).unwrap();
+
+    let entry = builder.finish().unwrap();
+
+    let snapshot = storage.clone().open_snapshot_read();
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let variable_registry = &translation_context.variable_registry;
+    let previous_stage_variable_annotations = &BTreeMap::new();
+    let block_annotations = infer_types(
+        &snapshot,
+        &entry,
+        variable_registry,
+        &type_manager,
+        previous_stage_variable_annotations,
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+    let entry_annotations = block_annotations.type_annotations_of(entry.conjunction()).unwrap();
+
+    let fan_outs = |var: Variable, fan_out: f64| {
+        let types = entry_annotations.vertex_annotations_of(&Vertex::Variable(var)).unwrap();
+        Arc::new(types.iter().map(|&type_| (type_, fan_out)).collect::<HashMap<_, _>>())
+    };
+    let hints = [
+        // few players per membership: verify from the relation
+        BoundLinksHint {
+            prefer_relation_side: false,
+            relation_fan_out: fan_outs(var_membership, 1.0),
+            player_fan_out: fan_outs(var_person, 100.0),
+        },
+        // few memberships per person: verify from the player
+        BoundLinksHint {
+            prefer_relation_side: true,
+            relation_fan_out: fan_outs(var_membership, 100.0),
+            player_fan_out: fan_outs(var_person, 1.0),
+        },
+        // no estimates: fall back to the planned side
+        BoundLinksHint {
+            prefer_relation_side: false,
+            relation_fan_out: Arc::new(HashMap::new()),
+            player_fan_out: Arc::new(HashMap::new()),
+        },
+    ];
+
+    for hint in hints {
+        let (row_vars, variable_positions, mapping, named_variables) = position_mapping(
+            [var_membership, var_person, var_membership_member_type],
+            [var_membership_type, var_person_type, var_membership_member_type],
+        );
+
+        // Plan
+        let steps = vec![
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_membership],
+                vec![ConstraintInstruction::Isa(
+                    IsaInstruction::new(isa_membership.clone(), Inputs::None([]), &entry_annotations).map(&mapping),
+                )],
+                vec![variable_positions[&var_membership]],
+                &named_variables,
+                1,
+            )),
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_person],
+                vec![ConstraintInstruction::Isa(
+                    IsaInstruction::new(isa_person.clone(), Inputs::None([]), &entry_annotations).map(&mapping),
+                )],
+                vec![variable_positions[&var_membership], variable_positions[&var_person]],
+                &named_variables,
+                2,
+            )),
+            ExecutionStep::Intersection(IntersectionStep::new(
+                mapping[&var_membership_member_type],
+                vec![ConstraintInstruction::BoundLinks(
+                    BoundLinksInstruction::new(
+                        links_membership_person.clone(),
+                        var_membership,
+                        var_person,
+                        hint,
+                        &entry_annotations,
+                    )
+                    .map(&mapping),
+                )],
+                vec![variable_positions[&var_membership_member_type]],
+                &named_variables,
+                3,
+            )),
+        ];
+
+        let executable = ConjunctionExecutable::new(
+            next_executable_id(),
+            steps,
+            variable_positions,
+            row_vars,
+            PlannerStatistics::new(),
+        );
+
+        // Executor
+        let snapshot = Arc::new(storage.clone().open_snapshot_read());
+        let executor = ConjunctionExecutor::new(
+            &executable,
+            &snapshot,
+            &thing_manager,
+            MaybeOwnedRow::empty(),
+            Arc::new(ExecutableFunctionRegistry::empty()),
+            &QueryProfile::new(false),
+        )
+        .unwrap();
+
+        let context = ExecutionContext::new(snapshot, thing_manager.clone(), Arc::default());
+        let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+
+        let rows: Vec<Result<MaybeOwnedRow<'static>, Box<ReadExecutionError>>> = iterator
+            .map_static(|row| row.map(|row| row.as_reference().into_owned()).map_err(|err| Box::new(err.clone())))
+            .collect();
+        assert_eq!(rows.len(), 2);
+        for row in rows {
+            row.unwrap();
+        }
+    }
+}
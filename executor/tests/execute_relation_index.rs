@@ -629,6 +629,7 @@ fn traverse_index_from_bound() {
                 lhs: CheckVertex::Variable(*mapping.get(&var_id).unwrap()),
                 rhs: CheckVertex::Parameter(id_0_parameter),
                 comparator: Comparator::Equal,
+                matcher: None,
             }],
             vec![variable_positions[&var_movie], variable_positions[&var_id]],
             2,
@@ -4,22 +4,51 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use concept::{thing::thing_manager::ThingManager, type_::type_manager::TypeManager};
+use answer::variable_value::VariableValue;
+use compiler::{
+    annotation::pipeline::{annotate_preamble_and_pipeline, AnnotatedPipeline},
+    executable::{
+        function::ExecutableFunctionRegistry,
+        pipeline::{compile_pipeline_and_functions, ExecutablePipeline, ExecutableStage},
+    },
+    VariablePosition,
+};
+use concept::{
+    thing::{
+        statistics::{Statistics, StatisticsFreshness},
+        thing_manager::ThingManager,
+    },
+    type_::type_manager::TypeManager,
+};
 use encoding::{
     graph::definition::definition_key_generator::DefinitionKeyGenerator,
     value::{label::Label, value::Value},
 };
 use executor::{
-    pipeline::stage::{ExecutionContext, StageIterator},
+    batch::Batch,
+    pipeline::{
+        pipeline::Pipeline,
+        stage::{ExecutionContext, MultiplicityPolicy, StageIterator},
+        PipelineExecutionError,
+    },
     ExecutionInterrupt,
 };
-use function::function_manager::FunctionManager;
+use function::function_manager::{FunctionManager, ReadThroughFunctionSignatureIndex};
+use ir::{
+    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    translation::pipeline::{translate_pipeline, TranslatedPipeline},
+};
 use lending_iterator::LendingIterator;
 use query::{query_cache::QueryCache, query_manager::QueryManager};
-use resource::profile::{CommitProfile, StorageCounters};
-use storage::{durability_client::WALClient, snapshot::CommittableSnapshot, MVCCStorage};
+use resource::profile::{CommitProfile, QueryProfile, StorageCounters};
+use storage::{
+    durability_client::WALClient, sequence_number::SequenceNumber, snapshot::CommittableSnapshot, MVCCStorage,
+};
 use test_utils::{assert_matches, TempDir};
 use test_utils_concept::{load_managers, setup_concept_storage};
 use test_utils_encoding::create_core_storage;
@@ -41,7 +70,7 @@ fn setup_common() -> Context {
     setup_concept_storage(&mut storage);
 
     let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
     let query_manager = QueryManager::new(None);
     let schema = r#"
     define
@@ -419,6 +448,81 @@ fn test_insert_match_insert() {
     snapshot.close_resources()
 }
 
+#[test]
+fn test_match_sees_uncommitted_inserts_in_intersections() {
+    let context = setup_common();
+    execute_write_query(
+        &context,
+        r#"insert
+            $p isa person, has age 10, has name 'John';
+            $org isa organisation;
+            (group: $org, member: $p) isa membership;"#,
+    );
+
+    let snapshot = context.storage.clone().open_snapshot_write();
+    // the joins below have to merge the committed data with the writes buffered by the first stage,
+    // whether they are driven from the owner, the attribute, the relation or the player side
+    let query_str = r#"
+    insert
+        $q isa person, has age 10, has name 'Alice';
+        $org isa organisation;
+        (group: $org, member: $q) isa membership;
+    match
+        $x isa person, has age $a, has name $n;
+        $a == 10;
+        $m isa membership, links (member: $x, group: $g);
+        $y isa person, has age $a;
+        $a > 5; $a <= 10;
+    "#;
+    let query = typeql::parse_query(query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &query,
+            query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    let mut row_count = 0;
+    while let Some(row) = iterator.next() {
+        row_count += row.unwrap().multiplicity();
+    }
+    drop(iterator);
+    // John and Alice are both members aged 10, and either can be paired with either as $y
+    assert_eq!(row_count, 4);
+
+    // a value range that is empty after narrowing yields nothing, just as it does in a read snapshot
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.close_resources();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let query_str = r#"
+    insert
+        $q isa person, has age 20;
+    match
+        $x isa person, has age $a;
+        $a > 20; $a <= 20;
+    "#;
+    let query = typeql::parse_query(query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &query,
+            query_str,
+        )
+        .unwrap();
+    let (mut iterator, _) = pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), None);
+}
+
 #[test]
 fn test_match_sort() {
     let context = setup_common();
@@ -594,3 +698,600 @@ fn test_require() {
         assert!(named_outputs.contains_key("p"));
     }
 }
+
+fn execute_read_query(context: &Context, query: &str) -> (Batch, HashMap<String, VariablePosition>) {
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let pipeline = typeql::parse_query(query).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_read_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &pipeline,
+            query,
+        )
+        .unwrap();
+    let named_outputs = pipeline.rows_positions().unwrap().clone();
+    let (iterator, _) = pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    (iterator.collect_owned().unwrap(), named_outputs)
+}
+
+#[test]
+fn test_count_only_match_reduce() {
+    let context = setup_common();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let insert_query_str = r#"insert
+        $p1 isa person, has name "Alice", has age 1, has age 2, has age 3;
+        $p2 isa person, has name "Bob", has name "Robert", has age 2;
+        $p3 isa person, has age 4;
+        $o1 isa organisation;
+        $o2 isa organisation;
+        (member: $p1, group: $o1) isa membership;
+        (member: $p2, group: $o1) isa membership;
+        (member: $p1, group: $o2) isa membership;
+        (member: $p3, group: $o2) isa membership;"#;
+    let insert_query = typeql::parse_query(insert_query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &insert_query,
+            insert_query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), Some(Ok(_)));
+    assert_matches!(iterator.next(), None);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let patterns = [
+        ("match $p isa person, has age $a;", 5),
+        ("match $p has age $a; $p has name $n;", 5),
+        ("match $m links (member: $p, group: $o);", 4),
+        ("match $m isa membership, links (member: $p); $p has age $a;", 8),
+    ];
+    for (pattern, expected) in patterns {
+        let (rows, _) = execute_read_query(&context, pattern);
+        let naive_count: u64 = rows.iter().map(|row| row.multiplicity()).sum();
+        assert_eq!(naive_count, expected, "{pattern}");
+
+        let (rows, named_outputs) = execute_read_query(&context, &format!("{pattern} reduce $count = count;"));
+        assert_eq!(rows.len(), 1);
+        let count = rows.iter().next().unwrap().get(named_outputs["count"]).as_value().clone().unwrap_integer();
+        assert_eq!(count as u64, naive_count, "{pattern}");
+    }
+}
+
+fn execute_write_query(context: &Context, query: &str) {
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let pipeline = typeql::parse_query(query).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &pipeline,
+            query,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+    }
+    drop(iterator);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+}
+
+#[test]
+fn test_grouped_count_only_match_reduce() {
+    let membership_fixture = r#"insert
+        $p1 isa person, has name "Alice", has age 1, has age 2, has age 3;
+        $p2 isa person, has name "Bob", has name "Robert", has age 2;
+        $p3 isa person, has age 4;
+        $o1 isa organisation;
+        $o2 isa organisation;
+        (member: $p1, group: $o1) isa membership;
+        (member: $p2, group: $o1) isa membership;
+        (member: $p1, group: $o2) isa membership;
+        (member: $p3, group: $o2) isa membership;"#;
+    let mut generated = String::from("insert\n");
+    for organisation in 0..10 {
+        generated.push_str(&format!("    $o{organisation} isa organisation;\n"));
+    }
+    for person in 0..300 {
+        generated.push_str(&format!("    $p{person} isa person, has age {};\n", person % 7));
+        for organisation in (0..10).filter(|organisation| (person + organisation) % 3 == 0) {
+            generated.push_str(&format!("    (member: $p{person}, group: $o{organisation}) isa membership;\n"));
+        }
+    }
+
+    let patterns = [
+        ("match $m links (member: $p);", "p"),
+        ("match $p isa person; (member: $p) isa membership;", "p"),
+        ("match $m isa membership, links (member: $p, group: $o);", "o"),
+        ("match $p has age $a; $m links (member: $p);", "a"),
+    ];
+    for data in [membership_fixture, generated.as_str()] {
+        let context = setup_common();
+        execute_write_query(&context, data);
+        for (pattern, group) in patterns {
+            let (rows, named_outputs) = execute_read_query(&context, pattern);
+            let mut naive_counts: HashMap<VariableValue<'static>, u64> = HashMap::new();
+            for row in rows.iter() {
+                *naive_counts.entry(row.get(named_outputs[group]).to_owned()).or_default() += row.multiplicity();
+            }
+            assert!(!naive_counts.is_empty(), "{pattern}");
+
+            let query = format!("{pattern} reduce $count = count groupby ${group};");
+            let stages = compile_read_query(&context, &query);
+            let Some(ExecutableStage::Match { executable, .. }) = stages.first() else {
+                panic!("expected a match: {query}")
+            };
+            assert!(executable.is_grouped_count_only(), "{query}");
+            assert_eq!(executable.selected_variables().len(), 1, "{query}");
+
+            let (rows, named_outputs) = execute_read_query(&context, &query);
+            let counts: HashMap<VariableValue<'static>, u64> = (rows.iter())
+                .map(|row| {
+                    let count = row.get(named_outputs["count"]).as_value().clone().unwrap_integer();
+                    (row.get(named_outputs[group]).to_owned(), count as u64)
+                })
+                .collect();
+            assert_eq!(counts, naive_counts, "{query}");
+        }
+    }
+}
+
+#[test]
+fn test_select_distinct_sort_variable() {
+    let context = setup_common();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let insert_query_str = r#"insert
+        $p1 isa person, has age 1, has age 2, has age 3;
+        $p2 isa person, has age 2, has age 4;
+        $p3 isa person, has age 4;"#;
+    let insert_query = typeql::parse_query(insert_query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &insert_query,
+            insert_query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), Some(Ok(_)));
+    assert_matches!(iterator.next(), None);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let (rows, _) = execute_read_query(&context, "match $person has age $age; select $person;");
+    assert_eq!(rows.iter().map(|row| row.multiplicity()).sum::<u64>(), 6);
+
+    let (rows, named_outputs) = execute_read_query(&context, "match $person has age $age; select $person; distinct;");
+    assert_eq!(rows.len(), 3);
+    assert!(rows.iter().all(|row| row.multiplicity() == 1));
+    assert!(named_outputs.contains_key("person"));
+}
+
+#[test]
+fn test_repeated_match_stage_is_compiled_once() {
+    let context = setup_common();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let insert_query_str = r#"insert
+        $p1 isa person, has name "Alice", has age 1, has age 2;
+        $p2 isa person, has name "Bob", has age 3;
+        $p3 isa person, has name "Carol";"#;
+    let insert_query = typeql::parse_query(insert_query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &insert_query,
+            insert_query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), Some(Ok(_)));
+    assert_matches!(iterator.next(), None);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    // the second and fourth stages have the same pattern and the same inputs, at the same positions
+    let query = "match $p isa person, has age $a; match $p has age $a; select $p, $a; match $p has age $a;";
+    let stages = compile_read_query(&context, query);
+    let matches = (stages.iter())
+        .filter_map(|stage| match stage {
+            ExecutableStage::Match { stage_id, executable } => Some((*stage_id, executable.clone())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let [(first_id, first), (second_id, second), (fourth_id, fourth)] = &matches[..] else { panic!("{stages:?}") };
+    assert!(!Arc::ptr_eq(first, second));
+    assert!(Arc::ptr_eq(second, fourth));
+    // the repeated stage shares the executable, but not the stage id its steps are profiled under
+    assert_eq!(*second_id, second.executable_id());
+    assert_eq!(HashSet::from([first_id, second_id, fourth_id]).len(), 3);
+
+    let (rows, named_outputs) = execute_read_query(&context, query);
+    // Carol has no age, and the repeated stages only re-read the age each input row is already bound to
+    assert_eq!(rows.iter().map(|row| row.multiplicity()).sum::<u64>(), 3);
+    assert!(named_outputs.contains_key("p") && named_outputs.contains_key("a"));
+
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let profile = Arc::new(QueryProfile::new(true));
+    let pipeline = Pipeline::build_read_pipeline(
+        snapshot,
+        context.thing_manager.clone(),
+        &HashMap::new(),
+        None,
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &stages,
+        None,
+        Arc::new(ParameterRegistry::default()),
+        None,
+        profile.clone(),
+        MultiplicityPolicy::default(),
+    )
+    .unwrap();
+    let (iterator, _) = pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    iterator.collect_owned().unwrap();
+    let stage_profiles = profile.stage_profiles().read().unwrap();
+    let rows_of = |stage_id: &u64| stage_profiles[stage_id].step_profile(0).unwrap().rows();
+    // each of the identical stages records the rows it matched itself: one per age of the people with an age
+    assert_eq!(rows_of(second_id), 3);
+    assert_eq!(rows_of(fourth_id), 3);
+}
+
+#[test]
+fn test_match_list_assignment() {
+    let context = setup_common();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let insert_query_str = r#"insert $p1 isa person, has age 1; $p2 isa person, has age 2;"#;
+    let insert_query = typeql::parse_query(insert_query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &insert_query,
+            insert_query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), Some(Ok(_)));
+    assert_matches!(iterator.next(), None);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let query = "match $p has age $a; let $ages = [$a, $a, 0]; let $first = $ages[0];";
+    let (rows, named_outputs) = execute_read_query(&context, query);
+    assert_eq!(rows.len(), 2);
+    for row in rows.iter() {
+        assert_matches!(row.get(named_outputs["ages"]), VariableValue::ValueList(list) if list.len() == 3);
+        assert_matches!(row.get(named_outputs["first"]).as_value().clone().unwrap_integer(), 1 | 2);
+    }
+}
+
+#[test]
+fn test_variable_of_every_satisfiable_branch_is_produced_by_the_disjunction() {
+    let context = setup_common();
+    execute_write_query(&context, r#"insert $_ isa person, has name "Alice", has age 1; $_ isa person, has age 2;"#);
+
+    // organisations own no names, so the last branch is pruned, and every remaining branch produces `$x`
+    let query = "match
+        $p isa person;
+        { $p has name $x; } or { $p has age $x; } or { $o isa organisation, has name $n; };
+    ";
+    let (rows, named_outputs) = execute_read_query(&context, query);
+    assert_eq!(rows.len(), 3);
+    for row in rows.iter() {
+        assert_matches!(row.get(named_outputs["x"]), VariableValue::Thing(_));
+    }
+}
+
+fn compile_read_query(context: &Context, query: &str) -> Vec<ExecutableStage> {
+    compile_read_pipeline(context, query, context.thing_manager.statistics()).executable_stages
+}
+
+fn compile_read_pipeline(context: &Context, query: &str, statistics: &Statistics) -> ExecutablePipeline {
+    let snapshot = context.storage.clone().open_snapshot_read();
+    let pipeline = typeql::parse_query(query).unwrap().into_structure().into_pipeline();
+    let function_index = ReadThroughFunctionSignatureIndex::new(
+        &snapshot,
+        &context.function_manager,
+        HashMapFunctionSignatureIndex::empty(),
+    );
+    let TranslatedPipeline {
+        translated_preamble,
+        translated_stages,
+        translated_fetch,
+        mut variable_registry,
+        value_parameters,
+    } = translate_pipeline(&snapshot, &function_index, &pipeline).unwrap();
+    let schema_functions = context.function_manager.get_annotated_functions(&snapshot, &context.type_manager).unwrap();
+    let AnnotatedPipeline { annotated_preamble, annotated_stages, annotated_fetch } = annotate_preamble_and_pipeline(
+        &snapshot,
+        &context.type_manager,
+        schema_functions.clone(),
+        &mut variable_registry,
+        &value_parameters,
+        translated_preamble,
+        translated_stages,
+        translated_fetch,
+    )
+    .unwrap();
+    compile_pipeline_and_functions(
+        statistics,
+        StatisticsFreshness::Current,
+        &variable_registry,
+        &value_parameters,
+        &schema_functions,
+        context.function_manager.function_versions(),
+        None,
+        annotated_preamble,
+        annotated_stages,
+        annotated_fetch,
+        &HashSet::new(),
+        None,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_sort_after_monotone_assignment_is_elided() {
+    let context = setup_common();
+    let snapshot = context.storage.clone().open_snapshot_write();
+    let insert_query_str = "insert $p isa person, has age 3, has age 1, has age 4, has age 2;";
+    let insert_query = typeql::parse_query(insert_query_str).unwrap().into_structure().into_pipeline();
+    let pipeline = context
+        .query_manager
+        .prepare_write_pipeline(
+            snapshot,
+            &context.type_manager,
+            context.thing_manager.clone(),
+            &context.function_manager,
+            &insert_query,
+            insert_query_str,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    assert_matches!(iterator.next(), Some(Ok(_)));
+    assert_matches!(iterator.next(), None);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+
+    let queries = [
+        ("match $a isa age; sort $a; match let $v = $a + 1; sort $v;", true, [2, 3, 4, 5]),
+        ("match $a isa age; sort $a; match let $v = $a; select $v; sort $v;", true, [1, 2, 3, 4]),
+        ("match $a isa age; sort $a desc; match let $v = 10 - $a; sort $v;", true, [6, 7, 8, 9]),
+        ("match $a isa age; sort $a; limit 10; match let $v = $a - 1; sort $v desc;", false, [3, 2, 1, 0]),
+        ("match $a isa age; sort $a; match let $v = $a * 2; sort $v;", false, [2, 4, 6, 8]),
+        ("match $a isa age; let $v = $a + 1; sort $v;", false, [2, 3, 4, 5]),
+    ];
+    for (query, is_presorted, expected) in queries {
+        let stages = compile_read_query(&context, query);
+        let Some(ExecutableStage::Sort(sort)) = stages.last() else { panic!("expected a final sort stage: {query}") };
+        assert_eq!(sort.is_presorted, is_presorted, "{query}");
+
+        let (rows, named_outputs) = execute_read_query(&context, query);
+        let values = rows.iter().map(|row| row.get(named_outputs["v"]).as_value().clone().unwrap_integer());
+        assert_eq!(values.collect::<Vec<_>>(), expected, "{query}");
+    }
+}
+
+fn execute_profiled_read_query(context: &Context, query: &str) -> (usize, u64) {
+    execute_profiled_read_query_with_policy(context, query, MultiplicityPolicy::default())
+}
+
+fn execute_profiled_read_query_with_policy(
+    context: &Context,
+    query: &str,
+    multiplicity_policy: MultiplicityPolicy,
+) -> (usize, u64) {
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let stages = compile_read_query(context, query);
+    let profile = Arc::new(QueryProfile::new(true));
+    let pipeline = Pipeline::build_read_pipeline(
+        snapshot,
+        context.thing_manager.clone(),
+        &HashMap::new(),
+        None,
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &stages,
+        None,
+        Arc::new(ParameterRegistry::default()),
+        None,
+        profile.clone(),
+        multiplicity_policy,
+    )
+    .unwrap();
+    let (iterator, _) = pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    let rows = iterator.collect_owned().unwrap().len();
+    let advances = profile
+        .stage_profiles()
+        .read()
+        .unwrap()
+        .values()
+        .flat_map(|stage| (0..).map_while(|index| stage.step_profile(index)))
+        .map(|step| step.storage_counters().get_raw_advance().unwrap_or(0))
+        .sum();
+    (rows, advances)
+}
+
+#[test]
+fn test_limit_bounds_the_rows_read_by_the_match() {
+    const PERSON_COUNT: usize = 500;
+    let context = setup_common();
+    let mut insert = String::from("insert\n");
+    for index in 0..PERSON_COUNT {
+        insert.push_str(&format!("$p{index} isa person, has age {index};\n"));
+    }
+    execute_write_query(&context, &insert);
+
+    let (rows, unlimited_advances) = execute_profiled_read_query(&context, "match $p isa person, has age $a;");
+    assert_eq!(rows, PERSON_COUNT);
+    assert!(unlimited_advances >= PERSON_COUNT as u64, "{unlimited_advances} advances");
+
+    let (rows, limited_advances) = execute_profiled_read_query(&context, "match $p isa person, has age $a; limit 1;");
+    assert_eq!(rows, 1);
+    assert!(limited_advances < 16, "{limited_advances} advances, unlimited: {unlimited_advances}");
+
+    // the steps after the intersection fill batches bounded by the limit too
+    let (rows, assigned_advances) =
+        execute_profiled_read_query(&context, "match $p isa person, has age $a; let $b = $a + 1; limit 1;");
+    assert_eq!(rows, 1);
+    assert!(assigned_advances < 16, "{assigned_advances} advances, unlimited: {unlimited_advances}");
+
+    // the match resumes after its first rows are skipped by the offset
+    let (rows, offset_advances) =
+        execute_profiled_read_query(&context, "match $p isa person, has age $a; offset 20; limit 3;");
+    assert_eq!(rows, 3);
+    assert!(offset_advances < unlimited_advances / 4, "{offset_advances} advances, unlimited: {unlimited_advances}");
+
+    // a sort consumes every row of the match, whatever the limit after it
+    let (rows, sorted_advances) =
+        execute_profiled_read_query(&context, "match $p isa person, has age $a; sort $a; limit 1;");
+    assert_eq!(rows, 1);
+    assert!(sorted_advances >= PERSON_COUNT as u64, "{sorted_advances} advances");
+}
+
+#[test]
+fn test_limit_bounds_the_rows_read_by_the_match_by_their_multiplicity() {
+    const NAME_COUNT: usize = 20;
+    const PERSON_COUNT: usize = 100;
+    let context = setup_common();
+    let names: String = (0..NAME_COUNT).map(|index| format!(", has name \"{index}\"")).collect();
+    let mut insert = format!("insert\n$first isa person, has age 1{names};\n");
+    for index in 0..PERSON_COUNT {
+        insert.push_str(&format!("$p{index} isa person, has age 2{names};\n"));
+    }
+    execute_write_query(&context, &insert);
+
+    // the names are not selected, so the second match reads each person as a row standing for one answer per name
+    let query = "match $a isa age; sort $a; match $p isa person, has age $a, has name $n; select $p;";
+    let policy = MultiplicityPolicy::ExpandUpTo(NAME_COUNT as u64);
+    let (_, unlimited_advances) = execute_profiled_read_query_with_policy(&context, query, policy);
+
+    // the answers of the person aged 1 leave one row to the limit, which the first person aged 2 provides
+    let limited = format!("{query} limit {};", NAME_COUNT + 1);
+    let (rows, limited_advances) = execute_profiled_read_query_with_policy(&context, &limited, policy);
+    assert_eq!(rows, NAME_COUNT + 1);
+    assert!(limited_advances < unlimited_advances / 10, "{limited_advances} advances, unlimited: {unlimited_advances}");
+}
+
+#[test]
+fn test_pipeline_records_the_statistics_version_it_was_planned_against() {
+    const QUERY: &str = "match $p isa person, has age $a; match $p has name $n; select $n;";
+    let context = setup_common();
+    execute_write_query(&context, "insert $p isa person, has age 1, has name 'Alice';");
+    let mut statistics = Statistics::new(SequenceNumber::MIN);
+    statistics.may_synchronise(context.storage.as_ref()).unwrap();
+    assert!(statistics.sequence_number > SequenceNumber::MIN);
+
+    let pipeline = compile_read_pipeline(&context, QUERY, &statistics);
+    assert_eq!(
+        pipeline.executable_stages.iter().filter(|stage| matches!(stage, ExecutableStage::Match { .. })).count(),
+        2
+    );
+    assert_eq!(pipeline.statistics_sequence_number, statistics.sequence_number);
+
+    // statistics refreshed after compilation leave the version recorded by the pipeline unchanged
+    let planned_sequence_number = statistics.sequence_number;
+    execute_write_query(&context, "insert $p isa person, has age 2;");
+    statistics.may_synchronise(context.storage.as_ref()).unwrap();
+    assert!(statistics.sequence_number > planned_sequence_number);
+    assert_eq!(pipeline.statistics_sequence_number, planned_sequence_number);
+    assert_eq!(
+        compile_read_pipeline(&context, QUERY, &statistics).statistics_sequence_number,
+        statistics.sequence_number
+    );
+}
+
+fn execute_read_query_with_policy(
+    context: &Context,
+    query: &str,
+    multiplicity_policy: MultiplicityPolicy,
+) -> Result<Vec<u64>, Box<PipelineExecutionError>> {
+    let snapshot = Arc::new(context.storage.clone().open_snapshot_read());
+    let stages = compile_read_query(context, query);
+    let pipeline = Pipeline::build_read_pipeline(
+        snapshot,
+        context.thing_manager.clone(),
+        &HashMap::new(),
+        None,
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        &stages,
+        None,
+        Arc::new(ParameterRegistry::default()),
+        None,
+        Arc::new(QueryProfile::new(false)),
+        multiplicity_policy,
+    )
+    .unwrap();
+    let (iterator, _) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).map_err(|(err, _)| err)?;
+    Ok(iterator.collect_owned()?.iter().map(|row| row.multiplicity()).collect())
+}
+
+#[test]
+fn test_limit_counts_the_answers_of_rows_with_multiplicities() {
+    let context = setup_common();
+    execute_write_query(
+        &context,
+        "insert
+            $p isa person, has age 1;
+            $o isa organisation;
+            (member: $p, group: $o) isa membership;
+            (member: $p, group: $o) isa membership;
+            (member: $p, group: $o) isa membership;",
+    );
+
+    // the anonymous memberships are counted towards the multiplicity of the single row of the match
+    let counted = "match (member: $p, group: $o) isa membership;";
+    assert_eq!(execute_read_query_with_policy(&context, counted, MultiplicityPolicy::Preserve).unwrap(), [3]);
+
+    let is_expand = |stage: &ExecutableStage| matches!(stage, ExecutableStage::Expand(_));
+    let limited = "match (member: $p, group: $o) isa membership; limit 2;";
+    let stages = compile_read_query(&context, limited);
+    assert_eq!(stages.iter().position(is_expand), Some(1));
+    assert_matches!(stages[2], ExecutableStage::Limit(_));
+    assert!(!compile_read_query(&context, "match $p isa person; limit 2;").iter().any(is_expand));
+    assert!(!compile_read_query(&context, "match (member: $p, group: $o) isa membership; reduce $c = count; limit 2;")
+        .iter()
+        .any(is_expand));
+
+    assert_eq!(execute_read_query_with_policy(&context, limited, MultiplicityPolicy::Preserve).unwrap(), [3]);
+    assert_eq!(execute_read_query_with_policy(&context, limited, MultiplicityPolicy::ExpandUpTo(10)).unwrap(), [1, 1]);
+    let skipped = "match (member: $p, group: $o) isa membership; offset 2; limit 5;";
+    assert_eq!(execute_read_query_with_policy(&context, skipped, MultiplicityPolicy::ExpandUpTo(10)).unwrap(), [1]);
+
+    let error = execute_read_query_with_policy(&context, limited, MultiplicityPolicy::ExpandUpTo(2)).unwrap_err();
+    assert_matches!(
+        *error,
+        PipelineExecutionError::MultiplicityExpansionLimitExceeded { multiplicity: 3, limit: 2, .. }
+    );
+}
@@ -0,0 +1,510 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    sync::Arc,
+};
+
+use compiler::{
+    annotation::{function::EmptyAnnotatedFunctionSignatures, match_inference::infer_types},
+    executable::{
+        function::ExecutableFunctionRegistry,
+        match_::planner::{conjunction_executable::ConjunctionExecutable, plan::PlannerConfig, plan_cache::PlanCache},
+    },
+};
+use concept::{thing::statistics::Statistics, type_::type_manager::TypeManager};
+use encoding::graph::definition::definition_key_generator::DefinitionKeyGenerator;
+use executor::{
+    conjunction_executor::ConjunctionExecutor, pipeline::stage::ExecutionContext, row::MaybeOwnedRow,
+    ExecutionInterrupt,
+};
+use function::function_manager::FunctionManager;
+use ir::{
+    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    translation::{match_::translate_match, PipelineTranslationContext},
+};
+use lending_iterator::LendingIterator;
+use query::query_manager::QueryManager;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use resource::profile::{CommitProfile, QueryProfile};
+use storage::{
+    durability_client::WALClient,
+    sequence_number::SequenceNumber,
+    snapshot::{CommittableSnapshot, ReadableSnapshot},
+    MVCCStorage,
+};
+use test_utils::TempDir;
+use test_utils_concept::{load_managers, setup_concept_storage};
+use test_utils_encoding::create_core_storage;
+
+/// Every generated entity owns a unique integer of this attribute type, so relations written in later batches can
+/// match their players.
+pub const INSTANCE_ID_ATTRIBUTE: &str = "dataset-id";
+
+const DEFAULT_BATCH_SIZE: usize = 64;
+
+/// Creates a storage in a temporary directory, defines `schema` and writes `data` with a single write query. Returns
+/// statistics synchronised with the written data. The storage must be dropped before the directory.
+pub fn create_database(schema: &str, data: &str) -> (TempDir, Arc<MVCCStorage<WALClient>>, Statistics) {
+    let (tmp_dir, storage) = create_empty_database();
+    let query_manager = QueryManager::new(None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
+    define(&storage, &query_manager, &function_manager, schema);
+    write(&storage, &query_manager, &function_manager, data);
+    let statistics = synchronised_statistics(&storage);
+    (tmp_dir, storage, statistics)
+}
+
+/// Creates a storage in a temporary directory and generates `size` units of `dataset` into it, as
+/// [`DatasetDescription::generate`].
+pub fn create_generated_database(
+    dataset: &DatasetDescription,
+    size: u64,
+    seed: u64,
+) -> (TempDir, Arc<MVCCStorage<WALClient>>, Statistics) {
+    let (tmp_dir, storage) = create_empty_database();
+    let statistics = dataset.generate(&storage, size, seed);
+    (tmp_dir, storage, statistics)
+}
+
+fn create_empty_database() -> (TempDir, Arc<MVCCStorage<WALClient>>) {
+    let (tmp_dir, mut storage) = create_core_storage();
+    setup_concept_storage(&mut storage);
+    (tmp_dir, storage)
+}
+
+/// Compiles the match stage that starts `query` against a read snapshot of `storage`. The query may not call
+/// functions.
+pub fn compile_conjunction(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    statistics: &Statistics,
+    query: &str,
+    planner_config: &PlannerConfig,
+) -> ConjunctionExecutable {
+    let (type_manager, _) = load_managers(storage.clone(), None);
+    let snapshot = storage.clone().open_snapshot_read();
+    compile_conjunction_in(&snapshot, &type_manager, statistics, query, planner_config, None)
+}
+
+/// Translates, annotates and plans the match stage that starts `query`, reusing the plans in `plan_cache` if given.
+pub fn compile_conjunction_in(
+    snapshot: &impl ReadableSnapshot,
+    type_manager: &TypeManager,
+    statistics: &Statistics,
+    query: &str,
+    planner_config: &PlannerConfig,
+    plan_cache: Option<&PlanCache>,
+) -> ConjunctionExecutable {
+    let match_ = typeql::parse_query(query).unwrap().into_structure().into_pipeline().stages.remove(0).into_match();
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let builder = translate_match(
+        &mut translation_context,
+        &mut value_parameters,
+        &HashMapFunctionSignatureIndex::empty(),
+        &match_,
+    )
+    .unwrap();
+    let block = builder.finish().unwrap();
+
+    let entry_annotations = infer_types(
+        snapshot,
+        &block,
+        &translation_context.variable_registry,
+        type_manager,
+        &BTreeMap::new(),
+        &EmptyAnnotatedFunctionSignatures,
+        false,
+    )
+    .unwrap();
+
+    compiler::executable::match_::planner::compile(
+        &block,
+        &BTreeMap::new(),
+        &HashMap::new(),
+        &block.conjunction().named_producible_variables(block.block_context()).collect(),
+        &entry_annotations,
+        &translation_context.variable_registry,
+        &HashMap::new(),
+        &value_parameters,
+        statistics,
+        &ExecutableFunctionRegistry::empty(),
+        planner_config,
+        plan_cache,
+    )
+    .unwrap()
+}
+
+/// Executes `executable` from an empty input row against a read snapshot of `storage`, recording into `profile`.
+/// Rows are produced lazily, and any execution error panics.
+pub fn execute_conjunction(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    executable: &ConjunctionExecutable,
+    profile: &QueryProfile,
+) -> impl Iterator<Item = MaybeOwnedRow<'static>> {
+    let (_, thing_manager) = load_managers(storage.clone(), None);
+    let snapshot = Arc::new(storage.clone().open_snapshot_read());
+    let executor = ConjunctionExecutor::new(
+        executable,
+        &snapshot,
+        &thing_manager,
+        MaybeOwnedRow::empty(),
+        Arc::new(ExecutableFunctionRegistry::empty()),
+        profile,
+    )
+    .unwrap();
+    let context = ExecutionContext::new(snapshot, thing_manager, Arc::default());
+    let iterator = executor.into_iterator(context, ExecutionInterrupt::new_uninterruptible());
+    iterator
+        .map_static(|row| row.map(|row| row.into_owned()).map_err(|err| err.clone()))
+        .into_iter()
+        .map(Result::unwrap)
+}
+
+/// A distribution of non-negative integers.
+#[derive(Clone, Debug)]
+pub enum Distribution {
+    Constant(u64),
+    /// Uniform over `min..=max`.
+    Uniform {
+        min: u64,
+        max: u64,
+    },
+    /// Zipfian over `0..=max`: `k` is drawn with weight `1 / (k + 1)^exponent`, so the smallest values dominate.
+    Zipfian {
+        max: u64,
+        exponent: f64,
+    },
+}
+
+impl Distribution {
+    fn sampler(&self) -> Sampler {
+        let cumulative_weights = match *self {
+            Self::Zipfian { max, exponent } => (0..=max)
+                .scan(0.0, |total, k| {
+                    *total += 1.0 / ((k + 1) as f64).powf(exponent);
+                    Some(*total)
+                })
+                .collect(),
+            Self::Constant(_) | Self::Uniform { .. } => Vec::new(),
+        };
+        Sampler { distribution: self.clone(), cumulative_weights }
+    }
+}
+
+struct Sampler {
+    distribution: Distribution,
+    cumulative_weights: Vec<f64>,
+}
+
+impl Sampler {
+    fn sample(&self, rng: &mut StdRng) -> u64 {
+        match self.distribution {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } => rng.gen_range(min..=max),
+            Distribution::Zipfian { .. } => {
+                let target = rng.gen::<f64>() * self.cumulative_weights.last().unwrap();
+                self.cumulative_weights.partition_point(|&weight| weight < target) as u64
+            }
+        }
+    }
+}
+
+/// How role players are picked among the instances of their type.
+#[derive(Clone, Copy, Debug)]
+pub enum Selection {
+    Uniform,
+    /// Picks the `k`-th instance with weight `1 / (k + 1)^exponent`, concentrating relations on a few hub players.
+    Zipfian {
+        exponent: f64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    Integer,
+    /// Strings are formed from the attribute label and the sampled integer, e.g. `'name 7'`.
+    String,
+}
+
+#[derive(Clone, Debug)]
+struct AttributeDescription {
+    label: String,
+    kind: ValueKind,
+    values: Distribution,
+}
+
+/// `count` instances of an entity type per unit of dataset size, each owning a sampled number of every attribute.
+#[derive(Clone, Debug)]
+pub struct EntityPopulation {
+    label: String,
+    count: u64,
+    ownerships: Vec<(String, Distribution)>,
+}
+
+impl EntityPopulation {
+    pub fn new(label: &str, count: u64) -> Self {
+        Self { label: label.to_owned(), count, ownerships: Vec::new() }
+    }
+
+    pub fn owns(mut self, attribute: &str, per_owner: Distribution) -> Self {
+        self.ownerships.push((attribute.to_owned(), per_owner));
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RoleDescription {
+    name: String,
+    player: String,
+    fan_out: Distribution,
+    selection: Selection,
+}
+
+/// `count` instances of a relation type per unit of dataset size. Roles whose sampled fan-out is zero are left
+/// unplayed, and relations left with no players at all are not written.
+#[derive(Clone, Debug)]
+pub struct RelationPopulation {
+    label: String,
+    count: u64,
+    roles: Vec<RoleDescription>,
+}
+
+impl RelationPopulation {
+    pub fn new(label: &str, count: u64) -> Self {
+        Self { label: label.to_owned(), count, roles: Vec::new() }
+    }
+
+    pub fn role(mut self, name: &str, player: &str, fan_out: Distribution, selection: Selection) -> Self {
+        self.roles.push(RoleDescription { name: name.to_owned(), player: player.to_owned(), fan_out, selection });
+        self
+    }
+}
+
+/// Describes a synthetic dataset, so tests can parameterise the skew of their data instead of spelling it out in
+/// insert queries. The schema is derived from the description, and the data is written through the write pipeline.
+#[derive(Clone, Debug)]
+pub struct DatasetDescription {
+    attributes: Vec<AttributeDescription>,
+    entities: Vec<EntityPopulation>,
+    relations: Vec<RelationPopulation>,
+    batch_size: usize,
+}
+
+impl DatasetDescription {
+    pub fn new() -> Self {
+        Self { attributes: Vec::new(), entities: Vec::new(), relations: Vec::new(), batch_size: DEFAULT_BATCH_SIZE }
+    }
+
+    pub fn attribute(mut self, label: &str, kind: ValueKind, values: Distribution) -> Self {
+        self.attributes.push(AttributeDescription { label: label.to_owned(), kind, values });
+        self
+    }
+
+    /// Several populations of the same entity type may own different attributes.
+    pub fn entities(mut self, population: EntityPopulation) -> Self {
+        self.entities.push(population);
+        self
+    }
+
+    pub fn relations(mut self, population: RelationPopulation) -> Self {
+        self.relations.push(population);
+        self
+    }
+
+    /// The number of instances written by each insert query.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn schema(&self) -> String {
+        let mut entity_types = BTreeMap::<&str, BTreeSet<String>>::new();
+        for population in &self.entities {
+            let capabilities = entity_types.entry(&population.label).or_default();
+            capabilities.insert(format!("owns {INSTANCE_ID_ATTRIBUTE} @key"));
+            for (attribute, _) in &population.ownerships {
+                capabilities.insert(format!("owns {attribute} @card(0..)"));
+            }
+        }
+        let mut relation_types = BTreeMap::<&str, BTreeSet<&str>>::new();
+        for population in &self.relations {
+            let roles = relation_types.entry(&population.label).or_default();
+            for role in &population.roles {
+                roles.insert(&role.name);
+                let player = entity_types.get_mut(role.player.as_str()).expect("role players must be generated");
+                player.insert(format!("plays {}:{}", population.label, role.name));
+            }
+        }
+
+        let mut schema = format!("define\n    attribute {INSTANCE_ID_ATTRIBUTE} value integer;\n");
+        for attribute in &self.attributes {
+            let value_type = match attribute.kind {
+                ValueKind::Integer => "integer",
+                ValueKind::String => "string",
+            };
+            writeln!(schema, "    attribute {} value {value_type};", attribute.label).unwrap();
+        }
+        for (label, roles) in relation_types {
+            let relates = roles.into_iter().map(|role| format!("relates {role} @card(0..)")).collect::<Vec<_>>();
+            writeln!(schema, "    relation {label} {};", relates.join(", ")).unwrap();
+        }
+        for (label, capabilities) in entity_types {
+            writeln!(schema, "    entity {label} {};", capabilities.into_iter().collect::<Vec<_>>().join(", "))
+                .unwrap();
+        }
+        schema
+    }
+
+    /// Defines the schema and writes `size` units of every population in batches, returning statistics synchronised
+    /// with the written data. The same seed always generates the same data.
+    pub fn generate(&self, storage: &Arc<MVCCStorage<WALClient>>, size: u64, seed: u64) -> Statistics {
+        let query_manager = QueryManager::new(None);
+        let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
+        define(storage, &query_manager, &function_manager, &self.schema());
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut instances = BTreeMap::<&str, Vec<u64>>::new();
+        let mut next_id = 0;
+        let mut statements = Vec::new();
+        for population in &self.entities {
+            let ownerships = (population.ownerships.iter())
+                .map(|(label, per_owner)| {
+                    let attribute = self.attribute_description(label);
+                    (attribute, attribute.values.sampler(), per_owner.sampler())
+                })
+                .collect::<Vec<_>>();
+            for _ in 0..population.count * size {
+                let mut statement = format!("$_ isa {}, has {INSTANCE_ID_ATTRIBUTE} {next_id}", population.label);
+                for (attribute, values, per_owner) in &ownerships {
+                    for _ in 0..per_owner.sample(&mut rng) {
+                        let value = values.sample(&mut rng);
+                        match attribute.kind {
+                            ValueKind::Integer => write!(statement, ", has {} {value}", attribute.label).unwrap(),
+                            ValueKind::String => {
+                                write!(statement, ", has {} '{} {value}'", attribute.label, attribute.label).unwrap()
+                            }
+                        }
+                    }
+                }
+                statements.push(statement + ";");
+                instances.entry(&population.label).or_default().push(next_id);
+                next_id += 1;
+            }
+        }
+        for batch in statements.chunks(self.batch_size) {
+            let query = format!("insert\n    {}", batch.join("\n    "));
+            write(storage, &query_manager, &function_manager, &query);
+        }
+
+        let mut relations = Vec::new();
+        for population in &self.relations {
+            let roles = (population.roles.iter())
+                .map(|role| {
+                    let players = instances.get(role.player.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+                    assert!(!players.is_empty(), "no instances of '{}' to play '{}'", role.player, role.name);
+                    let selection = match role.selection {
+                        Selection::Uniform => Distribution::Uniform { min: 0, max: players.len() as u64 - 1 },
+                        Selection::Zipfian { exponent } => {
+                            Distribution::Zipfian { max: players.len() as u64 - 1, exponent }
+                        }
+                    };
+                    (role, players, role.fan_out.sampler(), selection.sampler())
+                })
+                .collect::<Vec<_>>();
+            for _ in 0..population.count * size {
+                let mut role_players = Vec::new();
+                for (role, players, fan_out, selection) in &roles {
+                    for _ in 0..fan_out.sample(&mut rng) {
+                        role_players.push((role.name.as_str(), players[selection.sample(&mut rng) as usize]));
+                    }
+                }
+                if !role_players.is_empty() {
+                    relations.push((population.label.as_str(), role_players));
+                }
+            }
+        }
+        for batch in relations.chunks(self.batch_size) {
+            let players = batch.iter().flat_map(|(_, role_players)| role_players.iter().map(|&(_, id)| id));
+            let matches = (players.collect::<BTreeSet<_>>().into_iter())
+                .map(|id| format!("$e{id} has {INSTANCE_ID_ATTRIBUTE} {id};"))
+                .collect::<Vec<_>>();
+            let inserts = (batch.iter())
+                .map(|(label, role_players)| {
+                    let links = role_players.iter().map(|(role, id)| format!("{role}: $e{id}")).collect::<Vec<_>>();
+                    format!("({}) isa {label};", links.join(", "))
+                })
+                .collect::<Vec<_>>();
+            let query = format!("match\n    {}\ninsert\n    {}", matches.join("\n    "), inserts.join("\n    "));
+            write(storage, &query_manager, &function_manager, &query);
+        }
+
+        synchronised_statistics(storage)
+    }
+
+    fn attribute_description(&self, label: &str) -> &AttributeDescription {
+        (self.attributes.iter())
+            .find(|attribute| attribute.label == label)
+            .unwrap_or_else(|| panic!("attribute type '{label}' is not described"))
+    }
+}
+
+impl Default for DatasetDescription {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn define(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    query_manager: &QueryManager,
+    function_manager: &FunctionManager,
+    schema: &str,
+) {
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let mut snapshot = storage.clone().open_snapshot_schema();
+    let define = typeql::parse_query(schema).unwrap().into_structure().into_schema();
+    query_manager
+        .execute_schema(&mut snapshot, &type_manager, &thing_manager, function_manager, define, schema)
+        .unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+}
+
+fn write(
+    storage: &Arc<MVCCStorage<WALClient>>,
+    query_manager: &QueryManager,
+    function_manager: &FunctionManager,
+    source: &str,
+) {
+    let (type_manager, thing_manager) = load_managers(storage.clone(), None);
+    let query = typeql::parse_query(source).unwrap().into_structure().into_pipeline();
+    let pipeline = query_manager
+        .prepare_write_pipeline(
+            storage.clone().open_snapshot_write(),
+            &type_manager,
+            thing_manager,
+            function_manager,
+            &query,
+            source,
+        )
+        .unwrap();
+    let (mut iterator, ExecutionContext { snapshot, .. }) =
+        pipeline.into_rows_iterator(ExecutionInterrupt::new_uninterruptible()).unwrap();
+    while let Some(row) = iterator.next() {
+        row.unwrap();
+    }
+    drop(iterator);
+    let snapshot = Arc::into_inner(snapshot).unwrap();
+    snapshot.commit(&mut CommitProfile::DISABLED).unwrap();
+}
+
+fn synchronised_statistics(storage: &Arc<MVCCStorage<WALClient>>) -> Statistics {
+    let mut statistics = Statistics::new(SequenceNumber::new(0));
+    statistics.may_synchronise(storage).unwrap();
+    statistics
+}
@@ -25,9 +25,13 @@ use executor::{
     pipeline::{
         delete::DeleteStageExecutor,
         insert::InsertStageExecutor,
-        stage::{ExecutionContext, StageAPI, StageIterator},
+        stage::{
+            ExecutionContext, MultiplicityPolicy, StageAPI, StageIterator, DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT,
+            DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS,
+        },
         PipelineExecutionError,
     },
+    read::{pushdown::StoragePushdown, retry::RetryPolicy},
     row::MaybeOwnedRow,
     write::WriteError,
     ExecutionInterrupt, Provenance,
@@ -38,7 +42,10 @@ use ir::{
 };
 use itertools::Itertools;
 use lending_iterator::{AsHkt, AsNarrowingIterator, LendingIterator};
-use resource::profile::{CommitProfile, QueryProfile, StorageCounters};
+use resource::{
+    constants::traversal::FIXED_BATCH_ROWS_MAX,
+    profile::{CommitProfile, QueryProfile, StorageCounters},
+};
 use storage::{
     durability_client::WALClient,
     snapshot::{CommittableSnapshot, WritableSnapshot, WriteSnapshot},
@@ -223,6 +230,13 @@ fn execute_insert<Snapshot: WritableSnapshot + 'static>(
             thing_manager,
             parameters: Arc::new(value_parameters),
             profile: Arc::new(QueryProfile::new(false)),
+            max_cartesian_group_product: DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT,
+            max_cartesian_prefetch_rows: DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS,
+            retry_policy: RetryPolicy::default(),
+            batch_capacity: FIXED_BATCH_ROWS_MAX,
+            storage_pushdown: StoragePushdown::none(),
+            row_limit: None,
+            multiplicity_policy: MultiplicityPolicy::Preserve,
         },
     );
     let insert_executor = InsertStageExecutor::new(Arc::new(insert_plan), initial);
@@ -316,6 +330,13 @@ fn execute_delete<Snapshot: WritableSnapshot + 'static>(
             thing_manager,
             parameters: Arc::new(value_parameters),
             profile: Arc::new(QueryProfile::new(false)),
+            max_cartesian_group_product: DEFAULT_MAX_CARTESIAN_GROUP_PRODUCT,
+            max_cartesian_prefetch_rows: DEFAULT_MAX_CARTESIAN_PREFETCH_ROWS,
+            retry_policy: RetryPolicy::default(),
+            batch_capacity: FIXED_BATCH_ROWS_MAX,
+            storage_pushdown: StoragePushdown::none(),
+            row_limit: None,
+            multiplicity_policy: MultiplicityPolicy::Preserve,
         },
     );
     let delete_executor = DeleteStageExecutor::new(Arc::new(delete_plan), initial);
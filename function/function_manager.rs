@@ -8,11 +8,15 @@ use std::{
     collections::{HashMap, HashSet},
     fmt::Write,
     iter::zip,
-    sync::Arc,
+    mem,
+    sync::{Arc, Mutex},
 };
 
 use bytes::{byte_array::ByteArray, Bytes};
-use compiler::annotation::function::{annotate_stored_functions, AnnotatedSchemaFunctions};
+use compiler::{
+    annotation::function::{annotate_stored_functions, AnnotatedSchemaFunctions},
+    executable::function::FunctionVersions,
+};
 use concept::type_::type_manager::TypeManager;
 use encoding::{
     graph::{
@@ -54,11 +58,13 @@ use crate::{function::SchemaFunction, function_cache::FunctionCache, FunctionErr
 pub struct FunctionManager {
     definition_key_generator: Arc<DefinitionKeyGenerator>,
     function_cache: Option<Arc<FunctionCache>>,
+    function_versions: Arc<FunctionVersions>,
+    invalidated_functions: Mutex<Vec<FunctionID>>,
 }
 
 impl Default for FunctionManager {
     fn default() -> Self {
-        Self::new(Arc::new(DefinitionKeyGenerator::new()), None)
+        Self::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default())
     }
 }
 
@@ -66,8 +72,25 @@ impl FunctionManager {
     pub fn new(
         definition_key_generator: Arc<DefinitionKeyGenerator>,
         function_cache: Option<Arc<FunctionCache>>,
+        function_versions: Arc<FunctionVersions>,
     ) -> Self {
-        FunctionManager { definition_key_generator, function_cache }
+        FunctionManager {
+            definition_key_generator,
+            function_cache,
+            function_versions,
+            invalidated_functions: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The versions of the function definitions of the database, which queries are compiled against.
+    pub fn function_versions(&self) -> &FunctionVersions {
+        &self.function_versions
+    }
+
+    /// Removes and returns the functions redefined or undefined through this manager. Their versions must only be
+    /// bumped once the snapshot the changes were written to has committed.
+    pub fn take_invalidated_functions(&self) -> Vec<FunctionID> {
+        mem::take(&mut *self.invalidated_functions.lock().unwrap())
     }
 
     pub fn get_annotated_functions(
@@ -147,9 +170,10 @@ impl FunctionManager {
             Ok(None) => Err(FunctionError::FunctionNotFound {}),
             Ok(Some(key)) => Ok(key),
         }?;
-        snapshot.delete(definition_key.into_storage_key().into_owned_array());
+        snapshot.delete(definition_key.clone().into_storage_key().into_owned_array());
         let index_key = NameToFunctionDefinitionIndex::build(name);
         snapshot.delete(index_key.into_storage_key().into_owned_array());
+        self.invalidated_functions.lock().unwrap().push(FunctionID::Schema(definition_key));
 
         Ok(())
     }
@@ -182,6 +206,7 @@ impl FunctionManager {
             );
         }
         let [function] = functions;
+        self.invalidated_functions.lock().unwrap().push(function.function_id.clone().into());
         Ok(function)
     }
 
@@ -548,11 +573,12 @@ pub mod tests {
             vec![VariableCategory::Object],
             vec![(VariableCategory::Object, VariableOptionality::Required)],
             true,
+            false,
         );
         let parsed =
             functions_to_define.iter().map(|f| typeql::parse_definition_function(f).unwrap()).collect::<Vec<_>>();
         let sequence_number = {
-            let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+            let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
             let mut snapshot = storage.clone().open_snapshot_write();
             let stored_functions = function_manager.define_functions(&mut snapshot, parsed.iter()).unwrap();
             // Read buffered
@@ -576,7 +602,7 @@ pub mod tests {
 
         {
             // Read committed
-            let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+            let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
             let snapshot = storage.clone().open_snapshot_read();
             assert_eq!(
                 expected_function_id,
@@ -604,7 +630,8 @@ pub mod tests {
             // Read cached
             let cache = Arc::new(FunctionCache::new(storage.clone(), &type_manager, sequence_number).unwrap());
             let snapshot = storage.clone().open_snapshot_read();
-            let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), Some(cache.clone()));
+            let function_manager =
+                FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), Some(cache.clone()), Arc::default());
             let index = ReadThroughFunctionSignatureIndex::new(
                 &snapshot,
                 &function_manager,
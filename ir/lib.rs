@@ -261,6 +261,20 @@ typedb_error! {
             variable_count: u16,
             source_span: Option<Span>,
         ),
+        ComparisonOperandNotValueComparable(
+            53,
+            "The comparison operand '{operand}' is a '{category}', but only values, attributes and parameters can be compared.",
+            operand: String,
+            category: String,
+            source_span: Option<Span>,
+        ),
+        ExpressionInputNotInScope(
+            54,
+            "The variable '{variable}' is used as an expression input, but is only bound in {bound_in}, which is not visible from the expression.",
+            variable: String,
+            bound_in: String,
+            source_span: Option<Span>,
+        ),
         UnimplementedLanguageFeature(
             254,
             "The language feature is not yet implemented: {feature}.",
@@ -5,7 +5,7 @@
  */
 
 use std::{
-    collections::{hash_map, HashMap},
+    collections::{hash_map, HashMap, HashSet},
     fmt,
     hash::{DefaultHasher, Hasher},
     ops::ControlFlow,
@@ -21,6 +21,7 @@ use crate::{
     pattern::{
         constraint::{Constraint, Constraints, ConstraintsBuilder, Unsatisfiable},
         disjunction::{Disjunction, DisjunctionBuilder},
+        enclosing_span,
         negation::Negation,
         nested_pattern::NestedPattern,
         optional::Optional,
@@ -57,6 +58,13 @@ impl Conjunction {
         &mut self.nested_patterns
     }
 
+    pub fn optimise_away_negations(&mut self, negations: &[ScopeId]) {
+        self.nested_patterns.retain(|nested| match nested {
+            NestedPattern::Negation(negation) => !negations.contains(&negation.scope_id()),
+            NestedPattern::Disjunction(_) | NestedPattern::Optional(_) => true,
+        })
+    }
+
     pub fn set_unsatisfiable(&mut self) {
         let mut swapped_conjunction = Self::new(self.scope_id);
         std::mem::swap(self, &mut swapped_conjunction);
@@ -70,6 +78,13 @@ impl Conjunction {
         }
     }
 
+    /// The range of query text covered by the constraints and nested patterns of this block.
+    pub fn source_span(&self) -> Option<Span> {
+        let constraint_spans = self.constraints().iter().filter_map(Constraint::source_span);
+        let nested_spans = self.nested_patterns.iter().filter_map(NestedPattern::source_span);
+        enclosing_span(constraint_spans.chain(nested_spans))
+    }
+
     pub fn local_variables<'a>(&'a self, block_context: &'a BlockContext) -> impl Iterator<Item = Variable> + 'a {
         self.referenced_variables().filter(|var| block_context.is_variable_available(self.scope_id, *var))
     }
@@ -88,6 +103,44 @@ impl Conjunction {
             .unique()
     }
 
+    /// The anonymous relation variables introduced by the relation shorthand, `(role: $player) isa relation`, that
+    /// are used by exactly one `links` or indexed relation constraint and otherwise only typed by `isa` constraints.
+    /// Answers only depend on how many such relations exist, not on which they are.
+    pub fn single_use_anonymous_relations(&self) -> HashSet<Variable> {
+        fn relation_of(constraint: &Constraint<Variable>) -> Option<Variable> {
+            match constraint {
+                Constraint::Links(links) => links.relation().as_variable(),
+                Constraint::IndexedRelation(indexed_relation) => indexed_relation.relation().as_variable(),
+                _ => None,
+            }
+        }
+
+        let nested_variables: HashSet<Variable> = (self.nested_patterns.iter())
+            .flat_map(|nested| -> Box<dyn Iterator<Item = Variable>> {
+                match nested {
+                    NestedPattern::Disjunction(disjunction) => Box::new(disjunction.referenced_variables()),
+                    NestedPattern::Negation(negation) => Box::new(negation.referenced_variables()),
+                    NestedPattern::Optional(_) => unimplemented_feature!(Optionals),
+                }
+            })
+            .collect();
+        let mut uses: HashMap<Variable, usize> = HashMap::new();
+        for relation in self.constraints().iter().filter_map(relation_of).filter(Variable::is_anonymous) {
+            *uses.entry(relation).or_default() += 1;
+        }
+        uses.into_iter()
+            .filter(|&(relation, count)| {
+                count == 1
+                    && !nested_variables.contains(&relation)
+                    && self.constraints().iter().all(|constraint| match constraint {
+                        Constraint::Isa(isa) => isa.type_().as_variable() != Some(relation),
+                        _ => relation_of(constraint) == Some(relation) || !constraint.ids().contains(&relation),
+                    })
+            })
+            .map(|(relation, _)| relation)
+            .collect()
+    }
+
     pub fn named_producible_variables(&self, block_context: &BlockContext) -> impl Iterator<Item = Variable> + '_ {
         self.producible_variables(block_context).filter(Variable::is_named)
     }
@@ -15,6 +15,7 @@ use std::{
 
 use answer::variable::Variable;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use structural_equality::StructuralEquality;
 use typeql::common::Span;
 
@@ -308,6 +309,8 @@ impl<'cx, 'reg> ConstraintsBuilder<'cx, 'reg> {
         source_span: Option<Span>,
     ) -> Result<&Comparison<Variable>, Box<RepresentationError>> {
         comparator.validate_arguments(&lhs, &rhs, self.parameters(), source_span)?;
+        self.validate_comparison_operand(&lhs, source_span)?;
+        self.validate_comparison_operand(&rhs, source_span)?;
         let comparison = Comparison::new(lhs.clone(), rhs.clone(), comparator, source_span);
         if let Vertex::Variable(lhs_var) = lhs {
             debug_assert!(self.context.is_variable_available(self.constraints.scope, lhs_var));
@@ -330,6 +333,31 @@ impl<'cx, 'reg> ConstraintsBuilder<'cx, 'reg> {
         Ok(as_ref.as_comparison().unwrap())
     }
 
+    fn validate_comparison_operand(
+        &self,
+        operand: &Vertex<Variable>,
+        source_span: Option<Span>,
+    ) -> Result<(), Box<RepresentationError>> {
+        let category = match operand {
+            Vertex::Parameter(_) => return Ok(()),
+            Vertex::Label(_) => "type label".to_owned(),
+            &Vertex::Variable(variable) => match self.context.get_variable_category(variable) {
+                Some(category) if category.narrowest(VariableCategory::AttributeOrValue).is_none() => {
+                    category.to_string()
+                }
+                _ => return Ok(()),
+            },
+        };
+        let operand = match operand {
+            &Vertex::Variable(variable) => match self.context.get_variable_name(variable) {
+                Some(name) => format!("${name}"),
+                None => "$_".to_owned(),
+            },
+            _ => operand.to_string(),
+        };
+        Err(Box::new(RepresentationError::ComparisonOperandNotValueComparable { operand, category, source_span }))
+    }
+
     pub fn add_function_binding(
         &mut self,
         assigned: Vec<Variable>,
@@ -349,7 +377,13 @@ impl<'cx, 'reg> ConstraintsBuilder<'cx, 'reg> {
 
         let function_call =
             self.create_function_call(&assigned, callee_signature, arguments, function_name, source_span)?;
-        let binding = FunctionCallBinding::new(assigned, function_call, callee_signature.return_is_stream, source_span);
+        let binding = FunctionCallBinding::new(
+            assigned,
+            function_call,
+            callee_signature.return_is_stream,
+            callee_signature.returns_single_row,
+            source_span,
+        );
         for (index, var) in binding.ids_assigned().enumerate() {
             self.context.set_variable_category(var, callee_signature.returns[index].0, binding.clone().into())?;
         }
@@ -736,7 +770,7 @@ impl<ID: IrID> Constraint<ID> {
     pub fn source_span(&self) -> Option<Span> {
         match self {
             Constraint::Is(inner) => inner.source_span(),
-            Constraint::Kind(inner) => None,
+            Constraint::Kind(inner) => inner.source_span(),
             Constraint::Label(inner) => inner.source_span(),
             Constraint::RoleName(inner) => inner.source_span(),
             Constraint::Sub(inner) => inner.source_span(),
@@ -753,7 +787,7 @@ impl<ID: IrID> Constraint<ID> {
             Constraint::Plays(inner) => inner.source_span(),
             Constraint::Value(inner) => inner.source_span(),
             Constraint::LinksDeduplication(inner) => None,
-            Constraint::Unsatisfiable(inner) => None,
+            Constraint::Unsatisfiable(inner) => inner.source_span(),
         }
     }
 
@@ -1163,7 +1197,7 @@ pub struct Kind<ID> {
 }
 
 impl<ID> Kind<ID> {
-    fn source_span(&self) -> Option<Span> {
+    pub fn source_span(&self) -> Option<Span> {
         self.source_span
     }
 }
@@ -1232,7 +1266,7 @@ impl<ID: fmt::Debug> fmt::Debug for Kind<ID> {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum SubKind {
     Exact,
     Subtype,
@@ -1267,11 +1301,12 @@ impl fmt::Display for SubKind {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sub<ID> {
     kind: SubKind,
     subtype: Vertex<ID>,
     supertype: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -1361,10 +1396,11 @@ impl<ID: IrID> fmt::Display for Sub<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Is<ID> {
     lhs: Vertex<ID>,
     rhs: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -1448,11 +1484,12 @@ impl<ID: IrID> fmt::Display for Is<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Isa<ID> {
     kind: IsaKind,
     thing: Vertex<ID>,
     type_: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -1547,7 +1584,7 @@ impl<ID: IrID> fmt::Display for Isa<ID> {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum IsaKind {
     Exact,
     Subtype,
@@ -1582,10 +1619,11 @@ impl fmt::Display for IsaKind {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Iid<ID> {
     var: Vertex<ID>,
     iid: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -1667,11 +1705,12 @@ impl<ID: IrID> fmt::Display for Iid<ID> {
         write!(f, "{} iid {}", self.var, self.iid)
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Links<ID> {
     relation: Vertex<ID>,
     player: Vertex<ID>,
     role_type: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -1940,10 +1979,11 @@ impl<ID: IrID> From<IndexedRelation<ID>> for Constraint<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Has<ID> {
     owner: Vertex<ID>,
     attribute: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -2138,12 +2178,25 @@ pub struct FunctionCallBinding<ID> {
     assigned: Vec<Vertex<ID>>,
     function_call: FunctionCall<ID>,
     is_stream: bool,
+    returns_single_row: bool,
     source_span: Option<Span>,
 }
 
 impl<ID> FunctionCallBinding<ID> {
-    fn new(left: Vec<ID>, function_call: FunctionCall<ID>, is_stream: bool, source_span: Option<Span>) -> Self {
-        Self { assigned: left.into_iter().map(Vertex::Variable).collect(), function_call, is_stream, source_span }
+    fn new(
+        left: Vec<ID>,
+        function_call: FunctionCall<ID>,
+        is_stream: bool,
+        returns_single_row: bool,
+        source_span: Option<Span>,
+    ) -> Self {
+        Self {
+            assigned: left.into_iter().map(Vertex::Variable).collect(),
+            function_call,
+            is_stream,
+            returns_single_row,
+            source_span,
+        }
     }
 
     pub fn source_span(&self) -> Option<Span> {
@@ -2164,6 +2217,10 @@ impl<ID: IrID> FunctionCallBinding<ID> {
         self.is_stream
     }
 
+    pub fn returns_single_row(&self) -> bool {
+        self.returns_single_row
+    }
+
     pub fn ids(&self) -> impl Iterator<Item = ID> + '_ {
         self.ids_assigned().chain(self.function_call.argument_ids())
     }
@@ -2192,6 +2249,7 @@ impl<ID: IrID> FunctionCallBinding<ID> {
             assigned: self.assigned.into_iter().map(|v| v.map(mapping)).collect(),
             function_call: self.function_call.map(mapping),
             is_stream: self.is_stream,
+            returns_single_row: self.returns_single_row,
             source_span: self.source_span,
         }
     }
@@ -2209,6 +2267,7 @@ impl<ID: StructuralEquality + Ord> StructuralEquality for FunctionCallBinding<ID
         self.assigned.hash_into(&mut hasher);
         self.function_call.hash_into(&mut hasher);
         self.is_stream.hash_into(&mut hasher);
+        self.returns_single_row.hash_into(&mut hasher);
         hasher.finish()
     }
 
@@ -2216,6 +2275,7 @@ impl<ID: StructuralEquality + Ord> StructuralEquality for FunctionCallBinding<ID
         self.assigned.equals(&other.assigned)
             && self.function_call.equals(&other.function_call)
             && self.is_stream.equals(&other.is_stream)
+            && self.returns_single_row.equals(&other.returns_single_row)
     }
 }
 
@@ -2229,7 +2289,7 @@ impl<ID: IrID> fmt::Display for FunctionCallBinding<ID> {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Comparator {
     Equal,
     NotEqual,
@@ -2428,10 +2488,11 @@ impl<ID: IrID> fmt::Display for Comparison<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Owns<ID> {
     owner: Vertex<ID>,
     attribute: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -2514,10 +2575,11 @@ impl<ID: IrID> fmt::Display for Owns<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Relates<ID> {
     relation: Vertex<ID>,
     role_type: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -2604,10 +2666,11 @@ impl<ID: IrID> fmt::Display for Relates<ID> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plays<ID> {
     player: Vertex<ID>,
     role_type: Vertex<ID>,
+    #[serde(skip)]
     source_span: Option<Span>,
 }
 
@@ -2883,6 +2946,10 @@ impl Unsatisfiable {
     pub fn map<ID: IrID, T: Clone>(self, mapping: &HashMap<ID, T>) -> Unsatisfiable {
         self
     }
+
+    pub fn source_span(&self) -> Option<Span> {
+        self.conjunction.source_span()
+    }
 }
 
 impl PartialEq<Self> for Unsatisfiable {
@@ -17,7 +17,7 @@ use typeql::common::Span;
 use crate::{
     pattern::{
         conjunction::{Conjunction, ConjunctionBuilder},
-        BranchID, Scope, ScopeId, VariableBindingMode,
+        enclosing_span, BranchID, Scope, ScopeId, VariableBindingMode,
     },
     pipeline::block::{BlockBuilderContext, BlockContext, ScopeTransparency},
 };
@@ -45,6 +45,10 @@ impl Disjunction {
         &mut self.conjunctions
     }
 
+    pub fn source_span(&self) -> Option<Span> {
+        enclosing_span(self.conjunctions.iter().filter_map(Conjunction::source_span))
+    }
+
     pub fn named_producible_variables(&self, block_context: &BlockContext) -> impl Iterator<Item = Variable> + '_ {
         self.producible_variables(block_context).filter(Variable::is_named)
     }
@@ -72,6 +76,8 @@ impl Disjunction {
         self.variable_dependency(block_context).into_iter().filter_map(|(v, dep)| dep.is_required().then_some(v))
     }
 
+    // Computed from the current branches on every call: once unsatisfiable branches are optimised away, variables
+    // produced by every remaining branch are producing. Must not be cached across that optimisation.
     pub(crate) fn variable_dependency(
         &self,
         block_context: &BlockContext,
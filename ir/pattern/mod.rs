@@ -16,6 +16,7 @@ use std::{
 use answer::variable::Variable;
 use constraint::Constraint;
 use encoding::value::label::Label;
+use serde::{Deserialize, Serialize};
 use structural_equality::StructuralEquality;
 use typeql::common::Span;
 
@@ -32,7 +33,7 @@ pub mod expression;
 pub mod function_call;
 pub mod nested_pattern;
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct BranchID(pub u16);
 
 pub trait Scope {
@@ -69,7 +70,7 @@ pub trait IrID: Copy + fmt::Display + fmt::Debug + Hash + Eq + PartialEq + Ord +
 impl IrID for Variable {}
 
 // TODO: rename to 'Identifier' in lieu of a better name
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Vertex<ID> {
     Variable(ID),
     Label(Label),
@@ -188,11 +189,33 @@ impl<ID: fmt::Debug> fmt::Display for Vertex<ID> {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum ParameterID {
-    Value(usize, Span),
-    Iid(usize, Span),
-    FetchKey(usize, Span),
+    Value(usize, #[serde(with = "span_offsets")] Span),
+    Iid(usize, #[serde(with = "span_offsets")] Span),
+    FetchKey(usize, #[serde(with = "span_offsets")] Span),
+}
+
+/// The smallest span covering all of the given spans, or `None` if there are none.
+pub fn enclosing_span(spans: impl IntoIterator<Item = Span>) -> Option<Span> {
+    spans.into_iter().reduce(|lhs, rhs| Span {
+        begin_offset: usize::min(lhs.begin_offset, rhs.begin_offset),
+        end_offset: usize::max(lhs.end_offset, rhs.end_offset),
+    })
+}
+
+mod span_offsets {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use typeql::common::Span;
+
+    pub(super) fn serialize<S: Serializer>(span: &Span, serializer: S) -> Result<S::Ok, S::Error> {
+        (span.begin_offset, span.end_offset).serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Span, D::Error> {
+        let (begin_offset, end_offset) = <(usize, usize)>::deserialize(deserializer)?;
+        Ok(Span { begin_offset, end_offset })
+    }
 }
 
 impl ParameterID {
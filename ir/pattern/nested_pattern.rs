@@ -65,6 +65,14 @@ impl NestedPattern {
         }
     }
 
+    pub fn source_span(&self) -> Option<Span> {
+        match self {
+            NestedPattern::Disjunction(disjunction) => disjunction.source_span(),
+            NestedPattern::Negation(negation) => negation.conjunction().source_span(),
+            NestedPattern::Optional(optional) => optional.conjunction().source_span(),
+        }
+    }
+
     pub(crate) fn variable_dependency(
         &self,
         block_context: &BlockContext,
@@ -6,7 +6,7 @@
 
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum VariableCategory {
     Type,
     ThingType,
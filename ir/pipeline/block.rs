@@ -139,6 +139,7 @@ fn validate_conjunction(
         let name = variable_registry.get_variable_name(var).unwrap().clone();
         return Err(Box::new(RepresentationError::DisjointVariableReuse { name, source_span }));
     }
+    validate_expression_inputs_in_scope(conjunction, conjunction, &HashSet::new(), variable_registry, block_context)?;
 
     for (var, dep) in conjunction.variable_dependency(block_context) {
         if dep.is_required() && block_context.get_scope(&var) != Some(ScopeId::INPUT) {
@@ -156,6 +157,81 @@ fn validate_conjunction(
     Ok(())
 }
 
+// Every expression input must be produced by the expression's conjunction or one of its ancestors. Inputs bound only
+// in a sibling disjunction branch or a nested negation are otherwise merged into a requirement on the parent scope,
+// and surface late and unhelpfully, so we report where the variable is actually bound.
+fn validate_expression_inputs_in_scope(
+    root: &Conjunction,
+    conjunction: &Conjunction,
+    available_from_parents: &HashSet<Variable>,
+    variable_registry: &VariableRegistry,
+    block_context: &BlockContext,
+) -> Result<(), Box<RepresentationError>> {
+    let mut available = available_from_parents.clone();
+    available.extend(
+        conjunction
+            .variable_dependency(block_context)
+            .into_iter()
+            .filter_map(|(var, dep)| dep.is_producing().then_some(var)),
+    );
+    for binding in conjunction.constraints().iter().filter_map(|constraint| constraint.as_expression_binding()) {
+        for variable in binding.required_ids() {
+            if available.contains(&variable) || block_context.get_scope(&variable) == Some(ScopeId::INPUT) {
+                continue;
+            }
+            if let Some(bound_in) = find_binding_pattern(root, variable, block_context) {
+                return Err(Box::new(RepresentationError::ExpressionInputNotInScope {
+                    variable: variable_registry.get_variable_name(variable).cloned().unwrap_or(String::new()),
+                    bound_in,
+                    source_span: binding.source_span(),
+                }));
+            }
+        }
+    }
+
+    conjunction.nested_patterns().iter().try_for_each(|nested| match nested {
+        NestedPattern::Disjunction(disjunction) => disjunction.conjunctions().iter().try_for_each(|branch| {
+            validate_expression_inputs_in_scope(root, branch, &available, variable_registry, block_context)
+        }),
+        NestedPattern::Negation(negation) => validate_expression_inputs_in_scope(
+            root,
+            negation.conjunction(),
+            &available,
+            variable_registry,
+            block_context,
+        ),
+        NestedPattern::Optional(optional) => validate_expression_inputs_in_scope(
+            root,
+            optional.conjunction(),
+            &available,
+            variable_registry,
+            block_context,
+        ),
+    })
+}
+
+fn find_binding_pattern(conjunction: &Conjunction, variable: Variable, block_context: &BlockContext) -> Option<String> {
+    let produces = |conjunction: &Conjunction| {
+        conjunction.variable_dependency(block_context).get(&variable).is_some_and(|dep| dep.is_producing())
+    };
+    conjunction.nested_patterns().iter().find_map(|nested| match nested {
+        NestedPattern::Disjunction(disjunction) => {
+            disjunction.conjunctions().iter().enumerate().find_map(|(index, branch)| match produces(branch) {
+                true => Some(format!("branch {} of a disjunction", index + 1)),
+                false => find_binding_pattern(branch, variable, block_context),
+            })
+        }
+        NestedPattern::Negation(negation) => match produces(negation.conjunction()) {
+            true => Some("a negation".to_owned()),
+            false => find_binding_pattern(negation.conjunction(), variable, block_context),
+        },
+        NestedPattern::Optional(optional) => match produces(optional.conjunction()) {
+            true => Some("an optional".to_owned()),
+            false => find_binding_pattern(optional.conjunction(), variable, block_context),
+        },
+    })
+}
+
 fn validate_is_variables_have_same_category(
     conjunction: &Conjunction,
     variable_registry: &VariableRegistry,
@@ -363,6 +439,10 @@ impl<'a> BlockBuilderContext<'a> {
         self.variable_registry.get_variable_name(variable)
     }
 
+    pub(crate) fn get_variable_category(&self, variable: Variable) -> Option<VariableCategory> {
+        self.variable_registry.get_variable_category(variable)
+    }
+
     pub(crate) fn get_or_declare_variable(
         &mut self,
         name: &str,
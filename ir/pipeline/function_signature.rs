@@ -8,6 +8,7 @@ use std::{collections::HashMap, fmt, mem, ops::BitXor};
 
 use encoding::graph::definition::definition_key::DefinitionKey;
 use primitive::maybe_owns::MaybeOwns;
+use serde::{Deserialize, Serialize};
 use structural_equality::StructuralEquality;
 
 use crate::{
@@ -16,7 +17,7 @@ use crate::{
     translation::function::build_signature,
 };
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum FunctionID {
     Schema(DefinitionKey),
     Preamble(usize),
@@ -28,6 +29,9 @@ pub struct FunctionSignature {
     pub(crate) arguments: Vec<VariableCategory>, // TODO: Arguments cannot be optional
     pub(crate) returns: Vec<(VariableCategory, VariableOptionality)>,
     pub(crate) return_is_stream: bool,
+    /// Whether every call returns exactly one row, as a reduction does, so the returned values are determined by the
+    /// arguments.
+    pub(crate) returns_single_row: bool,
 }
 
 impl FunctionSignature {
@@ -36,8 +40,9 @@ impl FunctionSignature {
         arguments: Vec<VariableCategory>,
         returns: Vec<(VariableCategory, VariableOptionality)>,
         return_is_stream: bool,
+        returns_single_row: bool,
     ) -> FunctionSignature {
-        Self { function_id, arguments, returns, return_is_stream }
+        Self { function_id, arguments, returns, return_is_stream, returns_single_row }
     }
 
     pub fn function_id(&self) -> FunctionID {
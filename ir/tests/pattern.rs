@@ -5,7 +5,8 @@
  */
 
 use ir::{
-    pipeline::{function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
+    pattern::{constraint::Comparator, nested_pattern::NestedPattern, Scope, Vertex},
+    pipeline::{block::Block, function_signature::HashMapFunctionSignatureIndex, ParameterRegistry},
     translation::{match_::translate_match, PipelineTranslationContext},
     RepresentationError,
 };
@@ -182,3 +183,127 @@ fn variable_category_narrowing() {
 
     // println!("{}", conjunction);
 }
+
+#[test]
+fn comparison_against_type_variable() {
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+
+    let query = "match
+        $t sub entity;
+        $t > 5;
+    ";
+    let parsed = typeql::parse_query(query).unwrap().into_structure();
+    let typeql::query::QueryStructure::Pipeline(typeql::query::Pipeline { stages, .. }) = parsed else {
+        unreachable!()
+    };
+    let Stage::Match(match_) = stages.first().unwrap() else { unreachable!() };
+    let mut context = PipelineTranslationContext::new();
+    let mut parameters = ParameterRegistry::new();
+    let translated = translate_match(&mut context, &mut parameters, &empty_function_index, match_);
+    assert!(matches!(
+        translated.unwrap_err().as_ref(),
+        RepresentationError::ComparisonOperandNotValueComparable { operand, .. } if operand == "$t"
+    ));
+}
+
+#[test]
+fn comparison_against_anonymous_role_variable() {
+    let mut translation_context = PipelineTranslationContext::new();
+    let mut value_parameters = ParameterRegistry::new();
+    let mut builder = Block::builder(translation_context.new_block_builder_context(&mut value_parameters));
+    let mut conjunction = builder.conjunction_mut();
+    let mut constraints = conjunction.constraints_mut();
+    let var_relation = constraints.get_or_declare_variable("relation", None).unwrap();
+    let var_player = constraints.get_or_declare_variable("player", None).unwrap();
+    let var_value = constraints.get_or_declare_variable("value", None).unwrap();
+    let var_role = constraints.create_anonymous_variable(None).unwrap();
+    constraints.add_links(var_relation, var_player, var_role, None).unwrap();
+
+    let result =
+        constraints.add_comparison(Vertex::Variable(var_value), Vertex::Variable(var_role), Comparator::Greater, None);
+    assert!(matches!(
+        result.unwrap_err().as_ref(),
+        RepresentationError::ComparisonOperandNotValueComparable { operand, .. } if operand == "$_"
+    ));
+}
+
+#[test]
+fn expression_input_bound_in_sibling_branch() {
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+
+    let query = "match
+        $p isa person;
+        { $p has age $n; } or { let $v = $n + 1; };
+    ";
+    let parsed = typeql::parse_query(query).unwrap().into_structure();
+    let typeql::query::QueryStructure::Pipeline(typeql::query::Pipeline { stages, .. }) = parsed else {
+        unreachable!()
+    };
+    let Stage::Match(match_) = stages.first().unwrap() else { unreachable!() };
+    let mut context = PipelineTranslationContext::new();
+    let mut parameters = ParameterRegistry::new();
+    let translated = translate_match(&mut context, &mut parameters, &empty_function_index, match_).unwrap();
+    assert!(matches!(
+        translated.finish().unwrap_err().as_ref(),
+        RepresentationError::ExpressionInputNotInScope { variable, bound_in, .. }
+            if variable == "n" && bound_in == "branch 1 of a disjunction"
+    ));
+}
+
+#[test]
+fn expression_input_bound_in_negation() {
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+
+    let query = "match
+        $p isa person;
+        let $v = $n + 1;
+        not { $p has age $n; };
+    ";
+    let parsed = typeql::parse_query(query).unwrap().into_structure();
+    let typeql::query::QueryStructure::Pipeline(typeql::query::Pipeline { stages, .. }) = parsed else {
+        unreachable!()
+    };
+    let Stage::Match(match_) = stages.first().unwrap() else { unreachable!() };
+    let mut context = PipelineTranslationContext::new();
+    let mut parameters = ParameterRegistry::new();
+    let translated = translate_match(&mut context, &mut parameters, &empty_function_index, match_).unwrap();
+    assert!(matches!(
+        translated.finish().unwrap_err().as_ref(),
+        RepresentationError::ExpressionInputNotInScope { variable, bound_in, .. }
+            if variable == "n" && bound_in == "a negation"
+    ));
+}
+
+#[test]
+fn variable_produced_by_all_surviving_branches_is_producing() {
+    let empty_function_index = HashMapFunctionSignatureIndex::empty();
+
+    let query = "match
+        $p isa person;
+        $x isa name;
+        { $p has $x; } or { $c isa company, has $x; } or { $p has age $a; };
+    ";
+    let parsed = typeql::parse_query(query).unwrap().into_structure();
+    let typeql::query::QueryStructure::Pipeline(typeql::query::Pipeline { stages, .. }) = parsed else {
+        unreachable!()
+    };
+    let Stage::Match(match_) = stages.first().unwrap() else { unreachable!() };
+    let mut context = PipelineTranslationContext::new();
+    let mut parameters = ParameterRegistry::new();
+    let translated = translate_match(&mut context, &mut parameters, &empty_function_index, match_).unwrap();
+    let mut block = translated.finish().unwrap();
+    let x = context.get_variable("x").unwrap();
+    let block_context = block.block_context().clone();
+
+    let [NestedPattern::Disjunction(disjunction)] = block.conjunction_mut().nested_patterns_mut() else {
+        unreachable!()
+    };
+    // the branch binding only the age does not produce $x
+    assert!(!disjunction.named_producible_variables(&block_context).any(|var| var == x));
+
+    let unsatisfiable_branch = disjunction.conjunctions()[2].scope_id();
+    disjunction.optimise_away_unsatisfiable_branches(vec![unsatisfiable_branch]);
+    assert!(disjunction.named_producible_variables(&block_context).any(|var| var == x));
+    assert!(!disjunction.required_inputs(&block_context).any(|var| var == x));
+    assert!(!block.conjunction().required_inputs(&block_context).any(|var| var == x));
+}
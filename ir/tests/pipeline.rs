@@ -70,6 +70,7 @@ fn build_with_functions() {
         function_argument_categories,
         function_return_categories,
         false,
+        true,
     );
     conjunction
         .constraints_mut()
@@ -184,6 +184,8 @@ pub fn build_signature(function_id: FunctionID, function: &typeql::Function) ->
         .collect::<Vec<_>>();
 
     let return_is_stream = matches!(function.signature.output, Output::Stream(_));
+    // a reduction returns one row even over no answers, while `return first` and `return last` may return none
+    let returns_single_row = matches!(function.block.return_stmt, ReturnStatement::Reduce(_));
     let returns = match &function.signature.output {
         Output::Stream(stream) => &stream.types,
         Output::Single(single) => &single.types,
@@ -191,7 +193,7 @@ pub fn build_signature(function_id: FunctionID, function: &typeql::Function) ->
     .iter()
     .map(named_type_any_to_category_and_optionality)
     .collect::<Vec<_>>();
-    FunctionSignature::new(function_id.clone(), args, returns, return_is_stream)
+    FunctionSignature::new(function_id.clone(), args, returns, return_is_stream, returns_single_row)
 }
 
 fn named_type_any_to_category_and_optionality(
@@ -10,7 +10,7 @@ use std::{
 };
 
 use answer::Type;
-use compiler::executable::pipeline::ExecutablePipeline;
+use compiler::executable::{match_::planner::plan_cache::PlanCache, pipeline::ExecutablePipeline};
 use concept::thing::statistics::Statistics;
 use ir::{
     pipeline::{fetch::FetchObject, function::Function},
@@ -26,12 +26,18 @@ use structural_equality::StructuralEquality;
 #[derive(Debug)]
 pub struct QueryCache {
     cache: Cache<IRQuery, ExecutablePipeline>,
+    plan_cache: PlanCache,
 }
 
 impl QueryCache {
     pub fn new() -> Self {
         let cache = CacheBuilder::new(QUERY_PLAN_CACHE_SIZE).support_invalidation_closures().build();
-        QueryCache { cache }
+        QueryCache { cache, plan_cache: PlanCache::default() }
+    }
+
+    /// The plans of the conjunctions of the database, reused by queries that are not cached as a whole.
+    pub fn plan_cache(&self) -> &PlanCache {
+        &self.plan_cache
     }
 
     pub(crate) fn get(
@@ -83,6 +89,7 @@ impl QueryCache {
 
     pub fn force_reset(&self, _statistics: &Statistics) {
         self.cache.invalidate_all();
+        self.plan_cache.clear();
         QUERY_CACHE_FLUSH.increment();
     }
 }
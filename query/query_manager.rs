@@ -12,10 +12,13 @@ use compiler::{
     query_structure::extract_query_structure_from,
     transformation::transform::apply_transformations,
 };
-use concept::{thing::thing_manager::ThingManager, type_::type_manager::TypeManager};
+use concept::{
+    thing::{statistics::StatisticsFreshness, thing_manager::ThingManager},
+    type_::type_manager::TypeManager,
+};
 use executor::pipeline::{
     pipeline::Pipeline,
-    stage::{ReadPipelineStage, WritePipelineStage},
+    stage::{MultiplicityPolicy, ReadPipelineStage, WritePipelineStage},
 };
 use function::function_manager::{validate_no_cycles, FunctionManager, ReadThroughFunctionSignatureIndex};
 use ir::{
@@ -176,10 +179,15 @@ impl QueryManager {
 
                 let AnnotatedPipeline { annotated_preamble, annotated_stages, annotated_fetch } = annotated_pipeline;
                 // 3: Compile
+                let statistics_freshness = statistics_freshness(&thing_manager, snapshot.as_ref());
                 let executable_pipeline = compile_pipeline_and_functions(
                     thing_manager.statistics(),
+                    statistics_freshness,
                     &variable_registry,
+                    &parameters,
                     &annotated_schema_functions,
+                    function_manager.function_versions(),
+                    self.cache.as_deref().map(QueryCache::plan_cache),
                     annotated_preamble,
                     annotated_stages,
                     annotated_fetch,
@@ -190,7 +198,10 @@ impl QueryManager {
                     source_query: source_query.to_string(),
                     typedb_source: err,
                 })?;
-                if let Some(cache) = self.cache.as_ref() {
+                // plans estimated from stale statistics are replaced once the statistics catch up
+                if let Some(cache) =
+                    self.cache.as_ref().filter(|_| statistics_freshness == StatisticsFreshness::Current)
+                {
                     cache.insert(arced_preamble, arced_stages, arced_fetch, executable_pipeline.clone())
                 }
                 compile_profile.compilation_finished();
@@ -214,6 +225,7 @@ impl QueryManager {
             Arc::new(parameters),
             None,
             Arc::new(query_profile),
+            MultiplicityPolicy::default(),
         )
         .map_err(|typedb_source| {
             Box::new(QueryError::Pipeline { source_query: source_query.to_string(), typedb_source })
@@ -336,10 +348,15 @@ impl QueryManager {
                 let AnnotatedPipeline { annotated_preamble, annotated_stages, annotated_fetch } = annotated_pipeline;
 
                 // 3: Compile
+                let statistics_freshness = statistics_freshness(&thing_manager, &snapshot);
                 let executable_pipeline = match compile_pipeline_and_functions(
                     thing_manager.statistics(),
+                    statistics_freshness,
                     &variable_registry,
+                    &value_parameters,
                     &annotated_schema_functions,
+                    function_manager.function_versions(),
+                    self.cache.as_deref().map(QueryCache::plan_cache),
                     annotated_preamble,
                     annotated_stages,
                     annotated_fetch,
@@ -357,7 +374,10 @@ impl QueryManager {
                         ))
                     }
                 };
-                if let Some(cache) = self.cache.as_ref() {
+                // plans estimated from stale statistics are replaced once the statistics catch up
+                if let Some(cache) =
+                    self.cache.as_ref().filter(|_| statistics_freshness == StatisticsFreshness::Current)
+                {
                     cache.insert(arced_preamble, arced_stages, arced_fetch, executable_pipeline.clone())
                 }
                 compile_profile.compilation_finished();
@@ -380,6 +400,7 @@ impl QueryManager {
             executable_fetch,
             Arc::new(value_parameters),
             Arc::new(query_profile),
+            MultiplicityPolicy::default(),
         ))
     }
 
@@ -400,3 +421,8 @@ impl QueryManager {
         })
     }
 }
+
+fn statistics_freshness(thing_manager: &ThingManager, snapshot: &impl ReadableSnapshot) -> StatisticsFreshness {
+    let has_uncommitted_writes = snapshot.iterate_writes().next().is_some();
+    thing_manager.statistics().freshness_at(snapshot.open_sequence_number(), has_uncommitted_writes)
+}
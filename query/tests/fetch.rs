@@ -62,7 +62,7 @@ fn fetch() {
     let (_tmp_dir, mut storage) = create_core_storage();
     setup_concept_storage(&mut storage);
     let (type_manager, thing_manager) = load_managers(storage.clone(), None);
-    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None);
+    let function_manager = FunctionManager::new(Arc::new(DefinitionKeyGenerator::new()), None, Arc::default());
     define_schema(storage.clone(), type_manager.as_ref(), thing_manager.as_ref(), &function_manager);
     insert_data(
         storage.clone(),
@@ -395,6 +395,22 @@ impl fmt::Display for QueryProfile {
                 .sum::<u64>() as f64
                 / 1000.0;
         writeln!(f, "Query profile[measurements_enabled={}, total micros: {}]", self.enabled, total_micros)?;
+        let step_profiles = stage_profiles
+            .values()
+            .flat_map(|stage_profile| stage_profile.step_profiles.read().unwrap().clone())
+            .collect::<Vec<_>>();
+        let cartesian_activations =
+            step_profiles.iter().map(|step_profile| step_profile.cartesian_activations()).sum::<u64>();
+        if cartesian_activations > 0 {
+            // cartesian products may emit many more rows than their intersections, so they are summarised up front
+            writeln!(
+                f,
+                "  Cartesian products[activations: {}, rows: {}, reopens: {}]",
+                cartesian_activations,
+                step_profiles.iter().map(|step_profile| step_profile.cartesian_rows()).sum::<u64>(),
+                step_profiles.iter().map(|step_profile| step_profile.cartesian_reopens()).sum::<u64>(),
+            )?;
+        }
         writeln!(f, "{}", self.compile_profile)?;
         for (id, pattern_profile) in stage_profiles.iter().sorted_by_key(|(id, _)| *id) {
             writeln!(f, "  -----")?;
@@ -537,13 +553,23 @@ impl StageProfile {
     }
 
     pub fn extend_or_get(&self, index: usize, description_getter: impl Fn() -> String) -> Arc<StepProfile> {
+        self.extend_or_get_labelled(index, None, description_getter)
+    }
+
+    /// As `extend_or_get`, labelling a new step profile with a label that identifies the step across query plans.
+    pub fn extend_or_get_labelled(
+        &self,
+        index: usize,
+        label: Option<String>,
+        description_getter: impl Fn() -> String,
+    ) -> Arc<StepProfile> {
         if self.enabled {
             let profiles = self.step_profiles.read().unwrap();
             if index < profiles.len() {
                 profiles[index].clone()
             } else {
                 debug_assert!(index == profiles.len(), "Can only extend step profiles sequentially");
-                let profile = Arc::new(StepProfile::new_enabled(description_getter()));
+                let profile = Arc::new(StepProfile::new_enabled(label, description_getter()));
                 drop(profiles);
                 let mut profiles_mut = self.step_profiles.write().unwrap();
                 profiles_mut.push(profile.clone());
@@ -553,6 +579,10 @@ impl StageProfile {
             Arc::new(StepProfile::new_disabled())
         }
     }
+
+    pub fn step_profile(&self, index: usize) -> Option<Arc<StepProfile>> {
+        self.step_profiles.read().unwrap().get(index).cloned()
+    }
 }
 
 impl fmt::Display for StageProfile {
@@ -574,22 +604,36 @@ pub struct StepProfile {
 
 #[derive(Debug)]
 struct StepProfileData {
+    label: Option<String>,
     description: String,
     batches: AtomicU64,
     rows: AtomicU64,
     nanos: AtomicU64,
+    retries: AtomicU64,
+    yields: AtomicU64,
+    cartesian_activations: AtomicU64,
+    cartesian_rows: AtomicU64,
+    cartesian_reopens: AtomicU64,
     storage: StorageCounters,
+    instruction_profiles: RwLock<Vec<Arc<InstructionProfile>>>,
 }
 
 impl StepProfile {
-    fn new_enabled(description: String) -> Self {
+    fn new_enabled(label: Option<String>, description: String) -> Self {
         Self {
             data: Some(StepProfileData {
+                label,
                 description,
                 batches: AtomicU64::new(0),
                 rows: AtomicU64::new(0),
                 nanos: AtomicU64::new(0),
+                retries: AtomicU64::new(0),
+                yields: AtomicU64::new(0),
+                cartesian_activations: AtomicU64::new(0),
+                cartesian_rows: AtomicU64::new(0),
+                cartesian_reopens: AtomicU64::new(0),
                 storage: StorageCounters::new_enabled(),
+                instruction_profiles: RwLock::new(Vec::new()),
             }),
         }
     }
@@ -606,6 +650,11 @@ impl StepProfile {
         }
     }
 
+    /// The label of the step, which stays the same when the planner reorders or regroups the query's patterns.
+    pub fn label(&self) -> Option<&str> {
+        self.data.as_ref().and_then(|data| data.label.as_deref())
+    }
+
     pub fn storage_counters(&self) -> StorageCounters {
         if let Some(data) = self.data.as_ref() {
             data.storage.clone()
@@ -613,6 +662,101 @@ impl StepProfile {
             StorageCounters::DISABLED
         }
     }
+
+    pub fn batches(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.batches.load(Ordering::Relaxed))
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.rows.load(Ordering::Relaxed))
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_nanos(self.data.as_ref().map_or(0, |data| data.nanos.load(Ordering::Relaxed)))
+    }
+
+    pub fn record_retry(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.retries.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The number of storage reads retried after a transient error.
+    pub fn retries(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.retries.load(Ordering::Relaxed))
+    }
+
+    pub fn record_yield(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.yields.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The number of times the step returned early, having spent the yield budget of a call.
+    pub fn yields(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.yields.load(Ordering::Relaxed))
+    }
+
+    /// Records a cartesian product started at an intersection value with several tuples.
+    pub fn record_cartesian_activation(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.cartesian_activations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records a row emitted by a cartesian product, beyond the first row at its intersection value.
+    pub fn record_cartesian_row(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.cartesian_rows.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records an iterator reopened at an intersection value to enumerate a cartesian product.
+    pub fn record_cartesian_reopen(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.cartesian_reopens.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cartesian_activations(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.cartesian_activations.load(Ordering::Relaxed))
+    }
+
+    pub fn cartesian_rows(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.cartesian_rows.load(Ordering::Relaxed))
+    }
+
+    pub fn cartesian_reopens(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.cartesian_reopens.load(Ordering::Relaxed))
+    }
+
+    /// The profile of the instruction at `index` within this step, shared by every executor of the step.
+    pub fn extend_or_get_instruction(
+        &self,
+        index: usize,
+        description_getter: impl Fn() -> String,
+    ) -> Arc<InstructionProfile> {
+        match self.data.as_ref() {
+            Some(data) => {
+                let profiles = data.instruction_profiles.read().unwrap();
+                if index < profiles.len() {
+                    profiles[index].clone()
+                } else {
+                    debug_assert!(index == profiles.len(), "Can only extend instruction profiles sequentially");
+                    let profile = Arc::new(InstructionProfile::new_enabled(description_getter()));
+                    drop(profiles);
+                    let mut profiles_mut = data.instruction_profiles.write().unwrap();
+                    profiles_mut.push(profile.clone());
+                    profile
+                }
+            }
+            None => Arc::new(InstructionProfile::new_disabled()),
+        }
+    }
+
+    pub fn instruction_profiles(&self) -> Vec<Arc<InstructionProfile>> {
+        self.data.as_ref().map_or_else(Vec::new, |data| data.instruction_profiles.read().unwrap().clone())
+    }
 }
 
 impl fmt::Display for StepProfileData {
@@ -621,15 +765,131 @@ impl fmt::Display for StepProfileData {
         let micros = Duration::from_nanos(self.nanos.load(Ordering::Relaxed)).as_micros();
         let micros_per_row: f64 = micros as f64 / rows as f64;
         // TODO: print storage ops
+        if let Some(label) = &self.label {
+            write!(f, "[step {}] ", label)?;
+        }
         write!(
             f,
-            "{}\n    ==> batches: {}, rows: {}, micros: {}, micros/row: {:.1} ({})",
+            "{}\n    ==> batches: {}, rows: {}, micros: {}, micros/row: {:.1}, retries: {}, yields: {} ({})",
             &self.description,
             self.batches.load(Ordering::Relaxed),
             rows,
             micros,
             micros_per_row,
+            self.retries.load(Ordering::Relaxed),
+            self.yields.load(Ordering::Relaxed),
             self.storage,
+        )?;
+        let cartesian_activations = self.cartesian_activations.load(Ordering::Relaxed);
+        if cartesian_activations > 0 {
+            write!(
+                f,
+                "\n    ==> cartesian activations: {}, rows: {}, reopens: {}",
+                cartesian_activations,
+                self.cartesian_rows.load(Ordering::Relaxed),
+                self.cartesian_reopens.load(Ordering::Relaxed),
+            )?;
+        }
+        for profile in self.instruction_profiles.read().unwrap().iter() {
+            if let Some(data) = profile.data.as_ref() {
+                write!(f, "\n      - {}", data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Counts the work done by the iterators of one instruction of a step, so that the instructions of an intersection
+/// can be told apart.
+#[derive(Debug)]
+pub struct InstructionProfile {
+    data: Option<InstructionProfileData>,
+}
+
+#[derive(Debug)]
+struct InstructionProfileData {
+    description: String,
+    opens: AtomicU64,
+    reopens: AtomicU64,
+    advances: AtomicU64,
+    peeked_keys: AtomicU64,
+}
+
+impl InstructionProfile {
+    fn new_enabled(description: String) -> Self {
+        Self {
+            data: Some(InstructionProfileData {
+                description,
+                opens: AtomicU64::new(0),
+                reopens: AtomicU64::new(0),
+                advances: AtomicU64::new(0),
+                peeked_keys: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    fn new_disabled() -> Self {
+        Self { data: None }
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.data.as_ref().map(|data| data.description.as_str())
+    }
+
+    /// Records an iterator opened for an input row.
+    pub fn record_open(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.opens.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records an iterator reopened at an intersection value to enumerate a cartesian product.
+    pub fn record_reopen(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.reopens.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records an iterator moved forward, by advancing or by seeking.
+    pub fn record_advance(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.advances.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_peeked_key(&self) {
+        if let Some(data) = self.data.as_ref() {
+            data.peeked_keys.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn opens(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.opens.load(Ordering::Relaxed))
+    }
+
+    pub fn reopens(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.reopens.load(Ordering::Relaxed))
+    }
+
+    pub fn advances(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.advances.load(Ordering::Relaxed))
+    }
+
+    pub fn peeked_keys(&self) -> u64 {
+        self.data.as_ref().map_or(0, |data| data.peeked_keys.load(Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for InstructionProfileData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ==> opens: {}, reopens: {}, advances: {}, peeked keys: {}",
+            &self.description,
+            self.opens.load(Ordering::Relaxed),
+            self.reopens.load(Ordering::Relaxed),
+            self.advances.load(Ordering::Relaxed),
+            self.peeked_keys.load(Ordering::Relaxed),
         )
     }
 }
@@ -191,6 +191,9 @@ impl WriteBuffer {
         };
         let start_as_bound = Self::range_start_as_bound(range_start);
         let start_bytes = start_as_bound.as_ref().map(|bytes| bytes.as_ref());
+        if Self::is_empty_range(start_bytes, end) {
+            return BufferRangeIterator::new(Vec::new());
+        }
         // TODO: we shouldn't have to copy now that we use single-writer semantics
         BufferRangeIterator::new(
             self.writes
@@ -211,9 +214,25 @@ impl WriteBuffer {
         };
         let start_as_bound = Self::range_start_as_bound(range_start);
         let start_bytes = start_as_bound.as_ref().map(|bytes| bytes.as_ref());
+        if Self::is_empty_range(start_bytes, end) {
+            return false;
+        }
         self.writes.range::<[u8], _>((start_bytes, end)).any(|(_, write)| !write.is_delete())
     }
 
+    // Storage iterators yield nothing for a range whose start lies past its end (for example a value
+    // range narrowed from both sides), whereas BTreeMap::range panics, so we have to check up front.
+    fn is_empty_range(start: Bound<&[u8]>, end: Bound<&[u8]>) -> bool {
+        match (start, end) {
+            (Bound::Included(start), Bound::Excluded(end)) | (Bound::Excluded(start), Bound::Excluded(end)) => {
+                start >= end
+            }
+            (Bound::Excluded(start), Bound::Included(end)) => start >= end,
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+        }
+    }
+
     fn range_start_as_bound<const INLINE: usize>(
         range_start: RangeStart<Bytes<'_, INLINE>>,
     ) -> Bound<Bytes<'_, INLINE>> {
@@ -14,7 +14,7 @@ use resource::{
     profile::{CommitProfile, StorageCounters},
 };
 use storage::{
-    key_range::KeyRange,
+    key_range::{KeyRange, RangeEnd, RangeStart},
     key_value::{StorageKey, StorageKeyArray},
     snapshot::{CommittableSnapshot, ReadableSnapshot, WritableSnapshot},
 };
@@ -83,6 +83,33 @@ fn snapshot_buffered_put_iterate() {
     snapshot.close_resources();
 }
 
+#[test]
+fn snapshot_buffered_iterate_inverted_range_is_empty() {
+    init_logging();
+    let storage_path = create_tmp_dir();
+    let storage = create_storage::<TestKeyspaceSet>(&storage_path).unwrap();
+
+    let mut snapshot = storage.open_snapshot_write();
+
+    let key_1 = StorageKeyArray::<BUFFER_KEY_INLINE>::from((Keyspace, [0x1, 0x0, 0x10]));
+    let key_2 = StorageKeyArray::<BUFFER_KEY_INLINE>::from((Keyspace, [0x2, 0x0, 0x10]));
+    snapshot.put(key_1);
+    snapshot.put(key_2);
+
+    let start = StorageKeyArray::<BUFFER_KEY_INLINE>::from((Keyspace, [0x2]));
+    let end = StorageKeyArray::<BUFFER_KEY_INLINE>::from((Keyspace, [0x1]));
+    let range = KeyRange::new_variable_width(
+        RangeStart::ExcludePrefix(StorageKey::Array(start)),
+        RangeEnd::EndPrefixInclusive(StorageKey::Array(end)),
+    );
+    let items: Vec<(StorageKeyArray<BUFFER_KEY_INLINE>, ByteArray<BUFFER_VALUE_INLINE>)> = snapshot
+        .iterate_range(&range, StorageCounters::DISABLED)
+        .collect_cloned_vec(|k, v| (StorageKeyArray::from(k), ByteArray::from(v)))
+        .unwrap();
+    assert!(items.is_empty());
+    snapshot.close_resources();
+}
+
 #[test]
 fn snapshot_buffered_delete() {
     init_logging();